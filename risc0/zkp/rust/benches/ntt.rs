@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use rand::thread_rng;
-use risc0_zkp::core::{fp::Fp, ntt::interpolate_ntt};
+use risc0_zkp::core::{
+    fp::Fp,
+    ntt::{evaluate_ntt_fp, expand, interpolate_ntt, par_interpolate_ntt_fp},
+};
 use risc0_zkp::field::Elem;
 
 pub fn ntt(c: &mut Criterion) {
@@ -27,7 +30,42 @@ pub fn ntt(c: &mut Criterion) {
             b.iter(|| interpolate_ntt(&mut buf));
         });
     }
+    group.finish();
+
+    let mut group = c.benchmark_group("par_interpolate_ntt_fp");
+    for n in [15, 20].iter() {
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            let size = 1 << n;
+            let mut rng = thread_rng();
+            let mut buf = vec![Fp::random(&mut rng); size];
+            b.iter(|| par_interpolate_ntt_fp(&mut buf));
+        });
+    }
+}
+
+/// The low-degree extension as the prover runs it, by the expansion factor
+/// of 4, on either side of the size where the four-step NTT takes over.
+/// Throughput is per output element, so that the sizes compare directly.
+pub fn lde(c: &mut Criterion) {
+    const EXPAND_BITS: usize = 2;
+    let mut group = c.benchmark_group("lde");
+    group.sample_size(10);
+    for n in [18, 19, 20, 21, 22] {
+        let size = 1 << n;
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            let mut rng = thread_rng();
+            let coeffs: Vec<Fp> = (0..size >> EXPAND_BITS)
+                .map(|_| Fp::random(&mut rng))
+                .collect();
+            let mut buf = vec![Fp::ZERO; size];
+            b.iter(|| {
+                expand(&mut buf, &coeffs, EXPAND_BITS);
+                evaluate_ntt_fp(&mut buf, EXPAND_BITS);
+            });
+        });
+    }
 }
 
-criterion_group!(benches, ntt);
+criterion_group!(benches, ntt, lde);
 criterion_main!(benches);
@@ -0,0 +1,49 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+fn main() {
+    #[cfg(feature = "cuda")]
+    cuda::build();
+}
+
+#[cfg(feature = "cuda")]
+mod cuda {
+    use std::{env, path::PathBuf};
+
+    const KERNELS: &str = "kernels/cuda/zkp.cu";
+
+    pub fn build() {
+        println!("cargo:rerun-if-changed={KERNELS}");
+        println!("cargo:rerun-if-env-changed=CUDA_PATH");
+        println!("cargo:rerun-if-env-changed=RISC0_CUDA_ARCH");
+
+        let mut build = cc::Build::new();
+        build.cuda(true).flag("-std=c++17").file(KERNELS);
+        // Without RISC0_CUDA_ARCH (e.g. "sm_86"), nvcc targets its default
+        // architecture and the driver compiles the PTX for the device.
+        if let Ok(arch) = env::var("RISC0_CUDA_ARCH") {
+            build.flag(&format!("-arch={arch}"));
+        }
+        build.compile("risc0-zkp-cuda");
+
+        let cuda = env::var_os("CUDA_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/usr/local/cuda"));
+        println!(
+            "cargo:rustc-link-search=native={}",
+            cuda.join("lib64").display()
+        );
+        println!("cargo:rustc-link-lib=cudart");
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Kernels for the `cuda` feature's CudaHal (src/hal/cuda.rs).  Field
+// elements are BabyBear in the Montgomery form of the Rust Fp type, so
+// buffers are shared with the host without conversion.  Every launcher
+// returns the launch's cudaError_t.
+
+#include <cstdint>
+#include <cuda_runtime.h>
+
+namespace {
+
+constexpr uint32_t P = 15 * (1 << 27) + 1;
+// -P^-1 mod 2^32.
+constexpr uint32_t M = 0x77ffffff;
+// 2^32 mod P, the Montgomery form of one.
+constexpr uint32_t ONE = 0x0ffffffe;
+
+constexpr uint32_t THREADS = 256;
+
+__device__ inline uint32_t add(uint32_t a, uint32_t b) {
+  uint32_t r = a + b;
+  return r >= P ? r - P : r;
+}
+
+__device__ inline uint32_t sub(uint32_t a, uint32_t b) {
+  uint32_t r = a - b;
+  return r > P ? r + P : r;
+}
+
+__device__ inline uint32_t mul(uint32_t a, uint32_t b) {
+  uint64_t t = uint64_t(a) * b;
+  uint32_t m = uint32_t(t) * M;
+  uint32_t r = (t + uint64_t(m) * P) >> 32;
+  return r >= P ? r - P : r;
+}
+
+__device__ inline uint32_t pow(uint32_t x, uint32_t e) {
+  uint32_t r = ONE;
+  while (e) {
+    if (e & 1) {
+      r = mul(r, x);
+    }
+    x = mul(x, x);
+    e >>= 1;
+  }
+  return r;
+}
+
+__device__ inline uint32_t rev_bits(uint32_t x, uint32_t bits) {
+  return bits ? __brev(x) >> (32 - bits) : 0;
+}
+
+uint32_t blocks(uint64_t count) {
+  uint64_t blocks = (count + THREADS - 1) / THREADS;
+  return blocks < 65535 ? (blocks ? blocks : 1) : 65535;
+}
+
+#define GRID_STRIDE(idx, count)                                                                    \
+  for (uint64_t idx = blockIdx.x * uint64_t(blockDim.x) + threadIdx.x; idx < (count);              \
+       idx += uint64_t(blockDim.x) * gridDim.x)
+
+#define LAUNCH(kernel, count, ...)                                                                 \
+  kernel<<<blocks(count), THREADS>>>(__VA_ARGS__);                                                 \
+  return cudaGetLastError();
+
+__global__ void batch_expand(uint32_t* out, const uint32_t* in, uint64_t total, uint32_t out_size,
+                             uint32_t in_size) {
+  uint32_t expand = out_size / in_size;
+  GRID_STRIDE(idx, total) {
+    uint64_t row = idx / out_size;
+    uint32_t col = idx % out_size;
+    out[idx] = in[row * in_size + col / expand];
+  }
+}
+
+// One level of the butterflies in core/ntt.rs: the combination of blocks of
+// `1 << level` elements, with the twiddles of the whole transform.
+__global__ void ntt_level(uint32_t* io,
+                          const uint32_t* twiddles,
+                          uint64_t pairs,
+                          uint32_t n,
+                          uint32_t level,
+                          bool fwd) {
+  uint32_t half = 1 << (level - 1);
+  GRID_STRIDE(idx, pairs) {
+    uint64_t block = idx / half;
+    uint32_t i = idx % half;
+    uint64_t lo = block * 2 * half + i;
+    uint64_t hi = lo + half;
+    uint32_t w = twiddles[i << (n - level)];
+    uint32_t a = io[lo];
+    uint32_t b = io[hi];
+    if (fwd) {
+      b = mul(b, w);
+      io[lo] = add(a, b);
+      io[hi] = sub(a, b);
+    } else {
+      io[lo] = add(a, b);
+      io[hi] = mul(sub(a, b), w);
+    }
+  }
+}
+
+__global__ void bit_reverse(uint32_t* io, uint64_t total, uint32_t n) {
+  uint32_t mask = (1 << n) - 1;
+  GRID_STRIDE(idx, total) {
+    uint64_t row = idx & ~uint64_t(mask);
+    uint32_t i = idx & mask;
+    uint32_t j = rev_bits(i, n);
+    if (i < j) {
+      uint32_t tmp = io[row + i];
+      io[row + i] = io[row + j];
+      io[row + j] = tmp;
+    }
+  }
+}
+
+__global__ void scale(uint32_t* io, uint64_t total, uint32_t factor) {
+  GRID_STRIDE(idx, total) { io[idx] = mul(io[idx], factor); }
+}
+
+__global__ void coset_shift(uint32_t* io, uint64_t total, uint32_t bits, uint32_t shift) {
+  GRID_STRIDE(idx, total) {
+    uint32_t pos = idx & ((1 << bits) - 1);
+    io[idx] = mul(io[idx], pow(shift, rev_bits(pos, bits)));
+  }
+}
+
+__constant__ uint32_t ROUND_K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2};
+
+struct Digest {
+  uint32_t words[8];
+};
+
+__device__ inline Digest sha_init() {
+  return {{0x6a09e667,
+           0xbb67ae85,
+           0x3c6ef372,
+           0xa54ff53a,
+           0x510e527f,
+           0x9b05688c,
+           0x1f83d9ab,
+           0x5be0cd19}};
+}
+
+__device__ inline uint32_t rotr(uint32_t x, uint32_t n) {
+  return (x >> n) | (x << (32 - n));
+}
+
+__device__ void sha_compress(Digest& state, const uint32_t* block) {
+  uint32_t w[64];
+  for (int i = 0; i < 16; i++) {
+    w[i] = block[i];
+  }
+  for (int i = 16; i < 64; i++) {
+    uint32_t s0 = rotr(w[i - 15], 7) ^ rotr(w[i - 15], 18) ^ (w[i - 15] >> 3);
+    uint32_t s1 = rotr(w[i - 2], 17) ^ rotr(w[i - 2], 19) ^ (w[i - 2] >> 10);
+    w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+  }
+  uint32_t a = state.words[0], b = state.words[1], c = state.words[2], d = state.words[3];
+  uint32_t e = state.words[4], f = state.words[5], g = state.words[6], h = state.words[7];
+  for (int i = 0; i < 64; i++) {
+    uint32_t t1 = h + (rotr(e, 6) ^ rotr(e, 11) ^ rotr(e, 25)) + ((e & f) ^ (~e & g)) +
+                  ROUND_K[i] + w[i];
+    uint32_t t2 = (rotr(a, 2) ^ rotr(a, 13) ^ rotr(a, 22)) + ((a & b) ^ (a & c) ^ (b & c));
+    h = g;
+    g = f;
+    f = e;
+    e = d + t1;
+    d = c;
+    c = b;
+    b = a;
+    a = t1 + t2;
+  }
+  state.words[0] += a;
+  state.words[1] += b;
+  state.words[2] += c;
+  state.words[3] += d;
+  state.words[4] += e;
+  state.words[5] += f;
+  state.words[6] += g;
+  state.words[7] += h;
+}
+
+// The host writes each word into the block little-endian, and SHA-256 reads
+// the block big-endian.
+__device__ inline uint32_t block_word(uint32_t x) {
+  return __byte_perm(x, 0, 0x0123);
+}
+
+// Same as sha_cpu::Impl::hash_fps_stride.
+__global__ void sha_rows(Digest* out, const uint32_t* matrix, uint32_t count, uint32_t col_size) {
+  GRID_STRIDE(idx, count) {
+    Digest state = sha_init();
+    uint32_t block[16];
+    uint32_t off = 0;
+    for (uint32_t i = 0; i < col_size; i++) {
+      block[off++] = block_word(mul(matrix[idx + uint64_t(i) * count], 1));
+      if (off == 16) {
+        sha_compress(state, block);
+        off = 0;
+      }
+    }
+    if (off != 0) {
+      for (uint32_t i = off; i < 16; i++) {
+        block[i] = 0;
+      }
+      sha_compress(state, block);
+    }
+    out[idx] = state;
+  }
+}
+
+// Same as sha_cpu::Impl::hash_pair, over the layer of the tree in
+// `io[2 * size..4 * size]`.
+__global__ void sha_fold(Digest* io, uint32_t size) {
+  GRID_STRIDE(idx, size) {
+    const Digest* in = io + 2 * size + 2 * idx;
+    uint32_t block[16];
+    for (int i = 0; i < 8; i++) {
+      block[i] = block_word(in[0].words[i]);
+      block[8 + i] = block_word(in[1].words[i]);
+    }
+    Digest state = sha_init();
+    sha_compress(state, block);
+    io[size + idx] = state;
+  }
+}
+
+} // namespace
+
+extern "C" {
+
+cudaError_t risc0_cuda_batch_expand(
+    uint32_t* out, const uint32_t* in, uint32_t count, uint32_t out_size, uint32_t in_size) {
+  uint64_t total = uint64_t(count) * out_size;
+  LAUNCH(batch_expand, total, out, in, total, out_size, in_size)
+}
+
+cudaError_t risc0_cuda_ntt_level(uint32_t* io,
+                                 const uint32_t* twiddles,
+                                 uint32_t count,
+                                 uint32_t n,
+                                 uint32_t level,
+                                 bool fwd) {
+  uint64_t pairs = uint64_t(count) << (n - 1);
+  LAUNCH(ntt_level, pairs, io, twiddles, pairs, n, level, fwd)
+}
+
+cudaError_t risc0_cuda_bit_reverse(uint32_t* io, uint32_t count, uint32_t n) {
+  uint64_t total = uint64_t(count) << n;
+  LAUNCH(bit_reverse, total, io, total, n)
+}
+
+cudaError_t risc0_cuda_scale(uint32_t* io, uint64_t total, uint32_t factor) {
+  LAUNCH(scale, total, io, total, factor)
+}
+
+cudaError_t risc0_cuda_coset_shift(uint32_t* io, uint32_t count, uint32_t bits, uint32_t shift) {
+  uint64_t total = uint64_t(count) << bits;
+  LAUNCH(coset_shift, total, io, total, bits, shift)
+}
+
+cudaError_t risc0_cuda_sha_rows(Digest* out, const uint32_t* matrix, uint32_t count,
+                                uint32_t col_size) {
+  LAUNCH(sha_rows, count, out, matrix, count, col_size)
+}
+
+cudaError_t risc0_cuda_sha_fold(Digest* io, uint32_t size) {
+  LAUNCH(sha_fold, size, io, size)
+}
+
+} // extern "C"
@@ -0,0 +1,433 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Compute shaders for the `metal` feature's MetalHal (src/hal/metal.rs),
+// compiled from source when the MetalHal is created.  As in
+// kernels/cuda/zkp.cu, field elements are BabyBear in the Montgomery form of
+// the Rust Fp type.
+
+#include <metal_stdlib>
+
+using namespace metal;
+
+constant uint P = 15 * (1 << 27) + 1;
+// -P^-1 mod 2^32.
+constant uint M = 0x77ffffff;
+// 2^32 mod P, the Montgomery form of one.
+constant uint ONE = 0x0ffffffe;
+// The Montgomery form of -11, which X^4 is in Fp4.
+constant uint NBETA = 0x40000018;
+
+inline uint fp_add(uint a, uint b) {
+  uint r = a + b;
+  return r >= P ? r - P : r;
+}
+
+inline uint fp_sub(uint a, uint b) {
+  uint r = a - b;
+  return r > P ? r + P : r;
+}
+
+inline uint fp_mul(uint a, uint b) {
+  ulong t = ulong(a) * b;
+  uint m = uint(t) * M;
+  uint r = uint((t + ulong(m) * P) >> 32);
+  return r >= P ? r - P : r;
+}
+
+inline uint fp_pow(uint x, uint e) {
+  uint r = ONE;
+  while (e) {
+    if (e & 1) {
+      r = fp_mul(r, x);
+    }
+    x = fp_mul(x, x);
+    e >>= 1;
+  }
+  return r;
+}
+
+struct Fp4 {
+  uint elems[4];
+};
+
+inline Fp4 fp4_add(Fp4 a, Fp4 b) {
+  return {{fp_add(a.elems[0], b.elems[0]),
+           fp_add(a.elems[1], b.elems[1]),
+           fp_add(a.elems[2], b.elems[2]),
+           fp_add(a.elems[3], b.elems[3])}};
+}
+
+inline Fp4 fp4_mul_fp(Fp4 a, uint b) {
+  return {{fp_mul(a.elems[0], b),
+           fp_mul(a.elems[1], b),
+           fp_mul(a.elems[2], b),
+           fp_mul(a.elems[3], b)}};
+}
+
+// As in field/baby_bear.rs: multiply out, then replace X^4 with -11.
+inline Fp4 fp4_mul(Fp4 a, Fp4 b) {
+  thread const uint* x = a.elems;
+  thread const uint* y = b.elems;
+  uint r0 = fp_add(fp_mul(x[1], y[3]), fp_add(fp_mul(x[2], y[2]), fp_mul(x[3], y[1])));
+  uint r1 = fp_add(fp_mul(x[2], y[3]), fp_mul(x[3], y[2]));
+  uint r2 = fp_mul(x[3], y[3]);
+  return {{fp_add(fp_mul(x[0], y[0]), fp_mul(NBETA, r0)),
+           fp_add(fp_add(fp_mul(x[0], y[1]), fp_mul(x[1], y[0])), fp_mul(NBETA, r1)),
+           fp_add(fp_add(fp_mul(x[0], y[2]), fp_add(fp_mul(x[1], y[1]), fp_mul(x[2], y[0]))),
+                  fp_mul(NBETA, r2)),
+           fp_add(fp_add(fp_mul(x[0], y[3]), fp_mul(x[1], y[2])),
+                  fp_add(fp_mul(x[2], y[1]), fp_mul(x[3], y[0])))}};
+}
+
+inline uint rev_bits(uint x, uint bits) {
+  return bits ? reverse_bits(x) >> (32 - bits) : 0;
+}
+
+kernel void batch_expand(device uint* out [[buffer(0)]],
+                         const device uint* in [[buffer(1)]],
+                         constant uint& out_size [[buffer(2)]],
+                         constant uint& in_size [[buffer(3)]],
+                         uint idx [[thread_position_in_grid]]) {
+  uint row = idx / out_size;
+  uint col = idx % out_size;
+  out[idx] = in[row * in_size + col / (out_size / in_size)];
+}
+
+// One level of the butterflies in core/ntt.rs: the combination of blocks of
+// `1 << level` elements, with the twiddles of the whole transform.
+kernel void ntt_level(device uint* io [[buffer(0)]],
+                      const device uint* twiddles [[buffer(1)]],
+                      constant uint& n [[buffer(2)]],
+                      constant uint& level [[buffer(3)]],
+                      constant uint& fwd [[buffer(4)]],
+                      uint idx [[thread_position_in_grid]]) {
+  uint half_size = 1 << (level - 1);
+  uint block = idx / half_size;
+  uint i = idx % half_size;
+  uint lo = block * 2 * half_size + i;
+  uint hi = lo + half_size;
+  uint w = twiddles[i << (n - level)];
+  uint a = io[lo];
+  uint b = io[hi];
+  if (fwd) {
+    b = fp_mul(b, w);
+    io[lo] = fp_add(a, b);
+    io[hi] = fp_sub(a, b);
+  } else {
+    io[lo] = fp_add(a, b);
+    io[hi] = fp_mul(fp_sub(a, b), w);
+  }
+}
+
+kernel void bit_reverse(device uint* io [[buffer(0)]],
+                        constant uint& n [[buffer(1)]],
+                        uint idx [[thread_position_in_grid]]) {
+  uint mask = (1 << n) - 1;
+  uint row = idx & ~mask;
+  uint i = idx & mask;
+  uint j = rev_bits(i, n);
+  if (i < j) {
+    uint tmp = io[row + i];
+    io[row + i] = io[row + j];
+    io[row + j] = tmp;
+  }
+}
+
+kernel void scale(device uint* io [[buffer(0)]],
+                  constant uint& factor [[buffer(1)]],
+                  uint idx [[thread_position_in_grid]]) {
+  io[idx] = fp_mul(io[idx], factor);
+}
+
+kernel void coset_shift(device uint* io [[buffer(0)]],
+                        constant uint& bits [[buffer(1)]],
+                        constant uint& shift [[buffer(2)]],
+                        uint idx [[thread_position_in_grid]]) {
+  uint pos = idx & ((1 << bits) - 1);
+  io[idx] = fp_mul(io[idx], fp_pow(shift, rev_bits(pos, bits)));
+}
+
+// Same as CpuHal::mix_poly_coeffs, for coefficient `idx` of every
+// polynomial; `mix` holds the starting power and the mix.
+kernel void mix_poly_coeffs(device Fp4* out [[buffer(0)]],
+                            const device uint* in [[buffer(1)]],
+                            const device uint* combos [[buffer(2)]],
+                            const device Fp4* mix [[buffer(3)]],
+                            constant uint& input_size [[buffer(4)]],
+                            constant uint& count [[buffer(5)]],
+                            uint idx [[thread_position_in_grid]]) {
+  Fp4 cur = mix[0];
+  for (uint i = 0; i < input_size; i++) {
+    uint pos = count * combos[i] + idx;
+    out[pos] = fp4_add(out[pos], fp4_mul_fp(cur, in[count * i + idx]));
+    cur = fp4_mul(cur, mix[1]);
+  }
+}
+
+// Same as CpuHal::fri_fold, for element `idx` of each of the output's
+// columns; `mix` holds the FRI mix.
+kernel void fri_fold(device uint* out [[buffer(0)]],
+                     const device uint* in [[buffer(1)]],
+                     const device Fp4* mix [[buffer(2)]],
+                     constant uint& count [[buffer(3)]],
+                     constant uint& fold_po2 [[buffer(4)]],
+                     uint idx [[thread_position_in_grid]]) {
+  uint fold = 1 << fold_po2;
+  Fp4 tot = {{0, 0, 0, 0}};
+  Fp4 cur = {{ONE, 0, 0, 0}};
+  for (uint i = 0; i < fold; i++) {
+    uint pos = rev_bits(i, fold_po2) * count + idx;
+    Fp4 factor;
+    for (uint k = 0; k < 4; k++) {
+      factor.elems[k] = in[k * count * fold + pos];
+    }
+    tot = fp4_add(tot, fp4_mul(cur, factor));
+    cur = fp4_mul(cur, mix[0]);
+  }
+  for (uint k = 0; k < 4; k++) {
+    out[k * count + idx] = tot.elems[k];
+  }
+}
+
+// The division of CpuHal::eval_check: the constraint polynomial `tots` at
+// point `cycle` of the coset, over the zerofier x^(2^po2) - 1 there, written
+// one column per component.
+kernel void eval_check(device uint* check [[buffer(0)]],
+                       const device Fp4* tots [[buffer(1)]],
+                       constant uint& domain [[buffer(2)]],
+                       constant uint& po2 [[buffer(3)]],
+                       constant uint& root [[buffer(4)]],
+                       constant uint& shift [[buffer(5)]],
+                       uint cycle [[thread_position_in_grid]]) {
+  uint x = fp_mul(shift, fp_pow(root, cycle));
+  uint y = fp_pow(x, 1 << po2);
+  Fp4 ret = fp4_mul_fp(tots[cycle], fp_pow(fp_sub(y, ONE), P - 2));
+  for (uint k = 0; k < 4; k++) {
+    check[k * domain + cycle] = ret.elems[k];
+  }
+}
+
+constant uint ROUND_K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2};
+
+struct Digest {
+  uint words[8];
+};
+
+inline Digest sha_init() {
+  return {{0x6a09e667,
+           0xbb67ae85,
+           0x3c6ef372,
+           0xa54ff53a,
+           0x510e527f,
+           0x9b05688c,
+           0x1f83d9ab,
+           0x5be0cd19}};
+}
+
+inline uint rotr(uint x, uint n) {
+  return (x >> n) | (x << (32 - n));
+}
+
+void sha_compress(thread Digest& state, thread const uint* block) {
+  uint w[64];
+  for (int i = 0; i < 16; i++) {
+    w[i] = block[i];
+  }
+  for (int i = 16; i < 64; i++) {
+    uint s0 = rotr(w[i - 15], 7) ^ rotr(w[i - 15], 18) ^ (w[i - 15] >> 3);
+    uint s1 = rotr(w[i - 2], 17) ^ rotr(w[i - 2], 19) ^ (w[i - 2] >> 10);
+    w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+  }
+  uint a = state.words[0], b = state.words[1], c = state.words[2], d = state.words[3];
+  uint e = state.words[4], f = state.words[5], g = state.words[6], h = state.words[7];
+  for (int i = 0; i < 64; i++) {
+    uint t1 = h + (rotr(e, 6) ^ rotr(e, 11) ^ rotr(e, 25)) + ((e & f) ^ (~e & g)) + ROUND_K[i] +
+              w[i];
+    uint t2 = (rotr(a, 2) ^ rotr(a, 13) ^ rotr(a, 22)) + ((a & b) ^ (a & c) ^ (b & c));
+    h = g;
+    g = f;
+    f = e;
+    e = d + t1;
+    d = c;
+    c = b;
+    b = a;
+    a = t1 + t2;
+  }
+  state.words[0] += a;
+  state.words[1] += b;
+  state.words[2] += c;
+  state.words[3] += d;
+  state.words[4] += e;
+  state.words[5] += f;
+  state.words[6] += g;
+  state.words[7] += h;
+}
+
+// The host writes each word into the block little-endian, and SHA-256 reads
+// the block big-endian.
+inline uint block_word(uint x) {
+  return (x >> 24) | ((x >> 8) & 0xff00) | ((x << 8) & 0xff0000) | (x << 24);
+}
+
+// Same as sha_cpu::Impl::hash_fps_stride.
+kernel void sha_rows(device Digest* out [[buffer(0)]],
+                     const device uint* matrix [[buffer(1)]],
+                     constant uint& count [[buffer(2)]],
+                     constant uint& col_size [[buffer(3)]],
+                     uint idx [[thread_position_in_grid]]) {
+  Digest state = sha_init();
+  uint block[16];
+  uint off = 0;
+  for (uint i = 0; i < col_size; i++) {
+    block[off++] = block_word(fp_mul(matrix[idx + i * count], 1));
+    if (off == 16) {
+      sha_compress(state, block);
+      off = 0;
+    }
+  }
+  if (off != 0) {
+    for (uint i = off; i < 16; i++) {
+      block[i] = 0;
+    }
+    sha_compress(state, block);
+  }
+  out[idx] = state;
+}
+
+// Same as sha_cpu::Impl::hash_pair, over the layer of the tree in
+// `io[2 * size..4 * size]`.
+kernel void sha_fold(device Digest* io [[buffer(0)]],
+                     constant uint& size [[buffer(1)]],
+                     uint idx [[thread_position_in_grid]]) {
+  const device Digest* in = io + 2 * size + 2 * idx;
+  uint block[16];
+  for (int i = 0; i < 8; i++) {
+    block[i] = block_word(in[0].words[i]);
+    block[8 + i] = block_word(in[1].words[i]);
+  }
+  Digest state = sha_init();
+  sha_compress(state, block);
+  io[size + idx] = state;
+}
+
+// 2^64 mod P, by which fp_mul takes a canonical value to its Montgomery form.
+constant uint R2 = 0x45dddde3;
+
+constant uint POSEIDON_CELLS = 16;
+constant uint POSEIDON_RATE = 8;
+constant uint POSEIDON_ROUNDS_FULL = 8;
+constant uint POSEIDON_ROUNDS_PARTIAL = 13;
+
+struct PoseidonConsts {
+  // CELLS per round.
+  const device uint* round_constants;
+  // Row i and column j of the MDS matrix is cauchy[i + j].
+  const device uint* cauchy;
+};
+
+inline uint poseidon_sbox(uint x) {
+  uint x2 = fp_mul(x, x);
+  uint x4 = fp_mul(x2, x2);
+  return fp_mul(x, fp_mul(x2, x4));
+}
+
+// Same as core::poseidon::poseidon_permute.
+void poseidon_permute(thread uint* cells, PoseidonConsts consts) {
+  uint rounds = POSEIDON_ROUNDS_FULL + POSEIDON_ROUNDS_PARTIAL;
+  for (uint round = 0; round < rounds; round++) {
+    for (uint i = 0; i < POSEIDON_CELLS; i++) {
+      cells[i] = fp_add(cells[i], consts.round_constants[round * POSEIDON_CELLS + i]);
+    }
+    bool partial = round >= POSEIDON_ROUNDS_FULL / 2 &&
+                   round < POSEIDON_ROUNDS_FULL / 2 + POSEIDON_ROUNDS_PARTIAL;
+    if (partial) {
+      cells[0] = poseidon_sbox(cells[0]);
+    } else {
+      for (uint i = 0; i < POSEIDON_CELLS; i++) {
+        cells[i] = poseidon_sbox(cells[i]);
+      }
+    }
+    uint old[POSEIDON_CELLS];
+    for (uint i = 0; i < POSEIDON_CELLS; i++) {
+      old[i] = cells[i];
+    }
+    for (uint i = 0; i < POSEIDON_CELLS; i++) {
+      uint sum = 0;
+      for (uint j = 0; j < POSEIDON_CELLS; j++) {
+        sum = fp_add(sum, fp_mul(old[j], consts.cauchy[i + j]));
+      }
+      cells[i] = sum;
+    }
+  }
+}
+
+// Digests hold the canonical values of the first cells.
+inline Digest poseidon_digest(thread const uint* cells) {
+  Digest out;
+  for (uint i = 0; i < 8; i++) {
+    out.words[i] = fp_mul(cells[i], 1);
+  }
+  return out;
+}
+
+// Same as poseidon::Impl::hash_fps_stride; `len` is the Montgomery form of
+// `col_size`, which the sponge starts with in its capacity.  The domain of
+// field elements is zero.
+kernel void poseidon_rows(device Digest* out [[buffer(0)]],
+                          const device uint* matrix [[buffer(1)]],
+                          const device uint* round_constants [[buffer(2)]],
+                          const device uint* cauchy [[buffer(3)]],
+                          constant uint& count [[buffer(4)]],
+                          constant uint& col_size [[buffer(5)]],
+                          constant uint& len [[buffer(6)]],
+                          uint idx [[thread_position_in_grid]]) {
+  PoseidonConsts consts = {round_constants, cauchy};
+  uint cells[POSEIDON_CELLS] = {0};
+  cells[POSEIDON_RATE] = len;
+  uint i = 0;
+  do {
+    for (uint off = 0; off < POSEIDON_RATE && i < col_size; off++, i++) {
+      cells[off] = fp_add(cells[off], matrix[idx + i * count]);
+    }
+    poseidon_permute(cells, consts);
+  } while (i < col_size);
+  out[idx] = poseidon_digest(cells);
+}
+
+// Same as poseidon::Impl::hash_pair, over the layer of the tree in
+// `io[2 * size..4 * size]`.
+kernel void poseidon_fold(device Digest* io [[buffer(0)]],
+                          const device uint* round_constants [[buffer(1)]],
+                          const device uint* cauchy [[buffer(2)]],
+                          constant uint& size [[buffer(3)]],
+                          uint idx [[thread_position_in_grid]]) {
+  PoseidonConsts consts = {round_constants, cauchy};
+  const device Digest* in = io + 2 * size + 2 * idx;
+  uint cells[POSEIDON_CELLS];
+  for (uint i = 0; i < 8; i++) {
+    cells[i] = fp_mul(in[0].words[i], R2);
+    cells[8 + i] = fp_mul(in[1].words[i], R2);
+  }
+  poseidon_permute(cells, consts);
+  io[size + idx] = poseidon_digest(cells);
+}
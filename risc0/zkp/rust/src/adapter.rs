@@ -33,6 +33,16 @@ pub trait CustomStep {
     fn call(&mut self, name: &str, extra: &str, args: &[Fp]) -> Result<Vec<Fp>>;
 }
 
+/// A [CustomStep] for evaluating steps which never call out to the host,
+/// such as the accumulation step when it's spread across threads.
+pub struct NoCustomStep;
+
+impl CustomStep for NoCustomStep {
+    fn call(&mut self, name: &str, _extra: &str, _args: &[Fp]) -> Result<Vec<Fp>> {
+        bail!("Custom step {name} is not available here")
+    }
+}
+
 pub struct CircuitStepContext {
     pub size: usize,
     pub cycle: usize,
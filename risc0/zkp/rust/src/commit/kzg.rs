@@ -0,0 +1,325 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A KZG [CommitmentScheme] over BN254.
+//!
+//! Each column of the matrix is interpolated, over the roots of unity of
+//! the BN254 scalar field, into the polynomial whose value at the `i`th root
+//! is the column's `i`th value, lifted from BabyBear as an integer.  The
+//! commitment is that of each column's polynomial, and opening a row proves
+//! all of its values at once: the prover draws a challenge from the
+//! transcript and writes a single KZG proof for the random linear
+//! combination of the columns.  Points are written compressed, eight words
+//! each.
+//!
+//! A commitment is only binding if no one knows the secret of the
+//! [KzgParams]; take them from a trusted setup ceremony with
+//! [KzgParams::new].
+
+use alloc::{vec, vec::Vec};
+
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand_core::RngCore;
+
+use super::{CommitmentProver, CommitmentScheme, CommitmentVerifier};
+use crate::{
+    core::{fp::Fp, hash::HashSuite, sha::Digest},
+    field::Elem,
+    prove::write_iop::WriteIOP,
+    verify::{read_iop::ReadIOP, VerificationError},
+};
+
+/// The number of words a compressed point of G1 takes in a proof.
+pub const POINT_WORDS: usize = 8;
+
+/// The structured reference string of a KZG commitment: the powers of a
+/// secret `tau` in G1, and `tau` in G2.
+#[derive(Clone, Debug)]
+pub struct KzgParams {
+    powers: Vec<G1Affine>,
+    g2: G2Affine,
+    tau_g2: G2Affine,
+}
+
+impl KzgParams {
+    /// Parameters from the powers `[tau^i]G1` and from `G2` and `[tau]G2`.
+    /// These commit to matrices of up to `powers.len()` rows.
+    pub fn new(powers: Vec<G1Affine>, g2: G2Affine, tau_g2: G2Affine) -> Self {
+        assert!(!powers.is_empty());
+        KzgParams { powers, g2, tau_g2 }
+    }
+
+    /// Parameters for matrices of up to `rows` rows, from a `tau` drawn from
+    /// `rng`.  Whoever knows `tau` can open a commitment to any value, so
+    /// this is only for testing.
+    pub fn setup_insecure<R: RngCore>(rows: usize, rng: &mut R) -> Self {
+        let tau = random_fr(rng);
+        let mut powers = Vec::with_capacity(rows);
+        let mut power = Fr::one();
+        for _ in 0..rows {
+            powers.push(G1Projective::generator() * power);
+            power *= tau;
+        }
+        KzgParams::new(
+            G1Projective::normalize_batch(&powers),
+            G2Affine::generator(),
+            (G2Projective::generator() * tau).into_affine(),
+        )
+    }
+
+    /// The most rows a matrix committed with these parameters may have.
+    pub fn max_rows(&self) -> usize {
+        self.powers.len()
+    }
+
+    // Commit to the polynomial with the given coefficients.
+    fn commit(&self, coeffs: &[Fr]) -> G1Affine {
+        G1Projective::msm(&self.powers[..coeffs.len()], coeffs)
+            .unwrap()
+            .into_affine()
+    }
+}
+
+/// Commits to each column of a matrix as a polynomial, with [KzgParams].
+#[derive(Clone, Copy, Debug)]
+pub struct Kzg<'a> {
+    params: &'a KzgParams,
+}
+
+impl<'a> Kzg<'a> {
+    /// Commit with `params`.
+    pub fn new(params: &'a KzgParams) -> Self {
+        Kzg { params }
+    }
+
+    fn domain(&self, rows: usize) -> Radix2EvaluationDomain<Fr> {
+        assert!(rows.is_power_of_two() && rows <= self.params.max_rows());
+        Radix2EvaluationDomain::new(rows).unwrap()
+    }
+}
+
+impl<'a> CommitmentScheme for Kzg<'a> {
+    type Prover = KzgProver<'a>;
+
+    type Verifier = KzgVerifier<'a>;
+
+    fn prover(&self, matrix: &[Fp], rows: usize, cols: usize) -> KzgProver<'a> {
+        assert_eq!(matrix.len(), rows * cols);
+        let domain = self.domain(rows);
+        let coeffs: Vec<Vec<Fr>> = matrix
+            .chunks_exact(rows)
+            .map(|column| domain.ifft(&column.iter().map(|x| lift(*x)).collect::<Vec<_>>()))
+            .collect();
+        let commitments = coeffs
+            .iter()
+            .map(|coeffs| self.params.commit(coeffs))
+            .collect();
+        KzgProver {
+            params: self.params,
+            domain,
+            matrix: matrix.to_vec(),
+            coeffs,
+            commitments,
+        }
+    }
+
+    fn verifier(
+        &self,
+        iop: &mut ReadIOP,
+        rows: usize,
+        cols: usize,
+    ) -> Result<KzgVerifier<'a>, VerificationError> {
+        let domain = self.domain(rows);
+        let mut words = vec![0; cols * POINT_WORDS];
+        iop.read_u32s(&mut words)?;
+        iop.commit(&words_digest(iop.get_suite(), &words));
+        let commitments = words
+            .chunks_exact(POINT_WORDS)
+            .map(decode_point)
+            .collect::<Result<_, _>>()?;
+        Ok(KzgVerifier {
+            params: self.params,
+            domain,
+            commitments,
+        })
+    }
+}
+
+/// The columns of a matrix, as polynomials, and their commitments.
+pub struct KzgProver<'a> {
+    params: &'a KzgParams,
+    domain: Radix2EvaluationDomain<Fr>,
+    matrix: Vec<Fp>,
+    coeffs: Vec<Vec<Fr>>,
+    commitments: Vec<G1Affine>,
+}
+
+impl<'a> CommitmentProver for KzgProver<'a> {
+    fn commit(&self, iop: &mut WriteIOP) {
+        let words: Vec<u32> = self.commitments.iter().flat_map(encode_point).collect();
+        iop.write_u32_slice(&words);
+        iop.commit(&words_digest(iop.get_suite(), &words));
+    }
+
+    fn open(&mut self, iop: &mut WriteIOP, idx: usize) -> Vec<Fp> {
+        let rows = self.domain.size();
+        assert!(idx < rows);
+        let row: Vec<Fp> = (0..self.coeffs.len())
+            .map(|col| self.matrix[idx + col * rows])
+            .collect();
+        iop.write_fp_slice(&row);
+        iop.commit(&iop.get_suite().hash_elems(&row));
+        let gamma = random_fr(&mut *iop.rng);
+
+        // The columns combined by powers of gamma, divided by X - z.  Since
+        // the combination takes the combined value at z, there's no
+        // remainder.
+        let mut combined = vec![Fr::zero(); rows];
+        let mut scale = Fr::one();
+        for coeffs in self.coeffs.iter() {
+            for (sum, coeff) in combined.iter_mut().zip(coeffs) {
+                *sum += scale * coeff;
+            }
+            scale *= gamma;
+        }
+        let quotient = divide_by_linear(&combined, self.domain.element(idx));
+        let proof = self.params.commit(&quotient);
+        iop.write_u32_slice(&encode_point(&proof));
+        row
+    }
+}
+
+/// The commitments to the columns of a matrix.
+pub struct KzgVerifier<'a> {
+    params: &'a KzgParams,
+    domain: Radix2EvaluationDomain<Fr>,
+    commitments: Vec<G1Affine>,
+}
+
+impl<'a> CommitmentVerifier for KzgVerifier<'a> {
+    fn open(&mut self, iop: &mut ReadIOP, idx: usize) -> Result<Vec<Fp>, VerificationError> {
+        if idx >= self.domain.size() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        let mut row = vec![Fp::ZERO; self.commitments.len()];
+        iop.read_fps(&mut row)?;
+        iop.commit(&iop.get_suite().hash_elems(&row));
+        let gamma = random_fr(iop);
+        let mut words = [0; POINT_WORDS];
+        iop.read_u32s(&mut words)?;
+        let proof = decode_point(&words)?;
+
+        let mut scales = Vec::with_capacity(row.len());
+        let mut value = Fr::zero();
+        let mut scale = Fr::one();
+        for x in row.iter() {
+            scales.push(scale);
+            value += scale * lift(*x);
+            scale *= gamma;
+        }
+        let combined = G1Projective::msm(&self.commitments, &scales).unwrap();
+
+        // e(C - [y]G1, G2) == e(proof, [tau]G2 - [z]G2)
+        let z = self.domain.element(idx);
+        let g1 = self.params.powers[0];
+        let lhs = Bn254::pairing((combined - g1 * value).into_affine(), self.params.g2);
+        let rhs = Bn254::pairing(
+            proof,
+            (self.params.tau_g2.into_group() - self.params.g2 * z).into_affine(),
+        );
+        if lhs != rhs {
+            return Err(VerificationError::MethodVerificationError);
+        }
+        Ok(row)
+    }
+}
+
+// The value of a BabyBear element as a BN254 scalar.
+fn lift(x: Fp) -> Fr {
+    Fr::from(u32::from(x))
+}
+
+// A uniformly random scalar, reduced from 512 bits.
+fn random_fr<R: RngCore + ?Sized>(rng: &mut R) -> Fr {
+    let mut bytes = [0; 64];
+    rng.fill_bytes(&mut bytes);
+    Fr::from_le_bytes_mod_order(&bytes)
+}
+
+// The quotient of the polynomial with the given coefficients by X - z,
+// leaving out the remainder.
+fn divide_by_linear(coeffs: &[Fr], z: Fr) -> Vec<Fr> {
+    let mut quotient = vec![Fr::zero(); coeffs.len().saturating_sub(1)];
+    let mut carry = Fr::zero();
+    for (coeff, out) in coeffs.iter().skip(1).zip(quotient.iter_mut()).rev() {
+        carry = *coeff + carry * z;
+        *out = carry;
+    }
+    quotient
+}
+
+// Mix words into the transcript as field elements, by 16-bit halves.
+fn words_digest(suite: &dyn HashSuite, words: &[u32]) -> Digest {
+    let halves: Vec<Fp> = words
+        .iter()
+        .flat_map(|word| [Fp::new(word & 0xffff), Fp::new(word >> 16)])
+        .collect();
+    suite.hash_elems(&halves)
+}
+
+fn encode_point(point: &G1Affine) -> Vec<u32> {
+    let mut bytes = Vec::with_capacity(POINT_WORDS * 4);
+    point.serialize_compressed(&mut bytes).unwrap();
+    bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+// Decode a compressed point, checking that it's on the curve and in the
+// prime order subgroup.
+fn decode_point(words: &[u32]) -> Result<G1Affine, VerificationError> {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    G1Affine::deserialize_compressed(&bytes[..]).map_err(|_| VerificationError::ReceiptFormatError)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use ark_ff::{One, Zero};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::{divide_by_linear, Kzg, KzgParams};
+    use crate::commit::tests::check_openings;
+
+    #[test]
+    fn quotient() {
+        // (x^2 + 3x + 2) / (x + 1) == x + 2
+        let coeffs = [Fr::from(2u32), Fr::from(3u32), Fr::one()];
+        let quotient = divide_by_linear(&coeffs, -Fr::one());
+        assert_eq!(quotient, [Fr::from(2u32), Fr::one()]);
+        assert!(divide_by_linear(&[Fr::from(5u32)], Fr::zero()).is_empty());
+    }
+
+    #[test]
+    fn openings() {
+        let params = KzgParams::setup_insecure(16, &mut SmallRng::seed_from_u64(2));
+        check_openings(&Kzg::new(&params), 16, 3);
+        check_openings(&Kzg::new(&params), 4, 1);
+    }
+}
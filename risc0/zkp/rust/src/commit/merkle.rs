@@ -0,0 +1,108 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Merkle tree [CommitmentScheme] of the STARK.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "prove")]
+use super::CommitmentProver;
+use super::CommitmentScheme;
+#[cfg(feature = "verify")]
+use super::CommitmentVerifier;
+use crate::core::{fp::Fp, sha::Sha};
+#[cfg(feature = "verify")]
+use crate::verify::{merkle::MerkleTreeVerifier, read_iop::ReadIOP, VerificationError};
+#[cfg(feature = "prove")]
+use crate::{
+    hal::{cpu::CpuHal, Hal},
+    prove::{merkle::MerkleTreeProver, write_iop::WriteIOP},
+};
+
+/// Commits to each row of a matrix as a leaf of a Merkle tree, hashed on the
+/// CPU with the [Sha] `S`.  The transcript must hash with the same `S`.
+#[derive(Clone, Debug)]
+pub struct Merkle<S: Sha> {
+    sha: S,
+    queries: usize,
+}
+
+impl<S: Sha> Merkle<S> {
+    /// Commit with trees sized for `queries` openings; see
+    /// [MerkleTreeProver::new](crate::prove::merkle::MerkleTreeProver::new).
+    pub fn new(sha: S, queries: usize) -> Self {
+        Merkle { sha, queries }
+    }
+}
+
+impl<S: Sha + Sync> CommitmentScheme for Merkle<S> {
+    #[cfg(feature = "prove")]
+    type Prover = MerkleProver<S>;
+
+    #[cfg(feature = "verify")]
+    type Verifier = MerkleTreeVerifier;
+
+    #[cfg(feature = "prove")]
+    fn prover(&self, matrix: &[Fp], rows: usize, cols: usize) -> MerkleProver<S> {
+        let hal = CpuHal::with_sha(self.sha.clone());
+        let tree = MerkleTreeProver::new(&hal, &hal.copy_from(matrix), rows, cols, self.queries);
+        MerkleProver { hal, tree }
+    }
+
+    #[cfg(feature = "verify")]
+    fn verifier(
+        &self,
+        iop: &mut ReadIOP,
+        rows: usize,
+        cols: usize,
+    ) -> Result<MerkleTreeVerifier, VerificationError> {
+        MerkleTreeVerifier::new(iop, rows, cols, self.queries)
+    }
+}
+
+/// A Merkle tree and the [Hal] which built it.
+#[cfg(feature = "prove")]
+pub struct MerkleProver<S: Sha> {
+    hal: CpuHal<S>,
+    tree: MerkleTreeProver,
+}
+
+#[cfg(feature = "prove")]
+impl<S: Sha + Sync> CommitmentProver for MerkleProver<S> {
+    fn commit(&self, iop: &mut WriteIOP) {
+        self.tree.commit(&self.hal, iop)
+    }
+
+    fn open(&mut self, iop: &mut WriteIOP, idx: usize) -> Vec<Fp> {
+        self.tree.prove(iop, idx)
+    }
+}
+
+#[cfg(feature = "verify")]
+impl CommitmentVerifier for MerkleTreeVerifier {
+    fn open(&mut self, iop: &mut ReadIOP, idx: usize) -> Result<Vec<Fp>, VerificationError> {
+        self.verify(iop, idx)
+    }
+}
+
+#[cfg(all(test, feature = "prove", feature = "verify"))]
+mod tests {
+    use super::Merkle;
+    use crate::{commit::tests::check_openings, core::sha_cpu, QUERIES};
+
+    #[test]
+    fn openings() {
+        check_openings(&Merkle::new(sha_cpu::Impl {}, QUERIES), 1 << 8, 5);
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Commitments to matrices of field elements, opened a row at a time.
+//!
+//! A [CommitmentScheme] commits to a matrix of [Fp] values laid out as in
+//! [MerkleTreeProver](crate::prove::merkle::MerkleTreeProver): `cols`
+//! columns of `rows` values each, one column after another.  The prover
+//! writes the commitment to the transcript, and then proves the values of
+//! the rows the verifier asks for.  [Merkle](merkle::Merkle) commits with the
+//! Merkle trees of the STARK; with the `kzg` feature, `kzg::Kzg`
+//! commits to each column as a polynomial over the BN254 scalar field, for
+//! hybrid proof systems and on-chain verifiers which prefer pairing-based
+//! commitments.
+//!
+//! The STARK prover and verifier still commit with the Merkle trees
+//! directly: FRI checks the low degree of BabyBear polynomials through
+//! Merkle openings, which a commitment over another field can't stand in
+//! for.
+
+#[cfg(feature = "kzg")]
+pub mod kzg;
+pub mod merkle;
+
+use alloc::vec::Vec;
+
+use crate::core::fp::Fp;
+#[cfg(feature = "prove")]
+use crate::prove::write_iop::WriteIOP;
+#[cfg(feature = "verify")]
+use crate::verify::{read_iop::ReadIOP, VerificationError};
+
+/// A way of committing to a matrix and proving the values of its rows.
+pub trait CommitmentScheme {
+    /// The prover's side of a commitment.
+    #[cfg(feature = "prove")]
+    type Prover: CommitmentProver;
+
+    /// The verifier's side of a commitment.
+    #[cfg(feature = "verify")]
+    type Verifier: CommitmentVerifier;
+
+    /// Commit to `matrix`, which holds `cols` columns of `rows` values each.
+    /// `rows` must be a power of two.
+    #[cfg(feature = "prove")]
+    fn prover(&self, matrix: &[Fp], rows: usize, cols: usize) -> Self::Prover;
+
+    /// Read a commitment to a matrix of `rows` by `cols` values, as written
+    /// by [CommitmentProver::commit].
+    #[cfg(feature = "verify")]
+    fn verifier(
+        &self,
+        iop: &mut ReadIOP,
+        rows: usize,
+        cols: usize,
+    ) -> Result<Self::Verifier, VerificationError>;
+}
+
+/// The prover's side of a commitment made with a [CommitmentScheme].
+#[cfg(feature = "prove")]
+pub trait CommitmentProver {
+    /// Write the commitment and mix it into the transcript.
+    fn commit(&self, iop: &mut WriteIOP);
+
+    /// Write a proof of the values in row `idx`, and return them.
+    fn open(&mut self, iop: &mut WriteIOP, idx: usize) -> Vec<Fp>;
+}
+
+/// The verifier's side of a commitment made with a [CommitmentScheme].
+#[cfg(feature = "verify")]
+pub trait CommitmentVerifier {
+    /// Read the proof written by [CommitmentProver::open] for row `idx`, and
+    /// return the values it proves.
+    fn open(&mut self, iop: &mut ReadIOP, idx: usize) -> Result<Vec<Fp>, VerificationError>;
+}
+
+#[cfg(all(test, feature = "prove", feature = "verify"))]
+pub(crate) mod tests {
+    use alloc::vec::Vec;
+
+    use super::{CommitmentProver, CommitmentScheme, CommitmentVerifier};
+    use crate::{
+        core::{fp::Fp, sha_cpu},
+        prove::write_iop::WriteIOP,
+        verify::{read_iop::ReadIOP, VerificationError},
+    };
+
+    // Commits to a matrix with `scheme` and opens `queries` of its rows,
+    // checking the verifier accepts them, and that it rejects the proof with
+    // any one opened value changed.
+    pub fn check_openings<S: CommitmentScheme>(scheme: &S, rows: usize, cols: usize) {
+        let matrix: Vec<Fp> = (0..(rows * cols) as u32)
+            .map(|x| Fp::new(x * x + 1))
+            .collect();
+        let queries = [0, rows - 1, 1, rows / 2];
+        let sha = sha_cpu::Impl {};
+        let mut iop = WriteIOP::new(&sha);
+        iop.batch_openings = false;
+        let mut prover = scheme.prover(&matrix, rows, cols);
+        prover.commit(&mut iop);
+        for idx in queries {
+            let row = prover.open(&mut iop, idx);
+            let expected: Vec<Fp> = (0..cols).map(|col| matrix[idx + col * rows]).collect();
+            assert_eq!(row, expected);
+        }
+        let proof = iop.proof;
+
+        let verify = |proof: &[u32]| -> Result<Vec<Vec<Fp>>, VerificationError> {
+            let mut iop = ReadIOP::new(&sha, proof);
+            let mut verifier = scheme.verifier(&mut iop, rows, cols)?;
+            let rows = queries
+                .iter()
+                .map(|idx| verifier.open(&mut iop, *idx))
+                .collect::<Result<Vec<_>, _>>()?;
+            iop.verify_complete()?;
+            Ok(rows)
+        };
+        let opened = verify(&proof).unwrap();
+        for (idx, row) in queries.iter().zip(opened) {
+            let expected: Vec<Fp> = (0..cols).map(|col| matrix[idx + col * rows]).collect();
+            assert_eq!(row, expected);
+        }
+
+        // Change the first value of the last row opened.
+        let at = proof
+            .iter()
+            .rposition(|word| *word == u32::from(matrix[queries[3]]))
+            .unwrap();
+        let mut tampered = proof;
+        tampered[at] += 1;
+        assert!(verify(&tampered).is_err());
+    }
+}
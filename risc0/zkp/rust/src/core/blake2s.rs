@@ -0,0 +1,202 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! BLAKE2s-256 hashing, as specified by RFC 7693.
+//!
+//! BLAKE2s operates entirely on 32-bit words, so it maps directly onto the
+//! RV32IM instruction set and runs in guests without any intermediate
+//! 64-bit arithmetic.
+
+/// The number of bytes in a BLAKE2s block.
+pub const BLOCK_SIZE: usize = 64;
+
+/// The number of bytes in a BLAKE2s-256 digest.
+pub const DIGEST_SIZE: usize = 32;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+#[inline(always)]
+fn mix(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+/// The BLAKE2s compression function.
+///
+/// Mixes the 16 little-endian message words `m` into the chaining value
+/// `h`, where `t` is the number of bytes hashed so far including this block
+/// and `last` is set on the final block.
+pub fn compress(h: &mut [u32; 8], m: &[u32; 16], t: u64, last: bool) {
+    let mut v = [0u32; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if last {
+        v[14] = !v[14];
+    }
+    for s in SIGMA {
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// An incremental, unkeyed BLAKE2s-256 hasher.
+#[derive(Clone)]
+pub struct Blake2s {
+    h: [u32; 8],
+    buf: [u8; BLOCK_SIZE],
+    len: usize,
+    t: u64,
+}
+
+impl Blake2s {
+    /// Create a hasher with no input absorbed.
+    pub fn new() -> Self {
+        let mut h = IV;
+        // Parameter block: digest length, no key, fanout and depth of one.
+        h[0] ^= 0x0101_0000 | DIGEST_SIZE as u32;
+        Blake2s {
+            h,
+            buf: [0; BLOCK_SIZE],
+            len: 0,
+            t: 0,
+        }
+    }
+
+    /// Absorb `bytes` into the hasher.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            // The final block must be compressed with `last` set, so a full
+            // buffer is only compressed once more input arrives.
+            if self.len == BLOCK_SIZE {
+                self.t += BLOCK_SIZE as u64;
+                self.compress(false);
+                self.len = 0;
+            }
+            let take = (BLOCK_SIZE - self.len).min(bytes.len());
+            self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+            self.len += take;
+            bytes = &bytes[take..];
+        }
+    }
+
+    /// Pad the input and return the digest.
+    pub fn finalize(mut self) -> [u8; DIGEST_SIZE] {
+        self.t += self.len as u64;
+        self.buf[self.len..].fill(0);
+        self.compress(true);
+        let mut out = [0u8; DIGEST_SIZE];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.h) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, last: bool) {
+        let mut m = [0u32; 16];
+        for (word, chunk) in m.iter_mut().zip(self.buf.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        compress(&mut self.h, &m, self.t, last);
+    }
+}
+
+impl Default for Blake2s {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute the BLAKE2s-256 digest of `bytes`.
+pub fn digest(bytes: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut hasher = Blake2s::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{digest, Blake2s};
+
+    fn hex(bytes: &[u8]) -> alloc::string::String {
+        bytes.iter().map(|b| alloc::format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(
+            hex(&digest(b"")),
+            "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9"
+        );
+        assert_eq!(
+            hex(&digest(b"abc")),
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+        );
+        // Exactly one block, then one byte past it.
+        let data: Vec<u8> = (0..65).collect();
+        assert_eq!(
+            hex(&digest(&data[..64])),
+            "56f34e8b96557e90c1f24b52d0c89d51086acf1b00f634cf1dde9233b8eaaa3e"
+        );
+        assert_eq!(
+            hex(&digest(&data)),
+            "1b53ee94aaf34e4b159d48de352c7f0661d0a40edff95a0b1639b4090e974472"
+        );
+    }
+
+    #[test]
+    fn incremental() {
+        let data: Vec<u8> = (0..1000).map(|x| x as u8).collect();
+        let mut hasher = Blake2s::new();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(
+            hex(&hasher.finalize()),
+            "b5f9d7799111edafc9326fbf667be98140b5e20ce5e151793c59125bf654ac18"
+        );
+    }
+}
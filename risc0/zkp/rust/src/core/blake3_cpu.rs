@@ -0,0 +1,464 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A CPU implementation of BLAKE3.
+//!
+//! BLAKE3 compresses a 64-byte block in seven rounds where SHA-256 takes
+//! sixty-four, so committing to a trace with it takes roughly half the time
+//! on the CPU.  Seals committed with it can't be checked on-chain, where
+//! only SHA-256 and Keccak-256 are cheap, but suit users who don't need
+//! that.
+//!
+//! Inputs are hashed as plain BLAKE3 over their bytes:
+//!
+//! * a [Digest] is its [bytes](Digest::to_bytes), so a node of a Merkle tree is
+//!   a single, one-block compression of its children;
+//! * an [Fp] or a word is its canonical value, in little-endian order.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use super::{
+    fp::Fp,
+    fp4::Fp4,
+    sha::{Digest, Sha, DIGEST_WORDS},
+};
+
+/// The number of bytes in a BLAKE3 block.
+pub const BLOCK_LEN: usize = 64;
+
+/// The number of bytes in a BLAKE3 chunk, the leaves of its internal tree.
+pub const CHUNK_LEN: usize = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+#[inline(always)]
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+#[inline(always)]
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    // Columns
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+    // Diagonals
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+/// The BLAKE3 compression function.
+///
+/// Mixes the 16 little-endian message words `block`, of which the first
+/// `block_len` bytes are input, into the chaining value `cv`, and returns
+/// the full 16-word output; its first 8 words are the next chaining value.
+pub fn compress(
+    cv: &[u32; 8],
+    block: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut state = [
+        cv[0],
+        cv[1],
+        cv[2],
+        cv[3],
+        cv[4],
+        cv[5],
+        cv[6],
+        cv[7],
+        IV[0],
+        IV[1],
+        IV[2],
+        IV[3],
+        counter as u32,
+        (counter >> 32) as u32,
+        block_len,
+        flags,
+    ];
+    let mut m = *block;
+    for i in 0..7 {
+        round(&mut state, &m);
+        if i < 6 {
+            m = MSG_PERMUTATION.map(|j| m[j]);
+        }
+    }
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+    state
+}
+
+fn first_8(words: [u32; 16]) -> [u32; 8] {
+    words[..8].try_into().unwrap()
+}
+
+fn block_words(bytes: &[u8; BLOCK_LEN]) -> [u32; 16] {
+    let mut words = [0; 16];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    words
+}
+
+// The last compression of a chunk or parent, whose flags depend on whether
+// it's the root of the tree.
+struct Output {
+    cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        first_8(compress(
+            &self.cv,
+            &self.block,
+            self.counter,
+            self.block_len,
+            self.flags,
+        ))
+    }
+
+    fn root(&self) -> [u32; 8] {
+        first_8(compress(
+            &self.cv,
+            &self.block,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        ))
+    }
+}
+
+fn parent_output(left: &[u32; 8], right: &[u32; 8]) -> Output {
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(left);
+    block[8..].copy_from_slice(right);
+    Output {
+        cv: IV,
+        block,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+#[derive(Clone)]
+struct ChunkState {
+    cv: [u32; 8],
+    counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+}
+
+impl ChunkState {
+    fn new(counter: u64) -> Self {
+        ChunkState {
+            cv: IV,
+            counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            // Only compress a full block once more input arrives, since the
+            // last block of a chunk is compressed with other flags.
+            if self.block_len == BLOCK_LEN {
+                self.cv = first_8(compress(
+                    &self.cv,
+                    &block_words(&self.block),
+                    self.counter,
+                    BLOCK_LEN as u32,
+                    self.start_flag(),
+                ));
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+            let take = (BLOCK_LEN - self.block_len).min(bytes.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&bytes[..take]);
+            self.block_len += take;
+            bytes = &bytes[take..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        Output {
+            cv: self.cv,
+            block: block_words(&self.block),
+            counter: self.counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+/// An incremental BLAKE3 hasher, with a 32-byte output.
+#[derive(Clone)]
+pub struct Blake3 {
+    chunk: ChunkState,
+    // The chaining values of the complete subtrees to the left of `chunk`,
+    // one per set bit of the number of chunks hashed.
+    stack: Vec<[u32; 8]>,
+}
+
+impl Blake3 {
+    /// Create a hasher with no input absorbed.
+    pub fn new() -> Self {
+        Blake3 {
+            chunk: ChunkState::new(0),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Absorb `bytes` into the hasher.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            // As with blocks, only finish a full chunk once more input
+            // arrives, since the root is compressed with other flags.
+            if self.chunk.len() == CHUNK_LEN {
+                let mut cv = self.chunk.output().chaining_value();
+                let mut chunks = self.chunk.counter + 1;
+                while chunks & 1 == 0 {
+                    cv = parent_output(&self.stack.pop().unwrap(), &cv).chaining_value();
+                    chunks >>= 1;
+                }
+                self.stack.push(cv);
+                self.chunk = ChunkState::new(self.chunk.counter + 1);
+            }
+            let take = (CHUNK_LEN - self.chunk.len()).min(bytes.len());
+            self.chunk.update(&bytes[..take]);
+            bytes = &bytes[take..];
+        }
+    }
+
+    /// Return the 32-byte digest.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut output = self.chunk.output();
+        for left in self.stack.iter().rev() {
+            output = parent_output(left, &output.chaining_value());
+        }
+        let mut out = [0u8; 32];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(output.root()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hash_le_words(words: impl Iterator<Item = u32>) -> Box<Digest> {
+    let mut hasher = Blake3::new();
+    let mut buf = [0u8; BLOCK_LEN];
+    let mut len = 0;
+    for word in words {
+        buf[len..len + 4].copy_from_slice(&word.to_le_bytes());
+        len += 4;
+        if len == BLOCK_LEN {
+            hasher.update(&buf);
+            len = 0;
+        }
+    }
+    hasher.update(&buf[..len]);
+    Box::new(Digest::from_bytes(hasher.finalize()))
+}
+
+/// A CPU-based BLAKE3 implementation of [Sha].
+#[derive(Clone)]
+pub struct Impl {}
+
+impl Sha for Impl {
+    type DigestPtr = Box<Digest>;
+
+    const SUITE_ID: u32 = 3;
+
+    fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
+        let mut hasher = Blake3::new();
+        hasher.update(bytes);
+        Box::new(Digest::from_bytes(hasher.finalize()))
+    }
+
+    fn hash_words(&self, words: &[u32]) -> Self::DigestPtr {
+        hash_le_words(words.iter().copied())
+    }
+
+    // BLAKE3 pads its last block itself, so raw words are hashed exactly like
+    // any other words.
+    fn hash_raw_words(&self, words: &[u32]) -> Self::DigestPtr {
+        self.hash_words(words)
+    }
+
+    fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
+        hash_le_words(fps.iter().map(|fp| u32::from(*fp)))
+    }
+
+    fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
+        let flat: Vec<Fp> = fp4s.iter().flat_map(|fp4| fp4.elems()).copied().collect();
+        self.hash_fps(&flat)
+    }
+
+    // A single compression; the bytes of a digest are its words in
+    // big-endian order, but BLAKE3 reads them in little-endian order.
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Self::DigestPtr {
+        let mut block = [0; 16];
+        for (word, x) in block.iter_mut().zip(a.get().iter().chain(b.get())) {
+            *word = x.swap_bytes();
+        }
+        let out = compress(
+            &IV,
+            &block,
+            0,
+            BLOCK_LEN as u32,
+            CHUNK_START | CHUNK_END | ROOT,
+        );
+        let mut words = [0; DIGEST_WORDS];
+        for (word, x) in words.iter_mut().zip(out) {
+            *word = x.swap_bytes();
+        }
+        Box::new(Digest::new(words))
+    }
+
+    fn mix(&self, pool: &mut Self::DigestPtr, val: &Digest) {
+        for (pool_word, val_word) in pool.get_mut().iter_mut().zip(val.get()) {
+            *pool_word ^= *val_word;
+        }
+    }
+}
+
+impl core::fmt::Debug for Impl {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::write!(f, "CPU BLAKE3 implementation")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Blake3, Impl};
+    use crate::core::{
+        fp::Fp,
+        sha::{Digest, Sha},
+    };
+
+    // The official test vectors, whose input is 0, 1, 2, ... modulo 251.
+    fn vector(len: usize) -> String {
+        let input: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        Impl {}.hash_bytes(&input).to_hex()
+    }
+
+    #[test]
+    fn vectors() {
+        let sha = Impl {};
+        assert_eq!(
+            sha.hash_bytes(b"").to_hex(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            sha.hash_bytes(b"abc").to_hex(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(
+            vector(1),
+            "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213"
+        );
+        assert_eq!(
+            vector(1024),
+            "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"
+        );
+        // Crosses into a second chunk, then a tree of three chunks.
+        assert_eq!(
+            vector(1025),
+            "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"
+        );
+        assert_eq!(
+            vector(3072),
+            "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2"
+        );
+    }
+
+    #[test]
+    fn incremental() {
+        let input: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+        let mut hasher = Blake3::new();
+        for piece in input.chunks(100) {
+            hasher.update(piece);
+        }
+        assert_eq!(
+            Digest::from_bytes(hasher.finalize()),
+            *Impl {}.hash_bytes(&input)
+        );
+    }
+
+    #[test]
+    fn encodings() {
+        let sha = Impl {};
+        let a =
+            Digest::from_str("6a09e667bb67ae853c6ef372a54ff53a510e527f9b05688c1f83d9ab5be0cd19");
+        let b =
+            Digest::from_str("ed375cadc653bb9078cee904acee6f7ff2bf7476c92dc92911bae27c41ebc015");
+        let mut bytes = a.to_bytes().to_vec();
+        bytes.extend_from_slice(&b.to_bytes());
+        assert_eq!(*sha.hash_pair(&a, &b), *sha.hash_bytes(&bytes));
+
+        let fps: Vec<Fp> = (0..37).map(Fp::new).collect();
+        let bytes: Vec<u8> = (0..37u32).flat_map(|x| x.to_le_bytes()).collect();
+        assert_eq!(*sha.hash_fps(&fps), *sha.hash_bytes(&bytes));
+        let words: Vec<u32> = (0..37).collect();
+        assert_eq!(*sha.hash_words(&words), *sha.hash_bytes(&bytes));
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The hashes a proof is committed with.
+//!
+//! A [HashSuite] hashes the leaves and nodes of a proof's Merkle trees and
+//! drives its Fiat-Shamir transcript.  The prover and verifier carry one as
+//! a trait object, so the suite can be chosen at run time, and the prover
+//! records its [id](HashSuite::id) in the seal's header, the po2 word, so
+//! that a seal checked with the wrong suite fails with
+//! [HashSuiteMismatch](crate::verify::VerificationError::HashSuiteMismatch)
+//! rather than somewhere in the middle of the proof.
+//!
+//! Every [Sha] is a [HashSuite], with the id [Sha::SUITE_ID].
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::Debug;
+
+use rand::RngCore;
+
+use super::{
+    fp::Fp,
+    fp4::Fp4,
+    sha::{Digest, Sha},
+    sha_rng::ShaRng,
+};
+
+/// How far up a seal's po2 word the [id](HashSuite::id) of its hash suite
+/// is shifted.
+pub const SUITE_ID_SHIFT: u32 = 29;
+
+/// The bits of an [id](HashSuite::id) that fit in a seal's header.
+pub const SUITE_ID_MASK: u32 = 0b11;
+
+/// The randomness of a Fiat-Shamir transcript, which the prover and
+/// verifier both feed with their commitments.
+pub trait TranscriptRng: RngCore {
+    /// Mix a commitment into the transcript.
+    fn mix(&mut self, val: &Digest);
+}
+
+impl<S: Sha> TranscriptRng for ShaRng<S> {
+    fn mix(&mut self, val: &Digest) {
+        ShaRng::mix(self, val)
+    }
+}
+
+/// The hashes a proof's Merkle trees and transcript are built with.
+pub trait HashSuite: Debug {
+    /// Identifies this suite in a seal's header; at most [SUITE_ID_MASK].
+    fn id(&self) -> u32;
+
+    /// Hash a leaf of field elements.
+    fn hash_elems(&self, elems: &[Fp]) -> Digest;
+
+    /// Hash a node from its children.
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Digest;
+
+    /// Start a new transcript.
+    fn rng(&self) -> Box<dyn TranscriptRng + '_>;
+
+    /// Hash extension field elements, as the [hash_elems](Self::hash_elems)
+    /// of their components.
+    fn hash_ext_elems(&self, elems: &[Fp4]) -> Digest {
+        let flat: Vec<Fp> = elems.iter().flat_map(|x| x.elems()).copied().collect();
+        self.hash_elems(&flat)
+    }
+}
+
+impl<S: Sha> HashSuite for S {
+    fn id(&self) -> u32 {
+        S::SUITE_ID
+    }
+
+    fn hash_elems(&self, elems: &[Fp]) -> Digest {
+        *self.hash_fps(elems)
+    }
+
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Digest {
+        *Sha::hash_pair(self, a, b)
+    }
+
+    fn rng(&self) -> Box<dyn TranscriptRng + '_> {
+        Box::new(ShaRng::new(self))
+    }
+
+    fn hash_ext_elems(&self, elems: &[Fp4]) -> Digest {
+        *self.hash_fp4s(elems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::{HashSuite, SUITE_ID_MASK};
+    use crate::core::{
+        blake3_cpu,
+        fp::Fp,
+        fp4::Fp4,
+        keccak_cpu, poseidon,
+        sha::{self, Sha},
+    };
+
+    #[test]
+    fn suites() {
+        let suites: [&dyn HashSuite; 4] = [
+            sha::default_implementation(),
+            &keccak_cpu::Impl {},
+            &poseidon::Impl {},
+            &blake3_cpu::Impl {},
+        ];
+        for (i, suite) in suites.iter().enumerate() {
+            assert!(suite.id() <= SUITE_ID_MASK);
+            for other in &suites[i + 1..] {
+                assert_ne!(suite.id(), other.id());
+            }
+        }
+
+        let sha = sha::default_implementation();
+        let suite: &dyn HashSuite = sha;
+        let fps: Vec<Fp> = (0..20).map(Fp::new).collect();
+        assert_eq!(suite.hash_elems(&fps), *sha.hash_fps(&fps));
+        let (a, b) = (*sha.hash_bytes(b"a"), *sha.hash_bytes(b"b"));
+        assert_eq!(suite.hash_pair(&a, &b), *Sha::hash_pair(sha, &a, &b));
+        let fp4s = [Fp4::from_u32(1), Fp4::from_u32(2)];
+        assert_eq!(suite.hash_ext_elems(&fp4s), *sha.hash_fp4s(&fp4s));
+
+        // A transcript depends only on what was mixed into it.
+        let mut x = suite.rng();
+        let mut y = suite.rng();
+        x.mix(&a);
+        y.mix(&a);
+        assert_eq!(x.next_u32(), y.next_u32());
+        y.mix(&b);
+        assert_ne!(x.next_u32(), y.next_u32());
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Simple Keccak-256 wrappers.
+//!
+//! This is the hash used by the EVM's `KECCAK256` opcode, with the original
+//! Keccak padding rather than the padding of the FIPS-202 SHA-3 standard.
+//! Every input is encoded the way Solidity's `abi.encodePacked` would encode
+//! it, so an on-chain verifier can recompute each digest with a single
+//! opcode:
+//!
+//! * a [Digest] is a `bytes32`, its words in big-endian order;
+//! * an [Fp] is a `uint32`, in big-endian order.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use super::{
+    fp::Fp,
+    fp4::Fp4,
+    sha::{Digest, Sha},
+};
+
+// The number of bytes absorbed per permutation.
+const RATE: usize = 136;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The Keccak-f[1600] permutation.
+pub fn keccak_f(state: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS {
+        // Theta
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for i in 0..24 {
+            let next = state[PI_LANES[i]];
+            state[PI_LANES[i]] = last.rotate_left(ROTATIONS[i]);
+            last = next;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let row = [
+                state[5 * y],
+                state[5 * y + 1],
+                state[5 * y + 2],
+                state[5 * y + 3],
+                state[5 * y + 4],
+            ];
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        state[0] ^= rc;
+    }
+}
+
+/// An incremental Keccak-256 hasher.
+#[derive(Clone)]
+pub struct Keccak256 {
+    state: [u64; 25],
+    buf: [u8; RATE],
+    len: usize,
+}
+
+impl Keccak256 {
+    /// Create a hasher with no input absorbed.
+    pub fn new() -> Self {
+        Keccak256 {
+            state: [0; 25],
+            buf: [0; RATE],
+            len: 0,
+        }
+    }
+
+    /// Absorb `bytes` into the hasher.
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let take = (RATE - self.len).min(bytes.len());
+            self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+            self.len += take;
+            bytes = &bytes[take..];
+            if self.len == RATE {
+                self.absorb_block();
+            }
+        }
+    }
+
+    /// Pad the input and return the 32-byte digest.
+    pub fn finalize(mut self) -> [u8; 32] {
+        self.buf[self.len..].fill(0);
+        self.buf[self.len] |= 0x01;
+        self.buf[RATE - 1] |= 0x80;
+        self.absorb_block();
+        let mut out = [0u8; 32];
+        for (chunk, lane) in out.chunks_exact_mut(8).zip(self.state) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        out
+    }
+
+    fn absorb_block(&mut self) {
+        for (lane, chunk) in self.state.iter_mut().zip(self.buf.chunks_exact(8)) {
+            *lane ^= u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        keccak_f(&mut self.state);
+        self.len = 0;
+    }
+}
+
+impl Default for Keccak256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_digest(bytes: [u8; 32]) -> Box<Digest> {
+    Box::new(Digest::from_bytes(bytes))
+}
+
+fn hash_be_words(words: impl Iterator<Item = u32>) -> Box<Digest> {
+    let mut hasher = Keccak256::new();
+    for word in words {
+        hasher.update(&word.to_be_bytes());
+    }
+    to_digest(hasher.finalize())
+}
+
+/// A CPU-based Keccak-256 implementation of [Sha].
+#[derive(Clone)]
+pub struct Impl {}
+
+impl Sha for Impl {
+    type DigestPtr = Box<Digest>;
+
+    const SUITE_ID: u32 = 1;
+
+    fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
+        let mut hasher = Keccak256::new();
+        hasher.update(bytes);
+        to_digest(hasher.finalize())
+    }
+
+    fn hash_words(&self, words: &[u32]) -> Self::DigestPtr {
+        hash_be_words(words.iter().copied())
+    }
+
+    // Keccak has no separate compression function, so raw words are hashed
+    // exactly like any other words.
+    fn hash_raw_words(&self, words: &[u32]) -> Self::DigestPtr {
+        self.hash_words(words)
+    }
+
+    fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
+        hash_be_words(fps.iter().map(|fp| u32::from(*fp)))
+    }
+
+    fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
+        let flat: Vec<Fp> = fp4s.iter().flat_map(|fp4| fp4.elems()).copied().collect();
+        self.hash_fps(&flat)
+    }
+
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Self::DigestPtr {
+        hash_be_words(a.get().iter().chain(b.get()).copied())
+    }
+
+    fn mix(&self, pool: &mut Self::DigestPtr, val: &Digest) {
+        for (pool_word, val_word) in pool.get_mut().iter_mut().zip(val.get()) {
+            *pool_word ^= *val_word;
+        }
+    }
+}
+
+impl core::fmt::Debug for Impl {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::write!(f, "CPU Keccak-256 implementation")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Impl;
+    use crate::core::{
+        fp::Fp,
+        sha::{Digest, Sha},
+    };
+
+    #[test]
+    fn vectors() {
+        let sha = Impl {};
+        assert_eq!(
+            sha.hash_bytes(b"").to_hex(),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            sha.hash_bytes(b"abc").to_hex(),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        // Crosses the 136-byte block boundary.
+        assert_eq!(
+            sha.hash_bytes(&[0x61; 200]).to_hex(),
+            "96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d"
+        );
+    }
+
+    #[test]
+    fn encode_packed() {
+        let sha = Impl {};
+        let a =
+            Digest::from_str("6a09e667bb67ae853c6ef372a54ff53a510e527f9b05688c1f83d9ab5be0cd19");
+        let b =
+            Digest::from_str("ed375cadc653bb9078cee904acee6f7ff2bf7476c92dc92911bae27c41ebc015");
+        let mut packed = Vec::new();
+        packed.extend_from_slice(&packed_bytes(&a));
+        packed.extend_from_slice(&packed_bytes(&b));
+        assert_eq!(*sha.hash_pair(&a, &b), *sha.hash_bytes(&packed));
+
+        let fps: Vec<Fp> = (0..9).map(Fp::new).collect();
+        let bytes: Vec<u8> = (0..9u32).flat_map(|x| x.to_be_bytes()).collect();
+        assert_eq!(*sha.hash_fps(&fps), *sha.hash_bytes(&bytes));
+    }
+
+    fn packed_bytes(digest: &Digest) -> Vec<u8> {
+        digest
+            .get()
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect()
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Low-degree extension onto a coset.
+//!
+//! The prover commits to each polynomial by its values on a domain
+//! `blowup` times larger than its degree bound, shifted off the subgroup
+//! of roots of unity so that the committed values reveal nothing about the
+//! trace, which lives on the subgroup itself.  [CosetLde] holds those two
+//! parameters; [CosetLde::ZK] is the choice this crate's prover and
+//! verifier make.
+
+use alloc::vec::Vec;
+use core::ops::Mul;
+
+use super::{
+    fp::Fp,
+    log2_ceil,
+    ntt::{bit_reverse, evaluate_ntt, expand, interpolate_ntt},
+};
+use crate::{
+    field::{Elem, TwoAdicField},
+    INV_RATE,
+};
+
+/// A low-degree extension by a power-of-two `blowup` onto the coset
+/// `shift * H`, where `H` is the group of roots of unity of the extended
+/// domain's size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CosetLde {
+    blowup_bits: usize,
+    shift: Fp,
+}
+
+impl CosetLde {
+    /// The parameters of this crate's prover: a blowup of [INV_RATE], and a
+    /// shift of 3, which generates the multiplicative group and so lies
+    /// outside every proper subgroup.
+    pub const ZK: CosetLde = CosetLde {
+        blowup_bits: log2_ceil(INV_RATE),
+        shift: Fp::new(3),
+    };
+
+    /// An extension by `blowup`, a power of two, onto the coset of `shift`,
+    /// which must be non-zero.  A shift of one evaluates on the subgroup
+    /// itself.
+    pub fn new(blowup: usize, shift: Fp) -> Self {
+        assert!(blowup.is_power_of_two(), "Blowup must be a power of two");
+        assert!(shift != Fp::ZERO, "Coset shift must be non-zero");
+        CosetLde {
+            blowup_bits: log2_ceil(blowup),
+            shift,
+        }
+    }
+
+    /// The factor by which the domain exceeds the degree bound.
+    pub fn blowup(&self) -> usize {
+        1 << self.blowup_bits
+    }
+
+    /// The base 2 logarithm of [CosetLde::blowup], as the NTT's
+    /// `expand_bits` takes it.
+    pub fn blowup_bits(&self) -> usize {
+        self.blowup_bits
+    }
+
+    /// The element by which the domain is shifted.
+    pub fn shift(&self) -> Fp {
+        self.shift
+    }
+
+    /// The size of the domain for polynomials of `size` coefficients.
+    pub fn domain_size(&self, size: usize) -> usize {
+        size << self.blowup_bits
+    }
+
+    /// The `i`-th point of the domain for polynomials of `size`
+    /// coefficients: `shift * w^i`, where `w` is the root of unity of the
+    /// domain's size.
+    pub fn point(&self, size: usize, i: usize) -> Fp {
+        let po2 = log2_ceil(self.domain_size(size));
+        self.shift * Fp::root_of_unity(po2).pow(i)
+    }
+
+    /// Evaluate the polynomial with the given coefficients, lowest degree
+    /// first, at each point of its domain in turn.  The number of
+    /// coefficients must be a power of two.
+    pub fn evaluate<E>(&self, coeffs: &[E]) -> Vec<E>
+    where
+        E: Elem + Mul<Fp, Output = E>,
+    {
+        // f(shift * x) has coefficients c_i shift^i, and is evaluated on
+        // the subgroup.
+        let mut shifted: Vec<E> = coeffs.to_vec();
+        scale_powers(&mut shifted, self.shift);
+        bit_reverse(&mut shifted);
+        let mut out = Vec::with_capacity(self.domain_size(coeffs.len()));
+        out.resize(self.domain_size(coeffs.len()), E::ZERO);
+        expand(&mut out, &shifted, self.blowup_bits);
+        evaluate_ntt(&mut out, self.blowup_bits);
+        out
+    }
+
+    /// Recover the coefficients, lowest degree first, of the polynomial
+    /// whose values on its domain are `evals`: the inverse of
+    /// [CosetLde::evaluate].  Returns `None` if `evals` are not the values
+    /// of a polynomial of degree less than `evals.len() / blowup`.
+    pub fn interpolate<E>(&self, evals: &[E]) -> Option<Vec<E>>
+    where
+        E: Elem + Mul<Fp, Output = E>,
+    {
+        let size = evals.len() >> self.blowup_bits;
+        assert_eq!(self.domain_size(size), evals.len());
+        let mut coeffs = evals.to_vec();
+        interpolate_ntt(&mut coeffs);
+        bit_reverse(&mut coeffs);
+        if coeffs[size..].iter().any(|c| *c != E::ZERO) {
+            return None;
+        }
+        coeffs.truncate(size);
+        scale_powers(&mut coeffs, self.shift.inv());
+        Some(coeffs)
+    }
+}
+
+// Multiplies each `coeffs[i]` by `x^i`.
+fn scale_powers<E: Elem + Mul<Fp, Output = E>>(coeffs: &mut [E], x: Fp) {
+    let mut cur = Fp::ONE;
+    for coeff in coeffs {
+        *coeff = *coeff * cur;
+        cur *= x;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::CosetLde;
+    use crate::{
+        core::{fp::Fp, fp4::Fp4, poly::Polynomial},
+        field::Elem,
+    };
+
+    #[test]
+    fn evaluate() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let coeffs: Vec<Fp4> = (0..16).map(|_| Fp4::random(&mut rng)).collect();
+        let poly = Polynomial::new(coeffs.clone());
+        for lde in [
+            CosetLde::ZK,
+            CosetLde::new(8, Fp::new(5)),
+            CosetLde::new(1, Fp::ONE),
+        ] {
+            let evals = lde.evaluate(&coeffs);
+            assert_eq!(evals.len(), lde.domain_size(16));
+            for (i, y) in evals.iter().enumerate() {
+                assert_eq!(*y, poly.eval(Fp4::from_fp(lde.point(16, i))));
+            }
+            assert_eq!(lde.interpolate(&evals), Some(coeffs.clone()));
+        }
+        // A polynomial of too high a degree for the domain.
+        let lde = CosetLde::new(2, Fp::new(3));
+        let mut evals = lde.evaluate(&coeffs);
+        evals[0] += Fp4::ONE;
+        assert_eq!(lde.interpolate(&evals), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn blowup() {
+        CosetLde::new(3, Fp::new(3));
+    }
+}
@@ -34,9 +34,14 @@ pub mod fp4 {
     /// with multiple fields.
     pub const EXT_SIZE: usize = Fp4::EXT_SIZE;
 }
+pub mod blake2s;
+pub mod blake3_cpu;
+pub mod hash;
+pub mod keccak_cpu;
+pub mod lde;
 pub mod ntt;
 pub mod poly;
-pub mod rou;
+pub mod poseidon;
 pub mod sha;
 pub mod sha_cpu;
 pub mod sha_rng;
@@ -14,42 +14,25 @@
 
 //! An implementation of a Numeric Theoretic Transform (NTT).
 
+mod four_step;
+mod mixed;
+
 use core::ops::{Add, Mul, Sub};
 
 use paste::paste;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-use super::{
-    fp::Fp,
-    log2_ceil,
-    rou::{ROU_FWD, ROU_REV},
+pub use self::mixed::{
+    evaluate_ntt_mixed, interpolate_ntt_mixed, mixed_radix, mixed_root_of_unity,
 };
+use super::{fp::Fp, log2_ceil};
+pub use crate::util::{bit_rev_32, bit_reverse};
 
-use crate::field::Elem;
-
-/// Reverses the bits in a 32 bit number
-/// For example 1011...0100 becomes 0010...1101
-pub fn bit_rev_32(mut x: u32) -> u32 {
-    x = ((x & 0xaaaaaaaa) >> 1) | ((x & 0x55555555) << 1);
-    x = ((x & 0xcccccccc) >> 2) | ((x & 0x33333333) << 2);
-    x = ((x & 0xf0f0f0f0) >> 4) | ((x & 0x0f0f0f0f) << 4);
-    x = ((x & 0xff00ff00) >> 8) | ((x & 0x00ff00ff) << 8);
-    (x >> 16) | (x << 16)
-}
-
-/// Bit reverses the indices in an array of (1 << n) numbers.
-/// This permutes the values in the array so that a value which is previously
-/// in index i, will now go in the index i' given by reversing the bits of i.
-/// For example, with n=4, the value at index 3=0011 will go to index 12=1100.
-pub fn bit_reverse<T: Copy>(io: &mut [T]) {
-    let n = log2_ceil(io.len());
-    assert_eq!(1 << n, io.len());
-    for i in 0..io.len() {
-        let rev_idx = (bit_rev_32(i as u32) >> (32 - n)) as usize;
-        if i < rev_idx {
-            io.swap(i, rev_idx);
-        }
-    }
-}
+use crate::field::{
+    baby_bear::{simd::LANES, ElemVec},
+    Elem, TwoAdicField,
+};
 
 #[inline]
 fn fwd_butterfly_0<T>(_: &mut [T], _: usize) {
@@ -75,7 +58,7 @@ macro_rules! butterfly {
                 let half = 1 << ($n - 1);
                 [<fwd_butterfly_ $x>](&mut io[..half], expand_bits);
                 [<fwd_butterfly_ $x>](&mut io[half..], expand_bits);
-                let step = Fp::new(ROU_FWD[$n]);
+                let step = Fp::root_of_unity($n);
                 let mut cur = Fp::ONE;
                 for i in 0..half {
                     let a = io[i];
@@ -92,7 +75,7 @@ macro_rules! butterfly {
                 T: Copy + Mul<Fp, Output = T> + Add<Output = T> + Sub<Output = T>,
             {
                 let half = 1 << ($n - 1);
-                let step = Fp::new(ROU_REV[$n]);
+                let step = Fp::inv_root_of_unity($n);
                 let mut cur = Fp::ONE;
                 for i in 0..half {
                     let a = io[i];
@@ -136,16 +119,91 @@ butterfly!(3, 2);
 butterfly!(2, 1);
 butterfly!(1, 0);
 
+// Dispatches to the butterfly for a buffer of `1 << n` elements.
+fn fwd_butterfly<T>(io: &mut [T], n: usize, expand_bits: usize)
+where
+    T: Copy + Mul<Fp, Output = T> + Add<Output = T> + Sub<Output = T>,
+{
+    match n {
+        0 => fwd_butterfly_0(io, expand_bits),
+        1 => fwd_butterfly_1(io, expand_bits),
+        2 => fwd_butterfly_2(io, expand_bits),
+        3 => fwd_butterfly_3(io, expand_bits),
+        4 => fwd_butterfly_4(io, expand_bits),
+        5 => fwd_butterfly_5(io, expand_bits),
+        6 => fwd_butterfly_6(io, expand_bits),
+        7 => fwd_butterfly_7(io, expand_bits),
+        8 => fwd_butterfly_8(io, expand_bits),
+        9 => fwd_butterfly_9(io, expand_bits),
+        10 => fwd_butterfly_10(io, expand_bits),
+        11 => fwd_butterfly_11(io, expand_bits),
+        12 => fwd_butterfly_12(io, expand_bits),
+        13 => fwd_butterfly_13(io, expand_bits),
+        14 => fwd_butterfly_14(io, expand_bits),
+        15 => fwd_butterfly_15(io, expand_bits),
+        16 => fwd_butterfly_16(io, expand_bits),
+        17 => fwd_butterfly_17(io, expand_bits),
+        18 => fwd_butterfly_18(io, expand_bits),
+        19 => fwd_butterfly_19(io, expand_bits),
+        20 => fwd_butterfly_20(io, expand_bits),
+        21 => fwd_butterfly_21(io, expand_bits),
+        22 => fwd_butterfly_22(io, expand_bits),
+        23 => fwd_butterfly_23(io, expand_bits),
+        24 => fwd_butterfly_24(io, expand_bits),
+        25 => fwd_butterfly_25(io, expand_bits),
+        26 => fwd_butterfly_26(io, expand_bits),
+        27 => fwd_butterfly_27(io, expand_bits),
+        _ => unreachable!(),
+    }
+}
+
+fn rev_butterfly<T>(io: &mut [T], n: usize)
+where
+    T: Copy + Mul<Fp, Output = T> + Add<Output = T> + Sub<Output = T>,
+{
+    match n {
+        0 => rev_butterfly_0(io),
+        1 => rev_butterfly_1(io),
+        2 => rev_butterfly_2(io),
+        3 => rev_butterfly_3(io),
+        4 => rev_butterfly_4(io),
+        5 => rev_butterfly_5(io),
+        6 => rev_butterfly_6(io),
+        7 => rev_butterfly_7(io),
+        8 => rev_butterfly_8(io),
+        9 => rev_butterfly_9(io),
+        10 => rev_butterfly_10(io),
+        11 => rev_butterfly_11(io),
+        12 => rev_butterfly_12(io),
+        13 => rev_butterfly_13(io),
+        14 => rev_butterfly_14(io),
+        15 => rev_butterfly_15(io),
+        16 => rev_butterfly_16(io),
+        17 => rev_butterfly_17(io),
+        18 => rev_butterfly_18(io),
+        19 => rev_butterfly_19(io),
+        20 => rev_butterfly_20(io),
+        21 => rev_butterfly_21(io),
+        22 => rev_butterfly_22(io),
+        23 => rev_butterfly_23(io),
+        24 => rev_butterfly_24(io),
+        25 => rev_butterfly_25(io),
+        26 => rev_butterfly_26(io),
+        27 => rev_butterfly_27(io),
+        _ => unreachable!(),
+    }
+}
+
 /// Perform a reverse butterfly transform of a buffer of (1 << n) numbers.
 /// The result of this computation is a discrete Fourier transform, but with
 /// changed indices. This is described [here](https://en.wikipedia.org/wiki/Cooley%E2%80%93Tukey_FFT_algorithm#Data_reordering,_bit_reversal,_and_in-place_algorithms)
 /// The output of rev_butterfly(io, n) at index i is the sum over k from 0 to
-/// 2^n-1 of io\[k\] ROU_REV\[n\]^(k i'), where i' is i bit-reversed as an n-bit
-/// number.
+/// 2^n-1 of io\[k\] w^(k i'), where w = `Fp::inv_root_of_unity(n)` and i' is i
+/// bit-reversed as an n-bit number.
 ///
 /// As an example, we'll work through a trace of the rev_butterfly algorithm
-/// with n = 3 on a list of length 8. Let w = ROU_REV\[3\] be the eighth root of
-/// unity. We start with
+/// with n = 3 on a list of length 8. Let w = `Fp::inv_root_of_unity(3)` be the
+/// eighth root of unity. We start with
 ///
 ///   \[a0, a1, a2, a3, a4, a5, a6, a7\]
 ///
@@ -204,37 +262,7 @@ where
     let size = io.len();
     let n = log2_ceil(size);
     assert_eq!(1 << n, size);
-    match n {
-        0 => rev_butterfly_0(io),
-        1 => rev_butterfly_1(io),
-        2 => rev_butterfly_2(io),
-        3 => rev_butterfly_3(io),
-        4 => rev_butterfly_4(io),
-        5 => rev_butterfly_5(io),
-        6 => rev_butterfly_6(io),
-        7 => rev_butterfly_7(io),
-        8 => rev_butterfly_8(io),
-        9 => rev_butterfly_9(io),
-        10 => rev_butterfly_10(io),
-        11 => rev_butterfly_11(io),
-        12 => rev_butterfly_12(io),
-        13 => rev_butterfly_13(io),
-        14 => rev_butterfly_14(io),
-        15 => rev_butterfly_15(io),
-        16 => rev_butterfly_16(io),
-        17 => rev_butterfly_17(io),
-        18 => rev_butterfly_18(io),
-        19 => rev_butterfly_19(io),
-        20 => rev_butterfly_20(io),
-        21 => rev_butterfly_21(io),
-        22 => rev_butterfly_22(io),
-        23 => rev_butterfly_23(io),
-        24 => rev_butterfly_24(io),
-        25 => rev_butterfly_25(io),
-        26 => rev_butterfly_26(io),
-        27 => rev_butterfly_27(io),
-        _ => unreachable!(),
-    }
+    rev_butterfly(io, n);
     let norm = Fp::new(size as u32).inv();
     for i in 0..size {
         io[i] = io[i] * norm;
@@ -250,37 +278,210 @@ where
     let size = io.len();
     let n = log2_ceil(size);
     assert_eq!(1 << n, size);
-    match n {
-        0 => fwd_butterfly_0(io, expand_bits),
-        1 => fwd_butterfly_1(io, expand_bits),
-        2 => fwd_butterfly_2(io, expand_bits),
-        3 => fwd_butterfly_3(io, expand_bits),
-        4 => fwd_butterfly_4(io, expand_bits),
-        5 => fwd_butterfly_5(io, expand_bits),
-        6 => fwd_butterfly_6(io, expand_bits),
-        7 => fwd_butterfly_7(io, expand_bits),
-        8 => fwd_butterfly_8(io, expand_bits),
-        9 => fwd_butterfly_9(io, expand_bits),
-        10 => fwd_butterfly_10(io, expand_bits),
-        11 => fwd_butterfly_11(io, expand_bits),
-        12 => fwd_butterfly_12(io, expand_bits),
-        13 => fwd_butterfly_13(io, expand_bits),
-        14 => fwd_butterfly_14(io, expand_bits),
-        15 => fwd_butterfly_15(io, expand_bits),
-        16 => fwd_butterfly_16(io, expand_bits),
-        17 => fwd_butterfly_17(io, expand_bits),
-        18 => fwd_butterfly_18(io, expand_bits),
-        19 => fwd_butterfly_19(io, expand_bits),
-        20 => fwd_butterfly_20(io, expand_bits),
-        21 => fwd_butterfly_21(io, expand_bits),
-        22 => fwd_butterfly_22(io, expand_bits),
-        23 => fwd_butterfly_23(io, expand_bits),
-        24 => fwd_butterfly_24(io, expand_bits),
-        25 => fwd_butterfly_25(io, expand_bits),
-        26 => fwd_butterfly_26(io, expand_bits),
-        27 => fwd_butterfly_27(io, expand_bits),
-        _ => unreachable!(),
+    fwd_butterfly(io, n, expand_bits);
+}
+
+/// Transforms of at least `1 << FOUR_STEP_PO2` elements outgrow the cache,
+/// and use the four-step NTT; see [four_step].
+const FOUR_STEP_PO2: usize = 20;
+
+// Whether a forward transform of `1 << n` elements uses the four-step NTT,
+// which needs the expanded levels to stay within a row.
+fn use_four_step(n: usize, expand_bits: usize) -> bool {
+    n >= FOUR_STEP_PO2 && expand_bits <= n / 2
+}
+
+/// Same as [interpolate_ntt], but processes [LANES] base field elements at a
+/// time with [ElemVec].  Buffers of `1 << 20` elements or more use the
+/// four-step NTT, which keeps its working set in cache, for the same result.
+pub fn interpolate_ntt_fp(io: &mut [Fp]) {
+    let size = io.len();
+    let n = log2_ceil(size);
+    assert_eq!(1 << n, size);
+    if use_four_step(n, 0) {
+        four_step::rev_butterfly(io, n, false);
+    } else {
+        rev_butterfly_vec(io, n);
     }
+    scale_vec(io, Fp::new(size as u32).inv());
+}
+
+/// Same as [evaluate_ntt], but processes [LANES] base field elements at a
+/// time with [ElemVec].  Large buffers use the four-step NTT, as for
+/// [interpolate_ntt_fp].
+pub fn evaluate_ntt_fp(io: &mut [Fp], expand_bits: usize) {
+    let size = io.len();
+    let n = log2_ceil(size);
+    assert_eq!(1 << n, size);
+    if use_four_step(n, expand_bits) {
+        four_step::fwd_butterfly(io, n, expand_bits, false);
+    } else {
+        fwd_butterfly_vec(io, n, expand_bits);
+    }
+}
+
+// Twiddles for lanes `i..i + LANES` start at `step^i` and advance by
+// `step^LANES` per chunk.
+fn fwd_butterfly_vec(io: &mut [Fp], n: usize, expand_bits: usize) {
+    if n == 0 || n == expand_bits {
+        return;
+    }
+    let half = 1 << (n - 1);
+    if half < LANES {
+        return fwd_butterfly(io, n, expand_bits);
+    }
+    let (lo, hi) = io.split_at_mut(half);
+    fwd_butterfly_vec(lo, n - 1, expand_bits);
+    fwd_butterfly_vec(hi, n - 1, expand_bits);
+    let step = Fp::root_of_unity(n);
+    let stride = ElemVec::splat(step.pow(LANES));
+    let mut cur = ElemVec::powers(step);
+    for (lo, hi) in lo.chunks_exact_mut(LANES).zip(hi.chunks_exact_mut(LANES)) {
+        let a = ElemVec::load(lo);
+        let b = ElemVec::load(hi) * cur;
+        (a + b).store(lo);
+        (a - b).store(hi);
+        cur *= stride;
+    }
+}
+
+// Multiplies every element of `io` by `norm`.
+fn scale_vec(io: &mut [Fp], norm: Fp) {
+    let norm_vec = ElemVec::splat(norm);
+    let mut chunks = io.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        (ElemVec::load(chunk) * norm_vec).store(chunk);
+    }
+    for x in chunks.into_remainder() {
+        *x *= norm;
+    }
+}
+
+fn rev_butterfly_vec(io: &mut [Fp], n: usize) {
+    if n == 0 {
+        return;
+    }
+    let half = 1 << (n - 1);
+    if half < LANES {
+        return rev_butterfly(io, n);
+    }
+    let (lo, hi) = io.split_at_mut(half);
+    let step = Fp::inv_root_of_unity(n);
+    let stride = ElemVec::splat(step.pow(LANES));
+    let mut cur = ElemVec::powers(step);
+    for (lo, hi) in lo.chunks_exact_mut(LANES).zip(hi.chunks_exact_mut(LANES)) {
+        let a = ElemVec::load(lo);
+        let b = ElemVec::load(hi);
+        (a + b).store(lo);
+        ((a - b) * cur).store(hi);
+        cur *= stride;
+    }
+    rev_butterfly_vec(lo, n - 1);
+    rev_butterfly_vec(hi, n - 1);
+}
+
+/// The number of elements of each half of a butterfly level handed to a
+/// thread at once.
+#[cfg(feature = "parallel")]
+const PAR_BLOCK: usize = 1 << 12;
+
+/// Same as [interpolate_ntt_fp], but splits the work across rayon's current
+/// thread pool for buffers of at least
+/// [ntt_split_po2](crate::parallel::ntt_split_po2) elements, as a power of
+/// two.  The result is identical.
+#[cfg(feature = "parallel")]
+pub fn par_interpolate_ntt_fp(io: &mut [Fp]) {
+    let size = io.len();
+    let n = log2_ceil(size);
+    assert_eq!(1 << n, size);
+    if use_four_step(n, 0) {
+        four_step::rev_butterfly(io, n, true);
+    } else {
+        rev_butterfly_par(io, n, crate::parallel::ntt_split_po2());
+    }
+    let norm = Fp::new(size as u32).inv();
+    io.par_chunks_mut(PAR_BLOCK)
+        .for_each(|block| scale_vec(block, norm));
+}
+
+/// Same as [evaluate_ntt_fp], but splits the work across rayon's current
+/// thread pool for buffers of at least
+/// [ntt_split_po2](crate::parallel::ntt_split_po2) elements, as a power of
+/// two.  The result is identical.
+#[cfg(feature = "parallel")]
+pub fn par_evaluate_ntt_fp(io: &mut [Fp], expand_bits: usize) {
+    let size = io.len();
+    let n = log2_ceil(size);
+    assert_eq!(1 << n, size);
+    if use_four_step(n, expand_bits) {
+        four_step::fwd_butterfly(io, n, expand_bits, true);
+    } else {
+        fwd_butterfly_par(io, n, expand_bits, crate::parallel::ntt_split_po2());
+    }
+}
+
+// The radix-2 recursion of [fwd_butterfly_vec], with the two halves
+// transformed in parallel and the combining level split into blocks of
+// [PAR_BLOCK] pairs, each starting its twiddles at `step^offset`.  Requires
+// `split >= 4`, so that each half above the split holds whole vectors.
+#[cfg(feature = "parallel")]
+fn fwd_butterfly_par(io: &mut [Fp], n: usize, expand_bits: usize, split: usize) {
+    if n < split {
+        return fwd_butterfly_vec(io, n, expand_bits);
+    }
+    if n == expand_bits {
+        return;
+    }
+    let half = 1 << (n - 1);
+    let (lo, hi) = io.split_at_mut(half);
+    rayon::join(
+        || fwd_butterfly_par(lo, n - 1, expand_bits, split),
+        || fwd_butterfly_par(hi, n - 1, expand_bits, split),
+    );
+    let step = Fp::root_of_unity(n);
+    let stride = ElemVec::splat(step.pow(LANES));
+    lo.par_chunks_mut(PAR_BLOCK)
+        .zip(hi.par_chunks_mut(PAR_BLOCK))
+        .enumerate()
+        .for_each(|(block, (lo, hi))| {
+            let mut cur = ElemVec::powers(step) * ElemVec::splat(step.pow(block * PAR_BLOCK));
+            for (lo, hi) in lo.chunks_exact_mut(LANES).zip(hi.chunks_exact_mut(LANES)) {
+                let a = ElemVec::load(lo);
+                let b = ElemVec::load(hi) * cur;
+                (a + b).store(lo);
+                (a - b).store(hi);
+                cur *= stride;
+            }
+        });
+}
+
+// The parallel counterpart of [rev_butterfly_vec]; see [fwd_butterfly_par].
+#[cfg(feature = "parallel")]
+fn rev_butterfly_par(io: &mut [Fp], n: usize, split: usize) {
+    if n < split {
+        return rev_butterfly_vec(io, n);
+    }
+    let half = 1 << (n - 1);
+    let (lo, hi) = io.split_at_mut(half);
+    let step = Fp::inv_root_of_unity(n);
+    let stride = ElemVec::splat(step.pow(LANES));
+    lo.par_chunks_mut(PAR_BLOCK)
+        .zip(hi.par_chunks_mut(PAR_BLOCK))
+        .enumerate()
+        .for_each(|(block, (lo, hi))| {
+            let mut cur = ElemVec::powers(step) * ElemVec::splat(step.pow(block * PAR_BLOCK));
+            for (lo, hi) in lo.chunks_exact_mut(LANES).zip(hi.chunks_exact_mut(LANES)) {
+                let a = ElemVec::load(lo);
+                let b = ElemVec::load(hi);
+                (a + b).store(lo);
+                ((a - b) * cur).store(hi);
+                cur *= stride;
+            }
+        });
+    rayon::join(
+        || rev_butterfly_par(lo, n - 1, split),
+        || rev_butterfly_par(hi, n - 1, split),
+    );
 }
 
 /// Expand the `input` into `output` to support polynomial evaluation on
@@ -297,13 +498,12 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::field::Elem;
+    use crate::field::{Elem, TwoAdicField};
     use rand::thread_rng;
 
     use crate::core::{
         fp::Fp,
-        ntt::{bit_reverse, evaluate_ntt, interpolate_ntt},
-        rou::ROU_FWD,
+        ntt::{bit_reverse, evaluate_ntt, evaluate_ntt_fp, interpolate_ntt, interpolate_ntt_fp},
     };
 
     // Compare the complex version to the naive version
@@ -327,7 +527,7 @@ mod tests {
                 xn *= x;
             }
             goal[i] = tot;
-            x *= Fp::new(ROU_FWD[N]);
+            x *= Fp::root_of_unity(N);
         }
         // Now compute multiEvaluate in place
         bit_reverse(&mut buf);
@@ -356,6 +556,67 @@ mod tests {
         assert_eq!(orig, buf);
     }
 
+    // The vectorized transforms must agree with the generic ones.
+    #[test]
+    fn cmp_vectorized() {
+        const N: usize = 10;
+        const SIZE: usize = 1 << N;
+        let mut rng = thread_rng();
+        let orig: Vec<Fp> = (0..SIZE).map(|_| Fp::random(&mut rng)).collect();
+        for expand_bits in [0, 2] {
+            let mut goal = orig.clone();
+            let mut buf = orig.clone();
+            evaluate_ntt(&mut goal, expand_bits);
+            evaluate_ntt_fp(&mut buf, expand_bits);
+            assert_eq!(goal, buf);
+        }
+        let mut goal = orig.clone();
+        let mut buf = orig.clone();
+        interpolate_ntt(&mut goal);
+        interpolate_ntt_fp(&mut buf);
+        assert_eq!(goal, buf);
+        // Sizes smaller than one vector take the scalar path.
+        let mut goal = orig[..4].to_vec();
+        let mut buf = goal.clone();
+        interpolate_ntt(&mut goal);
+        interpolate_ntt_fp(&mut buf);
+        assert_eq!(goal, buf);
+    }
+
+    // The multi-threaded transforms must agree with the vectorized ones, at
+    // every level of the recursion.
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn cmp_parallel() {
+        use crate::core::ntt::{fwd_butterfly_par, rev_butterfly_par, PAR_BLOCK};
+
+        let mut rng = thread_rng();
+        // Large enough that the top levels split into several blocks.
+        let n = PAR_BLOCK.trailing_zeros() as usize + 3;
+        let orig: Vec<Fp> = (0..1 << n).map(|_| Fp::random(&mut rng)).collect();
+        for split in [4, 9, n, n + 1] {
+            for expand_bits in [0, 2] {
+                let mut goal = orig.clone();
+                let mut buf = orig.clone();
+                evaluate_ntt_fp(&mut goal, expand_bits);
+                fwd_butterfly_par(&mut buf, n, expand_bits, split);
+                assert_eq!(goal, buf);
+            }
+            let mut goal = orig.clone();
+            let mut buf = orig.clone();
+            super::rev_butterfly_vec(&mut goal, n);
+            rev_butterfly_par(&mut buf, n, split);
+            assert_eq!(goal, buf);
+        }
+        let mut goal = orig.clone();
+        let mut buf = orig.clone();
+        interpolate_ntt_fp(&mut goal);
+        super::par_interpolate_ntt_fp(&mut buf);
+        assert_eq!(goal, buf);
+        super::par_evaluate_ntt_fp(&mut buf, 0);
+        assert_eq!(orig, buf);
+    }
+
     #[test]
     fn expand() {
         const N: usize = 6;
@@ -386,7 +647,7 @@ mod tests {
                 xn *= x;
             }
             goal[i] = tot;
-            x *= Fp::new(ROU_FWD[N]);
+            x *= Fp::root_of_unity(N);
         }
         assert_eq!(goal, buf);
     }
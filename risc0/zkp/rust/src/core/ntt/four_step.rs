@@ -0,0 +1,154 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The four-step NTT, for transforms too large to fit in cache.
+//!
+//! A transform of `N = R * C` elements, viewed as `R` rows of `C`, splits
+//! into the butterflies that stay within a row, which are already cache
+//! friendly, and those that reach across rows, which the plain recursion
+//! performs with strides of `C` or more.  Here the latter run instead as
+//! `C` contiguous transforms of `R` elements each, on a transposed copy,
+//! with the twiddles that join the two halves applied in between.  The
+//! output is identical to the plain recursion's.
+
+use alloc::vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{bit_rev_32, fwd_butterfly_vec, rev_butterfly_vec};
+use crate::{
+    core::fp::Fp,
+    field::{Elem, TwoAdicField},
+    util,
+};
+
+/// The smallest transform this handles, below which the column passes cost
+/// more than they save.
+pub(super) const MIN_PO2: usize = 8;
+
+// Splits `n` into row and column bits, `r + c == n`, with the larger half
+// within a row.
+fn split(n: usize) -> (usize, usize) {
+    let r = n / 2;
+    (r, n - r)
+}
+
+/// Same as `fwd_butterfly_vec(io, n, expand_bits)`, for `n >= MIN_PO2` and
+/// `expand_bits <= n / 2`.
+pub(super) fn fwd_butterfly(io: &mut [Fp], n: usize, expand_bits: usize, par: bool) {
+    assert!(n >= MIN_PO2);
+    let (r, c) = split(n);
+    assert!(expand_bits <= c);
+    let (rows, cols) = (1 << r, 1 << c);
+    for_each_chunk(io, cols, par, |_, row| {
+        fwd_butterfly_vec(row, c, expand_bits)
+    });
+    let mut scratch = vec![Fp::ZERO; io.len()];
+    transpose(&mut scratch, io, rows, cols, par);
+    let w = Fp::root_of_unity(n);
+    for_each_chunk(&mut scratch, rows, par, |i, col| {
+        // Element `b` of column `i` needs w^(i * b'), for b' the bit
+        // reversal of b as an r-bit number.
+        twiddle(col, w.pow(i), r);
+        fwd_butterfly_vec(col, r, 0);
+    });
+    transpose(io, &scratch, cols, rows, par);
+}
+
+/// Same as `rev_butterfly_vec(io, n)`, for `n >= MIN_PO2`.
+pub(super) fn rev_butterfly(io: &mut [Fp], n: usize, par: bool) {
+    assert!(n >= MIN_PO2);
+    let (r, c) = split(n);
+    let (rows, cols) = (1 << r, 1 << c);
+    let mut scratch = vec![Fp::ZERO; io.len()];
+    transpose(&mut scratch, io, rows, cols, par);
+    let w = Fp::inv_root_of_unity(n);
+    for_each_chunk(&mut scratch, rows, par, |i, col| {
+        rev_butterfly_vec(col, r);
+        twiddle(col, w.pow(i), r);
+    });
+    transpose(io, &scratch, cols, rows, par);
+    for_each_chunk(io, cols, par, |_, row| rev_butterfly_vec(row, c));
+}
+
+// Multiplies element `b` of `col` by `base^b'`, for b' the bit reversal of
+// `b` as a `bits`-bit number.
+fn twiddle(col: &mut [Fp], base: Fp, bits: usize) {
+    let mut cur = Fp::ONE;
+    for s in 0..col.len() {
+        col[(bit_rev_32(s as u32) >> (32 - bits)) as usize] *= cur;
+        cur *= base;
+    }
+}
+
+// Writes the transpose of `src`, a `rows` by `cols` row-major matrix, to
+// `dst`, across rayon's thread pool if `par` is set.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn transpose(dst: &mut [Fp], src: &[Fp], rows: usize, cols: usize, par: bool) {
+    #[cfg(feature = "parallel")]
+    if par {
+        return util::par_transpose(dst, src, rows, cols);
+    }
+    util::transpose(dst, src, rows, cols);
+}
+
+// Calls `f` with the index and contents of each `len`-element chunk of
+// `io`, across rayon's thread pool if `par` is set.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn for_each_chunk<F>(io: &mut [Fp], len: usize, par: bool, f: F)
+where
+    F: Fn(usize, &mut [Fp]) + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    if par {
+        return io
+            .par_chunks_exact_mut(len)
+            .enumerate()
+            .for_each(|(i, chunk)| f(i, chunk));
+    }
+    io.chunks_exact_mut(len)
+        .enumerate()
+        .for_each(|(i, chunk)| f(i, chunk));
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use super::{fwd_butterfly, rev_butterfly, MIN_PO2};
+    use crate::{core::fp::Fp, field::Elem};
+
+    #[test]
+    fn cmp_recursive() {
+        let mut rng = thread_rng();
+        for n in [MIN_PO2, MIN_PO2 + 1, 11] {
+            let orig: Vec<Fp> = (0..1 << n).map(|_| Fp::random(&mut rng)).collect();
+            for par in [false, true] {
+                for expand_bits in [0, 2] {
+                    let mut goal = orig.clone();
+                    let mut buf = orig.clone();
+                    super::super::fwd_butterfly_vec(&mut goal, n, expand_bits);
+                    fwd_butterfly(&mut buf, n, expand_bits, par);
+                    assert_eq!(goal, buf);
+                }
+                let mut goal = orig.clone();
+                let mut buf = orig.clone();
+                super::super::rev_butterfly_vec(&mut goal, n);
+                rev_butterfly(&mut buf, n, par);
+                assert_eq!(goal, buf);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! NTTs whose length isn't a power of two.
+//!
+//! BabyBear's multiplicative group has order `15 * 2^27`, so it has a
+//! subgroup of every order `N = m * 2^k` with `m` dividing 15, and vectors
+//! of such a length can be transformed without padding them to the next
+//! power of two.  The mixed-radix transform views the `N` elements as `m`
+//! columns of `2^k`, transforms each column with the power-of-two
+//! butterflies of [super], applies twiddles, and finishes with `2^k` direct
+//! transforms of length `m` across the columns.
+//!
+//! Unlike the power-of-two transforms, both directions here keep their
+//! input and output in natural order.
+//!
+//! The prover doesn't use these transforms: the circuit, the FRI folding
+//! and the `po2` recorded in a seal all assume a domain of `2^po2` rows, so
+//! traces are still padded to a power of two.
+
+use alloc::vec::Vec;
+
+use super::{bit_reverse, evaluate_ntt_fp, interpolate_ntt_fp};
+use crate::{
+    core::fp::Fp,
+    field::{Elem, TwoAdicField},
+};
+
+/// The odd part of the order of BabyBear's multiplicative group.
+const ODD_ORDER: usize = 15;
+
+/// A generator of BabyBear's multiplicative group.
+const GENERATOR: u32 = 31;
+
+/// Splits a transform length into `(m, k)` with `size == m << k`, if BabyBear
+/// has a subgroup of that order.
+pub fn mixed_radix(size: usize) -> Option<(usize, usize)> {
+    if size == 0 {
+        return None;
+    }
+    let k = size.trailing_zeros() as usize;
+    let m = size >> k;
+    (ODD_ORDER % m == 0 && k <= Fp::TWO_ADICITY).then_some((m, k))
+}
+
+/// A primitive `size`-th root of unity, for a `size` accepted by
+/// [mixed_radix].  Its `m`-th power is `Fp::root_of_unity(k)`, so for a
+/// power of two it's that root.
+pub fn mixed_root_of_unity(size: usize) -> Fp {
+    let (m, k) = mixed_radix(size).unwrap_or_else(|| panic!("No subgroup of order {size}"));
+    // An element of order m.
+    let odd = Fp::new(GENERATOR).pow_u64(((ODD_ORDER << Fp::TWO_ADICITY) / m) as u64);
+    // Raising the 2^k-th root to m^-1 mod 2^k keeps the m-th power of the
+    // product equal to that root.  Newton's iteration inverts odd numbers
+    // modulo 2^64.
+    let m = m as u64;
+    let mut inv = m;
+    for _ in 0..5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(m.wrapping_mul(inv)));
+    }
+    Fp::root_of_unity(k).pow_u64(inv & ((1 << k) - 1)) * odd
+}
+
+/// Evaluates the polynomial with coefficients `io` at the powers
+/// `w^0, w^1, ...`, where `w` is the [mixed_root_of_unity] for the length of
+/// `io`, in place.
+pub fn evaluate_ntt_mixed(io: &mut [Fp]) {
+    let root = mixed_root_of_unity(io.len());
+    transform(io, root, |col| {
+        bit_reverse(col);
+        evaluate_ntt_fp(col, 0);
+    });
+}
+
+/// The inverse of [evaluate_ntt_mixed]: recovers the coefficients of the
+/// polynomial with the evaluations `io`, in place.
+pub fn interpolate_ntt_mixed(io: &mut [Fp]) {
+    let root = mixed_root_of_unity(io.len()).inv();
+    // The column transforms already divide by the length of a column.
+    transform(io, root, |col| {
+        interpolate_ntt_fp(col);
+        bit_reverse(col);
+    });
+    let (m, _) = mixed_radix(io.len()).unwrap();
+    let norm = Fp::new(m as u32).inv();
+    for x in io.iter_mut() {
+        *x *= norm;
+    }
+}
+
+// Transforms `io` with the primitive root `root`, given `column`, which
+// transforms 2^k elements in natural order with `root^m`.
+fn transform(io: &mut [Fp], root: Fp, column: impl Fn(&mut [Fp])) {
+    let (m, k) = mixed_radix(io.len()).unwrap();
+    if m == 1 {
+        column(io);
+        return;
+    }
+    let rows = 1 << k;
+    // Element `i` lands in column `i % m`.
+    let mut cols: Vec<Vec<Fp>> = (0..m)
+        .map(|col| io.iter().skip(col).step_by(m).copied().collect())
+        .collect();
+    for col in cols.iter_mut() {
+        column(col);
+    }
+    let small_root = root.pow(rows);
+    let mut small_powers = [Fp::ZERO; ODD_ORDER];
+    let mut cur = Fp::ONE;
+    for power in small_powers[..m].iter_mut() {
+        *power = cur;
+        cur *= small_root;
+    }
+    let mut step = Fp::ONE;
+    let mut twiddled = [Fp::ZERO; ODD_ORDER];
+    for row in 0..rows {
+        // twiddled[col] = cols[col][row] * root^(col * row)
+        let mut twiddle = Fp::ONE;
+        for (twiddled, col) in twiddled.iter_mut().zip(&cols) {
+            *twiddled = col[row] * twiddle;
+            twiddle *= step;
+        }
+        for out in 0..m {
+            io[row + rows * out] = twiddled[..m]
+                .iter()
+                .enumerate()
+                .fold(Fp::ZERO, |tot, (col, x)| {
+                    tot + *x * small_powers[col * out % m]
+                });
+        }
+        step *= root;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use super::{evaluate_ntt_mixed, interpolate_ntt_mixed, mixed_radix, mixed_root_of_unity};
+    use crate::{
+        core::{
+            fp::Fp,
+            ntt::{bit_reverse, evaluate_ntt_fp},
+        },
+        field::{Elem, TwoAdicField},
+    };
+
+    #[test]
+    fn sizes() {
+        assert_eq!(mixed_radix(0), None);
+        assert_eq!(mixed_radix(1), Some((1, 0)));
+        assert_eq!(mixed_radix(3 << 5), Some((3, 5)));
+        assert_eq!(mixed_radix(15 << 27), Some((15, 27)));
+        assert_eq!(mixed_radix(7), None);
+        assert_eq!(mixed_radix(9), None);
+        assert_eq!(mixed_radix(1 << 28), None);
+    }
+
+    #[test]
+    fn roots() {
+        for size in [1, 2, 3, 5, 12, 15 << 4, 5 << 27] {
+            let (m, k) = mixed_radix(size).unwrap();
+            let root = mixed_root_of_unity(size);
+            assert_eq!(root.pow(size), Fp::ONE);
+            for q in [2, 3, 5] {
+                if size % q == 0 {
+                    assert_ne!(root.pow(size / q), Fp::ONE);
+                }
+            }
+            assert_eq!(root.pow(m), Fp::root_of_unity(k));
+        }
+    }
+
+    #[test]
+    fn cmp_naive() {
+        let mut rng = thread_rng();
+        for size in [3, 5, 6, 15, 20, 3 << 6, 15 << 5] {
+            let coeffs: Vec<Fp> = (0..size).map(|_| Fp::random(&mut rng)).collect();
+            let root = mixed_root_of_unity(size);
+            let goal: Vec<Fp> = (0..size)
+                .map(|i| {
+                    let x = root.pow(i);
+                    coeffs.iter().rev().fold(Fp::ZERO, |tot, c| tot * x + *c)
+                })
+                .collect();
+            let mut buf = coeffs.clone();
+            evaluate_ntt_mixed(&mut buf);
+            assert_eq!(buf, goal);
+            interpolate_ntt_mixed(&mut buf);
+            assert_eq!(buf, coeffs);
+        }
+    }
+
+    #[test]
+    fn power_of_two() {
+        let mut rng = thread_rng();
+        let coeffs: Vec<Fp> = (0..1 << 8).map(|_| Fp::random(&mut rng)).collect();
+        let mut goal = coeffs.clone();
+        bit_reverse(&mut goal);
+        evaluate_ntt_fp(&mut goal, 0);
+        let mut buf = coeffs;
+        evaluate_ntt_mixed(&mut buf);
+        assert_eq!(buf, goal);
+    }
+}
@@ -12,18 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Polynomial utilites (currently only evaluation).
+//! Polynomial utilites.
+//!
+//! The free functions work in place on slices of coefficients, as the
+//! prover's buffers hold them.  [Polynomial] owns its coefficients and
+//! supports the usual arithmetic, for code which builds polynomials up.
 
-use alloc::vec;
+use alloc::{vec, vec::Vec};
+use core::ops;
 
-use super::fp4::Fp4;
-use crate::field::Elem;
+use super::{
+    fp::Fp,
+    log2_ceil,
+    ntt::{bit_reverse, evaluate_ntt, interpolate_ntt},
+};
+use crate::field::{self, Elem, TwoAdicField};
 
 /// Evaluate a polynomial whose coeffients are in the extension field at a
 /// point.
-pub fn poly_eval(coeffs: &[Fp4], x: Fp4) -> Fp4 {
-    let mut mul = Fp4::ONE;
-    let mut tot = Fp4::ZERO;
+pub fn poly_eval<E: Elem>(coeffs: &[E], x: E) -> E {
+    let mut mul = E::ONE;
+    let mut tot = E::ZERO;
     for i in 0..coeffs.len() {
         tot += coeffs[i] * mul;
         mul *= x;
@@ -35,7 +44,7 @@ pub fn poly_eval(coeffs: &[Fp4], x: Fp4) -> Fp4 {
 ///
 /// Given the goal value f(x) at a set of evalation points x, compute
 /// coefficients.
-pub fn poly_interpolate(out: &mut [Fp4], x: &[Fp4], fx: &[Fp4], size: usize) {
+pub fn poly_interpolate<E: Elem>(out: &mut [E], x: &[E], fx: &[E], size: usize) {
     // Special case the very easy ones
     if size == 1 {
         out[0] = fx[0];
@@ -47,18 +56,18 @@ pub fn poly_interpolate(out: &mut [Fp4], x: &[Fp4], fx: &[Fp4], size: usize) {
         return;
     }
     // Compute ft = product of (x - x_i) for all i
-    let mut ft = vec![Fp4::ZERO; size + 1];
-    ft[0] = Fp4::ONE;
+    let mut ft = vec![E::ZERO; size + 1];
+    ft[0] = E::ONE;
     for i in 0..size {
         for j in (0..i + 1).rev() {
             let value = ft[j];
             ft[j + 1] += value;
-            ft[j] *= -x[i];
+            ft[j] *= E::ZERO - x[i];
         }
     }
     // Clear output
     for i in 0..size {
-        out[i] = Fp4::ZERO;
+        out[i] = E::ZERO;
     }
     for i in 0..size {
         // Compute fr = ft / (x - x_i)
@@ -79,8 +88,8 @@ pub fn poly_interpolate(out: &mut [Fp4], x: &[Fp4], fx: &[Fp4], size: usize) {
 ///
 /// Take the coefficients in P, and divide by (X - z) for some z, return the
 /// remainder.
-pub fn poly_divide(p: &mut [Fp4], z: Fp4) -> Fp4 {
-    let mut cur = Fp4::ZERO;
+pub fn poly_divide<E: Elem>(p: &mut [E], z: E) -> E {
+    let mut cur = E::ZERO;
     for i in (0..p.len()).rev() {
         let next = z * cur + p[i];
         p[i] = cur;
@@ -88,3 +97,431 @@ pub fn poly_divide(p: &mut [Fp4], z: Fp4) -> Fp4 {
     }
     cur
 }
+
+/// The points of a coset `shift * H` of the subgroup `H` of `2^po2`-th roots
+/// of unity, with the barycentric weights for evaluating a polynomial of
+/// degree less than `2^po2` from its values there; see [eval_barycentric].
+/// Computing these once serves any number of evaluations on the same
+/// domain.
+#[derive(Clone, Debug)]
+pub struct BarycentricDomain {
+    points: Vec<Fp>,
+    weights: Vec<Fp>,
+    shift_n: Fp,
+}
+
+impl BarycentricDomain {
+    /// The domain of `shift * w^i` for `i < 2^po2`, with `w` the
+    /// `2^po2`-th root of unity.  `shift` must be non-zero.
+    pub fn new(po2: usize, shift: Fp) -> Self {
+        assert!(shift != Fp::ZERO, "Coset shift must be non-zero");
+        let size = 1 << po2;
+        let w = Fp::root_of_unity(po2);
+        let mut points = Vec::with_capacity(size);
+        let mut cur = shift;
+        for _ in 0..size {
+            points.push(cur);
+            cur *= w;
+        }
+        // The vanishing polynomial is Z(X) = X^n - shift^n, with derivative
+        // n x_i^(n - 1) = n shift^n / x_i at each point, so the weights
+        // 1 / Z'(x_i) are x_i / (n shift^n).
+        let shift_n = shift.pow(size);
+        let scale = (Fp::new(size as u32) * shift_n).inv();
+        let weights = points.iter().map(|x| *x * scale).collect();
+        BarycentricDomain {
+            points,
+            weights,
+            shift_n,
+        }
+    }
+
+    /// The number of points.
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The points, in the order [eval_barycentric] expects their values.
+    pub fn points(&self) -> &[Fp] {
+        &self.points
+    }
+}
+
+/// Evaluate at `point` the polynomial of degree less than `domain.size()`
+/// which takes the value `evals[i]` at the `i`-th point of `domain`, without
+/// interpolating it first.  This takes linear time, with a single batched
+/// inversion, by the barycentric formula
+///
+///   `f(z) = Z(z) * sum_i evals[i] * weight_i / (z - x_i)`,
+///
+/// where `Z` is the domain's vanishing polynomial.
+pub fn eval_barycentric<E>(evals: &[E], domain: &BarycentricDomain, point: E) -> E
+where
+    E: Elem + ops::Mul<Fp, Output = E> + From<Fp>,
+{
+    assert_eq!(evals.len(), domain.size());
+    let diffs: Vec<E> = domain.points.iter().map(|x| point - E::from(*x)).collect();
+    // The formula divides by zero on the domain itself, where the value is
+    // already known.
+    if let Some(i) = diffs.iter().position(|d| *d == E::ZERO) {
+        return evals[i];
+    }
+    let inv_diffs = field::batch_inv(&diffs);
+    let mut tot = E::ZERO;
+    for ((y, weight), inv_diff) in evals.iter().zip(&domain.weights).zip(inv_diffs) {
+        tot += *y * inv_diff * *weight;
+    }
+    let vanishing = point.pow(domain.size()) - E::from(domain.shift_n);
+    tot * vanishing
+}
+
+/// Products with fewer coefficients than this are multiplied directly,
+/// rather than through the NTT.
+const NTT_MUL_MIN: usize = 64;
+
+/// A polynomial with coefficients in `E`, stored lowest degree first with
+/// no trailing zeros, so that equal polynomials compare equal.
+///
+/// Multiplication of large polynomials, and evaluation and interpolation
+/// over a domain of roots of unity, use the NTT, which needs coefficients
+/// in [Fp] or an extension of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial<E> {
+    coeffs: Vec<E>,
+}
+
+impl<E: Elem> Polynomial<E> {
+    /// The polynomial with the given coefficients, lowest degree first.
+    pub fn new(coeffs: Vec<E>) -> Self {
+        let mut poly = Polynomial { coeffs };
+        poly.trim();
+        poly
+    }
+
+    /// The zero polynomial.
+    pub fn zero() -> Self {
+        Polynomial { coeffs: Vec::new() }
+    }
+
+    /// The constant polynomial `c`.
+    pub fn constant(c: E) -> Self {
+        Self::new(vec![c])
+    }
+
+    /// The polynomial `X - z`, whose only root is `z`.
+    pub fn linear(z: E) -> Self {
+        Self::new(vec![E::ZERO - z, E::ONE])
+    }
+
+    /// The coefficients, lowest degree first, without trailing zeros.
+    pub fn coeffs(&self) -> &[E] {
+        &self.coeffs
+    }
+
+    /// The degree, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.len().checked_sub(1)
+    }
+
+    /// Whether this is the zero polynomial.
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// Evaluate at `x`, by Horner's rule.
+    pub fn eval(&self, x: E) -> E {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(E::ZERO, |acc, coeff| acc * x + *coeff)
+    }
+
+    /// Evaluate at each of `xs`.
+    pub fn eval_many(&self, xs: &[E]) -> Vec<E> {
+        xs.iter().map(|x| self.eval(*x)).collect()
+    }
+
+    /// The polynomial of least degree through the points `(xs[i], ys[i])`.
+    /// The `xs` must be distinct.  This takes time quadratic in the number
+    /// of points; for a domain of roots of unity, use
+    /// [Polynomial::interpolate_domain].
+    pub fn interpolate(xs: &[E], ys: &[E]) -> Self {
+        assert_eq!(xs.len(), ys.len());
+        if xs.is_empty() {
+            return Self::zero();
+        }
+        let mut coeffs = vec![E::ZERO; xs.len()];
+        poly_interpolate(&mut coeffs, xs, ys, xs.len());
+        Self::new(coeffs)
+    }
+
+    /// Divide by `X - z`, returning the quotient and the remainder, which is
+    /// the value at `z`.
+    pub fn div_linear(&self, z: E) -> (Self, E) {
+        let mut quotient = self.coeffs.clone();
+        let rem = poly_divide(&mut quotient, z);
+        // The old leading coefficient shifted down; drop the slot it left.
+        quotient.pop();
+        (Self::new(quotient), rem)
+    }
+
+    /// Divide by `divisor`, returning the quotient `q` and the remainder `r`
+    /// with `self == q * divisor + r` and `r` of lower degree than
+    /// `divisor`.  Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        let divisor_degree = divisor.degree().expect("Division by the zero polynomial");
+        if self.coeffs.len() <= divisor_degree {
+            return (Self::zero(), self.clone());
+        }
+        let lead_inv = divisor.coeffs[divisor_degree].inv();
+        let mut rem = self.coeffs.clone();
+        let mut quotient = vec![E::ZERO; rem.len() - divisor_degree];
+        for i in (0..quotient.len()).rev() {
+            let factor = rem[i + divisor_degree] * lead_inv;
+            quotient[i] = factor;
+            for (r, d) in rem[i..].iter_mut().zip(&divisor.coeffs) {
+                *r -= factor * *d;
+            }
+        }
+        rem.truncate(divisor_degree);
+        (Self::new(quotient), Self::new(rem))
+    }
+
+    fn trim(&mut self) {
+        while self.coeffs.last() == Some(&E::ZERO) {
+            self.coeffs.pop();
+        }
+    }
+
+    fn mul_direct(&self, rhs: &Self) -> Self {
+        let mut out = vec![E::ZERO; self.coeffs.len() + rhs.coeffs.len() - 1];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in rhs.coeffs.iter().enumerate() {
+                out[i + j] += *a * *b;
+            }
+        }
+        Self::new(out)
+    }
+}
+
+impl<E: Elem + ops::Mul<Fp, Output = E>> Polynomial<E> {
+    /// Evaluate at each power of the `2^po2`-th root of unity, in order,
+    /// with the NTT.
+    pub fn eval_domain(&self, po2: usize) -> Vec<E> {
+        let size = 1 << po2;
+        // Since the root has order 2^po2, X^size is 1 on the domain, and
+        // higher coefficients fold onto lower ones.
+        let mut io = vec![E::ZERO; size];
+        for (i, coeff) in self.coeffs.iter().enumerate() {
+            io[i % size] += *coeff;
+        }
+        bit_reverse(&mut io);
+        evaluate_ntt(&mut io, 0);
+        io
+    }
+
+    /// The polynomial of degree less than `evals.len()`, a power of two,
+    /// which takes the value `evals[i]` at the `i`-th power of the
+    /// `evals.len()`-th root of unity; the inverse of
+    /// [Polynomial::eval_domain].
+    pub fn interpolate_domain(evals: &[E]) -> Self {
+        let mut io = evals.to_vec();
+        interpolate_ntt(&mut io);
+        bit_reverse(&mut io);
+        Self::new(io)
+    }
+
+    fn mul_ntt(&self, rhs: &Self) -> Self {
+        let len = self.coeffs.len() + rhs.coeffs.len() - 1;
+        let po2 = log2_ceil(len);
+        let lhs = self.eval_domain(po2);
+        let rhs = rhs.eval_domain(po2);
+        let product: Vec<E> = lhs.into_iter().zip(rhs).map(|(a, b)| a * b).collect();
+        Self::interpolate_domain(&product)
+    }
+}
+
+impl<'a, E: Elem> ops::Add for &'a Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn add(self, rhs: Self) -> Polynomial<E> {
+        let (long, short) = if self.coeffs.len() >= rhs.coeffs.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        let mut coeffs = long.coeffs.clone();
+        for (a, b) in coeffs.iter_mut().zip(&short.coeffs) {
+            *a += *b;
+        }
+        Polynomial::new(coeffs)
+    }
+}
+
+impl<E: Elem> ops::Add for Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn add(self, rhs: Self) -> Polynomial<E> {
+        &self + &rhs
+    }
+}
+
+impl<'a, E: Elem> ops::Neg for &'a Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn neg(self) -> Polynomial<E> {
+        Polynomial {
+            coeffs: self.coeffs.iter().map(|x| E::ZERO - *x).collect(),
+        }
+    }
+}
+
+impl<E: Elem> ops::Neg for Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn neg(self) -> Polynomial<E> {
+        -&self
+    }
+}
+
+impl<'a, E: Elem> ops::Sub for &'a Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn sub(self, rhs: Self) -> Polynomial<E> {
+        self + &-rhs
+    }
+}
+
+impl<E: Elem> ops::Sub for Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn sub(self, rhs: Self) -> Polynomial<E> {
+        &self - &rhs
+    }
+}
+
+/// Multiplies directly for small products and through the NTT otherwise.
+impl<'a, E: Elem + ops::Mul<Fp, Output = E>> ops::Mul for &'a Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn mul(self, rhs: Self) -> Polynomial<E> {
+        if self.is_zero() || rhs.is_zero() {
+            Polynomial::zero()
+        } else if self.coeffs.len() + rhs.coeffs.len() - 1 < NTT_MUL_MIN {
+            self.mul_direct(rhs)
+        } else {
+            self.mul_ntt(rhs)
+        }
+    }
+}
+
+impl<E: Elem + ops::Mul<Fp, Output = E>> ops::Mul for Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn mul(self, rhs: Self) -> Polynomial<E> {
+        &self * &rhs
+    }
+}
+
+/// Multiplies every coefficient by a scalar.
+impl<'a, E: Elem> ops::Mul<E> for &'a Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn mul(self, rhs: E) -> Polynomial<E> {
+        Polynomial::new(self.coeffs.iter().map(|x| *x * rhs).collect())
+    }
+}
+
+impl<E: Elem> ops::Mul<E> for Polynomial<E> {
+    type Output = Polynomial<E>;
+    fn mul(self, rhs: E) -> Polynomial<E> {
+        &self * rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{eval_barycentric, BarycentricDomain, Polynomial};
+    use crate::{
+        core::{fp::Fp, fp4::Fp4},
+        field::{Elem, TwoAdicField},
+    };
+
+    fn random<E: Elem>(rng: &mut impl Rng, len: usize) -> Polynomial<E> {
+        Polynomial::new((0..len).map(|_| E::random(rng)).collect())
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let x = Fp4::random(&mut rng);
+        for (m, n) in [(0, 3), (3, 5), (40, 50), (100, 30)] {
+            let a: Polynomial<Fp4> = random(&mut rng, m);
+            let b: Polynomial<Fp4> = random(&mut rng, n);
+            assert_eq!((&a + &b).eval(x), a.eval(x) + b.eval(x));
+            assert_eq!((&a - &b).eval(x), a.eval(x) - b.eval(x));
+            assert_eq!((&a * &b).eval(x), a.eval(x) * b.eval(x));
+            assert_eq!(
+                (&a * Fp4::from_u32(3)).eval(x),
+                a.eval(x) * Fp4::from_u32(3)
+            );
+            assert_eq!(&a * &b, a.mul_direct(&b));
+            assert!((&a - &a).is_zero());
+        }
+        let one = Polynomial::constant(Fp::ONE);
+        assert_eq!(one.degree(), Some(0));
+        assert_eq!(Polynomial::<Fp>::zero().degree(), None);
+        assert_eq!(Polynomial::new(vec![Fp::ONE, Fp::ZERO]), one);
+    }
+
+    #[test]
+    fn division() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let a: Polynomial<Fp4> = random(&mut rng, 20);
+        for n in [1, 2, 7, 20, 25] {
+            let b: Polynomial<Fp4> = random(&mut rng, n);
+            let (q, r) = a.div_rem(&b);
+            assert!(r.degree() < b.degree());
+            assert_eq!(&(&q * &b) + &r, a);
+        }
+        let z = Fp4::random(&mut rng);
+        let (q, rem) = a.div_linear(z);
+        assert_eq!(rem, a.eval(z));
+        assert_eq!(
+            a.div_rem(&Polynomial::linear(z)),
+            (q, Polynomial::constant(rem))
+        );
+    }
+
+    #[test]
+    fn barycentric() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let a: Polynomial<Fp4> = random(&mut rng, 16);
+        let z = Fp4::random(&mut rng);
+        for shift in [Fp::ONE, Fp::new(3)] {
+            let domain = BarycentricDomain::new(4, shift);
+            let xs: Vec<Fp4> = domain.points().iter().map(|x| Fp4::from_fp(*x)).collect();
+            let evals = a.eval_many(&xs);
+            assert_eq!(eval_barycentric(&evals, &domain, z), a.eval(z));
+            assert_eq!(eval_barycentric(&evals, &domain, xs[5]), evals[5]);
+        }
+        // Base field values work the same way.
+        let b: Polynomial<Fp> = random(&mut rng, 8);
+        let domain = BarycentricDomain::new(3, Fp::new(3));
+        let evals = b.eval_many(domain.points());
+        let x = Fp::new(12345);
+        assert_eq!(eval_barycentric(&evals, &domain, x), b.eval(x));
+    }
+
+    #[test]
+    fn interpolation() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        let a: Polynomial<Fp> = random(&mut rng, 16);
+        let xs: Vec<Fp> = (0..16).map(|_| Fp::random(&mut rng)).collect();
+        assert_eq!(Polynomial::interpolate(&xs, &a.eval_many(&xs)), a);
+
+        let evals = a.eval_domain(5);
+        let w = Fp::root_of_unity(5);
+        for (i, y) in evals.iter().enumerate() {
+            assert_eq!(*y, a.eval(w.pow(i)));
+        }
+        assert_eq!(Polynomial::interpolate_domain(&evals), a);
+        // Evaluating on a domain smaller than the degree folds the
+        // coefficients, which interpolation can't undo.
+        let small = a.eval_domain(2);
+        assert_eq!(small[1], a.eval(Fp::root_of_unity(2)));
+    }
+}
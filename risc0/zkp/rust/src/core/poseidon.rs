@@ -0,0 +1,421 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A CPU implementation of the Poseidon hash over BabyBear.
+//!
+//! Poseidon is an algebraic hash: its permutation is built from field
+//! additions, multiplications and powers, so a circuit checks a hash with a
+//! few hundred constraints where SHA-256 takes tens of thousands.  Seals
+//! which are to be verified within another proof commit with it, as a
+//! [HashSuite](super::hash::HashSuite).
+//!
+//! The permutation acts on [CELLS] elements, with full S-box rounds on
+//! either side of partial rounds which raise only the first cell.  The
+//! round counts are the smallest that meet the round number constraints of
+//! the Poseidon paper for 128-bit security with `x^7` as the S-box, plus the
+//! paper's security margin.  The round constants come from the paper's
+//! Grain LFSR, and the MDS matrix is the Cauchy matrix `1 / (i + j + CELLS)`.
+//!
+//! Digests hold [DIGEST_WORDS] canonical field elements, one per word.  The
+//! sponge absorbs [RATE] elements at a time, having started with the input
+//! length and whether the input was bytes or field elements in its
+//! capacity.  Bytes are absorbed as little-endian 16-bit halves, so every
+//! absorbed value is below the modulus.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use super::{
+    fp::Fp,
+    fp4::Fp4,
+    sha::{Digest, Sha, DIGEST_WORDS},
+};
+use crate::field::Elem;
+
+/// The number of field elements the permutation acts on.
+pub const CELLS: usize = 16;
+
+/// The number of field elements absorbed per permutation.
+pub const RATE: usize = DIGEST_WORDS;
+
+/// The number of rounds which raise every cell to the S-box power, half
+/// before the partial rounds and half after.
+pub const ROUNDS_FULL: usize = 8;
+
+/// The number of rounds which raise only the first cell.
+pub const ROUNDS_PARTIAL: usize = 13;
+
+const ROUNDS: usize = ROUNDS_FULL + ROUNDS_PARTIAL;
+
+// The capacity cells the sponge starts with the input length and domain in.
+const LEN_CELL: usize = RATE;
+const DOMAIN_CELL: usize = RATE + 1;
+
+// Inputs of field elements and of bytes absorb the same values differently,
+// so are kept apart by the domain.
+const DOMAIN_FPS: u32 = 0;
+const DOMAIN_BYTES: u32 = 1;
+
+const fn to_fps<const N: usize>(raw: [u32; N]) -> [Fp; N] {
+    let mut out = [Fp::new(0); N];
+    let mut i = 0;
+    while i < N {
+        out[i] = Fp::new(raw[i]);
+        i += 1;
+    }
+    out
+}
+
+// The round constants, CELLS per round, as drawn from the Grain LFSR seeded
+// with the field, S-box, width and round counts.
+pub(crate) const ROUND_CONSTANTS: [Fp; ROUNDS * CELLS] = to_fps([
+    0x69cbb6af, 0x46ad93f9, 0x60a00f4e, 0x6b1297cd, 0x23189afe, 0x732e7bef, 0x72c246de, 0x2c941900,
+    0x0557eede, 0x1580496f, 0x3a3ea77b, 0x54f3f271, 0x0f49b029, 0x47872fe1, 0x221e2e36, 0x1ab7202e,
+    0x487779a6, 0x3851c9d8, 0x38dc17c0, 0x209f8849, 0x268dcee8, 0x350c48da, 0x5b9ad32e, 0x0523272b,
+    0x3f89055b, 0x01e894b2, 0x13ddedde, 0x1b2ef334, 0x7507d8b4, 0x6ceeb94e, 0x52eb6ba2, 0x50642905,
+    0x05453f3f, 0x06349efc, 0x6922787c, 0x04bfff9c, 0x768c714a, 0x3e9ff21a, 0x15737c9c, 0x2229c807,
+    0x0d47f88c, 0x097e0ecc, 0x27eadba0, 0x2d7d29e4, 0x3502aaa0, 0x0f475fd7, 0x29fbda49, 0x018afffd,
+    0x0315b618, 0x6d4497d1, 0x1b171d9e, 0x52861abd, 0x2e5d0501, 0x3ec8646c, 0x6e5f250a, 0x148ae8e6,
+    0x17f5fa4a, 0x3e66d284, 0x0051aa3b, 0x483f7913, 0x2cfe5f15, 0x023427ca, 0x2cc78315, 0x1e36ea47,
+    0x5a8053c0, 0x693be639, 0x3858867d, 0x19334f6b, 0x128f0fd8, 0x4e2b1ccb, 0x61210ce0, 0x3c318939,
+    0x0b5b2f22, 0x2edb11d5, 0x213effdf, 0x0cac4606, 0x241af16d, 0x7290a80d, 0x6f7e5329, 0x598ec8a8,
+    0x76a859a0, 0x6559e868, 0x657b83af, 0x13271d3f, 0x1f876063, 0x0aeeae37, 0x706e9ca6, 0x46400cee,
+    0x72a05c26, 0x2c589c9e, 0x20bd37a7, 0x6a2d3d10, 0x20523767, 0x5b8fe9c4, 0x2aa501d6, 0x1e01ac3e,
+    0x1448bc54, 0x5ce5ad1c, 0x4918a14d, 0x2c46a83f, 0x4fcf6876, 0x61d8d5c8, 0x6ddf4ff9, 0x11fda4d3,
+    0x02933a8f, 0x170eaf81, 0x5a9c314f, 0x49a12590, 0x35ec52a1, 0x58eb1611, 0x5e481e65, 0x367125c9,
+    0x0eba33ba, 0x1fc28ded, 0x066399ad, 0x0cbec0ea, 0x75fd1af0, 0x50f5bf4e, 0x643d5f41, 0x6f4fe718,
+    0x5b3cbbde, 0x1e3afb3e, 0x296fb027, 0x45e1547b, 0x4a8db2ab, 0x59986d19, 0x30bcdfa3, 0x1db63932,
+    0x1d7c2824, 0x53b33681, 0x0673b747, 0x038a98a3, 0x2c5bce60, 0x351979cd, 0x5008fb73, 0x547bca78,
+    0x711af481, 0x3f93bf64, 0x644d987b, 0x3c8bcd87, 0x608758b8, 0x0be9313a, 0x21592938, 0x6d39ac05,
+    0x1cb929e6, 0x7504e146, 0x05bb5b26, 0x1a182621, 0x0129acb7, 0x0f925fd3, 0x615bdcf0, 0x4d0686a9,
+    0x0fd6440c, 0x2a6e7d07, 0x140ea354, 0x06e754ca, 0x0a30ed7d, 0x11d5b9f5, 0x5fd8cb87, 0x34e5a9cd,
+    0x16dd2e49, 0x4494e08b, 0x23d88c52, 0x3a093d8a, 0x402ac944, 0x46444226, 0x6dc1efa3, 0x2f629461,
+    0x77e0f6c6, 0x21920470, 0x2385c5d2, 0x480374c5, 0x5f570f5f, 0x318e2268, 0x0fc2fdbc, 0x553c94ea,
+    0x0d8eacbc, 0x43bab284, 0x4fa93dec, 0x4fe114db, 0x656d0ab3, 0x0775f045, 0x5ea6c684, 0x04f8447c,
+    0x68fc16ff, 0x3e0dfdf0, 0x2f8a1d04, 0x61f3951c, 0x2f92cb02, 0x204e8cdb, 0x687c9fdf, 0x3010d096,
+    0x27c99e66, 0x20984799, 0x59346f8e, 0x76b19839, 0x4e897114, 0x32ed764f, 0x097401a4, 0x03b4e414,
+    0x0ef721be, 0x148d5133, 0x186ff0f3, 0x4683accf, 0x34f4431d, 0x3c930fd8, 0x50ede262, 0x38446cdc,
+    0x4b1392b6, 0x280ab5db, 0x09e84fab, 0x4bdc713b, 0x083fa00e, 0x5788a978, 0x685349d4, 0x1dac7359,
+    0x5b9eac2c, 0x72e0224a, 0x72fc6025, 0x0a614c0b, 0x51e74ff3, 0x391598b9, 0x2c54352a, 0x506a28f0,
+    0x02d04b6d, 0x5a437f70, 0x54d9d369, 0x5c1f0776, 0x60f55ec1, 0x180ef798, 0x56be67e1, 0x681bc8fc,
+    0x65ae63c6, 0x240ab256, 0x3e62aaea, 0x46224af5, 0x4eaee2f0, 0x0c09758a, 0x12a973d6, 0x100e9fee,
+    0x1d7cd264, 0x3d7e3228, 0x259a060e, 0x074a8d27, 0x49620613, 0x52eaea48, 0x63ba465a, 0x008c4f74,
+    0x61c634cb, 0x2d98b6e6, 0x253bc6a5, 0x3c848dad, 0x540949ea, 0x4f862ddf, 0x5fa93a3d, 0x5c659ba8,
+    0x0f8b2954, 0x5ac41088, 0x770631d1, 0x35f6230e, 0x054e9cf7, 0x74eec058, 0x4c83003e, 0x570ddeba,
+    0x6c5e594a, 0x34cf7599, 0x0eb63eb2, 0x713e2dda, 0x6e59941c, 0x19707b6b, 0x129437ef, 0x57c4db39,
+    0x366cb7ec, 0x0e6335de, 0x5e1374ca, 0x493cf6d2, 0x2ffe3703, 0x19dd3b51, 0x3d64878f, 0x3ef43ee8,
+    0x64723e7c, 0x4fe5418a, 0x0f7b671d, 0x3f3adb8c, 0x1830fd89, 0x5b15366e, 0x3ca9204d, 0x149cee3c,
+    0x547bb959, 0x4d6a44a0, 0x771612ca, 0x3f5bdd26, 0x23a3d984, 0x170b07bd, 0x5a2a5094, 0x6e7e68b4,
+    0x1f3c8320, 0x0ffbb8b6, 0x5ebe7442, 0x45ffc700, 0x64d1f7b6, 0x1b30b661, 0x586ea500, 0x503111fd,
+    0x72b41cf7, 0x6468ad65, 0x64c713b1, 0x450b1ccd, 0x211e6028, 0x300b11ac, 0x74226654, 0x56308a44,
+    0x5aa55b4a, 0x52f2bc9a, 0x1a076e50, 0x5eb92894, 0x13baaf6f, 0x4d19b625, 0x30d25297, 0x52f00c13,
+    0x2a6753d7, 0x40bdd8de, 0x22acbb98, 0x77e41654, 0x23ab6b0f, 0x0629e7d6, 0x000eadff, 0x64cc8e81,
+    0x364fc012, 0x43cc48cd, 0x611baf29, 0x48bdf828, 0x1a8ab06f, 0x112ee5e0, 0x036e01dc, 0x18106634,
+]);
+
+// `1 / (k + CELLS)`, so that row `i` and column `j` of the MDS matrix is
+// `CAUCHY[i + j]`.
+pub(crate) const CAUCHY: [Fp; 2 * CELLS - 1] = to_fps([
+    0x70800001, 0x38787879, 0x49555556, 0x5ebca1b0, 0x72000001, 0x6124924a, 0x105d1746, 0x3e9bd37b,
+    0x73000001, 0x5b333334, 0x0dd89d8a, 0x08e38e39, 0x0cdb6db7, 0x14b08d3e, 0x74000001, 0x03def7be,
+    0x74400001, 0x32e8ba2f, 0x583c3c3d, 0x52492493, 0x24aaaaab, 0x2a298376, 0x2f5e50d8, 0x593b13b2,
+    0x75000001, 0x147ce0c8, 0x30924925, 0x1be82fa1, 0x082e8ba3, 0x4d555556, 0x5b4de9be,
+]);
+
+#[inline]
+fn sbox(x: Fp) -> Fp {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    x * x2 * x4
+}
+
+fn mix_cells(cells: &mut [Fp; CELLS]) {
+    let old = *cells;
+    for (i, cell) in cells.iter_mut().enumerate() {
+        *cell = old
+            .iter()
+            .zip(&CAUCHY[i..i + CELLS])
+            .fold(Fp::ZERO, |sum, (x, m)| sum + *x * *m);
+    }
+}
+
+/// The Poseidon permutation.
+pub fn poseidon_permute(cells: &mut [Fp; CELLS]) {
+    for (round, consts) in ROUND_CONSTANTS.chunks_exact(CELLS).enumerate() {
+        for (cell, c) in cells.iter_mut().zip(consts) {
+            *cell += *c;
+        }
+        let partial = (ROUNDS_FULL / 2..ROUNDS_FULL / 2 + ROUNDS_PARTIAL).contains(&round);
+        if partial {
+            cells[0] = sbox(cells[0]);
+        } else {
+            cells.iter_mut().for_each(|cell| *cell = sbox(*cell));
+        }
+        mix_cells(cells);
+    }
+}
+
+fn to_digest(cells: &[Fp]) -> Box<Digest> {
+    let mut words = [0; DIGEST_WORDS];
+    for (word, cell) in words.iter_mut().zip(cells) {
+        *word = u32::from(*cell);
+    }
+    Box::new(Digest::new(words))
+}
+
+fn sponge(domain: u32, len: usize, elems: impl Iterator<Item = Fp>) -> Box<Digest> {
+    let mut cells = [Fp::ZERO; CELLS];
+    cells[LEN_CELL] = Fp::new(len as u32);
+    cells[DOMAIN_CELL] = Fp::new(domain);
+    let mut elems = elems.peekable();
+    loop {
+        // The last block is padded with zeros, which the length tells apart.
+        for (cell, elem) in cells[..RATE].iter_mut().zip(&mut elems) {
+            *cell += elem;
+        }
+        poseidon_permute(&mut cells);
+        if elems.peek().is_none() {
+            break;
+        }
+    }
+    to_digest(&cells[..DIGEST_WORDS])
+}
+
+fn halves(word: u32) -> [Fp; 2] {
+    [Fp::new(word & 0xffff), Fp::new(word >> 16)]
+}
+
+/// A CPU-based Poseidon implementation of [Sha].
+#[derive(Clone)]
+pub struct Impl {}
+
+impl Sha for Impl {
+    type DigestPtr = Box<Digest>;
+
+    const SUITE_ID: u32 = 2;
+
+    fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
+        let elems = bytes.chunks(2).map(|chunk| match chunk {
+            [lo, hi] => Fp::new(u16::from_le_bytes([*lo, *hi]) as u32),
+            _ => Fp::new(chunk[0] as u32),
+        });
+        sponge(DOMAIN_BYTES, bytes.len(), elems)
+    }
+
+    // The same as hashing the little-endian bytes of the words.
+    fn hash_words(&self, words: &[u32]) -> Self::DigestPtr {
+        let elems = words.iter().flat_map(|word| halves(*word));
+        sponge(DOMAIN_BYTES, 4 * words.len(), elems)
+    }
+
+    // Poseidon has no separate compression function, so raw words are hashed
+    // exactly like any other words.
+    fn hash_raw_words(&self, words: &[u32]) -> Self::DigestPtr {
+        self.hash_words(words)
+    }
+
+    fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
+        sponge(DOMAIN_FPS, fps.len(), fps.iter().copied())
+    }
+
+    fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
+        let flat: Vec<Fp> = fp4s.iter().flat_map(|fp4| fp4.elems()).copied().collect();
+        self.hash_fps(&flat)
+    }
+
+    // Compresses the pair with a single permutation, keeping the first half.
+    fn hash_pair(&self, a: &Digest, b: &Digest) -> Self::DigestPtr {
+        let mut cells = [Fp::ZERO; CELLS];
+        for (cell, word) in cells.iter_mut().zip(a.get().iter().chain(b.get())) {
+            *cell = Fp::new(*word);
+        }
+        poseidon_permute(&mut cells);
+        to_digest(&cells[..DIGEST_WORDS])
+    }
+
+    // Adds rather than XORs, so the pool stays a vector of field elements.
+    fn mix(&self, pool: &mut Self::DigestPtr, val: &Digest) {
+        for (pool_word, val_word) in pool.get_mut().iter_mut().zip(val.get()) {
+            *pool_word = u32::from(Fp::new(*pool_word) + Fp::new(*val_word));
+        }
+    }
+}
+
+impl core::fmt::Debug for Impl {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::write!(f, "CPU Poseidon implementation")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        poseidon_permute, Impl, CAUCHY, CELLS, ROUNDS_FULL, ROUNDS_PARTIAL, ROUND_CONSTANTS,
+    };
+    use crate::{
+        core::{
+            fp::Fp,
+            fp4::Fp4,
+            sha::{Digest, Sha},
+        },
+        field::Elem,
+    };
+
+    // The Grain LFSR of the Poseidon reference implementation, which draws
+    // the round constants.
+    struct Grain(u128);
+
+    impl Grain {
+        fn new() -> Self {
+            // Field type, S-box type, field bits, width and round counts,
+            // then 30 ones.
+            let mut init = 1u128;
+            for (value, bits) in [
+                (0, 4),
+                (31, 12),
+                (CELLS as u128, 12),
+                (ROUNDS_FULL as u128, 10),
+                (ROUNDS_PARTIAL as u128, 10),
+                ((1 << 30) - 1, 30),
+            ] {
+                init = init << bits | value;
+            }
+            let mut grain = Grain(init);
+            for _ in 0..160 {
+                grain.clock();
+            }
+            grain
+        }
+
+        fn clock(&mut self) -> u128 {
+            let bit = |i: u32| self.0 >> (79 - i) & 1;
+            let next = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+            self.0 = (self.0 << 1 | next) & ((1 << 80) - 1);
+            next
+        }
+
+        fn next_bit(&mut self) -> u128 {
+            loop {
+                let keep = self.clock();
+                let bit = self.clock();
+                if keep == 1 {
+                    return bit;
+                }
+            }
+        }
+
+        fn next_fp(&mut self) -> u32 {
+            loop {
+                let value = (0..31).fold(0, |value, _| value << 1 | self.next_bit()) as u32;
+                if let Some(fp) = Fp::from_u32_checked(value) {
+                    return u32::from(fp);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn constants() {
+        let mut grain = Grain::new();
+        for c in ROUND_CONSTANTS {
+            assert_eq!(u32::from(c), grain.next_fp());
+        }
+        for (k, m) in CAUCHY.iter().enumerate() {
+            assert_eq!(*m * Fp::new((k + CELLS) as u32), Fp::ONE);
+        }
+    }
+
+    fn to_words(digest: &Digest) -> [u32; 8] {
+        *digest.get()
+    }
+
+    #[test]
+    fn vectors() {
+        let mut cells = [Fp::ZERO; CELLS];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = Fp::new(i as u32);
+        }
+        poseidon_permute(&mut cells);
+        assert_eq!(
+            cells[..8]
+                .iter()
+                .map(|cell| u32::from(*cell))
+                .collect::<Vec<_>>(),
+            [
+                0x1a2225ab, 0x43c736d5, 0x166f9ff3, 0x5dd5b27d, 0x17b8c9de, 0x4a2efe38, 0x161a1044,
+                0x31f36fcf
+            ]
+        );
+
+        let sha = Impl {};
+        let fps: Vec<Fp> = (1..10).map(Fp::new).collect();
+        assert_eq!(
+            to_words(&sha.hash_fps(&fps)),
+            [
+                0x5e956aad, 0x52ccce3a, 0x163c7ffe, 0x33aa1d09, 0x758359aa, 0x6ce56776, 0x59f47b0f,
+                0x4af407d3
+            ]
+        );
+        assert_eq!(
+            to_words(&sha.hash_fps(&[])),
+            [
+                0x2cf81282, 0x377bc8e8, 0x55443dc4, 0x1ed2a29b, 0x090c7b9b, 0x255c4663, 0x58297d0a,
+                0x08fc3246
+            ]
+        );
+        assert_eq!(
+            to_words(&sha.hash_bytes(b"abc")),
+            [
+                0x6b8ac022, 0x69d6ffdd, 0x3c994c49, 0x5ad0dac2, 0x14a54207, 0x52a91982, 0x65949b7d,
+                0x06d4bea0
+            ]
+        );
+    }
+
+    #[test]
+    fn encodings() {
+        let sha = Impl {};
+        let words = [0x01020304, 0xfffefdfc, 0];
+        let bytes: Vec<u8> = words
+            .iter()
+            .flat_map(|word: &u32| word.to_le_bytes())
+            .collect();
+        assert_eq!(*sha.hash_words(&words), *sha.hash_bytes(&bytes));
+        assert_eq!(*sha.hash_raw_words(&words), *sha.hash_words(&words));
+        // The lengths and domains keep apart inputs which pad alike.
+        assert_ne!(*sha.hash_bytes(&[0]), *sha.hash_bytes(&[]));
+        assert_ne!(*sha.hash_bytes(&[1, 0]), *sha.hash_fps(&[Fp::ONE]));
+        assert_ne!(*sha.hash_fps(&[Fp::ZERO]), *sha.hash_fps(&[]));
+
+        let fp4s = [Fp4::from_u32(3), Fp4::from_u32(4)];
+        let flat: Vec<Fp> = fp4s.iter().flat_map(|fp4| fp4.elems()).copied().collect();
+        assert_eq!(*sha.hash_fp4s(&fp4s), *sha.hash_fps(&flat));
+    }
+
+    #[test]
+    fn canonical() {
+        // Digests and the mixed pool stay field elements, so a circuit can
+        // take them as they are.
+        let sha = Impl {};
+        let a = sha.hash_bytes(b"a");
+        let b = sha.hash_bytes(b"b");
+        let mut pool = sha.hash_pair(&a, &b);
+        assert_ne!(*pool, *sha.hash_pair(&b, &a));
+        sha.mix(&mut pool, &Digest::new([u32::MAX; 8]));
+        for digest in [&a, &b, &pool] {
+            for word in digest.get() {
+                assert!(Fp::from_u32_checked(*word).is_some());
+            }
+        }
+    }
+}
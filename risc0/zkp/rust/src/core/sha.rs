@@ -35,16 +35,25 @@ pub const DIGEST_WORDS: usize = 8;
 /// The size of a word within a [Digest] (32-bits = 4 bytes).
 pub const DIGEST_WORD_SIZE: usize = mem::size_of::<u32>();
 
+/// The number of bytes represented by a [Digest].
+pub const DIGEST_BYTES: usize = DIGEST_WORDS * DIGEST_WORD_SIZE;
+
 /// The result of a SHA-256 hashing function.
+///
+/// A digest is held as [DIGEST_WORDS] words, the form the guest and the
+/// circuit use.  Its bytes, hex and base64 encodings follow the usual
+/// SHA-256 byte order, with each word big-endian, so they match the output
+/// of other SHA-256 implementations.  Digests compare in constant time, since
+/// they are often checked against values an attacker can choose.
 // TODO(nils): Remove 'Copy' trait on Digest; these are not small and
 // we don't want to copy them around accidentally.
-#[derive(Eq, PartialEq, Copy, Zeroable, Pod, Serialize, Deserialize)]
+#[derive(Eq, Copy, Zeroable, Pod, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Digest([u32; DIGEST_WORDS]);
 
 impl Digest {
     /// Create a new [Digest] from an existing array of words.
-    pub fn new(data: [u32; DIGEST_WORDS]) -> Digest {
+    pub const fn new(data: [u32; DIGEST_WORDS]) -> Digest {
         Digest(data)
     }
 
@@ -73,7 +82,7 @@ impl Digest {
     }
 
     /// Returns a slice of words.
-    pub fn get(&self) -> &[u32; DIGEST_WORDS] {
+    pub const fn get(&self) -> &[u32; DIGEST_WORDS] {
         &self.0
     }
 
@@ -82,31 +91,154 @@ impl Digest {
         &mut self.0
     }
 
+    /// Create a [Digest] from its bytes, in SHA-256 byte order.
+    pub fn from_bytes(bytes: [u8; DIGEST_BYTES]) -> Self {
+        let mut words = [0; DIGEST_WORDS];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(DIGEST_WORD_SIZE)) {
+            *word = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Digest(words)
+    }
+
+    /// Returns the bytes of this [Digest], in SHA-256 byte order.
+    pub fn to_bytes(&self) -> [u8; DIGEST_BYTES] {
+        let mut bytes = [0; DIGEST_BYTES];
+        for (chunk, word) in bytes.chunks_exact_mut(DIGEST_WORD_SIZE).zip(self.0) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        bytes
+    }
+
     /// Returns a hexadecimal string representation of the [Digest].
     pub fn to_hex(&self) -> String {
         fn hex(digit: u8) -> char {
             char::from_digit(digit as u32, 16).unwrap()
         }
-        self.0
+        self.to_bytes()
             .iter()
-            .flat_map(|word| word.to_be_bytes())
             .flat_map(|byte| [hex(byte >> 4), hex(byte & 0xF)])
             .collect()
     }
 
+    /// Parses a [Digest] from [DIGEST_BYTES] * 2 hexadecimal digits, in
+    /// either case.
+    pub fn from_hex(s: &str) -> Result<Self> {
+        if s.len() != 2 * DIGEST_BYTES || !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::msg("Digest must be 64 hexadecimal digits"));
+        }
+        let mut bytes = [0; DIGEST_BYTES];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+                .map_err(|_| Error::msg("Digest must be 64 hexadecimal digits"))?;
+        }
+        Ok(Self::from_bytes(bytes))
+    }
+
     /// Converts a hexadecimal string into a [Digest].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not a valid digest; see [Digest::from_hex].
     pub fn from_str(s: &str) -> Digest {
         s.into()
     }
+
+    /// Returns the standard, padded base64 encoding of this [Digest]'s bytes.
+    pub fn to_base64(&self) -> String {
+        let bytes = self.to_bytes();
+        let mut out = String::with_capacity(4 * ((DIGEST_BYTES + 2) / 3));
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(BASE64[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    /// Parses a [Digest] from the standard, padded base64 encoding of its
+    /// bytes, as produced by [Digest::to_base64].
+    pub fn from_base64(s: &str) -> Result<Self> {
+        let err = || Error::msg("Digest must be 44 characters of padded base64");
+        let s = s.as_bytes();
+        if s.len() != 4 * ((DIGEST_BYTES + 2) / 3) {
+            return Err(err());
+        }
+        let mut bytes = Vec::with_capacity(DIGEST_BYTES + 2);
+        for chunk in s.chunks(4) {
+            let mut n = 0u32;
+            let mut len = 0;
+            for (i, &c) in chunk.iter().enumerate() {
+                if c == b'=' {
+                    continue;
+                }
+                // Padding may only end a chunk.
+                if chunk[..i].contains(&b'=') {
+                    return Err(err());
+                }
+                let digit = BASE64.iter().position(|&d| d == c).ok_or_else(err)?;
+                n |= (digit as u32) << (18 - 6 * i);
+                len += 1;
+            }
+            if len < 2 {
+                return Err(err());
+            }
+            bytes.extend_from_slice(&n.to_be_bytes()[1..len]);
+        }
+        let bytes: [u8; DIGEST_BYTES] = bytes.try_into().map_err(|_| err())?;
+        let digest = Self::from_bytes(bytes);
+        // Reject encodings with stray bits in the final character, so each
+        // digest has just one encoding.
+        if digest.to_base64().as_bytes() != s {
+            return Err(err());
+        }
+        Ok(digest)
+    }
 }
 
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
 impl From<&str> for Digest {
     fn from(s: &str) -> Digest {
-        let words: Vec<u32> = (0..DIGEST_WORDS)
-            .into_iter()
-            .map(|x| u32::from_str_radix(&s[x * 8..(x + 1) * 8], 16).unwrap())
-            .collect();
-        Digest::new(words.try_into().unwrap())
+        Digest::from_hex(s).unwrap()
+    }
+}
+
+impl From<[u32; DIGEST_WORDS]> for Digest {
+    fn from(words: [u32; DIGEST_WORDS]) -> Digest {
+        Digest(words)
+    }
+}
+
+impl From<Digest> for [u32; DIGEST_WORDS] {
+    fn from(digest: Digest) -> Self {
+        digest.0
+    }
+}
+
+impl AsRef<[u32]> for Digest {
+    fn as_ref(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl PartialEq for Digest {
+    fn eq(&self, other: &Digest) -> bool {
+        // Accumulate the differences of every word, rather than stopping at
+        // the first one, so the time taken doesn't reveal where digests
+        // start to differ.
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
     }
 }
 
@@ -148,6 +280,9 @@ pub trait Sha: Clone + Debug {
     /// implementation wants to manage its own memory.
     type DigestPtr: Deref<Target = Digest> + Debug;
 
+    /// The [id](super::hash::HashSuite::id) of this hash as a suite.
+    const SUITE_ID: u32 = 0;
+
     /// Generate a SHA from a slice of bytes.
     fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr;
 
@@ -166,6 +301,19 @@ pub trait Sha: Clone + Debug {
     /// Generate a SHA from a slice of [Fps](Fp).
     fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr;
 
+    /// Generate a SHA from `size` [Fps](Fp), taking every `stride`th one
+    /// starting at `offset`.
+    fn hash_fps_stride(
+        &self,
+        fps: &[Fp],
+        offset: usize,
+        size: usize,
+        stride: usize,
+    ) -> Self::DigestPtr {
+        let fps: Vec<Fp> = (0..size).map(|i| fps[offset + i * stride]).collect();
+        self.hash_fps(&fps)
+    }
+
     /// Generate a SHA from a slice of [Fp4s](Fp4).
     fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr;
 
@@ -194,6 +342,42 @@ mod tests {
             Digest::new([119, 170, 18, 52, 86, 122, 169, 9])
         );
     }
+
+    #[test]
+    fn encodings() {
+        // SHA-256("abc")
+        let digest = Digest::new([
+            0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+            0xf20015ad,
+        ]);
+        let hex = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let base64 = "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=";
+
+        assert_eq!(digest.to_bytes()[..4], [0xba, 0x78, 0x16, 0xbf]);
+        assert_eq!(Digest::from_bytes(digest.to_bytes()), digest);
+        assert_eq!(digest.to_hex(), hex);
+        assert_eq!(digest.to_string(), hex);
+        assert_eq!(Digest::from_hex(hex).unwrap(), digest);
+        assert_eq!(Digest::from_hex(&hex.to_uppercase()).unwrap(), digest);
+        assert_eq!(digest.to_base64(), base64);
+        assert_eq!(Digest::from_base64(base64).unwrap(), digest);
+        let words: [u32; 8] = digest.into();
+        assert_eq!(Digest::from(words), digest);
+
+        assert!(Digest::from_hex(&hex[1..]).is_err());
+        assert!(Digest::from_hex(&hex.replace('a', "g")).is_err());
+        assert!(Digest::from_hex(&format!("+{}", &hex[1..])).is_err());
+        assert!(Digest::from_base64(&base64[1..]).is_err());
+        assert!(Digest::from_base64(&base64.replace('=', "A")).is_err());
+        // The last character carries bits past the end of the digest.
+        assert!(Digest::from_base64(&base64.replace("Fa0=", "Fa1=")).is_err());
+        assert!(Digest::from_base64(&base64.replace('+', "-")).is_err());
+
+        assert_ne!(digest, Digest::default());
+        let mut other = digest;
+        other.as_mut_slice()[7] ^= 1;
+        assert_ne!(digest, other);
+    }
 }
 
 #[allow(missing_docs)]
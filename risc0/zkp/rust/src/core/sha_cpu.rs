@@ -41,54 +41,13 @@ fn set_word(buf: &mut [u8], idx: usize, word: u32) {
     buf[(4 * idx)..(4 * idx + 4)].copy_from_slice(&word.to_le_bytes());
 }
 
-impl Impl {
-    /// Compute the hash of an array of [Fp]s using the specified stride.
-    pub fn hash_fps_stride(
-        &self,
-        fps: &[Fp],
-        offset: usize,
-        size: usize,
-        stride: usize,
-    ) -> Box<Digest> {
-        let mut state = INIT_256;
-        let mut block: GenericArray<u8, U64> = GenericArray::default();
-        let mut off = 0;
-        for i in 0..size {
-            set_word(
-                block.as_mut_slice(),
-                off,
-                u32::from(fps[offset + i * stride]),
-            );
-            off += 1;
-            if off == 16 {
-                compress256(&mut state, slice::from_ref(&block));
-                off = 0;
-            }
-        }
-        if off != 0 {
-            block[off * 4..].fill(0);
-            compress256(&mut state, slice::from_ref(&block));
-        }
-        Box::new(Digest::new(state))
-    }
-}
-
 impl Sha for Impl {
     type DigestPtr = Box<Digest>;
 
     fn hash_bytes(&self, bytes: &[u8]) -> Self::DigestPtr {
         let mut hasher = Sha256::new();
         hasher.update(bytes);
-        Box::new(Digest::new(
-            hasher
-                .finalize()
-                .as_slice()
-                .chunks(4)
-                .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
-                .collect::<Vec<u32>>()
-                .try_into()
-                .unwrap(),
-        ))
+        Box::new(Digest::from_bytes(hasher.finalize().into()))
     }
 
     fn hash_words(&self, words: &[u32]) -> Self::DigestPtr {
@@ -116,6 +75,35 @@ impl Sha for Impl {
         self.hash_fps_stride(fps, 0, fps.len(), 1)
     }
 
+    fn hash_fps_stride(
+        &self,
+        fps: &[Fp],
+        offset: usize,
+        size: usize,
+        stride: usize,
+    ) -> Box<Digest> {
+        let mut state = INIT_256;
+        let mut block: GenericArray<u8, U64> = GenericArray::default();
+        let mut off = 0;
+        for i in 0..size {
+            set_word(
+                block.as_mut_slice(),
+                off,
+                u32::from(fps[offset + i * stride]),
+            );
+            off += 1;
+            if off == 16 {
+                compress256(&mut state, slice::from_ref(&block));
+                off = 0;
+            }
+        }
+        if off != 0 {
+            block[off * 4..].fill(0);
+            compress256(&mut state, slice::from_ref(&block));
+        }
+        Box::new(Digest::new(state))
+    }
+
     fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
         let mut flat: Vec<Fp> = vec![];
         for i in 0..fp4s.len() {
@@ -14,11 +14,22 @@
 
 /// ! Baby bear field.
 /// ! Support for the base finite field modulo 15*2^27 + 1
-use crate::field::{self, Elem as FieldElem};
+use crate::field::{
+    self,
+    prime::{self, PrimeField},
+    Elem as FieldElem,
+};
 
 use core::ops;
 
-use bytemuck::{Pod, Zeroable};
+use bytemuck::Zeroable;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ff")]
+mod ff;
+pub mod simd;
+
+pub use simd::ElemVec;
 
 /// The BabyBear class is an element of the finite field F_p, where P is the
 /// prime number 15*2^27 + 1. Put another way, Fp is basically integer
@@ -39,169 +50,103 @@ use bytemuck::{Pod, Zeroable};
 ///
 /// The Fp class wraps all the standard arithmetic operations to make the finite
 /// field elements look basically like ordinary numbers (which they mostly are).
-#[derive(Eq, PartialEq, Clone, Copy, Debug, Pod, Zeroable)]
-#[repr(transparent)]
-pub struct Elem(u32);
-
-impl Default for Elem {
-    fn default() -> Self {
-        Self::ZERO
-    }
-}
+///
+/// Internally, elements are kept in Montgomery form, `x * 2^32 % P`, so that
+/// multiplication needs no division; see [to_montgomery]. Convert with
+/// `u32::from` and `Elem::from` to get at the canonical value, rather than
+/// reinterpreting an element's memory.
+///
+/// The arithmetic is shared with other 31-bit fields through [PrimeField];
+/// this module adds the roots of unity, square roots, and the extension
+/// field.
+pub type Elem = PrimeField<P>;
 
-/// The modulus of the field.
-const P: u32 = 15 * (1 << 27) + 1;
-/// The modulus of the field as a u64.
-const P_U64: u64 = P as u64;
+/// The modulus of the field, 15 * 2^27 + 1.
+// Written out, since rustc finds a cycle in evaluating an arithmetic
+// expression for `PrimeField<P>` while it checks the impls on that type.
+const P: u32 = 0x7800_0001;
 
-impl field::Elem for Elem {
-    const ZERO: Self = Elem::new(0);
+impl field::TwoAdicField for Elem {
+    const TWO_ADICITY: usize = 27;
 
-    const ONE: Self = Elem::new(1);
+    const TWO_ADIC_GENERATOR: Elem = Elem::new(137);
 
-    /// Compute the multiplicative inverse of `x`, or `1 / x` in finite field
-    /// terms. Since `x ^ (P - 1) == 1 % P` for any `x != 0` (as a
-    /// consequence of Fermat's little theorem), it follows that `x *
-    /// x ^ (P - 2) == 1 % P` for `x != 0`.  That is, `x ^ (P - 2)` is the
-    /// multiplicative inverse of `x`. Computed this way, the *inverse* of
-    /// zero comes out as zero, which is convenient in many cases, so we
-    /// leave it.
-    fn inv(self) -> Self {
-        self.pow((P - 2) as usize)
+    #[cfg(feature = "std")]
+    fn root_of_unity(po2: usize) -> Self {
+        ROOTS.root_of_unity(po2)
     }
 
-    fn random(rng: &mut impl rand::Rng) -> Self {
-        // Reject the last modulo-P region of possible uint32_t values, since it's
-        // uneven and will only return random values less than (2^32 % P).
-        const REJECT_CUTOFF: u32 = (u32::MAX / P) * P;
-        let mut val: u32 = rng.gen();
-
-        while val >= REJECT_CUTOFF {
-            val = rng.gen();
-        }
-        Elem::from(val)
+    #[cfg(feature = "std")]
+    fn inv_root_of_unity(po2: usize) -> Self {
+        ROOTS.inv_root_of_unity(po2)
     }
 }
 
-macro_rules! rou_array {
-    [$($x:literal),* $(,)?] => {
-        [$(Elem::new($x)),* ]
-    }
-}
-
-impl field::RootsOfUnity for Elem {
-    const MAX_ROU_PO2: usize = 27;
-
-    const ROU_FWD: &'static [Elem] = &rou_array![
-        1, 2013265920, 284861408, 1801542727, 567209306, 740045640, 918899846, 1881002012,
-        1453957774, 65325759, 1538055801, 515192888, 483885487, 157393079, 1695124103, 2005211659,
-        1540072241, 88064245, 1542985445, 1269900459, 1461624142, 825701067, 682402162, 1311873874,
-        1164520853, 352275361, 18769, 137
-    ];
-
-    const ROU_REV: &'static [Elem] = &rou_array![
-        1, 2013265920, 1728404513, 1592366214, 196396260, 1253260071, 72041623, 1091445674,
-        145223211, 1446820157, 1030796471, 2010749425, 1827366325, 1239938613, 246299276,
-        596347512, 1893145354, 246074437, 1525739923, 1194341128, 1463599021, 704606912, 95395244,
-        15672543, 647517488, 584175179, 137728885, 749463956
-    ];
-}
+#[cfg(feature = "std")]
+static ROOTS: field::RootTable<Elem> = field::RootTable::new();
 
 impl Elem {
-    /// Create a new [BabyBear] from a raw integer.
-    pub const fn new(x: u32) -> Self {
-        Self(x % P)
-    }
-}
-
-impl ops::Add for Elem {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Elem(add(self.0, rhs.0))
-    }
-}
-
-impl ops::AddAssign for Elem {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0 = add(self.0, rhs.0)
-    }
-}
-
-impl ops::Sub for Elem {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self {
-        Elem(sub(self.0, rhs.0))
-    }
-}
-
-impl ops::SubAssign for Elem {
-    fn sub_assign(&mut self, rhs: Self) {
-        self.0 = sub(self.0, rhs.0)
-    }
-}
-
-impl ops::Mul for Elem {
-    type Output = Self;
-    fn mul(self, rhs: Self) -> Self {
-        Elem(mul(self.0, rhs.0))
-    }
-}
-
-impl ops::MulAssign for Elem {
-    fn mul_assign(&mut self, rhs: Self) {
-        self.0 = mul(self.0, rhs.0)
-    }
-}
-
-impl ops::Neg for Elem {
-    type Output = Self;
-    fn neg(self) -> Self {
-        Elem(0) - self
-    }
-}
-
-impl From<Elem> for u32 {
-    fn from(x: Elem) -> Self {
-        x.0
-    }
-}
-
-impl From<&Elem> for u32 {
-    fn from(x: &Elem) -> Self {
-        x.0
-    }
-}
-
-impl From<Elem> for u64 {
-    fn from(x: Elem) -> Self {
-        x.0.into()
+    /// Compute a square root of `x`, or `None` if `x` is not a square.  The
+    /// other square root is the negation of this one.
+    ///
+    /// This uses Tonelli-Shanks.  Since `P - 1 = Q * 2^S` with `Q = 15` and
+    /// `S = 27`, the 2^S-th root of unity stands in for the power of a
+    /// non-residue, and each step fixes one bit of the 2-adic part.
+    pub fn sqrt(self) -> Option<Self> {
+        use field::TwoAdicField;
+
+        const Q: usize = 15;
+        if self.legendre() == -1 {
+            return None;
+        }
+        if self == Elem::ZERO {
+            return Some(Elem::ZERO);
+        }
+        let mut m = Self::TWO_ADICITY;
+        let mut c = Self::TWO_ADIC_GENERATOR;
+        let mut t = self.pow(Q);
+        let mut r = self.pow((Q + 1) / 2);
+        while t != Elem::ONE {
+            // Find the least i with t^(2^i) == 1; i < m since x is a square.
+            let mut i = 0;
+            let mut t2i = t;
+            while t2i != Elem::ONE {
+                t2i *= t2i;
+                i += 1;
+            }
+            let mut b = c;
+            for _ in 0..m - i - 1 {
+                b *= b;
+            }
+            m = i;
+            c = b * b;
+            t *= c;
+            r *= b;
+        }
+        Some(r)
     }
 }
 
-impl From<u32> for Elem {
-    fn from(x: u32) -> Self {
-        Elem(x % P)
-    }
+fn add(lhs: u32, rhs: u32) -> u32 {
+    prime::add::<P>(lhs, rhs)
 }
 
-impl From<u64> for Elem {
-    fn from(x: u64) -> Self {
-        Elem((x % P_U64) as u32)
-    }
+fn sub(lhs: u32, rhs: u32) -> u32 {
+    prime::sub::<P>(lhs, rhs)
 }
 
-fn add(lhs: u32, rhs: u32) -> u32 {
-    let x = lhs + rhs;
-    return if x >= P { x - P } else { x };
+fn mul(lhs: u32, rhs: u32) -> u32 {
+    prime::mul::<P>(lhs, rhs)
 }
 
-fn sub(lhs: u32, rhs: u32) -> u32 {
-    let x = lhs.wrapping_sub(rhs);
-    return if x > P { x.wrapping_add(P) } else { x };
+/// Convert a canonical value below P into Montgomery form, `x * 2^32 % P`.
+pub const fn to_montgomery(x: u32) -> u32 {
+    prime::to_montgomery::<P>(x)
 }
 
-fn mul(lhs: u32, rhs: u32) -> u32 {
-    (((lhs as u64) * (rhs as u64)) % P_U64) as u32
+/// Convert a value in Montgomery form back to its canonical value below P.
+pub const fn from_montgomery(x: u32) -> u32 {
+    prime::from_montgomery::<P>(x)
 }
 
 /// The size of the extension field in elements, 4 in this case.
@@ -215,7 +160,7 @@ const EXT_SIZE: usize = 4;
 /// which is important. The irreducible polynomial was choosen to be the most
 /// simple possible one, `x^4 - B`, where `11` is the smallest `B` which makes
 /// the polynomial irreducable.
-#[derive(Eq, PartialEq, Clone, Copy, Debug, Pod, Zeroable)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Zeroable, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct ExtElem([Elem; EXT_SIZE]);
 
@@ -239,21 +184,6 @@ impl field::Elem for ExtElem {
         ])
     }
 
-    /// Raise a [ExtElem] to a power of `n`.
-    fn pow(self, n: usize) -> Self {
-        let mut n = n;
-        let mut tot = ExtElem::from(1);
-        let mut x = self;
-        while n != 0 {
-            if n % 2 == 1 {
-                tot *= x;
-            }
-            n = n / 2;
-            x *= x;
-        }
-        tot
-    }
-
     /// Compute the multiplicative inverse of an `ExtElem`.
     fn inv(self) -> Self {
         let a = &self.0;
@@ -307,9 +237,44 @@ impl From<[Elem; EXT_SIZE]> for ExtElem {
     }
 }
 
+/// Generates each component independently; see the impl for [Elem].
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for ExtElem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::{arbitrary::any, strategy::Strategy};
+        any::<[Elem; EXT_SIZE]>().prop_map(ExtElem).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ExtElem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(ExtElem(arbitrary::Arbitrary::arbitrary(u)?))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ExtElem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 const BETA: Elem = Elem::new(11);
 const NBETA: Elem = Elem::new(P - 11);
 
+/// Powers of `(-BETA)^((P - 1) / 4)`, a primitive 4th root of unity, by which
+/// [ExtElem::frobenius] scales each coefficient.
+const FROBENIUS: [Elem; EXT_SIZE] = [
+    Elem::new(1),
+    Elem::new(1728404513),
+    Elem::new(P - 1),
+    Elem::new(284861408),
+];
+
 impl ExtElem {
     /// Explicitly construct an ExtElem from parts.
     pub fn new(x0: Elem, x1: Elem, x2: Elem, x3: Elem) -> Self {
@@ -345,6 +310,105 @@ impl ExtElem {
     pub fn elems(&self) -> &[Elem] {
         &self.0
     }
+
+    /// Returns the encodings of the components, in order; see
+    /// [Elem::to_le_bytes].
+    pub fn to_le_bytes(self) -> [u8; 16] {
+        let mut out = [0; 16];
+        for (chunk, elem) in out.chunks_exact_mut(4).zip(self.0) {
+            chunk.copy_from_slice(&elem.to_le_bytes());
+        }
+        out
+    }
+
+    /// Decode the encoding written by [ExtElem::to_le_bytes], or return
+    /// `None` if any component is not less than P.
+    pub fn from_le_bytes_checked(bytes: [u8; 16]) -> Option<Self> {
+        let mut elems = [Elem::ZERO; EXT_SIZE];
+        for (elem, chunk) in elems.iter_mut().zip(bytes.chunks_exact(4)) {
+            *elem = Elem::from_le_bytes_checked(chunk.try_into().unwrap())?;
+        }
+        Some(ExtElem(elems))
+    }
+
+    /// Returns `x^P`, the image of `x` under the Frobenius automorphism,
+    /// which fixes exactly the elements of the base field.  Since
+    /// `X^P = X * (X^4)^((P - 1) / 4)` and `X^4 = -BETA`, this just scales
+    /// each coefficient by a 4th root of unity.
+    pub fn frobenius(self) -> Self {
+        let mut out = self;
+        for (coeff, scale) in out.0.iter_mut().zip(FROBENIUS) {
+            *coeff *= scale;
+        }
+        out
+    }
+
+    /// Returns the conjugates of `x`, `x^(P^i)` for each `i` below
+    /// [EXT_SIZE], starting with `x` itself.
+    pub fn conjugates(self) -> [Self; EXT_SIZE] {
+        let mut out = [self; EXT_SIZE];
+        for i in 1..EXT_SIZE {
+            out[i] = out[i - 1].frobenius();
+        }
+        out
+    }
+
+    /// Returns the norm of `x`, the product of its conjugates, which is in
+    /// the base field and is zero only for zero.
+    pub fn norm(self) -> Elem {
+        self.conjugates()
+            .into_iter()
+            .fold(ExtElem::ONE, |acc, x| acc * x)
+            .const_part()
+    }
+
+    /// Returns the trace of `x`, the sum of its conjugates.  The 4th roots of
+    /// unity cancel in every coefficient but the constant one, so this is
+    /// `4 * x0`.
+    pub fn trace(self) -> Elem {
+        Elem::new(EXT_SIZE as u32) * self.0[0]
+    }
+
+    /// Compute a square root of `x`, or `None` if `x` is not a square.
+    ///
+    /// This is Tonelli-Shanks as in [Elem::sqrt], over the extension's
+    /// multiplicative group of order `P^4 - 1 = Q * 2^S`, with `S = 29`.
+    /// The generator `X` of the extension is not a square, since its norm,
+    /// 11, is not a square in [Elem], so its Q-th power supplies the 2^S-th
+    /// root of unity.
+    pub fn sqrt(self) -> Option<Self> {
+        const S: usize = 29;
+        const Q: u128 = 0x62e0_8003_4bc0_000a_8c00_000f;
+        if self == ExtElem::ZERO {
+            return Some(ExtElem::ZERO);
+        }
+        let x = ExtElem([Elem::ZERO, Elem::ONE, Elem::ZERO, Elem::ZERO]);
+        let mut m = S;
+        let mut c = x.pow_bytes(&Q.to_le_bytes());
+        let mut t = self.pow_bytes(&Q.to_le_bytes());
+        let mut r = self.pow_bytes(&((Q + 1) / 2).to_le_bytes());
+        while t != ExtElem::ONE {
+            // Find the least i with t^(2^i) == 1; for a square, i < m.
+            let mut i = 0;
+            let mut t2i = t;
+            while t2i != ExtElem::ONE {
+                t2i *= t2i;
+                i += 1;
+            }
+            if i == m {
+                return None;
+            }
+            let mut b = c;
+            for _ in 0..m - i - 1 {
+                b *= b;
+            }
+            m = i;
+            c = b * b;
+            t *= c;
+            r *= b;
+        }
+        Some(r)
+    }
 }
 
 impl ops::Add for ExtElem {
@@ -442,6 +506,9 @@ impl ops::Neg for ExtElem {
     }
 }
 
+impl_div_sum_product!(ExtElem);
+impl_elem_bytes!(ExtElem, 16);
+
 impl From<u32> for ExtElem {
     fn from(x: u32) -> Self {
         Self([Elem::from(x), Elem::ZERO, Elem::ZERO, Elem::ZERO])
@@ -454,16 +521,64 @@ impl From<Elem> for ExtElem {
     }
 }
 
+/// Constant-time support for [ExtElem], enabled by the `ct` feature; the
+/// [Elem] support is shared through [PrimeField].
+#[cfg(feature = "ct")]
+mod ct {
+    use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+    use super::ExtElem;
+
+    impl ConstantTimeEq for ExtElem {
+        fn ct_eq(&self, other: &Self) -> Choice {
+            self.0[..].ct_eq(&other.0[..])
+        }
+    }
+
+    impl ConditionallySelectable for ExtElem {
+        fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+            let mut out = *a;
+            for (x, y) in out.0.iter_mut().zip(&b.0) {
+                x.conditional_assign(y, choice);
+            }
+            out
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::field;
-    use super::{Elem, ExtElem, P, P_U64};
+    use super::{from_montgomery, to_montgomery, Elem, ExtElem, EXT_SIZE, P};
     use crate::field::Elem as FieldElem;
     use rand::SeedableRng;
 
+    const P_U64: u64 = P as u64;
+
+    macro_rules! rou_array {
+        [$($x:literal),* $(,)?] => {
+            [$(Elem::new($x)),* ]
+        }
+    }
+
+    // Known-good roots of unity, indexed by po2.
+    const ROU_FWD: [Elem; 28] = rou_array![
+        1, 2013265920, 284861408, 1801542727, 567209306, 740045640, 918899846, 1881002012,
+        1453957774, 65325759, 1538055801, 515192888, 483885487, 157393079, 1695124103, 2005211659,
+        1540072241, 88064245, 1542985445, 1269900459, 1461624142, 825701067, 682402162, 1311873874,
+        1164520853, 352275361, 18769, 137
+    ];
+
+    const ROU_REV: [Elem; 28] = rou_array![
+        1, 2013265920, 1728404513, 1592366214, 196396260, 1253260071, 72041623, 1091445674,
+        145223211, 1446820157, 1030796471, 2010749425, 1827366325, 1239938613, 246299276,
+        596347512, 1893145354, 246074437, 1525739923, 1194341128, 1463599021, 704606912, 95395244,
+        15672543, 647517488, 584175179, 137728885, 749463956
+    ];
+
     #[test]
     pub fn roots_of_unity() {
-        field::test::test_roots_of_unity::<Elem>();
+        field::test::test_roots_of_unity::<Elem>(&ROU_FWD, &ROU_REV);
     }
 
     #[test]
@@ -471,6 +586,93 @@ mod tests {
         field::test::test_field_ops::<Elem>(P_U64);
     }
 
+    #[test]
+    pub fn div_sum_product() {
+        field::test::test_div_sum_product::<Elem>();
+        field::test::test_div_sum_product::<ExtElem>();
+    }
+
+    #[test]
+    pub fn batch_inv() {
+        field::test::test_batch_inv::<Elem>();
+        field::test::test_batch_inv::<ExtElem>();
+    }
+
+    #[test]
+    pub fn field_axioms() {
+        field::test::test_field_axioms::<Elem>();
+        field::test::test_field_axioms::<ExtElem>();
+    }
+
+    #[test]
+    pub fn elem_bytes() {
+        field::test::test_elem_bytes::<Elem>();
+        field::test::test_elem_bytes::<ExtElem>();
+    }
+
+    #[test]
+    pub fn pow_variants() {
+        field::test::test_pow::<Elem>();
+        field::test::test_pow::<ExtElem>();
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut u = Unstructured::new(&[0xff; 64]);
+        let x = ExtElem::arbitrary(&mut u).unwrap();
+        for elem in x.elems() {
+            assert!(u32::from(elem) < P);
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut x = ExtElem::from_u32(7) + ExtElem::new(Elem::ONE, Elem::ONE, Elem::ONE, Elem::ONE);
+        x.zeroize();
+        assert_eq!(x, ExtElem::ZERO);
+
+        let mut secret = field::SecretElem::new(Elem::new(5));
+        *secret.expose_mut() += Elem::ONE;
+        assert_eq!(secret.expose(), Elem::new(6));
+        assert_eq!(format!("{secret:?}"), "SecretElem(..)");
+    }
+
+    #[test]
+    fn sqrt() {
+        assert_eq!(Elem::ZERO.legendre(), 0);
+        assert_eq!(Elem::ZERO.sqrt(), Some(Elem::ZERO));
+        assert_eq!(Elem::ONE.legendre(), 1);
+        assert_eq!(Elem::new(4).sqrt().map(|r| r * r), Some(Elem::new(4)));
+        // 31 generates the multiplicative group, so is not a square.
+        assert_eq!(Elem::new(31).legendre(), -1);
+        assert_eq!(Elem::new(31).sqrt(), None);
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        let mut squares = 0;
+        for _ in 0..1000 {
+            let x = Elem::random(&mut rng);
+            let sq = x * x;
+            assert_eq!(sq.legendre(), 1);
+            let root = sq.sqrt().unwrap();
+            assert!(root == x || root == -x);
+            match x.sqrt() {
+                Some(root) => {
+                    assert_eq!(root * root, x);
+                    squares += 1;
+                }
+                None => assert_eq!(x.legendre(), -1),
+            }
+        }
+        // About half of the elements are squares.
+        assert!((400..600).contains(&squares));
+    }
+
     #[test]
     fn isa_field() {
         let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
@@ -500,19 +702,168 @@ mod tests {
     #[test]
     fn inv() {
         // Smoke test for inv
-        assert_eq!(Elem(5).inv() * Elem(5), Elem(1));
+        assert_eq!(Elem::new(5).inv() * Elem::new(5), Elem::new(1));
     }
 
     #[test]
     fn pow() {
         // Smoke tests for pow
-        assert_eq!(Elem(5).pow(0), Elem(1));
-        assert_eq!(Elem(5).pow(1), Elem(5));
-        assert_eq!(Elem(5).pow(2), Elem(25));
+        assert_eq!(Elem::new(5).pow(0), Elem::new(1));
+        assert_eq!(Elem::new(5).pow(1), Elem::new(5));
+        assert_eq!(Elem::new(5).pow(2), Elem::new(25));
         // Mathematica says PowerMod[5, 1000, 15*2^27 + 1] == 589699054
-        assert_eq!(Elem(5).pow(1000), Elem(589699054));
-        assert_eq!(Elem(5).pow((P - 2) as usize) * Elem(5), Elem(1));
-        assert_eq!(Elem(5).pow((P - 1) as usize), Elem(1));
+        assert_eq!(Elem::new(5).pow(1000), Elem::new(589699054));
+        assert_eq!(
+            Elem::new(5).pow((P - 2) as usize) * Elem::new(5),
+            Elem::new(1)
+        );
+        assert_eq!(Elem::new(5).pow((P - 1) as usize), Elem::new(1));
+    }
+
+    #[test]
+    fn montgomery() {
+        assert_eq!(Elem::M, 0x77ff_ffff);
+        assert_eq!(Elem::R2, 0x45dd_dde3);
+        assert_eq!(to_montgomery(0), 0);
+        assert_eq!(to_montgomery(1), ((1u64 << 32) % P_U64) as u32);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let x = u32::from(Elem::random(&mut rng));
+            assert!(to_montgomery(x) < P);
+            assert_eq!(from_montgomery(to_montgomery(x)), x);
+        }
+        // Products of the largest elements still reduce below P.
+        let max = Elem::new(P - 1);
+        assert_eq!(max * max, Elem::ONE);
+        assert_eq!(alloc::format!("{:?}", Elem::new(7)), "Elem(7)");
+    }
+
+    #[test]
+    fn ext_frobenius() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let x = ExtElem::random(&mut rng);
+            let y = ExtElem::random(&mut rng);
+            assert_eq!(x.frobenius(), x.pow(P as usize));
+            let conjugates = x.conjugates();
+            assert_eq!(conjugates[0], x);
+            assert_eq!(conjugates[3].frobenius(), x);
+            assert_eq!((x * y).frobenius(), x.frobenius() * y.frobenius());
+            assert_eq!(
+                ExtElem::from_fp(x.trace()),
+                conjugates.iter().fold(ExtElem::ZERO, |acc, c| acc + *c)
+            );
+            assert_eq!((x * y).norm(), x.norm() * y.norm());
+            assert_eq!((x + y).trace(), x.trace() + y.trace());
+        }
+        // The base field is fixed.
+        let a = Elem::new(12345);
+        assert_eq!(ExtElem::from_fp(a).frobenius(), ExtElem::from_fp(a));
+        assert_eq!(ExtElem::from_fp(a).norm(), a.pow(4));
+        assert_eq!(ExtElem::from_fp(a).trace(), a * Elem::new(4));
+        let x = ExtElem::new(Elem::ZERO, Elem::ONE, Elem::ZERO, Elem::ZERO);
+        assert_ne!(x.frobenius(), x);
+        assert_eq!(x.norm(), Elem::new(11));
+        assert_eq!(ExtElem::ZERO.norm(), Elem::ZERO);
+    }
+
+    #[test]
+    fn ext_sqrt() {
+        // P^4 - 1 = Q * 2^29 with Q odd.
+        let order = (P_U64 as u128).pow(4) - 1;
+        assert_eq!(order, 0x62e0_8003_4bc0_000a_8c00_000f << 29);
+        assert_eq!(ExtElem::ZERO.sqrt(), Some(ExtElem::ZERO));
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let x = ExtElem::random(&mut rng);
+            let root = (x * x).sqrt().unwrap();
+            assert!(root == x || root == -x);
+        }
+        // Every element of the base field is a square in the extension.
+        assert!(ExtElem::from_fp(Elem::new(31)).sqrt().is_some());
+        let x = ExtElem::new(Elem::ZERO, Elem::ONE, Elem::ZERO, Elem::ZERO);
+        assert_eq!(x.sqrt(), None);
+        assert_eq!((x * ExtElem::from_u32(4)).sqrt(), None);
+    }
+
+    #[cfg(feature = "ct")]
+    #[test]
+    fn constant_time() {
+        use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let x = Elem::random(&mut rng);
+            if x != Elem::ZERO {
+                assert_eq!(x.inv() * x, Elem::ONE);
+            }
+        }
+        let (a, b) = (ExtElem::random(&mut rng), ExtElem::random(&mut rng));
+        assert!(bool::from(a.ct_eq(&a)));
+        assert!(!bool::from(a.ct_eq(&b)));
+        assert_eq!(ExtElem::conditional_select(&a, &b, Choice::from(0)), a);
+        assert_eq!(ExtElem::conditional_select(&a, &b, Choice::from(1)), b);
+        let max = Elem::new(P - 1);
+        assert_eq!(max + Elem::ONE, Elem::ZERO);
+        assert_eq!(Elem::ZERO - Elem::ONE, max);
+    }
+
+    #[test]
+    fn serde() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        let x = Elem::random(&mut rng);
+        let bytes = bincode::serialize(&x).unwrap();
+        assert_eq!(bytes, u32::from(x).to_le_bytes());
+        assert_eq!(bincode::deserialize::<Elem>(&bytes).unwrap(), x);
+        let a = ExtElem::random(&mut rng);
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bytes.len(), 4 * EXT_SIZE);
+        assert_eq!(bincode::deserialize::<ExtElem>(&bytes).unwrap(), a);
+        // Non-canonical values are rejected.
+        assert!(bincode::deserialize::<Elem>(&P.to_le_bytes()).is_err());
+        assert!(bincode::deserialize::<Elem>(&u32::MAX.to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn checked_and_strings() {
+        use crate::field::ParseElemError;
+
+        assert_eq!(Elem::from_u32_checked(P - 1), Some(Elem::new(P - 1)));
+        assert_eq!(Elem::from_u32_checked(P), None);
+        assert_eq!(Elem::from_u32_checked(u32::MAX), None);
+        assert_eq!(Elem::new(P - 1).to_le_bytes(), (P - 1).to_le_bytes());
+        assert_eq!(Elem::from_le_bytes_checked(P.to_le_bytes()), None);
+        let mut bytes = crate::field::elems_to_bytes(&[Elem::ONE, Elem::ONE]);
+        bytes[4..].copy_from_slice(&P.to_le_bytes());
+        assert_eq!(
+            crate::field::bytes_to_elems::<Elem>(&bytes),
+            Err(crate::field::ElemBytesError::OutOfRange(1))
+        );
+
+        let x = Elem::new(0x1234abcd);
+        assert_eq!(x.to_string(), "305441741");
+        assert_eq!(format!("{:x}", x), "1234abcd");
+        assert_eq!(format!("{:#X}", x), "0x1234ABCD");
+        assert_eq!("305441741".parse(), Ok(x));
+        assert_eq!("0x1234abcd".parse(), Ok(x));
+        assert_eq!("0X1234ABCD".parse(), Ok(x));
+        assert_eq!("2013265920".parse(), Ok(Elem::new(P - 1)));
+        assert_eq!(
+            "2013265921".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!(
+            "0xffffffff".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!(
+            "0x1ffffffff".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!("".parse::<Elem>(), Err(ParseElemError::Invalid));
+        assert_eq!("-1".parse::<Elem>(), Err(ParseElemError::Invalid));
+        assert_eq!("0xg".parse::<Elem>(), Err(ParseElemError::Invalid));
     }
 
     #[test]
@@ -0,0 +1,224 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementations of the [ff] traits, enabled by the `ff` feature.
+//!
+//! [Elem] implements [ff::PrimeField] and [ExtElem] implements [ff::Field],
+//! so BabyBear can be used directly with libraries built on those traits.
+//! The feature also enables `ct`, whose constant-time equality and selection
+//! the traits require.  Square roots are computed with variable-time
+//! Tonelli-Shanks, as in [Elem::sqrt] and [ExtElem::sqrt].
+
+use core::ops;
+
+use ff::{Field, PrimeField};
+use rand_core::RngCore;
+use subtle::{Choice, ConstantTimeEq, ConstantTimeLess, CtOption};
+
+use super::{Elem, ExtElem, P};
+use crate::field::Elem as FieldElem;
+
+// The operators on references which the ff traits expect.
+macro_rules! ref_ops {
+    ($t:ty) => {
+        impl<'a> ops::Add<&'a $t> for $t {
+            type Output = $t;
+            fn add(self, rhs: &'a $t) -> $t {
+                self + *rhs
+            }
+        }
+
+        impl<'a> ops::Sub<&'a $t> for $t {
+            type Output = $t;
+            fn sub(self, rhs: &'a $t) -> $t {
+                self - *rhs
+            }
+        }
+
+        impl<'a> ops::Mul<&'a $t> for $t {
+            type Output = $t;
+            fn mul(self, rhs: &'a $t) -> $t {
+                self * *rhs
+            }
+        }
+
+        impl<'a> ops::AddAssign<&'a $t> for $t {
+            fn add_assign(&mut self, rhs: &'a $t) {
+                *self += *rhs
+            }
+        }
+
+        impl<'a> ops::SubAssign<&'a $t> for $t {
+            fn sub_assign(&mut self, rhs: &'a $t) {
+                *self -= *rhs
+            }
+        }
+
+        impl<'a> ops::MulAssign<&'a $t> for $t {
+            fn mul_assign(&mut self, rhs: &'a $t) {
+                *self *= *rhs
+            }
+        }
+    };
+}
+
+ref_ops!(Elem);
+ref_ops!(ExtElem);
+
+impl Field for Elem {
+    fn random(mut rng: impl RngCore) -> Self {
+        <Elem as FieldElem>::random(&mut rng)
+    }
+
+    fn zero() -> Self {
+        Elem::ZERO
+    }
+
+    fn one() -> Self {
+        Elem::ONE
+    }
+
+    fn square(&self) -> Self {
+        *self * *self
+    }
+
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        CtOption::new(FieldElem::inv(*self), !self.is_zero())
+    }
+
+    fn sqrt(&self) -> CtOption<Self> {
+        match Elem::sqrt(*self) {
+            Some(root) => CtOption::new(root, Choice::from(1)),
+            None => CtOption::new(Elem::ZERO, Choice::from(0)),
+        }
+    }
+}
+
+impl PrimeField for Elem {
+    /// The canonical value as 4 little-endian bytes.
+    type Repr = [u8; 4];
+
+    fn from_repr(repr: Self::Repr) -> CtOption<Self> {
+        let x = u32::from_le_bytes(repr);
+        CtOption::new(Elem::new(x), x.ct_lt(&P))
+    }
+
+    fn to_repr(&self) -> Self::Repr {
+        u32::from(self).to_le_bytes()
+    }
+
+    fn is_odd(&self) -> Choice {
+        Choice::from((u32::from(self) & 1) as u8)
+    }
+
+    const NUM_BITS: u32 = 31;
+
+    const CAPACITY: u32 = 30;
+
+    fn multiplicative_generator() -> Self {
+        Elem::new(31)
+    }
+
+    const S: u32 = 27;
+
+    /// The generator raised to the odd part of `P - 1`, `31^15`.  This is a
+    /// different primitive 2^27-th root of unity from
+    /// [TwoAdicField::TWO_ADIC_GENERATOR](crate::field::TwoAdicField), which
+    /// the NTTs use.
+    fn root_of_unity() -> Self {
+        Elem::new(440564289)
+    }
+}
+
+impl Field for ExtElem {
+    fn random(mut rng: impl RngCore) -> Self {
+        <ExtElem as FieldElem>::random(&mut rng)
+    }
+
+    fn zero() -> Self {
+        ExtElem::ZERO
+    }
+
+    fn one() -> Self {
+        ExtElem::ONE
+    }
+
+    fn square(&self) -> Self {
+        *self * *self
+    }
+
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        CtOption::new(FieldElem::inv(*self), !self.ct_eq(&ExtElem::ZERO))
+    }
+
+    fn sqrt(&self) -> CtOption<Self> {
+        match ExtElem::sqrt(*self) {
+            Some(root) => CtOption::new(root, Choice::from(1)),
+            None => CtOption::new(ExtElem::ZERO, Choice::from(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::{Field, PrimeField};
+    use rand::SeedableRng;
+
+    use super::super::{Elem, ExtElem, P};
+
+    #[test]
+    fn prime_field() {
+        let g = Elem::multiplicative_generator();
+        let root = Elem::root_of_unity();
+        assert_eq!(Field::pow_vartime(&g, [((P - 1) >> Elem::S) as u64]), root);
+        let mut order = root;
+        for _ in 0..Elem::S - 1 {
+            order = order.square();
+        }
+        assert_ne!(order, Elem::one());
+        assert_eq!(order.square(), Elem::one());
+        assert!((P as u64) < 1 << Elem::NUM_BITS);
+        assert!((P as u64) > 1 << Elem::CAPACITY);
+
+        let x = Elem::new(12345);
+        assert_eq!(Elem::from_repr(x.to_repr()).unwrap(), x);
+        assert!(bool::from(x.is_odd()));
+        assert!(bool::from(Elem::from_repr(P.to_le_bytes()).is_none()));
+        assert_eq!(Elem::from_str_vartime("12345"), Some(x));
+        assert!(bool::from(Elem::zero().invert().is_none()));
+    }
+
+    #[test]
+    fn field_ops() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let a = <Elem as Field>::random(&mut rng);
+            assert_eq!(a * a.invert().unwrap(), Elem::one());
+            assert_eq!(a.square().sqrt().map(|r| r.square()).unwrap(), a.square());
+            let b = <ExtElem as Field>::random(&mut rng);
+            assert_eq!(b * b.invert().unwrap(), ExtElem::one());
+            assert_eq!(b.square().sqrt().map(|r| r.square()).unwrap(), b.square());
+            assert_eq!([a, a, a].iter().sum::<Elem>(), a.double() + a);
+            assert_eq!([b, b].into_iter().product::<ExtElem>(), b.square());
+        }
+    }
+}
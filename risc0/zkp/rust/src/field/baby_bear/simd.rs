@@ -0,0 +1,366 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic on [LANES] BabyBear elements at once.
+//!
+//! [ElemVec] uses AVX2 on x86_64 builds with the `avx2` target feature
+//! enabled (for example with `-C target-cpu=native`), NEON on aarch64, and
+//! a lane-by-lane loop everywhere else.  Lanes hold elements in the same
+//! Montgomery form as [Elem], so loading and storing them is a plain copy.
+
+use core::{mem, ops};
+
+use super::Elem;
+use crate::field::Elem as FieldElem;
+
+/// The number of elements in an [ElemVec].
+pub const LANES: usize = 8;
+
+type Lanes = [u32; LANES];
+
+/// A vector of [LANES] BabyBear elements, operated on lane by lane.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ElemVec([Elem; LANES]);
+
+impl ElemVec {
+    /// A vector with `x` in every lane.
+    pub const fn splat(x: Elem) -> Self {
+        ElemVec([x; LANES])
+    }
+
+    /// The vector `[1, x, x^2, ..., x^(LANES - 1)]`.
+    pub fn powers(x: Elem) -> Self {
+        let mut out = [Elem::ONE; LANES];
+        for i in 1..LANES {
+            out[i] = out[i - 1] * x;
+        }
+        ElemVec(out)
+    }
+
+    /// Loads the first [LANES] elements of `src`.
+    ///
+    /// Panics if `src` is shorter than [LANES].
+    #[inline(always)]
+    pub fn load(src: &[Elem]) -> Self {
+        ElemVec(src[..LANES].try_into().unwrap())
+    }
+
+    /// Stores the lanes to the first [LANES] elements of `dst`.
+    ///
+    /// Panics if `dst` is shorter than [LANES].
+    #[inline(always)]
+    pub fn store(self, dst: &mut [Elem]) {
+        dst[..LANES].copy_from_slice(&self.0);
+    }
+
+    /// The lanes as an array.
+    pub const fn to_array(self) -> [Elem; LANES] {
+        self.0
+    }
+
+    #[inline(always)]
+    fn raw(self) -> Lanes {
+        // SAFETY: Elem is a transparent wrapper around u32.
+        unsafe { mem::transmute(self.0) }
+    }
+
+    #[inline(always)]
+    fn from_raw(raw: Lanes) -> Self {
+        // SAFETY: Every lane comes out of imp reduced below P.
+        ElemVec(unsafe { mem::transmute(raw) })
+    }
+}
+
+impl From<[Elem; LANES]> for ElemVec {
+    fn from(lanes: [Elem; LANES]) -> Self {
+        ElemVec(lanes)
+    }
+}
+
+impl ops::Add for ElemVec {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self::from_raw(imp::add(&self.raw(), &rhs.raw()))
+    }
+}
+
+impl ops::AddAssign for ElemVec {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::Sub for ElemVec {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self::from_raw(imp::sub(&self.raw(), &rhs.raw()))
+    }
+}
+
+impl ops::SubAssign for ElemVec {
+    #[inline(always)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl ops::Mul for ElemVec {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_raw(imp::mul(&self.raw(), &rhs.raw()))
+    }
+}
+
+impl ops::MulAssign for ElemVec {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+// Each backend works on Montgomery-form lanes below P, and returns lanes
+// below P.  Reductions from [0, 2P) use an unsigned min against the value
+// less P, which wraps to a large number when the value is already below P.
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+mod imp {
+    use core::arch::x86_64::*;
+
+    use super::super::{Elem, P};
+    use super::Lanes;
+
+    const M: u32 = Elem::M;
+
+    #[inline(always)]
+    unsafe fn load(x: &Lanes) -> __m256i {
+        _mm256_loadu_si256(x.as_ptr() as *const __m256i)
+    }
+
+    #[inline(always)]
+    unsafe fn store(x: __m256i) -> Lanes {
+        let mut out = [0; super::LANES];
+        _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, x);
+        out
+    }
+
+    #[inline(always)]
+    unsafe fn reduce(x: __m256i) -> __m256i {
+        _mm256_min_epu32(x, _mm256_sub_epi32(x, _mm256_set1_epi32(P as i32)))
+    }
+
+    #[inline(always)]
+    pub fn add(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: AVX2 is enabled for this build.
+        unsafe { store(reduce(_mm256_add_epi32(load(lhs), load(rhs)))) }
+    }
+
+    #[inline(always)]
+    pub fn sub(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: AVX2 is enabled for this build.
+        unsafe {
+            let diff = _mm256_sub_epi32(load(lhs), load(rhs));
+            store(_mm256_min_epu32(
+                diff,
+                _mm256_add_epi32(diff, _mm256_set1_epi32(P as i32)),
+            ))
+        }
+    }
+
+    // Montgomery-reduces the 64-bit products in each 64-bit lane of `t`,
+    // leaving the results, below 2P, in the upper 32 bits.
+    #[inline(always)]
+    unsafe fn mont_reduce(t: __m256i) -> __m256i {
+        let m = _mm256_mul_epu32(t, _mm256_set1_epi32(M as i32));
+        _mm256_add_epi64(t, _mm256_mul_epu32(m, _mm256_set1_epi32(P as i32)))
+    }
+
+    #[inline(always)]
+    pub fn mul(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: AVX2 is enabled for this build.
+        unsafe {
+            let (lhs, rhs) = (load(lhs), load(rhs));
+            // _mm256_mul_epu32 multiplies the even lanes, so shift the odd
+            // lanes down to multiply them too.
+            let even = mont_reduce(_mm256_mul_epu32(lhs, rhs));
+            let odd = mont_reduce(_mm256_mul_epu32(
+                _mm256_srli_epi64(lhs, 32),
+                _mm256_srli_epi64(rhs, 32),
+            ));
+            let prod = _mm256_blend_epi32(_mm256_srli_epi64(even, 32), odd, 0b1010_1010);
+            store(reduce(prod))
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod imp {
+    use core::arch::aarch64::*;
+
+    use super::super::{Elem, P};
+    use super::Lanes;
+
+    const M: u32 = Elem::M;
+
+    #[inline(always)]
+    unsafe fn load(x: &Lanes) -> (uint32x4_t, uint32x4_t) {
+        (vld1q_u32(x.as_ptr()), vld1q_u32(x.as_ptr().add(4)))
+    }
+
+    #[inline(always)]
+    unsafe fn store((lo, hi): (uint32x4_t, uint32x4_t)) -> Lanes {
+        let mut out = [0; super::LANES];
+        vst1q_u32(out.as_mut_ptr(), lo);
+        vst1q_u32(out.as_mut_ptr().add(4), hi);
+        out
+    }
+
+    #[inline(always)]
+    unsafe fn add4(lhs: uint32x4_t, rhs: uint32x4_t) -> uint32x4_t {
+        let sum = vaddq_u32(lhs, rhs);
+        vminq_u32(sum, vsubq_u32(sum, vdupq_n_u32(P)))
+    }
+
+    #[inline(always)]
+    unsafe fn sub4(lhs: uint32x4_t, rhs: uint32x4_t) -> uint32x4_t {
+        let diff = vsubq_u32(lhs, rhs);
+        vminq_u32(diff, vaddq_u32(diff, vdupq_n_u32(P)))
+    }
+
+    // Montgomery-reduces two 64-bit products to results below 2P.
+    #[inline(always)]
+    unsafe fn mont_reduce(t: uint64x2_t) -> uint32x2_t {
+        let m = vmul_u32(vmovn_u64(t), vdup_n_u32(M));
+        vshrn_n_u64(vmlal_u32(t, m, vdup_n_u32(P)), 32)
+    }
+
+    #[inline(always)]
+    unsafe fn mul4(lhs: uint32x4_t, rhs: uint32x4_t) -> uint32x4_t {
+        let lo = mont_reduce(vmull_u32(vget_low_u32(lhs), vget_low_u32(rhs)));
+        let hi = mont_reduce(vmull_high_u32(lhs, rhs));
+        let prod = vcombine_u32(lo, hi);
+        vminq_u32(prod, vsubq_u32(prod, vdupq_n_u32(P)))
+    }
+
+    #[inline(always)]
+    pub fn add(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: NEON is enabled for this build.
+        unsafe {
+            let ((a0, a1), (b0, b1)) = (load(lhs), load(rhs));
+            store((add4(a0, b0), add4(a1, b1)))
+        }
+    }
+
+    #[inline(always)]
+    pub fn sub(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: NEON is enabled for this build.
+        unsafe {
+            let ((a0, a1), (b0, b1)) = (load(lhs), load(rhs));
+            store((sub4(a0, b0), sub4(a1, b1)))
+        }
+    }
+
+    #[inline(always)]
+    pub fn mul(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        // SAFETY: NEON is enabled for this build.
+        unsafe {
+            let ((a0, a1), (b0, b1)) = (load(lhs), load(rhs));
+            store((mul4(a0, b0), mul4(a1, b1)))
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "avx2"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+mod imp {
+    use super::Lanes;
+
+    #[inline(always)]
+    fn lanes(lhs: &Lanes, rhs: &Lanes, f: impl Fn(u32, u32) -> u32) -> Lanes {
+        let mut out = [0; super::LANES];
+        for i in 0..super::LANES {
+            out[i] = f(lhs[i], rhs[i]);
+        }
+        out
+    }
+
+    #[inline(always)]
+    pub fn add(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        lanes(lhs, rhs, super::super::add)
+    }
+
+    #[inline(always)]
+    pub fn sub(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        lanes(lhs, rhs, super::super::sub)
+    }
+
+    #[inline(always)]
+    pub fn mul(lhs: &Lanes, rhs: &Lanes) -> Lanes {
+        lanes(lhs, rhs, super::super::mul)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::{ElemVec, LANES};
+    use crate::field::{baby_bear::Elem, Elem as FieldElem};
+
+    #[test]
+    fn matches_scalar() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        let edges = [0, 1, 2, super::super::P - 2, super::super::P - 1];
+        for round in 0..1_000 {
+            let mut a = [Elem::ZERO; LANES];
+            let mut b = [Elem::ZERO; LANES];
+            for i in 0..LANES {
+                a[i] = Elem::random(&mut rng);
+                b[i] = Elem::random(&mut rng);
+            }
+            if round < edges.len() * edges.len() {
+                a[round % LANES] = Elem::new(edges[round / edges.len()]);
+                b[round % LANES] = Elem::new(edges[round % edges.len()]);
+            }
+            let (va, vb) = (ElemVec::from(a), ElemVec::from(b));
+            let (sum, diff, prod) = (va + vb, va - vb, va * vb);
+            for i in 0..LANES {
+                assert_eq!(sum.to_array()[i], a[i] + b[i]);
+                assert_eq!(diff.to_array()[i], a[i] - b[i]);
+                assert_eq!(prod.to_array()[i], a[i] * b[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn powers() {
+        let x = Elem::new(3);
+        let powers = ElemVec::powers(x).to_array();
+        for (i, power) in powers.iter().enumerate() {
+            assert_eq!(*power, x.pow(i));
+        }
+        let mut buf = [Elem::ZERO; LANES + 1];
+        ElemVec::splat(x).store(&mut buf);
+        assert_eq!(ElemVec::load(&buf), ElemVec::splat(x));
+        assert_eq!(buf[LANES], Elem::ZERO);
+    }
+}
@@ -0,0 +1,667 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BN254 scalar field.
+//!
+//! Support for the finite field modulo the order r of the BN254 (alt_bn128)
+//! curve's groups, for wrapping STARK proofs in a SNARK over that curve and
+//! for code which must agree with such a SNARK's verifier, such as the one
+//! precompiled into Ethereum.
+
+use crate::field::{self, Elem as FieldElem};
+
+use core::{fmt, ops, str::FromStr};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The Bn254 class is an element of the finite field F_r, where r is the
+/// 254-bit prime
+/// 21888242871839275222246405745257275088548364400416034343698204186575808495617.
+///
+/// Elements are kept in Montgomery form, as four little-endian 64-bit limbs,
+/// so that products can be reduced without a division.  The multiplicative
+/// group has order divisible by 2^28, so NTTs of up to 2^28 points are
+/// supported.  The field is much slower than [BabyBear](super::baby_bear),
+/// and is only meant for the few operations a SNARK wrapper needs.
+#[derive(Eq, PartialEq, Clone, Copy, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct Elem([u64; LIMBS]);
+
+/// The number of 64-bit limbs in an element.
+const LIMBS: usize = 4;
+
+/// The modulus of the field, r, as little-endian limbs.
+const P: [u64; LIMBS] = [
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+];
+
+/// `r - 2`, the exponent which inverts an element.
+const P_MINUS_2: [u64; LIMBS] = [P[0] - 2, P[1], P[2], P[3]];
+
+/// `2^512 mod r`, which converts a canonical value into Montgomery form.
+const R2: [u64; LIMBS] = [
+    0x1bb8e645ae216da7,
+    0x53fe3ab1e35c59e3,
+    0x8c49833d53bb8085,
+    0x0216d0b17f4e44a5,
+];
+
+/// `-r^-1 mod 2^64`, used to clear the low limb in each round of a
+/// Montgomery multiplication.
+const M: u64 = 0xc2e1f593efffffff;
+
+impl Default for Elem {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl fmt::Debug for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Elem({self:#x})")
+    }
+}
+
+/// Elements display as their canonical value, in decimal or, with `{:x}` and
+/// `{:X}`, in hexadecimal.
+impl fmt::Display for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Peel off 19 decimal digits at a time, least significant first;
+        // 77 digits are enough for any element.
+        const CHUNK: u128 = 10_000_000_000_000_000_000;
+        let mut limbs = self.to_limbs();
+        let mut chunks = [0u64; 5];
+        let mut len = 0;
+        loop {
+            let mut rem = 0u128;
+            for limb in limbs.iter_mut().rev() {
+                let cur = (rem << 64) | *limb as u128;
+                *limb = (cur / CHUNK) as u64;
+                rem = cur % CHUNK;
+            }
+            chunks[len] = rem as u64;
+            len += 1;
+            if limbs == [0; LIMBS] {
+                break;
+            }
+        }
+        write!(f, "{}", chunks[len - 1])?;
+        for chunk in chunks[..len - 1].iter().rev() {
+            write!(f, "{chunk:019}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::LowerHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_hex(self, f, false)
+    }
+}
+
+impl fmt::UpperHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_hex(self, f, true)
+    }
+}
+
+fn fmt_hex(x: &Elem, f: &mut fmt::Formatter, upper: bool) -> fmt::Result {
+    if f.alternate() {
+        write!(f, "0x")?;
+    }
+    let limbs = x.to_limbs();
+    let top = limbs.iter().rposition(|limb| *limb != 0).unwrap_or(0);
+    for (i, limb) in limbs[..=top].iter().enumerate().rev() {
+        match (i == top, upper) {
+            (true, false) => write!(f, "{limb:x}")?,
+            (true, true) => write!(f, "{limb:X}")?,
+            (false, false) => write!(f, "{limb:016x}")?,
+            (false, true) => write!(f, "{limb:016X}")?,
+        }
+    }
+    Ok(())
+}
+
+/// Parses a canonical value, in decimal or `0x`-prefixed hexadecimal.  Values
+/// that are not less than r are rejected rather than reduced.
+impl FromStr for Elem {
+    type Err = field::ParseElemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => (hex, 16),
+            None => (s, 10),
+        };
+        if digits.is_empty() {
+            return Err(field::ParseElemError::Invalid);
+        }
+        let mut limbs = [0u64; LIMBS];
+        for c in digits.chars() {
+            let digit = c.to_digit(radix).ok_or(field::ParseElemError::Invalid)?;
+            // limbs = limbs * radix + digit, failing on overflow.
+            let mut carry = digit as u128;
+            for limb in limbs.iter_mut() {
+                let cur = *limb as u128 * radix as u128 + carry;
+                *limb = cur as u64;
+                carry = cur >> 64;
+            }
+            if carry != 0 {
+                return Err(field::ParseElemError::OutOfRange);
+            }
+        }
+        Elem::from_limbs_checked(limbs).ok_or(field::ParseElemError::OutOfRange)
+    }
+}
+
+/// Elements serialize as the little-endian limbs of their canonical value.
+/// Deserializing rejects values that are not less than r.
+impl Serialize for Elem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_limbs().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Elem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let limbs = <[u64; LIMBS]>::deserialize(deserializer)?;
+        Elem::from_limbs_checked(limbs).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Other("a 256-bit integer"),
+                &"an integer less than the BN254 scalar modulus",
+            )
+        })
+    }
+}
+
+/// Reduces random 256-bit values, which shrink towards zero.  Values below
+/// `2^256 mod r` are slightly more likely than the rest.
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for Elem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        proptest::arbitrary::any::<[u64; LIMBS]>()
+            .prop_map(Elem::from_limbs_reduced)
+            .boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Elem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Elem::from_limbs_reduced(arbitrary::Arbitrary::arbitrary(
+            u,
+        )?))
+    }
+}
+
+/// Overwrites the element with [Elem::ZERO](field::Elem::ZERO), whose
+/// representation is all zeros.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Elem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl field::Elem for Elem {
+    const ZERO: Self = Elem::new(0);
+
+    const ONE: Self = Elem::new(1);
+
+    /// Compute the multiplicative inverse of `x` as `x ^ (r - 2)`, which
+    /// maps zero to zero.  The exponent doesn't fit in a u64, so this uses
+    /// [FieldElem::pow_bytes].
+    fn inv(self) -> Self {
+        let mut exp = [0; 8 * LIMBS];
+        for (bytes, limb) in exp.chunks_exact_mut(8).zip(P_MINUS_2) {
+            bytes.copy_from_slice(&limb.to_le_bytes());
+        }
+        self.pow_bytes(&exp)
+    }
+
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        // r is just under 2^254, so drawing 254 bits and rejecting values of
+        // r and above takes fewer than two tries on average, and leaves every
+        // element equally likely.
+        loop {
+            let mut limbs: [u64; LIMBS] = rng.gen();
+            limbs[LIMBS - 1] >>= 2;
+            if let Some(x) = Elem::from_limbs_checked(limbs) {
+                return x;
+            }
+        }
+    }
+}
+
+impl field::TwoAdicField for Elem {
+    const TWO_ADICITY: usize = 28;
+
+    /// `5^((r - 1) / 2^28)`, as used by arkworks and gnark.
+    const TWO_ADIC_GENERATOR: Elem = Elem::from_limbs_reduced([
+        0x9bd61b6e725b19f0,
+        0x402d111e41112ed4,
+        0x00e0a7eb8ef62abc,
+        0x2a3c09f0a58a7e85,
+    ]);
+
+    #[cfg(feature = "std")]
+    fn root_of_unity(po2: usize) -> Self {
+        ROOTS.root_of_unity(po2)
+    }
+
+    #[cfg(feature = "std")]
+    fn inv_root_of_unity(po2: usize) -> Self {
+        ROOTS.inv_root_of_unity(po2)
+    }
+}
+
+#[cfg(feature = "std")]
+static ROOTS: field::RootTable<Elem> = field::RootTable::new();
+
+impl Elem {
+    /// Create a new [Elem] from a small integer.
+    pub const fn new(x: u64) -> Self {
+        Self::from_limbs_reduced([x, 0, 0, 0])
+    }
+
+    /// Create a new [Elem] from the little-endian limbs of a canonical
+    /// value, or `None` if the value is not less than r.
+    pub const fn from_limbs_checked(limbs: [u64; LIMBS]) -> Option<Self> {
+        if less_than_p(&limbs) {
+            Some(Self::from_limbs_reduced(limbs))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the little-endian limbs of the canonical value.
+    pub fn to_limbs(self) -> [u64; LIMBS] {
+        mont_mul(&self.0, &[1, 0, 0, 0])
+    }
+
+    /// Create a new [Elem] from the 32-byte big-endian encoding of a
+    /// canonical value, as used for `uint256` by the EVM, or `None` if the
+    /// value is not less than r.
+    pub fn from_bytes_be(bytes: &[u8; 32]) -> Option<Self> {
+        let mut limbs = [0u64; LIMBS];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks_exact(8)) {
+            *limb = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Self::from_limbs_checked(limbs)
+    }
+
+    /// Returns the 32-byte big-endian encoding of the canonical value; see
+    /// [Elem::from_bytes_be].
+    pub fn to_bytes_be(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.rchunks_exact_mut(8).zip(self.to_limbs()) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Returns the 32-byte little-endian encoding of the canonical value;
+    /// see [ElemBytes](field::ElemBytes).
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.to_limbs()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decode the 32-byte little-endian encoding of a canonical value, or
+    /// return `None` if the value is not less than r.
+    pub fn from_le_bytes_checked(bytes: [u8; 32]) -> Option<Self> {
+        let mut limbs = [0u64; LIMBS];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Self::from_limbs_checked(limbs)
+    }
+
+    // Converts a value below 2^256 into Montgomery form, reducing it modulo
+    // r.
+    const fn from_limbs_reduced(limbs: [u64; LIMBS]) -> Self {
+        Self(mont_mul(&limbs, &R2))
+    }
+}
+
+impl ops::Add for Elem {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Elem(add(&self.0, &rhs.0))
+    }
+}
+
+impl ops::AddAssign for Elem {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = add(&self.0, &rhs.0)
+    }
+}
+
+impl ops::Sub for Elem {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Elem(sub(&self.0, &rhs.0))
+    }
+}
+
+impl ops::SubAssign for Elem {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = sub(&self.0, &rhs.0)
+    }
+}
+
+impl ops::Mul for Elem {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Elem(mont_mul(&self.0, &rhs.0))
+    }
+}
+
+impl ops::MulAssign for Elem {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0 = mont_mul(&self.0, &rhs.0)
+    }
+}
+
+impl ops::Neg for Elem {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Elem::ZERO - self
+    }
+}
+
+impl_div_sum_product!(Elem);
+impl_elem_bytes!(Elem, 32);
+
+impl From<u32> for Elem {
+    fn from(x: u32) -> Self {
+        Elem::new(x.into())
+    }
+}
+
+impl From<u64> for Elem {
+    fn from(x: u64) -> Self {
+        Elem::new(x)
+    }
+}
+
+// Returns `a + b * c + carry` as low and high words.
+const fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let x = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (x as u64, (x >> 64) as u64)
+}
+
+const fn less_than_p(x: &[u64; LIMBS]) -> bool {
+    let mut i = LIMBS;
+    while i > 0 {
+        i -= 1;
+        if x[i] != P[i] {
+            return x[i] < P[i];
+        }
+    }
+    false
+}
+
+// Returns `x - y` and whether it borrowed.
+const fn sub_limbs(x: &[u64; LIMBS], y: &[u64; LIMBS]) -> ([u64; LIMBS], bool) {
+    let mut out = [0u64; LIMBS];
+    let mut borrow = false;
+    let mut i = 0;
+    while i < LIMBS {
+        let (d, b0) = x[i].overflowing_sub(y[i]);
+        let (d, b1) = d.overflowing_sub(borrow as u64);
+        out[i] = d;
+        borrow = b0 | b1;
+        i += 1;
+    }
+    (out, borrow)
+}
+
+// Reduce a value below 2r to below r.
+const fn reduce_once(x: [u64; LIMBS]) -> [u64; LIMBS] {
+    if less_than_p(&x) {
+        x
+    } else {
+        sub_limbs(&x, &P).0
+    }
+}
+
+fn add(x: &[u64; LIMBS], y: &[u64; LIMBS]) -> [u64; LIMBS] {
+    // r is below 2^255, so the sum of two elements can't overflow.
+    let mut out = [0u64; LIMBS];
+    let mut carry = false;
+    for i in 0..LIMBS {
+        let (s, c0) = x[i].overflowing_add(y[i]);
+        let (s, c1) = s.overflowing_add(carry as u64);
+        out[i] = s;
+        carry = c0 | c1;
+    }
+    reduce_once(out)
+}
+
+fn sub(x: &[u64; LIMBS], y: &[u64; LIMBS]) -> [u64; LIMBS] {
+    let (out, borrow) = sub_limbs(x, y);
+    if !borrow {
+        return out;
+    }
+    // Add r back; the carry out cancels the borrow.
+    let mut fixed = [0u64; LIMBS];
+    let mut carry = 0;
+    for i in 0..LIMBS {
+        let (s, c) = mac(out[i], P[i], 1, carry);
+        fixed[i] = s;
+        carry = c;
+    }
+    fixed
+}
+
+// Montgomery multiplication, returning `x * y / 2^256 mod r`, by the CIOS
+// method: each round adds `x * y[i]`, then the multiple of r which clears
+// the low limb, and shifts down a limb.  With `y` below r and `x` below
+// 2^256, the result is below 2r, and a final subtraction brings it below r.
+const fn mont_mul(x: &[u64; LIMBS], y: &[u64; LIMBS]) -> [u64; LIMBS] {
+    let mut t = [0u64; LIMBS + 2];
+    let mut i = 0;
+    while i < LIMBS {
+        let mut carry = 0;
+        let mut j = 0;
+        while j < LIMBS {
+            let (lo, hi) = mac(t[j], x[j], y[i], carry);
+            t[j] = lo;
+            carry = hi;
+            j += 1;
+        }
+        let (lo, hi) = mac(t[LIMBS], carry, 1, 0);
+        t[LIMBS] = lo;
+        t[LIMBS + 1] = hi;
+
+        let m = t[0].wrapping_mul(M);
+        let (_, mut carry) = mac(t[0], m, P[0], 0);
+        let mut j = 1;
+        while j < LIMBS {
+            let (lo, hi) = mac(t[j], m, P[j], carry);
+            t[j - 1] = lo;
+            carry = hi;
+            j += 1;
+        }
+        let (lo, hi) = mac(t[LIMBS], carry, 1, 0);
+        t[LIMBS - 1] = lo;
+        t[LIMBS] = t[LIMBS + 1] + hi;
+        i += 1;
+    }
+    reduce_once([t[0], t[1], t[2], t[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+    use super::{Elem, P};
+    use crate::field::{Elem as FieldElem, TwoAdicField};
+    use rand::SeedableRng;
+
+    // r - 1, which is -1.
+    const MINUS_ONE: &str =
+        "21888242871839275222246405745257275088548364400416034343698204186575808495616";
+
+    #[test]
+    pub fn div_sum_product() {
+        field::test::test_div_sum_product::<Elem>();
+    }
+
+    #[test]
+    pub fn batch_inv() {
+        field::test::test_batch_inv::<Elem>();
+    }
+
+    #[test]
+    pub fn field_axioms() {
+        field::test::test_field_axioms::<Elem>();
+    }
+
+    #[test]
+    pub fn elem_bytes() {
+        field::test::test_elem_bytes::<Elem>();
+    }
+
+    #[test]
+    pub fn pow_variants() {
+        field::test::test_pow::<Elem>();
+    }
+
+    #[test]
+    fn edge_cases() {
+        let minus_one = Elem::ZERO - Elem::ONE;
+        assert_eq!(minus_one * minus_one, Elem::ONE);
+        assert_eq!(minus_one + Elem::ONE, Elem::ZERO);
+        assert_eq!(-Elem::ONE, minus_one);
+        assert_eq!(Elem::from_limbs_checked(P), None);
+        assert_eq!(Elem::from_limbs_checked([u64::MAX; 4]), None);
+        assert_eq!(
+            Elem::from_limbs_checked([P[0] - 1, P[1], P[2], P[3]]),
+            Some(minus_one)
+        );
+        // 1 / 2 = (r + 1) / 2.
+        assert_eq!(
+            Elem::new(2).inv().to_limbs(),
+            [
+                0xa1f0fac9f8000001,
+                0x9419f4243cdcb848,
+                0xdc2822db40c0ac2e,
+                0x183227397098d014
+            ]
+        );
+        assert_eq!(Elem::ZERO.inv(), Elem::ZERO);
+        assert_eq!(Elem::new(u64::MAX).to_limbs(), [u64::MAX, 0, 0, 0]);
+    }
+
+    #[test]
+    fn isa_field() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let a = Elem::random(&mut rng);
+            let b = Elem::random(&mut rng);
+            let c = Elem::random(&mut rng);
+            assert_eq!(a + b, b + a);
+            assert_eq!(a * b, b * a);
+            assert_eq!(a + (b + c), (a + b) + c);
+            assert_eq!(a * (b * c), (a * b) * c);
+            assert_eq!(a * (b + c), a * b + a * c);
+            assert_eq!(a - b + b, a);
+            if a != Elem::ZERO {
+                assert_eq!(a.inv() * a, Elem::ONE);
+            }
+            assert_eq!(Elem::from_limbs_checked(a.to_limbs()), Some(a));
+        }
+    }
+
+    #[test]
+    fn roots_of_unity() {
+        // The generator has order exactly 2^28.
+        let root = Elem::root_of_unity(Elem::TWO_ADICITY);
+        assert_eq!(root, Elem::TWO_ADIC_GENERATOR);
+        assert_eq!(root.pow(1 << 27), -Elem::ONE);
+        for po2 in 0..=Elem::TWO_ADICITY {
+            let mut cur = Elem::root_of_unity(po2);
+            assert_eq!(cur * Elem::inv_root_of_unity(po2), Elem::ONE);
+            for _ in 0..po2 {
+                assert_ne!(cur, Elem::ONE);
+                cur *= cur;
+            }
+            assert_eq!(cur, Elem::ONE);
+        }
+    }
+
+    #[test]
+    fn serde() {
+        let x = -Elem::ONE;
+        let bytes = bincode::serialize(&x).unwrap();
+        assert_eq!(bincode::deserialize::<Elem>(&bytes).unwrap(), x);
+        assert!(bincode::deserialize::<Elem>(&bincode::serialize(&P).unwrap()).is_err());
+    }
+
+    #[test]
+    fn bytes_and_strings() {
+        use crate::field::ParseElemError;
+
+        let x = -Elem::ONE;
+        assert_eq!(x.to_string(), MINUS_ONE);
+        assert_eq!(
+            format!("{:#x}", x),
+            "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000"
+        );
+        assert_eq!(format!("{:X}", Elem::new(0xab)), "AB");
+        assert_eq!(Elem::ZERO.to_string(), "0");
+        assert_eq!(
+            Elem::new(10_000_000_000_000_000_000).to_string(),
+            "10000000000000000000"
+        );
+        assert_eq!(format!("{:x}", Elem::ZERO), "0");
+        assert_eq!(MINUS_ONE.parse::<Elem>(), Ok(x));
+        assert_eq!(format!("{:#x}", x).parse::<Elem>(), Ok(x));
+        assert_eq!(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+                .parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!(
+            format!("0x1{}", "0".repeat(64)).parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!("".parse::<Elem>(), Err(ParseElemError::Invalid));
+        assert_eq!("+1".parse::<Elem>(), Err(ParseElemError::Invalid));
+        assert_eq!("0x".parse::<Elem>(), Err(ParseElemError::Invalid));
+
+        let bytes = x.to_bytes_be();
+        assert_eq!(bytes[0], 0x30);
+        assert_eq!(bytes[31], 0x00);
+        assert_eq!(Elem::from_bytes_be(&bytes), Some(x));
+        assert_eq!(Elem::from_bytes_be(&[0xff; 32]), None);
+
+        let mut le = bytes;
+        le.reverse();
+        assert_eq!(x.to_le_bytes(), le);
+        assert_eq!(Elem::from_le_bytes_checked(le), Some(x));
+        assert_eq!(Elem::from_le_bytes_checked([0xff; 32]), None);
+    }
+}
@@ -0,0 +1,785 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Goldilocks field.
+//!
+//! Support for the base finite field modulo 2^64 - 2^32 + 1.
+
+use crate::field::{self, Elem as FieldElem};
+
+use core::{fmt, ops, str::FromStr};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The Goldilocks class is an element of the finite field F_p, where P is
+/// the prime number 2^64 - 2^32 + 1.
+///
+/// Elements fit in a 64-bit word, and the special form of P lets products be
+/// reduced with a few additions and subtractions instead of a division. The
+/// multiplicative group has order divisible by 2^32, so NTTs of up to 2^32
+/// points are supported. This is the field used by Plonky2 and related
+/// systems, and is provided to compare against [BabyBear](super::baby_bear).
+#[derive(Eq, PartialEq, Clone, Copy, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct Elem(u64);
+
+impl Default for Elem {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl fmt::Debug for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Elem").field(&self.0).finish()
+    }
+}
+
+/// Elements display as their canonical value, in decimal or, with `{:x}` and
+/// `{:X}`, in hexadecimal.
+impl fmt::Display for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::UpperHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+/// Parses a canonical value, in decimal or `0x`-prefixed hexadecimal.  Values
+/// that are not less than P are rejected rather than reduced.
+impl FromStr for Elem {
+    type Err = field::ParseElemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Elem::from_u64_checked(field::parse_u64(s)?).ok_or(field::ParseElemError::OutOfRange)
+    }
+}
+
+/// Elements serialize as their canonical u64 value. Deserializing rejects
+/// values that are not less than P.
+impl Serialize for Elem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Elem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = u64::deserialize(deserializer)?;
+        Elem::from_u64_checked(x).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Unsigned(x),
+                &"an integer less than the Goldilocks modulus",
+            )
+        })
+    }
+}
+
+/// Generates canonical values uniformly, shrinking towards zero.
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for Elem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        (0..P).prop_map(Elem::new).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Elem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Elem::new(u.int_in_range(0..=P - 1)?))
+    }
+}
+
+/// Overwrites the element with [Elem::ZERO](field::Elem::ZERO), whose
+/// representation is all zeros.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Elem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The modulus of the field.
+const P: u64 = 0xffff_ffff_0000_0001;
+/// `2^64 - P`, which is also `2^64 % P`.
+const EPSILON: u64 = 0xffff_ffff;
+
+impl field::Elem for Elem {
+    const ZERO: Self = Elem::new(0);
+
+    const ONE: Self = Elem::new(1);
+
+    /// Compute the multiplicative inverse of `x` as `x ^ (P - 2)`, which
+    /// maps zero to zero.  The exponent doesn't fit in a 32-bit usize, so
+    /// this uses [FieldElem::pow_u64].
+    fn inv(self) -> Self {
+        self.pow_u64(P - 2)
+    }
+
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        // Reject values of P and above, so that every element is equally
+        // likely.
+        let mut val: u64 = rng.gen();
+        while val >= P {
+            val = rng.gen();
+        }
+        Elem(val)
+    }
+}
+
+impl field::TwoAdicField for Elem {
+    const TWO_ADICITY: usize = 32;
+
+    const TWO_ADIC_GENERATOR: Elem = Elem::new(1753635133440165772);
+
+    #[cfg(feature = "std")]
+    fn root_of_unity(po2: usize) -> Self {
+        ROOTS.root_of_unity(po2)
+    }
+
+    #[cfg(feature = "std")]
+    fn inv_root_of_unity(po2: usize) -> Self {
+        ROOTS.inv_root_of_unity(po2)
+    }
+}
+
+#[cfg(feature = "std")]
+static ROOTS: field::RootTable<Elem> = field::RootTable::new();
+
+impl Elem {
+    /// Create a new [Elem] from a raw integer.
+    pub const fn new(x: u64) -> Self {
+        Self(x % P)
+    }
+
+    /// Create a new [Elem] from a canonical value, or `None` if `x` is not
+    /// less than P.  Prefer this to [Elem::new], which silently reduces, for
+    /// untrusted input.
+    pub const fn from_u64_checked(x: u64) -> Option<Self> {
+        if x < P {
+            Some(Self(x))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the little-endian encoding of the canonical value; see
+    /// [ElemBytes](field::ElemBytes).
+    pub const fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Decode the little-endian encoding of a canonical value, or return
+    /// `None` if the value is not less than P.
+    pub const fn from_le_bytes_checked(bytes: [u8; 8]) -> Option<Self> {
+        Self::from_u64_checked(u64::from_le_bytes(bytes))
+    }
+}
+
+impl ops::Add for Elem {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Elem(add(self.0, rhs.0))
+    }
+}
+
+impl ops::AddAssign for Elem {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = add(self.0, rhs.0)
+    }
+}
+
+impl ops::Sub for Elem {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Elem(sub(self.0, rhs.0))
+    }
+}
+
+impl ops::SubAssign for Elem {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = sub(self.0, rhs.0)
+    }
+}
+
+impl ops::Mul for Elem {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Elem(mul(self.0, rhs.0))
+    }
+}
+
+impl ops::MulAssign for Elem {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0 = mul(self.0, rhs.0)
+    }
+}
+
+impl ops::Neg for Elem {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Elem(0) - self
+    }
+}
+
+impl_div_sum_product!(Elem);
+impl_elem_bytes!(Elem, 8);
+
+impl From<Elem> for u64 {
+    fn from(x: Elem) -> Self {
+        x.0
+    }
+}
+
+impl From<&Elem> for u64 {
+    fn from(x: &Elem) -> Self {
+        x.0
+    }
+}
+
+impl From<u32> for Elem {
+    fn from(x: u32) -> Self {
+        Elem(x.into())
+    }
+}
+
+impl From<u64> for Elem {
+    fn from(x: u64) -> Self {
+        Elem::new(x)
+    }
+}
+
+fn add(lhs: u64, rhs: u64) -> u64 {
+    // An overflow wraps by 2^64, which is EPSILON modulo P.  With both inputs
+    // below P, adding EPSILON back can't overflow again.
+    let (x, overflow) = lhs.overflowing_add(rhs);
+    let x = if overflow { x + EPSILON } else { x };
+    if x >= P {
+        x - P
+    } else {
+        x
+    }
+}
+
+fn sub(lhs: u64, rhs: u64) -> u64 {
+    let (x, underflow) = lhs.overflowing_sub(rhs);
+    if underflow {
+        x - EPSILON
+    } else {
+        x
+    }
+}
+
+fn mul(lhs: u64, rhs: u64) -> u64 {
+    reduce((lhs as u128) * (rhs as u128))
+}
+
+// Reduce a 128-bit value modulo P.  Writing `x = lo + 2^64 * (hi_lo + 2^32 *
+// hi_hi)`, and using `2^64 = EPSILON` and `2^96 = -1` modulo P, gives
+// `x = lo - hi_hi + EPSILON * hi_lo`.
+fn reduce(x: u128) -> u64 {
+    let lo = x as u64;
+    let hi = (x >> 64) as u64;
+    let hi_hi = hi >> 32;
+    let hi_lo = hi & EPSILON;
+
+    let (t0, underflow) = lo.overflowing_sub(hi_hi);
+    let t0 = if underflow {
+        t0.wrapping_sub(EPSILON)
+    } else {
+        t0
+    };
+    let t1 = hi_lo * EPSILON;
+    let (t2, overflow) = t0.overflowing_add(t1);
+    let t2 = if overflow {
+        t2.wrapping_add(EPSILON)
+    } else {
+        t2
+    };
+    if t2 >= P {
+        t2 - P
+    } else {
+        t2
+    }
+}
+
+/// The size of the extension field in elements, 2 in this case.
+const EXT_SIZE: usize = 2;
+
+/// Instances of `ExtElem` are elements of a finite field `F_p^2`. They are
+/// represented as elements of `F_p[X] / (X^2 - 7)`, where `7` is the smallest
+/// quadratic non-residue, so the field has about `2^128` elements. This is the
+/// same extension Plonky2 uses.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Pod, Zeroable, Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct ExtElem([Elem; EXT_SIZE]);
+
+const W: Elem = Elem::new(7);
+
+impl Default for ExtElem {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl field::Elem for ExtElem {
+    const ZERO: ExtElem = ExtElem([Elem::ZERO, Elem::ZERO]);
+    const ONE: ExtElem = ExtElem([Elem::ONE, Elem::ZERO]);
+
+    /// Generate a random field element uniformly.
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        Self([Elem::random(rng), Elem::random(rng)])
+    }
+
+    /// Compute the multiplicative inverse of an `ExtElem` by multiplying
+    /// through by the conjugate: `1 / (a0 + a1 X) = (a0 - a1 X) / (a0^2 - W
+    /// a1^2)`, where the denominator is in the subfield.  As with `Elem`,
+    /// zero maps to zero.
+    fn inv(self) -> Self {
+        self.frobenius() * self.norm().inv()
+    }
+}
+
+impl field::ExtElem for ExtElem {
+    const EXT_SIZE: usize = EXT_SIZE;
+
+    type SubElem = Elem;
+
+    fn from_subfield(elem: &Elem) -> Self {
+        Self::from(*elem)
+    }
+}
+
+impl ExtElem {
+    /// Explicitly construct an ExtElem from parts.
+    pub fn new(x0: Elem, x1: Elem) -> Self {
+        Self([x0, x1])
+    }
+
+    /// Returns the elements of a [ExtElem].
+    pub fn elems(&self) -> &[Elem] {
+        &self.0
+    }
+
+    /// Returns the encodings of the components, in order; see
+    /// [Elem::to_le_bytes].
+    pub fn to_le_bytes(self) -> [u8; 16] {
+        let mut out = [0; 16];
+        for (chunk, elem) in out.chunks_exact_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&elem.to_le_bytes());
+        }
+        out
+    }
+
+    /// Decode the encoding written by [ExtElem::to_le_bytes], or return
+    /// `None` if any component is not less than P.
+    pub fn from_le_bytes_checked(bytes: [u8; 16]) -> Option<Self> {
+        let mut elems = [Elem::ZERO; EXT_SIZE];
+        for (elem, chunk) in elems.iter_mut().zip(bytes.chunks_exact(8)) {
+            *elem = Elem::from_le_bytes_checked(chunk.try_into().unwrap())?;
+        }
+        Some(ExtElem(elems))
+    }
+
+    /// Returns `x^P`, the image of `x` under the Frobenius automorphism,
+    /// which fixes exactly the elements of the base field.  Since `W` is a
+    /// quadratic non-residue, `X^P = -X`, so this is the conjugate
+    /// `a0 - a1 X`.
+    pub fn frobenius(self) -> Self {
+        let [a0, a1] = self.0;
+        ExtElem([a0, -a1])
+    }
+
+    /// Returns the conjugates of `x`, `x` itself followed by
+    /// [ExtElem::frobenius].
+    pub fn conjugates(self) -> [Self; EXT_SIZE] {
+        [self, self.frobenius()]
+    }
+
+    /// Returns the norm of `x`, the product of its conjugates
+    /// `a0^2 - W a1^2`, which is zero only for zero.
+    pub fn norm(self) -> Elem {
+        let [a0, a1] = self.0;
+        a0 * a0 - W * a1 * a1
+    }
+
+    /// Returns the trace of `x`, the sum of its conjugates `2 a0`.
+    pub fn trace(self) -> Elem {
+        self.0[0] + self.0[0]
+    }
+}
+
+impl ops::Add for ExtElem {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let mut lhs = self;
+        lhs += rhs;
+        lhs
+    }
+}
+
+impl ops::AddAssign for ExtElem {
+    fn add_assign(&mut self, rhs: Self) {
+        for i in 0..self.0.len() {
+            self.0[i] += rhs.0[i];
+        }
+    }
+}
+
+impl ops::Sub for ExtElem {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        let mut lhs = self;
+        lhs -= rhs;
+        lhs
+    }
+}
+
+impl ops::SubAssign for ExtElem {
+    fn sub_assign(&mut self, rhs: Self) {
+        for i in 0..self.0.len() {
+            self.0[i] -= rhs.0[i];
+        }
+    }
+}
+
+/// Implement the simple multiplication case by the subfield Elem.
+impl ops::MulAssign<Elem> for ExtElem {
+    fn mul_assign(&mut self, rhs: Elem) {
+        for i in 0..self.0.len() {
+            self.0[i] *= rhs;
+        }
+    }
+}
+
+impl ops::Mul<Elem> for ExtElem {
+    type Output = Self;
+    fn mul(self, rhs: Elem) -> Self {
+        let mut lhs = self;
+        lhs *= rhs;
+        lhs
+    }
+}
+
+impl ops::Mul<ExtElem> for Elem {
+    type Output = ExtElem;
+    fn mul(self, rhs: ExtElem) -> ExtElem {
+        rhs * self
+    }
+}
+
+// Multiply out the polynomials and reduce with `X^2 = W`.
+impl ops::MulAssign for ExtElem {
+    fn mul_assign(&mut self, rhs: Self) {
+        let [a0, a1] = self.0;
+        let [b0, b1] = rhs.0;
+        self.0 = [a0 * b0 + W * (a1 * b1), a0 * b1 + a1 * b0];
+    }
+}
+
+impl ops::Mul for ExtElem {
+    type Output = ExtElem;
+    fn mul(self, rhs: ExtElem) -> ExtElem {
+        let mut lhs = self;
+        lhs *= rhs;
+        lhs
+    }
+}
+
+impl ops::Neg for ExtElem {
+    type Output = Self;
+    fn neg(self) -> Self {
+        ExtElem::ZERO - self
+    }
+}
+
+impl_div_sum_product!(ExtElem);
+impl_elem_bytes!(ExtElem, 16);
+
+impl From<[Elem; EXT_SIZE]> for ExtElem {
+    fn from(val: [Elem; EXT_SIZE]) -> Self {
+        ExtElem(val)
+    }
+}
+
+/// Generates each component independently; see the impl for [Elem].
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for ExtElem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::{arbitrary::any, strategy::Strategy};
+        any::<[Elem; EXT_SIZE]>().prop_map(ExtElem).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ExtElem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(ExtElem(arbitrary::Arbitrary::arbitrary(u)?))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ExtElem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl From<Elem> for ExtElem {
+    fn from(x: Elem) -> Self {
+        Self([x, Elem::ZERO])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+    use super::{reduce, Elem, ExtElem, EPSILON, P};
+    use crate::field::{Elem as FieldElem, ExtElem as _};
+    use rand::SeedableRng;
+
+    macro_rules! rou_array {
+        [$($x:literal),* $(,)?] => {
+            [$(Elem::new($x)),* ]
+        }
+    }
+
+    // Known-good roots of unity, indexed by po2.
+    const ROU_FWD: [Elem; 33] = rou_array![
+        1,
+        18446744069414584320,
+        281474976710656,
+        18446744069397807105,
+        17293822564807737345,
+        70368744161280,
+        549755813888,
+        17870292113338400769,
+        13797081185216407910,
+        1803076106186727246,
+        11353340290879379826,
+        455906449640507599,
+        17492915097719143606,
+        1532612707718625687,
+        16207902636198568418,
+        17776499369601055404,
+        6115771955107415310,
+        12380578893860276750,
+        9306717745644682924,
+        18146160046829613826,
+        3511170319078647661,
+        17654865857378133588,
+        5416168637041100469,
+        16905767614792059275,
+        9713644485405565297,
+        5456943929260765144,
+        17096174751763063430,
+        1213594585890690845,
+        6414415596519834757,
+        16116352524544190054,
+        9123114210336311365,
+        4614640910117430873,
+        1753635133440165772
+    ];
+
+    const ROU_REV: [Elem; 33] = rou_array![
+        1,
+        18446744069414584320,
+        18446462594437873665,
+        1099511627520,
+        68719476736,
+        18446744069414322177,
+        18302628881338728449,
+        18442240469787213841,
+        2117504431143841456,
+        4459017075746761332,
+        4295002282146690441,
+        8548973421900915981,
+        11164456749895610016,
+        3968367389790187850,
+        4654242210262998966,
+        1553425662128427817,
+        7868944258580147481,
+        14744321562856667967,
+        2513567076326282710,
+        5089696809409609209,
+        17260140776825220475,
+        11898519751787946856,
+        15307271466853436433,
+        5456584715443070302,
+        1219213613525454263,
+        13843946492009319323,
+        16884827967813875098,
+        10516896061424301529,
+        4514835231089717636,
+        16488041148801377373,
+        16303955383020744715,
+        10790884855407511297,
+        8554224884056360729
+    ];
+
+    #[test]
+    pub fn roots_of_unity() {
+        field::test::test_roots_of_unity::<Elem>(&ROU_FWD, &ROU_REV);
+    }
+
+    #[test]
+    pub fn field_ops() {
+        field::test::test_field_ops::<Elem>(P);
+    }
+
+    #[test]
+    pub fn div_sum_product() {
+        field::test::test_div_sum_product::<Elem>();
+        field::test::test_div_sum_product::<ExtElem>();
+    }
+
+    #[test]
+    pub fn batch_inv() {
+        field::test::test_batch_inv::<Elem>();
+        field::test::test_batch_inv::<ExtElem>();
+    }
+
+    #[test]
+    pub fn field_axioms() {
+        field::test::test_field_axioms::<Elem>();
+        field::test::test_field_axioms::<ExtElem>();
+    }
+
+    #[test]
+    pub fn elem_bytes() {
+        field::test::test_elem_bytes::<Elem>();
+        field::test::test_elem_bytes::<ExtElem>();
+    }
+
+    #[test]
+    pub fn pow_variants() {
+        field::test::test_pow::<Elem>();
+        field::test::test_pow::<ExtElem>();
+    }
+
+    #[test]
+    fn edge_cases() {
+        let max = Elem::new(P - 1);
+        assert_eq!(max + max, Elem::new(P - 2));
+        assert_eq!(max * max, Elem::ONE);
+        assert_eq!(Elem::ZERO - Elem::ONE, max);
+        assert_eq!(Elem::from(u64::MAX), Elem::new(EPSILON - 1));
+        // The largest product of two elements, and values straddling P.
+        let big = (P - 1) as u128;
+        assert_eq!(reduce(big * big) as u128, (big * big) % P as u128);
+        assert_eq!(reduce(P as u128), 0);
+        assert_eq!(reduce(u128::MAX) as u128, u128::MAX % P as u128);
+    }
+
+    #[test]
+    fn isa_field() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let a = ExtElem::random(&mut rng);
+            let b = ExtElem::random(&mut rng);
+            let c = ExtElem::random(&mut rng);
+            assert_eq!(a + b, b + a);
+            assert_eq!(a * b, b * a);
+            assert_eq!(a + (b + c), (a + b) + c);
+            assert_eq!(a * (b * c), (a * b) * c);
+            assert_eq!(a * (b + c), a * b + a * c);
+            if a != ExtElem::ZERO {
+                assert_eq!(a.inv() * a, ExtElem::ONE);
+            }
+            assert_eq!(a + (-a), ExtElem::ZERO);
+            let x = Elem::random(&mut rng);
+            assert_eq!(a * x, a * ExtElem::from_subfield(&x));
+        }
+        assert_eq!(ExtElem::ZERO.inv(), ExtElem::ZERO);
+    }
+
+    #[test]
+    fn ext_frobenius() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let x = ExtElem::random(&mut rng);
+            let y = ExtElem::random(&mut rng);
+            assert_eq!(x.frobenius(), x.pow(P as usize));
+            assert_eq!(x.frobenius().frobenius(), x);
+            let [c0, c1] = x.conjugates();
+            assert_eq!(ExtElem::from_subfield(&x.norm()), c0 * c1);
+            assert_eq!(ExtElem::from_subfield(&x.trace()), c0 + c1);
+            assert_eq!((x * y).norm(), x.norm() * y.norm());
+            assert_eq!((x + y).trace(), x.trace() + y.trace());
+        }
+        // The base field is fixed.
+        let a = ExtElem::from_subfield(&Elem::new(12345));
+        assert_eq!(a.frobenius(), a);
+        assert_eq!(a.norm(), Elem::new(12345 * 12345));
+        assert_eq!(a.trace(), Elem::new(2 * 12345));
+        let x = ExtElem::new(Elem::ZERO, Elem::ONE);
+        assert_eq!(x.norm(), -Elem::new(7));
+        assert_eq!(ExtElem::ZERO.norm(), Elem::ZERO);
+    }
+
+    #[test]
+    fn serde() {
+        let x = Elem::new(P - 1);
+        let bytes = bincode::serialize(&x).unwrap();
+        assert_eq!(bytes, (P - 1).to_le_bytes());
+        assert_eq!(bincode::deserialize::<Elem>(&bytes).unwrap(), x);
+        let a = ExtElem::new(x, Elem::new(3));
+        assert_eq!(
+            bincode::deserialize::<ExtElem>(&bincode::serialize(&a).unwrap()).unwrap(),
+            a
+        );
+        assert!(bincode::deserialize::<Elem>(&P.to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn checked_and_strings() {
+        use crate::field::ParseElemError;
+
+        assert_eq!(Elem::from_u64_checked(P - 1), Some(Elem::new(P - 1)));
+        assert_eq!(Elem::from_u64_checked(P), None);
+
+        let x = Elem::new(P - 1);
+        assert_eq!(x.to_string(), "18446744069414584320");
+        assert_eq!(format!("{:#x}", x), "0xffffffff00000000");
+        assert_eq!("0xffffffff00000000".parse(), Ok(x));
+        assert_eq!("18446744069414584320".parse(), Ok(x));
+        assert_eq!(
+            "18446744069414584321".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!(
+            "0x10000000000000000".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!("x".parse::<Elem>(), Err(ParseElemError::Invalid));
+    }
+}
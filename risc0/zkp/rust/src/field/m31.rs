@@ -0,0 +1,652 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mersenne-31 field.
+//!
+//! Support for the base finite field modulo 2^31 - 1, its degree-4
+//! extension, and FFTs over the circle group; see [circle].
+
+pub mod circle;
+
+use crate::field::{self, Elem as FieldElem};
+
+use core::{fmt, ops, str::FromStr};
+
+use bytemuck::{Pod, Zeroable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The M31 class is an element of the finite field F_p, where P is the
+/// Mersenne prime 2^31 - 1.
+///
+/// Elements fit in a 32-bit word, and since `2^31 = 1` modulo P, products
+/// are reduced by folding the high bits onto the low ones.  The
+/// multiplicative group only has a subgroup of order 2, so there are no NTTs
+/// and this field doesn't implement [TwoAdicField](field::TwoAdicField);
+/// the circle group, of order 2^31, provides FFT domains instead.  This is
+/// the field used by Circle STARKs, and is provided to experiment with them.
+#[derive(Eq, PartialEq, Clone, Copy, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct Elem(u32);
+
+impl Default for Elem {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl fmt::Debug for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Elem").field(&self.0).finish()
+    }
+}
+
+/// Elements display as their canonical value, in decimal or, with `{:x}` and
+/// `{:X}`, in hexadecimal.
+impl fmt::Display for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::UpperHex for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+/// Parses a canonical value, in decimal or `0x`-prefixed hexadecimal.  Values
+/// that are not less than P are rejected rather than reduced.
+impl FromStr for Elem {
+    type Err = field::ParseElemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let x = field::parse_u64(s)?;
+        u32::try_from(x)
+            .ok()
+            .and_then(Elem::from_u32_checked)
+            .ok_or(field::ParseElemError::OutOfRange)
+    }
+}
+
+/// Elements serialize as their canonical u32 value. Deserializing rejects
+/// values that are not less than P.
+impl Serialize for Elem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Elem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = u32::deserialize(deserializer)?;
+        Elem::from_u32_checked(x).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Unsigned(x.into()),
+                &"an integer less than the Mersenne-31 modulus",
+            )
+        })
+    }
+}
+
+/// Generates canonical values uniformly, shrinking towards zero.
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for Elem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        (0..P).prop_map(Elem::new).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Elem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Elem::new(u.int_in_range(0..=P - 1)?))
+    }
+}
+
+/// Overwrites the element with [Elem::ZERO](field::Elem::ZERO), whose
+/// representation is all zeros.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Elem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The modulus of the field.
+const P: u32 = (1 << 31) - 1;
+
+impl field::Elem for Elem {
+    const ZERO: Self = Elem::new(0);
+
+    const ONE: Self = Elem::new(1);
+
+    /// Compute the multiplicative inverse of `x` as `x ^ (P - 2)`, which
+    /// maps zero to zero.
+    fn inv(self) -> Self {
+        self.pow_u64((P - 2) as u64)
+    }
+
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        // Reject P itself, so that every element is equally likely.
+        let mut val: u32 = rng.gen::<u32>() & P;
+        while val == P {
+            val = rng.gen::<u32>() & P;
+        }
+        Elem(val)
+    }
+}
+
+impl Elem {
+    /// Create a new [Elem] from a raw integer.
+    pub const fn new(x: u32) -> Self {
+        Self(x % P)
+    }
+
+    /// Create a new [Elem] from a canonical value, or `None` if `x` is not
+    /// less than P.  Prefer this to [Elem::new], which silently reduces, for
+    /// untrusted input.
+    pub const fn from_u32_checked(x: u32) -> Option<Self> {
+        if x < P {
+            Some(Self(x))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the little-endian encoding of the canonical value; see
+    /// [ElemBytes](field::ElemBytes).
+    pub const fn to_le_bytes(self) -> [u8; 4] {
+        self.0.to_le_bytes()
+    }
+
+    /// Decode the little-endian encoding of a canonical value, or return
+    /// `None` if the value is not less than P.
+    pub const fn from_le_bytes_checked(bytes: [u8; 4]) -> Option<Self> {
+        Self::from_u32_checked(u32::from_le_bytes(bytes))
+    }
+}
+
+impl ops::Add for Elem {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Elem(add(self.0, rhs.0))
+    }
+}
+
+impl ops::AddAssign for Elem {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = add(self.0, rhs.0)
+    }
+}
+
+impl ops::Sub for Elem {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Elem(sub(self.0, rhs.0))
+    }
+}
+
+impl ops::SubAssign for Elem {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = sub(self.0, rhs.0)
+    }
+}
+
+impl ops::Mul for Elem {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Elem(mul(self.0, rhs.0))
+    }
+}
+
+impl ops::MulAssign for Elem {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0 = mul(self.0, rhs.0)
+    }
+}
+
+impl ops::Neg for Elem {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Elem(0) - self
+    }
+}
+
+impl_div_sum_product!(Elem);
+impl_elem_bytes!(Elem, 4);
+
+impl From<Elem> for u32 {
+    fn from(x: Elem) -> Self {
+        x.0
+    }
+}
+
+impl From<Elem> for u64 {
+    fn from(x: Elem) -> Self {
+        x.0.into()
+    }
+}
+
+impl From<u32> for Elem {
+    fn from(x: u32) -> Self {
+        Elem::new(x)
+    }
+}
+
+impl From<u64> for Elem {
+    fn from(x: u64) -> Self {
+        Elem((x % P as u64) as u32)
+    }
+}
+
+fn add(lhs: u32, rhs: u32) -> u32 {
+    // Both inputs are below 2^31, so the sum can't overflow.
+    let x = lhs + rhs;
+    if x >= P {
+        x - P
+    } else {
+        x
+    }
+}
+
+fn sub(lhs: u32, rhs: u32) -> u32 {
+    if lhs >= rhs {
+        lhs - rhs
+    } else {
+        lhs + P - rhs
+    }
+}
+
+fn mul(lhs: u32, rhs: u32) -> u32 {
+    reduce(lhs as u64 * rhs as u64)
+}
+
+// Reduce a product of two elements modulo P.  Since `2^31 = 1` modulo P,
+// adding the bits above 2^31 to the bits below leaves the value unchanged;
+// doing so twice brings a 62-bit product to at most P + 1.
+fn reduce(x: u64) -> u32 {
+    let x = (x & P as u64) + (x >> 31);
+    let x = ((x & P as u64) + (x >> 31)) as u32;
+    if x >= P {
+        x - P
+    } else {
+        x
+    }
+}
+
+/// The size of the extension field in elements, 4 in this case.
+const EXT_SIZE: usize = 4;
+
+/// Instances of `ExtElem` are elements of a finite field `F_p^4`, built as a
+/// tower: `F_p^2 = F_p[i] / (i^2 + 1)`, since -1 is a non-residue, and then
+/// `F_p^4 = F_p^2[u] / (u^2 - 2 - i)`.  The field has about `2^124`
+/// elements.  The coefficients are `[a0, a1, a2, a3]` for
+/// `(a0 + a1 i) + (a2 + a3 i) u`.  This is the same extension Stwo uses.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Pod, Zeroable, Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct ExtElem([Elem; EXT_SIZE]);
+
+// Arithmetic in the intermediate field F_p^2, on `a + b i` as `(a, b)`.
+type Complex = (Elem, Elem);
+
+fn complex_mul((a, b): Complex, (c, d): Complex) -> Complex {
+    (a * c - b * d, a * d + b * c)
+}
+
+fn complex_inv((a, b): Complex) -> Complex {
+    let norm_inv = (a * a + b * b).inv();
+    (a * norm_inv, -b * norm_inv)
+}
+
+// `u^2`, which is `2 + i`.
+const R: Complex = (Elem::new(2), Elem::new(1));
+
+impl Default for ExtElem {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl field::Elem for ExtElem {
+    const ZERO: ExtElem = ExtElem([Elem::ZERO; EXT_SIZE]);
+    const ONE: ExtElem = ExtElem([Elem::ONE, Elem::ZERO, Elem::ZERO, Elem::ZERO]);
+
+    /// Generate a random field element uniformly.
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        Self([
+            Elem::random(rng),
+            Elem::random(rng),
+            Elem::random(rng),
+            Elem::random(rng),
+        ])
+    }
+
+    /// Compute the multiplicative inverse of an `ExtElem` by multiplying
+    /// through by the conjugate: `1 / (x + y u) = (x - y u) / (x^2 - R
+    /// y^2)`, where the denominator is in `F_p^2`.  As with `Elem`, zero maps
+    /// to zero.
+    fn inv(self) -> Self {
+        let [a0, a1, a2, a3] = self.0;
+        let (x, y) = ((a0, a1), (a2, a3));
+        let (xx, yy) = (complex_mul(x, x), complex_mul(R, complex_mul(y, y)));
+        let denom_inv = complex_inv((xx.0 - yy.0, xx.1 - yy.1));
+        let (b0, b1) = complex_mul(x, denom_inv);
+        let (b2, b3) = complex_mul(y, denom_inv);
+        ExtElem([b0, b1, -b2, -b3])
+    }
+}
+
+impl field::ExtElem for ExtElem {
+    const EXT_SIZE: usize = EXT_SIZE;
+
+    type SubElem = Elem;
+
+    fn from_subfield(elem: &Elem) -> Self {
+        Self::from(*elem)
+    }
+}
+
+impl ExtElem {
+    /// Explicitly construct an ExtElem from parts.
+    pub fn new(a0: Elem, a1: Elem, a2: Elem, a3: Elem) -> Self {
+        Self([a0, a1, a2, a3])
+    }
+
+    /// Returns the elements of a [ExtElem].
+    pub fn elems(&self) -> &[Elem] {
+        &self.0
+    }
+
+    /// Returns the encodings of the components, in order; see
+    /// [Elem::to_le_bytes].
+    pub fn to_le_bytes(self) -> [u8; 16] {
+        let mut out = [0; 16];
+        for (chunk, elem) in out.chunks_exact_mut(4).zip(self.0) {
+            chunk.copy_from_slice(&elem.to_le_bytes());
+        }
+        out
+    }
+
+    /// Decode the encoding written by [ExtElem::to_le_bytes], or return
+    /// `None` if any component is not less than P.
+    pub fn from_le_bytes_checked(bytes: [u8; 16]) -> Option<Self> {
+        let mut elems = [Elem::ZERO; EXT_SIZE];
+        for (elem, chunk) in elems.iter_mut().zip(bytes.chunks_exact(4)) {
+            *elem = Elem::from_le_bytes_checked(chunk.try_into().unwrap())?;
+        }
+        Some(ExtElem(elems))
+    }
+}
+
+impl ops::Add for ExtElem {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let mut lhs = self;
+        lhs += rhs;
+        lhs
+    }
+}
+
+impl ops::AddAssign for ExtElem {
+    fn add_assign(&mut self, rhs: Self) {
+        for i in 0..self.0.len() {
+            self.0[i] += rhs.0[i];
+        }
+    }
+}
+
+impl ops::Sub for ExtElem {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        let mut lhs = self;
+        lhs -= rhs;
+        lhs
+    }
+}
+
+impl ops::SubAssign for ExtElem {
+    fn sub_assign(&mut self, rhs: Self) {
+        for i in 0..self.0.len() {
+            self.0[i] -= rhs.0[i];
+        }
+    }
+}
+
+/// Implement the simple multiplication case by the subfield Elem.
+impl ops::MulAssign<Elem> for ExtElem {
+    fn mul_assign(&mut self, rhs: Elem) {
+        for i in 0..self.0.len() {
+            self.0[i] *= rhs;
+        }
+    }
+}
+
+impl ops::Mul<Elem> for ExtElem {
+    type Output = Self;
+    fn mul(self, rhs: Elem) -> Self {
+        let mut lhs = self;
+        lhs *= rhs;
+        lhs
+    }
+}
+
+impl ops::Mul<ExtElem> for Elem {
+    type Output = ExtElem;
+    fn mul(self, rhs: ExtElem) -> ExtElem {
+        rhs * self
+    }
+}
+
+// Multiply out `(x + y u) (z + w u)` in `F_p^2` and reduce with `u^2 = R`.
+impl ops::MulAssign for ExtElem {
+    fn mul_assign(&mut self, rhs: Self) {
+        let [a0, a1, a2, a3] = self.0;
+        let [b0, b1, b2, b3] = rhs.0;
+        let (x, y, z, w) = ((a0, a1), (a2, a3), (b0, b1), (b2, b3));
+        let (xz, yw) = (complex_mul(x, z), complex_mul(R, complex_mul(y, w)));
+        let (xw, yz) = (complex_mul(x, w), complex_mul(y, z));
+        self.0 = [xz.0 + yw.0, xz.1 + yw.1, xw.0 + yz.0, xw.1 + yz.1];
+    }
+}
+
+impl ops::Mul for ExtElem {
+    type Output = ExtElem;
+    fn mul(self, rhs: ExtElem) -> ExtElem {
+        let mut lhs = self;
+        lhs *= rhs;
+        lhs
+    }
+}
+
+impl ops::Neg for ExtElem {
+    type Output = Self;
+    fn neg(self) -> Self {
+        ExtElem::ZERO - self
+    }
+}
+
+impl_div_sum_product!(ExtElem);
+impl_elem_bytes!(ExtElem, 16);
+
+impl From<[Elem; EXT_SIZE]> for ExtElem {
+    fn from(val: [Elem; EXT_SIZE]) -> Self {
+        ExtElem(val)
+    }
+}
+
+/// Generates each component independently; see the impl for [Elem].
+#[cfg(any(test, feature = "proptest"))]
+impl proptest::arbitrary::Arbitrary for ExtElem {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::{arbitrary::any, strategy::Strategy};
+        any::<[Elem; EXT_SIZE]>().prop_map(ExtElem).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ExtElem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(ExtElem(arbitrary::Arbitrary::arbitrary(u)?))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ExtElem {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl From<Elem> for ExtElem {
+    fn from(x: Elem) -> Self {
+        Self([x, Elem::ZERO, Elem::ZERO, Elem::ZERO])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+    use super::{reduce, Elem, ExtElem, P};
+    use crate::field::{Elem as FieldElem, ExtElem as _};
+    use rand::SeedableRng;
+
+    #[test]
+    pub fn field_ops() {
+        field::test::test_field_ops::<Elem>(P as u64);
+    }
+
+    #[test]
+    pub fn div_sum_product() {
+        field::test::test_div_sum_product::<Elem>();
+        field::test::test_div_sum_product::<ExtElem>();
+    }
+
+    #[test]
+    pub fn batch_inv() {
+        field::test::test_batch_inv::<Elem>();
+        field::test::test_batch_inv::<ExtElem>();
+    }
+
+    #[test]
+    pub fn field_axioms() {
+        field::test::test_field_axioms::<Elem>();
+        field::test::test_field_axioms::<ExtElem>();
+    }
+
+    #[test]
+    pub fn elem_bytes() {
+        field::test::test_elem_bytes::<Elem>();
+        field::test::test_elem_bytes::<ExtElem>();
+    }
+
+    #[test]
+    pub fn pow_variants() {
+        field::test::test_pow::<Elem>();
+        field::test::test_pow::<ExtElem>();
+    }
+
+    #[test]
+    fn edge_cases() {
+        // The largest product of two elements, and values straddling P.
+        let big = (P - 1) as u64;
+        assert_eq!(reduce(big * big) as u64, (big * big) % P as u64);
+        assert_eq!(reduce(P as u64), 0);
+        assert_eq!(reduce(P as u64 + 1), 1);
+        assert_eq!(Elem::new(P - 1) + Elem::new(P - 1), Elem::new(P - 2));
+        assert_eq!(Elem::new(0) - Elem::new(1), Elem::new(P - 1));
+        // -1 is not a square, so F_p^2 is a field.
+        assert_eq!(
+            Elem::new(P - 1).pow(((P - 1) / 2) as usize),
+            Elem::new(P - 1)
+        );
+    }
+
+    #[test]
+    fn isa_field() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let a = ExtElem::random(&mut rng);
+            let b = ExtElem::random(&mut rng);
+            let c = ExtElem::random(&mut rng);
+            assert_eq!(a + b, b + a);
+            assert_eq!(a * b, b * a);
+            assert_eq!(a + (b + c), (a + b) + c);
+            assert_eq!(a * (b * c), (a * b) * c);
+            assert_eq!(a * (b + c), a * b + a * c);
+            if a != ExtElem::ZERO {
+                assert_eq!(a.inv() * a, ExtElem::ONE);
+            }
+            assert_eq!(a + (-a), ExtElem::ZERO);
+            let x = Elem::random(&mut rng);
+            assert_eq!(a * x, a * ExtElem::from_subfield(&x));
+        }
+        assert_eq!(ExtElem::ZERO.inv(), ExtElem::ZERO);
+
+        // u^2 = 2 + i, and i^2 = -1.
+        let i = ExtElem::new(Elem::ZERO, Elem::ONE, Elem::ZERO, Elem::ZERO);
+        let u = ExtElem::new(Elem::ZERO, Elem::ZERO, Elem::ONE, Elem::ZERO);
+        assert_eq!(i * i, -ExtElem::ONE);
+        assert_eq!(
+            u * u,
+            ExtElem::new(Elem::new(2), Elem::ONE, Elem::ZERO, Elem::ZERO)
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let x = Elem::new(P - 1);
+        let bytes = bincode::serialize(&x).unwrap();
+        assert_eq!(bytes, (P - 1).to_le_bytes());
+        assert_eq!(bincode::deserialize::<Elem>(&bytes).unwrap(), x);
+        let a = ExtElem::new(x, Elem::new(3), Elem::new(4), Elem::new(5));
+        assert_eq!(
+            bincode::deserialize::<ExtElem>(&bincode::serialize(&a).unwrap()).unwrap(),
+            a
+        );
+        assert!(bincode::deserialize::<Elem>(&P.to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn checked_and_strings() {
+        use crate::field::ParseElemError;
+
+        assert_eq!(Elem::from_u32_checked(P - 1), Some(Elem::new(P - 1)));
+        assert_eq!(Elem::from_u32_checked(P), None);
+        assert_eq!(Elem::new(P - 1).to_string(), "2147483646");
+        assert_eq!(format!("{:x}", Elem::new(P - 1)), "7ffffffe");
+        assert_eq!("0x7ffffffe".parse::<Elem>(), Ok(Elem::new(P - 1)));
+        assert_eq!(
+            "2147483647".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!(
+            "4294967296".parse::<Elem>(),
+            Err(ParseElemError::OutOfRange)
+        );
+        assert_eq!("x".parse::<Elem>(), Err(ParseElemError::Invalid));
+    }
+}
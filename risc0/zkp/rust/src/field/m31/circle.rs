@@ -0,0 +1,388 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The circle group over [Elem] and FFTs over its cosets.
+//!
+//! The points `(x, y)` with `x^2 + y^2 = 1` form a group under
+//! `(x0, y0) * (x1, y1) = (x0 x1 - y0 y1, x0 y1 + x1 y0)`, the rotations
+//! of the unit circle.  Over M31 it has order `P + 1 = 2^31`, so unlike the
+//! multiplicative group it has subgroups of every power-of-two size, and
+//! these play the part of the roots of unity:
+//!
+//! * A [CircleDomain] of size `2^n` is the coset of the subgroup of order `2^n`
+//!   which is closed under [CirclePoint::inverse], made of the odd powers of
+//!   the generator of order `2^(n + 1)`.
+//! * [CircleDomain::interpolate] finds the coefficients of the unique
+//!   polynomial taking the given values on the domain, and
+//!   [CircleDomain::evaluate] undoes it.  The polynomials are in the basis
+//!   whose `j`-th element is the product of `y`, `x`, `pi(x)`, `pi(pi(x))` and
+//!   so on, taking each factor for which the corresponding bit of `j` (least
+//!   significant first) is set.  Here `pi(x) = 2 x^2 - 1` is the x-coordinate
+//!   of the square of a point.
+//! * [eval_at_point] evaluates such a polynomial anywhere on the circle.
+//!
+//! Zero-padding the coefficients and evaluating over a domain twice the size
+//! is a low-degree extension, as with NTTs.
+
+use alloc::vec::Vec;
+use core::ops;
+
+use super::Elem;
+use crate::field::{self, Elem as FieldElem};
+
+/// The base 2 logarithm of the order of the circle group.
+pub const LOG_ORDER: usize = 31;
+
+/// A point on the circle `x^2 + y^2 = 1` over [Elem].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CirclePoint {
+    /// The x coordinate.
+    pub x: Elem,
+    /// The y coordinate.
+    pub y: Elem,
+}
+
+impl CirclePoint {
+    /// The identity of the group, `(1, 0)`.
+    pub const IDENTITY: Self = CirclePoint {
+        x: Elem::new(1),
+        y: Elem::new(0),
+    };
+
+    /// A generator of the whole group, of order 2^[LOG_ORDER].  All the
+    /// domains are derived from this one.
+    pub const GENERATOR: Self = CirclePoint {
+        x: Elem::new(2),
+        y: Elem::new(1268011823),
+    };
+
+    /// Returns the generator of the subgroup of order `2^log_size`, chosen
+    /// so that `subgroup_generator(n + 1)^2 == subgroup_generator(n)`.
+    pub fn subgroup_generator(log_size: usize) -> Self {
+        assert!(
+            log_size <= LOG_ORDER,
+            "No subgroup of order 2^{log_size}; the circle group has order 2^{LOG_ORDER}"
+        );
+        let mut point = Self::GENERATOR;
+        for _ in log_size..LOG_ORDER {
+            point = point.double();
+        }
+        point
+    }
+
+    /// Returns whether the point lies on the circle.
+    pub fn is_on_circle(&self) -> bool {
+        self.x * self.x + self.y * self.y == Elem::ONE
+    }
+
+    /// Returns the inverse of the point, its reflection `(x, -y)`.
+    pub fn inverse(self) -> Self {
+        CirclePoint {
+            x: self.x,
+            y: -self.y,
+        }
+    }
+
+    /// Returns the square of the point, `(2 x^2 - 1, 2 x y)`.
+    pub fn double(self) -> Self {
+        CirclePoint {
+            x: double_x(self.x),
+            y: (self.x + self.x) * self.y,
+        }
+    }
+
+    /// Returns the point raised to the given power.
+    pub fn pow(self, exp: usize) -> Self {
+        let mut n = exp;
+        let mut tot = Self::IDENTITY;
+        let mut point = self;
+        while n != 0 {
+            if n % 2 == 1 {
+                tot = tot * point;
+            }
+            n /= 2;
+            point = point.double();
+        }
+        tot
+    }
+}
+
+impl ops::Mul for CirclePoint {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        CirclePoint {
+            x: self.x * rhs.x - self.y * rhs.y,
+            y: self.x * rhs.y + rhs.x * self.y,
+        }
+    }
+}
+
+// The x coordinate of the square of a point with x coordinate `x`.
+fn double_x(x: Elem) -> Elem {
+    let xx = x * x;
+    xx + xx - Elem::ONE
+}
+
+/// A coset of size `2^log_size` on which [CircleDomain::interpolate] and
+/// [CircleDomain::evaluate] work; see [circle](self).  Point `i` is `q *
+/// g^i`, where `g` is the generator of the subgroup of order `2^log_size`
+/// and `q^2 = g`, so that point `size - 1 - i` is the inverse of point `i`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircleDomain {
+    log_size: usize,
+    first: CirclePoint,
+    step: CirclePoint,
+}
+
+impl CircleDomain {
+    /// The domain of size `2^log_size`, which must be between 1 and
+    /// [LOG_ORDER] - 1.
+    pub fn new(log_size: usize) -> Self {
+        assert!(
+            (1..LOG_ORDER).contains(&log_size),
+            "Circle domains have between 2^1 and 2^{} points",
+            LOG_ORDER - 1
+        );
+        let first = CirclePoint::subgroup_generator(log_size + 1);
+        CircleDomain {
+            log_size,
+            first,
+            step: first.double(),
+        }
+    }
+
+    /// The base 2 logarithm of the number of points.
+    pub fn log_size(&self) -> usize {
+        self.log_size
+    }
+
+    /// The number of points.
+    pub fn size(&self) -> usize {
+        1 << self.log_size
+    }
+
+    /// Returns point `i` of the domain.
+    pub fn at(&self, i: usize) -> CirclePoint {
+        self.first * self.step.pow(i)
+    }
+
+    /// Returns the points of the domain, in order.
+    pub fn points(&self) -> Vec<CirclePoint> {
+        let mut points = Vec::with_capacity(self.size());
+        let mut point = self.first;
+        for _ in 0..self.size() {
+            points.push(point);
+            point = point * self.step;
+        }
+        points
+    }
+
+    /// Returns the coefficients of the polynomial which takes the value
+    /// `evals[i]` at point `i` of the domain.
+    pub fn interpolate(&self, evals: &[Elem]) -> Vec<Elem> {
+        assert_eq!(evals.len(), self.size(), "Wrong number of evaluations");
+        let n = evals.len();
+        let half = n / 2;
+        let points = self.points();
+        // Split `f(x, y) = f0(x) + y f1(x)` by pairing each point with its
+        // inverse, which is at the mirrored index.
+        let inv_2y =
+            field::batch_inv(&points[..half].iter().map(|p| p.y + p.y).collect::<Vec<_>>());
+        let inv_2 = Elem::new(2).inv();
+        let mut f0 = Vec::with_capacity(half);
+        let mut f1 = Vec::with_capacity(half);
+        for k in 0..half {
+            let (a, b) = (evals[k], evals[n - 1 - k]);
+            f0.push((a + b) * inv_2);
+            f1.push((a - b) * inv_2y[k]);
+        }
+        let xs: Vec<Elem> = points[..half].iter().map(|p| p.x).collect();
+        interleave(&interpolate_line(&f0, &xs), &interpolate_line(&f1, &xs))
+    }
+
+    /// Returns the values at each point of the domain of the polynomial with
+    /// the given coefficients, undoing [CircleDomain::interpolate].
+    pub fn evaluate(&self, coeffs: &[Elem]) -> Vec<Elem> {
+        assert_eq!(coeffs.len(), self.size(), "Wrong number of coefficients");
+        let n = coeffs.len();
+        let half = n / 2;
+        let points = self.points();
+        let xs: Vec<Elem> = points[..half].iter().map(|p| p.x).collect();
+        let (c0, c1) = deinterleave(coeffs);
+        let (f0, f1) = (evaluate_line(&c0, &xs), evaluate_line(&c1, &xs));
+        let mut evals = alloc::vec![Elem::ZERO; n];
+        for k in 0..half {
+            let y_f1 = points[k].y * f1[k];
+            evals[k] = f0[k] + y_f1;
+            evals[n - 1 - k] = f0[k] - y_f1;
+        }
+        evals
+    }
+}
+
+/// Evaluate the polynomial with the given coefficients, in the basis of
+/// [CircleDomain::interpolate], at any point of the circle.  The number of
+/// coefficients must be a power of two.
+pub fn eval_at_point(coeffs: &[Elem], point: CirclePoint) -> Elem {
+    assert!(
+        coeffs.len().is_power_of_two(),
+        "Coefficients must be a power of two"
+    );
+    let log_size = coeffs.len().trailing_zeros() as usize;
+    // The factor for bit i of the basis index.
+    let mut factors = Vec::with_capacity(log_size);
+    let mut x = point.x;
+    if log_size > 0 {
+        factors.push(point.y);
+    }
+    for _ in 1..log_size {
+        factors.push(x);
+        x = double_x(x);
+    }
+    // Fold away the most significant bit of the index each round.
+    let mut cur = coeffs.to_vec();
+    for factor in factors.into_iter().rev() {
+        let half = cur.len() / 2;
+        for j in 0..half {
+            let high = cur[j + half];
+            cur[j] += factor * high;
+        }
+        cur.truncate(half);
+    }
+    cur[0]
+}
+
+// Interpolate over x coordinates which pair `xs[k]` with `-xs[m - 1 - k]`,
+// splitting `g(x) = g0(pi(x)) + x g1(pi(x))`.  The x coordinates left
+// after applying `pi` pair up the same way.
+fn interpolate_line(vals: &[Elem], xs: &[Elem]) -> Vec<Elem> {
+    let m = vals.len();
+    if m == 1 {
+        return vals.to_vec();
+    }
+    let half = m / 2;
+    let inv_2x = field::batch_inv(&xs[..half].iter().map(|x| *x + *x).collect::<Vec<_>>());
+    let inv_2 = Elem::new(2).inv();
+    let mut g0 = Vec::with_capacity(half);
+    let mut g1 = Vec::with_capacity(half);
+    for k in 0..half {
+        let (a, b) = (vals[k], vals[m - 1 - k]);
+        g0.push((a + b) * inv_2);
+        g1.push((a - b) * inv_2x[k]);
+    }
+    let next_xs: Vec<Elem> = xs[..half].iter().map(|x| double_x(*x)).collect();
+    interleave(
+        &interpolate_line(&g0, &next_xs),
+        &interpolate_line(&g1, &next_xs),
+    )
+}
+
+fn evaluate_line(coeffs: &[Elem], xs: &[Elem]) -> Vec<Elem> {
+    let m = coeffs.len();
+    if m == 1 {
+        return coeffs.to_vec();
+    }
+    let half = m / 2;
+    let next_xs: Vec<Elem> = xs[..half].iter().map(|x| double_x(*x)).collect();
+    let (c0, c1) = deinterleave(coeffs);
+    let (g0, g1) = (evaluate_line(&c0, &next_xs), evaluate_line(&c1, &next_xs));
+    let mut vals = alloc::vec![Elem::ZERO; m];
+    for k in 0..half {
+        let x_g1 = xs[k] * g1[k];
+        vals[k] = g0[k] + x_g1;
+        vals[m - 1 - k] = g0[k] - x_g1;
+    }
+    vals
+}
+
+// The coefficients of `a(v) + w b(v)`, given those of a and b, where w is
+// the factor for the least significant bit of the index.
+fn interleave(a: &[Elem], b: &[Elem]) -> Vec<Elem> {
+    a.iter().zip(b).flat_map(|(a, b)| [*a, *b]).collect()
+}
+
+fn deinterleave(coeffs: &[Elem]) -> (Vec<Elem>, Vec<Elem>) {
+    let even = coeffs.iter().step_by(2).copied().collect();
+    let odd = coeffs.iter().skip(1).step_by(2).copied().collect();
+    (even, odd)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::{eval_at_point, CircleDomain, CirclePoint, Elem, LOG_ORDER};
+    use crate::field::Elem as FieldElem;
+
+    #[test]
+    fn group() {
+        let g = CirclePoint::GENERATOR;
+        assert!(g.is_on_circle());
+        // The generator has order exactly 2^31.
+        let mut cur = g;
+        for _ in 0..LOG_ORDER {
+            assert_ne!(cur, CirclePoint::IDENTITY);
+            cur = cur.double();
+        }
+        assert_eq!(cur, CirclePoint::IDENTITY);
+        assert_eq!(g * g, g.double());
+        assert_eq!(g * g.inverse(), CirclePoint::IDENTITY);
+        assert_eq!(g.pow(5), g * g * g * g * g);
+        for log_size in 0..LOG_ORDER {
+            let h = CirclePoint::subgroup_generator(log_size + 1);
+            assert!(h.is_on_circle());
+            assert_eq!(h.double(), CirclePoint::subgroup_generator(log_size));
+        }
+    }
+
+    #[test]
+    fn domain() {
+        let domain = CircleDomain::new(4);
+        let points = domain.points();
+        assert_eq!(points.len(), 16);
+        for (i, point) in points.iter().enumerate() {
+            assert!(point.is_on_circle());
+            assert_eq!(*point, domain.at(i));
+            assert_eq!(point.inverse(), points[15 - i]);
+        }
+        let mut sorted = points;
+        sorted.sort_by_key(|p| (u32::from(p.x), u32::from(p.y)));
+        sorted.dedup();
+        assert_eq!(sorted.len(), 16);
+    }
+
+    #[test]
+    fn fft() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for log_size in 1..8 {
+            let domain = CircleDomain::new(log_size);
+            let evals: Vec<Elem> = (0..domain.size()).map(|_| Elem::random(&mut rng)).collect();
+            let coeffs = domain.interpolate(&evals);
+            assert_eq!(domain.evaluate(&coeffs), evals);
+            for (point, eval) in domain.points().into_iter().zip(&evals) {
+                assert_eq!(eval_at_point(&coeffs, point), *eval);
+            }
+
+            // Low-degree extension onto a domain twice the size.
+            let mut padded = coeffs.clone();
+            padded.resize(2 * coeffs.len(), Elem::ZERO);
+            let big = CircleDomain::new(log_size + 1);
+            let extended = big.evaluate(&padded);
+            for (point, eval) in big.points().into_iter().zip(&extended) {
+                assert_eq!(eval_at_point(&coeffs, point), *eval);
+            }
+            assert_eq!(big.interpolate(&extended), padded);
+        }
+    }
+}
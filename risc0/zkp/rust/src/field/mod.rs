@@ -14,7 +14,8 @@
 
 // TODO: Document better
 
-use core::{cmp, ops};
+use alloc::{vec, vec::Vec};
+use core::{cmp, fmt, num::IntErrorKind, ops};
 
 /// A field with field elements.
 pub trait Elem:
@@ -40,8 +41,15 @@ pub trait Elem:
     /// terms.
     fn inv(self) -> Self;
 
-    /// Returns this element raised to the given power.
+    /// Returns this element raised to the given power.  For exponents which
+    /// may not fit in a usize, such as `P - 2` for a 64-bit `P` on a 32-bit
+    /// target, use [Elem::pow_u64] or [Elem::pow_bytes].
     fn pow(self, exp: usize) -> Self {
+        self.pow_u64(exp as u64)
+    }
+
+    /// Returns this element raised to the given power.
+    fn pow_u64(self, exp: u64) -> Self {
         let mut n = exp;
         let mut tot = Self::ONE;
         let mut x = self;
@@ -55,8 +63,198 @@ pub trait Elem:
         tot
     }
 
+    /// Returns this element raised to the power `exp`, a little-endian
+    /// unsigned integer of any length, for exponents beyond a u64.  This
+    /// squares once for every bit of `exp`, including leading zeros.
+    fn pow_bytes(self, exp: &[u8]) -> Self {
+        let mut tot = Self::ONE;
+        for byte in exp.iter().rev() {
+            for bit in (0..8).rev() {
+                tot *= tot;
+                if (byte >> bit) & 1 == 1 {
+                    tot *= self;
+                }
+            }
+        }
+        tot
+    }
+
     /// Returns a random valid field element.
     fn random(rng: &mut impl rand::Rng) -> Self;
+
+    /// Replace each element of `elems` with its multiplicative inverse, using
+    /// a single inversion and 3 multiplications per element (Montgomery's
+    /// trick).  As with [Elem::inv], zeros are left as zero.
+    fn batch_inv(elems: &mut [Self]) {
+        // prefix[i] is the product of the non-zero elements before i.
+        let mut prefix = Vec::with_capacity(elems.len());
+        let mut acc = Self::ONE;
+        for x in elems.iter() {
+            prefix.push(acc);
+            if *x != Self::ZERO {
+                acc *= *x;
+            }
+        }
+        // Peel one element off the inverted product at a time.
+        let mut inv = acc.inv();
+        for (x, prefix) in elems.iter_mut().zip(prefix).rev() {
+            if *x != Self::ZERO {
+                let rest = inv * *x;
+                *x = inv * prefix;
+                inv = rest;
+            }
+        }
+    }
+}
+
+/// Returns the multiplicative inverse of each element of `elems`; see
+/// [Elem::batch_inv].
+pub fn batch_inv<E: Elem>(elems: &[E]) -> Vec<E> {
+    let mut out = elems.to_vec();
+    E::batch_inv(&mut out);
+    out
+}
+
+/// The error returned when parsing a field element from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseElemError {
+    /// The string is not a decimal or `0x`-prefixed hexadecimal integer.
+    Invalid,
+    /// The integer is not less than the modulus of the field.
+    OutOfRange,
+}
+
+impl fmt::Display for ParseElemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseElemError::Invalid => write!(f, "invalid field element"),
+            ParseElemError::OutOfRange => write!(f, "field element out of range"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseElemError {}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal integer, for the `FromStr`
+/// impls of field elements.
+fn parse_u64(s: &str) -> Result<u64, ParseElemError> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|err| match err.kind() {
+        IntErrorKind::PosOverflow => ParseElemError::OutOfRange,
+        _ => ParseElemError::Invalid,
+    })
+}
+
+/// A field element with a canonical, fixed-size, little-endian byte
+/// encoding.
+///
+/// Unlike a `bytemuck` cast, which exposes the in-memory representation
+/// (Montgomery form for some fields) and accepts any bit pattern, this
+/// encodes the canonical value, and decoding rejects values which are not
+/// less than the modulus.  Extension elements encode their components in
+/// order.
+pub trait ElemBytes: Elem {
+    /// The length of the encoding, in bytes.
+    const BYTES: usize;
+
+    /// Write the encoding of this element to `out`, which must be
+    /// [ElemBytes::BYTES] long.
+    fn write_le_bytes(self, out: &mut [u8]);
+
+    /// Decode an element from `bytes`, or return `None` if `bytes` is not
+    /// [ElemBytes::BYTES] long or does not encode a canonical value.
+    fn read_le_bytes_checked(bytes: &[u8]) -> Option<Self>;
+}
+
+/// The error returned when decoding field elements from bytes fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElemBytesError {
+    /// The length of the input is not a multiple of [ElemBytes::BYTES].
+    Length(usize),
+    /// The element at this index is not less than the modulus.
+    OutOfRange(usize),
+}
+
+impl fmt::Display for ElemBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElemBytesError::Length(len) => {
+                write!(f, "{len} bytes is not a whole number of field elements")
+            }
+            ElemBytesError::OutOfRange(index) => {
+                write!(f, "field element {index} out of range")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ElemBytesError {}
+
+/// Encode `elems` as the concatenation of their [ElemBytes] encodings.
+pub fn elems_to_bytes<E: ElemBytes>(elems: &[E]) -> Vec<u8> {
+    let mut out = vec![0; elems.len() * E::BYTES];
+    for (elem, chunk) in elems.iter().zip(out.chunks_exact_mut(E::BYTES)) {
+        elem.write_le_bytes(chunk);
+    }
+    out
+}
+
+/// Decode the elements written by [elems_to_bytes], rejecting a trailing
+/// partial element and any non-canonical value.
+pub fn bytes_to_elems<E: ElemBytes>(bytes: &[u8]) -> Result<Vec<E>, ElemBytesError> {
+    if bytes.len() % E::BYTES != 0 {
+        return Err(ElemBytesError::Length(bytes.len()));
+    }
+    bytes
+        .chunks_exact(E::BYTES)
+        .enumerate()
+        .map(|(i, chunk)| E::read_le_bytes_checked(chunk).ok_or(ElemBytesError::OutOfRange(i)))
+        .collect()
+}
+
+/// A field element derived from confidential input, which is zeroized when
+/// dropped.  Its [Debug](fmt::Debug) output is redacted.
+#[cfg(feature = "zeroize")]
+pub struct SecretElem<E: Elem + zeroize::Zeroize>(E);
+
+#[cfg(feature = "zeroize")]
+impl<E: Elem + zeroize::Zeroize> SecretElem<E> {
+    /// Wrap `elem`.  Copies made before wrapping are not zeroized.
+    pub fn new(elem: E) -> Self {
+        SecretElem(elem)
+    }
+
+    /// Returns the element.  The copy returned is not zeroized.
+    pub fn expose(&self) -> E {
+        self.0
+    }
+
+    /// Returns a reference to the element, to update it in place.
+    pub fn expose_mut(&mut self) -> &mut E {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Elem + zeroize::Zeroize> Drop for SecretElem<E> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<E: Elem + zeroize::Zeroize> zeroize::ZeroizeOnDrop for SecretElem<E> {}
+
+#[cfg(feature = "zeroize")]
+impl<E: Elem + zeroize::Zeroize> fmt::Debug for SecretElem<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretElem(..)")
+    }
 }
 
 /// A field extensension.
@@ -68,45 +266,310 @@ pub trait ExtElem: Elem + ops::Mul<Self::SubElem, Output = Self> {
     fn from_subfield(elem: &Self::SubElem) -> Self;
 }
 
-pub trait RootsOfUnity: Sized + 'static {
-    /// Maximum root of unity which is a power of 2, i.e. there is
-    /// 2^MAX_ROU_PO2th root of unity, but no 2^(MAX_ROU_PO2+1)th.
-    const MAX_ROU_PO2: usize;
+/// A field whose multiplicative group has a subgroup of order
+/// 2^TWO_ADICITY, so it supports NTTs over any power-of-two domain up to that
+/// size.
+pub trait TwoAdicField: Elem + 'static {
+    /// The largest n such that 2^n divides the order of the multiplicative
+    /// group, i.e. there is a 2^TWO_ADICITY-th root of unity, but no
+    /// 2^(TWO_ADICITY+1)-th.
+    const TWO_ADICITY: usize;
 
-    /// For each power of 2, what is the 'forward' root of unity for
-    /// the po2.  That is, this list satisfies ROU_FWD\[i+1\] ^ 2 =
-    /// ROU_FWD\[i\] in the prime field which implies ROU_FWD\[i\] ^
-    /// (2 ^ i) = 1.
-    const ROU_FWD: &'static [Self];
+    /// A generator of the subgroup of order 2^TWO_ADICITY, i.e. a primitive
+    /// 2^TWO_ADICITY-th root of unity.  All the other roots are derived from
+    /// this one.
+    const TWO_ADIC_GENERATOR: Self;
 
-    /// For each power of 2, what is the 'reverse' root of unity for
-    /// the po2.  This list satisfies ROU_FWD\[i\] * ROU_REV\[i\] = 1
-    /// in the prime field F_2013265921.
-    const ROU_REV: &'static [Self];
+    /// Returns the 'forward' primitive 2^po2-th root of unity.  These are
+    /// chosen so that `root_of_unity(po2 + 1)^2 == root_of_unity(po2)`.
+    fn root_of_unity(po2: usize) -> Self {
+        check_po2::<Self>(po2);
+        let mut root = Self::TWO_ADIC_GENERATOR;
+        for _ in po2..Self::TWO_ADICITY {
+            root *= root;
+        }
+        root
+    }
+
+    /// Returns the 'reverse' primitive 2^po2-th root of unity, the inverse of
+    /// [TwoAdicField::root_of_unity].
+    fn inv_root_of_unity(po2: usize) -> Self {
+        Self::root_of_unity(po2).inv()
+    }
+}
+
+fn check_po2<F: TwoAdicField>(po2: usize) {
+    assert!(
+        po2 <= F::TWO_ADICITY,
+        "No 2^{po2}-th root of unity; the two-adicity is {}",
+        F::TWO_ADICITY
+    );
+}
+
+/// The forward and reverse roots of unity of `F`, indexed by po2.
+#[cfg(any(test, feature = "std"))]
+fn compute_roots<F: TwoAdicField>() -> (Vec<F>, Vec<F>) {
+    let mut fwd = Vec::with_capacity(F::TWO_ADICITY + 1);
+    let mut rev = Vec::with_capacity(F::TWO_ADICITY + 1);
+    let mut root = F::TWO_ADIC_GENERATOR;
+    let mut inv_root = root.inv();
+    for _ in 0..=F::TWO_ADICITY {
+        fwd.push(root);
+        rev.push(inv_root);
+        root *= root;
+        inv_root *= inv_root;
+    }
+    fwd.reverse();
+    rev.reverse();
+    (fwd, rev)
 }
 
-#[cfg(test)]
+/// Roots of unity of a [TwoAdicField], computed on first use.  Fields keep
+/// one of these in a `static` to memoize [TwoAdicField::root_of_unity] and
+/// [TwoAdicField::inv_root_of_unity].
+#[cfg(feature = "std")]
+pub struct RootTable<F> {
+    init: std::sync::Once,
+    roots: core::cell::UnsafeCell<(Vec<F>, Vec<F>)>,
+}
+
+// SAFETY: `roots` is only written once, inside `init`, and only read after
+// `init` has completed.
+#[cfg(feature = "std")]
+unsafe impl<F: Send + Sync> Sync for RootTable<F> {}
+
+#[cfg(feature = "std")]
+impl<F> RootTable<F> {
+    /// Create an empty table.
+    pub const fn new() -> Self {
+        RootTable {
+            init: std::sync::Once::new(),
+            roots: core::cell::UnsafeCell::new((Vec::new(), Vec::new())),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> Default for RootTable<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: TwoAdicField> RootTable<F> {
+    fn get(&self) -> &(Vec<F>, Vec<F>) {
+        self.init.call_once(|| {
+            // SAFETY: no reference to `roots` exists until `init` completes.
+            unsafe { *self.roots.get() = compute_roots::<F>() }
+        });
+        // SAFETY: `roots` is never written after `init` completes.
+        unsafe { &*self.roots.get() }
+    }
+
+    /// See [TwoAdicField::root_of_unity].
+    pub fn root_of_unity(&self, po2: usize) -> F {
+        check_po2::<F>(po2);
+        self.get().0[po2]
+    }
+
+    /// See [TwoAdicField::inv_root_of_unity].
+    pub fn inv_root_of_unity(&self, po2: usize) -> F {
+        check_po2::<F>(po2);
+        self.get().1[po2]
+    }
+}
+
+/// Checks and [proptest] strategies for field implementations, and for code
+/// generic over a field, such as the constraint polynomials of a circuit.
+/// They are available with the `proptest` feature:
+///
+/// ```ignore
+/// use proptest::prelude::*;
+/// use risc0_zkp::field::{baby_bear::Elem, test::elem};
+///
+/// proptest! {
+///     #[test]
+///     fn square_difference(a in elem::<Elem>(), b in elem::<Elem>()) {
+///         prop_assert_eq!(a * a - b * b, (a + b) * (a - b));
+///     }
+/// }
+/// ```
+///
+/// Every field element type implements [proptest::arbitrary::Arbitrary],
+/// generating canonical values uniformly and shrinking towards zero; the
+/// strategies here add the edge cases zero, one and minus one.
+#[cfg(any(test, feature = "proptest"))]
 pub mod test {
-    use super::{Elem, RootsOfUnity};
-    use core::fmt::Debug;
+    use super::{Elem, TwoAdicField};
+    use alloc::vec::Vec;
+    use core::{fmt::Debug, ops};
+    use proptest::{
+        collection::{vec, SizeRange},
+        prelude::*,
+        test_runner::TestRunner,
+    };
     use rand::Rng;
 
-    pub fn test_roots_of_unity<F: Elem + RootsOfUnity + Debug>() {
-        let mut cur: Option<F> = None;
+    /// Zero, one or minus one, the values most likely to expose an edge case.
+    pub fn special<F: Elem + Debug>() -> impl Strategy<Value = F> {
+        prop_oneof![Just(F::ZERO), Just(F::ONE), Just(F::ZERO - F::ONE)]
+    }
 
-        for &rou in F::ROU_FWD.iter().rev() {
-            if let Some(ref mut curval) = &mut cur {
-                *curval *= *curval;
-                assert_eq!(*curval, rou);
-            } else {
-                cur = Some(rou);
+    /// Any element, favouring the [special] ones.
+    pub fn elem<F: Elem + Arbitrary>() -> impl Strategy<Value = F> {
+        prop_oneof![1 => special::<F>(), 4 => any::<F>()]
+    }
+
+    /// Any non-zero element, for divisors.
+    pub fn non_zero<F: Elem + Arbitrary>() -> impl Strategy<Value = F> {
+        elem::<F>().prop_filter("zero", |x| *x != F::ZERO)
+    }
+
+    /// Vectors of elements with a length in `len`, such as the coefficients
+    /// of a polynomial or the columns of a trace row.
+    pub fn elems<F: Elem + Arbitrary>(len: impl Into<SizeRange>) -> impl Strategy<Value = Vec<F>> {
+        vec(elem::<F>(), len)
+    }
+
+    /// Check the field axioms for `F` on random elements.
+    pub fn test_field_axioms<F: Elem + Arbitrary>() {
+        let mut runner = TestRunner::default();
+        runner
+            .run(&(elem::<F>(), elem::<F>(), elem::<F>()), |(a, b, c)| {
+                prop_assert_eq!((a + b) + c, a + (b + c));
+                prop_assert_eq!(a + b, b + a);
+                prop_assert_eq!((a * b) * c, a * (b * c));
+                prop_assert_eq!(a * b, b * a);
+                prop_assert_eq!(a * (b + c), a * b + a * c);
+                prop_assert_eq!(a - b + b, a);
+                prop_assert_eq!(a + F::ZERO, a);
+                prop_assert_eq!(a * F::ONE, a);
+                if a != F::ZERO {
+                    prop_assert_eq!(a * a.inv(), F::ONE);
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    /// Check the roots of unity of `F` against `fwd` and `rev`, known-good
+    /// tables of the forward and reverse roots indexed by po2.
+    pub fn test_roots_of_unity<F: TwoAdicField + Debug>(fwd: &[F], rev: &[F]) {
+        assert_eq!(fwd.len(), F::TWO_ADICITY + 1);
+        assert_eq!(rev.len(), F::TWO_ADICITY + 1);
+        let (computed_fwd, computed_rev) = super::compute_roots::<F>();
+        assert_eq!(computed_fwd, fwd);
+        assert_eq!(computed_rev, rev);
+        for po2 in 0..=F::TWO_ADICITY {
+            assert_eq!(F::root_of_unity(po2), fwd[po2]);
+            assert_eq!(F::inv_root_of_unity(po2), rev[po2]);
+        }
+
+        // The roots are primitive: squaring 2^po2-th root po2 times reaches
+        // one, and no sooner.
+        for po2 in 0..=F::TWO_ADICITY {
+            let mut cur = F::root_of_unity(po2);
+            for _ in 0..po2 {
+                assert_ne!(cur, F::ONE);
+                cur *= cur;
             }
+            assert_eq!(cur, F::ONE);
+            assert_eq!(F::root_of_unity(po2) * F::inv_root_of_unity(po2), F::ONE);
         }
-        assert_eq!(cur, Some(F::ONE));
+    }
+
+    /// Check that [ElemBytes](super::ElemBytes) encodings of `F` round-trip
+    /// and that truncated input is rejected.
+    pub fn test_elem_bytes<F: super::ElemBytes + Arbitrary>() {
+        let mut runner = TestRunner::default();
+        runner
+            .run(&elems::<F>(0..8), |elems| {
+                let bytes = super::elems_to_bytes(&elems);
+                prop_assert_eq!(bytes.len(), elems.len() * F::BYTES);
+                prop_assert_eq!(super::bytes_to_elems::<F>(&bytes), Ok(elems));
+                if !bytes.is_empty() {
+                    prop_assert_eq!(
+                        super::bytes_to_elems::<F>(&bytes[1..]),
+                        Err(super::ElemBytesError::Length(bytes.len() - 1))
+                    );
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(F::read_le_bytes_checked(&[0; 1]), None);
+    }
+
+    pub fn test_div_sum_product<F>()
+    where
+        F: Elem
+            + Debug
+            + ops::Div<Output = F>
+            + ops::DivAssign
+            + core::iter::Sum
+            + for<'a> core::iter::Sum<&'a F>
+            + core::iter::Product
+            + for<'a> core::iter::Product<&'a F>,
+    {
+        let mut rng = rand::thread_rng();
+        let elems: Vec<F> = (0..10).map(|_| non_zero_rand(&mut rng)).collect();
+        let (a, b) = (elems[0], elems[1]);
+        assert_eq!(a / b * b, a);
+        let mut c = a;
+        c /= b;
+        assert_eq!(c, a * b.inv());
+        assert_eq!(a / F::ZERO, F::ZERO);
 
-        for (&fwd, &rev) in F::ROU_FWD.iter().zip(F::ROU_REV.iter()) {
-            assert_eq!(fwd * rev, F::ONE);
+        let mut sum = F::ZERO;
+        let mut product = F::ONE;
+        for x in elems.iter() {
+            sum += *x;
+            product *= *x;
         }
+        assert_eq!(elems.iter().sum::<F>(), sum);
+        assert_eq!(elems.iter().copied().sum::<F>(), sum);
+        assert_eq!(elems.iter().product::<F>(), product);
+        assert_eq!(elems.iter().copied().product::<F>(), product);
+        assert_eq!(core::iter::empty::<F>().sum::<F>(), F::ZERO);
+        assert_eq!(core::iter::empty::<F>().product::<F>(), F::ONE);
+    }
+
+    pub fn test_batch_inv<F: Elem + Debug>() {
+        let mut rng = rand::thread_rng();
+        let mut elems: Vec<F> = (0..100).map(|_| non_zero_rand(&mut rng)).collect();
+        elems[0] = F::ZERO;
+        elems[37] = F::ZERO;
+        elems[99] = F::ONE;
+        let expected: Vec<F> = elems.iter().map(|x| x.inv()).collect();
+        assert_eq!(super::batch_inv(&elems), expected);
+        F::batch_inv(&mut elems);
+        assert_eq!(elems, expected);
+
+        let mut empty: [F; 0] = [];
+        F::batch_inv(&mut empty);
+        assert_eq!(super::batch_inv(&[F::ZERO]), [F::ZERO]);
+    }
+
+    pub fn test_pow<F: Elem + Debug>() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let x = F::random(&mut rng);
+            let exp: u64 = rng.gen();
+            let expected = x.pow_u64(exp);
+            assert_eq!(x.pow_bytes(&exp.to_le_bytes()), expected);
+            // Leading zeros don't change the exponent.
+            let wide = (exp as u128).to_le_bytes();
+            assert_eq!(x.pow_bytes(&wide), expected);
+            assert_eq!(x.pow(exp as u32 as usize), x.pow_u64(exp as u32 as u64));
+            // x^(2^64 + 1) == (x^(2^32))^(2^32) * x
+            let mut big = [0u8; 9];
+            big[0] = 1;
+            big[8] = 1;
+            let x32 = x.pow_u64(1 << 32);
+            assert_eq!(x.pow_bytes(&big), x32.pow_u64(1 << 32) * x);
+        }
+        assert_eq!(F::ZERO.pow_bytes(&[]), F::ONE);
+        assert_eq!(F::ZERO.pow_u64(0), F::ONE);
     }
 
     fn non_zero_rand<F: Elem>(r: &mut impl Rng) -> F {
@@ -157,5 +620,85 @@ pub mod test {
     }
 }
 
+/// Implements division, as multiplication by [Elem::inv], along with [Sum]
+/// and [Product] over owned and borrowed elements, for a field element type.
+/// As with [Elem::inv], dividing by zero gives zero.  Generic parameters, if
+/// any, go in brackets before the type, as in `([const P: u32] Foo<P>)`.
+///
+/// [Sum]: core::iter::Sum
+/// [Product]: core::iter::Product
+macro_rules! impl_div_sum_product {
+    ([$($g:tt)*] $t:ty) => {
+        impl<$($g)*> core::ops::Div for $t {
+            type Output = $t;
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            fn div(self, rhs: $t) -> $t {
+                self * $crate::field::Elem::inv(rhs)
+            }
+        }
+
+        impl<$($g)*> core::ops::DivAssign for $t {
+            #[allow(clippy::suspicious_op_assign_impl)]
+            fn div_assign(&mut self, rhs: $t) {
+                *self *= $crate::field::Elem::inv(rhs)
+            }
+        }
+
+        impl<$($g)*> core::iter::Sum for $t {
+            fn sum<I: Iterator<Item = $t>>(iter: I) -> $t {
+                iter.fold(<$t as $crate::field::Elem>::ZERO, |acc, x| acc + x)
+            }
+        }
+
+        impl<'a, $($g)*> core::iter::Sum<&'a $t> for $t {
+            fn sum<I: Iterator<Item = &'a $t>>(iter: I) -> $t {
+                iter.copied().sum()
+            }
+        }
+
+        impl<$($g)*> core::iter::Product for $t {
+            fn product<I: Iterator<Item = $t>>(iter: I) -> $t {
+                iter.fold(<$t as $crate::field::Elem>::ONE, |acc, x| acc * x)
+            }
+        }
+
+        impl<'a, $($g)*> core::iter::Product<&'a $t> for $t {
+            fn product<I: Iterator<Item = &'a $t>>(iter: I) -> $t {
+                iter.copied().product()
+            }
+        }
+    };
+    ($t:ty) => {
+        impl_div_sum_product!([] $t);
+    };
+}
+
+/// Implements [ElemBytes] for a field element type with inherent
+/// `to_le_bytes` and `from_le_bytes_checked` methods over `[u8; $n]`, with
+/// generic parameters as for `impl_div_sum_product`.
+macro_rules! impl_elem_bytes {
+    ([$($g:tt)*] $t:ty, $n:expr) => {
+        impl<$($g)*> $crate::field::ElemBytes for $t {
+            const BYTES: usize = $n;
+
+            fn write_le_bytes(self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_le_bytes_checked(bytes: &[u8]) -> Option<Self> {
+                let bytes: [u8; $n] = bytes.try_into().ok()?;
+                Self::from_le_bytes_checked(bytes)
+            }
+        }
+    };
+    ($t:ty, $n:expr) => {
+        impl_elem_bytes!([] $t, $n);
+    };
+}
+
 /// Fields available for use with zkp:
 pub mod baby_bear;
+pub mod bn254;
+pub mod goldilocks;
+pub mod m31;
+pub mod prime;
@@ -0,0 +1,522 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Small prime fields.
+//!
+//! Support for any base finite field modulo an odd prime below 2^31, with
+//! the modulus as a const generic.  [baby_bear::Elem](super::baby_bear::Elem)
+//! is one instance; experimental circuits can instantiate others, such as
+//! KoalaBear, `2^31 - 2^24 + 1`, without copying its arithmetic:
+//!
+//! ```
+//! use risc0_zkp::field::{prime::PrimeField, Elem};
+//!
+//! type KoalaBear = PrimeField<{ (1 << 31) - (1 << 24) + 1 }>;
+//!
+//! let x = KoalaBear::new(3);
+//! assert_eq!(x * x.inv(), KoalaBear::ONE);
+//! ```
+//!
+//! This provides [Elem](field::Elem) and the conversions; a field which is
+//! used for NTTs also needs a [TwoAdicField](field::TwoAdicField) impl, with
+//! the generator for its modulus, in the module which defines its alias.
+
+use crate::field::{self, Elem as FieldElem};
+
+use core::{fmt, ops, str::FromStr};
+
+use bytemuck::Zeroable;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An element of the finite field F_p, where `P` is an odd prime below 2^31.
+/// The bound lets a sum of two elements fit in a u32 without overflow.
+/// `P` is not checked for primality; with a composite modulus, [inv] and
+/// division give meaningless results.
+///
+/// Internally, elements are kept in Montgomery form, `x * 2^32 % P`, so that
+/// multiplication needs no division; see [to_montgomery]. Convert with
+/// `u32::from` and `PrimeField::from` to get at the canonical value, rather
+/// than reinterpreting an element's memory.
+///
+/// [inv]: field::Elem::inv
+#[derive(Eq, PartialEq, Clone, Copy)]
+#[repr(transparent)]
+pub struct PrimeField<const P: u32>(pub(super) u32);
+
+// SAFETY: All zeros is the Montgomery form of zero.
+unsafe impl<const P: u32> Zeroable for PrimeField<P> {}
+
+impl<const P: u32> fmt::Debug for PrimeField<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Elem")
+            .field(&from_montgomery::<P>(self.0))
+            .finish()
+    }
+}
+
+/// Elements display as their canonical value, in decimal or, with `{:x}` and
+/// `{:X}`, in hexadecimal.
+impl<const P: u32> fmt::Display for PrimeField<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&u32::from(self), f)
+    }
+}
+
+impl<const P: u32> fmt::LowerHex for PrimeField<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&u32::from(self), f)
+    }
+}
+
+impl<const P: u32> fmt::UpperHex for PrimeField<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&u32::from(self), f)
+    }
+}
+
+/// Parses a canonical value, in decimal or `0x`-prefixed hexadecimal.  Values
+/// that are not less than P are rejected rather than reduced.
+impl<const P: u32> FromStr for PrimeField<P> {
+    type Err = field::ParseElemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let x = field::parse_u64(s)?;
+        u32::try_from(x)
+            .ok()
+            .and_then(Self::from_u32_checked)
+            .ok_or(field::ParseElemError::OutOfRange)
+    }
+}
+
+impl<const P: u32> Default for PrimeField<P> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+/// Elements serialize as their canonical value, so for example bincode
+/// writes them as a little-endian u32. Deserializing rejects values that are
+/// not less than P.
+impl<const P: u32> Serialize for PrimeField<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(from_montgomery::<P>(self.0))
+    }
+}
+
+impl<'de, const P: u32> Deserialize<'de> for PrimeField<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = u32::deserialize(deserializer)?;
+        Self::from_u32_checked(x).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Unsigned(x.into()),
+                &"an integer less than the field modulus",
+            )
+        })
+    }
+}
+
+/// Generates canonical values uniformly, shrinking towards zero.
+#[cfg(any(test, feature = "proptest"))]
+impl<const P: u32> proptest::arbitrary::Arbitrary for PrimeField<P> {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+        (0..P).prop_map(Self::new).boxed()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a, const P: u32> arbitrary::Arbitrary<'a> for PrimeField<P> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::new(u.int_in_range(0..=P - 1)?))
+    }
+}
+
+/// Overwrites the element with [ZERO](field::Elem::ZERO), whose
+/// representation is all zeros.
+#[cfg(feature = "zeroize")]
+impl<const P: u32> zeroize::Zeroize for PrimeField<P> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<const P: u32> field::Elem for PrimeField<P> {
+    const ZERO: Self = Self::new(0);
+
+    const ONE: Self = Self::new(1);
+
+    /// Compute the multiplicative inverse of `x`, or `1 / x` in finite field
+    /// terms. Since `x ^ (P - 1) == 1 % P` for any `x != 0` (as a
+    /// consequence of Fermat's little theorem), it follows that `x *
+    /// x ^ (P - 2) == 1 % P` for `x != 0`.  That is, `x ^ (P - 2)` is the
+    /// multiplicative inverse of `x`. Computed this way, the *inverse* of
+    /// zero comes out as zero, which is convenient in many cases, so we
+    /// leave it.
+    fn inv(self) -> Self {
+        #[cfg(feature = "ct")]
+        let inv = ct::pow_fixed_window(self, P - 2);
+        #[cfg(not(feature = "ct"))]
+        let inv = self.pow_u64((P - 2) as u64);
+        inv
+    }
+
+    fn random(rng: &mut impl rand::Rng) -> Self {
+        // Reject the last modulo-P region of possible uint32_t values, since it's
+        // uneven and will only return random values less than (2^32 % P).
+        let reject_cutoff: u32 = (u32::MAX / P) * P;
+        let mut val: u32 = rng.gen();
+
+        while val >= reject_cutoff {
+            val = rng.gen();
+        }
+        Self::from(val)
+    }
+}
+
+impl<const P: u32> PrimeField<P> {
+    /// `-1 / P % 2^32`, used to reduce products in Montgomery form.  This is
+    /// also where an unsupported modulus is rejected, since every element
+    /// passes through a multiplication on its way into Montgomery form.
+    pub(crate) const M: u32 = {
+        assert!(
+            P % 2 == 1 && P > 2 && P < 1 << 31,
+            "PrimeField needs an odd modulus below 2^31"
+        );
+        montgomery_m(P)
+    };
+
+    /// `2^64 % P`, used to convert into Montgomery form.
+    pub(crate) const R2: u32 = montgomery_r2(P);
+
+    /// Create a new element from a raw integer, reducing it modulo P.
+    pub const fn new(x: u32) -> Self {
+        Self(to_montgomery::<P>(x % P))
+    }
+
+    /// Create a new element from a canonical value, or `None` if `x` is not
+    /// less than P.  Prefer this to [PrimeField::new], which silently
+    /// reduces, for untrusted input such as proof words.
+    pub const fn from_u32_checked(x: u32) -> Option<Self> {
+        if x < P {
+            Some(Self(to_montgomery::<P>(x)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the little-endian encoding of the canonical value; see
+    /// [ElemBytes](field::ElemBytes).
+    pub const fn to_le_bytes(self) -> [u8; 4] {
+        from_montgomery::<P>(self.0).to_le_bytes()
+    }
+
+    /// Decode the little-endian encoding of a canonical value, or return
+    /// `None` if the value is not less than P.
+    pub const fn from_le_bytes_checked(bytes: [u8; 4]) -> Option<Self> {
+        Self::from_u32_checked(u32::from_le_bytes(bytes))
+    }
+
+    /// Compute the Legendre symbol of `x`: 1 if `x` is a non-zero square, -1
+    /// if it is not a square, and 0 if it is zero.  By Euler's criterion,
+    /// this is `x ^ ((P - 1) / 2)`.
+    pub fn legendre(self) -> i32 {
+        let symbol = self.pow_u64(((P - 1) / 2) as u64);
+        if symbol == Self::ZERO {
+            0
+        } else if symbol == Self::ONE {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+impl<const P: u32> ops::Add for PrimeField<P> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(add::<P>(self.0, rhs.0))
+    }
+}
+
+impl<const P: u32> ops::AddAssign for PrimeField<P> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 = add::<P>(self.0, rhs.0)
+    }
+}
+
+impl<const P: u32> ops::Sub for PrimeField<P> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(sub::<P>(self.0, rhs.0))
+    }
+}
+
+impl<const P: u32> ops::SubAssign for PrimeField<P> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 = sub::<P>(self.0, rhs.0)
+    }
+}
+
+impl<const P: u32> ops::Mul for PrimeField<P> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self(mul::<P>(self.0, rhs.0))
+    }
+}
+
+impl<const P: u32> ops::MulAssign for PrimeField<P> {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0 = mul::<P>(self.0, rhs.0)
+    }
+}
+
+impl<const P: u32> ops::Neg for PrimeField<P> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(0) - self
+    }
+}
+
+impl_div_sum_product!([const P: u32] PrimeField<P>);
+impl_elem_bytes!([const P: u32] PrimeField<P>, 4);
+
+impl<const P: u32> From<PrimeField<P>> for u32 {
+    fn from(x: PrimeField<P>) -> Self {
+        from_montgomery::<P>(x.0)
+    }
+}
+
+impl<const P: u32> From<&PrimeField<P>> for u32 {
+    fn from(x: &PrimeField<P>) -> Self {
+        from_montgomery::<P>(x.0)
+    }
+}
+
+impl<const P: u32> From<PrimeField<P>> for u64 {
+    fn from(x: PrimeField<P>) -> Self {
+        from_montgomery::<P>(x.0).into()
+    }
+}
+
+impl<const P: u32> From<u32> for PrimeField<P> {
+    fn from(x: u32) -> Self {
+        Self::new(x)
+    }
+}
+
+impl<const P: u32> From<u64> for PrimeField<P> {
+    fn from(x: u64) -> Self {
+        Self(to_montgomery::<P>((x % P as u64) as u32))
+    }
+}
+
+/// Computes `-1 / p % 2^32` for odd `p`.  Each Newton step `x * (2 - p * x)`
+/// doubles the number of correct low bits of `1 / p`, and `p` itself is
+/// already correct to 3 bits, so five steps cover all 32.
+pub(crate) const fn montgomery_m(p: u32) -> u32 {
+    let mut x = p;
+    let mut i = 0;
+    while i < 5 {
+        x = x.wrapping_mul(2u32.wrapping_sub(p.wrapping_mul(x)));
+        i += 1;
+    }
+    x.wrapping_neg()
+}
+
+/// Computes `2^64 % p`.
+pub(crate) const fn montgomery_r2(p: u32) -> u32 {
+    let r = (1u64 << 32) % p as u64;
+    ((r * r) % p as u64) as u32
+}
+
+// Reduces `x < 2 * P` to below P.
+#[cfg(not(feature = "ct"))]
+const fn reduce_once<const P: u32>(x: u32) -> u32 {
+    if x >= P {
+        x - P
+    } else {
+        x
+    }
+}
+
+pub(crate) fn add<const P: u32>(lhs: u32, rhs: u32) -> u32 {
+    reduce_once::<P>(lhs + rhs)
+}
+
+#[cfg(not(feature = "ct"))]
+pub(crate) fn sub<const P: u32>(lhs: u32, rhs: u32) -> u32 {
+    let x = lhs.wrapping_sub(rhs);
+    return if x > P { x.wrapping_add(P) } else { x };
+}
+
+// All ones if the top bit of `x` is set, else zero.  With P below 2^31, a
+// subtraction that goes below zero always sets the top bit.
+#[cfg(feature = "ct")]
+const fn borrow_mask(x: u32) -> u32 {
+    ((x as i32) >> 31) as u32
+}
+
+// Reduces `x < 2 * P` to below P without branching on its value.
+#[cfg(feature = "ct")]
+const fn reduce_once<const P: u32>(x: u32) -> u32 {
+    let y = x.wrapping_sub(P);
+    let mask = borrow_mask(y);
+    (y & !mask) | (x & mask)
+}
+
+#[cfg(feature = "ct")]
+pub(crate) fn sub<const P: u32>(lhs: u32, rhs: u32) -> u32 {
+    let x = lhs.wrapping_sub(rhs);
+    x.wrapping_add(P & borrow_mask(x))
+}
+
+// Multiplication in Montgomery form: `lhs * rhs / 2^32 % P`, with the
+// reduction fused into the multiply.  Since both inputs are below P, the
+// intermediate `t + m * P` is below `2 * P * 2^32` and fits in a u64.
+pub(crate) const fn mul<const P: u32>(lhs: u32, rhs: u32) -> u32 {
+    let t = (lhs as u64) * (rhs as u64);
+    let m = (t as u32).wrapping_mul(PrimeField::<P>::M);
+    let u = ((t + (m as u64) * (P as u64)) >> 32) as u32;
+    reduce_once::<P>(u)
+}
+
+/// Convert a canonical value below P into Montgomery form, `x * 2^32 % P`.
+pub const fn to_montgomery<const P: u32>(x: u32) -> u32 {
+    mul::<P>(x, PrimeField::<P>::R2)
+}
+
+/// Convert a value in Montgomery form back to its canonical value below P.
+pub const fn from_montgomery<const P: u32>(x: u32) -> u32 {
+    mul::<P>(x, 1)
+}
+
+/// Constant-time field operations, enabled by the `ct` feature.
+///
+/// With it, addition, subtraction and multiplication reduce without
+/// branches, and inversion uses a fixed-window exponentiation whose sequence
+/// of operations doesn't depend on the element.
+#[cfg(feature = "ct")]
+mod ct {
+    use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+    use super::PrimeField;
+    use crate::field::Elem as _;
+
+    const WINDOW: u32 = 4;
+
+    /// Raises `x` to the public exponent `exp`, one fixed-size window of
+    /// bits at a time.
+    pub(super) fn pow_fixed_window<const P: u32>(x: PrimeField<P>, exp: u32) -> PrimeField<P> {
+        let mut table = [PrimeField::ONE; 1 << WINDOW];
+        for i in 1..table.len() {
+            table[i] = table[i - 1] * x;
+        }
+        let mut tot = PrimeField::ONE;
+        for window in (0..u32::BITS / WINDOW).rev() {
+            for _ in 0..WINDOW {
+                tot *= tot;
+            }
+            let digit = (exp >> (window * WINDOW)) & ((1 << WINDOW) - 1);
+            tot *= table[digit as usize];
+        }
+        tot
+    }
+
+    impl<const P: u32> ConstantTimeEq for PrimeField<P> {
+        fn ct_eq(&self, other: &Self) -> Choice {
+            // Montgomery form is unique for values below P.
+            self.0.ct_eq(&other.0)
+        }
+    }
+
+    impl<const P: u32> ConditionallySelectable for PrimeField<P> {
+        fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+            PrimeField(u32::conditional_select(&a.0, &b.0, choice))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{montgomery_m, montgomery_r2, PrimeField};
+    use crate::field::{self, Elem};
+
+    const KOALA_BEAR: u32 = (1 << 31) - (1 << 24) + 1;
+    type KoalaBear = PrimeField<KOALA_BEAR>;
+
+    #[test]
+    fn field_ops() {
+        field::test::test_field_ops::<KoalaBear>(KOALA_BEAR as u64);
+    }
+
+    #[test]
+    fn div_sum_product() {
+        field::test::test_div_sum_product::<KoalaBear>();
+    }
+
+    #[test]
+    fn batch_inv() {
+        field::test::test_batch_inv::<KoalaBear>();
+    }
+
+    #[test]
+    fn field_axioms() {
+        field::test::test_field_axioms::<KoalaBear>();
+    }
+
+    #[test]
+    fn elem_bytes() {
+        field::test::test_elem_bytes::<KoalaBear>();
+    }
+
+    #[test]
+    fn pow_variants() {
+        field::test::test_pow::<KoalaBear>();
+    }
+
+    #[cfg(feature = "ct")]
+    #[test]
+    fn constant_time() {
+        use rand::SeedableRng;
+        use subtle::{Choice, ConditionallySelectable};
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..1_000 {
+            let x = KoalaBear::random(&mut rng);
+            assert_eq!(super::ct::pow_fixed_window(x, 1000), x.pow(1000));
+        }
+        let (a, b) = (KoalaBear::new(1), KoalaBear::new(2));
+        assert_eq!(KoalaBear::conditional_select(&a, &b, Choice::from(1)), b);
+    }
+
+    #[test]
+    fn montgomery() {
+        for p in [KOALA_BEAR, 15 * (1 << 27) + 1, (1 << 31) - 1, 3, 65537] {
+            assert_eq!(p.wrapping_mul(montgomery_m(p)), u32::MAX);
+            let r = (1u64 << 32) % p as u64;
+            assert_eq!(montgomery_r2(p) as u64, r * r % p as u64);
+        }
+        assert_eq!(KoalaBear::M, 0x7eff_ffff);
+        let max = KoalaBear::new(KOALA_BEAR - 1);
+        assert_eq!(max * max, KoalaBear::ONE);
+        assert_eq!(KoalaBear::new(KOALA_BEAR + 2), KoalaBear::new(2));
+        assert_eq!(
+            KoalaBear::from(u64::MAX),
+            KoalaBear::new((u64::MAX % KOALA_BEAR as u64) as u32)
+        );
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Choosing a [Hal] when the program runs rather than when it's built.
+//!
+//! [HalBackend] names the backends this build supports and says which of
+//! them this machine can run; [AnyHal] wraps the chosen backend's [Hal], so
+//! that the prover is instantiated once for all of them.
+
+use std::{env, fmt};
+
+#[cfg(feature = "cuda")]
+use super::cuda::{self, CudaHal};
+#[cfg(feature = "metal")]
+use super::metal::{self, MetalHal};
+use super::{cpu::CpuHal, fused::FusedExpr, Buffer, Hal};
+use crate::{
+    adapter::PolyFp,
+    core::{fp::Fp, fp4::Fp4, sha::Digest},
+};
+
+/// The environment variable [HalBackend::from_env] reads a backend's name
+/// from.
+pub const HAL_BACKEND_ENV: &str = "RISC0_HAL";
+
+/// A backend the prover can run on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HalBackend {
+    /// [CpuHal], which is always available.
+    Cpu,
+    /// [CudaHal], which needs the `cuda` feature and a CUDA device.
+    #[cfg(feature = "cuda")]
+    Cuda,
+    /// [MetalHal], which needs the `metal` feature and a Metal device.
+    #[cfg(feature = "metal")]
+    Metal,
+}
+
+impl HalBackend {
+    /// The backends of this build, fastest first.
+    pub const ALL: &'static [HalBackend] = &[
+        #[cfg(feature = "cuda")]
+        HalBackend::Cuda,
+        #[cfg(feature = "metal")]
+        HalBackend::Metal,
+        HalBackend::Cpu,
+    ];
+
+    /// The backend's name, as accepted by [HalBackend::from_name].
+    pub fn name(self) -> &'static str {
+        match self {
+            HalBackend::Cpu => "cpu",
+            #[cfg(feature = "cuda")]
+            HalBackend::Cuda => "cuda",
+            #[cfg(feature = "metal")]
+            HalBackend::Metal => "metal",
+        }
+    }
+
+    /// The backend called `name`, if this build has it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether this machine can run the backend.
+    pub fn is_available(self) -> bool {
+        match self {
+            HalBackend::Cpu => true,
+            #[cfg(feature = "cuda")]
+            HalBackend::Cuda => cuda::device_count() > 0,
+            #[cfg(feature = "metal")]
+            HalBackend::Metal => metal::is_available(),
+        }
+    }
+
+    /// The fastest backend this machine can run.
+    pub fn detect() -> Self {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.is_available())
+            .unwrap_or(HalBackend::Cpu)
+    }
+
+    /// The backend named by the `RISC0_HAL` environment variable, or
+    /// [HalBackend::detect] if it isn't set.
+    ///
+    /// Fails if the variable names a backend which this build doesn't have
+    /// or this machine can't run, rather than quietly proving on another.
+    pub fn from_env() -> Result<Self, HalBackendError> {
+        let name = match env::var(HAL_BACKEND_ENV) {
+            Ok(name) => name,
+            Err(_) => return Ok(Self::detect()),
+        };
+        let backend = Self::from_name(&name).ok_or(HalBackendError::Unknown(name))?;
+        if !backend.is_available() {
+            return Err(HalBackendError::Unavailable(backend));
+        }
+        Ok(backend)
+    }
+
+    /// Create the backend's [Hal].
+    pub fn hal(self) -> AnyHal {
+        match self {
+            HalBackend::Cpu => AnyHal::Cpu(CpuHal::new()),
+            #[cfg(feature = "cuda")]
+            HalBackend::Cuda => AnyHal::Cuda(CudaHal::new()),
+            #[cfg(feature = "metal")]
+            HalBackend::Metal => AnyHal::Metal(MetalHal::new()),
+        }
+    }
+}
+
+/// The error returned when the chosen backend can't be used; see
+/// [HalBackend::from_env].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HalBackendError {
+    /// This build has no backend of this name.
+    Unknown(String),
+    /// This machine can't run the backend.
+    Unavailable(HalBackend),
+}
+
+impl fmt::Display for HalBackendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HalBackendError::Unknown(name) => {
+                write!(f, "{HAL_BACKEND_ENV}: unknown backend {name:?}")
+            }
+            HalBackendError::Unavailable(backend) => write!(
+                f,
+                "backend {:?} isn't available on this machine",
+                backend.name()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HalBackendError {}
+
+/// The [Hal] of one of the [HalBackend]s.
+///
+/// Buffers belong to the backend which allocated them, so every buffer
+/// passed to an [AnyHal] must come from the same one.
+pub enum AnyHal {
+    /// Runs on [HalBackend::Cpu].
+    Cpu(CpuHal),
+    /// Runs on [HalBackend::Cuda].
+    #[cfg(feature = "cuda")]
+    Cuda(CudaHal),
+    /// Runs on [HalBackend::Metal].
+    #[cfg(feature = "metal")]
+    Metal(MetalHal),
+}
+
+impl AnyHal {
+    /// The backend this [Hal] runs on.
+    pub fn backend(&self) -> HalBackend {
+        match self {
+            AnyHal::Cpu(_) => HalBackend::Cpu,
+            #[cfg(feature = "cuda")]
+            AnyHal::Cuda(_) => HalBackend::Cuda,
+            #[cfg(feature = "metal")]
+            AnyHal::Metal(_) => HalBackend::Metal,
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($self:ident, $hal:ident => $call:expr) => {
+        match $self {
+            AnyHal::Cpu($hal) => $call,
+            #[cfg(feature = "cuda")]
+            AnyHal::Cuda($hal) => $call,
+            #[cfg(feature = "metal")]
+            AnyHal::Metal($hal) => $call,
+        }
+    };
+}
+
+impl Hal for AnyHal {
+    fn alloc<T: 'static + Default + Clone>(&self, size: usize) -> Buffer<T> {
+        dispatch!(self, hal => hal.alloc(size))
+    }
+
+    fn copy_from<T: 'static + Clone>(&self, slice: &[T]) -> Buffer<T> {
+        dispatch!(self, hal => hal.copy_from(slice))
+    }
+
+    fn wrap_vec<T: 'static + Clone>(&self, vec: Vec<T>) -> Buffer<T> {
+        dispatch!(self, hal => hal.wrap_vec(vec))
+    }
+
+    unsafe fn wrap_external<T: 'static + Clone>(&self, ptr: *mut T, len: usize) -> Buffer<T> {
+        dispatch!(self, hal => hal.wrap_external(ptr, len))
+    }
+
+    fn batch_expand(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, count: usize) {
+        dispatch!(self, hal => hal.batch_expand(output, input, count))
+    }
+
+    fn batch_evaluate_ntt(&self, io: &Buffer<Fp>, count: usize, expand_bits: usize) {
+        dispatch!(self, hal => hal.batch_evaluate_ntt(io, count, expand_bits))
+    }
+
+    fn batch_interpolate_ntt(&self, io: &Buffer<Fp>, count: usize) {
+        dispatch!(self, hal => hal.batch_interpolate_ntt(io, count))
+    }
+
+    fn batch_bit_reverse(&self, io: &Buffer<Fp>, count: usize) {
+        dispatch!(self, hal => hal.batch_bit_reverse(io, count))
+    }
+
+    fn batch_evaluate_any(
+        &self,
+        coeffs: &Buffer<Fp>,
+        poly_count: usize,
+        which: &Buffer<u32>,
+        xs: &Buffer<Fp4>,
+        out: &Buffer<Fp4>,
+    ) {
+        dispatch!(self, hal => hal.batch_evaluate_any(coeffs, poly_count, which, xs, out))
+    }
+
+    fn coset_shift(&self, io: &Buffer<Fp>, count: usize, shift: Fp) {
+        dispatch!(self, hal => hal.coset_shift(io, count, shift))
+    }
+
+    fn mix_poly_coeffs(
+        &self,
+        out: &Buffer<Fp4>,
+        mix_start: &Fp4,
+        mix: &Fp4,
+        input: &Buffer<Fp>,
+        combos: &Buffer<u32>,
+        input_size: usize,
+        count: usize,
+    ) {
+        dispatch!(self, hal => {
+            hal.mix_poly_coeffs(out, mix_start, mix, input, combos, input_size, count)
+        })
+    }
+
+    fn eltwise_add_fp(&self, output: &Buffer<Fp>, input1: &Buffer<Fp>, input2: &Buffer<Fp>) {
+        dispatch!(self, hal => hal.eltwise_add_fp(output, input1, input2))
+    }
+
+    fn eltwise_mul_add_fp(
+        &self,
+        output: &Buffer<Fp>,
+        a: &Buffer<Fp>,
+        b: &Buffer<Fp>,
+        c: &Buffer<Fp>,
+    ) {
+        dispatch!(self, hal => hal.eltwise_mul_add_fp(output, a, b, c))
+    }
+
+    fn eltwise_sub_mul_fp(&self, output: &Buffer<Fp>, a: &Buffer<Fp>, b: &Buffer<Fp>, k: Fp) {
+        dispatch!(self, hal => hal.eltwise_sub_mul_fp(output, a, b, k))
+    }
+
+    fn eltwise_fused_fp(&self, output: &Buffer<Fp>, inputs: &[&Buffer<Fp>], expr: &FusedExpr) {
+        dispatch!(self, hal => hal.eltwise_fused_fp(output, inputs, expr))
+    }
+
+    fn eltwise_sum_fp4(&self, output: &Buffer<Fp>, input: &Buffer<Fp4>) {
+        dispatch!(self, hal => hal.eltwise_sum_fp4(output, input))
+    }
+
+    fn eltwise_copy_fp(&self, output: &Buffer<Fp>, input: &Buffer<Fp>) {
+        dispatch!(self, hal => hal.eltwise_copy_fp(output, input))
+    }
+
+    fn eltwise_copy_digest(&self, output: &Buffer<Digest>, input: &Buffer<Digest>) {
+        dispatch!(self, hal => hal.eltwise_copy_digest(output, input))
+    }
+
+    fn eval_check<P: PolyFp + Sync>(
+        &self,
+        check: &Buffer<Fp>,
+        args: &[&Buffer<Fp>],
+        poly: &P,
+        poly_mix: Fp4,
+        po2: usize,
+    ) {
+        dispatch!(self, hal => hal.eval_check(check, args, poly, poly_mix, po2))
+    }
+
+    fn fri_fold(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, mix: &Fp4) {
+        dispatch!(self, hal => hal.fri_fold(output, input, mix))
+    }
+
+    fn sha_rows(&self, output: &Buffer<Digest>, matrix: &Buffer<Fp>) {
+        dispatch!(self, hal => hal.sha_rows(output, matrix))
+    }
+
+    fn sha_fold(&self, io: &Buffer<Digest>, input_size: usize, output_size: usize) {
+        dispatch!(self, hal => hal.sha_fold(io, input_size, output_size))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for backend in HalBackend::ALL {
+            assert_eq!(HalBackend::from_name(backend.name()), Some(*backend));
+        }
+        assert_eq!(HalBackend::from_name("CPU"), Some(HalBackend::Cpu));
+        assert_eq!(HalBackend::from_name("tpu"), None);
+    }
+
+    #[test]
+    fn detect() {
+        let backend = HalBackend::detect();
+        assert!(backend.is_available());
+        assert_eq!(backend.hal().backend(), backend);
+    }
+
+    #[test]
+    fn from_env() {
+        // The only test which touches the variable, so it can't race.
+        env::set_var(HAL_BACKEND_ENV, "tpu");
+        assert_eq!(
+            HalBackend::from_env(),
+            Err(HalBackendError::Unknown("tpu".into()))
+        );
+        env::set_var(HAL_BACKEND_ENV, "cpu");
+        assert_eq!(HalBackend::from_env(), Ok(HalBackend::Cpu));
+        env::remove_var(HAL_BACKEND_ENV);
+        assert_eq!(HalBackend::from_env(), Ok(HalBackend::detect()));
+    }
+}
@@ -20,25 +20,60 @@ use core::{
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
+    adapter::{PolyFp, PolyFpContext},
     core::{
         fp::Fp,
         fp4::{Fp4, EXT_SIZE},
+        lde::CosetLde,
         log2_ceil,
-        ntt::{bit_rev_32, bit_reverse, evaluate_ntt, expand, interpolate_ntt},
+        ntt::{bit_rev_32, bit_reverse, expand, par_evaluate_ntt_fp, par_interpolate_ntt_fp},
         sha::{Digest, Sha},
         sha_cpu,
     },
-    field::Elem,
-    FRI_FOLD,
+    field::{Elem, TwoAdicField},
+    FRI_FOLD, INV_RATE,
 };
 #[allow(unused_imports)]
 use log::debug;
 use ndarray::{ArrayView, ArrayViewMut, Axis};
 use rayon::prelude::*;
 
-use super::{Buffer, BufferTrait, Hal};
+use super::{fused, fused::FusedExpr, Buffer, BufferTrait, Hal};
 
-pub struct CpuHal {}
+/// A [Hal] which runs on the CPU, committing to rows with the [Sha] `S`.
+pub struct CpuHal<S: Sha = sha_cpu::Impl> {
+    sha: S,
+}
+
+impl CpuHal {
+    /// Create a [CpuHal] which hashes with SHA-256.
+    pub fn new() -> Self {
+        Self::with_sha(sha_cpu::Impl {})
+    }
+}
+
+impl Default for CpuHal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Sha> CpuHal<S> {
+    /// Create a [CpuHal] which hashes with the given [Sha].
+    ///
+    /// The same [Sha] must be passed to the prover and verifier, so that the
+    /// Merkle trees and the Fiat-Shamir transcript agree.
+    pub fn with_sha(sha: S) -> Self {
+        CpuHal { sha }
+    }
+}
+
+// Number of elements each thread handles at a time in the fused kernels.
+const FUSED_CHUNK: usize = 1 << 12;
+
+// Minimum number of cycles each thread handles when evaluating the check
+// polynomial.
+const EVAL_CHUNK: usize = 1 << 10;
 
 #[derive(Debug)]
 struct Region(usize, usize);
@@ -60,26 +95,58 @@ impl Region {
     }
 }
 
+enum Storage<T> {
+    Owned(Vec<T>),
+    // Memory owned by the caller of `Hal::wrap_external`.
+    External(*mut T, usize),
+}
+
+impl<T> Storage<T> {
+    fn as_slice(&self) -> &[T] {
+        match self {
+            Storage::Owned(vec) => vec,
+            Storage::External(ptr, len) => unsafe { from_raw_parts(*ptr, *len) },
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        match self {
+            Storage::Owned(vec) => vec,
+            Storage::External(ptr, len) => unsafe { from_raw_parts_mut(*ptr, *len) },
+        }
+    }
+}
+
 struct CpuBuffer<T> {
-    buf: Rc<RefCell<Vec<T>>>,
+    buf: Rc<RefCell<Storage<T>>>,
     region: Region,
 }
 
 impl<T: Default + Clone> CpuBuffer<T> {
     fn new(size: usize) -> Self {
-        let buf = vec![T::default(); size];
-        CpuBuffer {
-            buf: Rc::new(RefCell::new(buf)),
-            region: Region(0, size),
-        }
+        Self::from_vec(vec![T::default(); size])
     }
 }
 
 impl<T: Clone> CpuBuffer<T> {
     fn copy_from(slice: &[T]) -> Self {
+        Self::from_vec(Vec::from(slice))
+    }
+}
+
+impl<T> CpuBuffer<T> {
+    fn from_vec(vec: Vec<T>) -> Self {
+        let size = vec.len();
         CpuBuffer {
-            buf: Rc::new(RefCell::new(Vec::from(slice))),
-            region: Region(0, slice.len()),
+            buf: Rc::new(RefCell::new(Storage::Owned(vec))),
+            region: Region(0, size),
+        }
+    }
+
+    unsafe fn from_external(ptr: *mut T, len: usize) -> Self {
+        CpuBuffer {
+            buf: Rc::new(RefCell::new(Storage::External(ptr, len))),
+            region: Region(0, len),
         }
     }
 }
@@ -87,12 +154,12 @@ impl<T: Clone> CpuBuffer<T> {
 impl<T> CpuBuffer<T> {
     fn as_slice<'a>(&'a self) -> Ref<'a, [T]> {
         let vec = self.buf.borrow();
-        Ref::map(vec, |vec| &vec[self.region.range()])
+        Ref::map(vec, |vec| &vec.as_slice()[self.region.range()])
     }
 
     fn as_slice_mut<'a>(&'a self) -> RefMut<'a, [T]> {
         let vec = self.buf.borrow_mut();
-        RefMut::map(vec, |vec| &mut vec[self.region.range()])
+        RefMut::map(vec, |vec| &mut vec.as_mut_slice()[self.region.range()])
     }
 }
 
@@ -112,16 +179,60 @@ impl<T: 'static + Clone> BufferTrait<T> for CpuBuffer<T> {
 
     fn view(&self, f: &mut dyn FnMut(&[T])) {
         let buf = self.buf.borrow();
-        f(&buf[self.region.range()]);
+        f(&buf.as_slice()[self.region.range()]);
     }
 
     fn view_mut(&self, f: &mut dyn FnMut(&mut [T])) {
         let mut buf = self.buf.borrow_mut();
-        f(&mut buf[self.region.range()]);
+        f(&mut buf.as_mut_slice()[self.region.range()]);
+    }
+}
+
+// The value everywhere of the polynomial whose coefficients, expanded by
+// `expand_bits`, are `row`, if that polynomial is constant.
+fn constant_evaluation(row: &[Fp], expand_bits: usize) -> Option<Fp> {
+    // Expansion repeats the constant term in the first 1 << expand_bits
+    // entries.
+    if row[(1 << expand_bits)..].iter().all(|x| *x == Fp::ZERO) {
+        Some(row[0])
+    } else {
+        None
     }
 }
 
-impl Hal for CpuHal {
+/// The constraint polynomial `poly` at each point of the evaluation domain
+/// of a trace of `1 << po2` rows, before [Hal::eval_check] divides it by the
+/// zerofier; `args` are buffers of the [CpuHal].
+///
+/// Each cycle only reads the trace, so cycles are evaluated in parallel and
+/// collected in order; the result doesn't depend on the number of threads.
+pub(crate) fn eval_constraints<P: PolyFp + Sync>(
+    args: &[&Buffer<Fp>],
+    poly: &P,
+    poly_mix: Fp4,
+    po2: usize,
+) -> Vec<Fp4> {
+    let domain = (1 << po2) * INV_RATE;
+    let args: Vec<Ref<[Fp]>> = args
+        .iter()
+        .map(|arg| arg.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice())
+        .collect();
+    let args: Vec<&[Fp]> = args.iter().map(|arg| &arg[..]).collect();
+    (0..domain)
+        .into_par_iter()
+        .with_min_len(EVAL_CHUNK)
+        .map(|cycle| {
+            let ctx = PolyFpContext {
+                size: domain,
+                cycle,
+                mix: poly_mix,
+            };
+            poly.poly_fp(&ctx, &args).tot
+        })
+        .collect()
+}
+
+impl<S: Sha + Sync> Hal for CpuHal<S> {
     fn alloc<T: 'static + Default + Clone>(&self, size: usize) -> Buffer<T> {
         let buf = CpuBuffer::new(size);
         Rc::new(buf)
@@ -132,6 +243,14 @@ impl Hal for CpuHal {
         Rc::new(buf)
     }
 
+    fn wrap_vec<T: 'static + Clone>(&self, vec: Vec<T>) -> Buffer<T> {
+        Rc::new(CpuBuffer::from_vec(vec))
+    }
+
+    unsafe fn wrap_external<T: 'static + Clone>(&self, ptr: *mut T, len: usize) -> Buffer<T> {
+        Rc::new(CpuBuffer::from_external(ptr, len))
+    }
+
     fn batch_expand(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, count: usize) {
         let out_size = output.size() / count;
         let in_size = input.size() / count;
@@ -157,7 +276,10 @@ impl Hal for CpuHal {
         assert_eq!(row_size * count, io.size());
         let mut io = io.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice_mut();
         io.par_chunks_exact_mut(row_size).for_each(|row| {
-            evaluate_ntt(row, expand_bits);
+            match constant_evaluation(row, expand_bits) {
+                Some(constant) => row.fill(constant),
+                None => par_evaluate_ntt_fp(row, expand_bits),
+            }
         });
     }
 
@@ -166,7 +288,15 @@ impl Hal for CpuHal {
         assert_eq!(row_size * count, io.size());
         let mut io = io.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice_mut();
         io.par_chunks_exact_mut(row_size).for_each(|row| {
-            interpolate_ntt(row);
+            // Padded regions of a trace often leave whole columns constant;
+            // these interpolate to a constant polynomial without any butterflies.
+            let first = row[0];
+            if row.iter().all(|x| *x == first) {
+                row.fill(Fp::ZERO);
+                row[0] = first;
+            } else {
+                par_interpolate_ntt_fp(row);
+            }
         });
     }
 
@@ -216,7 +346,7 @@ impl Hal for CpuHal {
             });
     }
 
-    fn zk_shift(&self, io: &Buffer<Fp>, poly_count: usize) {
+    fn coset_shift(&self, io: &Buffer<Fp>, poly_count: usize, shift: Fp) {
         let bits = log2_ceil(io.size() / poly_count);
         let count = io.size();
         assert_eq!(io.size(), poly_count * (1 << bits));
@@ -226,8 +356,7 @@ impl Hal for CpuHal {
             .for_each(|(io, idx)| {
                 let pos = idx & ((1 << bits) - 1);
                 let rev = bit_rev_32(pos as u32) >> (32 - bits);
-                let pow3 = Fp::new(3).pow(rev as usize);
-                *io = *io * pow3;
+                *io *= shift.pow(rev as usize);
             });
     }
 
@@ -274,6 +403,76 @@ impl Hal for CpuHal {
             });
     }
 
+    fn eltwise_mul_add_fp(
+        &self,
+        output: &Buffer<Fp>,
+        a: &Buffer<Fp>,
+        b: &Buffer<Fp>,
+        c: &Buffer<Fp>,
+    ) {
+        assert_eq!(output.size(), a.size());
+        assert_eq!(output.size(), b.size());
+        assert_eq!(output.size(), c.size());
+        let mut output = output
+            .downcast_ref::<CpuBuffer<Fp>>()
+            .unwrap()
+            .as_slice_mut();
+        let a = a.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        let b = b.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        let c = c.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        let (a, b, c) = (&a[..], &b[..], &c[..]);
+        output
+            .par_chunks_mut(FUSED_CHUNK)
+            .enumerate()
+            .for_each(|(i, output)| {
+                let range = i * FUSED_CHUNK..;
+                fused::mul_add_simd(output, &a[range.clone()], &b[range.clone()], &c[range]);
+            });
+    }
+
+    fn eltwise_sub_mul_fp(&self, output: &Buffer<Fp>, a: &Buffer<Fp>, b: &Buffer<Fp>, k: Fp) {
+        assert_eq!(output.size(), a.size());
+        assert_eq!(output.size(), b.size());
+        let mut output = output
+            .downcast_ref::<CpuBuffer<Fp>>()
+            .unwrap()
+            .as_slice_mut();
+        let a = a.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        let b = b.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        let (a, b) = (&a[..], &b[..]);
+        output
+            .par_chunks_mut(FUSED_CHUNK)
+            .enumerate()
+            .for_each(|(i, output)| {
+                let range = i * FUSED_CHUNK..;
+                fused::sub_mul_simd(output, &a[range.clone()], &b[range], k);
+            });
+    }
+
+    fn eltwise_fused_fp(&self, output: &Buffer<Fp>, inputs: &[&Buffer<Fp>], expr: &FusedExpr) {
+        assert!(inputs.len() >= expr.num_inputs());
+        for input in inputs {
+            assert_eq!(output.size(), input.size());
+        }
+        let mut output = output
+            .downcast_ref::<CpuBuffer<Fp>>()
+            .unwrap()
+            .as_slice_mut();
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|input| input.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice())
+            .collect();
+        let inputs: Vec<&[Fp]> = inputs.iter().map(|input| &input[..]).collect();
+        output
+            .par_chunks_mut(FUSED_CHUNK)
+            .enumerate()
+            .for_each(|(i, output)| {
+                let offset = i * FUSED_CHUNK;
+                let inputs: Vec<&[Fp]> = inputs.iter().map(|input| &input[offset..]).collect();
+                fused::fused_simd(output, &inputs, expr);
+            });
+    }
+
     fn eltwise_sum_fp4(&self, output: &Buffer<Fp>, input: &Buffer<Fp4>) {
         let count = output.size() / EXT_SIZE;
         let to_add = input.size() / count;
@@ -332,6 +531,41 @@ impl Hal for CpuHal {
             });
     }
 
+    fn eval_check<P: PolyFp + Sync>(
+        &self,
+        check: &Buffer<Fp>,
+        args: &[&Buffer<Fp>],
+        poly: &P,
+        poly_mix: Fp4,
+        po2: usize,
+    ) {
+        const EXP_PO2: usize = log2_ceil(INV_RATE);
+        let domain = (1 << po2) * INV_RATE;
+        assert_eq!(check.size(), EXT_SIZE * domain);
+        let tots = eval_constraints(args, poly, poly_mix, po2);
+        let mut check = check
+            .downcast_ref::<CpuBuffer<Fp>>()
+            .unwrap()
+            .as_slice_mut();
+        let rets: Vec<Fp4> = tots
+            .into_par_iter()
+            .enumerate()
+            .with_min_len(EVAL_CHUNK)
+            .map(|(cycle, tot)| {
+                let x = Fp::root_of_unity(po2 + EXP_PO2).pow(cycle);
+                // The prover evaluates on the coset of the LDE shift, where
+                // x^size - 1 has no roots.
+                let y = (CosetLde::ZK.shift() * x).pow(1 << po2);
+                tot * (y - Fp::new(1)).inv()
+            })
+            .collect();
+        for (cycle, ret) in rets.iter().enumerate() {
+            for i in 0..EXT_SIZE {
+                check[i * domain + cycle] = ret.elems()[i];
+            }
+        }
+    }
+
     fn fri_fold(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, mix: &Fp4) {
         let count = output.size() / EXT_SIZE;
         assert_eq!(output.size(), count * EXT_SIZE);
@@ -372,14 +606,12 @@ impl Hal for CpuHal {
             .downcast_ref::<CpuBuffer<Digest>>()
             .unwrap()
             .as_slice_mut();
-        let matrix = matrix
-            .downcast_ref::<CpuBuffer<Fp>>()
-            .unwrap()
-            .as_slice()
-            .to_vec(); // TODO: avoid copy
-        let sha = sha_cpu::Impl {};
+        let matrix = matrix.downcast_ref::<CpuBuffer<Fp>>().unwrap().as_slice();
+        // The borrow guard isn't Sync, but the slice it guards is.
+        let matrix: &[Fp] = &matrix;
+        let sha = &self.sha;
         output.par_iter_mut().enumerate().for_each(|(idx, output)| {
-            *output = *sha.hash_fps_stride(&matrix, idx, col_size, count);
+            *output = *sha.hash_fps_stride(matrix, idx, col_size, count);
         });
     }
 
@@ -389,7 +621,7 @@ impl Hal for CpuHal {
             .downcast_ref::<CpuBuffer<Digest>>()
             .unwrap()
             .as_slice_mut();
-        let sha = sha_cpu::Impl {};
+        let sha = &self.sha;
         let (output, input) = unsafe {
             (
                 from_raw_parts_mut(io.as_mut_ptr().add(output_size), output_size),
@@ -412,11 +644,12 @@ mod test {
     use rand::thread_rng;
 
     use super::*;
+    use crate::core::ntt::interpolate_ntt;
 
     #[test]
     #[should_panic]
     fn check_req() {
-        let hal = CpuHal {};
+        let hal = CpuHal::new();
         let a = hal.alloc(10);
         let b = hal.alloc(20);
         hal.eltwise_add_fp(&a, &b, &b);
@@ -424,7 +657,7 @@ mod test {
 
     #[test]
     fn fp() {
-        let hal = CpuHal {};
+        let hal = CpuHal::new();
         const COUNT: usize = 1024 * 1024;
         test_binary(
             &hal,
@@ -436,6 +669,115 @@ mod test {
         );
     }
 
+    #[test]
+    fn fused_fp() {
+        let hal = CpuHal::new();
+        // Spans several chunks and ends partway through a block of lanes.
+        const COUNT: usize = FUSED_CHUNK * 3 + 5;
+        let mut rng = thread_rng();
+        let k = Fp::random(&mut rng);
+        let mut random = || -> Vec<Fp> { (0..COUNT).map(|_| Fp::random(&mut rng)).collect() };
+        let (a, b, c) = (random(), random(), random());
+        let (a_buf, b_buf, c_buf) = (hal.copy_from(&a), hal.copy_from(&b), hal.copy_from(&c));
+        let output: Buffer<Fp> = hal.alloc(COUNT);
+
+        hal.eltwise_mul_add_fp(&output, &a_buf, &b_buf, &c_buf);
+        output.view(&mut |output| {
+            assert!((0..COUNT).all(|i| output[i] == a[i] * b[i] + c[i]));
+        });
+
+        hal.eltwise_sub_mul_fp(&output, &a_buf, &b_buf, k);
+        output.view(&mut |output| {
+            assert!((0..COUNT).all(|i| output[i] == a[i] - b[i] * k));
+        });
+
+        let expr = FusedExpr::input(0) * FusedExpr::input(1) - FusedExpr::input(2);
+        hal.eltwise_fused_fp(&output, &[&a_buf, &b_buf, &c_buf], &expr);
+        output.view(&mut |output| {
+            assert!((0..COUNT).all(|i| output[i] == a[i] * b[i] - c[i]));
+        });
+    }
+
+    #[test]
+    fn external_buffers() {
+        let hal = CpuHal::new();
+        let mut rng = thread_rng();
+        let mut data: Vec<Fp> = (0..16).map(|_| Fp::random(&mut rng)).collect();
+        let golden: Vec<Fp> = data.iter().map(|x| *x + *x).collect();
+
+        let owned = hal.wrap_vec(data.clone());
+        let external = unsafe { hal.wrap_external(data.as_mut_ptr(), data.len()) };
+        hal.eltwise_add_fp(&external.slice(0, 16), &owned, &owned);
+        drop(external);
+        assert_eq!(data, golden);
+    }
+
+    #[test]
+    fn constant_evaluation_shortcut() {
+        const SIZE: usize = 1 << 8;
+        const EXPAND_BITS: usize = 2;
+        let mut rng = thread_rng();
+        let constant = Fp::random(&mut rng);
+        let mut row = vec![Fp::ZERO; SIZE << EXPAND_BITS];
+        row[..1 << EXPAND_BITS].fill(constant);
+        assert_eq!(
+            super::constant_evaluation(&row, EXPAND_BITS),
+            Some(constant)
+        );
+        row[SIZE] = Fp::ONE;
+        assert_eq!(super::constant_evaluation(&row, EXPAND_BITS), None);
+    }
+
+    #[test]
+    fn constant_ntt() {
+        const N: usize = 10;
+        const SIZE: usize = 1 << N;
+        const EXPAND_BITS: usize = 2;
+        let hal = CpuHal::new();
+        let mut rng = thread_rng();
+        let constant = Fp::random(&mut rng);
+        let mut golden = vec![constant; SIZE];
+        interpolate_ntt(&mut golden);
+
+        let io: Buffer<Fp> = hal.alloc(SIZE);
+        io.view_mut(&mut |io| io.fill(constant));
+        hal.batch_interpolate_ntt(&io, 1);
+        io.view(&mut |io| assert_eq!(io, &golden[..]));
+
+        let expanded: Buffer<Fp> = hal.alloc(SIZE << EXPAND_BITS);
+        hal.batch_expand(&expanded, &io, 1);
+        hal.batch_evaluate_ntt(&expanded, 1, EXPAND_BITS);
+        expanded.view(&mut |expanded| {
+            assert!(expanded.iter().all(|x| *x == constant));
+        });
+    }
+
+    #[test]
+    fn keccak_rows() {
+        use crate::core::keccak_cpu;
+
+        let sha = keccak_cpu::Impl {};
+        let hal = CpuHal::with_sha(sha.clone());
+        const ROWS: usize = 8;
+        const COLS: usize = 5;
+        let mut rng = thread_rng();
+        let matrix: Vec<Fp> = (0..ROWS * COLS).map(|_| Fp::random(&mut rng)).collect();
+        let nodes = hal.alloc(ROWS * 2);
+        hal.sha_rows(&nodes.slice(ROWS, ROWS), &hal.copy_from(&matrix));
+        hal.sha_fold(&nodes, ROWS, ROWS / 2);
+        nodes.view(&mut |nodes| {
+            let row =
+                |idx: usize| -> Vec<Fp> { (0..COLS).map(|i| matrix[idx + i * ROWS]).collect() };
+            for idx in 0..ROWS {
+                assert_eq!(nodes[ROWS + idx], *sha.hash_fps(&row(idx)));
+            }
+            assert_eq!(
+                nodes[ROWS / 2],
+                *sha.hash_pair(&nodes[ROWS], &nodes[ROWS + 1])
+            );
+        });
+    }
+
     fn test_binary<T, H, HF, CF>(hal: &H, hal_fn: HF, cpu_fn: CF, count: usize)
     where
         T: Elem + Default + Debug + 'static,
@@ -0,0 +1,692 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [Hal] which runs on an NVIDIA GPU, enabled by the `cuda` feature.
+//!
+//! [CudaHal] runs the NTTs, the coset shift and the Merkle tree's hashing,
+//! which dominate proving time, with the kernels of `kernels/cuda/zkp.cu`.
+//! Each of its buffers keeps a host copy next to its device allocation, and
+//! copies between the two only when the other side has changed since, so
+//! that callers can view buffers and the remaining operations can run on the
+//! host, through a [CpuHal], without managing transfers.
+
+use core::{
+    cell::{Cell, RefCell},
+    mem::size_of,
+    ptr::null_mut,
+};
+use std::{collections::BTreeMap, ffi::CStr, rc::Rc};
+
+use crate::{
+    adapter::PolyFp,
+    core::{fp::Fp, fp4::Fp4, log2_ceil, sha::Digest},
+    field::{Elem, TwoAdicField},
+};
+
+use super::{cpu::CpuHal, fused::FusedExpr, Buffer, BufferTrait, Hal};
+
+mod ffi {
+    use std::os::raw::{c_char, c_void};
+
+    use crate::core::{fp::Fp, sha::Digest};
+
+    pub const MEMCPY_HOST_TO_DEVICE: u32 = 1;
+    pub const MEMCPY_DEVICE_TO_HOST: u32 = 2;
+    pub const HOST_REGISTER_MAPPED: u32 = 2;
+
+    extern "C" {
+        pub fn cudaGetDeviceCount(count: *mut i32) -> u32;
+        pub fn cudaMemGetInfo(free: *mut usize, total: *mut usize) -> u32;
+        pub fn cudaGetErrorString(err: u32) -> *const c_char;
+        pub fn cudaMalloc(ptr: *mut *mut c_void, size: usize) -> u32;
+        pub fn cudaFree(ptr: *mut c_void) -> u32;
+        pub fn cudaMemcpy(dst: *mut c_void, src: *const c_void, size: usize, kind: u32) -> u32;
+        pub fn cudaDeviceSynchronize() -> u32;
+        pub fn cudaHostRegister(ptr: *mut c_void, size: usize, flags: u32) -> u32;
+        pub fn cudaHostUnregister(ptr: *mut c_void) -> u32;
+        pub fn cudaHostGetDevicePointer(
+            device: *mut *mut c_void,
+            host: *mut c_void,
+            flags: u32,
+        ) -> u32;
+
+        pub fn risc0_cuda_batch_expand(
+            output: *mut Fp,
+            input: *const Fp,
+            count: u32,
+            out_size: u32,
+            in_size: u32,
+        ) -> u32;
+        pub fn risc0_cuda_ntt_level(
+            io: *mut Fp,
+            twiddles: *const Fp,
+            count: u32,
+            n: u32,
+            level: u32,
+            fwd: bool,
+        ) -> u32;
+        pub fn risc0_cuda_bit_reverse(io: *mut Fp, count: u32, n: u32) -> u32;
+        pub fn risc0_cuda_scale(io: *mut Fp, total: u64, factor: Fp) -> u32;
+        pub fn risc0_cuda_coset_shift(io: *mut Fp, count: u32, bits: u32, shift: Fp) -> u32;
+        pub fn risc0_cuda_sha_rows(
+            output: *mut Digest,
+            matrix: *const Fp,
+            count: u32,
+            col_size: u32,
+        ) -> u32;
+        pub fn risc0_cuda_sha_fold(io: *mut Digest, output_size: u32) -> u32;
+    }
+}
+
+// Panics with CUDA's description of `err`, unless it is cudaSuccess.
+fn check(err: u32, what: &str) {
+    if err != 0 {
+        let msg = unsafe { CStr::from_ptr(ffi::cudaGetErrorString(err)) };
+        panic!("CUDA {what} failed: {}", msg.to_string_lossy());
+    }
+}
+
+/// The number of CUDA devices on this machine; zero without a driver.
+pub fn device_count() -> usize {
+    let mut count = 0;
+    match unsafe { ffi::cudaGetDeviceCount(&mut count) } {
+        0 => count as usize,
+        _ => 0,
+    }
+}
+
+/// The memory of the current CUDA device in bytes, if there is one.
+pub fn device_memory() -> Option<u64> {
+    let (mut free, mut total) = (0, 0);
+    match unsafe { ffi::cudaMemGetInfo(&mut free, &mut total) } {
+        0 => Some(total as u64),
+        _ => None,
+    }
+}
+
+// Which side of a [Mirror] holds the buffer's current contents.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Fresh {
+    Both,
+    Host,
+    Device,
+}
+
+// A host buffer and its copy on the device.
+struct Mirror<T> {
+    host: Buffer<T>,
+    len: usize,
+    // Null until a kernel first uses the buffer, so buffers which only the
+    // host touches take no device memory.
+    device: Cell<*mut T>,
+    // Set for memory owned by the caller of `Hal::wrap_external`, which is
+    // registered with the driver and shared by the host and `device`.
+    registered: Option<*mut T>,
+    fresh: Cell<Fresh>,
+}
+
+impl<T> Mirror<T> {
+    fn new(host: Buffer<T>) -> Self {
+        Mirror {
+            len: host.size(),
+            host,
+            device: Cell::new(null_mut()),
+            registered: None,
+            fresh: Cell::new(Fresh::Host),
+        }
+    }
+
+    unsafe fn register(host: Buffer<T>, ptr: *mut T) -> Self {
+        let len = host.size();
+        let mut device = null_mut();
+        check(
+            ffi::cudaHostRegister(ptr.cast(), len * size_of::<T>(), ffi::HOST_REGISTER_MAPPED),
+            "cudaHostRegister",
+        );
+        check(
+            ffi::cudaHostGetDevicePointer(&mut device, ptr.cast(), 0),
+            "cudaHostGetDevicePointer",
+        );
+        Mirror {
+            host,
+            len,
+            device: Cell::new(device.cast()),
+            registered: Some(ptr),
+            fresh: Cell::new(Fresh::Both),
+        }
+    }
+
+    // Bring the host copy up to date, for reading on the host.
+    fn sync_host(&self) {
+        if self.fresh.get() != Fresh::Device {
+            return;
+        }
+        let device = self.device.get();
+        if self.registered.is_some() {
+            check(
+                unsafe { ffi::cudaDeviceSynchronize() },
+                "cudaDeviceSynchronize",
+            );
+        } else {
+            // The copy waits for the kernels which wrote `device`.
+            self.host.view_mut(&mut |host| {
+                check(
+                    unsafe {
+                        ffi::cudaMemcpy(
+                            host.as_mut_ptr().cast(),
+                            device.cast(),
+                            self.len * size_of::<T>(),
+                            ffi::MEMCPY_DEVICE_TO_HOST,
+                        )
+                    },
+                    "cudaMemcpy",
+                );
+            });
+        }
+        self.fresh.set(Fresh::Both);
+    }
+
+    // Bring the device copy up to date, allocating it if need be, for
+    // reading in a kernel.
+    fn sync_device(&self) -> *mut T {
+        if self.device.get().is_null() {
+            let mut device = null_mut();
+            check(
+                unsafe { ffi::cudaMalloc(&mut device, self.len * size_of::<T>()) },
+                "cudaMalloc",
+            );
+            self.device.set(device.cast());
+        }
+        let device = self.device.get();
+        if self.fresh.get() == Fresh::Host && self.registered.is_none() {
+            self.host.view(&mut |host| {
+                check(
+                    unsafe {
+                        ffi::cudaMemcpy(
+                            device.cast(),
+                            host.as_ptr().cast(),
+                            self.len * size_of::<T>(),
+                            ffi::MEMCPY_HOST_TO_DEVICE,
+                        )
+                    },
+                    "cudaMemcpy",
+                );
+            });
+        }
+        self.fresh.set(Fresh::Both);
+        device
+    }
+}
+
+impl<T> Drop for Mirror<T> {
+    fn drop(&mut self) {
+        // Errors are ignored: the memory is gone either way, and panicking
+        // here could abort an unwinding thread.
+        unsafe {
+            match self.registered {
+                Some(ptr) => {
+                    ffi::cudaDeviceSynchronize();
+                    ffi::cudaHostUnregister(ptr.cast());
+                }
+                None if !self.device.get().is_null() => {
+                    ffi::cudaFree(self.device.get().cast());
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+struct CudaBuffer<T> {
+    mirror: Rc<Mirror<T>>,
+    offset: usize,
+    size: usize,
+}
+
+impl<T> CudaBuffer<T> {
+    fn new(host: Buffer<T>) -> Self {
+        CudaBuffer {
+            offset: 0,
+            size: host.size(),
+            mirror: Rc::new(Mirror::new(host)),
+        }
+    }
+
+    // The buffer's device address, for a kernel which reads it.
+    fn device(&self) -> *const T {
+        unsafe { self.mirror.sync_device().add(self.offset) }
+    }
+
+    // The buffer's device address, for a kernel which reads and writes it.
+    // Any host view after the kernel copies the result back.
+    fn device_mut(&self) -> *mut T {
+        let device = self.mirror.sync_device();
+        self.mirror.fresh.set(Fresh::Device);
+        unsafe { device.add(self.offset) }
+    }
+}
+
+impl<T: 'static> CudaBuffer<T> {
+    // The buffer's host copy, for reading on the host.
+    fn host(&self) -> Buffer<T> {
+        self.mirror.sync_host();
+        self.mirror.host.slice(self.offset, self.size)
+    }
+
+    // The buffer's host copy, for reading and writing on the host.
+    fn host_mut(&self) -> Buffer<T> {
+        let host = self.host();
+        self.mirror.fresh.set(Fresh::Host);
+        host
+    }
+}
+
+impl<T: 'static> BufferTrait<T> for CudaBuffer<T> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn slice(&self, offset: usize, size: usize) -> Buffer<T> {
+        assert!(offset + size <= self.size());
+        Rc::new(CudaBuffer {
+            mirror: Rc::clone(&self.mirror),
+            offset: self.offset + offset,
+            size,
+        })
+    }
+
+    fn view(&self, f: &mut dyn FnMut(&[T])) {
+        self.host().view(f);
+    }
+
+    fn view_mut(&self, f: &mut dyn FnMut(&mut [T])) {
+        self.host_mut().view_mut(f);
+    }
+}
+
+fn cuda<T: 'static>(buf: &Buffer<T>) -> &CudaBuffer<T> {
+    buf.downcast_ref::<CudaBuffer<T>>().unwrap()
+}
+
+fn host<T: 'static>(buf: &Buffer<T>) -> Buffer<T> {
+    cuda(buf).host()
+}
+
+fn host_mut<T: 'static>(buf: &Buffer<T>) -> Buffer<T> {
+    cuda(buf).host_mut()
+}
+
+/// A [Hal] which runs the NTTs, the coset shift and SHA-256 Merkle-leaf
+/// hashing on an NVIDIA GPU, and everything else on the host.
+///
+/// It commits to rows with SHA-256, like [CpuHal::new], so its proofs are
+/// the same as the CPU's.
+pub struct CudaHal {
+    cpu: CpuHal,
+    // Powers of the forward or reverse root of unity for a transform of
+    // `1 << n` elements, by `(n, fwd)`.
+    twiddles: RefCell<BTreeMap<(usize, bool), CudaBuffer<Fp>>>,
+}
+
+impl CudaHal {
+    /// Create a [CudaHal] on the current CUDA device.
+    ///
+    /// Panics if this machine has no CUDA device; check [device_count]
+    /// first, or choose a [HalBackend](super::backend::HalBackend).
+    pub fn new() -> Self {
+        assert!(device_count() > 0, "No CUDA device");
+        CudaHal {
+            cpu: CpuHal::new(),
+            twiddles: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn twiddles(&self, n: usize, fwd: bool) -> *const Fp {
+        let mut twiddles = self.twiddles.borrow_mut();
+        let buf = twiddles.entry((n, fwd)).or_insert_with(|| {
+            let step = match fwd {
+                true => Fp::root_of_unity(n),
+                false => Fp::inv_root_of_unity(n),
+            };
+            let mut cur = Fp::ONE;
+            let powers = (0..1 << (n - 1))
+                .map(|_| {
+                    let power = cur;
+                    cur *= step;
+                    power
+                })
+                .collect();
+            CudaBuffer::new(self.cpu.wrap_vec(powers))
+        });
+        buf.device()
+    }
+
+    // Runs the butterflies of core::ntt on each of the `count` rows of `io`,
+    // one level of the recursion per launch.
+    fn butterflies(&self, io: &Buffer<Fp>, count: usize, fwd: bool, expand_bits: usize) -> usize {
+        let row_size = io.size() / count;
+        let n = log2_ceil(row_size);
+        assert_eq!(row_size * count, io.size());
+        assert_eq!(1 << n, row_size);
+        if n == 0 {
+            return row_size;
+        }
+        let twiddles = self.twiddles(n, fwd);
+        let io = cuda(io).device_mut();
+        let launch = |level: usize| {
+            let err = unsafe {
+                ffi::risc0_cuda_ntt_level(io, twiddles, count as u32, n as u32, level as u32, fwd)
+            };
+            check(err, "NTT");
+        };
+        match fwd {
+            true => (expand_bits + 1..=n).for_each(launch),
+            false => (1..=n).rev().for_each(launch),
+        }
+        row_size
+    }
+}
+
+impl Default for CudaHal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hal for CudaHal {
+    fn alloc<T: 'static + Default + Clone>(&self, size: usize) -> Buffer<T> {
+        Rc::new(CudaBuffer::new(self.cpu.alloc(size)))
+    }
+
+    fn copy_from<T: 'static + Clone>(&self, slice: &[T]) -> Buffer<T> {
+        Rc::new(CudaBuffer::new(self.cpu.copy_from(slice)))
+    }
+
+    fn wrap_vec<T: 'static + Clone>(&self, vec: Vec<T>) -> Buffer<T> {
+        Rc::new(CudaBuffer::new(self.cpu.wrap_vec(vec)))
+    }
+
+    unsafe fn wrap_external<T: 'static + Clone>(&self, ptr: *mut T, len: usize) -> Buffer<T> {
+        let host = self.cpu.wrap_external(ptr, len);
+        Rc::new(CudaBuffer {
+            mirror: Rc::new(Mirror::register(host, ptr)),
+            offset: 0,
+            size: len,
+        })
+    }
+
+    fn batch_expand(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, count: usize) {
+        let out_size = output.size() / count;
+        let in_size = input.size() / count;
+        let expand_bits = log2_ceil(out_size / in_size);
+        assert_eq!(out_size, in_size * (1 << expand_bits));
+        assert_eq!(out_size * count, output.size());
+        assert_eq!(in_size * count, input.size());
+        let err = unsafe {
+            ffi::risc0_cuda_batch_expand(
+                cuda(output).device_mut(),
+                cuda(input).device(),
+                count as u32,
+                out_size as u32,
+                in_size as u32,
+            )
+        };
+        check(err, "batch_expand");
+    }
+
+    fn batch_evaluate_ntt(&self, io: &Buffer<Fp>, count: usize, expand_bits: usize) {
+        self.butterflies(io, count, true, expand_bits);
+    }
+
+    fn batch_interpolate_ntt(&self, io: &Buffer<Fp>, count: usize) {
+        let row_size = self.butterflies(io, count, false, 0);
+        let norm = Fp::new(row_size as u32).inv();
+        let err = unsafe { ffi::risc0_cuda_scale(cuda(io).device_mut(), io.size() as u64, norm) };
+        check(err, "batch_interpolate_ntt");
+    }
+
+    fn batch_bit_reverse(&self, io: &Buffer<Fp>, count: usize) {
+        let row_size = io.size() / count;
+        let n = log2_ceil(row_size);
+        assert_eq!(row_size * count, io.size());
+        assert_eq!(1 << n, row_size);
+        let err =
+            unsafe { ffi::risc0_cuda_bit_reverse(cuda(io).device_mut(), count as u32, n as u32) };
+        check(err, "batch_bit_reverse");
+    }
+
+    fn batch_evaluate_any(
+        &self,
+        coeffs: &Buffer<Fp>,
+        poly_count: usize,
+        which: &Buffer<u32>,
+        xs: &Buffer<Fp4>,
+        out: &Buffer<Fp4>,
+    ) {
+        self.cpu.batch_evaluate_any(
+            &host(coeffs),
+            poly_count,
+            &host(which),
+            &host(xs),
+            &host_mut(out),
+        );
+    }
+
+    fn coset_shift(&self, io: &Buffer<Fp>, poly_count: usize, shift: Fp) {
+        let bits = log2_ceil(io.size() / poly_count);
+        assert_eq!(io.size(), poly_count * (1 << bits));
+        let err = unsafe {
+            ffi::risc0_cuda_coset_shift(
+                cuda(io).device_mut(),
+                poly_count as u32,
+                bits as u32,
+                shift,
+            )
+        };
+        check(err, "coset_shift");
+    }
+
+    fn mix_poly_coeffs(
+        &self,
+        output: &Buffer<Fp4>,
+        mix_start: &Fp4,
+        mix: &Fp4,
+        input: &Buffer<Fp>,
+        combos: &Buffer<u32>,
+        input_size: usize,
+        count: usize,
+    ) {
+        self.cpu.mix_poly_coeffs(
+            &host_mut(output),
+            mix_start,
+            mix,
+            &host(input),
+            &host(combos),
+            input_size,
+            count,
+        );
+    }
+
+    fn eltwise_add_fp(&self, output: &Buffer<Fp>, input1: &Buffer<Fp>, input2: &Buffer<Fp>) {
+        self.cpu
+            .eltwise_add_fp(&host_mut(output), &host(input1), &host(input2));
+    }
+
+    fn eltwise_mul_add_fp(
+        &self,
+        output: &Buffer<Fp>,
+        a: &Buffer<Fp>,
+        b: &Buffer<Fp>,
+        c: &Buffer<Fp>,
+    ) {
+        self.cpu
+            .eltwise_mul_add_fp(&host_mut(output), &host(a), &host(b), &host(c));
+    }
+
+    fn eltwise_sub_mul_fp(&self, output: &Buffer<Fp>, a: &Buffer<Fp>, b: &Buffer<Fp>, k: Fp) {
+        self.cpu
+            .eltwise_sub_mul_fp(&host_mut(output), &host(a), &host(b), k);
+    }
+
+    fn eltwise_fused_fp(&self, output: &Buffer<Fp>, inputs: &[&Buffer<Fp>], expr: &FusedExpr) {
+        let inputs: Vec<Buffer<Fp>> = inputs.iter().map(|input| host(input)).collect();
+        let inputs: Vec<&Buffer<Fp>> = inputs.iter().collect();
+        self.cpu.eltwise_fused_fp(&host_mut(output), &inputs, expr);
+    }
+
+    fn eltwise_sum_fp4(&self, output: &Buffer<Fp>, input: &Buffer<Fp4>) {
+        self.cpu.eltwise_sum_fp4(&host_mut(output), &host(input));
+    }
+
+    fn eltwise_copy_fp(&self, output: &Buffer<Fp>, input: &Buffer<Fp>) {
+        self.cpu.eltwise_copy_fp(&host_mut(output), &host(input));
+    }
+
+    fn eltwise_copy_digest(&self, output: &Buffer<Digest>, input: &Buffer<Digest>) {
+        self.cpu
+            .eltwise_copy_digest(&host_mut(output), &host(input));
+    }
+
+    fn eval_check<P: PolyFp + Sync>(
+        &self,
+        check: &Buffer<Fp>,
+        args: &[&Buffer<Fp>],
+        poly: &P,
+        poly_mix: Fp4,
+        po2: usize,
+    ) {
+        let args: Vec<Buffer<Fp>> = args.iter().map(|arg| host(arg)).collect();
+        let args: Vec<&Buffer<Fp>> = args.iter().collect();
+        self.cpu
+            .eval_check(&host_mut(check), &args, poly, poly_mix, po2);
+    }
+
+    fn fri_fold(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, mix: &Fp4) {
+        self.cpu.fri_fold(&host_mut(output), &host(input), mix);
+    }
+
+    fn sha_rows(&self, output: &Buffer<Digest>, matrix: &Buffer<Fp>) {
+        let count = output.size();
+        let col_size = matrix.size() / output.size();
+        assert_eq!(matrix.size(), col_size * count);
+        let err = unsafe {
+            ffi::risc0_cuda_sha_rows(
+                cuda(output).device_mut(),
+                cuda(matrix).device(),
+                count as u32,
+                col_size as u32,
+            )
+        };
+        check(err, "sha_rows");
+    }
+
+    fn sha_fold(&self, io: &Buffer<Digest>, input_size: usize, output_size: usize) {
+        assert_eq!(input_size, 2 * output_size);
+        assert!(2 * input_size <= io.size());
+        let err = unsafe { ffi::risc0_cuda_sha_fold(cuda(io).device_mut(), output_size as u32) };
+        check(err, "sha_fold");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+    use crate::core::{lde::CosetLde, sha_cpu};
+
+    // Tests pass trivially on machines without a CUDA device.
+    fn gpu() -> Option<CudaHal> {
+        (device_count() > 0).then(CudaHal::new)
+    }
+
+    fn random(size: usize) -> Vec<Fp> {
+        let mut rng = thread_rng();
+        (0..size).map(|_| Fp::random(&mut rng)).collect()
+    }
+
+    fn to_vec<T: Clone>(buf: &Buffer<T>) -> Vec<T> {
+        let mut vec = Vec::new();
+        buf.view(&mut |buf| vec.extend_from_slice(buf));
+        vec
+    }
+
+    #[test]
+    fn lde() {
+        const COUNT: usize = 3;
+        const N: usize = 10;
+        const EXPAND_BITS: usize = 2;
+
+        fn lde<H: Hal>(hal: &H, evals: &[Fp]) -> (Vec<Fp>, Vec<Fp>) {
+            let io = hal.copy_from(evals);
+            hal.batch_interpolate_ntt(&io, COUNT);
+            let coeffs = to_vec(&io);
+            hal.batch_bit_reverse(&io, COUNT);
+            hal.coset_shift(&io, COUNT, CosetLde::ZK.shift());
+            let expanded = hal.alloc(evals.len() << EXPAND_BITS);
+            hal.batch_expand(&expanded, &io, COUNT);
+            hal.batch_evaluate_ntt(&expanded, COUNT, EXPAND_BITS);
+            (coeffs, to_vec(&expanded))
+        }
+
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let evals = random(COUNT << N);
+        assert_eq!(lde(&gpu, &evals), lde(&CpuHal::new(), &evals));
+    }
+
+    #[test]
+    fn merkle() {
+        const ROWS: usize = 64;
+        const COLS: usize = 37;
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let sha = sha_cpu::Impl {};
+        let matrix: Vec<Fp> = random(ROWS * COLS);
+        let nodes = gpu.alloc(ROWS * 2);
+        gpu.sha_rows(&nodes.slice(ROWS, ROWS), &gpu.copy_from(&matrix));
+        gpu.sha_fold(&nodes, ROWS, ROWS / 2);
+        let nodes = to_vec(&nodes);
+        for idx in 0..ROWS {
+            let row: Vec<Fp> = (0..COLS).map(|i| matrix[idx + i * ROWS]).collect();
+            assert_eq!(nodes[ROWS + idx], *sha.hash_fps(&row));
+        }
+        for idx in 0..ROWS / 2 {
+            let pair = sha.hash_pair(&nodes[ROWS + 2 * idx], &nodes[ROWS + 2 * idx + 1]);
+            assert_eq!(nodes[ROWS / 2 + idx], *pair);
+        }
+    }
+
+    #[test]
+    fn host_fallback() {
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let mut data = random(1 << 8);
+        let golden: Vec<Fp> = data.iter().map(|x| *x + *x).collect();
+        let external = unsafe { gpu.wrap_external(data.as_mut_ptr(), data.len()) };
+        // Leaves the device copy newer, so the host op must fetch it.
+        gpu.batch_bit_reverse(&external, 1);
+        gpu.batch_bit_reverse(&external, 1);
+        let owned = gpu.wrap_vec(to_vec(&external));
+        gpu.eltwise_add_fp(&external, &owned, &owned);
+        drop(external);
+        assert_eq!(data, golden);
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Fused element-wise kernels over field element vectors.
+//!
+//! Evaluating a constraint as a sequence of whole-vector operations reads
+//! and writes every intermediate vector, so the cost is dominated by memory
+//! traffic rather than arithmetic. The kernels here evaluate a short chain
+//! of operations per element instead, keeping intermediates in registers.
+//!
+//! Each kernel comes in a scalar form and a SIMD form. The SIMD forms work
+//! on blocks of [LANES] elements with [ElemVec], which uses AVX2 or NEON
+//! where available; the scalar forms handle any remainder and serve as the
+//! reference implementation.
+
+use core::ops;
+
+use crate::{core::fp::Fp, field::baby_bear::ElemVec};
+
+/// Number of elements processed together by the SIMD kernels.
+pub const LANES: usize = crate::field::baby_bear::simd::LANES;
+
+/// Maximum number of intermediate values a [FusedExpr] may need at once.
+pub const MAX_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Input(usize),
+    Const(Fp),
+    Add,
+    Sub,
+    Mul,
+}
+
+/// A short chain of field operations, evaluated element-wise in a single
+/// pass by [Hal::eltwise_fused_fp](super::Hal::eltwise_fused_fp).
+///
+/// Expressions are built with the usual arithmetic operators:
+///
+/// ```
+/// use risc0_zkp::{core::fp::Fp, hal::fused::FusedExpr};
+///
+/// // (a * b + c) * 3
+/// let expr = (FusedExpr::input(0) * FusedExpr::input(1) + FusedExpr::input(2))
+///     * FusedExpr::constant(Fp::new(3));
+/// assert_eq!(expr.num_inputs(), 3);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FusedExpr {
+    // Postfix program; evaluating it leaves exactly one value on the stack.
+    ops: Vec<Op>,
+    depth: usize,
+    num_inputs: usize,
+}
+
+impl FusedExpr {
+    /// The element of input buffer `index`.
+    pub fn input(index: usize) -> Self {
+        FusedExpr {
+            ops: vec![Op::Input(index)],
+            depth: 1,
+            num_inputs: index + 1,
+        }
+    }
+
+    /// The same value `value` for every element.
+    pub fn constant(value: Fp) -> Self {
+        FusedExpr {
+            ops: vec![Op::Const(value)],
+            depth: 1,
+            num_inputs: 0,
+        }
+    }
+
+    /// The number of input buffers this expression reads.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// Evaluates the expression for a single element, given the value of
+    /// each input.
+    pub fn eval(&self, inputs: &[Fp]) -> Fp {
+        assert!(inputs.len() >= self.num_inputs);
+        let mut stack = [Fp::default(); MAX_DEPTH];
+        let mut top = 0;
+        for op in self.ops.iter() {
+            match *op {
+                Op::Input(index) => {
+                    stack[top] = inputs[index];
+                    top += 1;
+                }
+                Op::Const(value) => {
+                    stack[top] = value;
+                    top += 1;
+                }
+                Op::Add | Op::Sub | Op::Mul => {
+                    top -= 1;
+                    let (lhs, rhs) = (stack[top - 1], stack[top]);
+                    stack[top - 1] = match *op {
+                        Op::Add => lhs + rhs,
+                        Op::Sub => lhs - rhs,
+                        _ => lhs * rhs,
+                    };
+                }
+            }
+        }
+        stack[0]
+    }
+
+    // Evaluates one block of LANES elements starting at `offset`.
+    fn eval_lanes(&self, inputs: &[&[Fp]], offset: usize) -> ElemVec {
+        let mut stack = [ElemVec::default(); MAX_DEPTH];
+        let mut top = 0;
+        for op in self.ops.iter() {
+            match *op {
+                Op::Input(index) => {
+                    stack[top] = ElemVec::load(&inputs[index][offset..]);
+                    top += 1;
+                }
+                Op::Const(value) => {
+                    stack[top] = ElemVec::splat(value);
+                    top += 1;
+                }
+                Op::Add | Op::Sub | Op::Mul => {
+                    top -= 1;
+                    let (lhs, rhs) = (stack[top - 1], stack[top]);
+                    stack[top - 1] = match *op {
+                        Op::Add => lhs + rhs,
+                        Op::Sub => lhs - rhs,
+                        _ => lhs * rhs,
+                    };
+                }
+            }
+        }
+        stack[0]
+    }
+
+    fn binary(mut self, rhs: FusedExpr, op: Op) -> Self {
+        // The left operand stays on the stack while the right one is evaluated.
+        let depth = self.depth.max(1 + rhs.depth);
+        assert!(
+            depth <= MAX_DEPTH,
+            "Fused expression needs more than {MAX_DEPTH} intermediates"
+        );
+        self.ops.extend(rhs.ops);
+        self.ops.push(op);
+        self.depth = depth;
+        self.num_inputs = self.num_inputs.max(rhs.num_inputs);
+        self
+    }
+}
+
+impl ops::Add for FusedExpr {
+    type Output = FusedExpr;
+
+    fn add(self, rhs: FusedExpr) -> FusedExpr {
+        self.binary(rhs, Op::Add)
+    }
+}
+
+impl ops::Sub for FusedExpr {
+    type Output = FusedExpr;
+
+    fn sub(self, rhs: FusedExpr) -> FusedExpr {
+        self.binary(rhs, Op::Sub)
+    }
+}
+
+impl ops::Mul for FusedExpr {
+    type Output = FusedExpr;
+
+    fn mul(self, rhs: FusedExpr) -> FusedExpr {
+        self.binary(rhs, Op::Mul)
+    }
+}
+
+/// Computes `out = a * b + c`, one element at a time.
+pub fn mul_add_scalar(out: &mut [Fp], a: &[Fp], b: &[Fp], c: &[Fp]) {
+    for (i, out) in out.iter_mut().enumerate() {
+        *out = a[i] * b[i] + c[i];
+    }
+}
+
+/// Computes `out = a * b + c`, a block of [LANES] elements at a time.
+pub fn mul_add_simd(out: &mut [Fp], a: &[Fp], b: &[Fp], c: &[Fp]) {
+    assert!(a.len() >= out.len() && b.len() >= out.len() && c.len() >= out.len());
+    let bulk = out.len() - out.len() % LANES;
+    for (i, out) in out[..bulk].chunks_exact_mut(LANES).enumerate() {
+        let base = i * LANES;
+        let (a, b, c) = (&a[base..], &b[base..], &c[base..]);
+        (ElemVec::load(a) * ElemVec::load(b) + ElemVec::load(c)).store(out);
+    }
+    mul_add_scalar(&mut out[bulk..], &a[bulk..], &b[bulk..], &c[bulk..]);
+}
+
+/// Computes `out = a - b * k`, one element at a time.
+pub fn sub_mul_scalar(out: &mut [Fp], a: &[Fp], b: &[Fp], k: Fp) {
+    for (i, out) in out.iter_mut().enumerate() {
+        *out = a[i] - b[i] * k;
+    }
+}
+
+/// Computes `out = a - b * k`, a block of [LANES] elements at a time.
+pub fn sub_mul_simd(out: &mut [Fp], a: &[Fp], b: &[Fp], k: Fp) {
+    assert!(a.len() >= out.len() && b.len() >= out.len());
+    let k_vec = ElemVec::splat(k);
+    let bulk = out.len() - out.len() % LANES;
+    for (i, out) in out[..bulk].chunks_exact_mut(LANES).enumerate() {
+        let base = i * LANES;
+        (ElemVec::load(&a[base..]) - ElemVec::load(&b[base..]) * k_vec).store(out);
+    }
+    sub_mul_scalar(&mut out[bulk..], &a[bulk..], &b[bulk..], k);
+}
+
+/// Evaluates `expr` over `inputs`, one element at a time.
+pub fn fused_scalar(out: &mut [Fp], inputs: &[&[Fp]], expr: &FusedExpr) {
+    assert!(inputs.len() >= expr.num_inputs);
+    let mut values = vec![Fp::default(); inputs.len()];
+    for (i, out) in out.iter_mut().enumerate() {
+        for (value, input) in values.iter_mut().zip(inputs) {
+            *value = input[i];
+        }
+        *out = expr.eval(&values);
+    }
+}
+
+/// Evaluates `expr` over `inputs`, a block of [LANES] elements at a time.
+pub fn fused_simd(out: &mut [Fp], inputs: &[&[Fp]], expr: &FusedExpr) {
+    assert!(inputs.len() >= expr.num_inputs);
+    assert!(inputs.iter().all(|input| input.len() >= out.len()));
+    let bulk = out.len() - out.len() % LANES;
+    for (i, out) in out[..bulk].chunks_exact_mut(LANES).enumerate() {
+        expr.eval_lanes(inputs, i * LANES).store(out);
+    }
+    let tail: Vec<&[Fp]> = inputs.iter().map(|input| &input[bulk..]).collect();
+    fused_scalar(&mut out[bulk..], &tail, expr);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+
+    use super::*;
+    use crate::field::Elem;
+
+    fn random(count: usize) -> Vec<Fp> {
+        let mut rng = thread_rng();
+        (0..count).map(|_| Fp::random(&mut rng)).collect()
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        // Not a multiple of LANES, so the tail is exercised too.
+        const COUNT: usize = 1000 + 3;
+        let (a, b, c) = (random(COUNT), random(COUNT), random(COUNT));
+        let k = random(1)[0];
+        let mut scalar = vec![Fp::default(); COUNT];
+        let mut simd = vec![Fp::default(); COUNT];
+
+        mul_add_scalar(&mut scalar, &a, &b, &c);
+        mul_add_simd(&mut simd, &a, &b, &c);
+        assert_eq!(scalar, simd);
+        assert_eq!(scalar[7], a[7] * b[7] + c[7]);
+
+        sub_mul_scalar(&mut scalar, &a, &b, k);
+        sub_mul_simd(&mut simd, &a, &b, k);
+        assert_eq!(scalar, simd);
+        assert_eq!(scalar[7], a[7] - b[7] * k);
+    }
+
+    #[test]
+    fn fused_chain() {
+        const COUNT: usize = 100;
+        let (a, b, c) = (random(COUNT), random(COUNT), random(COUNT));
+        let k = Fp::new(5);
+        let expr = (FusedExpr::input(0) - FusedExpr::input(1) * FusedExpr::constant(k))
+            * (FusedExpr::input(2) + FusedExpr::input(0));
+        assert_eq!(expr.num_inputs(), 3);
+
+        let golden: Vec<Fp> = (0..COUNT)
+            .map(|i| (a[i] - b[i] * k) * (c[i] + a[i]))
+            .collect();
+        let mut scalar = vec![Fp::default(); COUNT];
+        let mut simd = vec![Fp::default(); COUNT];
+        fused_scalar(&mut scalar, &[&a, &b, &c], &expr);
+        fused_simd(&mut simd, &[&a, &b, &c], &expr);
+        assert_eq!(scalar, golden);
+        assert_eq!(simd, golden);
+    }
+
+    #[test]
+    #[should_panic(expected = "intermediates")]
+    fn too_deep() {
+        let mut expr = FusedExpr::input(0);
+        for _ in 0..MAX_DEPTH {
+            expr = FusedExpr::input(0) + expr;
+        }
+    }
+}
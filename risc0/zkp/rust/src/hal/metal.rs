@@ -0,0 +1,805 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A [Hal] which runs on an Apple GPU with Metal, enabled by the `metal`
+//! feature.
+//!
+//! [MetalHal] runs the NTTs, the coset shift, the mixing of polynomial
+//! coefficients, the FRI folds and the Merkle tree's hashing, with SHA-256 or
+//! Poseidon, with the compute shaders of `kernels/metal/zkp.metal`.  Its
+//! buffers live in memory shared by the CPU and GPU, so they need no copies:
+//! each kernel waits for the GPU before returning, and the remaining
+//! operations run through a [CpuHal] over the same memory.
+//!
+//! The constraint polynomial is Rust code generated per circuit rather than
+//! a shader, so [Hal::eval_check] evaluates it on the CPU and divides it by
+//! the zerofier on the GPU.  The element-wise operations and
+//! [Hal::batch_evaluate_any] also run on the CPU; they're small next to the
+//! NTTs.
+
+use core::{cell::RefCell, ffi::c_void, mem::size_of};
+use std::{collections::BTreeMap, rc::Rc};
+
+use ::metal::{
+    BufferRef, CommandQueue, CompileOptions, ComputeCommandEncoderRef, ComputePipelineState,
+    Device, MTLResourceOptions, MTLSize,
+};
+
+use crate::{
+    adapter::PolyFp,
+    core::{
+        fp::Fp,
+        fp4::{Fp4, EXT_SIZE},
+        lde::CosetLde,
+        log2_ceil, poseidon,
+        sha::Digest,
+    },
+    field::{Elem, TwoAdicField},
+    FRI_FOLD, FRI_FOLD_PO2, INV_RATE,
+};
+
+use super::{
+    cpu::{self, CpuHal},
+    fused::FusedExpr,
+    Buffer, BufferTrait, Hal,
+};
+
+const SHADERS: &str = include_str!("../../kernels/metal/zkp.metal");
+
+const KERNELS: &[&str] = &[
+    "batch_expand",
+    "ntt_level",
+    "bit_reverse",
+    "scale",
+    "coset_shift",
+    "mix_poly_coeffs",
+    "fri_fold",
+    "eval_check",
+    "sha_rows",
+    "sha_fold",
+    "poseidon_rows",
+    "poseidon_fold",
+];
+
+// The granularity at which caller-owned memory is mapped for the GPU; that
+// of Apple Silicon's pages, and a multiple of Intel Macs'.
+const PAGE_SIZE: usize = 1 << 14;
+
+/// Whether this machine has a Metal device.
+pub fn is_available() -> bool {
+    Device::system_default().is_some()
+}
+
+/// How much memory the Metal device can use without hurting performance, in
+/// bytes, if there is a device.
+pub fn device_memory() -> Option<u64> {
+    Device::system_default().map(|device| device.recommended_max_working_set_size())
+}
+
+// A Metal buffer in shared memory, and a host buffer over the same memory.
+struct Storage<T> {
+    buf: ::metal::Buffer,
+    // Where the elements start in `buf`, which for `Hal::wrap_external`
+    // maps the whole pages around the caller's memory.
+    byte_offset: usize,
+    host: Buffer<T>,
+}
+
+// Elements are never dropped; the prover's buffers only hold plain data.
+struct MetalBuffer<T> {
+    storage: Rc<Storage<T>>,
+    offset: usize,
+    size: usize,
+}
+
+impl<T> MetalBuffer<T> {
+    fn new(storage: Storage<T>) -> Self {
+        MetalBuffer {
+            offset: 0,
+            size: storage.host.size(),
+            storage: Rc::new(storage),
+        }
+    }
+
+    // The buffer's Metal buffer and byte offset, to bind to a kernel.
+    fn arg(&self) -> (&BufferRef, u64) {
+        let offset = self.storage.byte_offset + self.offset * size_of::<T>();
+        (&self.storage.buf, offset as u64)
+    }
+}
+
+impl<T: 'static> BufferTrait<T> for MetalBuffer<T> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn slice(&self, offset: usize, size: usize) -> Buffer<T> {
+        assert!(offset + size <= self.size());
+        Rc::new(MetalBuffer {
+            storage: Rc::clone(&self.storage),
+            offset: self.offset + offset,
+            size,
+        })
+    }
+
+    fn view(&self, f: &mut dyn FnMut(&[T])) {
+        host(self).view(f);
+    }
+
+    fn view_mut(&self, f: &mut dyn FnMut(&mut [T])) {
+        host(self).view_mut(f);
+    }
+}
+
+fn metal<T: 'static>(buf: &Buffer<T>) -> &MetalBuffer<T> {
+    buf.downcast_ref::<MetalBuffer<T>>().unwrap()
+}
+
+// The buffer's memory as a buffer of the [CpuHal].
+fn host<T: 'static>(buf: &MetalBuffer<T>) -> Buffer<T> {
+    buf.storage.host.slice(buf.offset, buf.size)
+}
+
+fn host_of<T: 'static>(buf: &Buffer<T>) -> Buffer<T> {
+    host(metal(buf))
+}
+
+// The Montgomery form of `x`, as the shaders take it.
+fn raw(x: Fp) -> u32 {
+    // Fp is a transparent wrapper of its Montgomery form.
+    unsafe { core::mem::transmute(x) }
+}
+
+// The Poseidon constants, as the shaders take them.
+struct PoseidonConsts {
+    round_constants: MetalBuffer<Fp>,
+    cauchy: MetalBuffer<Fp>,
+}
+
+/// A [Hal] which runs the NTTs, the FRI folds and Merkle tree hashing on an
+/// Apple GPU, and the rest on the CPU.
+///
+/// [MetalHal::new] commits to rows with SHA-256, like [CpuHal::new], and
+/// [MetalHal::with_poseidon] with Poseidon, like a [CpuHal] of
+/// [poseidon::Impl]; either way its proofs are the same as the CPU's.
+pub struct MetalHal {
+    device: Device,
+    queue: CommandQueue,
+    pipelines: BTreeMap<&'static str, ComputePipelineState>,
+    cpu: CpuHal,
+    // Hashes with Poseidon rather than SHA-256 if set.
+    poseidon: Option<PoseidonConsts>,
+    // Powers of the forward or reverse root of unity for a transform of
+    // `1 << n` elements, by `(n, fwd)`.
+    twiddles: RefCell<BTreeMap<(usize, bool), MetalBuffer<Fp>>>,
+}
+
+impl MetalHal {
+    /// Create a [MetalHal] on the system's default Metal device, compiling
+    /// its shaders.
+    ///
+    /// Panics if this machine has no Metal device; check [is_available]
+    /// first, or choose a [HalBackend](super::backend::HalBackend).
+    pub fn new() -> Self {
+        let device = Device::system_default().expect("No Metal device");
+        let library = device
+            .new_library_with_source(SHADERS, &CompileOptions::new())
+            .unwrap_or_else(|err| panic!("Failed to compile the Metal shaders: {err}"));
+        let pipelines = KERNELS
+            .iter()
+            .map(|name| {
+                let function = library.get_function(name, None).unwrap();
+                let pipeline = device
+                    .new_compute_pipeline_state_with_function(&function)
+                    .unwrap_or_else(|err| panic!("Failed to build Metal kernel {name}: {err}"));
+                (*name, pipeline)
+            })
+            .collect();
+        MetalHal {
+            queue: device.new_command_queue(),
+            device,
+            pipelines,
+            cpu: CpuHal::new(),
+            poseidon: None,
+            twiddles: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Create a [MetalHal] like [MetalHal::new], which commits to rows with
+    /// Poseidon rather than SHA-256.
+    pub fn with_poseidon() -> Self {
+        let mut hal = Self::new();
+        hal.poseidon = Some(PoseidonConsts {
+            round_constants: hal.new_buffer(poseidon::ROUND_CONSTANTS.len(), |i| {
+                poseidon::ROUND_CONSTANTS[i]
+            }),
+            cauchy: hal.new_buffer(poseidon::CAUCHY.len(), |i| poseidon::CAUCHY[i]),
+        });
+        hal
+    }
+
+    fn new_buffer<T>(&self, len: usize, mut init: impl FnMut(usize) -> T) -> MetalBuffer<T>
+    where
+        T: 'static + Clone,
+    {
+        let bytes = (len * size_of::<T>()).max(1) as u64;
+        let buf = self
+            .device
+            .new_buffer(bytes, MTLResourceOptions::StorageModeShared);
+        let ptr = buf.contents() as *mut T;
+        for i in 0..len {
+            unsafe { ptr.add(i).write(init(i)) };
+        }
+        let host = unsafe { self.cpu.wrap_external(ptr, len) };
+        MetalBuffer::new(Storage {
+            buf,
+            byte_offset: 0,
+            host,
+        })
+    }
+
+    // Encodes the dispatches of `f` into a command buffer, and waits for the
+    // GPU to run them.  Dispatches run in order, each seeing the writes of
+    // the ones before.
+    fn run(&self, f: impl FnOnce(&Encoder)) {
+        let cmd = self.queue.new_command_buffer();
+        let encoder = cmd.new_compute_command_encoder();
+        f(&Encoder { hal: self, encoder });
+        encoder.end_encoding();
+        cmd.commit();
+        cmd.wait_until_completed();
+    }
+
+    fn twiddles(&self, n: usize, fwd: bool) -> (::metal::Buffer, u64) {
+        let mut twiddles = self.twiddles.borrow_mut();
+        let buf = twiddles.entry((n, fwd)).or_insert_with(|| {
+            let step = match fwd {
+                true => Fp::root_of_unity(n),
+                false => Fp::inv_root_of_unity(n),
+            };
+            let mut cur = Fp::ONE;
+            self.new_buffer(1 << (n - 1), |_| {
+                let power = cur;
+                cur *= step;
+                power
+            })
+        });
+        let (twiddles, offset) = buf.arg();
+        (twiddles.to_owned(), offset)
+    }
+
+    // Runs the butterflies of core::ntt on each of the `count` rows of `io`,
+    // one level of the recursion per dispatch.
+    fn butterflies(&self, io: &Buffer<Fp>, count: usize, fwd: bool, expand_bits: usize) -> usize {
+        let row_size = io.size() / count;
+        let n = log2_ceil(row_size);
+        assert_eq!(row_size * count, io.size());
+        assert_eq!(1 << n, row_size);
+        if n == 0 {
+            return row_size;
+        }
+        let (twiddles, twiddles_offset) = self.twiddles(n, fwd);
+        let levels: Vec<usize> = match fwd {
+            true => (expand_bits + 1..=n).collect(),
+            false => (1..=n).rev().collect(),
+        };
+        self.run(|encoder| {
+            for level in levels {
+                encoder.dispatch(
+                    "ntt_level",
+                    io.size() / 2,
+                    &[metal(io).arg(), (&*twiddles, twiddles_offset)],
+                    &[n as u32, level as u32, fwd as u32],
+                );
+            }
+        });
+        row_size
+    }
+}
+
+impl Default for MetalHal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Encoder<'a> {
+    hal: &'a MetalHal,
+    encoder: &'a ComputeCommandEncoderRef,
+}
+
+impl<'a> Encoder<'a> {
+    // Dispatches `threads` threads of `kernel`, whose arguments are
+    // `buffers` followed by `scalars`.
+    fn dispatch(
+        &self,
+        kernel: &str,
+        threads: usize,
+        buffers: &[(&BufferRef, u64)],
+        scalars: &[u32],
+    ) {
+        if threads == 0 {
+            return;
+        }
+        let pipeline = &self.hal.pipelines[kernel];
+        self.encoder.set_compute_pipeline_state(pipeline);
+        for (index, (buf, offset)) in buffers.iter().enumerate() {
+            self.encoder.set_buffer(index as u64, Some(*buf), *offset);
+        }
+        for (index, scalar) in scalars.iter().enumerate() {
+            self.encoder.set_bytes(
+                (buffers.len() + index) as u64,
+                size_of::<u32>() as u64,
+                scalar as *const u32 as *const c_void,
+            );
+        }
+        let group = pipeline
+            .max_total_threads_per_threadgroup()
+            .min(threads as u64);
+        self.encoder.dispatch_threads(
+            MTLSize {
+                width: threads as u64,
+                height: 1,
+                depth: 1,
+            },
+            MTLSize {
+                width: group,
+                height: 1,
+                depth: 1,
+            },
+        );
+    }
+}
+
+impl Hal for MetalHal {
+    fn alloc<T: 'static + Default + Clone>(&self, size: usize) -> Buffer<T> {
+        Rc::new(self.new_buffer(size, |_| T::default()))
+    }
+
+    fn copy_from<T: 'static + Clone>(&self, slice: &[T]) -> Buffer<T> {
+        Rc::new(self.new_buffer(slice.len(), |i| slice[i].clone()))
+    }
+
+    fn wrap_vec<T: 'static + Clone>(&self, vec: Vec<T>) -> Buffer<T> {
+        let len = vec.len();
+        let mut elems = vec.into_iter();
+        Rc::new(self.new_buffer(len, |_| elems.next().unwrap()))
+    }
+
+    unsafe fn wrap_external<T: 'static + Clone>(&self, ptr: *mut T, len: usize) -> Buffer<T> {
+        // Metal only maps whole pages of host memory.
+        let start = ptr as usize & !(PAGE_SIZE - 1);
+        let end = (ptr as usize + (len * size_of::<T>()).max(1) + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let buf = self.device.new_buffer_with_bytes_no_copy(
+            start as *const c_void,
+            (end - start) as u64,
+            MTLResourceOptions::StorageModeShared,
+            None,
+        );
+        Rc::new(MetalBuffer::new(Storage {
+            buf,
+            byte_offset: ptr as usize - start,
+            host: self.cpu.wrap_external(ptr, len),
+        }))
+    }
+
+    fn batch_expand(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, count: usize) {
+        let out_size = output.size() / count;
+        let in_size = input.size() / count;
+        let expand_bits = log2_ceil(out_size / in_size);
+        assert_eq!(out_size, in_size * (1 << expand_bits));
+        assert_eq!(out_size * count, output.size());
+        assert_eq!(in_size * count, input.size());
+        self.run(|encoder| {
+            encoder.dispatch(
+                "batch_expand",
+                output.size(),
+                &[metal(output).arg(), metal(input).arg()],
+                &[out_size as u32, in_size as u32],
+            );
+        });
+    }
+
+    fn batch_evaluate_ntt(&self, io: &Buffer<Fp>, count: usize, expand_bits: usize) {
+        self.butterflies(io, count, true, expand_bits);
+    }
+
+    fn batch_interpolate_ntt(&self, io: &Buffer<Fp>, count: usize) {
+        let row_size = self.butterflies(io, count, false, 0);
+        let norm = Fp::new(row_size as u32).inv();
+        self.run(|encoder| {
+            encoder.dispatch("scale", io.size(), &[metal(io).arg()], &[raw(norm)]);
+        });
+    }
+
+    fn batch_bit_reverse(&self, io: &Buffer<Fp>, count: usize) {
+        let row_size = io.size() / count;
+        let n = log2_ceil(row_size);
+        assert_eq!(row_size * count, io.size());
+        assert_eq!(1 << n, row_size);
+        self.run(|encoder| {
+            encoder.dispatch("bit_reverse", io.size(), &[metal(io).arg()], &[n as u32]);
+        });
+    }
+
+    fn batch_evaluate_any(
+        &self,
+        coeffs: &Buffer<Fp>,
+        poly_count: usize,
+        which: &Buffer<u32>,
+        xs: &Buffer<Fp4>,
+        out: &Buffer<Fp4>,
+    ) {
+        self.cpu.batch_evaluate_any(
+            &host_of(coeffs),
+            poly_count,
+            &host_of(which),
+            &host_of(xs),
+            &host_of(out),
+        );
+    }
+
+    fn coset_shift(&self, io: &Buffer<Fp>, poly_count: usize, shift: Fp) {
+        let bits = log2_ceil(io.size() / poly_count);
+        assert_eq!(io.size(), poly_count * (1 << bits));
+        self.run(|encoder| {
+            encoder.dispatch(
+                "coset_shift",
+                io.size(),
+                &[metal(io).arg()],
+                &[bits as u32, raw(shift)],
+            );
+        });
+    }
+
+    fn mix_poly_coeffs(
+        &self,
+        output: &Buffer<Fp4>,
+        mix_start: &Fp4,
+        mix: &Fp4,
+        input: &Buffer<Fp>,
+        combos: &Buffer<u32>,
+        input_size: usize,
+        count: usize,
+    ) {
+        assert_eq!(input.size(), input_size * count);
+        assert_eq!(combos.size(), input_size);
+        let mix = self.copy_from(&[*mix_start, *mix]);
+        self.run(|encoder| {
+            encoder.dispatch(
+                "mix_poly_coeffs",
+                count,
+                &[
+                    metal(output).arg(),
+                    metal(input).arg(),
+                    metal(combos).arg(),
+                    metal(&mix).arg(),
+                ],
+                &[input_size as u32, count as u32],
+            );
+        });
+    }
+
+    fn eltwise_add_fp(&self, output: &Buffer<Fp>, input1: &Buffer<Fp>, input2: &Buffer<Fp>) {
+        self.cpu
+            .eltwise_add_fp(&host_of(output), &host_of(input1), &host_of(input2));
+    }
+
+    fn eltwise_mul_add_fp(
+        &self,
+        output: &Buffer<Fp>,
+        a: &Buffer<Fp>,
+        b: &Buffer<Fp>,
+        c: &Buffer<Fp>,
+    ) {
+        self.cpu
+            .eltwise_mul_add_fp(&host_of(output), &host_of(a), &host_of(b), &host_of(c));
+    }
+
+    fn eltwise_sub_mul_fp(&self, output: &Buffer<Fp>, a: &Buffer<Fp>, b: &Buffer<Fp>, k: Fp) {
+        self.cpu
+            .eltwise_sub_mul_fp(&host_of(output), &host_of(a), &host_of(b), k);
+    }
+
+    fn eltwise_fused_fp(&self, output: &Buffer<Fp>, inputs: &[&Buffer<Fp>], expr: &FusedExpr) {
+        let inputs: Vec<Buffer<Fp>> = inputs.iter().map(|input| host_of(input)).collect();
+        let inputs: Vec<&Buffer<Fp>> = inputs.iter().collect();
+        self.cpu.eltwise_fused_fp(&host_of(output), &inputs, expr);
+    }
+
+    fn eltwise_sum_fp4(&self, output: &Buffer<Fp>, input: &Buffer<Fp4>) {
+        self.cpu.eltwise_sum_fp4(&host_of(output), &host_of(input));
+    }
+
+    fn eltwise_copy_fp(&self, output: &Buffer<Fp>, input: &Buffer<Fp>) {
+        self.cpu.eltwise_copy_fp(&host_of(output), &host_of(input));
+    }
+
+    fn eltwise_copy_digest(&self, output: &Buffer<Digest>, input: &Buffer<Digest>) {
+        self.cpu
+            .eltwise_copy_digest(&host_of(output), &host_of(input));
+    }
+
+    fn eval_check<P: PolyFp + Sync>(
+        &self,
+        check: &Buffer<Fp>,
+        args: &[&Buffer<Fp>],
+        poly: &P,
+        poly_mix: Fp4,
+        po2: usize,
+    ) {
+        let domain = (1 << po2) * INV_RATE;
+        assert_eq!(check.size(), EXT_SIZE * domain);
+        let args: Vec<Buffer<Fp>> = args.iter().map(|arg| host_of(arg)).collect();
+        let args: Vec<&Buffer<Fp>> = args.iter().collect();
+        let tots = self.wrap_vec(cpu::eval_constraints(&args, poly, poly_mix, po2));
+        let root = Fp::root_of_unity(po2 + log2_ceil(INV_RATE));
+        self.run(|encoder| {
+            encoder.dispatch(
+                "eval_check",
+                domain,
+                &[metal(check).arg(), metal(&tots).arg()],
+                &[
+                    domain as u32,
+                    po2 as u32,
+                    raw(root),
+                    raw(CosetLde::ZK.shift()),
+                ],
+            );
+        });
+    }
+
+    fn fri_fold(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, mix: &Fp4) {
+        let count = output.size() / EXT_SIZE;
+        assert_eq!(output.size(), count * EXT_SIZE);
+        assert_eq!(input.size(), output.size() * FRI_FOLD);
+        let mix = self.copy_from(&[*mix]);
+        self.run(|encoder| {
+            encoder.dispatch(
+                "fri_fold",
+                count,
+                &[metal(output).arg(), metal(input).arg(), metal(&mix).arg()],
+                &[count as u32, FRI_FOLD_PO2 as u32],
+            );
+        });
+    }
+
+    fn sha_rows(&self, output: &Buffer<Digest>, matrix: &Buffer<Fp>) {
+        let count = output.size();
+        let col_size = matrix.size() / output.size();
+        assert_eq!(matrix.size(), col_size * count);
+        self.run(|encoder| match &self.poseidon {
+            None => encoder.dispatch(
+                "sha_rows",
+                count,
+                &[metal(output).arg(), metal(matrix).arg()],
+                &[count as u32, col_size as u32],
+            ),
+            Some(consts) => encoder.dispatch(
+                "poseidon_rows",
+                count,
+                &[
+                    metal(output).arg(),
+                    metal(matrix).arg(),
+                    consts.round_constants.arg(),
+                    consts.cauchy.arg(),
+                ],
+                &[count as u32, col_size as u32, raw(Fp::new(col_size as u32))],
+            ),
+        });
+    }
+
+    fn sha_fold(&self, io: &Buffer<Digest>, input_size: usize, output_size: usize) {
+        assert_eq!(input_size, 2 * output_size);
+        assert!(2 * input_size <= io.size());
+        self.run(|encoder| match &self.poseidon {
+            None => encoder.dispatch(
+                "sha_fold",
+                output_size,
+                &[metal(io).arg()],
+                &[output_size as u32],
+            ),
+            Some(consts) => encoder.dispatch(
+                "poseidon_fold",
+                output_size,
+                &[
+                    metal(io).arg(),
+                    consts.round_constants.arg(),
+                    consts.cauchy.arg(),
+                ],
+                &[output_size as u32],
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use super::*;
+    use crate::{
+        adapter::{MixState, PolyFpContext},
+        core::{sha::Sha, sha_cpu},
+    };
+
+    // Tests pass trivially on machines without a Metal device.
+    fn gpu() -> Option<MetalHal> {
+        is_available().then(MetalHal::new)
+    }
+
+    fn random(size: usize) -> Vec<Fp> {
+        let mut rng = thread_rng();
+        (0..size).map(|_| Fp::random(&mut rng)).collect()
+    }
+
+    fn to_vec<T: Clone>(buf: &Buffer<T>) -> Vec<T> {
+        let mut vec = Vec::new();
+        buf.view(&mut |buf| vec.extend_from_slice(buf));
+        vec
+    }
+
+    #[test]
+    fn lde() {
+        const COUNT: usize = 3;
+        const N: usize = 10;
+        const EXPAND_BITS: usize = 2;
+
+        fn lde<H: Hal>(hal: &H, evals: &[Fp]) -> (Vec<Fp>, Vec<Fp>) {
+            let io = hal.copy_from(evals);
+            hal.batch_interpolate_ntt(&io, COUNT);
+            let coeffs = to_vec(&io);
+            hal.batch_bit_reverse(&io, COUNT);
+            hal.coset_shift(&io, COUNT, CosetLde::ZK.shift());
+            let expanded = hal.alloc(evals.len() << EXPAND_BITS);
+            hal.batch_expand(&expanded, &io, COUNT);
+            hal.batch_evaluate_ntt(&expanded, COUNT, EXPAND_BITS);
+            (coeffs, to_vec(&expanded))
+        }
+
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let evals = random(COUNT << N);
+        assert_eq!(lde(&gpu, &evals), lde(&CpuHal::new(), &evals));
+    }
+
+    fn merkle<S: Sha>(gpu: MetalHal, sha: S) {
+        const ROWS: usize = 64;
+        const COLS: usize = 37;
+        let matrix: Vec<Fp> = random(ROWS * COLS);
+        let nodes = gpu.alloc(ROWS * 2);
+        gpu.sha_rows(&nodes.slice(ROWS, ROWS), &gpu.copy_from(&matrix));
+        gpu.sha_fold(&nodes, ROWS, ROWS / 2);
+        let nodes = to_vec(&nodes);
+        for idx in 0..ROWS {
+            let row: Vec<Fp> = (0..COLS).map(|i| matrix[idx + i * ROWS]).collect();
+            assert_eq!(nodes[ROWS + idx], *sha.hash_fps(&row));
+        }
+        for idx in 0..ROWS / 2 {
+            let pair = sha.hash_pair(&nodes[ROWS + 2 * idx], &nodes[ROWS + 2 * idx + 1]);
+            assert_eq!(nodes[ROWS / 2 + idx], *pair);
+        }
+    }
+
+    #[test]
+    fn merkle_sha() {
+        if let Some(gpu) = gpu() {
+            merkle(gpu, sha_cpu::Impl {});
+        }
+    }
+
+    #[test]
+    fn merkle_poseidon() {
+        if is_available() {
+            merkle(MetalHal::with_poseidon(), poseidon::Impl {});
+        }
+    }
+
+    #[test]
+    fn fri_fold() {
+        const COUNT: usize = 1 << 6;
+
+        fn fold<H: Hal>(hal: &H, input: &[Fp], mix: &Fp4) -> Vec<Fp> {
+            let output = hal.alloc(COUNT * EXT_SIZE);
+            hal.fri_fold(&output, &hal.copy_from(input), mix);
+            to_vec(&output)
+        }
+
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let input = random(COUNT * EXT_SIZE * FRI_FOLD);
+        let mix = Fp4::random(&mut thread_rng());
+        assert_eq!(fold(&gpu, &input, &mix), fold(&CpuHal::new(), &input, &mix));
+    }
+
+    // Constrains the product of one column and the previous row of the
+    // other, and their difference.
+    struct Product;
+
+    impl PolyFp for Product {
+        fn poly_fp(&self, ctx: &PolyFpContext, args: &[&[Fp]]) -> MixState {
+            let a = ctx._get(args[0], 0, 0, 0, "");
+            let b = ctx._get(args[0], 1, 1, 0, "");
+            let x = ctx._true("");
+            let x = ctx._and_eqz(x, a * b, "");
+            ctx._and_eqz(x, a - b, "")
+        }
+    }
+
+    #[test]
+    fn eval_check() {
+        const PO2: usize = 5;
+
+        fn check<H: Hal>(hal: &H, trace: &[Fp], mix: Fp4) -> Vec<Fp> {
+            let check = hal.alloc((EXT_SIZE * INV_RATE) << PO2);
+            hal.eval_check(&check, &[&hal.copy_from(trace)], &Product, mix, PO2);
+            to_vec(&check)
+        }
+
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let trace = random((2 * INV_RATE) << PO2);
+        let mix = Fp4::random(&mut thread_rng());
+        assert_eq!(check(&gpu, &trace, mix), check(&CpuHal::new(), &trace, mix));
+    }
+
+    #[test]
+    fn mix_poly_coeffs() {
+        const INPUT_SIZE: usize = 5;
+        const COUNT: usize = 1 << 8;
+
+        fn mix<H: Hal>(hal: &H, input: &[Fp], mix: &[Fp4]) -> Vec<Fp4> {
+            let output = hal.alloc(2 * COUNT);
+            let combos = hal.copy_from(&[0, 1, 1, 0, 1]);
+            let input = hal.copy_from(input);
+            hal.mix_poly_coeffs(
+                &output, &mix[0], &mix[1], &input, &combos, INPUT_SIZE, COUNT,
+            );
+            to_vec(&output)
+        }
+
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let input = random(INPUT_SIZE * COUNT);
+        let mut rng = thread_rng();
+        let factors = [Fp4::random(&mut rng), Fp4::random(&mut rng)];
+        assert_eq!(
+            mix(&gpu, &input, &factors),
+            mix(&CpuHal::new(), &input, &factors)
+        );
+    }
+
+    #[test]
+    fn external_buffers() {
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let mut data = random(1 << 8);
+        let mut golden = data.clone();
+        crate::core::ntt::bit_reverse(&mut golden);
+        let external = unsafe { gpu.wrap_external(data.as_mut_ptr(), data.len()) };
+        gpu.batch_bit_reverse(&external, 1);
+        drop(external);
+        assert_eq!(data, golden);
+    }
+}
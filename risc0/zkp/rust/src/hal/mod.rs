@@ -12,11 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! The hardware abstraction layer the prover runs its kernels through.
+//!
+//! The prover keeps its polynomials in [Buffer]s allocated by a [Hal], and
+//! performs the expensive steps of a proof, the NTTs and coset LDE, the
+//! Merkle-tree hashing, constraint evaluation and the FRI folds, with the
+//! [Hal]'s methods.  [cpu::CpuHal] is the reference implementation; another
+//! backend, such as an accelerator, implements [Hal] over its own buffers and
+//! is passed to `prove::prove` in its place.
+
+pub mod backend;
 pub mod cpu;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+pub mod fused;
+#[cfg(feature = "metal")]
+pub mod metal;
 
 use std::rc::Rc;
 
-use crate::core::{fp::Fp, fp4::Fp4, sha::Digest};
+use self::fused::FusedExpr;
+use crate::{
+    adapter::PolyFp,
+    core::{fp::Fp, fp4::Fp4, sha::Digest},
+};
 use downcast_rs::{impl_downcast, Downcast};
 
 pub type Buffer<T> = Rc<dyn BufferTrait<T>>;
@@ -33,11 +52,29 @@ pub trait BufferTrait<T>: Downcast {
 
 impl_downcast!(BufferTrait<T>);
 
+/// A backend for the prover's kernels.  Buffers passed to a [Hal] must have
+/// been allocated by the same [Hal].
 pub trait Hal {
     fn alloc<T: 'static + Default + Clone>(&self, size: usize) -> Buffer<T>;
 
     fn copy_from<T: 'static + Clone>(&self, slice: &[T]) -> Buffer<T>;
 
+    /// Create a buffer holding the contents of `vec`, without copying them
+    /// where the backend can address host memory directly.
+    fn wrap_vec<T: 'static + Clone>(&self, vec: Vec<T>) -> Buffer<T>;
+
+    /// Create a buffer over `len` elements of caller-owned memory at `ptr`,
+    /// without copying them. Backends which can't address host memory
+    /// directly, such as GPUs, should pin the memory and map it instead.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes of `len` elements of `T` for
+    /// as long as the returned buffer, or any slice of it, is alive. During
+    /// that time the memory must not be accessed other than through the
+    /// buffer.
+    unsafe fn wrap_external<T: 'static + Clone>(&self, ptr: *mut T, len: usize) -> Buffer<T>;
+
     fn batch_expand(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, count: usize);
 
     fn batch_evaluate_ntt(&self, io: &Buffer<Fp>, count: usize, expand_bits: usize);
@@ -55,7 +92,18 @@ pub trait Hal {
         out: &Buffer<Fp4>,
     );
 
-    fn zk_shift(&self, io: &Buffer<Fp>, count: usize);
+    /// Convert each of the `count` polynomials in `io`, whose coefficients
+    /// are in bit-reversed order, from `f(x)` to `f(shift * x)`, so that
+    /// evaluating them on the subgroup of roots of unity evaluates the
+    /// originals on its coset by `shift`; see
+    /// [CosetLde](crate::core::lde::CosetLde).
+    fn coset_shift(&self, io: &Buffer<Fp>, count: usize, shift: Fp);
+
+    /// [Hal::coset_shift] by the prover's shift, that of
+    /// [CosetLde::ZK](crate::core::lde::CosetLde::ZK).
+    fn zk_shift(&self, io: &Buffer<Fp>, count: usize) {
+        self.coset_shift(io, count, crate::core::lde::CosetLde::ZK.shift());
+    }
 
     fn mix_poly_coeffs(
         &self,
@@ -70,12 +118,42 @@ pub trait Hal {
 
     fn eltwise_add_fp(&self, output: &Buffer<Fp>, input1: &Buffer<Fp>, input2: &Buffer<Fp>);
 
+    /// Computes `output = a * b + c` in a single pass.
+    fn eltwise_mul_add_fp(
+        &self,
+        output: &Buffer<Fp>,
+        a: &Buffer<Fp>,
+        b: &Buffer<Fp>,
+        c: &Buffer<Fp>,
+    );
+
+    /// Computes `output = a - b * k` in a single pass.
+    fn eltwise_sub_mul_fp(&self, output: &Buffer<Fp>, a: &Buffer<Fp>, b: &Buffer<Fp>, k: Fp);
+
+    /// Evaluates `expr` element-wise over `inputs` in a single pass.
+    fn eltwise_fused_fp(&self, output: &Buffer<Fp>, inputs: &[&Buffer<Fp>], expr: &FusedExpr);
+
     fn eltwise_sum_fp4(&self, output: &Buffer<Fp>, input: &Buffer<Fp4>);
 
     fn eltwise_copy_fp(&self, output: &Buffer<Fp>, input: &Buffer<Fp>);
 
     fn eltwise_copy_digest(&self, output: &Buffer<Digest>, input: &Buffer<Digest>);
 
+    /// Evaluates the constraints of `poly` at each point of the prover's
+    /// LDE domain for a trace of `1 << po2` cycles, divides them by the
+    /// trace domain's vanishing polynomial, and stores the results in the
+    /// [EXT_SIZE](crate::core::fp4::EXT_SIZE) columns of `check`.  `args`
+    /// are the evaluated register groups and globals, in the order `poly`
+    /// reads them.
+    fn eval_check<P: PolyFp + Sync>(
+        &self,
+        check: &Buffer<Fp>,
+        args: &[&Buffer<Fp>],
+        poly: &P,
+        poly_mix: Fp4,
+        po2: usize,
+    );
+
     fn fri_fold(&self, output: &Buffer<Fp>, input: &Buffer<Fp>, mix: &Fp4);
 
     fn sha_rows(&self, output: &Buffer<Digest>, matrix: &Buffer<Fp>);
@@ -16,15 +16,20 @@
 
 extern crate alloc;
 
-#[cfg(any(feature = "prove", feature = "verify"))]
+#[cfg(any(feature = "hal", feature = "prove", feature = "verify"))]
 pub mod adapter;
+#[cfg(any(feature = "prove", feature = "verify"))]
+pub mod commit;
 pub mod core;
 #[cfg(feature = "hal")]
 pub mod hal;
 mod merkle;
+#[cfg(feature = "parallel")]
+pub mod parallel;
 #[cfg(feature = "prove")]
 pub mod prove;
 pub mod taps;
+pub mod util;
 #[cfg(feature = "verify")]
 pub mod verify;
 
@@ -37,6 +42,22 @@ pub const MAX_CYCLES: usize = 1 << MAX_CYCLES_PO2;
 /// ~100 bits of conjectured security
 pub const QUERIES: usize = 50;
 pub const ZK_CYCLES: usize = QUERIES;
+/// Set in the po2 word of a seal whose trace was padded with zeros instead
+/// of random values, giving integrity but not zero knowledge. Such seals
+/// also commit to this flag before anything else, so it can't be changed
+/// without invalidating the proof.
+pub const NON_ZK_FLAG: u32 = 1 << 31;
+/// Set in the po2 word of a seal whose Merkle openings are batched: a
+/// column or node the verifier already has from an earlier query isn't
+/// sent again.  Seals from the C++ prover don't set it.
+pub const BATCHED_OPENINGS_FLAG: u32 = 1 << 28;
+/// Set in the po2 word of a seal whose data and accumulator trees leave
+/// constant columns out of their leaves.  After the top of each such tree,
+/// the seal lists the index and value of every constant column, and commits
+/// to the list along with the root.  This keeps a trace padded with zeros
+/// from hashing the padding column by column.  Seals from the C++ prover
+/// don't set it.
+pub const CONSTANT_COLUMNS_FLAG: u32 = 1 << 27;
 pub const MIN_PO2: usize = core::log2_ceil(1 + ZK_CYCLES);
 
 pub const INV_RATE: usize = 4;
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::to_po2;
+use alloc::vec::Vec;
+
+use crate::core::{fp::Fp, to_po2};
 
 /// The parameters of a merkle tree of prime field elements, including:
 /// row_size - the number of leaves in the tree
@@ -60,3 +62,11 @@ impl MerkleTreeParams {
         }
     }
 }
+
+/// Puts the constant columns a tree left out of its leaves back into `row`,
+/// given their indices, in increasing order, and values.
+pub fn insert_constants(row: &mut Vec<Fp>, constants: &[(usize, Fp)]) {
+    for (col, value) in constants {
+        row.insert(*col, *value);
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration for spreading proving work across threads.
+//!
+//! Parallel work runs on rayon's current thread pool: the global pool, or
+//! the pool of an enclosing [rayon::ThreadPool::install].
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Buffers smaller than this can't be split below their vector width.
+const MIN_NTT_SPLIT_PO2: usize = 4;
+
+static NTT_SPLIT_PO2: AtomicUsize = AtomicUsize::new(14);
+
+/// Build rayon's global thread pool with `threads` threads, or with one per
+/// CPU if `threads` is zero.  Returns false if the global pool already
+/// exists, having been built by an earlier call or by its first use, in
+/// which case it keeps its size.
+pub fn init_thread_pool(threads: usize) -> bool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .is_ok()
+}
+
+/// Split single NTTs of at least `1 << po2` elements across threads.
+/// Smaller NTTs, which don't repay the cost of handing out work, run on
+/// one thread; the default is 14.  Values below 4 are raised to 4.
+pub fn set_ntt_split_po2(po2: usize) {
+    NTT_SPLIT_PO2.store(po2.max(MIN_NTT_SPLIT_PO2), Ordering::Relaxed);
+}
+
+/// The smallest size, as a power of two, of an NTT which is split across
+/// threads; see [set_ntt_split_po2].
+pub fn ntt_split_po2() -> usize {
+    NTT_SPLIT_PO2.load(Ordering::Relaxed)
+}
@@ -12,24 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, ops::Range};
 
 use rand::thread_rng;
+use rayon::prelude::*;
 
 use crate::{
-    adapter::{CircuitDef, CircuitStepContext, CustomStep, PolyFpContext},
+    adapter::{CircuitDef, CircuitStepAccum, CircuitStepContext, CustomStep, NoCustomStep},
     core::{
         fp::Fp,
         fp4::{Fp4, EXT_SIZE},
-        log2_ceil,
-        rou::ROU_FWD,
-        sha::Sha,
+        hash::SUITE_ID_SHIFT,
+        sha::{Digest, DIGEST_WORDS},
     },
     field::Elem,
-    hal::Buffer,
+    hal::{Buffer, Hal},
     prove::{executor::Executor, write_iop::WriteIOP, Circuit},
     taps::{RegisterGroup, TapSet},
-    INV_RATE, ZK_CYCLES,
+    BATCHED_OPENINGS_FLAG, CONSTANT_COLUMNS_FLAG, NON_ZK_FLAG, ZK_CYCLES,
 };
 
 pub struct ProveAdapter<'a, C: CircuitDef<S>, S: CustomStep> {
@@ -53,17 +53,45 @@ impl<'a, C: CircuitDef<S>, S: CustomStep> ProveAdapter<'a, C, S> {
     }
 }
 
-impl<'a, C: CircuitDef<CS>, CS: CustomStep> Circuit for ProveAdapter<'a, C, CS> {
+/// With the `zeroize` feature, the accum columns, which are computed from
+/// the witness, are zeroized when the adapter is dropped.
+#[cfg(feature = "zeroize")]
+impl<'a, C: CircuitDef<S>, S: CustomStep> Drop for ProveAdapter<'a, C, S> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.accum);
+    }
+}
+
+// Number of cycles each thread handles when computing the accum columns.
+const ACCUM_CHUNK: usize = 1 << 12;
+
+impl<'a, C, CS> Circuit for ProveAdapter<'a, C, CS>
+where
+    C: CircuitDef<CS> + CircuitStepAccum<NoCustomStep> + Sync,
+    CS: CustomStep,
+{
     fn get_taps(&self) -> &TapSet {
         self.exec.circuit.get_taps()
     }
 
-    fn execute<S: Sha>(&mut self, iop: &mut WriteIOP<S>) {
+    fn execute(&mut self, iop: &mut WriteIOP) {
         iop.write_fp_slice(&self.exec.output);
-        iop.write_u32_slice(&[self.exec.po2 as u32]);
+        let mut po2 = self.exec.po2 as u32 | iop.get_suite().id() << SUITE_ID_SHIFT;
+        if iop.batch_openings {
+            po2 |= BATCHED_OPENINGS_FLAG;
+        }
+        if iop.constant_columns {
+            po2 |= CONSTANT_COLUMNS_FLAG;
+        }
+        if self.exec.zk {
+            iop.write_u32_slice(&[po2]);
+        } else {
+            iop.write_u32_slice(&[po2 | NON_ZK_FLAG]);
+            iop.commit(&Digest::new([NON_ZK_FLAG; DIGEST_WORDS]));
+        }
     }
 
-    fn accumulate<S: Sha>(&mut self, iop: &mut WriteIOP<S>) {
+    fn accumulate(&mut self, iop: &mut WriteIOP) {
         // Make the mixing values
         self.mix
             .resize_with(self.exec.circuit.mix_size(), || Fp::random(&mut iop.rng));
@@ -74,6 +102,7 @@ impl<'a, C: CircuitDef<CS>, CS: CustomStep> Circuit for ProveAdapter<'a, C, CS>
             .get_taps()
             .group_size(RegisterGroup::Accum);
         self.accum.resize(self.steps * accum_size, Fp::ZERO);
+        let cycles = self.steps - ZK_CYCLES;
         let args: &mut [&mut [Fp]] = &mut [
             &mut self.exec.code,
             &mut self.exec.output,
@@ -81,20 +110,34 @@ impl<'a, C: CircuitDef<CS>, CS: CustomStep> Circuit for ProveAdapter<'a, C, CS>
             &mut self.mix,
             &mut self.accum,
         ];
-        for cycle in 0..self.steps - ZK_CYCLES {
-            self.exec
-                .circuit
-                .step_accum(
-                    &CircuitStepContext {
-                        size: self.steps,
-                        cycle,
-                    },
-                    &mut self.exec.custom,
-                    args,
-                )
-                .unwrap();
+        match carried_groups(self.exec.circuit.get_taps()) {
+            Some(groups) if cycles > ACCUM_CHUNK => accum_parallel(
+                &self.exec.circuit,
+                &mut self.exec.custom,
+                args,
+                &groups,
+                self.steps,
+                cycles,
+            ),
+            _ => {
+                for cycle in 0..cycles {
+                    CircuitStepAccum::<CS>::step_accum(
+                        &self.exec.circuit,
+                        &CircuitStepContext {
+                            size: self.steps,
+                            cycle,
+                        },
+                        &mut self.exec.custom,
+                        args,
+                    )
+                    .unwrap();
+                }
+            }
         }
         // Add random noise to end of accum
+        if !self.exec.zk {
+            return;
+        }
         let mut rng = thread_rng();
         for i in self.steps - ZK_CYCLES..self.steps {
             for j in 0..accum_size {
@@ -103,45 +146,24 @@ impl<'a, C: CircuitDef<CS>, CS: CustomStep> Circuit for ProveAdapter<'a, C, CS>
         }
     }
 
-    fn eval_check(
+    fn eval_check<H: Hal>(
         &self,
+        hal: &H,
         check: &Buffer<Fp>,
         code: &Buffer<Fp>,
         data: &Buffer<Fp>,
         accum: &Buffer<Fp>,
         poly_mix: Fp4,
     ) {
-        const EXP_PO2: usize = log2_ceil(INV_RATE);
-
-        let domain = self.steps * INV_RATE;
-        code.view_mut(&mut |code| {
-            data.view_mut(&mut |data| {
-                accum.view_mut(&mut |accum| {
-                    check.view_mut(&mut |check| {
-                        // TODO: parallelize
-                        for cycle in 0..domain {
-                            let args: &[&[Fp]] =
-                                &[&code, &self.exec.output, &data, &self.mix, &accum];
-                            let cond = self.exec.circuit.poly_fp(
-                                &PolyFpContext {
-                                    size: domain,
-                                    cycle,
-                                    mix: poly_mix,
-                                },
-                                args,
-                            );
-                            let x = Fp::new(ROU_FWD[self.exec.po2 + EXP_PO2]).pow(cycle);
-                            // TODO: what is this magic number 3?
-                            let y = (Fp::new(3) * x).pow(1 << self.exec.po2);
-                            let ret = cond.tot * (y - Fp::new(1)).inv();
-                            for i in 0..EXT_SIZE {
-                                check[i * domain + cycle] = ret.elems()[i];
-                            }
-                        }
-                    });
-                });
-            });
-        });
+        let output = hal.copy_from(&self.exec.output);
+        let mix = hal.copy_from(&self.mix);
+        hal.eval_check(
+            check,
+            &[code, &output, data, &mix, accum],
+            &self.exec.circuit,
+            poly_mix,
+            self.exec.po2,
+        );
     }
 
     fn po2(&self) -> u32 {
@@ -160,3 +182,164 @@ impl<'a, C: CircuitDef<CS>, CS: CustomStep> Circuit for ProveAdapter<'a, C, CS>
         &self.accum
     }
 }
+
+// Returns the first column of each group of accum columns that the
+// accumulation step carries over from the previous cycle, or None if they
+// don't form whole extension field elements.
+fn carried_groups(taps: &TapSet) -> Option<Vec<usize>> {
+    let mut carried: Vec<usize> = taps
+        .group_taps(RegisterGroup::Accum)
+        .filter(|tap| tap.back() != 0)
+        .map(|tap| (tap.back() == 1).then_some(tap.offset()))
+        .collect::<Option<_>>()?;
+    carried.sort();
+    carried.dedup();
+    let groups: Vec<usize> = carried.chunks(EXT_SIZE).map(|group| group[0]).collect();
+    let contiguous = carried
+        .chunks(EXT_SIZE)
+        .all(|group| group.len() == EXT_SIZE && group[EXT_SIZE - 1] == group[0] + EXT_SIZE - 1);
+    (!groups.is_empty() && contiguous).then_some(groups)
+}
+
+// The accumulation arguments, shared between worker threads.
+struct SharedArgs([(*mut Fp, usize); 5]);
+
+// SAFETY: The accumulation step only writes the accum argument, and only
+// at the cycle it is evaluating, so workers given disjoint cycle ranges
+// never write the same element, nor read an element another is writing.
+unsafe impl Sync for SharedArgs {}
+
+impl SharedArgs {
+    fn new(args: &mut [&mut [Fp]]) -> Self {
+        let mut raw = [(core::ptr::null_mut(), 0); 5];
+        for (raw, arg) in raw.iter_mut().zip(args.iter_mut()) {
+            *raw = (arg.as_mut_ptr(), arg.len());
+        }
+        SharedArgs(raw)
+    }
+
+    // SAFETY: See the Sync impl; callers must stay within their own cycles.
+    unsafe fn get(&self) -> [&mut [Fp]; 5] {
+        self.0
+            .map(|(ptr, len)| core::slice::from_raw_parts_mut(ptr, len))
+    }
+}
+
+// Computes the accum columns for `cycles` cycles as a parallel prefix
+// product.
+//
+// This relies on the accumulation step updating each carried group of
+// columns as an extension field element `x' = a * x + b`, where `x` is
+// the value on the previous cycle and `a` and `b` depend only on the
+// current cycle, which is how grand product accumulators are built. The
+// composition over a chunk of cycles then has the same form, so:
+//
+// 1. Each chunk is evaluated in parallel from a first row seeded with 0
+//    and then 1, which yields `b` and `a + b` for the whole chunk.
+// 2. The true first row of each chunk is evaluated in order. Applying the
+//    chunk's map to it gives the true last row, which feeds the next chunk.
+// 3. The remaining rows of each chunk are evaluated again in parallel,
+//    now from their true first row.
+fn accum_parallel<C, CS>(
+    circuit: &C,
+    custom: &mut CS,
+    args: &mut [&mut [Fp]],
+    groups: &[usize],
+    size: usize,
+    cycles: usize,
+) where
+    C: CircuitStepAccum<CS> + CircuitStepAccum<NoCustomStep> + Sync,
+    CS: CustomStep,
+{
+    const ACCUM: usize = 4;
+    let accum_size = args[ACCUM].len() / size;
+    let chunks: Vec<Range<usize>> = (0..cycles)
+        .step_by(ACCUM_CHUNK)
+        .map(|start| start..(start + ACCUM_CHUNK).min(cycles))
+        .collect();
+    let shared = SharedArgs::new(args);
+
+    let get = |accum: &[Fp], group: usize, cycle: usize| {
+        let elem = |i| accum[(group + i) * size + cycle];
+        Fp4::new(elem(0), elem(1), elem(2), elem(3))
+    };
+    let set = |accum: &mut [Fp], group: usize, cycle: usize, value: Fp4| {
+        for i in 0..EXT_SIZE {
+            accum[(group + i) * size + cycle] = value.elems()[i];
+        }
+    };
+    let clear = |accum: &mut [Fp], cycles: Range<usize>| {
+        for col in 0..accum_size {
+            accum[col * size + cycles.start..col * size + cycles.end].fill(Fp::ZERO);
+        }
+    };
+    let step = |args: &mut [&mut [Fp]], cycle| {
+        CircuitStepAccum::<NoCustomStep>::step_accum(
+            circuit,
+            &CircuitStepContext { size, cycle },
+            &mut NoCustomStep,
+            args,
+        )
+    };
+
+    let maps: Vec<Vec<(Fp4, Fp4)>> = chunks
+        .par_iter()
+        .map(|chunk| {
+            // SAFETY: Only rows within this chunk are touched.
+            let mut args = unsafe { shared.get() };
+            let mut run = |seed: Fp4| -> Vec<Fp4> {
+                clear(args[ACCUM], chunk.clone());
+                for &group in groups {
+                    set(args[ACCUM], group, chunk.start, seed);
+                }
+                for cycle in chunk.start + 1..chunk.end {
+                    // Fake seeds may fail checks; only the carried values
+                    // matter here, and phase 3 re-runs these cycles.
+                    let _ = step(&mut args, cycle);
+                }
+                groups
+                    .iter()
+                    .map(|&group| get(args[ACCUM], group, chunk.end - 1))
+                    .collect()
+            };
+            let offsets = run(Fp4::ZERO);
+            let scaled = run(Fp4::ONE);
+            scaled
+                .into_iter()
+                .zip(offsets)
+                .map(|(scaled, offset)| (scaled - offset, offset))
+                .collect()
+        })
+        .collect();
+
+    // SAFETY: No workers are running.
+    let mut args = unsafe { shared.get() };
+    for (chunk, map) in chunks.iter().zip(maps) {
+        clear(args[ACCUM], chunk.clone());
+        CircuitStepAccum::<CS>::step_accum(
+            circuit,
+            &CircuitStepContext {
+                size,
+                cycle: chunk.start,
+            },
+            custom,
+            &mut args,
+        )
+        .unwrap();
+        if chunk.len() > 1 {
+            for (&group, (scale, offset)) in groups.iter().zip(map) {
+                let first = get(args[ACCUM], group, chunk.start);
+                set(args[ACCUM], group, chunk.end - 1, scale * first + offset);
+            }
+        }
+    }
+
+    chunks.par_iter().for_each(|chunk| {
+        // SAFETY: Only rows within this chunk are touched.
+        let mut args = unsafe { shared.get() };
+        clear(args[ACCUM], chunk.start + 1..chunk.end);
+        for cycle in chunk.start + 1..chunk.end {
+            step(&mut args, cycle).unwrap();
+        }
+    });
+}
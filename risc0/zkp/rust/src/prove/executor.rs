@@ -17,6 +17,7 @@ use std::cmp::max;
 use anyhow::{bail, Result};
 use log::debug;
 use rand::thread_rng;
+use rayon::prelude::*;
 
 use crate::{
     adapter::{CircuitDef, CircuitStepContext, CustomStep},
@@ -26,6 +27,26 @@ use crate::{
     MIN_PO2, ZK_CYCLES,
 };
 
+// Copies the first `cycles` rows of each column of `trace`, which has
+// `steps` rows, into a trace of twice as many rows.  Columns are copied in
+// parallel; each lands at the same place as it would serially.
+fn expand_columns(trace: &[Fp], steps: usize, cycles: usize) -> Vec<Fp> {
+    let mut expanded = vec![Fp::ZERO; trace.len() * 2];
+    expanded
+        .par_chunks_exact_mut(steps * 2)
+        .zip(trace.par_chunks_exact(steps))
+        .for_each(|(tgt, src)| tgt[..cycles].copy_from_slice(&src[..cycles]));
+    expanded
+}
+
+// Replaces a trace with its expansion.  With the `zeroize` feature, the old
+// trace is zeroized first.
+fn replace_trace(trace: &mut Vec<Fp>, expanded: Vec<Fp>) {
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(trace);
+    *trace = expanded;
+}
+
 pub struct Executor<C: CircuitDef<S>, S: CustomStep> {
     pub circuit: C,
     pub custom: S,
@@ -37,6 +58,9 @@ pub struct Executor<C: CircuitDef<S>, S: CustomStep> {
     pub po2: usize,
     pub steps: usize,
     pub halted: bool,
+    /// Whether to pad the trace with random values for zero knowledge, or
+    /// with zeros.
+    pub zk: bool,
     max_po2: usize,
     pub cycle: usize,
 }
@@ -61,6 +85,7 @@ impl<C: CircuitDef<S>, S: CustomStep> Executor<C, S> {
             po2,
             steps,
             halted: false,
+            zk: true,
             max_po2,
             cycle: 0,
         }
@@ -106,22 +131,10 @@ impl<C: CircuitDef<S>, S: CustomStep> Executor<C, S> {
         if self.steps >= (1 << self.max_po2) {
             bail!("Cannot expand, max po2 of {} reached.", self.max_po2);
         }
-        let mut new_code = vec![Fp::ZERO; self.code.len() * 2];
-        let mut new_data = vec![Fp::ZERO; self.data.len() * 2];
-        for i in 0..self.code_size {
-            let idx = i * self.steps;
-            let src = &self.code[idx..idx + self.cycle];
-            let tgt = &mut new_code[idx * 2..idx * 2 + self.cycle];
-            tgt.copy_from_slice(src);
-        }
-        for i in 0..self.data_size {
-            let idx = i * self.steps;
-            let src = &self.data[idx..idx + self.cycle];
-            let tgt = &mut new_data[idx * 2..idx * 2 + self.cycle];
-            tgt.copy_from_slice(src);
-        }
-        self.code = new_code;
-        self.data = new_data;
+        let code = expand_columns(&self.code, self.steps, self.cycle);
+        let data = expand_columns(&self.data, self.steps, self.cycle);
+        replace_trace(&mut self.code, code);
+        replace_trace(&mut self.data, data);
         self.po2 += 1;
         self.steps *= 2;
         Ok(())
@@ -138,7 +151,11 @@ impl<C: CircuitDef<S>, S: CustomStep> Executor<C, S> {
                 self.code[j * self.steps + i] = Fp::ZERO;
             }
             for j in 0..self.data_size {
-                self.data[j * self.steps + i] = Fp::random(&mut rng);
+                self.data[j * self.steps + i] = if self.zk {
+                    Fp::random(&mut rng)
+                } else {
+                    Fp::ZERO
+                };
             }
         }
         // Do the verify cycles
@@ -172,3 +189,46 @@ impl<C: CircuitDef<S>, S: CustomStep> Executor<C, S> {
         self.output[idx]
     }
 }
+
+/// With the `zeroize` feature, the trace, which holds the witness, is
+/// zeroized when the executor is dropped.
+#[cfg(feature = "zeroize")]
+impl<C: CircuitDef<S>, S: CustomStep> Drop for Executor<C, S> {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+
+        self.code.zeroize();
+        self.data.zeroize();
+        self.output.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_columns;
+    use crate::{core::fp::Fp, field::Elem};
+
+    #[test]
+    fn expand() {
+        let (steps, cycles, columns) = (8, 5, 3);
+        let trace: Vec<Fp> = (0..steps * columns)
+            .map(|i| Fp::new(i as u32 + 1))
+            .collect();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let serial = pool.install(|| expand_columns(&trace, steps, cycles));
+        assert_eq!(expand_columns(&trace, steps, cycles), serial);
+        for col in 0..columns {
+            for row in 0..steps * 2 {
+                let expected = if row < cycles {
+                    trace[col * steps + row]
+                } else {
+                    Fp::ZERO
+                };
+                assert_eq!(serial[col * steps * 2 + row], expected);
+            }
+        }
+    }
+}
@@ -12,18 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::vec::Vec;
+use alloc::{collections::BTreeSet, vec, vec::Vec};
 use core::cmp;
 #[allow(unused_imports)]
 use log::debug;
 
 use crate::{
-    core::{
-        fp::Fp,
-        sha::{Digest, Sha},
-    },
+    core::{fp::Fp, hash::HashSuite, sha::Digest},
+    field::Elem,
     hal::{Buffer, Hal},
-    merkle::MerkleTreeParams,
+    merkle::{insert_constants, MerkleTreeParams},
     prove::write_iop::WriteIOP,
 };
 
@@ -39,6 +37,13 @@ pub struct MerkleTreeProver {
     root: Digest,
     // Buffers to copy proofs though to limit GPU/CPU transfers
     tmp_proof: Buffer<Digest>,
+    // With batched openings, the rows opened so far, and the nodes below the
+    // top layer that the verifier has from them.
+    opened: BTreeSet<usize>,
+    known: BTreeSet<usize>,
+    // For a tree made with new_compact, the index and value of each constant
+    // column, which the matrix and the leaves leave out.
+    constants: Option<Vec<(usize, Fp)>>,
 }
 
 impl MerkleTreeProver {
@@ -61,7 +66,7 @@ impl MerkleTreeProver {
         let params = MerkleTreeParams::new(rows, cols, queries);
         // Allocate nodes
         let nodes = hal.alloc(rows * 2);
-        let tmp_proof = hal.alloc(cmp::max(params.top_size, params.layers - params.top_layer));
+        let tmp_proof = Self::alloc_tmp_proof(hal, &params);
         // Sha each column
         hal.sha_rows(&nodes.slice(rows, rows), matrix);
         // For each layer, sha up the layer below
@@ -81,11 +86,59 @@ impl MerkleTreeProver {
             nodes,
             root: root.unwrap(),
             tmp_proof,
+            opened: BTreeSet::new(),
+            known: BTreeSet::new(),
+            constants: None,
         }
     }
 
-    /// Write the 'top' of the merkle tree and commit to the root.
-    pub fn commit<H: Hal, S: Sha>(&self, hal: &H, iop: &mut WriteIOP<S>) {
+    /// Like [MerkleTreeProver::new], but leaves every constant column out of
+    /// the leaves, as described for
+    /// [CONSTANT_COLUMNS_FLAG](crate::CONSTANT_COLUMNS_FLAG).  A matrix with
+    /// no other columns is hashed in full.
+    pub fn new_compact<H: Hal>(
+        hal: &H,
+        matrix: &Buffer<Fp>,
+        rows: usize,
+        cols: usize,
+        queries: usize,
+    ) -> Self {
+        assert_eq!(matrix.size(), rows * cols);
+        let mut constants = Vec::new();
+        let mut kept = Vec::new();
+        matrix.view(&mut |view| {
+            for (col, column) in view.chunks_exact(rows).enumerate() {
+                if column.iter().all(|x| *x == column[0]) {
+                    constants.push((col, column[0]));
+                } else {
+                    kept.push(col);
+                }
+            }
+        });
+        if constants.is_empty() || kept.is_empty() {
+            let mut tree = Self::new(hal, matrix, rows, cols, queries);
+            tree.constants = Some(Vec::new());
+            return tree;
+        }
+        let mut values = Vec::with_capacity(rows * kept.len());
+        matrix.view(&mut |view| {
+            for col in kept.iter() {
+                values.extend_from_slice(&view[col * rows..(col + 1) * rows]);
+            }
+        });
+        let mut tree = Self::new(hal, &hal.copy_from(&values), rows, kept.len(), queries);
+        tree.constants = Some(constants);
+        tree
+    }
+
+    fn alloc_tmp_proof<H: Hal>(hal: &H, params: &MerkleTreeParams) -> Buffer<Digest> {
+        hal.alloc(cmp::max(params.top_size, params.layers - params.top_layer))
+    }
+
+    /// Write the 'top' of the merkle tree and commit to the root, followed,
+    /// for a tree made with [MerkleTreeProver::new_compact], by the constant
+    /// columns.
+    pub fn commit<H: Hal>(&self, hal: &H, iop: &mut WriteIOP) {
         let top_size = self.params.top_size;
         let mut proof_slice = self.tmp_proof.slice(0, top_size);
         hal.eltwise_copy_digest(&mut proof_slice, &self.nodes.slice(top_size, top_size));
@@ -93,6 +146,17 @@ impl MerkleTreeProver {
             iop.write_digest_slice(view);
         });
         iop.commit(self.root());
+        if let Some(constants) = &self.constants {
+            // The count, then an index and value per column.
+            let mut elems = vec![Fp::new(constants.len() as u32)];
+            for (col, value) in constants {
+                elems.push(Fp::new(*col as u32));
+                elems.push(*value);
+            }
+            iop.write_fp_slice(&elems);
+            let digest = iop.get_suite().hash_elems(&elems);
+            iop.commit(&digest);
+        }
     }
 
     /// Get the root digest of the tree.
@@ -100,16 +164,22 @@ impl MerkleTreeProver {
         &self.root
     }
 
-    /// Generate a proof at a given index, and return the values at that column.
+    /// Generate a proof at a given index, and return the values at that column,
+    /// including any constant columns.
     ///
     /// The format of the proof is always:
-    /// 1) The column itself
+    /// 1) The column itself, less any constant columns
     /// 2) The 'other' digests up to the top.
     ///
     /// It is presumed the verifier is given the index of the row from other
     /// parts of the protocol, and verification will of course fail if the
     /// wrong row is specified.
-    pub fn prove<S: Sha>(&self, iop: &mut WriteIOP<S>, idx: usize) -> Vec<Fp> {
+    ///
+    /// With [batched openings](WriteIOP::batch_openings), a row that was
+    /// opened before is not written again, and neither is any node the
+    /// verifier has from an earlier opening: the path stops at the first such
+    /// node on the way up.
+    pub fn prove(&mut self, iop: &mut WriteIOP, idx: usize) -> Vec<Fp> {
         assert!(idx < self.params.row_size);
         let mut out = Vec::with_capacity(self.params.col_size);
         self.matrix.view(&mut |view| {
@@ -117,16 +187,320 @@ impl MerkleTreeProver {
                 out.push(view[idx + i * self.params.row_size]);
             }
         });
+        if iop.batch_openings && !self.opened.insert(idx) {
+            return self.with_constants(out);
+        }
         iop.write_fp_slice(out.as_slice());
         let mut idx = idx + self.params.row_size;
+        let top_size = self.params.top_size;
+        let known = &mut self.known;
         self.nodes.view(&mut |view| {
-            while idx >= 2 * self.params.top_size {
-                let low_bit = idx % 2;
+            while idx >= 2 * top_size {
+                if iop.batch_openings {
+                    if !known.insert(idx) {
+                        break;
+                    }
+                    if !known.insert(idx ^ 1) {
+                        idx /= 2;
+                        continue;
+                    }
+                }
+                iop.write_digest_slice(&[view[idx ^ 1]]);
                 idx /= 2;
-                let other_idx = 2 * idx + (1 - low_bit);
-                iop.write_digest_slice(&[view[other_idx]]);
             }
         });
-        out
+        self.with_constants(out)
+    }
+
+    fn with_constants(&self, mut row: Vec<Fp>) -> Vec<Fp> {
+        if let Some(constants) = &self.constants {
+            insert_constants(&mut row, constants);
+        }
+        row
+    }
+}
+
+/// Builds a [MerkleTreeProver] from rows that arrive one at a time.
+///
+/// Each row is hashed as it's pushed, and each node as soon as both its
+/// children are, so when the last row arrives only that row's branch is left
+/// to hash.  This lets a prover commit to a matrix while it's still
+/// computing it, rather than hashing the whole matrix afterwards with
+/// [MerkleTreeProver::new]; the tree is the same either way, provided the
+/// suite is the one the [Hal] hashes with.
+pub struct MerkleTreeBuilder<'a> {
+    suite: &'a dyn HashSuite,
+    params: MerkleTreeParams,
+    // Laid out like the matrix passed to MerkleTreeProver::new.
+    matrix: Vec<Fp>,
+    // Laid out like MerkleTreeProver::nodes.
+    nodes: Vec<Digest>,
+    pushed: usize,
+}
+
+impl<'a> MerkleTreeBuilder<'a> {
+    /// Start a tree of `rows` rows of `cols` values each, with the parameters
+    /// of [MerkleTreeProver::new].
+    pub fn new(suite: &'a dyn HashSuite, rows: usize, cols: usize, queries: usize) -> Self {
+        MerkleTreeBuilder {
+            suite,
+            params: MerkleTreeParams::new(rows, cols, queries),
+            matrix: vec![Fp::ZERO; rows * cols],
+            nodes: vec![Digest::default(); rows * 2],
+            pushed: 0,
+        }
+    }
+
+    /// The number of rows pushed so far.
+    pub fn len(&self) -> usize {
+        self.pushed
+    }
+
+    /// Whether no rows have been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.pushed == 0
+    }
+
+    /// Append the next row of the tree, and hash it along with every node it
+    /// completes.
+    pub fn push_row(&mut self, row: &[Fp]) {
+        let rows = self.params.row_size;
+        assert!(self.pushed < rows, "All {rows} rows were already pushed");
+        assert_eq!(row.len(), self.params.col_size);
+        let idx = self.pushed;
+        for (col, value) in row.iter().enumerate() {
+            self.matrix[idx + col * rows] = *value;
+        }
+        let mut node = idx + rows;
+        self.nodes[node] = self.suite.hash_elems(row);
+        // A right child completes its parent.
+        while node > 1 && node % 2 == 1 {
+            let parent = node / 2;
+            self.nodes[parent] = self
+                .suite
+                .hash_pair(&self.nodes[node - 1], &self.nodes[node]);
+            node = parent;
+        }
+        self.pushed += 1;
+    }
+
+    /// Hand the tree to `hal`, once every row has been pushed.
+    pub fn finish<H: Hal>(self, hal: &H) -> MerkleTreeProver {
+        assert_eq!(
+            self.pushed, self.params.row_size,
+            "Only {} of {} rows were pushed",
+            self.pushed, self.params.row_size
+        );
+        let tmp_proof = MerkleTreeProver::alloc_tmp_proof(hal, &self.params);
+        MerkleTreeProver {
+            root: self.nodes[1],
+            matrix: hal.wrap_vec(self.matrix),
+            nodes: hal.wrap_vec(self.nodes),
+            params: self.params,
+            tmp_proof,
+            opened: BTreeSet::new(),
+            known: BTreeSet::new(),
+            constants: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use rand::thread_rng;
+
+    use super::{MerkleTreeBuilder, MerkleTreeProver};
+    use crate::{
+        core::{
+            fp::Fp,
+            sha::{Digest, Sha},
+            sha_cpu,
+        },
+        field::Elem,
+        hal::{cpu::CpuHal, Hal},
+    };
+    #[cfg(feature = "verify")]
+    use crate::{
+        prove::write_iop::WriteIOP,
+        verify::{merkle::MerkleTreeVerifier, read_iop::ReadIOP},
+        QUERIES,
+    };
+
+    #[test]
+    fn matches_serial_tree() {
+        // Enough leaves that rayon splits the leaf hashing and the lower
+        // layers across threads.
+        const ROWS: usize = 1 << 12;
+        const COLS: usize = 7;
+        let hal = CpuHal::new();
+        let sha = sha_cpu::Impl {};
+        let mut rng = thread_rng();
+        let matrix: Vec<Fp> = (0..ROWS * COLS).map(|_| Fp::random(&mut rng)).collect();
+        let tree = MerkleTreeProver::new(&hal, &hal.copy_from(&matrix), ROWS, COLS, 50);
+
+        let mut layer: Vec<Digest> = (0..ROWS)
+            .map(|row| {
+                let leaf: Vec<Fp> = (0..COLS).map(|col| matrix[row + col * ROWS]).collect();
+                *sha.hash_fps(&leaf)
+            })
+            .collect();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks_exact(2)
+                .map(|pair| *sha.hash_pair(&pair[0], &pair[1]))
+                .collect();
+        }
+        assert_eq!(*tree.root(), layer[0]);
+    }
+
+    #[test]
+    fn builder() {
+        const ROWS: usize = 1 << 8;
+        const COLS: usize = 5;
+        let hal = CpuHal::new();
+        let sha = sha_cpu::Impl {};
+        let mut rng = thread_rng();
+        let matrix: Vec<Fp> = (0..ROWS * COLS).map(|_| Fp::random(&mut rng)).collect();
+        let tree = MerkleTreeProver::new(&hal, &hal.copy_from(&matrix), ROWS, COLS, 50);
+
+        let mut builder = MerkleTreeBuilder::new(&sha, ROWS, COLS, 50);
+        for row in 0..ROWS {
+            let values: Vec<Fp> = (0..COLS).map(|col| matrix[row + col * ROWS]).collect();
+            builder.push_row(&values);
+        }
+        assert_eq!(builder.len(), ROWS);
+        let built = builder.finish(&hal);
+        assert_eq!(built.root(), tree.root());
+        built
+            .matrix
+            .view(&mut |built| assert_eq!(built, &matrix[..]));
+        built.nodes.view(&mut |built| {
+            tree.nodes
+                .view(&mut |nodes| assert_eq!(built[1..], nodes[1..]))
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder_unfinished() {
+        let hal = CpuHal::new();
+        let mut builder = MerkleTreeBuilder::new(&sha_cpu::Impl {}, 4, 1, 1);
+        builder.push_row(&[Fp::ONE]);
+        builder.finish(&hal);
+    }
+
+    // Opens `queries` of a tree, returning the proof and the opened rows.
+    #[cfg(feature = "verify")]
+    fn open(batch_openings: bool, queries: &[usize]) -> (Vec<u32>, Vec<Vec<Fp>>) {
+        const ROWS: usize = 1 << 10;
+        const COLS: usize = 6;
+        let hal = CpuHal::new();
+        let sha = sha_cpu::Impl {};
+        let matrix: Vec<Fp> = (0..(ROWS * COLS) as u32).map(Fp::new).collect();
+        let mut tree = MerkleTreeProver::new(&hal, &hal.copy_from(&matrix), ROWS, COLS, QUERIES);
+        let mut iop = WriteIOP::new(&sha);
+        iop.batch_openings = batch_openings;
+        tree.commit(&hal, &mut iop);
+        let rows = queries
+            .iter()
+            .map(|idx| tree.prove(&mut iop, *idx))
+            .collect();
+        (iop.proof, rows)
+    }
+
+    #[cfg(feature = "verify")]
+    fn check(batch_openings: bool, queries: &[usize], proof: &[u32]) -> Vec<Vec<Fp>> {
+        let sha = sha_cpu::Impl {};
+        let mut iop = ReadIOP::new(&sha, proof);
+        iop.set_batch_openings(batch_openings);
+        let mut tree = MerkleTreeVerifier::new(&mut iop, 1 << 10, 6, QUERIES).unwrap();
+        let rows = queries
+            .iter()
+            .map(|idx| tree.verify(&mut iop, *idx).unwrap())
+            .collect();
+        iop.verify_complete().unwrap();
+        rows
+    }
+
+    #[test]
+    #[cfg(feature = "verify")]
+    fn constant_columns() {
+        const ROWS: usize = 1 << 10;
+        const COLS: usize = 6;
+        let hal = CpuHal::new();
+        let sha = sha_cpu::Impl {};
+        let mut rng = thread_rng();
+        // Columns 1 and 4 are constant.
+        let matrix: Vec<Fp> = (0..ROWS * COLS)
+            .map(|i| match i / ROWS {
+                1 => Fp::ZERO,
+                4 => Fp::new(7),
+                _ => Fp::random(&mut rng),
+            })
+            .collect();
+        let queries: Vec<usize> = (0..QUERIES).map(|i| (i * 389) % ROWS).collect();
+        let open = |compact: bool| {
+            let matrix = hal.copy_from(&matrix);
+            let mut tree = if compact {
+                MerkleTreeProver::new_compact(&hal, &matrix, ROWS, COLS, QUERIES)
+            } else {
+                MerkleTreeProver::new(&hal, &matrix, ROWS, COLS, QUERIES)
+            };
+            let mut iop = WriteIOP::new(&sha);
+            tree.commit(&hal, &mut iop);
+            let rows: Vec<Vec<Fp>> = queries
+                .iter()
+                .map(|idx| tree.prove(&mut iop, *idx))
+                .collect();
+            (iop.proof, rows)
+        };
+        let (proof, rows) = open(false);
+        let (compact, compact_rows) = open(true);
+        assert_eq!(compact_rows, rows);
+        assert!(compact.len() < proof.len());
+
+        let mut iop = ReadIOP::new(&sha, &compact);
+        iop.set_batch_openings(true);
+        let mut tree = MerkleTreeVerifier::new_compact(&mut iop, ROWS, COLS, QUERIES).unwrap();
+        for (idx, row) in queries.iter().zip(rows.iter()) {
+            assert_eq!(&tree.verify(&mut iop, *idx).unwrap(), row);
+        }
+        iop.verify_complete().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "verify")]
+    fn batched_openings() {
+        // Spread over the tree, with a few rows opened twice.
+        let mut queries: Vec<usize> = (0..QUERIES).map(|i| (i * 389 + i * i * 7) % 1024).collect();
+        queries.extend_from_slice(&[queries[3], queries[17]]);
+
+        let (proof, rows) = open(false, &queries);
+        assert_eq!(check(false, &queries, &proof), rows);
+        let (batched, batched_rows) = open(true, &queries);
+        assert_eq!(batched_rows, rows);
+        assert_eq!(check(true, &queries, &batched), rows);
+        assert!(batched.len() < proof.len());
+
+        // A batched proof can't be read as an unbatched one.
+        let sha = sha_cpu::Impl {};
+        let mut iop = ReadIOP::new(&sha, &batched);
+        let mut tree = MerkleTreeVerifier::new(&mut iop, 1 << 10, 6, QUERIES).unwrap();
+        assert!(queries
+            .iter()
+            .any(|idx| tree.verify(&mut iop, *idx).is_err()));
+
+        // Every node the batched proof still sends is checked.
+        let last = batched.len() - 1;
+        let mut tampered = batched.clone();
+        tampered[last] ^= 1;
+        let mut iop = ReadIOP::new(&sha, &tampered);
+        iop.set_batch_openings(true);
+        let mut tree = MerkleTreeVerifier::new(&mut iop, 1 << 10, 6, QUERIES).unwrap();
+        assert!(queries
+            .iter()
+            .any(|idx| tree.verify(&mut iop, *idx).is_err()));
     }
 }
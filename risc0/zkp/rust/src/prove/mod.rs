@@ -15,7 +15,7 @@
 pub mod adapter;
 pub mod executor;
 mod fri;
-mod merkle;
+pub mod merkle;
 pub mod poly_group;
 pub mod write_iop;
 
@@ -28,11 +28,10 @@ use crate::{
     core::{
         fp::Fp,
         fp4::{Fp4, EXT_SIZE},
+        hash::HashSuite,
         poly::{poly_divide, poly_interpolate},
-        rou::ROU_REV,
-        sha::Sha,
     },
-    field::Elem,
+    field::{Elem, TwoAdicField},
     hal::{Buffer, Hal},
     prove::{fri::fri_prove, poly_group::PolyGroup, write_iop::WriteIOP},
     taps::{RegisterGroup, TapSet},
@@ -44,14 +43,15 @@ pub trait Circuit {
 
     /// Perform initial 'execution' setting code + data.
     /// Additionally, write any 'results' as needed.
-    fn execute<S: Sha>(&mut self, iop: &mut WriteIOP<S>);
+    fn execute(&mut self, iop: &mut WriteIOP);
 
     /// Perform 'accumlate' stage, using the iop for any RNG state.
-    fn accumulate<S: Sha>(&mut self, iop: &mut WriteIOP<S>);
+    fn accumulate(&mut self, iop: &mut WriteIOP);
 
-    /// Compute check polynomial.
-    fn eval_check(
+    /// Compute check polynomial, with [Hal::eval_check].
+    fn eval_check<H: Hal>(
         &self,
+        hal: &H,
         check: &Buffer<Fp>,
         code: &Buffer<Fp>,
         data: &Buffer<Fp>,
@@ -68,17 +68,35 @@ pub trait Circuit {
     fn get_accum(&self) -> &[Fp];
 }
 
-pub fn prove_without_seal<H: Hal, S: Sha, C: Circuit>(_hal: &H, sha: &S, circuit: &mut C) {
-    let mut iop = WriteIOP::new(sha);
+pub fn prove_without_seal<H: Hal, C: Circuit>(_hal: &H, suite: &dyn HashSuite, circuit: &mut C) {
+    let mut iop = WriteIOP::new(suite);
     circuit.execute(&mut iop);
 }
 
-pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> Vec<u32> {
+// Makes the PolyGroup of a trace group, which leaves out constant columns if
+// the seal's header says so.
+fn trace_group<'a, H: Hal>(
+    hal: &H,
+    iop: &WriteIOP,
+    coeffs: &'a Buffer<Fp>,
+    count: usize,
+    size: usize,
+) -> PolyGroup<'a> {
+    if iop.constant_columns {
+        PolyGroup::new_compact(hal, coeffs, count, size)
+    } else {
+        PolyGroup::new(hal, coeffs, count, size)
+    }
+}
+
+/// Prove an execution of `circuit`, committing with `suite`.  The [Hal]
+/// must hash rows with the same suite.
+pub fn prove<H: Hal, C: Circuit>(hal: &H, suite: &dyn HashSuite, circuit: &mut C) -> Vec<u32> {
     let taps = circuit.get_taps().clone();
     let code_size = taps.group_size(RegisterGroup::Code);
     let data_size = taps.group_size(RegisterGroup::Data);
     let accum_size = taps.group_size(RegisterGroup::Accum);
-    let mut iop = WriteIOP::new(sha);
+    let mut iop = WriteIOP::new(suite);
 
     circuit.execute(&mut iop);
 
@@ -88,12 +106,12 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
 
     // Make code + data PolyGroups + commit them
     let code_coeffs = make_coeffs(hal, circuit.get_code(), code_size);
-    let code_group = PolyGroup::new(hal, &code_coeffs, code_size, size);
+    let mut code_group = PolyGroup::new(hal, &code_coeffs, code_size, size);
     code_group.merkle.commit(hal, &mut iop);
     debug!("codeGroup: {}", code_group.merkle.root());
 
     let data_coeffs = make_coeffs(hal, circuit.get_data(), data_size);
-    let data_group = PolyGroup::new(hal, &data_coeffs, data_size, size);
+    let mut data_group = trace_group(hal, &iop, &data_coeffs, data_size, size);
     data_group.merkle.commit(hal, &mut iop);
     debug!("dataGroup: {}", data_group.merkle.root());
 
@@ -103,7 +121,7 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
     debug!("size = {size}, accumSize = {accum_size}");
     debug!("getAccum.size() = {}", circuit.get_accum().len());
     let accum_coeffs = make_coeffs(hal, circuit.get_accum(), accum_size);
-    let accum_group = PolyGroup::new(hal, &accum_coeffs, accum_size, size);
+    let mut accum_group = trace_group(hal, &iop, &accum_coeffs, accum_size, size);
     accum_group.merkle.commit(hal, &mut iop);
     debug!("accumGroup: {}", accum_group.merkle.root());
 
@@ -114,6 +132,7 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
     let domain = size * INV_RATE;
     let check_poly = hal.alloc(EXT_SIZE * domain);
     circuit.eval_check(
+        hal,
         &check_poly,
         &code_group.evaluated,
         &data_group.evaluated,
@@ -154,7 +173,7 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
     // invRate*size to 16 polys of size, without actually doing anything.
 
     // Make the PolyGroup + add it to the IOP;
-    let check_group = PolyGroup::new(hal, &check_poly, CHECK_SIZE, size);
+    let mut check_group = PolyGroup::new(hal, &check_poly, CHECK_SIZE, size);
     check_group.merkle.commit(hal, &mut iop);
     debug!("checkGroup: {}", check_group.merkle.root());
 
@@ -169,7 +188,7 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
     //   LOG(1, "Z = " << Z);
 
     // Get rev rou for size
-    let back_one = Fp4::from_u32(ROU_REV[po2 as usize]);
+    let back_one = Fp4::from(Fp::inv_root_of_unity(po2 as usize));
     let mut all_xs = Vec::new();
 
     // Do evaluations of all of the various polynomials at the appropriate points.
@@ -228,7 +247,7 @@ pub fn prove<H: Hal, S: Sha, C: Circuit>(hal: &H, sha: &S, circuit: &mut C) -> V
 
     debug!("Size of U = {}", coeff_u.len());
     iop.write_fp4_slice(&coeff_u);
-    let hash_u = sha.hash_fp4s(&coeff_u);
+    let hash_u = suite.hash_ext_elems(&coeff_u);
     iop.commit(&hash_u);
 
     // Set the mix mix value
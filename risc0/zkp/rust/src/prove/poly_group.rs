@@ -13,10 +13,10 @@
 // limitations under the License.
 
 use crate::{
-    core::{fp::Fp, log2_ceil},
+    core::{fp::Fp, lde::CosetLde},
     hal::{Buffer, Hal},
     prove::merkle::MerkleTreeProver,
-    INV_RATE, QUERIES,
+    QUERIES,
 };
 
 /// A PolyGroup represents a group of polynomials, all of the same maximum
@@ -58,13 +58,34 @@ pub struct PolyGroup<'a> {
 
 impl<'a> PolyGroup<'a> {
     pub fn new<H: Hal>(hal: &H, coeffs: &'a Buffer<Fp>, count: usize, size: usize) -> Self {
+        Self::make(hal, coeffs, count, size, false)
+    }
+
+    /// Like [PolyGroup::new], but with a tree that leaves out constant
+    /// columns; see [MerkleTreeProver::new_compact].  Only the trace groups
+    /// after the code group are committed this way.
+    pub fn new_compact<H: Hal>(hal: &H, coeffs: &'a Buffer<Fp>, count: usize, size: usize) -> Self {
+        Self::make(hal, coeffs, count, size, true)
+    }
+
+    fn make<H: Hal>(
+        hal: &H,
+        coeffs: &'a Buffer<Fp>,
+        count: usize,
+        size: usize,
+        compact: bool,
+    ) -> Self {
         assert_eq!(coeffs.size(), count * size);
-        let domain = size * INV_RATE;
+        let domain = CosetLde::ZK.domain_size(size);
         let evaluated = hal.alloc(count * domain);
         hal.batch_expand(&evaluated, &coeffs, count);
-        hal.batch_evaluate_ntt(&evaluated, count, log2_ceil(INV_RATE));
+        hal.batch_evaluate_ntt(&evaluated, count, CosetLde::ZK.blowup_bits());
         hal.batch_bit_reverse(&coeffs, count);
-        let merkle = MerkleTreeProver::new(hal, &evaluated, domain, count, QUERIES);
+        let merkle = if compact {
+            MerkleTreeProver::new_compact(hal, &evaluated, domain, count, QUERIES)
+        } else {
+            MerkleTreeProver::new(hal, &evaluated, domain, count, QUERIES)
+        };
         PolyGroup {
             coeffs,
             count,
@@ -12,33 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 
 use crate::core::{
     fp::Fp,
     fp4::Fp4,
-    sha::{Digest, Sha},
-    sha_rng::ShaRng,
+    hash::{HashSuite, TranscriptRng},
+    sha::Digest,
 };
 
-pub struct WriteIOP<S: Sha> {
-    sha: S,
+pub struct WriteIOP<'a> {
+    suite: &'a dyn HashSuite,
     pub proof: Vec<u32>,
-    pub rng: ShaRng<S>,
+    pub rng: Box<dyn TranscriptRng + 'a>,
+    /// Whether Merkle openings are batched; see
+    /// [BATCHED_OPENINGS_FLAG](crate::BATCHED_OPENINGS_FLAG).
+    pub batch_openings: bool,
+    /// Whether trace trees leave out constant columns; see
+    /// [CONSTANT_COLUMNS_FLAG](crate::CONSTANT_COLUMNS_FLAG).
+    pub constant_columns: bool,
 }
 
-impl<S: Sha> WriteIOP<S> {
-    /// Create a new empty proof
-    pub fn new(sha: &S) -> Self {
+impl<'a> WriteIOP<'a> {
+    /// Create a new empty proof, committed with `suite`.
+    pub fn new(suite: &'a dyn HashSuite) -> Self {
         WriteIOP {
-            sha: sha.clone(),
+            suite,
             proof: Vec::new(),
-            rng: ShaRng::new(sha),
+            rng: suite.rng(),
+            batch_openings: true,
+            constant_columns: true,
         }
     }
 
-    pub fn get_sha(&self) -> &S {
-        &self.sha
+    pub fn get_suite(&self) -> &'a dyn HashSuite {
+        self.suite
     }
 
     /// Called by the prover to write some data.
@@ -14,7 +14,6 @@
 
 use alloc::{
     collections::{BTreeMap, BTreeSet},
-    rc::Rc,
     vec,
     vec::Vec,
 };
@@ -56,7 +55,7 @@ impl Tap {
 
 /// This class is an implementation detail and carefully built to be efficient
 /// on RISC-V for use in recursion.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TapData {
     // The offset in register group (reg #)
     pub offset: u16,
@@ -90,6 +89,9 @@ impl PartialOrd for TapData {
     }
 }
 
+// Owned rather than shared so that circuits holding a TapSet can be used
+// from several threads at once; cloning happens once per proof.
+#[derive(Clone)]
 struct TapSetData {
     taps: Vec<TapData>,
     combo_taps: Vec<u16>,
@@ -100,7 +102,7 @@ struct TapSetData {
 
 #[derive(Clone)]
 pub struct TapSet {
-    data: Rc<TapSetData>,
+    data: TapSetData,
 }
 
 impl TapSet {
@@ -166,13 +168,13 @@ impl TapSet {
         combo_begin.push(combo_taps.len().try_into().unwrap());
         assert!(combo_taps.len() < 64 * 1024);
         TapSet {
-            data: Rc::new(TapSetData {
+            data: TapSetData {
                 taps,
                 combo_taps,
                 combo_begin,
                 group_begin,
                 combos_count: combos.len(),
-            }),
+            },
         }
     }
 
@@ -0,0 +1,206 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Permutations of vectors and matrices, as used by the NTT.
+//!
+//! Matrices are dense and row-major: element `(row, col)` of a `rows` by
+//! `cols` matrix is at `row * cols + col`.  A column-major matrix, such as a
+//! trace whose registers are stored one after another, is the row-major
+//! matrix of its transpose.
+
+use alloc::vec;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Reverses the bits in a 32 bit number
+/// For example 1011...0100 becomes 0010...1101
+pub fn bit_rev_32(mut x: u32) -> u32 {
+    x = ((x & 0xaaaaaaaa) >> 1) | ((x & 0x55555555) << 1);
+    x = ((x & 0xcccccccc) >> 2) | ((x & 0x33333333) << 2);
+    x = ((x & 0xf0f0f0f0) >> 4) | ((x & 0x0f0f0f0f) << 4);
+    x = ((x & 0xff00ff00) >> 8) | ((x & 0x00ff00ff) << 8);
+    (x >> 16) | (x << 16)
+}
+
+/// Reverses the low `bits` bits of `x`, which must be below `1 << bits`.
+pub fn bit_rev(x: usize, bits: usize) -> usize {
+    assert!(bits <= 32 && (x as u64) < 1 << bits);
+    match bits {
+        0 => 0,
+        _ => (bit_rev_32(x as u32) >> (32 - bits)) as usize,
+    }
+}
+
+/// Bit reverses the indices in an array of (1 << n) numbers.
+/// This permutes the values in the array so that a value which is previously
+/// in index i, will now go in the index i' given by reversing the bits of i.
+/// For example, with n=4, the value at index 3=0011 will go to index 12=1100.
+pub fn bit_reverse<T: Copy>(io: &mut [T]) {
+    let n = crate::core::log2_ceil(io.len());
+    assert_eq!(1 << n, io.len());
+    for i in 0..io.len() {
+        let rev_idx = bit_rev(i, n);
+        if i < rev_idx {
+            io.swap(i, rev_idx);
+        }
+    }
+}
+
+/// [bit_reverse]s each of the `len`-element chunks of `io`, such as the rows
+/// of a matrix.
+pub fn bit_reverse_chunks<T: Copy>(io: &mut [T], len: usize) {
+    assert_eq!(io.len() % len, 0);
+    io.chunks_exact_mut(len).for_each(bit_reverse);
+}
+
+/// The side of the square tiles [transpose] copies at a time, which keeps
+/// both the reads and the writes within a few cache lines.
+const TILE: usize = 16;
+
+// Writes a band of `dst` rows, which are the src columns from `col0`.
+fn transpose_band<T: Copy>(dst: &mut [T], src: &[T], rows: usize, cols: usize, col0: usize) {
+    let width = dst.len() / rows;
+    for row0 in (0..rows).step_by(TILE) {
+        for col in 0..width {
+            for row in row0..rows.min(row0 + TILE) {
+                dst[col * rows + row] = src[row * cols + col0 + col];
+            }
+        }
+    }
+}
+
+/// Writes the transpose of `src`, a `rows` by `cols` matrix, to `dst`, which
+/// becomes a `cols` by `rows` matrix.
+pub fn transpose<T: Copy>(dst: &mut [T], src: &[T], rows: usize, cols: usize) {
+    assert_eq!(src.len(), rows * cols);
+    assert_eq!(dst.len(), rows * cols);
+    if rows == 0 {
+        return;
+    }
+    for (band, dst) in dst.chunks_mut(TILE * rows).enumerate() {
+        transpose_band(dst, src, rows, cols, band * TILE);
+    }
+}
+
+/// Same as [transpose], across rayon's thread pool.
+#[cfg(feature = "parallel")]
+pub fn par_transpose<T: Copy + Send + Sync>(dst: &mut [T], src: &[T], rows: usize, cols: usize) {
+    assert_eq!(src.len(), rows * cols);
+    assert_eq!(dst.len(), rows * cols);
+    if rows == 0 {
+        return;
+    }
+    dst.par_chunks_mut(TILE * rows)
+        .enumerate()
+        .for_each(|(band, dst)| transpose_band(dst, src, rows, cols, band * TILE));
+}
+
+/// Transposes `matrix`, a `rows` by `cols` matrix, in place, so that it
+/// becomes a `cols` by `rows` matrix.
+///
+/// Square matrices swap elements across the diagonal.  Others follow the
+/// cycles of the permutation, which takes a bit per element to track
+/// instead of a copy of the matrix, but is slower than [transpose].
+pub fn transpose_in_place<T: Copy>(matrix: &mut [T], rows: usize, cols: usize) {
+    let size = rows * cols;
+    assert_eq!(matrix.len(), size);
+    if rows == cols {
+        for row in 0..rows {
+            for col in row + 1..cols {
+                matrix.swap(row * cols + col, col * rows + row);
+            }
+        }
+        return;
+    }
+    if size <= 1 {
+        return;
+    }
+    // The element at `i < size - 1` moves to `i * rows % (size - 1)`; the
+    // last stays in place.
+    let mut moved = vec![false; size];
+    for start in 1..size - 1 {
+        if moved[start] {
+            continue;
+        }
+        let mut idx = start;
+        let mut value = matrix[start];
+        loop {
+            idx = idx * rows % (size - 1);
+            core::mem::swap(&mut value, &mut matrix[idx]);
+            moved[idx] = true;
+            if idx == start {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bit_rev, bit_reverse, bit_reverse_chunks, transpose, transpose_in_place};
+
+    fn naive(src: &[u32], rows: usize, cols: usize) -> Vec<u32> {
+        let mut dst = vec![0; src.len()];
+        for row in 0..rows {
+            for col in 0..cols {
+                dst[col * rows + row] = src[row * cols + col];
+            }
+        }
+        dst
+    }
+
+    #[test]
+    fn reverse() {
+        assert_eq!(bit_rev(0, 0), 0);
+        assert_eq!(bit_rev(0b0011, 4), 0b1100);
+        assert_eq!(bit_rev(1, 32), 1 << 31);
+
+        let orig: Vec<u32> = (0..16).collect();
+        let mut io = orig.clone();
+        bit_reverse(&mut io);
+        assert_eq!(io[12], 3);
+
+        let mut rows: Vec<u32> = (0..32).collect();
+        bit_reverse_chunks(&mut rows, 16);
+        assert_eq!(&rows[..16], &io[..]);
+        assert_eq!(rows[16 + 12], 16 + 3);
+
+        bit_reverse(&mut io);
+        assert_eq!(io, orig);
+    }
+
+    #[test]
+    fn transposes() {
+        for (rows, cols) in [(0, 3), (1, 7), (5, 5), (16, 32), (17, 45), (64, 3)] {
+            let src: Vec<u32> = (0..(rows * cols) as u32).collect();
+            let goal = naive(&src, rows, cols);
+
+            let mut dst = vec![0; src.len()];
+            transpose(&mut dst, &src, rows, cols);
+            assert_eq!(dst, goal);
+
+            #[cfg(feature = "parallel")]
+            {
+                let mut dst = vec![0; src.len()];
+                super::par_transpose(&mut dst, &src, rows, cols);
+                assert_eq!(dst, goal);
+            }
+
+            let mut matrix = src.clone();
+            transpose_in_place(&mut matrix, rows, cols);
+            assert_eq!(matrix, goal);
+        }
+    }
+}
@@ -19,19 +19,23 @@ use crate::{
     core::{
         fp::Fp,
         fp4::Fp4,
-        sha::{Digest, Sha},
+        hash::{SUITE_ID_MASK, SUITE_ID_SHIFT},
+        sha::{Digest, DIGEST_WORDS},
     },
     field::Elem,
     taps::TapSet,
     verify::{read_iop::ReadIOP, Circuit, VerificationError},
+    BATCHED_OPENINGS_FLAG, CONSTANT_COLUMNS_FLAG, MAX_CYCLES_PO2, NON_ZK_FLAG,
 };
 
 pub struct VerifyAdapter<'a, C: CircuitInfo + PolyExt + TapsProvider> {
     circuit: &'a C,
     po2: u32,
+    zk: bool,
     steps: usize,
     out: Vec<Fp>,
     mix: Vec<Fp>,
+    code_root: Digest,
 }
 
 impl<'a, C: CircuitInfo + PolyExt + TapsProvider> VerifyAdapter<'a, C> {
@@ -39,11 +43,29 @@ impl<'a, C: CircuitInfo + PolyExt + TapsProvider> VerifyAdapter<'a, C> {
         VerifyAdapter {
             circuit,
             po2: 0,
+            zk: true,
             steps: 0,
             out: Vec::new(),
             mix: Vec::new(),
+            code_root: Digest::default(),
         }
     }
+
+    /// Whether the seal read by [Circuit::execute] was proven with
+    /// zero-knowledge padding; see [NON_ZK_FLAG].
+    pub fn zk(&self) -> bool {
+        self.zk
+    }
+
+    /// The root of the code tree passed to [Circuit::check_code].
+    pub fn code_root(&self) -> &Digest {
+        &self.code_root
+    }
+
+    /// The outputs of the circuit read by [Circuit::execute].
+    pub fn outputs(&self) -> &[Fp] {
+        &self.out
+    }
 }
 
 impl<'a, C: CircuitInfo + PolyExt + TapsProvider> Circuit for VerifyAdapter<'a, C> {
@@ -51,18 +73,36 @@ impl<'a, C: CircuitInfo + PolyExt + TapsProvider> Circuit for VerifyAdapter<'a,
         self.circuit.get_taps()
     }
 
-    fn execute<S: Sha>(&mut self, iop: &mut ReadIOP<S>) {
+    fn execute(&mut self, iop: &mut ReadIOP) -> Result<(), VerificationError> {
         // Read the outputs + size
         self.out.resize(self.circuit.output_size(), Fp::ZERO);
-        iop.read_fps(&mut self.out);
+        iop.read_fps(&mut self.out)?;
         let mut slice = [0u32; 1];
-        iop.read_u32s(&mut slice);
-        self.po2 = slice[0];
+        iop.read_u32s(&mut slice)?;
+        if (slice[0] >> SUITE_ID_SHIFT) & SUITE_ID_MASK != iop.get_suite().id() {
+            return Err(VerificationError::HashSuiteMismatch);
+        }
+        self.zk = slice[0] & NON_ZK_FLAG == 0;
+        iop.set_batch_openings(slice[0] & BATCHED_OPENINGS_FLAG != 0);
+        iop.set_constant_columns(slice[0] & CONSTANT_COLUMNS_FLAG != 0);
+        self.po2 = slice[0]
+            & !(NON_ZK_FLAG
+                | BATCHED_OPENINGS_FLAG
+                | CONSTANT_COLUMNS_FLAG
+                | SUITE_ID_MASK << SUITE_ID_SHIFT);
+        if !self.zk {
+            iop.commit(&Digest::new([NON_ZK_FLAG; DIGEST_WORDS]));
+        }
+        if self.po2 as usize > MAX_CYCLES_PO2 {
+            return Err(VerificationError::ReceiptFormatError);
+        }
         self.steps = 1 << self.po2;
+        Ok(())
     }
 
-    fn accumulate<S: Sha>(&mut self, iop: &mut ReadIOP<S>) {
+    fn accumulate(&mut self, iop: &mut ReadIOP) {
         // Fill in accum mix
+        self.mix.clear();
         for _ in 0..self.circuit.mix_size() {
             self.mix.push(Fp::random(iop));
         }
@@ -72,8 +112,10 @@ impl<'a, C: CircuitInfo + PolyExt + TapsProvider> Circuit for VerifyAdapter<'a,
         self.po2
     }
 
-    fn check_code(&self, _root: &Digest) -> Result<(), VerificationError> {
-        // TODO: check code merkle tree
+    fn check_code(&mut self, root: &Digest) -> Result<(), VerificationError> {
+        // Which code roots are acceptable depends on the method, so this is
+        // left to the claim check of verify_seal.
+        self.code_root = *root;
         Ok(())
     }
 
@@ -21,12 +21,10 @@ use crate::{
         fp::Fp,
         fp4::{Fp4, EXT_SIZE},
         log2_ceil,
-        ntt::{bit_reverse, interpolate_ntt},
-        rou::{ROU_FWD, ROU_REV},
-        sha::Sha,
+        poly::{eval_barycentric, BarycentricDomain},
     },
-    field::Elem,
-    verify::{merkle::MerkleTreeVerifier, read_iop::ReadIOP},
+    field::{Elem, TwoAdicField},
+    verify::{merkle::MerkleTreeVerifier, read_iop::ReadIOP, VerificationError},
     FRI_FOLD, FRI_MIN_DEGREE, INV_RATE, QUERIES,
 };
 
@@ -37,39 +35,38 @@ struct VerifyRoundInfo {
     domain: usize,
     merkle: MerkleTreeVerifier,
     mix: Fp4,
+    fold_domain: BarycentricDomain,
 }
 
-fn fold_eval(values: &mut [Fp4], mix: Fp4, s: usize, j: usize) -> Fp4 {
-    interpolate_ntt(values);
-    bit_reverse(values);
+// Evaluates the polynomial through `values`, on the FRI_FOLD-th roots of
+// unity, at `mix / w^j`, for `w` the root of unity of the unfolded domain.
+fn fold_eval(values: &[Fp4], fold_domain: &BarycentricDomain, mix: Fp4, s: usize, j: usize) -> Fp4 {
     let root_po2 = log2_ceil(FRI_FOLD * s);
-    let inv_wk: Fp = Fp::new(ROU_REV[root_po2]).pow(j);
-    let mut mul = Fp::ONE;
-    let mut tot = Fp4::ZERO;
-    let mut mix_pow = Fp4::ONE;
-    for i in 0..FRI_FOLD {
-        tot += values[i] * mul * mix_pow;
-        mul *= inv_wk;
-        mix_pow *= mix;
-    }
-    tot
+    let inv_wk: Fp = Fp::inv_root_of_unity(root_po2).pow(j);
+    eval_barycentric(values, fold_domain, mix * inv_wk)
 }
 
 impl VerifyRoundInfo {
-    pub fn new<S: Sha>(iop: &mut ReadIOP<S>, in_domain: usize) -> Self {
+    pub fn new(iop: &mut ReadIOP, in_domain: usize) -> Result<Self, VerificationError> {
         let domain = in_domain / FRI_FOLD;
-        VerifyRoundInfo {
+        Ok(VerifyRoundInfo {
             domain,
-            merkle: MerkleTreeVerifier::new(iop, domain, FRI_FOLD * EXT_SIZE, QUERIES),
+            merkle: MerkleTreeVerifier::new(iop, domain, FRI_FOLD * EXT_SIZE, QUERIES)?,
             mix: Fp4::random(iop),
-        }
+            fold_domain: BarycentricDomain::new(log2_ceil(FRI_FOLD), Fp::ONE),
+        })
     }
 
-    pub fn verify_query<S: Sha>(&mut self, iop: &mut ReadIOP<S>, pos: &mut usize, goal: &mut Fp4) {
+    pub fn verify_query(
+        &mut self,
+        iop: &mut ReadIOP,
+        pos: &mut usize,
+        goal: &mut Fp4,
+    ) -> Result<(), VerificationError> {
         let quot = *pos / self.domain;
         let group = *pos % self.domain;
         // Get the column data
-        let data = self.merkle.verify(iop, group);
+        let data = self.merkle.verify(iop, group)?;
         let mut data4 = vec![];
         for i in 0..FRI_FOLD {
             data4.push(Fp4::new(
@@ -80,42 +77,49 @@ impl VerifyRoundInfo {
             ));
         }
         // Check the existing goal
-        assert_eq!(data4[quot], *goal);
+        if data4[quot] != *goal {
+            return Err(VerificationError::MethodVerificationError);
+        }
         // Compute the new goal + pos
-        *goal = fold_eval(&mut data4, self.mix, self.domain, group);
+        *goal = fold_eval(&data4, &self.fold_domain, self.mix, self.domain, group);
         *pos = group;
+        Ok(())
     }
 }
 
-pub fn fri_verify<S: Sha, F>(iop: &mut ReadIOP<S>, mut degree: usize, mut inner: F)
+pub fn fri_verify<F>(
+    iop: &mut ReadIOP,
+    mut degree: usize,
+    mut inner: F,
+) -> Result<(), VerificationError>
 where
-    F: FnMut(&mut ReadIOP<S>, usize) -> Fp4,
+    F: FnMut(&mut ReadIOP, usize) -> Result<Fp4, VerificationError>,
 {
     let orig_domain = INV_RATE * degree;
     let mut domain = orig_domain;
     // Prep the folding verfiers
     let mut rounds = vec![];
     while degree > FRI_MIN_DEGREE {
-        rounds.push(VerifyRoundInfo::new(iop, domain));
+        rounds.push(VerifyRoundInfo::new(iop, domain)?);
         domain /= FRI_FOLD;
         degree /= FRI_FOLD;
     }
     // Grab the final coeffs + commit
     let mut final_coeffs = vec![Fp::ZERO; EXT_SIZE * degree];
-    iop.read_fps(&mut final_coeffs);
-    let final_digest = iop.get_sha().hash_fps(&final_coeffs); // padding?
+    iop.read_fps(&mut final_coeffs)?;
+    let final_digest = iop.get_suite().hash_elems(&final_coeffs); // padding?
     iop.commit(&final_digest);
     // Get the generator for the final polynomial evaluations
-    let gen = Fp::new(ROU_FWD[log2_ceil(domain)]);
+    let gen = Fp::root_of_unity(log2_ceil(domain));
     // Do queries
     for _ in 0..QUERIES {
         let rng = iop.next_u32();
         let mut pos = rng as usize % orig_domain;
         // Do the 'inner' verification for this index
-        let mut goal = inner(iop, pos);
+        let mut goal = inner(iop, pos)?;
         // Verify the per-round proofs
         for round in &mut rounds {
-            round.verify_query(iop, &mut pos, &mut goal);
+            round.verify_query(iop, &mut pos, &mut goal)?;
         }
         // Do final verification
         let x = gen.pow(pos);
@@ -131,6 +135,9 @@ where
             fx += cur * coeff;
             cur *= x;
         }
-        assert_eq!(fx, goal)
+        if fx != goal {
+            return Err(VerificationError::MethodVerificationError);
+        }
     }
+    Ok(())
 }
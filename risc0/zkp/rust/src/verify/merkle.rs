@@ -12,16 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, vec, vec::Vec};
 
 use crate::{
-    core::{
-        fp::Fp,
-        sha::{Digest, Sha},
-    },
+    core::{fp::Fp, sha::Digest},
     field::Elem,
-    merkle::MerkleTreeParams,
-    verify::read_iop::ReadIOP,
+    merkle::{insert_constants, MerkleTreeParams},
+    verify::{read_iop::ReadIOP, VerificationError},
 };
 
 /// A struct against which we verify merkle branches, consisting of the
@@ -30,31 +27,81 @@ use crate::{
 pub struct MerkleTreeVerifier {
     params: MerkleTreeParams,
     top: Vec<Digest>,
+    // With batched openings, the rows opened so far, and the nodes below the
+    // top layer that their branches proved.
+    opened: BTreeMap<usize, Vec<Fp>>,
+    known: BTreeMap<usize, Digest>,
+    // The index and value of each constant column left out of the leaves.
+    constants: Vec<(usize, Fp)>,
 }
 
 impl MerkleTreeVerifier {
     /// Constructs a new MerkleTreeVerifier by making the params, and then
     /// computing the root hashes from the top level hashes.
-    pub fn new<S: Sha>(
-        iop: &mut ReadIOP<S>,
+    pub fn new(
+        iop: &mut ReadIOP,
         row_size: usize,
         col_size: usize,
         queries: usize,
-    ) -> Self {
+    ) -> Result<Self, VerificationError> {
         let params = MerkleTreeParams::new(row_size, col_size, queries);
         // Initialize a vector to hold the digests.
         // Vector is twice as long as the "top" row - the children of the entry at index
         // i are stored at 2*i and 2*i+1.
         let mut top = vec![Digest::default(); params.top_size * 2];
         // Fill top vector with digests from IOP.
-        iop.read_digests(&mut top[params.top_size..]);
+        iop.read_digests(&mut top[params.top_size..])?;
         // Populate hashes up to the root of the tree.
         for i in (1..params.top_size).rev() {
-            top[i] = *iop.get_sha().hash_pair(&top[2 * i], &top[2 * i + 1]);
+            top[i] = iop.get_suite().hash_pair(&top[2 * i], &top[2 * i + 1]);
         }
         // Commit to root (index 1).
         iop.commit(&top[1]);
-        MerkleTreeVerifier { params, top }
+        Ok(MerkleTreeVerifier {
+            params,
+            top,
+            opened: BTreeMap::new(),
+            known: BTreeMap::new(),
+            constants: Vec::new(),
+        })
+    }
+
+    /// Reads a tree committed by
+    /// [MerkleTreeProver::new_compact](crate::prove::merkle::MerkleTreeProver::new_compact),
+    /// whose leaves leave out the constant columns listed after its top.
+    pub fn new_compact(
+        iop: &mut ReadIOP,
+        row_size: usize,
+        col_size: usize,
+        queries: usize,
+    ) -> Result<Self, VerificationError> {
+        let mut tree = Self::new(iop, row_size, col_size, queries)?;
+        let mut header = [Fp::ZERO; 1];
+        iop.read_fps(&mut header)?;
+        let count = u32::from(header[0]) as usize;
+        // At least one column is hashed, unless there are none.
+        if count > 0 && count >= col_size {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        let mut pairs = vec![Fp::ZERO; 2 * count];
+        iop.read_fps(&mut pairs)?;
+        for pair in pairs.chunks_exact(2) {
+            let col = u32::from(pair[0]) as usize;
+            let in_order = match tree.constants.last() {
+                Some((last, _)) => col > *last,
+                None => true,
+            };
+            if col >= col_size || !in_order {
+                return Err(VerificationError::ReceiptFormatError);
+            }
+            tree.constants.push((col, pair[1]));
+        }
+        let mut elems = header.to_vec();
+        elems.extend_from_slice(&pairs);
+        let digest = iop.get_suite().hash_elems(&elems);
+        iop.commit(&digest);
+        tree.params = MerkleTreeParams::new(row_size, col_size - count, queries);
+        Ok(tree)
     }
 
     /// Returns the root hash of the tree.
@@ -62,15 +109,27 @@ impl MerkleTreeVerifier {
         &self.top[1]
     }
 
-    /// Verifies a branch provided by an IOP.
-    pub fn verify<S: Sha>(&self, iop: &mut ReadIOP<S>, mut idx: usize) -> Vec<Fp> {
+    /// Verifies a branch provided by an IOP, and returns the row it opens,
+    /// including any constant columns.
+    pub fn verify(&mut self, iop: &mut ReadIOP, idx: usize) -> Result<Vec<Fp>, VerificationError> {
+        let mut row = if iop.batch_openings() {
+            self.verify_batched(iop, idx)?
+        } else {
+            self.verify_branch(iop, idx)?
+        };
+        insert_constants(&mut row, &self.constants);
+        Ok(row)
+    }
+
+    fn verify_branch(&self, iop: &mut ReadIOP, idx: usize) -> Result<Vec<Fp>, VerificationError> {
+        let mut idx = idx;
         assert!(idx < self.params.row_size);
         // Initialize a vector to hold field elements.
         let mut out = vec![Fp::ZERO; self.params.col_size];
         // Read out field elements from IOP.
-        iop.read_fps(&mut out);
+        iop.read_fps(&mut out)?;
         // Get the hash at the leaf of the tree by hashing these field elements.
-        let mut cur = *iop.get_sha().hash_fps(&out);
+        let mut cur = iop.get_suite().hash_elems(&out);
         // Shift idx to start of the row
         idx += self.params.row_size;
         while idx >= 2 * self.params.top_size {
@@ -79,18 +138,71 @@ impl MerkleTreeVerifier {
             let low_bit = idx % 2;
             // Retrieve the other parent from the IOP.
             let mut other = [Digest::default(); 1];
-            iop.read_digests(&mut other);
+            iop.read_digests(&mut other)?;
             // Now ascend to the parent index, and compute the hash there.
             idx /= 2;
             if low_bit == 1 {
-                cur = *iop.get_sha().hash_pair(&other[0], &cur);
+                cur = iop.get_suite().hash_pair(&other[0], &cur);
             } else {
-                cur = *iop.get_sha().hash_pair(&cur, &other[0]);
+                cur = iop.get_suite().hash_pair(&cur, &other[0]);
             }
         }
         // Once we reduce to an index for which we have the hash, check that it's
         // correct.
-        assert_eq!(self.top[idx], cur);
-        out
+        if self.top[idx] != cur {
+            return Err(VerificationError::MethodVerificationError);
+        }
+        Ok(out)
+    }
+
+    // Verifies a branch of a batched opening, which leaves out the row if it
+    // was opened before, and stops at the first node proved by an earlier
+    // branch.
+    fn verify_batched(
+        &mut self,
+        iop: &mut ReadIOP,
+        row: usize,
+    ) -> Result<Vec<Fp>, VerificationError> {
+        assert!(row < self.params.row_size);
+        if let Some(out) = self.opened.get(&row) {
+            return Ok(out.clone());
+        }
+        let mut out = vec![Fp::ZERO; self.params.col_size];
+        iop.read_fps(&mut out)?;
+        let suite = iop.get_suite();
+        let mut cur = suite.hash_elems(&out);
+        // The nodes this branch proves, once it reaches a known node.
+        let mut proved = Vec::new();
+        let mut idx = row + self.params.row_size;
+        let expected = loop {
+            if idx < 2 * self.params.top_size {
+                break self.top[idx];
+            }
+            if let Some(known) = self.known.get(&idx) {
+                break *known;
+            }
+            let other = match self.known.get(&(idx ^ 1)) {
+                Some(other) => *other,
+                None => {
+                    let mut other = [Digest::default(); 1];
+                    iop.read_digests(&mut other)?;
+                    proved.push((idx ^ 1, other[0]));
+                    other[0]
+                }
+            };
+            proved.push((idx, cur));
+            cur = if idx % 2 == 1 {
+                suite.hash_pair(&other, &cur)
+            } else {
+                suite.hash_pair(&cur, &other)
+            };
+            idx /= 2;
+        };
+        if expected != cur {
+            return Err(VerificationError::MethodVerificationError);
+        }
+        self.known.extend(proved);
+        self.opened.insert(row, out.clone());
+        Ok(out)
     }
 }
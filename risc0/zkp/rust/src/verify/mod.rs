@@ -14,25 +14,30 @@
 
 pub mod adapter;
 mod fri;
-mod merkle;
+pub(crate) mod merkle;
 pub mod read_iop;
+pub mod reference;
 
 use alloc::vec;
 use core::fmt;
 // use log::debug;
 
 use crate::{
+    adapter::{CircuitInfo, PolyExt, TapsProvider},
     core::{
         fp::Fp,
         fp4::{Fp4, EXT_SIZE},
+        hash::HashSuite,
+        lde::CosetLde,
         log2_ceil,
         poly::poly_eval,
-        rou::{ROU_FWD, ROU_REV},
-        sha::{Digest, Sha},
+        sha::Digest,
     },
-    field::Elem,
+    field::{Elem, TwoAdicField},
     taps::{RegisterGroup, TapSet},
-    verify::{fri::fri_verify, merkle::MerkleTreeVerifier, read_iop::ReadIOP},
+    verify::{
+        adapter::VerifyAdapter, fri::fri_verify, merkle::MerkleTreeVerifier, read_iop::ReadIOP,
+    },
     INV_RATE, MAX_CYCLES_PO2, QUERIES,
 };
 
@@ -42,6 +47,12 @@ const CHECK_SIZE: usize = INV_RATE * EXT_SIZE;
 pub enum VerificationError {
     ReceiptFormatError,
     MethodVerificationError,
+    /// The seal is valid, but the claim check passed to [verify_seal]
+    /// rejected what it proves.
+    InvalidClaim,
+    /// The seal was committed with a different [HashSuite] than the one it
+    /// was checked with.
+    HashSuiteMismatch,
 }
 
 impl fmt::Display for VerificationError {
@@ -49,22 +60,111 @@ impl fmt::Display for VerificationError {
         match self {
             VerificationError::ReceiptFormatError => write!(f, "invalid receipt format"),
             VerificationError::MethodVerificationError => write!(f, "method verification failed"),
+            VerificationError::InvalidClaim => write!(f, "claim rejected"),
+            VerificationError::HashSuiteMismatch => write!(f, "seal uses another hash suite"),
         }
     }
 }
 
 pub trait Circuit {
     fn taps(&self) -> &TapSet;
-    fn execute<S: Sha>(&mut self, iop: &mut ReadIOP<S>);
-    fn accumulate<S: Sha>(&mut self, iop: &mut ReadIOP<S>);
+    fn execute(&mut self, iop: &mut ReadIOP) -> Result<(), VerificationError>;
+    fn accumulate(&mut self, iop: &mut ReadIOP);
     fn po2(&self) -> u32;
-    fn check_code(&self, root: &Digest) -> Result<(), VerificationError>;
+    fn check_code(&mut self, root: &Digest) -> Result<(), VerificationError>;
     fn compute_polynomial(&self, u: &[Fp4], mix: Fp4) -> Fp4;
 }
 
-pub fn verify<S, C>(sha: &S, circuit: &mut C, seal: &[u32]) -> Result<(), VerificationError>
+/// What a seal proves, as passed by [verify_seal] to its claim check.
+#[derive(Clone, Copy, Debug)]
+pub struct SealClaim<'a> {
+    /// The outputs of the circuit, read from the start of the seal.  For
+    /// the zkVM these are its global registers, which commit to the
+    /// journal.
+    pub outputs: &'a [Fp],
+
+    /// The trace had 2^`po2` cycles.
+    pub po2: u32,
+
+    /// Whether the trace was padded with random values, giving zero
+    /// knowledge; see [NON_ZK_FLAG](crate::NON_ZK_FLAG).
+    pub zk: bool,
+
+    /// The root of the Merkle tree over the code columns.  For the zkVM,
+    /// this is the entry of the method ID for `po2`.
+    pub code_root: &'a Digest,
+}
+
+/// Verifies that `seal` is a valid proof of an execution of `circuit`,
+/// committed to with `suite`, and then passes what it proves to
+/// `check_claim`, which decides whether that is the claim the caller
+/// expects.
+///
+/// This is the STARK verification core of the zkVM's receipts without
+/// their plumbing, for integrations such as custom claims, other receipt
+/// formats and code generators for on-chain verifiers.  Its signature and
+/// the layout of [SealClaim] are kept stable across releases; the zkVM's
+/// own receipts are checked through it.
+///
+/// `check_claim` is only called for a valid seal, and should return
+/// [VerificationError::InvalidClaim] to reject one.
+pub fn verify_seal<C, F>(
+    suite: &dyn HashSuite,
+    circuit: &C,
+    seal: &[u32],
+    check_claim: F,
+) -> Result<(), VerificationError>
+where
+    C: CircuitInfo + PolyExt + TapsProvider,
+    F: FnOnce(&SealClaim) -> Result<(), VerificationError>,
+{
+    let mut verifier = VerifyAdapter::new(circuit);
+    verify(suite, &mut verifier, seal)?;
+    check_claim(&SealClaim {
+        outputs: verifier.outputs(),
+        po2: verifier.po2(),
+        zk: verifier.zk(),
+        code_root: verifier.code_root(),
+    })
+}
+
+/// Verifies `seal` with both the fast verifier and the slow
+/// [reference](reference::verify) one, succeeding only if both accept it.
+///
+/// This is much slower than [verify]; it is meant for receipts where an
+/// extra check is worth the cost. `circuit` is executed once per verifier.
+pub fn paranoid_verify<C>(
+    suite: &dyn HashSuite,
+    circuit: &mut C,
+    seal: &[u32],
+) -> Result<(), VerificationError>
+where
+    C: Circuit,
+{
+    verify(suite, circuit, seal)?;
+    reference::verify(suite, circuit, seal)
+}
+
+// Reads the tree of a trace group, which leaves out constant columns if the
+// seal's header says so.
+fn trace_merkle(
+    iop: &mut ReadIOP,
+    domain: usize,
+    col_size: usize,
+) -> Result<MerkleTreeVerifier, VerificationError> {
+    if iop.constant_columns() {
+        MerkleTreeVerifier::new_compact(iop, domain, col_size, QUERIES)
+    } else {
+        MerkleTreeVerifier::new(iop, domain, col_size, QUERIES)
+    }
+}
+
+pub fn verify<C>(
+    suite: &dyn HashSuite,
+    circuit: &mut C,
+    seal: &[u32],
+) -> Result<(), VerificationError>
 where
-    S: Sha,
     C: Circuit,
 {
     if seal.len() == 0 {
@@ -73,14 +173,16 @@ where
     let taps = circuit.taps().clone();
 
     // Make IOP
-    let mut iop = ReadIOP::new(sha, seal);
+    let mut iop = ReadIOP::new(suite, seal);
 
     // Read any execution state
-    circuit.execute(&mut iop);
+    circuit.execute(&mut iop)?;
 
     // Get the size
     let po2 = circuit.po2();
-    assert!(po2 as usize <= MAX_CYCLES_PO2);
+    if po2 as usize > MAX_CYCLES_PO2 {
+        return Err(VerificationError::ReceiptFormatError);
+    }
     let size = 1 << po2;
     let domain = INV_RATE * size;
     // debug!("size = {size}, po2 = {po2}");
@@ -92,9 +194,9 @@ where
     let combo_count = taps.combos_size();
 
     // Get code and data merkle roots
-    let code_merkle = MerkleTreeVerifier::new(&mut iop, domain, code_size, QUERIES);
+    let mut code_merkle = MerkleTreeVerifier::new(&mut iop, domain, code_size, QUERIES)?;
     // debug!("codeRoot = {}", code_merkle.root());
-    let data_merkle = MerkleTreeVerifier::new(&mut iop, domain, data_size, QUERIES);
+    let mut data_merkle = trace_merkle(&mut iop, domain, data_size)?;
     // debug!("dataRoot = {}", data_merkle.root());
 
     // Verify code is valid
@@ -103,24 +205,24 @@ where
     // Prep accumulation
     circuit.accumulate(&mut iop);
 
-    let accum_merkle = MerkleTreeVerifier::new(&mut iop, domain, accum_size, QUERIES);
+    let mut accum_merkle = trace_merkle(&mut iop, domain, accum_size)?;
     // debug!("accumRoot = {}", accum_merkle.root());
 
     // Set the poly mix value
     let poly_mix = Fp4::random(&mut iop);
 
-    let check_merkle = MerkleTreeVerifier::new(&mut iop, domain, CHECK_SIZE, QUERIES);
+    let mut check_merkle = MerkleTreeVerifier::new(&mut iop, domain, CHECK_SIZE, QUERIES)?;
     // debug!("checkRoot = {}", check_merkle.root());
 
     let z = Fp4::random(&mut iop);
     // debug!("Z = {z:?}");
-    let back_one = Fp::from(ROU_REV[po2 as usize]);
+    let back_one = Fp::inv_root_of_unity(po2 as usize);
 
     // Read the U coeffs + commit their hash
     let num_taps = taps.tap_size();
     let mut coeff_u = vec![Fp4::ZERO; num_taps + CHECK_SIZE];
-    iop.read_fp4s(&mut coeff_u);
-    let hash_u = *sha.hash_fp4s(&coeff_u);
+    iop.read_fp4s(&mut coeff_u)?;
+    let hash_u = suite.hash_ext_elems(&coeff_u);
     iop.commit(&hash_u);
 
     // Now, convert to evaluated values
@@ -151,9 +253,11 @@ where
         check += coeff_u[num_taps + rmi + 8] * z.pow(i) * Fp4::new(fp0, fp0, fp1, fp0);
         check += coeff_u[num_taps + rmi + 12] * z.pow(i) * Fp4::new(fp0, fp0, fp0, fp1);
     }
-    check *= (Fp4::from_u32(3) * z).pow(size) - Fp4::ONE;
+    check *= (Fp4::from_fp(CosetLde::ZK.shift()) * z).pow(size) - Fp4::ONE;
     // debug!("Check = {check:?}");
-    assert_eq!(check, result);
+    if check != result {
+        return Err(VerificationError::MethodVerificationError);
+    }
 
     // Set the mix mix value
     let mix = Fp4::random(&mut iop);
@@ -183,15 +287,15 @@ where
     }
     // debug!("cur_mix: {cur_mix:?}");
 
-    let gen = Fp::new(ROU_FWD[log2_ceil(domain)]);
+    let gen = Fp::root_of_unity(log2_ceil(domain));
     // debug!("FRI-verify, size = {size}");
-    fri_verify(&mut iop, size, |iop: &mut ReadIOP<S>, idx: usize| -> Fp4 {
+    fri_verify(&mut iop, size, |iop: &mut ReadIOP, idx: usize| {
         let x = Fp4::from_fp(gen.pow(idx));
         let mut rows = vec![];
-        rows.push(accum_merkle.verify(iop, idx));
-        rows.push(code_merkle.verify(iop, idx));
-        rows.push(data_merkle.verify(iop, idx));
-        let check_row = check_merkle.verify(iop, idx);
+        rows.push(accum_merkle.verify(iop, idx)?);
+        rows.push(code_merkle.verify(iop, idx)?);
+        rows.push(data_merkle.verify(iop, idx)?);
+        let check_row = check_merkle.verify(iop, idx)?;
         let mut cur = Fp4::ONE;
         let mut tot = vec![Fp4::ZERO; combo_count + 1];
         for reg in taps.regs() {
@@ -202,20 +306,23 @@ where
             tot[combo_count] += cur * check_row[i];
             cur *= mix;
         }
+        // Invert all the divisors at once.
+        let mut divisors = vec![Fp4::ONE; combo_count + 1];
+        for (i, divisor) in divisors.iter_mut().take(combo_count).enumerate() {
+            for back in taps.get_combo(i).slice() {
+                *divisor *= x - z * back_one.pow(*back as usize);
+            }
+        }
+        divisors[combo_count] = x - z.pow(INV_RATE);
+        Fp4::batch_inv(&mut divisors);
         let mut ret = Fp4::ZERO;
         for i in 0..combo_count {
             let num = tot[i] - poly_eval(&combo_u[i], x);
-            let mut divisor = Fp4::ONE;
-            for back in taps.get_combo(i).slice() {
-                divisor *= x - z * back_one.pow(*back as usize);
-            }
-            ret += num * divisor.inv();
+            ret += num * divisors[i];
         }
         let check_num = tot[combo_count] - combo_u[combo_count][0];
-        let check_div = x - z.pow(INV_RATE);
-        ret += check_num * check_div.inv();
-        ret
-    });
-    iop.verify_complete();
-    Ok(())
+        ret += check_num * divisors[combo_count];
+        Ok(ret)
+    })?;
+    iop.verify_complete()
 }
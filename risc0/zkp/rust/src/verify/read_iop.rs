@@ -12,78 +12,139 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::boxed::Box;
+use core::fmt;
+
 use rand::{Error, RngCore};
 
+use super::VerificationError;
 use crate::core::{
     fp::Fp,
     fp4::Fp4,
-    sha::{Digest, Sha, DIGEST_WORDS},
-    sha_rng::ShaRng,
+    hash::{HashSuite, TranscriptRng},
+    sha::{Digest, DIGEST_WORDS},
 };
 
-#[derive(Debug)]
-pub struct ReadIOP<'a, S: Sha> {
-    sha: S,
+// Proof words must already be canonical; a prover that wrote out-of-range
+// values has produced a malformed receipt.
+fn read_fp(word: u32) -> Result<Fp, VerificationError> {
+    Fp::from_u32_checked(word).ok_or(VerificationError::ReceiptFormatError)
+}
+
+pub struct ReadIOP<'a> {
+    suite: &'a dyn HashSuite,
     proof: &'a [u32],
-    rng: ShaRng<S>,
+    rng: Box<dyn TranscriptRng + 'a>,
+    batch_openings: bool,
+    constant_columns: bool,
 }
 
-impl<'a, S: Sha> ReadIOP<'a, S> {
-    pub fn new(sha: &'a S, proof: &'a [u32]) -> Self {
+impl<'a> ReadIOP<'a> {
+    /// Read `proof`, which was committed with `suite`.
+    pub fn new(suite: &'a dyn HashSuite, proof: &'a [u32]) -> Self {
         ReadIOP {
-            sha: sha.clone(),
+            suite,
             proof,
-            rng: ShaRng::new(sha),
+            rng: suite.rng(),
+            batch_openings: false,
+            constant_columns: false,
         }
     }
 
-    pub fn get_sha(&self) -> &S {
-        &self.sha
+    /// Whether Merkle openings are batched; see
+    /// [BATCHED_OPENINGS_FLAG](crate::BATCHED_OPENINGS_FLAG).  Set from the
+    /// seal's header.
+    pub fn batch_openings(&self) -> bool {
+        self.batch_openings
     }
 
-    pub fn read_u32s(&mut self, x: &mut [u32]) {
-        for i in 0..x.len() {
-            x[i] = self.proof[i];
+    pub fn set_batch_openings(&mut self, batch_openings: bool) {
+        self.batch_openings = batch_openings;
+    }
+
+    /// Whether trace trees leave out constant columns; see
+    /// [CONSTANT_COLUMNS_FLAG](crate::CONSTANT_COLUMNS_FLAG).  Set from the
+    /// seal's header.
+    pub fn constant_columns(&self) -> bool {
+        self.constant_columns
+    }
+
+    pub fn set_constant_columns(&mut self, constant_columns: bool) {
+        self.constant_columns = constant_columns;
+    }
+
+    pub fn get_suite(&self) -> &'a dyn HashSuite {
+        self.suite
+    }
+
+    // Takes the next `len` words of the proof, failing if it's too short.
+    fn take(&mut self, len: usize) -> Result<&'a [u32], VerificationError> {
+        if self.proof.len() < len {
+            return Err(VerificationError::ReceiptFormatError);
         }
-        self.proof = &self.proof[x.len()..];
+        let (head, tail) = self.proof.split_at(len);
+        self.proof = tail;
+        Ok(head)
+    }
+
+    pub fn read_u32s(&mut self, x: &mut [u32]) -> Result<(), VerificationError> {
+        x.copy_from_slice(self.take(x.len())?);
+        Ok(())
     }
 
-    pub fn read_fps(&mut self, x: &mut [Fp]) {
+    pub fn read_fps(&mut self, x: &mut [Fp]) -> Result<(), VerificationError> {
+        let words = self.take(x.len())?;
         for i in 0..x.len() {
-            x[i] = Fp::from(self.proof[i]);
+            x[i] = read_fp(words[i])?;
         }
-        self.proof = &self.proof[x.len()..];
+        Ok(())
     }
 
-    pub fn read_fp4s(&mut self, x: &mut [Fp4]) {
+    pub fn read_fp4s(&mut self, x: &mut [Fp4]) -> Result<(), VerificationError> {
+        let words = self.take(4 * x.len())?;
         for i in 0..x.len() {
             x[i] = Fp4::new(
-                Fp::from(self.proof[4 * i + 0]),
-                Fp::from(self.proof[4 * i + 1]),
-                Fp::from(self.proof[4 * i + 2]),
-                Fp::from(self.proof[4 * i + 3]),
+                read_fp(words[4 * i + 0])?,
+                read_fp(words[4 * i + 1])?,
+                read_fp(words[4 * i + 2])?,
+                read_fp(words[4 * i + 3])?,
             )
         }
-        self.proof = &self.proof[4 * x.len()..];
+        Ok(())
     }
 
-    pub fn read_digests(&mut self, x: &mut [Digest]) {
+    pub fn read_digests(&mut self, x: &mut [Digest]) -> Result<(), VerificationError> {
+        let words = self.take(DIGEST_WORDS * x.len())?;
         for i in 0..x.len() {
-            x[i] = Digest::from_slice(&self.proof[DIGEST_WORDS * i..DIGEST_WORDS * (i + 1)]);
+            x[i] = Digest::from_slice(&words[DIGEST_WORDS * i..DIGEST_WORDS * (i + 1)]);
         }
-        self.proof = &self.proof[DIGEST_WORDS * x.len()..];
+        Ok(())
     }
 
     pub fn commit(&mut self, digest: &Digest) {
         self.rng.mix(digest);
     }
 
-    pub fn verify_complete(&self) {
-        assert_eq!(self.proof.len(), 0);
+    pub fn verify_complete(&self) -> Result<(), VerificationError> {
+        if !self.proof.is_empty() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for ReadIOP<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadIOP")
+            .field("suite", &self.suite)
+            .field("remaining", &self.proof.len())
+            .field("batch_openings", &self.batch_openings)
+            .field("constant_columns", &self.constant_columns)
+            .finish()
     }
 }
 
-impl<'a, S: Sha> RngCore for ReadIOP<'a, S> {
+impl<'a> RngCore for ReadIOP<'a> {
     fn next_u32(&mut self) -> u32 {
         self.rng.next_u32()
     }
@@ -0,0 +1,411 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A slow, straightforward second implementation of [verify](super::verify).
+//!
+//! Every polynomial here is evaluated term by term, every inverse is taken
+//! on its own, and Merkle branches are followed all the way to the root,
+//! even where a batched opening leaves out nodes proved by earlier ones. The
+//! only code shared with the fast verifier is the transcript ([ReadIOP]),
+//! the circuit's constraint polynomial and the field arithmetic, so the two
+//! verifiers agreeing on a seal is a useful cross-check of the optimizations
+//! in the fast one.
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use rand::RngCore;
+
+use crate::{
+    core::{
+        fp::Fp,
+        fp4::{Fp4, EXT_SIZE},
+        hash::HashSuite,
+        log2_ceil,
+        sha::Digest,
+    },
+    field::{Elem, TwoAdicField},
+    merkle::MerkleTreeParams,
+    taps::RegisterGroup,
+    verify::{read_iop::ReadIOP, Circuit, VerificationError, CHECK_SIZE},
+    FRI_FOLD, FRI_MIN_DEGREE, INV_RATE, MAX_CYCLES_PO2, QUERIES,
+};
+
+// Evaluates `coeffs` at `x` one monomial at a time.
+fn eval_naive(coeffs: &[Fp4], x: Fp4) -> Fp4 {
+    let mut tot = Fp4::ZERO;
+    for (i, coeff) in coeffs.iter().enumerate() {
+        tot += *coeff * x.pow(i);
+    }
+    tot
+}
+
+// The unit vector for coordinate `i` of the extension field.
+fn basis(i: usize) -> Fp4 {
+    let mut elems = [Fp::ZERO; EXT_SIZE];
+    elems[i] = Fp::ONE;
+    Fp4::new(elems[0], elems[1], elems[2], elems[3])
+}
+
+struct MerkleTree {
+    params: MerkleTreeParams,
+    // The committed top of the tree, from the top row (first) to the root.
+    layers: Vec<Vec<Digest>>,
+    // With batched openings, the rows opened so far, and the nodes below the
+    // top row proved along the way, by their height above the leaves and
+    // position.
+    opened: BTreeMap<usize, Vec<Fp>>,
+    proved: BTreeMap<(usize, usize), Digest>,
+    // The width of a row, and the values of the constant columns left out of
+    // the leaves, by index.
+    cols: usize,
+    constants: BTreeMap<usize, Fp>,
+}
+
+impl MerkleTree {
+    fn new(iop: &mut ReadIOP, row_size: usize, col_size: usize) -> Result<Self, VerificationError> {
+        let params = MerkleTreeParams::new(row_size, col_size, QUERIES);
+        let mut row = vec![Digest::default(); params.top_size];
+        iop.read_digests(&mut row)?;
+        let mut layers = vec![row];
+        while layers.last().unwrap().len() > 1 {
+            let below = layers.last().unwrap();
+            let above = below
+                .chunks(2)
+                .map(|pair| iop.get_suite().hash_pair(&pair[0], &pair[1]))
+                .collect();
+            layers.push(above);
+        }
+        let root = *layers.last().unwrap().first().unwrap();
+        iop.commit(&root);
+        Ok(MerkleTree {
+            params,
+            layers,
+            opened: BTreeMap::new(),
+            proved: BTreeMap::new(),
+            cols: col_size,
+            constants: BTreeMap::new(),
+        })
+    }
+
+    // Reads a tree followed by the list of its constant columns: a count,
+    // then an index and a value for each, in increasing order of index.
+    fn new_compact(
+        iop: &mut ReadIOP,
+        row_size: usize,
+        col_size: usize,
+    ) -> Result<Self, VerificationError> {
+        let mut tree = Self::new(iop, row_size, col_size)?;
+        let mut elems = vec![Fp::ZERO; 1];
+        iop.read_fps(&mut elems)?;
+        let count = u32::from(elems[0]) as usize;
+        if count > 0 && count >= col_size {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        let mut prev = None;
+        for _ in 0..count {
+            let mut pair = [Fp::ZERO; 2];
+            iop.read_fps(&mut pair)?;
+            let col = u32::from(pair[0]) as usize;
+            if col >= col_size || prev.map_or(false, |prev| col <= prev) {
+                return Err(VerificationError::ReceiptFormatError);
+            }
+            prev = Some(col);
+            tree.constants.insert(col, pair[1]);
+            elems.extend_from_slice(&pair);
+        }
+        let digest = iop.get_suite().hash_elems(&elems);
+        iop.commit(&digest);
+        tree.params = MerkleTreeParams::new(row_size, col_size - count, QUERIES);
+        Ok(tree)
+    }
+
+    fn root(&self) -> &Digest {
+        &self.layers.last().unwrap()[0]
+    }
+
+    // Opens a row, filling in the constant columns around the hashed ones.
+    fn verify(&mut self, iop: &mut ReadIOP, pos: usize) -> Result<Vec<Fp>, VerificationError> {
+        let mut hashed = self.verify_leaf(iop, pos)?.into_iter();
+        Ok((0..self.cols)
+            .map(|col| match self.constants.get(&col) {
+                Some(value) => *value,
+                None => hashed.next().unwrap(),
+            })
+            .collect())
+    }
+
+    // With batched openings, a sibling proved by an earlier branch isn't in
+    // the proof, and neither is a row opened before.
+    fn verify_leaf(
+        &mut self,
+        iop: &mut ReadIOP,
+        mut pos: usize,
+    ) -> Result<Vec<Fp>, VerificationError> {
+        if pos >= self.params.row_size {
+            return Err(VerificationError::MethodVerificationError);
+        }
+        let batched = iop.batch_openings();
+        let row = pos;
+        if batched {
+            if let Some(out) = self.opened.get(&row) {
+                return Ok(out.clone());
+            }
+        }
+        let mut out = vec![Fp::ZERO; self.params.col_size];
+        iop.read_fps(&mut out)?;
+        let suite = iop.get_suite();
+        let mut cur = suite.hash_elems(&out);
+        let hash = |pos: usize, cur: &Digest, other: &Digest| {
+            if pos % 2 == 1 {
+                suite.hash_pair(other, cur)
+            } else {
+                suite.hash_pair(cur, other)
+            }
+        };
+        // Siblings below the top row come from the proof...
+        let mut proved = vec![];
+        for height in 0..self.params.layers - self.params.top_layer {
+            let other = match self.proved.get(&(height, pos ^ 1)) {
+                Some(other) => *other,
+                None => {
+                    let mut other = [Digest::default(); 1];
+                    iop.read_digests(&mut other)?;
+                    other[0]
+                }
+            };
+            if batched {
+                proved.push(((height, pos), cur));
+                proved.push(((height, pos ^ 1), other));
+            }
+            cur = hash(pos, &cur, &other);
+            pos /= 2;
+        }
+        // ...and the rest from the committed top of the tree.
+        for layer in &self.layers[..self.layers.len() - 1] {
+            cur = hash(pos, &cur, &layer[pos ^ 1]);
+            pos /= 2;
+        }
+        if cur != *self.root() {
+            return Err(VerificationError::MethodVerificationError);
+        }
+        if batched {
+            self.proved.extend(proved);
+            self.opened.insert(row, out.clone());
+        }
+        Ok(out)
+    }
+}
+
+// Folds the `FRI_FOLD` evaluations in `values` by computing the coefficients
+// of the polynomial through them with a direct inverse DFT.
+fn fold_eval(values: &[Fp4], mix: Fp4, domain: usize, group: usize) -> Fp4 {
+    let inv_root = Fp::inv_root_of_unity(log2_ceil(FRI_FOLD));
+    let inv_wk = Fp::inv_root_of_unity(log2_ceil(FRI_FOLD * domain)).pow(group);
+    let norm = Fp::new(FRI_FOLD as u32).inv();
+    let mut tot = Fp4::ZERO;
+    for k in 0..FRI_FOLD {
+        let mut coeff = Fp4::ZERO;
+        for (i, value) in values.iter().enumerate() {
+            coeff += *value * inv_root.pow(i * k);
+        }
+        tot += coeff * norm * inv_wk.pow(k) * mix.pow(k);
+    }
+    tot
+}
+
+fn fri_verify<F>(iop: &mut ReadIOP, degree: usize, mut inner: F) -> Result<(), VerificationError>
+where
+    F: FnMut(&mut ReadIOP, usize) -> Result<Fp4, VerificationError>,
+{
+    let orig_domain = INV_RATE * degree;
+    let mut rounds = vec![];
+    let mut domain = orig_domain;
+    let mut degree = degree;
+    while degree > FRI_MIN_DEGREE {
+        domain /= FRI_FOLD;
+        degree /= FRI_FOLD;
+        let merkle = MerkleTree::new(iop, domain, FRI_FOLD * EXT_SIZE)?;
+        let mix = Fp4::random(iop);
+        rounds.push((domain, merkle, mix));
+    }
+    let mut final_coeffs = vec![Fp::ZERO; EXT_SIZE * degree];
+    iop.read_fps(&mut final_coeffs)?;
+    let final_digest = iop.get_suite().hash_elems(&final_coeffs);
+    iop.commit(&final_digest);
+    let coeffs: Vec<Fp4> = (0..degree)
+        .map(|i| {
+            Fp4::new(
+                final_coeffs[i],
+                final_coeffs[degree + i],
+                final_coeffs[2 * degree + i],
+                final_coeffs[3 * degree + i],
+            )
+        })
+        .collect();
+    let gen = Fp::root_of_unity(log2_ceil(domain));
+    for _ in 0..QUERIES {
+        let mut pos = iop.next_u32() as usize % orig_domain;
+        let mut goal = inner(iop, pos)?;
+        for (domain, merkle, mix) in &mut rounds {
+            let quot = pos / *domain;
+            let group = pos % *domain;
+            let data = merkle.verify(iop, group)?;
+            let values: Vec<Fp4> = (0..FRI_FOLD)
+                .map(|i| {
+                    Fp4::new(
+                        data[i],
+                        data[FRI_FOLD + i],
+                        data[2 * FRI_FOLD + i],
+                        data[3 * FRI_FOLD + i],
+                    )
+                })
+                .collect();
+            if values[quot] != goal {
+                return Err(VerificationError::MethodVerificationError);
+            }
+            goal = fold_eval(&values, *mix, *domain, group);
+            pos = group;
+        }
+        if eval_naive(&coeffs, Fp4::from_fp(gen.pow(pos))) != goal {
+            return Err(VerificationError::MethodVerificationError);
+        }
+    }
+    Ok(())
+}
+
+/// Verifies `seal` like [verify](super::verify), using the slow reference
+/// implementation.
+pub fn verify<C>(
+    suite: &dyn HashSuite,
+    circuit: &mut C,
+    seal: &[u32],
+) -> Result<(), VerificationError>
+where
+    C: Circuit,
+{
+    if seal.is_empty() {
+        return Err(VerificationError::ReceiptFormatError);
+    }
+    let taps = circuit.taps().clone();
+    let mut iop = ReadIOP::new(suite, seal);
+    circuit.execute(&mut iop)?;
+    let po2 = circuit.po2() as usize;
+    if po2 > MAX_CYCLES_PO2 {
+        return Err(VerificationError::ReceiptFormatError);
+    }
+    let size = 1 << po2;
+    let domain = INV_RATE * size;
+
+    // Only the trace trees may leave out constant columns.
+    let trace_tree = |iop: &mut ReadIOP, group| {
+        let col_size = taps.group_size(group);
+        if iop.constant_columns() {
+            MerkleTree::new_compact(iop, domain, col_size)
+        } else {
+            MerkleTree::new(iop, domain, col_size)
+        }
+    };
+    let mut code_merkle = MerkleTree::new(&mut iop, domain, taps.group_size(RegisterGroup::Code))?;
+    let mut data_merkle = trace_tree(&mut iop, RegisterGroup::Data)?;
+    circuit.check_code(code_merkle.root())?;
+    circuit.accumulate(&mut iop);
+    let mut accum_merkle = trace_tree(&mut iop, RegisterGroup::Accum)?;
+    let poly_mix = Fp4::random(&mut iop);
+    let mut check_merkle = MerkleTree::new(&mut iop, domain, CHECK_SIZE)?;
+    let z = Fp4::random(&mut iop);
+    let back_one = Fp::inv_root_of_unity(po2);
+
+    let num_taps = taps.tap_size();
+    let mut coeff_u = vec![Fp4::ZERO; num_taps + CHECK_SIZE];
+    iop.read_fp4s(&mut coeff_u)?;
+    let hash_u = suite.hash_ext_elems(&coeff_u);
+    iop.commit(&hash_u);
+
+    // Evaluate every tap at its own point, one register at a time.
+    let mut eval_u = vec![];
+    let mut cur_pos = 0;
+    for reg in taps.regs() {
+        let coeffs = &coeff_u[cur_pos..cur_pos + reg.size()];
+        for i in 0..reg.size() {
+            eval_u.push(eval_naive(coeffs, z * back_one.pow(reg.back(i))));
+        }
+        cur_pos += reg.size();
+    }
+    let result = circuit.compute_polynomial(&eval_u, poly_mix);
+
+    // The check polynomial is split into EXT_SIZE pieces of degree `size`,
+    // each stored as EXT_SIZE base field coefficients.
+    let remap = [0, 2, 1, 3];
+    let mut check = Fp4::ZERO;
+    for i in 0..EXT_SIZE {
+        for j in 0..EXT_SIZE {
+            let coeff = coeff_u[num_taps + remap[i] + EXT_SIZE * j];
+            check += coeff * z.pow(i) * basis(j);
+        }
+    }
+    check *= (Fp4::from_u32(3) * z).pow(size) - Fp4::ONE;
+    if check != result {
+        return Err(VerificationError::MethodVerificationError);
+    }
+
+    let mix = Fp4::random(&mut iop);
+    let combo_count = taps.combos_size();
+    // combo_u[i] mixes the coefficients of every register in combo `i`; the
+    // last entry holds the check polynomial.
+    let mut combo_u: Vec<Vec<Fp4>> = (0..combo_count)
+        .map(|i| vec![Fp4::ZERO; taps.get_combo(i).size()])
+        .collect();
+    combo_u.push(vec![Fp4::ZERO]);
+    let mut cur_pos = 0;
+    for (k, reg) in taps.regs().enumerate() {
+        for i in 0..reg.size() {
+            combo_u[reg.combo_id()][i] += mix.pow(k) * coeff_u[cur_pos + i];
+        }
+        cur_pos += reg.size();
+    }
+    let num_regs = taps.regs().count();
+    for i in 0..CHECK_SIZE {
+        combo_u[combo_count][0] += mix.pow(num_regs + i) * coeff_u[num_taps + i];
+    }
+
+    let gen = Fp::root_of_unity(log2_ceil(domain));
+    fri_verify(&mut iop, size, |iop: &mut ReadIOP, idx: usize| {
+        let x = Fp4::from_fp(gen.pow(idx));
+        let rows = [
+            accum_merkle.verify(iop, idx)?,
+            code_merkle.verify(iop, idx)?,
+            data_merkle.verify(iop, idx)?,
+        ];
+        let check_row = check_merkle.verify(iop, idx)?;
+        let mut tot = vec![Fp4::ZERO; combo_count + 1];
+        for (k, reg) in taps.regs().enumerate() {
+            tot[reg.combo_id()] += mix.pow(k) * rows[reg.group() as usize][reg.offset()];
+        }
+        for (i, check) in check_row.iter().enumerate() {
+            tot[combo_count] += mix.pow(num_regs + i) * *check;
+        }
+        let mut ret = Fp4::ZERO;
+        for i in 0..combo_count {
+            let mut divisor = Fp4::ONE;
+            for back in taps.get_combo(i).slice() {
+                divisor *= x - z * back_one.pow(*back as usize);
+            }
+            ret += (tot[i] - eval_naive(&combo_u[i], x)) / divisor;
+        }
+        let divisor = x - z.pow(INV_RATE);
+        ret += (tot[combo_count] - combo_u[combo_count][0]) / divisor;
+        Ok(ret)
+    })?;
+    iop.verify_complete()
+}
@@ -22,6 +22,8 @@ fn main() {
         .file("accum_regs.cpp")
         .file("compute_cycle.cpp")
         .file("context.cpp")
+        .file("custom_cycle.cpp")
+        .file("custom_op.cpp")
         .file("data_regs.cpp")
         .file("decode_cycle.cpp")
         .file("divide_cycle.cpp")
@@ -66,6 +66,10 @@ CONSTSCALAR size_t kAccumSize = 10;
 CONSTSCALAR size_t kOutputRegs = 9;
 CONSTSCALAR size_t kMinCycles = 512;
 CONSTSCALAR size_t kZkCycles = kQueries;
+// Set in the po2 word of a seal whose final kZkCycles were padded with zeros
+// instead of noise.  Such seals also commit to a digest of this flag right
+// after the po2 word.
+CONSTSCALAR uint32_t kNonZkFlag = uint32_t(1) << 31;
 
 CONSTSCALAR size_t kRegistersGlobalOffset = 0;
 CONSTSCALAR size_t kRegistersGlobalSize = kOutputRegs * 2;
@@ -75,6 +79,14 @@ CONSTSCALAR size_t kGlobalSize = kAccumMixGlobalOffset + kAccumMixGlobalSize;
 
 CONSTSCALAR size_t kComboCount = 5;
 
+// Custom instructions are R-type instructions with the RISC-V custom-0 major opcode.  Their func3
+// selects one of up to kMaxCustomOps ops registered with the circuit, which decode to the op IDs
+// starting at kCustomOpIdBase.  These are the IDs left free after the multiply ops, whose cycle
+// custom ops share.
+CONSTSCALAR size_t kCustomOpcode = 0b00010;
+CONSTSCALAR size_t kCustomOpIdBase = 38;
+CONSTSCALAR size_t kMaxCustomOps = 2;
+
 /// The maximum number of entries that a MethodId table can have. This is based on the maximum
 /// number of cycles that the zkVM is allowed to execute.
 CONSTSCALAR size_t kMaxCodeDigestCount = log2Ceil(kMaxCycles / kMinCycles) + 1;
@@ -81,6 +81,8 @@ public:
   virtual void memWrite(ValPtr cycle, ValPtr addr, ValPtr low, ValPtr high, SourceLoc loc) = 0;
   virtual std::array<ValPtr, 2> memRead(ValPtr cycle, ValPtr addr, SourceLoc loc) = 0;
   virtual std::array<ValPtr, 5> memCheck(SourceLoc loc) = 0;
+  virtual std::vector<ValPtr>
+  customCall(const char* name, std::vector<ValPtr> args, size_t count, SourceLoc loc) = 0;
 };
 
 void setGlobalContext(Context* context);
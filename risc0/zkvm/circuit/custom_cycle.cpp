@@ -0,0 +1,43 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#include "risc0/zkvm/circuit/custom_cycle.h"
+
+#include "risc0/zkvm/circuit/custom_op.h"
+#include "risc0/zkvm/circuit/step_state.h"
+
+namespace risc0::circuit {
+
+void CustomCycle::set(StepState& state) {
+  Value cycle = state.code.cycle.get();
+  auto decode = state.getPrev(1).asDecode();
+  ValueU32 R1 = decode.rs1.get();
+  ValueU32 R2 = decode.rs2.get();
+  const CustomOps& ops = getCustomOps();
+  // As with the multiply ops, the low part of the op ID is enough to tell ops apart
+  for (size_t i = 0; i < ops.size(); i++) {
+    if (!ops[i]) {
+      continue;
+    }
+    BYZ_IF(decode.opID1.is((kCustomOpIdBase + i) & 7)) {
+      risc0Log((std::string("C%u: Custom: ") + ops[i]->name()).c_str(), {cycle});
+      BufAlloc opAlloc = alloc;
+      resultInfo.result.set(ops[i]->set(opAlloc, R1, R2));
+      risc0Log("  result = 0x%04x%04x", {resultInfo.result.high(), resultInfo.result.low()});
+    }
+  }
+}
+
+} // namespace risc0::circuit
@@ -0,0 +1,35 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#pragma once
+
+#include "risc0/zkvm/circuit/cycle.h"
+
+namespace risc0::circuit {
+
+// The cycle which runs a registered CustomOp.  It shares the MULTIPLY cycle type, using op IDs
+// which no multiply op has, so the circuit only grows when ops are registered.  Its registers
+// alias those of MultiplyCycle, starting with the same result registers, and the rest of the
+// result info is set by MultiplyCycle.
+struct CustomCycle {
+  CustomCycle(BufAlloc& alloc) : resultInfo(alloc), alloc(alloc) {}
+
+  void set(StepState& state);
+
+  ResultInfoRegs resultInfo;
+  BufAlloc alloc;
+};
+
+} // namespace risc0::circuit
@@ -0,0 +1,50 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#include "risc0/zkvm/circuit/custom_op.h"
+
+#include <stdexcept>
+
+namespace risc0::circuit {
+
+static CustomOps& customOps() {
+  static CustomOps ops;
+  return ops;
+}
+
+void registerCustomOp(size_t func3, std::shared_ptr<CustomOp> op) {
+  if (func3 >= kMaxCustomOps) {
+    throw std::runtime_error("Custom op func3 out of range");
+  }
+  if (customOps()[func3]) {
+    throw std::runtime_error("Custom op already registered");
+  }
+  customOps()[func3] = op;
+}
+
+const CustomOps& getCustomOps() {
+  return customOps();
+}
+
+bool hasCustomOps() {
+  for (const auto& op : customOps()) {
+    if (op) {
+      return true;
+    }
+  }
+  return false;
+}
+
+} // namespace risc0::circuit
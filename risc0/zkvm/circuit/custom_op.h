@@ -0,0 +1,64 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#pragma once
+
+#include "risc0/zkvm/circuit/constants.h"
+#include "risc0/zkvm/circuit/types.h"
+
+#include <array>
+#include <memory>
+
+namespace risc0::circuit {
+
+// A constrained instruction added to the circuit without changing its sources.
+//
+// Custom instructions are encoded as `.insn r 0x0b, func3, 0, rd, rs1, rs2`, where func3 picks
+// the registered op.  Like MUL, an op reads rs1 and rs2 and writes its result to rd in a single
+// cycle after decode.  The op runs under two selectors (the cycle type and the op ID), so its
+// own constraints may have degree at most kMaxDegree - 2.
+class CustomOp {
+public:
+  virtual ~CustomOp() {}
+
+  // The name of the op, used in logs.  Nondeterministic values the op asks the executor for are
+  // requested with customCall, by whatever names the op chooses.
+  virtual const char* name() const = 0;
+
+  // Compute and constrain the result of the op from the values of rs1 and rs2.  Any registers
+  // the op needs are allocated from alloc, which is shared with the other ops of the cycle.
+  virtual ValueU32 set(BufAlloc& alloc, ValueU32 rs1, ValueU32 rs2) = 0;
+};
+
+using CustomOps = std::array<std::shared_ptr<CustomOp>, kMaxCustomOps>;
+
+// Register op as the custom instruction with the given func3.  Ops must be registered before the
+// circuit is generated, usually by RISC0_REGISTER_CUSTOM_OP in a library linked into
+// make-circuit.
+void registerCustomOp(size_t func3, std::shared_ptr<CustomOp> op);
+
+// The registered ops, indexed by func3, with null entries for unused slots.
+const CustomOps& getCustomOps();
+
+// Returns true if any op is registered.  With none, the circuit is exactly the RV32IM circuit.
+bool hasCustomOps();
+
+#define RISC0_REGISTER_CUSTOM_OP(func3, type)                                                      \
+  static const bool kRegistered##type = [] {                                                       \
+    ::risc0::circuit::registerCustomOp(func3, std::make_shared<type>());                           \
+    return true;                                                                                   \
+  }();
+
+} // namespace risc0::circuit
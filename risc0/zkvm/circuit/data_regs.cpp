@@ -14,6 +14,8 @@
 
 #include "risc0/zkvm/circuit/data_regs.h"
 
+#include "risc0/zkvm/circuit/custom_cycle.h"
+#include "risc0/zkvm/circuit/custom_op.h"
 #include "risc0/zkvm/circuit/mem_check.h"
 #include "risc0/zkvm/circuit/step_state.h"
 
@@ -117,6 +119,11 @@ void DataRegs::setExec(StepState& state) {
       BufAlloc allocCopy = alloc;
       MultiplyCycle multiply(allocCopy);
       multiply.set(state);
+      if (hasCustomOps()) {
+        BufAlloc customAlloc = alloc;
+        CustomCycle custom(customAlloc);
+        custom.set(state);
+      }
     }
     BYZ_IF(cycleType.is(DataCycleType::DIVIDE)) {
       BufAlloc allocCopy = alloc;
@@ -14,6 +14,7 @@
 
 #include "risc0/zkvm/circuit/decode_cycle.h"
 
+#include "risc0/zkvm/circuit/custom_op.h"
 #include "risc0/zkvm/circuit/step_state.h"
 
 namespace risc0::circuit {
@@ -121,6 +122,14 @@ void DecodeCycle::set(StepState& state) {
     // Special case for halt, basically to avoid OPH
     ANYOP(SHA_SYNC, 63, HALT, 0b11100, 0, 0, R, 0)
 #undef ANYOP
+    for (size_t i = 0; i < kMaxCustomOps; i++) {
+      if (getCustomOps()[i]) {
+        BYZ_IF(match(kCustomOpcode, opcode) * match(i, func3) * match(0, func7)) {
+          opID1.set((kCustomOpIdBase + i) & 7);
+          opID2.set((kCustomOpIdBase + i) >> 3);
+        }
+      }
+    }
   }
 
   // Now verify we got it right + set the immediate value + next step
@@ -157,6 +166,30 @@ void DecodeCycle::set(StepState& state) {
   ANYOP(SHA_SYNC, 63, HALT, 0b11100, 0, 0, R, 0)
 #undef ANYOP
 
+  // Custom ops are R-type, and run in the multiply cycle; see CustomCycle
+  for (size_t i = 0; i < kMaxCustomOps; i++) {
+    if (!getCustomOps()[i]) {
+      continue;
+    }
+    size_t id = kCustomOpIdBase + i;
+    BYZ_IF(opID1.is(id & 7) * opID2.is(id >> 3)) {
+      equate(opcode, kCustomOpcode);
+      equate(func3, i);
+      equate(func7, 0);
+      BYZ_GROUP {
+        imm.set(immR);
+        val2Low.setPartExact(rs2.low(), 0, 16);
+        val2Split.setPartExact(val2Low.getPart(4, 2), 0, 2);
+        val2OH.set(val2Low.getPart(0, 4) + 16 * val2Split.get(0));
+      }
+      nextCycleType.set(DataCycleType::MULTIPLY);
+      risc0Log((std::string("C%u: pc: %08x Decode: ") + getCustomOps()[i]->name() +
+                " r%u=0x%04x%04x, r%u=0x%04x%04x")
+                   .c_str(),
+               {cycle, pc * 4, rs1Id, rs1.high(), rs1.low(), rs2Id, rs2.high(), rs2.low()});
+    }
+  }
+
   // Done with decode
 #undef OPC
 #undef OPM
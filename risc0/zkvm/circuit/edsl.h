@@ -192,6 +192,23 @@ inline std::array<Value, 5> memCheck(SourceLoc loc = SourceLoc::current()) {
   return {v1, v2, v3, v4, v5};
 }
 
+// Ask the executor for N values computed by the custom op `name`; see CustomOp.  Like divide, this
+// may only be used in nondet regions, and the results must be constrained.
+template <size_t N>
+inline std::array<Value, N>
+customCall(const char* name, std::vector<Value> args, SourceLoc loc = SourceLoc::current()) {
+  std::vector<std::shared_ptr<ValueImplBase>> impls;
+  for (auto& arg : args) {
+    impls.push_back(arg.getImpl());
+  }
+  auto vals = getGlobalContext()->customCall(name, impls, N, loc);
+  std::array<Value, N> out;
+  for (size_t i = 0; i < N; i++) {
+    out[i] = vals[i];
+  }
+  return out;
+}
+
 // RIAA style nondet region
 class NondetGuard {
 public:
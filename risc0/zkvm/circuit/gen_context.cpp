@@ -280,6 +280,28 @@ std::array<Context::ValPtr, 5> GenContext::memCheck(SourceLoc loc) {
   return {o1, o2, o3, o4, o5};
 }
 
+std::vector<Context::ValPtr>
+GenContext::customCall(const char* name, std::vector<ValPtr> args, size_t count, SourceLoc loc) {
+  std::vector<ValPtr> results;
+  for (size_t i = 0; i < count; i++) {
+    results.push_back(newDef());
+  }
+  begl();
+  if (count) {
+    outs << "auto [";
+    for (size_t i = 0; i < count; i++) {
+      outs << (i ? ", " : "") << results[i];
+    }
+    outs << "] = ";
+  }
+  outs << "ctx.custom<" << count << ">(\"" << name << "\", {";
+  for (size_t i = 0; i < args.size(); i++) {
+    outs << (i ? ", " : "") << args[i];
+  }
+  outs << "})" << endl(loc);
+  return results;
+}
+
 std::ostream& GenContext::begl() {
   for (size_t i = 0; i < indent; i++) {
     outs << "  ";
@@ -67,6 +67,8 @@ public:
   void memWrite(ValPtr cycle, ValPtr addr, ValPtr low, ValPtr high, SourceLoc loc) override;
   std::array<ValPtr, 2> memRead(ValPtr cycle, ValPtr addr, SourceLoc loc) override;
   std::array<ValPtr, 5> memCheck(SourceLoc loc) override;
+  std::vector<ValPtr>
+  customCall(const char* name, std::vector<ValPtr> args, size_t count, SourceLoc loc) override;
 
 private:
   size_t nextId = 0;
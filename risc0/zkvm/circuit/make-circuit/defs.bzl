@@ -1,17 +1,19 @@
+load("//bazel/rules/cc:defs.bzl", "cc_binary")
+
 def _impl(ctx):
     out_file = ctx.outputs.out
     ctx.actions.run(
         mnemonic = "MakeCircuit",
-        executable = ctx.executable._tool,
+        executable = ctx.executable.tool,
         arguments = [out_file.path],
         outputs = [out_file],
-        tools = [ctx.executable._tool],
+        tools = [ctx.executable.tool],
     )
     return [DefaultInfo(files = depset([out_file]))]
 
 make_circuit = rule(
     attrs = {
-        "_tool": attr.label(
+        "tool": attr.label(
             default = Label("//risc0/zkvm/circuit/make-circuit"),
             allow_single_file = True,
             executable = True,
@@ -21,3 +23,23 @@ make_circuit = rule(
     },
     implementation = _impl,
 )
+
+def make_circuit_with_ops(name, out, ops, **kwargs):
+    """Generate the circuit with the custom ops registered by `ops`.
+
+    Each of `ops` is a cc_library which registers its ops with
+    RISC0_REGISTER_CUSTOM_OP; it should set `alwayslink = True` so that the
+    registration is linked in.  The output replaces step.cpp.inc, including
+    the taps and check polynomials, for a prover built with these ops.
+    """
+    cc_binary(
+        name = name + "_tool",
+        srcs = [Label("//risc0/zkvm/circuit/make-circuit:make-circuit.cpp")],
+        deps = [Label("//risc0/zkvm/circuit")] + ops,
+    )
+    make_circuit(
+        name = name,
+        out = out,
+        tool = ":" + name + "_tool",
+        **kwargs
+    )
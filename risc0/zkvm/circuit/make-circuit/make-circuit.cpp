@@ -13,6 +13,7 @@
 // limitations under the License.
 
 #include "risc0/core/log.h"
+#include "risc0/zkvm/circuit/custom_op.h"
 #include "risc0/zkvm/circuit/make_circuit.h"
 
 int main(int argc, char* argv[]) {
@@ -23,6 +24,12 @@ int main(int argc, char* argv[]) {
     return 1;
   }
 
+  const auto& ops = risc0::circuit::getCustomOps();
+  for (size_t i = 0; i < ops.size(); i++) {
+    if (ops[i]) {
+      LOG(1, "Custom op " << i << ": " << ops[i]->name());
+    }
+  }
   risc0::circuit::make_circuit(argv[1]);
   return 0;
 }
@@ -711,4 +711,9 @@ std::array<Context::ValPtr, 5> PolyContext::memCheck(SourceLoc loc) {
   throw std::runtime_error("Unimplemented");
 }
 
+std::vector<Context::ValPtr>
+PolyContext::customCall(const char* name, std::vector<ValPtr> args, size_t count, SourceLoc loc) {
+  throw std::runtime_error("Unimplemented");
+}
+
 } // namespace risc0::circuit
@@ -35,6 +35,63 @@ constexpr uint32_t kSendRecvChannel_InitialInput = 0;
 constexpr uint32_t kSendRecvChannel_Stdout = 1;
 // Write bytes to standard error
 constexpr uint32_t kSendRecvChannel_Stderr = 2;
+// Request accelerated BabyBear field operations from the host.
+constexpr uint32_t kSendRecvChannel_Field = 3;
+// Look up a key in a Merkleized key-value store served by the host.
+constexpr uint32_t kSendRecvChannel_Kv = 4;
+// Request a confidential input, which the host unseals on demand.  The
+// guest sends the input's name; the host replies with a status word,
+// followed by the plaintext if the status is zero.
+constexpr uint32_t kSendRecvChannel_Secret = 5;
+// Send a log record from the guest_log! macros.  The record is a level
+// byte, the length of the module path as a little-endian u16, the module
+// path and then the message.  The host replies with nothing.
+constexpr uint32_t kSendRecvChannel_Log = 6;
+
+// Levels of the records sent on kSendRecvChannel_Log.
+constexpr uint8_t kLogLevelError = 1;
+constexpr uint8_t kLogLevelWarn = 2;
+constexpr uint8_t kLogLevelInfo = 3;
+constexpr uint8_t kLogLevelDebug = 4;
+constexpr uint8_t kLogLevelTrace = 5;
+
+// Status words which start each response on kSendRecvChannel_Secret.
+constexpr uint32_t kSecretOk = 0;
+constexpr uint32_t kSecretNotFound = 1;
+constexpr uint32_t kSecretUnsealFailed = 2;
+
+// The journal of a guest which failed an assertion holds exactly two words:
+// kJournalAssertFailed followed by the guest's failure code.
+constexpr uint32_t kJournalAssertFailed = 0xA55E27F0;
+
+// Responses that do not fit in the remaining INPUT region are split.
+// The host writes a single length word with kSendRecvSplit set and the
+// total length in the remaining bits, and holds the response back.  The
+// guest then repeatedly sends two words on kSendRecvChannel_Continue:
+// the address of a fresh header buffer and the address at which the next
+// chunk of data should land, both in never-written heap memory.  The
+// host writes up to kSendRecvChunkSize bytes of data and stores their
+// length in the first header word.  On the final chunk the length word
+// has kSendRecvChunkLast set and is followed by the SHA-256 digest of the
+// whole response, which the guest checks after reassembly.  Continuation
+// requests never produce a reply in the INPUT region.
+constexpr uint32_t kSendRecvChannel_Continue = 0xFFFFFFFF;
+constexpr uint32_t kSendRecvSplit = 0x80000000;
+constexpr uint32_t kSendRecvChunkLast = 0x80000000;
+constexpr uint32_t kSendRecvChunkSize = 64 * 1024;
+
+// Operations available on kSendRecvChannel_Field.  The guest sends the
+// operation word followed by its operands as field elements, one per
+// word; the host replies with the results, one per word.  Results are
+// not trusted by the guest, so each operation must be cheap to check.
+
+// Compute the multiplicative inverse of each operand (zero maps to zero).
+constexpr uint32_t kFieldOp_Inv = 0;
+// Evaluate the polynomial whose coefficients are the operands over the
+// roots of unity of the same (power of two) size, in natural order.
+constexpr uint32_t kFieldOp_NttFwd = 1;
+// The inverse of kFieldOp_NttFwd.
+constexpr uint32_t kFieldOp_NttInv = 2;
 
 // To invoke accelerated SHA, the guest writes ShaDescriptor structs
 // in sequence to the "SHA" memory region.  Once the ShaDescriptor has
@@ -29,6 +29,10 @@ constexpr size_t kMemSize = (1 << kMemBits) * 4;
 
 constexpr size_t k1MB = 0x00100000;
 
+// Granularity at which the host reports guest memory usage.  Must match
+// PAGE_SIZE in zkvm/sdk/rust/platform/src/memory.rs.
+constexpr size_t kPageSize = 1024;
+
 // Must match risc0.ld and zkvm/sdk/rust/platform/src/memory.rs.
 //
 // The circuit treats sections where the high bit (1 << (kMemBits -
@@ -16,3 +16,8 @@
 mod ffi {}
 
 pub const LINKER_SCRIPT: &[u8] = include_bytes!("../risc0.ld");
+
+// The pieces of a TinyGo target for guests.
+pub const TINYGO_LINKER_SCRIPT: &[u8] = include_bytes!("../tinygo/risc0.ld");
+pub const TINYGO_START: &[u8] = include_bytes!("../tinygo/start.S");
+pub const TINYGO_TARGET: &[u8] = include_bytes!("../tinygo/target.json");
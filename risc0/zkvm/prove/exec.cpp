@@ -18,6 +18,9 @@
 #include "risc0/core/log.h"
 #include "risc0/core/util.h"
 #include "risc0/zkvm/circuit/constants.h"
+#include "risc0/zkvm/platform/memory.h"
+
+#include <algorithm>
 
 namespace risc0 {
 
@@ -36,7 +39,7 @@ void ExecState::init(size_t maxSteps, MemoryHandler& io) {
   context.io = &io;
   context.curStep = 0;
   context.mem.data[0] = 0;
-  context.numSteps = nearestPo2(image.size() + 3 + kZkCycles);
+  context.numSteps = std::max(nearestPo2(image.size() + 3 + kZkCycles), minSteps);
   if (context.numSteps > maxSteps) {
     throw std::runtime_error("Elf too large to fix in maxSteps");
   }
@@ -62,6 +65,8 @@ void ExecState::init(size_t maxSteps, MemoryHandler& io) {
   }
   // Call onLoaded
   io.onLoaded(context.mem);
+  // Only accesses made by the guest itself count towards touched pages
+  context.mem.trackPages = true;
   // Step over what should be the 'reset' cycle
   // At this point, we should be ready to do normal
   // code execution
@@ -108,6 +113,7 @@ void ExecState::fini() {
   }
   // Do fini cycle
   dataStepExec(context, code.data(), data.data());
+  context.io->onFini(context.mem);
 }
 
 uint32_t ExecState::getPC() {
@@ -138,6 +144,33 @@ std::vector<uint32_t> ExecState::getRegisters() {
   return out;
 }
 
+std::vector<uint32_t> ExecState::backtrace(size_t maxFrames) {
+  std::vector<uint32_t> frames;
+  try {
+    frames.push_back(getPC());
+    uint32_t fp = getRegisters()[8];
+    while (frames.size() < maxFrames) {
+      if (fp % 4 != 0 || fp < 8 || fp > kMemStackEnd) {
+        break;
+      }
+      uint32_t ra = context.mem.load(fp - 4);
+      uint32_t next = context.mem.load(fp - 8);
+      if (ra < kMemProgStart || ra >= kMemProgEnd) {
+        break;
+      }
+      frames.push_back(ra);
+      if (next <= fp) {
+        break;
+      }
+      fp = next;
+    }
+  } catch (const std::exception& err) {
+    // Stop at the first unmapped frame or malformed cycle.
+    LOG(1, "ExecState::backtrace> " << err.what());
+  }
+  return frames;
+}
+
 void ExecState::expand() {
   if (context.numSteps == maxSteps) {
     throw std::runtime_error("Expand failed: at max steps");
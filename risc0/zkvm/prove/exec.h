@@ -38,6 +38,11 @@ struct ExecState {
   // Get the state of all 32 registers
   std::vector<uint32_t> getRegisters();
 
+  // Walk the guest's frame pointers from the last executed cycle: the pc
+  // followed by each caller's return address, innermost first.  Must match
+  // walk in zkvm/sdk/rust/src/host/backtrace.rs.
+  std::vector<uint32_t> backtrace(size_t maxFrames = 64);
+
   void expand();
 
   uint32_t startAddr;
@@ -45,6 +50,9 @@ struct ExecState {
   StepContext context;
   std::vector<Fp> code;
   std::vector<Fp> data;
+  // The minimum number of steps to allocate for the trace.  The trace starts
+  // at the smallest power of two which fits the image and at least this size.
+  size_t minSteps = 0;
   size_t maxSteps;
   bool done;
 };
@@ -12,17 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#include <algorithm>
+#include <cstring>
 #include <sstream>
 
 #include "risc0/core/align.h"
 #include "risc0/core/log.h"
 #include "risc0/zkp/core/sha256.h"
+#include "risc0/zkp/core/sha256_cpu.h"
 #include "risc0/zkvm/platform/io.h"
 #include "risc0/zkvm/platform/memory.h"
 #include "risc0/zkvm/prove/step.h"
 
 namespace risc0 {
 
+// Overwrite a response which may hold a secret input.
+static void wipe(std::vector<uint8_t>& buf) {
+  volatile uint8_t* ptr = buf.data();
+  for (size_t i = 0; i < buf.size(); ++i) {
+    ptr[i] = 0;
+  }
+}
+
 static void processSHA(MemoryState& mem, const ShaDescriptor& desc) {
   uint16_t type = (desc.typeAndCount & 0xFFFF) >> 4;
   uint16_t count = desc.typeAndCount & 0xFFFF;
@@ -50,6 +61,11 @@ void IoHandler::onFault(const std::string& msg) {
   throw std::runtime_error(msg);
 }
 
+std::vector<uint32_t> IoHandler::onCustom(const std::string& name,
+                                          const std::vector<uint32_t>& args) {
+  throw std::runtime_error("No handler for custom op " + name);
+}
+
 MemoryHandler::MemoryHandler() : MemoryHandler(nullptr) {}
 
 MemoryHandler::MemoryHandler(IoHandler* io) : io(io), cur_host_to_guest_offset(kMemInputStart) {}
@@ -60,6 +76,26 @@ void MemoryHandler::onInit(MemoryState& mem) {
   }
 }
 
+void MemoryHandler::onFini(const MemoryState& mem) {
+  if (io) {
+    io->onFini(mem);
+  }
+}
+
+void MemoryHandler::onBacktrace(const std::vector<uint32_t>& frames) {
+  if (io) {
+    io->onBacktrace(frames);
+  }
+}
+
+std::vector<uint32_t> MemoryHandler::onCustom(const std::string& name,
+                                              const std::vector<uint32_t>& args) {
+  if (!io) {
+    throw std::runtime_error("No handler for custom op " + name);
+  }
+  return io->onCustom(name, args);
+}
+
 void MemoryHandler::onWrite(MemoryState& mem, uint32_t cycle, uint32_t addr, uint32_t value) {
   LOG(2, "MemoryHandler::onWrite> " << hex(addr) << ": " << hex(value));
   switch (addr) {
@@ -129,20 +165,42 @@ void MemoryHandler::onWrite(MemoryState& mem, uint32_t cycle, uint32_t addr, uin
           "MemoryHandler::onWrite> GPIO_SendReceive, channel " << channel
                                                                << " size=" << buf.size());
       mem.loadRegion(value, buf.data(), buf.size());
+      if (channel == kSendRecvChannel_Continue) {
+        sendChunk(mem, buf);
+        break;
+      }
       BufferU8 result = io->onSendRecv(channel, buf);
       LOG(1,
           "MemoryHandler::onWrite> GPIO_SendReceive, host replied with " << result.size()
                                                                          << " bytes");
       size_t aligned_len = align(result.size());
-      if ((cur_host_to_guest_offset + sizeof(uint32_t) + aligned_len) >= kMemInputEnd) {
+      if ((cur_host_to_guest_offset + sizeof(uint32_t)) >= kMemInputEnd) {
         throw(std::runtime_error("Read buffer overrun"));
       }
+      if ((cur_host_to_guest_offset + sizeof(uint32_t) + aligned_len) >= kMemInputEnd) {
+        if (!split.empty()) {
+          throw(std::runtime_error("Split response was not fully read"));
+        }
+        if (result.size() >= kSendRecvSplit) {
+          throw(std::runtime_error("Response too large"));
+        }
+        LOG(1, "MemoryHandler::onWrite> splitting " << result.size() << " byte response");
+        mem.store(cur_host_to_guest_offset, kSendRecvSplit | result.size());
+        cur_host_to_guest_offset += sizeof(uint32_t);
+        splitDigest = shaHash(result.data(), result.size());
+        split = std::move(result);
+        splitOffset = 0;
+        break;
+      }
       mem.store(cur_host_to_guest_offset, result.size());
       cur_host_to_guest_offset += sizeof(uint32_t);
       for (size_t i = 0; i < result.size(); ++i) {
         mem.storeByte(cur_host_to_guest_offset + i, result[i]);
       }
       cur_host_to_guest_offset += aligned_len;
+      if (channel == kSendRecvChannel_Secret) {
+        wipe(result);
+      }
     } else {
       throw std::runtime_error("SendRecv called with no IO handler set");
     }
@@ -150,6 +208,43 @@ void MemoryHandler::onWrite(MemoryState& mem, uint32_t cycle, uint32_t addr, uin
   }
 }
 
+void MemoryHandler::sendChunk(MemoryState& mem, const std::vector<uint8_t>& request) {
+  if (split.empty()) {
+    throw std::runtime_error("Continuation requested with no split response pending");
+  }
+  if (request.size() != 2 * sizeof(uint32_t)) {
+    throw std::runtime_error("Bad continuation request");
+  }
+  uint32_t header;
+  uint32_t dest;
+  std::memcpy(&header, request.data(), sizeof(uint32_t));
+  std::memcpy(&dest, request.data() + sizeof(uint32_t), sizeof(uint32_t));
+  size_t len = std::min<size_t>(kSendRecvChunkSize, split.size() - splitOffset);
+  bool last = splitOffset + len == split.size();
+  size_t headerLen = sizeof(uint32_t) * (last ? 1 + sizeof(ShaDigest) / sizeof(uint32_t) : 1);
+  auto checkHeap = [](uint32_t addr, size_t size) {
+    if (addr % sizeof(uint32_t) || addr < kMemHeapStart || addr + size > kMemHeapEnd) {
+      throw std::runtime_error("Bad continuation address: " + hex(addr));
+    }
+  };
+  checkHeap(header, headerLen);
+  checkHeap(dest, len);
+  for (size_t i = 0; i < len; ++i) {
+    mem.storeByte(dest + i, split[splitOffset + i]);
+  }
+  splitOffset += len;
+  if (last) {
+    mem.store(header, kSendRecvChunkLast | len);
+    for (size_t i = 0; i < 8; ++i) {
+      mem.store(header + (1 + i) * sizeof(uint32_t), splitDigest.words[i]);
+    }
+    wipe(split);
+    split.clear();
+  } else {
+    mem.store(header, len);
+  }
+}
+
 void MemoryState::dump(size_t logLevel) {
   LOG(logLevel, "MemoryState::dump> size: " << data.size());
   if (getLogLevel() >= logLevel) {
@@ -159,6 +254,34 @@ void MemoryState::dump(size_t logLevel) {
   }
 }
 
+void MemoryState::touch(uint32_t addr, bool isWrite) {
+  if (!trackPages) {
+    return;
+  }
+  auto& counts = pageAccesses[addr * 4 / kPageSize];
+  if (isWrite) {
+    counts.second++;
+  } else {
+    counts.first++;
+  }
+}
+
+std::vector<PageInfo> MemoryState::touchedPages() const {
+  constexpr size_t kPageWords = kPageSize / sizeof(uint32_t);
+  std::vector<PageInfo> pages;
+  for (const auto& [page, counts] : pageAccesses) {
+    uint32_t words[kPageWords] = {};
+    uint32_t base = page * kPageWords;
+    for (auto it = data.lower_bound(base); it != data.end() && it->first < base + kPageWords;
+         ++it) {
+      words[it->first - base] = it->second;
+    }
+    ShaDigest digest = shaHash(reinterpret_cast<const uint8_t*>(words), kPageSize);
+    pages.push_back({uint32_t(page * kPageSize), counts.first, counts.second, digest});
+  }
+  return pages;
+}
+
 size_t MemoryState::strlen(uint32_t addr) {
   size_t len = 0;
   while (loadByte(addr++)) {
@@ -28,7 +28,11 @@ namespace {
 
 class RiscVProveCircuit : public ProveCircuit {
 public:
-  RiscVProveCircuit(const std::vector<uint8_t>& elfBytes, MemoryHandler& io);
+  RiscVProveCircuit(const std::vector<uint8_t>& elfBytes,
+                    MemoryHandler& io,
+                    uint32_t minPo2,
+                    uint32_t maxPo2,
+                    bool zk);
   TapSetRef getTaps() const override { return getRiscVTaps(); }
   void execute(WriteIOP& iop) override;
   void accumulate(WriteIOP& iop) override;
@@ -50,17 +54,34 @@ private:
   ExecState exec_;
   std::vector<Fp> accum_;
   MemoryHandler& io_;
+  uint32_t minPo2_;
+  uint32_t maxPo2_;
+  bool zk_;
   uint32_t po2_;
 };
 
 } // namespace
 
-RiscVProveCircuit::RiscVProveCircuit(const std::vector<uint8_t>& elfBytes, MemoryHandler& io)
-    : exec_(elfBytes), io_(io) {}
+RiscVProveCircuit::RiscVProveCircuit(const std::vector<uint8_t>& elfBytes,
+                                     MemoryHandler& io,
+                                     uint32_t minPo2,
+                                     uint32_t maxPo2,
+                                     bool zk)
+    : exec_(elfBytes), io_(io), minPo2_(minPo2), maxPo2_(maxPo2), zk_(zk) {
+  if (minPo2 > maxPo2 || maxPo2 > kMaxCyclesPo2) {
+    throw std::runtime_error("Invalid po2 range");
+  }
+}
 
 void RiscVProveCircuit::execute(WriteIOP& iop) {
   // Run actual RISC-V execution
-  exec_.run(kMaxCycles, io_);
+  exec_.minSteps = size_t(1) << minPo2_;
+  try {
+    exec_.run(size_t(1) << maxPo2_, io_);
+  } catch (...) {
+    io_.onBacktrace(exec_.backtrace());
+    throw;
+  }
 
   // Get # of timesteps
   po2_ = log2Ceil(exec_.context.numSteps);
@@ -75,8 +96,16 @@ void RiscVProveCircuit::execute(WriteIOP& iop) {
     iop.write(&regVal, 1);
   }
 
-  // Write the po2 for size
-  iop.write(&po2_, 1);
+  // Write the po2 for size, flagging and committing to a non-ZK trace
+  uint32_t po2Word = zk_ ? po2_ : (po2_ | kNonZkFlag);
+  iop.write(&po2Word, 1);
+  if (!zk_) {
+    ShaDigest flag;
+    for (size_t i = 0; i < 8; i++) {
+      flag.words[i] = kNonZkFlag;
+    }
+    iop.commit(flag);
+  }
 
   // Now, do memory verification
   for (size_t i = 0; i < size - kZkCycles; i++) {
@@ -97,7 +126,7 @@ void RiscVProveCircuit::execute(WriteIOP& iop) {
   // Fill final cycles of data with noise to make ZK
   for (size_t i = 0; i < kDataSize; i++) {
     for (size_t j = size - kZkCycles; j < size; j++) {
-      exec_.data[i * size + j] = Fp::random(CryptoRng::shared());
+      exec_.data[i * size + j] = zk_ ? Fp::random(CryptoRng::shared()) : Fp(0);
     }
   }
 #endif
@@ -136,7 +165,7 @@ void RiscVProveCircuit::accumulate(WriteIOP& iop) {
   // Fill final cycles of accum with noise to make ZK
   for (size_t i = 0; i < kAccumSize; i++) {
     for (size_t j = size - kZkCycles; j < size; j++) {
-      accum_[i * size + j] = Fp::random(CryptoRng::shared());
+      accum_[i * size + j] = zk_ ? Fp::random(CryptoRng::shared()) : Fp(0);
     }
   }
 #endif
@@ -208,8 +237,11 @@ void RiscVProveCircuit::evalCheck( //
 using oneapi::tbb::parallel_for;
 
 std::unique_ptr<ProveCircuit> getRiscVProveCircuit(const std::vector<uint8_t>& elfBytes,
-                                                   MemoryHandler& io) {
-  return std::make_unique<RiscVProveCircuit>(elfBytes, io);
+                                                   MemoryHandler& io,
+                                                   uint32_t minPo2,
+                                                   uint32_t maxPo2,
+                                                   bool zk) {
+  return std::make_unique<RiscVProveCircuit>(elfBytes, io, minPo2, maxPo2, zk);
 }
 
 } // namespace risc0
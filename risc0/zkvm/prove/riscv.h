@@ -16,12 +16,19 @@
 
 #include <memory>
 
+#include "risc0/zkp/core/constants.h"
 #include "risc0/zkp/prove/prove.h"
 #include "risc0/zkvm/prove/exec.h"
 
 namespace risc0 {
 
+// The trace size starts at the smallest power of two which fits the image (but
+// at least 2^minPo2) and doubles as needed, up to 2^maxPo2.  Without zk, the
+// padding cycles are zero rather than random, and the seal is flagged as such.
 std::unique_ptr<ProveCircuit> getRiscVProveCircuit(const std::vector<uint8_t>& elfBytes,
-                                                   MemoryHandler& io);
+                                                   MemoryHandler& io,
+                                                   uint32_t minPo2 = 0,
+                                                   uint32_t maxPo2 = kMaxCyclesPo2,
+                                                   bool zk = true);
 
 } // namespace risc0
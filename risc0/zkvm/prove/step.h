@@ -16,6 +16,7 @@
 
 #include "risc0/core/key.h"
 #include "risc0/zkp/core/fp.h"
+#include "risc0/zkp/core/sha256.h"
 #include "risc0/zkvm/circuit/constants.h"
 #include "risc0/zkvm/platform/io.h"
 
@@ -41,10 +42,26 @@ struct MemoryEvent {
   }
 };
 
+// How a guest used one page of memory while executing.
+struct PageInfo {
+  uint32_t addr;
+  uint32_t reads;
+  uint32_t writes;
+  ShaDigest digest;
+};
+
 struct MemoryState {
   std::map<uint32_t, uint32_t> data;
   std::set<MemoryEvent> history;
 
+  // Read and write counts by page index, recorded once trackPages is set.
+  std::map<uint32_t, std::pair<uint32_t, uint32_t>> pageAccesses;
+  bool trackPages = false;
+
+  void touch(uint32_t addr, bool isWrite);
+  // The pages accessed since tracking started, with digests of their contents.
+  std::vector<PageInfo> touchedPages() const;
+
   void dump(size_t logLevel);
 
   uint8_t loadByte(uint32_t addr);
@@ -64,6 +81,11 @@ struct IoHandler {
   virtual BufferU8 onSendRecv(uint32_t channelId, const BufferU8& data) { return BufferU8(); }
   virtual void onCommit(const BufferU8& data) {}
   virtual void onFault(const std::string& msg);
+  virtual void onFini(const MemoryState& mem) {}
+  virtual void onBacktrace(const std::vector<uint32_t>& frames) {}
+  // Compute the nondeterministic values a custom op of the circuit asks for by `name`.
+  virtual std::vector<uint32_t> onCustom(const std::string& name,
+                                         const std::vector<uint32_t>& args);
   virtual KeyStore& getKeyStore() = 0;
 };
 
@@ -87,6 +109,17 @@ public:
   // new data.
   virtual void onWrite(MemoryState& mem, uint32_t cycle, uint32_t addr, uint32_t value);
 
+  // Called once execution is complete, before the trace is proven.
+  virtual void onFini(const MemoryState& mem);
+
+  // Called when execution fails, with the guest's frame-pointer backtrace.
+  virtual void onBacktrace(const std::vector<uint32_t>& frames);
+
+  // Called when a custom op of the circuit asks for nondeterministic values; see
+  // risc0/zkvm/circuit/custom_op.h.
+  virtual std::vector<uint32_t> onCustom(const std::string& name,
+                                         const std::vector<uint32_t>& args);
+
   // Called after the system is halted, gets final memory state & final output.
   virtual void onHalt(const MemoryState& mem, const std::array<uint32_t, 8>& output) {}
 
@@ -96,6 +129,14 @@ private:
   // Memory address of current host->guest transmission.  The host can only
   // write to each memory location once, so this advances after each write.
   uint32_t cur_host_to_guest_offset;
+
+  // A response too large for the INPUT region, held back until the guest
+  // fetches it chunk by chunk over kSendRecvChannel_Continue.
+  std::vector<uint8_t> split;
+  size_t splitOffset = 0;
+  ShaDigest splitDigest;
+
+  void sendChunk(MemoryState& mem, const std::vector<uint8_t>& request);
 };
 
 struct StepContext {
@@ -115,6 +156,21 @@ struct StepContext {
   std::array<Fp, 2> memRead(Fp cycle, Fp addr);
   std::array<Fp, 5> memCheck(); // Cycle, Addr, IsWrite, Low, High
   std::array<Fp, 4> divide(Fp numerLow, Fp numerHigh, Fp denomLow, Fp denomHigh);
+  template <size_t N> std::array<Fp, N> custom(const char* name, std::vector<Fp> args) {
+    std::vector<uint32_t> words;
+    for (Fp arg : args) {
+      words.push_back(arg.asUInt32());
+    }
+    std::vector<uint32_t> results = io->onCustom(name, words);
+    if (results.size() != N) {
+      throw std::runtime_error(std::string("Wrong number of results from custom op ") + name);
+    }
+    std::array<Fp, N> out;
+    for (size_t i = 0; i < N; i++) {
+      out[i] = results[i];
+    }
+    return out;
+  }
   void requireDigits(Fp* buf, size_t bits, size_t offset, size_t size);
   void requireMux(Fp* buf, size_t offset, size_t size, const char* msg);
   void requireZero(Fp val, const char* msg);
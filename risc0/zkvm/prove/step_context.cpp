@@ -103,6 +103,7 @@ void StepContext::memWrite(Fp cycle, Fp addrp, Fp low, Fp high) {
   bool doWrite = addr < (1 << (kMemBits - 1));
   MemoryEvent evt = {addr, cycle.asUInt32(), doWrite, data};
   mem.history.emplace(evt);
+  mem.touch(addr, true);
   auto it = mem.data.find(addr);
   if (it == mem.data.end()) {
     mem.data.insert({addr, data});
@@ -122,6 +123,7 @@ std::array<Fp, 2> StepContext::memRead(Fp cycle, Fp addr) {
   uint32_t data = mem.data[addr.asUInt32()];
   MemoryEvent evt = {addr.asUInt32(), cycle.asUInt32(), false, data};
   mem.history.emplace(evt);
+  mem.touch(addr.asUInt32(), false);
   return {data & 0xffff, data >> 16};
 }
 
@@ -108,7 +108,7 @@ fn main() {
             }
             let computed = MethodId::compute_with_limit(&elf_contents, args.limit).unwrap();
             if let Some(method_id_file) = args.method_id {
-                std::fs::write(&method_id_file, computed.as_slice().unwrap()).unwrap();
+                std::fs::write(&method_id_file, computed.to_bytes()).unwrap();
                 if args.verbose > 0 {
                     eprintln!("Saved method id to {}", method_id_file);
                 }
@@ -120,8 +120,7 @@ fn main() {
     let opts: ProverOpts =
         ProverOpts::default().with_skip_seal(args.skip_seal || args.receipt.is_none());
 
-    let mut prover =
-        Prover::new_with_opts(&elf_contents, method_id.as_slice().unwrap(), opts).unwrap();
+    let mut prover = Prover::new_with_opts(&elf_contents, &method_id.to_bytes(), opts).unwrap();
     if let Some(input) = args.initial_input {
         let input_bytes = fs::read(input).unwrap();
         if args.verbose > 0 {
@@ -140,7 +139,7 @@ fn main() {
     } else {
         if args.verbose > 0 {
             eprintln!("Verifying that we executed correctly.");
-            receipt.verify(method_id.as_slice().unwrap()).unwrap();
+            receipt.verify(&method_id.to_bytes()[..]).unwrap();
         }
     }
     if let Some(receipt_file) = args.receipt {
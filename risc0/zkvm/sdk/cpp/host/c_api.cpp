@@ -14,7 +14,12 @@
 
 #include "risc0/zkvm/sdk/cpp/host/c_api.h"
 
+#include <chrono>
+#include <cstring>
 #include <memory>
+#include <string>
+
+#include "oneapi/tbb/task_arena.h"
 
 #include "risc0/core/archive.h"
 #include "risc0/core/log.h"
@@ -61,6 +66,8 @@ struct risc0_method_id {
 
 struct risc0_prover {
   std::unique_ptr<risc0::Prover> prover;
+  // Zero lets TBB pick the number of threads.
+  uint32_t maxThreads = 0;
 };
 
 struct risc0_receipt {
@@ -139,8 +146,21 @@ size_t risc0_prover_get_output_len(risc0_error* err, const risc0_prover* ptr) {
 
 risc0_receipt* risc0_prover_run(risc0_error* err, risc0_prover* ptr) {
   return ffi_wrap<risc0_receipt*>(err, nullptr, [&] {
-    risc0::Receipt receipt = ptr->prover->run();
-    return new risc0_receipt{receipt};
+    if (!ptr->maxThreads) {
+      return new risc0_receipt{ptr->prover->run()};
+    }
+    oneapi::tbb::task_arena arena(ptr->maxThreads);
+    return arena.execute([&] { return new risc0_receipt{ptr->prover->run()}; });
+  });
+}
+
+void risc0_prover_set_max_threads(risc0_error* err, risc0_prover* ptr, uint32_t max_threads) {
+  return ffi_wrap_void(err, [&] { ptr->maxThreads = max_threads; });
+}
+
+void risc0_prover_set_deadline(risc0_error* err, risc0_prover* ptr, uint64_t millis) {
+  return ffi_wrap_void(err, [&] {
+    ptr->prover->setDeadline(std::chrono::steady_clock::now() + std::chrono::milliseconds(millis));
   });
 }
 
@@ -148,6 +168,44 @@ void risc0_prover_set_skip_seal(risc0_error* err, risc0_prover* ptr, bool skip_s
   return ffi_wrap_void(err, [&] { ptr->prover->setSkipSeal(skip_seal); });
 }
 
+void risc0_prover_set_zk(risc0_error* err, risc0_prover* ptr, bool zk) {
+  return ffi_wrap_void(err, [&] { ptr->prover->setZk(zk); });
+}
+
+void risc0_prover_set_po2_range(risc0_error* err,
+                                risc0_prover* ptr,
+                                uint32_t min_po2,
+                                uint32_t max_po2) {
+  return ffi_wrap_void(err, [&] { ptr->prover->setPo2Range(min_po2, max_po2); });
+}
+
+uint32_t risc0_prover_get_po2(risc0_error* err, const risc0_prover* ptr) {
+  return ffi_wrap<uint32_t>(err, 0, [&] { return ptr->prover->getPo2(); });
+}
+
+static_assert(sizeof(risc0_page_info) == sizeof(risc0::PageInfo), "PageInfo layout mismatch");
+
+const risc0_page_info* risc0_prover_get_touched_pages_buf(risc0_error* err,
+                                                         const risc0_prover* ptr) {
+  return ffi_wrap<const risc0_page_info*>(err, nullptr, [&] {
+    return reinterpret_cast<const risc0_page_info*>(ptr->prover->getTouchedPages().data());
+  });
+}
+
+size_t risc0_prover_get_touched_pages_len(risc0_error* err, const risc0_prover* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->prover->getTouchedPages().size(); });
+}
+
+const uint32_t* risc0_prover_get_backtrace_buf(risc0_error* err, const risc0_prover* ptr) {
+  return ffi_wrap<const uint32_t*>(err, nullptr, [&] {
+    return ptr->prover->getBacktrace().data();
+  });
+}
+
+size_t risc0_prover_get_backtrace_len(risc0_error* err, const risc0_prover* ptr) {
+  return ffi_wrap(err, 0, [&] { return ptr->prover->getBacktrace().size(); });
+}
+
 void risc0_prover_set_sendrecv_handler(
     risc0_error* err,
     risc0_prover* ptr,
@@ -159,6 +217,10 @@ void risc0_prover_set_sendrecv_handler(
         channel_id,
         [=](uint32_t channel_id, const std::vector<uint8_t>& buf) -> std::vector<uint8_t> {
           risc0_u8buffer* to_guest_buf = callback(channel_id, buf.data(), buf.size(), cbdata);
+          if (!to_guest_buf) {
+            throw std::runtime_error("SendRecv handler failed on channel " +
+                                     std::to_string(channel_id));
+          }
           risc0_u8buffer to_guest_vec = std::move(*to_guest_buf);
           delete to_guest_buf;
 
@@ -167,6 +229,19 @@ void risc0_prover_set_sendrecv_handler(
   });
 }
 
+void risc0_prover_set_stdout_handler(risc0_error* err,
+                                     risc0_prover* ptr,
+                                     bool (*callback)(const uint8_t* buf, size_t len, void* cbdata),
+                                     void* cbdata) {
+  return ffi_wrap_void(err, [&] {
+    ptr->prover->setStdoutHandler([=](const std::vector<uint8_t>& buf) {
+      if (!callback(buf.data(), buf.size(), cbdata)) {
+        throw std::runtime_error("Failed to write guest stdout");
+      }
+    });
+  });
+}
+
 risc0_receipt* risc0_receipt_new(risc0_error* err,
                                  const uint8_t* journal,
                                  const size_t journal_len,
@@ -187,6 +262,18 @@ void risc0_receipt_verify(risc0_error* err,
                 [&] { ptr->receipt.verify(risc0::loadMethodId(method_id_buf, method_id_len)); });
 }
 
+void risc0_receipt_verify_claim(risc0_error* err,
+                                const risc0_receipt* ptr,
+                                const uint8_t* method_id_buf,
+                                const size_t method_id_len,
+                                const uint32_t* journal_digest) {
+  ffi_wrap_void(err, [&] {
+    risc0::ShaDigest digest;
+    memcpy(&digest, journal_digest, sizeof(risc0::ShaDigest));
+    ptr->receipt.verifyClaim(risc0::loadMethodId(method_id_buf, method_id_len), digest);
+  });
+}
+
 const uint32_t* risc0_receipt_get_seal_buf(risc0_error* err, const risc0_receipt* ptr) {
   return ffi_wrap<const uint32_t*>(err, nullptr, [&] { return ptr->receipt.seal.data(); });
 }
@@ -44,6 +44,14 @@ typedef struct {
   risc0_string* msg;
 } risc0_error;
 
+// Must match risc0::PageInfo.
+typedef struct {
+  uint32_t addr;
+  uint32_t reads;
+  uint32_t writes;
+  uint32_t digest[8];
+} risc0_page_info;
+
 //
 // String
 //
@@ -99,6 +107,28 @@ risc0_receipt* risc0_prover_run(risc0_error* err, risc0_prover* ptr);
 
 void risc0_prover_set_skip_seal(risc0_error* err, risc0_prover* ptr, bool skip_seal);
 
+void risc0_prover_set_zk(risc0_error* err, risc0_prover* ptr, bool zk);
+
+void risc0_prover_set_max_threads(risc0_error* err, risc0_prover* ptr, uint32_t max_threads);
+
+void risc0_prover_set_deadline(risc0_error* err, risc0_prover* ptr, uint64_t millis);
+
+void risc0_prover_set_po2_range(risc0_error* err,
+                                risc0_prover* ptr,
+                                uint32_t min_po2,
+                                uint32_t max_po2);
+
+uint32_t risc0_prover_get_po2(risc0_error* err, const risc0_prover* ptr);
+
+const risc0_page_info* risc0_prover_get_touched_pages_buf(risc0_error* err,
+                                                         const risc0_prover* ptr);
+
+size_t risc0_prover_get_touched_pages_len(risc0_error* err, const risc0_prover* ptr);
+
+const uint32_t* risc0_prover_get_backtrace_buf(risc0_error* err, const risc0_prover* ptr);
+
+size_t risc0_prover_get_backtrace_len(risc0_error* err, const risc0_prover* ptr);
+
 void risc0_prover_set_sendrecv_handler(
     risc0_error* err,
     risc0_prover* ptr,
@@ -106,6 +136,11 @@ void risc0_prover_set_sendrecv_handler(
     risc0_u8buffer* (*callback)(uint32_t channel_id, const uint8_t* buf, size_t len, void* cbdata),
     void* cbdata);
 
+void risc0_prover_set_stdout_handler(risc0_error* err,
+                                     risc0_prover* ptr,
+                                     bool (*callback)(const uint8_t* buf, size_t len, void* cbdata),
+                                     void* cbdata);
+
 //
 // Receipt
 //
@@ -121,6 +156,12 @@ void risc0_receipt_verify(risc0_error* err,
                           const uint8_t* method_id_buf,
                           const size_t method_id_len);
 
+void risc0_receipt_verify_claim(risc0_error* err,
+                                const risc0_receipt* ptr,
+                                const uint8_t* method_id_buf,
+                                const size_t method_id_len,
+                                const uint32_t* journal_digest);
+
 const uint32_t* risc0_receipt_get_seal_buf(risc0_error* err, const risc0_receipt* ptr);
 
 size_t risc0_receipt_get_seal_len(risc0_error* err, const risc0_receipt* ptr);
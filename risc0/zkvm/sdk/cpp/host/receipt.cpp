@@ -29,7 +29,7 @@
 
 namespace risc0 {
 
-void Receipt::verify(const MethodId& methodId) const {
+void Receipt::verifySeal(const MethodId& methodId) const {
   if (methodId.empty()) {
     throw std::runtime_error("Missing method ID");
   }
@@ -38,6 +38,21 @@ void Receipt::verify(const MethodId& methodId) const {
   }
   std::unique_ptr<VerifyCircuit> circuit = getRiscVVerifyCircuit(methodId);
   risc0::verify(*circuit, seal.data(), seal.size());
+}
+
+void Receipt::verifyClaim(const MethodId& methodId, const ShaDigest& journalDigest) const {
+  verifySeal(methodId);
+  // Journals of up to 32 bytes are committed directly rather than hashed.
+  if (seal[8] <= 32) {
+    throw std::runtime_error("Receipt::verifyClaim> journal is too short to be claimed by digest");
+  }
+  if (memcmp(&journalDigest, seal.data(), sizeof(ShaDigest)) != 0) {
+    throw std::runtime_error("Receipt journal/seal root mismatch");
+  }
+}
+
+void Receipt::verify(const MethodId& methodId) const {
+  verifySeal(methodId);
   if (journal.size() != seal[8]) {
     std::stringstream ss;
     ss << "Receipt::verify> journal size (" << journal.size() << ") does not match receipt seal ("
@@ -69,6 +84,9 @@ struct Prover::Impl : public IoHandler {
     setSendRecvHandler(kSendRecvChannel_Stdout, [this](uint32_t, const BufferU8& buf) -> BufferU8 {
       LOG(1, "IoHandler::Stdout> " << buf.size());
       outputBuffer.insert(outputBuffer.end(), buf.begin(), buf.end());
+      if (stdoutHandler) {
+        stdoutHandler(buf);
+      }
       return BufferU8();
     });
     setSendRecvHandler(kSendRecvChannel_Stderr, [](uint32_t, const BufferU8& buf) -> BufferU8 {
@@ -95,7 +113,14 @@ struct Prover::Impl : public IoHandler {
     sendRecvHandlers.insert(std::make_pair(channelId, handler));
   }
 
+  void checkDeadline() const {
+    if (hasDeadline && std::chrono::steady_clock::now() >= deadline) {
+      throw std::runtime_error("Deadline exceeded");
+    }
+  }
+
   BufferU8 onSendRecv(uint32_t channel, const BufferU8& buf) override {
+    checkDeadline();
     auto it = sendRecvHandlers.find(channel);
     if (it == sendRecvHandlers.end()) {
       throw(std::runtime_error("Unknown channel " + std::to_string(channel)));
@@ -108,6 +133,13 @@ struct Prover::Impl : public IoHandler {
     commitBuffer.insert(commitBuffer.end(), buf.begin(), buf.end());
   }
 
+  void onFini(const MemoryState& mem) override {
+    touchedPages = mem.touchedPages();
+    checkDeadline();
+  }
+
+  void onBacktrace(const std::vector<uint32_t>& frames) override { backtrace = frames; }
+
   KeyStore& getKeyStore() override { return keyStore; }
 
   std::vector<uint8_t> elfContents;
@@ -121,10 +153,15 @@ struct Prover::Impl : public IoHandler {
   ArchiveWriter<VectorStreamWriter> inputWriter;
   ArchiveReader<CheckedStreamReader> outputReader;
   ArchiveReader<CheckedStreamReader> commitReader;
+  std::vector<PageInfo> touchedPages;
+  std::vector<uint32_t> backtrace;
+  bool hasDeadline = false;
+  std::chrono::steady_clock::time_point deadline;
 
   std::map<uint32_t /* channel id */,
            std::function<BufferU8(uint32_t /* channelId*/, const BufferU8&)> /* handler */>
       sendRecvHandlers;
+  std::function<void(const BufferU8&)> stdoutHandler;
 };
 
 CheckedStreamReader::CheckedStreamReader(const BufferU8& buffer) : buffer(buffer), cursor(0) {}
@@ -219,23 +256,44 @@ void Prover::writeInput(const void* ptr, size_t size) {
   }
 }
 
+const std::vector<PageInfo>& Prover::getTouchedPages() const {
+  return impl->touchedPages;
+}
+
 void Prover::setSendRecvHandler(
     uint32_t channelId,
     const std::function<BufferU8(uint32_t /* channelId*/, const BufferU8&)>& handler) {
   impl->setSendRecvHandler(channelId, handler);
 }
 
+void Prover::setStdoutHandler(const std::function<void(const BufferU8&)>& handler) {
+  impl->stdoutHandler = handler;
+}
+
+void Prover::setDeadline(std::chrono::steady_clock::time_point deadline) {
+  impl->hasDeadline = true;
+  impl->deadline = deadline;
+}
+
+const std::vector<uint32_t>& Prover::getBacktrace() const {
+  return impl->backtrace;
+}
+
 Receipt Prover::run() {
+  impl->backtrace.clear();
   // Set the memory handlers to call back to the impl
   MemoryHandler handler(impl.get());
   // Make the circuit
-  std::unique_ptr<ProveCircuit> circuit = getRiscVProveCircuit(impl->elfContents, handler);
+  std::unique_ptr<ProveCircuit> circuit =
+      getRiscVProveCircuit(impl->elfContents, handler, min_po2, max_po2, zk);
   if (skip_seal) {
     risc0::runWithoutSeal(*circuit);
+    po2 = circuit->getPo2();
     Receipt receipt{getCommit(), {} /* no seal */};
     return receipt;
   } else {
     BufferU32 seal = prove(*circuit);
+    po2 = circuit->getPo2();
     // Attach the full version of the output journal + construct receipt object
     Receipt receipt{getCommit(), seal};
     // Verify receipt to make sure it works
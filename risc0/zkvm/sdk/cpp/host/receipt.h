@@ -16,11 +16,13 @@
 
 #include "risc0/core/archive.h"
 #include "risc0/core/key.h"
+#include "risc0/zkp/core/constants.h"
 #include "risc0/zkp/core/sha256.h"
 #include "risc0/zkvm/prove/method_id.h"
 #include "risc0/zkvm/prove/step.h"
 
 #include <array>
+#include <chrono>
 #include <functional>
 #include <memory>
 #include <vector>
@@ -52,6 +54,13 @@ struct Receipt {
   // Verify a receipt against some code, throws if invalid.
   void verify(const MethodId& methodId) const;
 
+  // Verify the seal against some code and the digest of a journal stored
+  // elsewhere, without needing the journal itself; throws if invalid.
+  void verifyClaim(const MethodId& methodId, const ShaDigest& journalDigest) const;
+
+  // Verify only the seal against some code, throws if invalid.
+  void verifySeal(const MethodId& methodId) const;
+
   template <typename Archive> void transfer(Archive& ar) {
     ar.transfer(journal);
     ar.transfer(seal);
@@ -110,10 +119,40 @@ public:
   Receipt run();
 
   void setSkipSeal(bool skipSeal) { skip_seal = skipSeal; }
+
+  // Pad the trace with zeros instead of noise, proving integrity but not zero
+  // knowledge.  The seal records the choice.
+  void setZk(bool zk) { this->zk = zk; }
+
+  // Limit the trace size to between 2^minPo2 and 2^maxPo2 cycles.  Within
+  // this range, the smallest size which fits the execution is chosen.
+  void setPo2Range(uint32_t minPo2, uint32_t maxPo2) {
+    min_po2 = minPo2;
+    max_po2 = maxPo2;
+  }
+
+  // Stop executing the guest with an exception once the deadline passes.  The
+  // deadline is checked on each sendrecv request and again before proving.
+  void setDeadline(std::chrono::steady_clock::time_point deadline);
+
+  // The po2 of the trace chosen by the last call to run.
+  uint32_t getPo2() const { return po2; }
+
+  // The pages of memory the guest accessed during the last call to run.
+  const std::vector<PageInfo>& getTouchedPages() const;
+
+  // The guest's frame-pointer backtrace if the last call to run faulted, or
+  // empty otherwise.
+  const std::vector<uint32_t>& getBacktrace() const;
+
   void setSendRecvHandler(
       uint32_t channelId,
       const std::function<BufferU8(uint32_t /* channelId*/, const BufferU8&)>& handler);
 
+  // Pass the guest's stdout to handler as it is written, in addition to
+  // collecting it for getOutput.
+  void setStdoutHandler(const std::function<void(const BufferU8&)>& handler);
+
 private:
   Prover() = default;
 
@@ -125,6 +164,10 @@ private:
   struct Impl;
   std::unique_ptr<Impl> impl;
   bool skip_seal = false;
+  bool zk = true;
+  uint32_t min_po2 = 0;
+  uint32_t max_po2 = kMaxCyclesPo2;
+  uint32_t po2 = 0;
 };
 
 } // namespace risc0
@@ -0,0 +1,86 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// @file risc0_guest.h
+/// @brief The C API for zkVM guests.
+///
+/// These functions are provided by the risc0-zkvm-guest crate. A guest
+/// written in C defines its entrypoint with RISC0_ENTRY, is compiled by
+/// risc0_build::compile_c from the guest crate's build script, and is linked
+/// into a guest crate whose main.rs calls risc0_zkvm_guest::entry_c!().
+
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/// @brief The number of words in a SHA-256 digest.
+#define RISC0_DIGEST_WORDS 8
+
+/// @brief Defines the guest's entrypoint.
+///
+/// @param fn A function taking no arguments and returning void.
+#define RISC0_ENTRY(fn)                                                                            \
+  void risc0_c_main(void) { fn(); }
+
+/// @brief Reads data from the host's initial input.
+///
+/// Each call consumes a whole number of words, so reads of a length which is
+/// not a multiple of four skip the rest of the last word.
+///
+/// @param buf The buffer to read into.
+/// @param len The number of bytes to read.
+void risc0_read(void* buf, size_t len);
+
+/// @brief Writes data to the host.
+///
+/// @param buf The data to write, padded with zeros to a multiple of four bytes.
+/// @param len The size of the data in bytes.
+void risc0_write(const void* buf, size_t len);
+
+/// @brief Commits data to the journal.
+///
+/// @param buf The data to commit, padded with zeros to a multiple of four bytes.
+/// @param len The size of the data in bytes.
+void risc0_commit(const void* buf, size_t len);
+
+/// @brief Exchanges data with the host.
+///
+/// @param channel The channel to send the request on.
+/// @param buf The request.
+/// @param len The size of the request in bytes.
+/// @param out_len Set to the size of the reply in bytes.
+/// @return The reply, which stays valid until the guest exits.
+const void* risc0_sendrecv(uint32_t channel, const void* buf, size_t len, size_t* out_len);
+
+/// @brief Computes the SHA-256 digest of data.
+///
+/// @param buf The data to hash.
+/// @param len The size of the data in bytes.
+/// @param digest Set to the digest.
+void risc0_sha256(const void* buf, size_t len, uint32_t digest[RISC0_DIGEST_WORDS]);
+
+/// @brief Halts immediately with a failure code if a condition is false.
+///
+/// @param cond The condition to check.
+/// @param code The failure code recorded in the journal.
+void risc0_assert(int cond, uint32_t code);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif
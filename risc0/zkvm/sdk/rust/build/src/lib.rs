@@ -20,6 +20,8 @@ use std::{
     collections::HashMap,
     default::Default,
     env,
+    error::Error,
+    fmt::Write as _,
     fs::{self, File},
     io::{BufRead, BufReader, Cursor, Read, Write},
     path::{Path, PathBuf},
@@ -28,13 +30,23 @@ use std::{
 
 use assert_fs::{fixture::PathChild, TempDir};
 use cargo_metadata::{MetadataCommand, Package};
-use risc0_zkvm::host::{MethodId, DEFAULT_METHOD_ID_LIMIT};
-use risc0_zkvm_platform_sys::LINKER_SCRIPT;
+use risc0_zkvm::{
+    host::{MethodId, DEFAULT_METHOD_ID_LIMIT},
+    platform::memory::LAYOUT,
+};
+use risc0_zkvm_platform_sys::{LINKER_SCRIPT, TINYGO_LINKER_SCRIPT, TINYGO_START, TINYGO_TARGET};
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 use zip::ZipArchive;
 
+mod no_io;
+mod size;
+mod strip;
+
+pub use size::SizeReport;
+
 const TARGET_JSON: &[u8] = include_bytes!("../riscv32im-risc0-zkvm-elf.json");
+const C_HEADER: &[u8] = include_bytes!("../risc0_guest.h");
 
 #[derive(Debug, Deserialize)]
 struct Risc0Metadata {
@@ -82,25 +94,97 @@ impl Risc0Method {
         println!("Computing MethodID for {} ({:})!", self.name, elf_sha_hex);
         let elf_contents = std::fs::read(&self.elf_path).unwrap();
         let method_id = MethodId::compute_with_limit(&elf_contents, code_limit).unwrap();
-        let slice = method_id.as_slice().unwrap();
-        std::fs::write(method_id_path, slice).unwrap();
+        let method_id = method_id.to_bytes();
+        std::fs::write(method_id_path, &method_id).unwrap();
         std::fs::write(elf_sha_path, elf_sha).unwrap();
-        Vec::from(slice)
+        method_id
+    }
+
+    // Writes a per-crate breakdown of the guest image to `<elf>.sizes`.
+    fn write_size_report(&self) -> Result<(), Box<dyn Error>> {
+        let elf_contents = std::fs::read(&self.elf_path)?;
+        let report = SizeReport::from_elf(&elf_contents)?;
+        std::fs::write(self.elf_path.with_extension("sizes"), report.to_string())?;
+        Ok(())
+    }
+
+    // Writes a copy of the ELF with only its loaded segments to
+    // `<elf>.stripped`, and embeds that instead.  The original is left in
+    // place, with its symbols, for resolving guest addresses.
+    fn strip(self) -> Result<Self, Box<dyn Error>> {
+        let elf_contents = std::fs::read(&self.elf_path)?;
+        let elf_path = self.elf_path.with_extension("stripped");
+        std::fs::write(&elf_path, strip::strip_elf(&elf_contents)?)?;
+        Ok(Risc0Method {
+            name: self.name,
+            elf_path,
+        })
+    }
+
+    // Fails the build if a guest built with `no-io` still refers to the I/O
+    // that feature removes.
+    fn check_no_io(&self) {
+        let elf_contents = std::fs::read(&self.elf_path).unwrap();
+        let symbols = no_io::removed_symbols(&elf_contents).unwrap();
+        if !symbols.is_empty() {
+            panic!(
+                "Guest {} is built with no-io but refers to removed I/O: {}",
+                self.name,
+                symbols.join(", ")
+            );
+        }
     }
 
-    fn rust_def(&self, code_limit: u32) -> String {
+    fn rust_def(&self, code_limit: u32, types: Option<&MethodTypes>) -> String {
         let elf_path = self.elf_path.display();
+        let name = &self.name;
         let upper = self.name.to_uppercase();
+        let camel = to_camel_case(&self.name);
         let method_id = self.make_method_id(code_limit);
-        format!(
+        let mut def = format!(
             r##"
 pub const {upper}_PATH: &'static str = r#"{elf_path}"#;
 pub const {upper}_ID: &'static [u8] = &{method_id:?};
+
+pub struct {camel};
+
+impl risc0_zkvm::method::Method for {camel} {{
+    const NAME: &'static str = "{name}";
+    const PATH: &'static str = {upper}_PATH;
+    const ELF: &'static [u8] = include_bytes!(r#"{elf_path}"#);
+    const ID: &'static [u8] = {upper}_ID;
+}}
             "##
-        )
+        );
+        if let Some(MethodTypes { input, output }) = types {
+            write!(
+                def,
+                r##"
+impl risc0_zkvm::method::TypedMethod for {camel} {{
+    type Input = {input};
+    type Output = {output};
+}}
+                "##
+            )
+            .unwrap();
+        }
+        def
     }
 }
 
+// Converts a method name such as "my_method" into "MyMethod".
+fn to_camel_case(name: &str) -> String {
+    name.split(|c| c == '_' || c == '-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 struct ZipMapEntry {
     zip_url: &'static str,
@@ -298,11 +382,32 @@ fn build_guest_package<P>(
     target_dir: P,
     guest_build_env: &GuestBuildEnv,
     features: &Vec<String>,
+    optimize_size: bool,
+    frame_pointers: bool,
 ) where
     P: AsRef<Path>,
 {
     fs::create_dir_all(target_dir.as_ref()).unwrap();
     let cargo = env::var("CARGO").unwrap();
+    let mut rustflags = vec!["-C", "passes=loweratomic"];
+    let mut std_features = "build-std-features=compiler-builtins-mem";
+    if optimize_size {
+        // Abort on panic without formatting a message, drop the file and line
+        // of each panic site, and let the linker fold identical functions and
+        // merge duplicate constants.
+        std_features = "build-std-features=compiler-builtins-mem,panic_immediate_abort";
+        rustflags.extend([
+            "-Z",
+            "location-detail=none",
+            "-C",
+            "link-arg=--icf=all",
+            "-C",
+            "link-arg=-O2",
+        ]);
+    }
+    if frame_pointers {
+        rustflags.extend(["-C", "force-frame-pointers=yes"]);
+    }
     let mut args = vec![
         "build",
         "--release",
@@ -311,7 +416,7 @@ fn build_guest_package<P>(
         "-Z",
         "build-std=core,alloc,std,proc_macro,panic_abort",
         "-Z",
-        "build-std-features=compiler-builtins-mem",
+        std_features,
         "--manifest-path",
         pkg.manifest_path.as_str(),
         "--target-dir",
@@ -336,7 +441,7 @@ fn build_guest_package<P>(
 
     let mut cmd = Command::new(cargo);
     let mut child = cmd
-        .env("CARGO_ENCODED_RUSTFLAGS", "-C\x1fpasses=loweratomic")
+        .env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"))
         .env("__CARGO_TESTS_ONLY_SRC_ROOT", risc0_standard_lib)
         .args(args)
         .stderr(Stdio::piped())
@@ -501,6 +606,34 @@ pub struct GuestOptions {
     pub features: Vec<String>,
 
     pub test_mode: bool,
+
+    /// Shrink the guest image by compiling out panic messages and locations
+    /// and having the linker fold duplicate code and constants, write a
+    /// per-crate size report next to each method's ELF, and embed a copy of
+    /// the ELF stripped down to its loaded segments.  Guest panics then
+    /// abort without reporting a message to the host.
+    pub optimize_size: bool,
+
+    /// Keep a frame pointer in every function, including the standard
+    /// library, so that the host can report a backtrace when the guest
+    /// faults without needing debug info.  Costs a few cycles per call, and
+    /// turning the option on or off changes the method ID.
+    pub frame_pointers: bool,
+
+    /// Input and output types of methods, indexed by method name.  Methods
+    /// listed here implement `risc0_zkvm::method::TypedMethod`.
+    pub method_types: HashMap<String, MethodTypes>,
+}
+
+/// The input and output types of a method, as rust type expressions which
+/// must be nameable from the crate embedding the methods.
+#[derive(Debug, Clone)]
+pub struct MethodTypes {
+    /// The type the guest reads with `env::read`.
+    pub input: String,
+
+    /// The type the guest commits to its journal.
+    pub output: String,
 }
 
 impl Default for GuestOptions {
@@ -509,6 +642,9 @@ impl Default for GuestOptions {
             code_limit: DEFAULT_METHOD_ID_LIMIT,
             features: vec![],
             test_mode: false,
+            optimize_size: false,
+            frame_pointers: false,
+            method_types: HashMap::new(),
         }
     }
 }
@@ -547,12 +683,41 @@ pub fn embed_methods_with_options(mut guest_pkg_to_options: HashMap<&str, GuestO
                 &out_dir.join("riscv-guest"),
                 &guest_build_env,
                 &guest_options.features,
+                guest_options.optimize_size,
+                guest_options.frame_pointers,
             );
         }
 
+        let no_io = no_io::enabled(&guest_pkg, &guest_options.features);
         for method in guest_methods(&guest_pkg, &out_dir) {
+            if no_io {
+                method.check_no_io();
+            }
+            // The checks and the report read symbols, so they run before
+            // stripping.
+            let method = if guest_options.optimize_size {
+                if let Err(err) = method.write_size_report() {
+                    println!(
+                        "cargo:warning=Could not write a size report for {}: {err}",
+                        method.name
+                    );
+                }
+                let name = method.name.clone();
+                method
+                    .strip()
+                    .unwrap_or_else(|err| panic!("Could not strip guest {name}: {err}"))
+            } else {
+                method
+            };
             methods_file
-                .write_all(method.rust_def(guest_options.code_limit).as_bytes())
+                .write_all(
+                    method
+                        .rust_def(
+                            guest_options.code_limit,
+                            guest_options.method_types.get(&method.name),
+                        )
+                        .as_bytes(),
+                )
                 .unwrap();
         }
     }
@@ -587,12 +752,199 @@ pub fn embed_methods() {
     embed_methods_with_options(HashMap::new())
 }
 
+// Generates the MEMORY block of the linker script from the platform's memory
+// layout.
+fn memory_block() -> String {
+    let mut block = String::from("MEMORY {\n");
+    for entry in LAYOUT {
+        let name = match entry.attrs {
+            "" => entry.name.to_string(),
+            attrs => format!("{:<8}({attrs})", entry.name),
+        };
+        writeln!(
+            block,
+            "  {name:<12} : ORIGIN = 0x{:08X}, LENGTH = {:>3}M",
+            entry.region.start(),
+            entry.region.len_bytes() / (1024 * 1024)
+        )
+        .unwrap();
+    }
+    block += "}";
+    block
+}
+
+// Parses a linker script size such as "10M" into bytes.
+fn parse_size(size: &str) -> usize {
+    let (digits, scale) = match size.as_bytes().last() {
+        Some(b'M') => (&size[..size.len() - 1], 1024 * 1024),
+        Some(b'K') => (&size[..size.len() - 1], 1024),
+        _ => (size, 1),
+    };
+    digits.parse::<usize>().unwrap() * scale
+}
+
+// Replaces the MEMORY block of the given linker script with one generated
+// from the platform's memory layout, panicking if the two disagree.
+fn generate_linker_script(script: &str) -> String {
+    let start = script
+        .find("MEMORY {")
+        .expect("Linker script has no MEMORY block");
+    let len = script[start..].find('}').unwrap() + 1;
+    let regions: Vec<(String, usize, usize)> = script[start + "MEMORY {".len()..start + len - 1]
+        .lines()
+        .filter(|line| line.contains("ORIGIN"))
+        .map(|line| {
+            let name = line.split_whitespace().next().unwrap().to_string();
+            let field = |key: &str| {
+                let value = line.split(key).nth(1).unwrap();
+                value.split(',').next().unwrap().trim().to_string()
+            };
+            let origin = usize::from_str_radix(field("ORIGIN =").trim_start_matches("0x"), 16);
+            (name, origin.unwrap(), parse_size(&field("LENGTH =")))
+        })
+        .collect();
+    let expected: Vec<(String, usize, usize)> = LAYOUT
+        .iter()
+        .map(|entry| {
+            let region = &entry.region;
+            (entry.name.to_string(), region.start(), region.len_bytes())
+        })
+        .collect();
+    assert_eq!(
+        regions, expected,
+        "risc0.ld does not match risc0_zkvm::platform::memory::LAYOUT"
+    );
+    format!(
+        "{}{}{}",
+        &script[..start],
+        memory_block(),
+        &script[start + len..]
+    )
+}
+
 /// Called inside the guest crate's build.rs to do special linking for the ZKVM
 pub fn link() {
     if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "riscv32" {
         let out_dir = env::var_os("OUT_DIR").unwrap();
         let linker_script = Path::new(&out_dir).join("risc0.ld");
-        fs::write(&linker_script, LINKER_SCRIPT).unwrap();
+        let script = std::str::from_utf8(LINKER_SCRIPT).unwrap();
+        fs::write(&linker_script, generate_linker_script(script)).unwrap();
         println!("cargo:rustc-link-arg=-T{}", linker_script.to_str().unwrap());
     }
 }
+
+/// Called inside the guest crate's build.rs to compile C sources written
+/// against `risc0_guest.h` into a static library called `name`, and link it
+/// into the guest.
+///
+/// The header is placed on the include path.  The compiler defaults to
+/// `clang` and the archiver to `llvm-ar`; `RISC0_CC` and `RISC0_AR` override
+/// them, and `RISC0_CFLAGS` adds compiler flags.
+pub fn compile_c<P: AsRef<Path>>(name: &str, sources: &[P]) {
+    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() != "riscv32" {
+        return;
+    }
+    for var in ["RISC0_CC", "RISC0_AR", "RISC0_CFLAGS"] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let include_dir = out_dir.join("include");
+    fs::create_dir_all(&include_dir).unwrap();
+    fs::write(include_dir.join("risc0_guest.h"), C_HEADER).unwrap();
+
+    let cc = env::var("RISC0_CC").unwrap_or_else(|_| "clang".to_string());
+    let cflags = env::var("RISC0_CFLAGS").unwrap_or_default();
+    let mut objects = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        let source = source.as_ref();
+        println!("cargo:rerun-if-changed={}", source.display());
+        let object = out_dir.join(format!("{name}-{i}.o"));
+        let mut cmd = Command::new(&cc);
+        if cc.contains("clang") {
+            cmd.arg("--target=riscv32-unknown-elf");
+        }
+        let status = cmd
+            .args(["-march=rv32im", "-mabi=ilp32", "-O2", "-ffreestanding"])
+            .arg("-I")
+            .arg(&include_dir)
+            .args(cflags.split_whitespace())
+            .arg("-c")
+            .arg(source)
+            .arg("-o")
+            .arg(&object)
+            .status()
+            .unwrap();
+        if !status.success() {
+            panic!("Failed to compile {}", source.display());
+        }
+        objects.push(object);
+    }
+
+    let ar = env::var("RISC0_AR").unwrap_or_else(|_| "llvm-ar".to_string());
+    let lib = out_dir.join(format!("lib{name}.a"));
+    let _ = fs::remove_file(&lib);
+    let status = Command::new(ar)
+        .arg("crs")
+        .arg(&lib)
+        .args(&objects)
+        .status()
+        .unwrap();
+    if !status.success() {
+        panic!("Failed to archive {}", lib.display());
+    }
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static={name}");
+}
+
+/// Writes a TinyGo target for zkVM guests into `dir`, returning the path of
+/// its target file, for use as `tinygo build -target <path>`.
+///
+/// The target links with a linker script generated from the platform's
+/// memory layout and starts from the entry shim in
+/// zkvm/platform/tinygo/start.S, which follows the guest ABI described in
+/// `risc0_zkvm::platform::abi`.
+pub fn tinygo_target<P: AsRef<Path>>(dir: P) -> PathBuf {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).unwrap();
+    let linker_script = dir.join("risc0.ld");
+    let script = std::str::from_utf8(TINYGO_LINKER_SCRIPT).unwrap();
+    fs::write(&linker_script, generate_linker_script(script)).unwrap();
+    let start = dir.join("start.S");
+    fs::write(&start, TINYGO_START).unwrap();
+
+    let mut target: serde_json::Value = serde_json::from_slice(TINYGO_TARGET).unwrap();
+    target["linkerscript"] = linker_script.to_str().unwrap().into();
+    target["extra-files"][0] = start.to_str().unwrap().into();
+    let target_path = dir.join("risc0.json");
+    fs::write(&target_path, serde_json::to_string_pretty(&target).unwrap()).unwrap();
+    target_path
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_platform_sys::{LINKER_SCRIPT, TINYGO_LINKER_SCRIPT};
+
+    use super::{generate_linker_script, tinygo_target};
+
+    #[test]
+    fn linker_script_matches_layout() {
+        for script in [LINKER_SCRIPT, TINYGO_LINKER_SCRIPT] {
+            let script = std::str::from_utf8(script).unwrap();
+            assert_eq!(generate_linker_script(script), script);
+        }
+    }
+
+    #[test]
+    fn tinygo() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let target = tinygo_target(dir.path());
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(target).unwrap()).unwrap();
+        let linker_script = json["linkerscript"].as_str().unwrap();
+        assert!(std::path::Path::new(linker_script).exists());
+        assert!(json["extra-files"][0]
+            .as_str()
+            .unwrap()
+            .ends_with("start.S"));
+    }
+}
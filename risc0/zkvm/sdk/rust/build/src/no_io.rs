@@ -0,0 +1,133 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use cargo_metadata::Package;
+use xmas_elf::{sections::SectionData, symbol_table::Entry, ElfFile};
+
+const GUEST_CRATE: &str = "risc0-zkvm-guest";
+
+// Functions the guest crate's `no-io` feature compiles out, as the module
+// and final path component of their legacy-mangled names.
+const REMOVED: &[(&str, &str)] = &[
+    ("io", "host_sendrecv"),
+    ("io", "recv_split"),
+    ("env", "send_recv"),
+    ("env", "send_recv_as_u32"),
+    ("env", "reader"),
+    ("env", "read_secret"),
+    ("env", "write"),
+];
+
+// C entry points the `no-io` feature compiles out.
+const REMOVED_C: &[&str] = &["risc0_sendrecv", "risc0_write"];
+
+// Whether the guest package builds the guest crate with `no-io`, either
+// directly in its dependency or through one of the enabled `features`.
+pub(crate) fn enabled(pkg: &Package, features: &[String]) -> bool {
+    let direct = pkg
+        .dependencies
+        .iter()
+        .any(|dep| dep.name == GUEST_CRATE && dep.features.iter().any(|f| f == "no-io"));
+    let forwarded = features
+        .iter()
+        .map(String::as_str)
+        .chain(["default"])
+        .filter_map(|name| pkg.features.get(name))
+        .flatten()
+        .any(|f| {
+            f.strip_suffix("/no-io")
+                .map_or(false, |dep| dep.trim_end_matches('?') == GUEST_CRATE)
+        });
+    direct || forwarded
+}
+
+// Returns the names of symbols in a linked guest ELF which refer to I/O the
+// `no-io` feature removes, whether defined or left undefined.
+pub(crate) fn removed_symbols(input: &[u8]) -> Result<Vec<String>, &'static str> {
+    let elf = ElfFile::new(input)?;
+    let mut found = Vec::new();
+    for section in elf.section_iter() {
+        let symbols = match section.get_data(&elf)? {
+            SectionData::SymbolTable32(symbols) => symbols,
+            _ => continue,
+        };
+        for symbol in symbols {
+            let name = symbol.get_name(&elf)?;
+            if is_removed(name) && !found.iter().any(|f| f == name) {
+                found.push(name.to_string());
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn is_removed(symbol: &str) -> bool {
+    if REMOVED_C.contains(&symbol) {
+        return true;
+    }
+    let path = match symbol_path(symbol) {
+        Some(path) => path,
+        None => return false,
+    };
+    match (path.first(), path.get(1), path.last()) {
+        (Some(&"risc0_zkvm_guest"), Some(module), Some(name)) if path.len() > 2 => {
+            REMOVED.contains(&(*module, *name))
+        }
+        _ => false,
+    }
+}
+
+// Splits a legacy-mangled rust symbol into its path components, dropping the
+// trailing hash.
+fn symbol_path(symbol: &str) -> Option<Vec<&str>> {
+    let mut mangled = symbol.strip_prefix("_ZN")?;
+    let mut path = Vec::new();
+    while !mangled.starts_with('E') {
+        let digits = mangled.bytes().take_while(u8::is_ascii_digit).count();
+        let len: usize = mangled[..digits].parse().ok()?;
+        path.push(mangled.get(digits..digits + len)?);
+        mangled = &mangled[digits + len..];
+    }
+    if path.last()?.starts_with('h') {
+        path.pop();
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_removed;
+
+    #[test]
+    fn removed_symbols() {
+        assert!(is_removed(
+            "_ZN16risc0_zkvm_guest2io13host_sendrecv17h0123456789abcdefE"
+        ));
+        assert!(is_removed(
+            "_ZN16risc0_zkvm_guest3env3Env9send_recv17h0123456789abcdefE"
+        ));
+        assert!(is_removed(
+            "_ZN16risc0_zkvm_guest3env5write17h0123456789abcdefE"
+        ));
+        assert!(is_removed("risc0_sendrecv"));
+        assert!(!is_removed(
+            "_ZN16risc0_zkvm_guest3env6commit17h0123456789abcdefE"
+        ));
+        assert!(!is_removed(
+            "_ZN16risc0_zkvm_guest2io13initial_input17h0123456789abcdefE"
+        ));
+        assert!(!is_removed("_ZN4core3fmt5write17h0123456789abcdefE"));
+        assert!(!is_removed("_ZN16risc0_zkvm_guest"));
+    }
+}
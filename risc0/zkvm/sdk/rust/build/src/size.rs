@@ -0,0 +1,145 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, fmt};
+
+use xmas_elf::{
+    program::Type as ProgramType,
+    sections::{SectionData, SHF_ALLOC},
+    symbol_table::Entry,
+    ElfFile,
+};
+
+// Bucket for loaded bytes that aren't covered by any symbol: alignment
+// padding, merged string literals and the like.
+const UNATTRIBUTED: &str = "[unattributed]";
+
+// Bucket for symbols that don't carry a rust crate name, such as C functions
+// or anonymous constants.
+const OTHER: &str = "[other]";
+
+/// A breakdown of a guest image's loaded size by the crate each symbol came
+/// from.
+#[derive(Debug)]
+pub struct SizeReport {
+    /// The total number of bytes loaded into guest memory.
+    pub total: u64,
+
+    /// Crate names and the bytes attributed to them, largest first.
+    pub crates: Vec<(String, u64)>,
+}
+
+impl SizeReport {
+    /// Builds a report from the symbol table of a linked guest ELF.
+    pub fn from_elf(input: &[u8]) -> Result<Self, &'static str> {
+        let elf = ElfFile::new(input)?;
+        let mut total = 0;
+        for phdr in elf.program_iter() {
+            if phdr.get_type()? == ProgramType::Load {
+                total += phdr.mem_size();
+            }
+        }
+
+        let mut sizes: BTreeMap<String, u64> = BTreeMap::new();
+        let mut attributed = 0;
+        for section in elf.section_iter() {
+            let symbols = match section.get_data(&elf)? {
+                SectionData::SymbolTable32(symbols) => symbols,
+                _ => continue,
+            };
+            for symbol in symbols {
+                let shndx = symbol.shndx();
+                // Skip undefined, absolute and common symbols.
+                if shndx == 0 || shndx >= 0xff00 || symbol.size() == 0 {
+                    continue;
+                }
+                if elf.section_header(shndx)?.flags() & SHF_ALLOC == 0 {
+                    continue;
+                }
+                let name = symbol_crate(symbol.get_name(&elf)?).unwrap_or(OTHER);
+                *sizes.entry(name.to_string()).or_default() += symbol.size();
+                attributed += symbol.size();
+            }
+        }
+        if total > attributed {
+            sizes.insert(UNATTRIBUTED.to_string(), total - attributed);
+        }
+
+        let mut crates: Vec<(String, u64)> = sizes.into_iter().collect();
+        crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(SizeReport { total, crates })
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>10}  {:>6}  crate", "bytes", "%")?;
+        for (name, size) in &self.crates {
+            let percent = 100.0 * *size as f64 / self.total.max(1) as f64;
+            writeln!(f, "{size:>10}  {percent:>5.1}%  {name}")?;
+        }
+        writeln!(f, "{:>10}  {:>5.1}%  total", self.total, 100.0)
+    }
+}
+
+// Extracts the crate a legacy-mangled rust symbol belongs to. For trait impls
+// such as `<core::fmt::Arguments as core::fmt::Display>::fmt` this is the
+// crate of the implementing type.
+fn symbol_crate(symbol: &str) -> Option<&str> {
+    let mangled = symbol.strip_prefix("_ZN")?;
+    let digits = mangled.bytes().take_while(u8::is_ascii_digit).count();
+    let len: usize = mangled[..digits].parse().ok()?;
+    let first = mangled.get(digits..digits + len)?;
+    match first.strip_prefix("_$LT$") {
+        Some(inner) => {
+            let inner = inner
+                .trim_start_matches("$RF$")
+                .trim_start_matches("mut$u20$");
+            // Types without a path, such as generic parameters or
+            // primitives, don't name a crate.
+            let path = &inner[..inner.find('$').unwrap_or(inner.len())];
+            path.find("..").map(|end| &path[..end])
+        }
+        None => Some(first),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::symbol_crate;
+
+    #[test]
+    fn crate_names() {
+        assert_eq!(
+            symbol_crate("_ZN4core3fmt5write17h0123456789abcdefE"),
+            Some("core")
+        );
+        assert_eq!(
+            symbol_crate("_ZN10risc0_zkvm5guest3env4read17h0123456789abcdefE"),
+            Some("risc0_zkvm")
+        );
+        assert_eq!(
+            symbol_crate(
+                "_ZN56_$LT$alloc..string..String$u20$as$u20$core..fmt..Write$GT$9write_str17hE"
+            ),
+            Some("alloc")
+        );
+        assert_eq!(
+            symbol_crate("_ZN60_$LT$$RF$T$u20$as$u20$core..fmt..Display$GT$3fmt17hE"),
+            None
+        );
+        assert_eq!(symbol_crate("memcpy"), None);
+        assert_eq!(symbol_crate("_ZN"), None);
+    }
+}
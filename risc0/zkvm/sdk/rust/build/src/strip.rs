@@ -0,0 +1,96 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Post-link stripping of guest ELFs.
+//!
+//! The zkVM loads a guest from its `PT_LOAD` segments alone, so the rest of
+//! the file (section headers, symbol and string tables, debug info and the
+//! like) is dead weight once the build has read the symbols it needs.
+//! Dropping it leaves the loaded image, and so the method ID, unchanged.
+
+use xmas_elf::{header::Class, program::Type as ProgramType, ElfFile};
+
+// Offsets of `e_shoff`, and of `e_shnum` and `e_shstrndx`, in an ELF32
+// header.
+const SHOFF: std::ops::Range<usize> = 0x20..0x24;
+const SHNUM_SHSTRNDX: std::ops::Range<usize> = 0x30..0x34;
+
+/// Returns a copy of the ELF `input` cut down to its header, program headers
+/// and loaded segments, without a section header table.
+pub(crate) fn strip_elf(input: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let elf = ElfFile::new(input)?;
+    if elf.header.pt1.class() != Class::ThirtyTwo {
+        return Err("Guest ELF is not 32-bit");
+    }
+    let pt2 = &elf.header.pt2;
+    let mut end = pt2.ph_offset() + pt2.ph_count() as u64 * pt2.ph_entry_size() as u64;
+    for phdr in elf.program_iter() {
+        if phdr.get_type()? == ProgramType::Load {
+            end = end.max(phdr.offset() + phdr.file_size());
+        }
+    }
+    if end > input.len() as u64 {
+        return Err("Guest ELF segment runs past the end of the file");
+    }
+    let mut out = input[..end as usize].to_vec();
+    out[SHOFF].fill(0);
+    out[SHNUM_SHSTRNDX].fill(0);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use xmas_elf::{program::Type as ProgramType, ElfFile};
+
+    use super::strip_elf;
+
+    // A little-endian RISC-V ELF32 with one loaded segment of four bytes,
+    // followed by four bytes that no segment covers and one section header.
+    fn elf() -> Vec<u8> {
+        let mut elf = vec![0; 0x54];
+        elf[..7].copy_from_slice(b"\x7fELF\x01\x01\x01");
+        elf[0x10..0x18].copy_from_slice(&[2, 0, 0xf3, 0, 1, 0, 0, 0]);
+        elf[0x1c] = 0x34; // e_phoff
+        elf[0x20] = 0x5c; // e_shoff
+        elf[0x28..0x34].copy_from_slice(&[0x34, 0, 0x20, 0, 1, 0, 0x28, 0, 1, 0, 0, 0]);
+        // The program header: PT_LOAD of 4 bytes at offset 0x54.
+        elf[0x34] = 1;
+        elf[0x38] = 0x54;
+        elf[0x44] = 4;
+        elf[0x48] = 4;
+        elf.extend_from_slice(&[1, 2, 3, 4, 0xaa, 0xbb, 0xcc, 0xdd]);
+        elf.extend_from_slice(&[0; 0x28]);
+        elf
+    }
+
+    #[test]
+    fn keeps_loaded_segments() {
+        let input = elf();
+        let stripped = strip_elf(&input).unwrap();
+        assert_eq!(stripped.len(), 0x58);
+        let elf = ElfFile::new(&stripped).unwrap();
+        assert_eq!(elf.header.pt2.sh_count(), 0);
+        assert_eq!(elf.header.pt2.sh_offset(), 0);
+        let phdr = elf.program_iter().next().unwrap();
+        assert_eq!(phdr.get_type().unwrap(), ProgramType::Load);
+        assert_eq!(&stripped[phdr.offset() as usize..], [1, 2, 3, 4]);
+        assert_eq!(strip_elf(&stripped).unwrap(), stripped);
+    }
+
+    #[test]
+    fn truncated() {
+        let input = elf();
+        assert!(strip_elf(&input[..0x56]).is_err());
+    }
+}
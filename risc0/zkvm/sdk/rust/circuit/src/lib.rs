@@ -1,5 +1,7 @@
 // This code is automatically generated
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
 mod poly_ext;
 mod poly_fp;
 mod step_accum;
@@ -17,29 +17,38 @@ use core::{
     cell::UnsafeCell,
 };
 
-use risc0_zkvm::platform::{memory, WORD_SIZE};
+use risc0_zkvm::platform::WORD_SIZE;
 
-use crate::{_fault, align_up};
+use super::{out_of_memory, Fresh, Grow};
+use crate::align_up;
 
 // Bump pointer allocator for *single* core systems
-struct BumpPointerAlloc {
+pub(crate) struct BumpPointerAlloc {
     head: UnsafeCell<usize>,
-    end: usize,
+    end: UnsafeCell<usize>,
+}
+
+impl BumpPointerAlloc {
+    pub const fn new(start: usize, end: usize) -> Self {
+        BumpPointerAlloc {
+            head: UnsafeCell::new(start),
+            end: UnsafeCell::new(end),
+        }
+    }
 }
 
 // SAFETY: single threaded environment
 unsafe impl Sync for BumpPointerAlloc {}
 
-#[cfg(target_arch = "riscv32")]
 unsafe impl GlobalAlloc for BumpPointerAlloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let head = self.head.get();
 
         // move start up to the next alignment boundary
-        let alloc_start = align_up(*head, WORD_SIZE);
+        let alloc_start = align_up(*head, layout.align().max(WORD_SIZE));
         let alloc_end = alloc_start.checked_add(layout.size()).unwrap();
-        if alloc_end > self.end {
-            _fault();
+        if alloc_end > *self.end.get() {
+            out_of_memory(layout)
         } else {
             *head = alloc_end;
             alloc_start as *mut u8
@@ -51,15 +60,15 @@ unsafe impl GlobalAlloc for BumpPointerAlloc {
     }
 }
 
-#[cfg(target_arch = "riscv32")]
-#[global_allocator]
-static HEAP: BumpPointerAlloc = BumpPointerAlloc {
-    head: UnsafeCell::new(memory::HEAP.start()),
-    end: memory::HEAP.end(),
-};
+impl Fresh for BumpPointerAlloc {
+    unsafe fn alloc_fresh(&self, layout: Layout) -> *mut u8 {
+        // Memory is never reused, so every allocation is fresh.
+        self.alloc(layout)
+    }
+}
 
-#[cfg(target_arch = "riscv32")]
-#[alloc_error_handler]
-unsafe fn alloc_fault(_layout: Layout) -> ! {
-    _fault()
+impl Grow for BumpPointerAlloc {
+    unsafe fn grow(&self, end: usize) {
+        *self.end.get() = end;
+    }
 }
@@ -0,0 +1,133 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A debugging wrapper which counts live allocations.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    fmt::{self, Write},
+};
+
+use risc0_zkvm::platform::io::SENDRECV_CHANNEL_STDERR;
+
+use super::{Fresh, Grow};
+
+pub(crate) struct LeakCheck<A> {
+    inner: A,
+    live: Cell<usize>,
+    live_bytes: Cell<usize>,
+    peak_bytes: Cell<usize>,
+}
+
+// SAFETY: single threaded environment
+unsafe impl<A> Sync for LeakCheck<A> {}
+
+impl<A> LeakCheck<A> {
+    pub const fn new(inner: A) -> Self {
+        LeakCheck {
+            inner,
+            live: Cell::new(0),
+            live_bytes: Cell::new(0),
+            peak_bytes: Cell::new(0),
+        }
+    }
+
+    fn track(&self, allocated: usize, freed: usize) {
+        let live_bytes = self.live_bytes.get() + allocated - freed;
+        self.live_bytes.set(live_bytes);
+        self.peak_bytes.set(self.peak_bytes.get().max(live_bytes));
+    }
+
+    // Write the number of live allocations to stderr, if there are any.
+    pub fn report(&self) {
+        if self.live.get() == 0 {
+            return;
+        }
+        // Format on the stack, so that reporting doesn't allocate.
+        let mut msg = StackString::<128>::new();
+        let _ = writeln!(
+            msg,
+            "Leak check: {} allocations ({} bytes) still live, peak {} bytes",
+            self.live.get(),
+            self.live_bytes.get(),
+            self.peak_bytes.get()
+        );
+        crate::env::send_recv(SENDRECV_CHANNEL_STDERR, msg.as_bytes());
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for LeakCheck<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        self.live.set(self.live.get() + 1);
+        self.track(layout.size(), 0);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.live.set(self.live.get() - 1);
+        self.track(0, layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let ptr = self.inner.realloc(ptr, layout, new_size);
+        self.track(new_size, layout.size());
+        ptr
+    }
+}
+
+impl<A: Fresh> Fresh for LeakCheck<A> {
+    // Fresh memory is never freed, so it isn't counted.
+    unsafe fn alloc_fresh(&self, layout: Layout) -> *mut u8 {
+        self.inner.alloc_fresh(layout)
+    }
+}
+
+impl<A: Grow> Grow for LeakCheck<A> {
+    unsafe fn grow(&self, end: usize) {
+        self.inner.grow(end)
+    }
+}
+
+struct StackString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> StackString<N> {
+    fn new() -> Self {
+        StackString {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl<const N: usize> Write for StackString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > N {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
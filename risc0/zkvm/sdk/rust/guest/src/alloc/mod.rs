@@ -0,0 +1,117 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The guest heap.
+//!
+//! The default bump allocator never frees, which is the cheapest option for
+//! short-lived guests.  The `heap-tlsf` feature selects a two-level
+//! segregated fit allocator instead, which reuses freed memory at the cost
+//! of a few more cycles per allocation, and `heap-leak-check` wraps either
+//! one to report allocations still live when `main` returns.
+//!
+//! The host may only write to memory the guest has never touched, so the
+//! buffers it fills in, such as SHA digests and split responses, come from
+//! [alloc_fresh], which never hands out memory that was freed.
+
+#![cfg_attr(not(target_arch = "riscv32"), allow(dead_code))]
+
+use core::alloc::Layout;
+
+use risc0_zkvm::platform::memory;
+
+use crate::{_fault, hook::HOOK_HEAP_SIZE};
+
+mod bump;
+#[cfg(feature = "heap-leak-check")]
+mod leak;
+#[cfg(feature = "heap-tlsf")]
+mod tlsf;
+
+#[cfg(not(feature = "heap-tlsf"))]
+type Backend = bump::BumpPointerAlloc;
+#[cfg(feature = "heap-tlsf")]
+type Backend = tlsf::Tlsf;
+
+#[cfg(not(feature = "heap-leak-check"))]
+type Heap = Backend;
+#[cfg(feature = "heap-leak-check")]
+type Heap = leak::LeakCheck<Backend>;
+
+// An allocator whose heap can be extended in place.
+trait Grow {
+    // Move the end of the heap up to `end`.
+    //
+    // SAFETY: `end` must not be below the current end, and the memory up to
+    // it must be unused.
+    unsafe fn grow(&self, end: usize);
+}
+
+// An allocator which can hand out memory the guest has never touched.
+trait Fresh {
+    // Allocate memory which has never been read or written, and which is
+    // never freed or handed out again.
+    unsafe fn alloc_fresh(&self, layout: Layout) -> *mut u8;
+}
+
+#[cfg(not(feature = "heap-leak-check"))]
+const fn new_heap(start: usize, end: usize) -> Heap {
+    Backend::new(start, end)
+}
+
+#[cfg(feature = "heap-leak-check")]
+const fn new_heap(start: usize, end: usize) -> Heap {
+    leak::LeakCheck::new(Backend::new(start, end))
+}
+
+#[cfg(target_arch = "riscv32")]
+#[global_allocator]
+static HEAP: Heap = new_heap(memory::HEAP.start(), memory::HEAP.end() - HOOK_HEAP_SIZE);
+
+#[cfg(target_arch = "riscv32")]
+#[alloc_error_handler]
+unsafe fn alloc_fault(layout: Layout) -> ! {
+    crate::hook::run_alloc_error_hook(layout);
+    _fault()
+}
+
+// Called when the heap is exhausted.
+fn out_of_memory(layout: Layout) -> ! {
+    crate::hook::run_alloc_error_hook(layout);
+    unsafe { _fault() }
+}
+
+// Allocate memory for the host to write into; see Fresh.  It is never freed.
+#[cfg(target_arch = "riscv32")]
+pub(crate) unsafe fn alloc_fresh(layout: Layout) -> *mut u8 {
+    HEAP.alloc_fresh(layout)
+}
+
+#[cfg(not(target_arch = "riscv32"))]
+pub(crate) unsafe fn alloc_fresh(layout: Layout) -> *mut u8 {
+    _alloc::alloc::alloc(layout)
+}
+
+// Make the memory held back for hooks available to the allocator.
+pub(crate) fn release_reserve() {
+    #[cfg(target_arch = "riscv32")]
+    unsafe {
+        HEAP.grow(memory::HEAP.end())
+    };
+}
+
+// Report allocations which are still live, if leak checking is enabled.
+pub(crate) fn report_leaks() {
+    #[cfg(all(target_arch = "riscv32", feature = "heap-leak-check"))]
+    HEAP.report();
+}
@@ -0,0 +1,429 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A two-level segregated fit allocator.
+//!
+//! Free blocks are kept in lists bucketed first by the power of two of their
+//! size and then linearly within it, with bitmaps of the non-empty lists, so
+//! both allocation and freeing take constant time.  Freed blocks are merged
+//! with their free neighbors, which keeps fragmentation low for guests that
+//! allocate and free a lot.
+//!
+//! The heap starts small and grows towards its limit as allocations need
+//! it, so the memory past its end has never been touched.  Fresh
+//! allocations are carved from there as blocks which are never freed.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    mem::size_of,
+    ptr::{self, null_mut},
+};
+
+use super::{out_of_memory, Fresh, Grow};
+use crate::align_up;
+
+// Payloads are aligned to, and sized in multiples of, ALIGN bytes.
+const ALIGN_LOG2: usize = 3;
+const ALIGN: usize = 1 << ALIGN_LOG2;
+
+// Each power of two is split into SL_COUNT lists.
+const SL_LOG2: usize = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+
+// Sizes below 1 << FL_SHIFT share the first list of lists.
+const FL_SHIFT: usize = SL_LOG2 + ALIGN_LOG2;
+const FL_COUNT: usize = usize::BITS as usize - FL_SHIFT + 1;
+
+// The header in front of each payload: prev_phys and size.
+const HEADER: usize = 2 * size_of::<usize>();
+// Big enough for the free list links.
+const MIN_SIZE: usize = 2 * size_of::<usize>();
+
+// Set in Block::size when the block is free.
+const FREE: usize = 1;
+
+// The heap grows by at least this many bytes at a time.
+const GROW_SIZE: usize = 1 << 16;
+
+#[repr(C)]
+struct Block {
+    // The block physically before this one, or null for the first block.
+    prev_phys: *mut Block,
+    // The size of the payload in bytes, with the FREE flag.
+    size: usize,
+    // Free list links, stored in the payload of free blocks only.
+    next_free: *mut Block,
+    prev_free: *mut Block,
+}
+
+impl Block {
+    unsafe fn size(block: *mut Block) -> usize {
+        (*block).size & !FREE
+    }
+
+    unsafe fn is_free(block: *mut Block) -> bool {
+        (*block).size & FREE != 0
+    }
+
+    unsafe fn payload(block: *mut Block) -> usize {
+        block as usize + HEADER
+    }
+
+    unsafe fn from_payload(ptr: *mut u8) -> *mut Block {
+        (ptr as usize - HEADER) as *mut Block
+    }
+
+    unsafe fn next_phys(block: *mut Block) -> *mut Block {
+        (Block::payload(block) + Block::size(block)) as *mut Block
+    }
+}
+
+// The list holding free blocks of `size` bytes.
+fn mapping(size: usize) -> (usize, usize) {
+    if size < 1 << FL_SHIFT {
+        (0, size >> ALIGN_LOG2)
+    } else {
+        let log2 = (usize::BITS - 1 - size.leading_zeros()) as usize;
+        (log2 - FL_SHIFT + 1, (size >> (log2 - SL_LOG2)) ^ SL_COUNT)
+    }
+}
+
+// The first list whose blocks all hold at least `size` bytes.
+fn mapping_search(size: usize) -> (usize, usize) {
+    if size < 1 << FL_SHIFT {
+        mapping(size)
+    } else {
+        let log2 = (usize::BITS - 1 - size.leading_zeros()) as usize;
+        mapping(size.saturating_add((1 << (log2 - SL_LOG2)) - 1))
+    }
+}
+
+struct State {
+    initialized: bool,
+    start: usize,
+    // The end of the memory the heap covers so far; nothing past it has
+    // been touched.
+    end: usize,
+    // How far the heap may grow.
+    limit: usize,
+    // A zero sized, used block at the end of the heap, so that the last
+    // block never merges past it.
+    sentinel: *mut Block,
+    fl_bitmap: usize,
+    sl_bitmap: [usize; FL_COUNT],
+    heads: [[*mut Block; SL_COUNT]; FL_COUNT],
+}
+
+impl State {
+    unsafe fn init(&mut self) {
+        self.initialized = true;
+        let start = align_up(self.start, ALIGN);
+        let end = start.saturating_add(GROW_SIZE).min(self.limit) & !(ALIGN - 1);
+        if end < start + 3 * HEADER + MIN_SIZE {
+            return;
+        }
+        self.end = end;
+        let first = start as *mut Block;
+        let sentinel = (end - HEADER) as *mut Block;
+        (*first).prev_phys = null_mut();
+        (*first).size = (sentinel as usize - Block::payload(first)) | FREE;
+        (*sentinel).prev_phys = first;
+        (*sentinel).size = 0;
+        self.sentinel = sentinel;
+        self.insert(first);
+    }
+
+    unsafe fn insert(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping(Block::size(block));
+        let head = self.heads[fl][sl];
+        (*block).next_free = head;
+        (*block).prev_free = null_mut();
+        if !head.is_null() {
+            (*head).prev_free = block;
+        }
+        self.heads[fl][sl] = block;
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+    }
+
+    unsafe fn remove(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping(Block::size(block));
+        let next = (*block).next_free;
+        let prev = (*block).prev_free;
+        if !next.is_null() {
+            (*next).prev_free = prev;
+        }
+        if !prev.is_null() {
+            (*prev).next_free = next;
+        } else {
+            self.heads[fl][sl] = next;
+            if next.is_null() {
+                self.sl_bitmap[fl] &= !(1 << sl);
+                if self.sl_bitmap[fl] == 0 {
+                    self.fl_bitmap &= !(1 << fl);
+                }
+            }
+        }
+    }
+
+    // Find and unlink a free block of at least `size` bytes.
+    unsafe fn take(&mut self, size: usize) -> *mut Block {
+        let (mut fl, sl) = mapping_search(size);
+        if fl >= FL_COUNT {
+            return null_mut();
+        }
+        let mut bits = self.sl_bitmap[fl] & (!0 << sl);
+        if bits == 0 {
+            let fls = self.fl_bitmap & (!0usize).checked_shl(fl as u32 + 1).unwrap_or(0);
+            if fls == 0 {
+                return null_mut();
+            }
+            fl = fls.trailing_zeros() as usize;
+            bits = self.sl_bitmap[fl];
+        }
+        let block = self.heads[fl][bits.trailing_zeros() as usize];
+        self.remove(block);
+        block
+    }
+
+    // Mark `block` free, merge it with its free neighbors and file it.
+    unsafe fn release(&mut self, mut block: *mut Block) {
+        (*block).size |= FREE;
+        let next = Block::next_phys(block);
+        if Block::is_free(next) {
+            self.remove(next);
+            (*block).size += HEADER + Block::size(next);
+            (*Block::next_phys(block)).prev_phys = block;
+        }
+        let prev = (*block).prev_phys;
+        if !prev.is_null() && Block::is_free(prev) {
+            self.remove(prev);
+            (*prev).size += HEADER + Block::size(block);
+            (*Block::next_phys(prev)).prev_phys = prev;
+            block = prev;
+        }
+        self.insert(block);
+    }
+
+    // Shrink the used `block` to `size` bytes, freeing the remainder if it
+    // is big enough to hold a block of its own.
+    unsafe fn split(&mut self, block: *mut Block, size: usize) {
+        let rest_size = Block::size(block) - size;
+        if rest_size >= HEADER + MIN_SIZE {
+            let rest = (Block::payload(block) + size) as *mut Block;
+            (*rest).prev_phys = block;
+            (*rest).size = rest_size - HEADER;
+            (*Block::next_phys(rest)).prev_phys = rest;
+            (*block).size = size;
+            self.release(rest);
+        }
+    }
+
+    // Move the end of the heap up to `end`, turning the old sentinel into
+    // a free block which covers the new memory.
+    unsafe fn extend(&mut self, end: usize) -> bool {
+        let old = self.sentinel;
+        let end = end & !(ALIGN - 1);
+        if old.is_null() || end < old as usize + 2 * HEADER + MIN_SIZE {
+            return false;
+        }
+        let sentinel = (end - HEADER) as *mut Block;
+        (*old).size = sentinel as usize - Block::payload(old);
+        (*sentinel).prev_phys = old;
+        (*sentinel).size = 0;
+        self.sentinel = sentinel;
+        self.end = end;
+        self.release(old);
+        true
+    }
+
+    // Grow the heap by enough to hold a free block of `size` bytes, short
+    // of the limit.  Finding a block rounds its size up to the next list,
+    // so this leaves room for that too.
+    unsafe fn grow_for(&mut self, size: usize) -> bool {
+        let needed = size
+            .saturating_add(size >> SL_LOG2)
+            .saturating_add(2 * HEADER + MIN_SIZE)
+            .max(GROW_SIZE);
+        self.extend(self.end.saturating_add(needed).min(self.limit))
+    }
+
+    unsafe fn malloc(&mut self, size: usize) -> *mut u8 {
+        if !self.initialized {
+            self.init();
+        }
+        let size = match size.checked_add(ALIGN - 1) {
+            Some(size) => (size & !(ALIGN - 1)).max(MIN_SIZE),
+            None => return null_mut(),
+        };
+        let mut block = self.take(size);
+        if block.is_null() && self.grow_for(size) {
+            block = self.take(size);
+        }
+        if block.is_null() {
+            return null_mut();
+        }
+        (*block).size &= !FREE;
+        self.split(block, size);
+        Block::payload(block) as *mut u8
+    }
+
+    // Carve a used block of `size` bytes out of the untouched memory past
+    // the end of the heap.  The block is never released, so its memory is
+    // never handed out again.
+    unsafe fn fresh(&mut self, size: usize) -> *mut u8 {
+        if !self.initialized {
+            self.init();
+        }
+        let block = self.sentinel;
+        if block.is_null() {
+            return null_mut();
+        }
+        // The old sentinel's header was written, but its payload starts at
+        // the end of the heap.
+        let end = match size
+            .checked_add(ALIGN - 1 + HEADER)
+            .and_then(|size| Block::payload(block).checked_add(size & !(ALIGN - 1)))
+        {
+            Some(end) if end <= self.limit => end,
+            _ => return null_mut(),
+        };
+        let sentinel = (end - HEADER) as *mut Block;
+        (*block).size = sentinel as usize - Block::payload(block);
+        (*sentinel).prev_phys = block;
+        (*sentinel).size = 0;
+        self.sentinel = sentinel;
+        self.end = end;
+        Block::payload(block) as *mut u8
+    }
+
+    // Resize the allocation at `ptr` in place, if there is room.
+    unsafe fn resize(&mut self, ptr: *mut u8, size: usize) -> bool {
+        let block = Block::from_payload(ptr);
+        let size = align_up(size.max(MIN_SIZE), ALIGN);
+        if size > Block::size(block) {
+            let next = Block::next_phys(block);
+            if !Block::is_free(next) || Block::size(block) + HEADER + Block::size(next) < size {
+                return false;
+            }
+            self.remove(next);
+            (*block).size += HEADER + Block::size(next);
+            (*Block::next_phys(block)).prev_phys = block;
+        }
+        self.split(block, size);
+        true
+    }
+}
+
+pub(crate) struct Tlsf(UnsafeCell<State>);
+
+// SAFETY: single threaded environment
+unsafe impl Sync for Tlsf {}
+
+impl Tlsf {
+    pub const fn new(start: usize, end: usize) -> Self {
+        Tlsf(UnsafeCell::new(State {
+            initialized: false,
+            start,
+            end: start,
+            limit: end,
+            sentinel: null_mut(),
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            heads: [[null_mut(); SL_COUNT]; FL_COUNT],
+        }))
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn state(&self) -> &mut State {
+        &mut *self.0.get()
+    }
+}
+
+unsafe impl GlobalAlloc for Tlsf {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let state = self.state();
+        let ptr = if layout.align() <= ALIGN {
+            state.malloc(layout.size())
+        } else {
+            // Over-allocate, and keep the start of the allocation in the word
+            // before the aligned pointer.
+            let ptr = match layout.size().checked_add(layout.align()) {
+                Some(size) => state.malloc(size),
+                None => null_mut(),
+            };
+            if ptr.is_null() {
+                ptr
+            } else {
+                let aligned = align_up(ptr as usize + 1, layout.align()) as *mut usize;
+                *aligned.sub(1) = ptr as usize;
+                aligned as *mut u8
+            }
+        };
+        if ptr.is_null() {
+            out_of_memory(layout)
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let ptr = if layout.align() <= ALIGN {
+            ptr
+        } else {
+            *(ptr as *mut usize).sub(1) as *mut u8
+        };
+        self.state().release(Block::from_payload(ptr));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if layout.align() <= ALIGN && self.state().resize(ptr, new_size) {
+            return ptr;
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        self.dealloc(ptr, layout);
+        new_ptr
+    }
+}
+
+impl Fresh for Tlsf {
+    unsafe fn alloc_fresh(&self, layout: Layout) -> *mut u8 {
+        let state = self.state();
+        let ptr = if layout.align() <= ALIGN {
+            state.fresh(layout.size())
+        } else {
+            match layout.size().checked_add(layout.align()) {
+                Some(size) => state.fresh(size),
+                None => null_mut(),
+            }
+        };
+        if ptr.is_null() {
+            out_of_memory(layout)
+        }
+        align_up(ptr as usize, layout.align()) as *mut u8
+    }
+}
+
+impl Grow for Tlsf {
+    unsafe fn grow(&self, end: usize) {
+        // The heap reaches the new memory as it grows.
+        let state = self.state();
+        state.limit = end;
+        if state.sentinel.is_null() {
+            state.initialized = false;
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// BLAKE2s is built from 32-bit additions, XORs and rotations, which RV32IM
+// executes directly, so the portable implementation is used as-is.  The
+// circuit has no BLAKE2s accelerator, so this is software hashing, at the
+// cost of the guest cycles it runs.
+pub use risc0_zkp::core::blake2s::{compress, digest, Blake2s, BLOCK_SIZE, DIGEST_SIZE};
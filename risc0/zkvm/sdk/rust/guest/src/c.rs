@@ -0,0 +1,126 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::{ffi::c_void, slice};
+
+use _alloc::vec::Vec;
+use risc0_zkvm::platform::WORD_SIZE;
+use serde::{ser::SerializeTuple, Serialize, Serializer};
+
+use crate::{env, sha};
+
+/// Use the C function defined with `RISC0_ENTRY` as the entrypoint.
+///
+/// The C functions declared in `risc0_guest.h` are implemented by this crate;
+/// build the C sources with `risc0_build::compile_c` from the guest crate's
+/// build script.
+///
+/// # Example
+///
+/// ```ignore
+/// #![no_main]
+///
+/// risc0_zkvm_guest::entry_c!();
+/// ```
+#[macro_export]
+macro_rules! entry_c {
+    () => {
+        #[no_mangle]
+        fn __main() {
+            extern "C" {
+                fn risc0_c_main();
+            }
+            unsafe { risc0_c_main() }
+        }
+    };
+}
+
+// The `len` bytes at `buf`, which may be null if `len` is zero.
+unsafe fn bytes<'a>(buf: *const c_void, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(buf as *const u8, len)
+    }
+}
+
+// Serializes as the bare words, with no length prefix.
+struct Words(Vec<u32>);
+
+impl Words {
+    unsafe fn new(buf: *const c_void, len: usize) -> Self {
+        let mut words = Vec::new();
+        words.resize((len + WORD_SIZE - 1) / WORD_SIZE, 0u32);
+        let dest: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+        dest[..len].copy_from_slice(bytes(buf, len));
+        Words(words)
+    }
+}
+
+impl Serialize for Words {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for word in &self.0 {
+            tuple.serialize_element(word)?;
+        }
+        tuple.end()
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn risc0_read(buf: *mut c_void, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let dest = slice::from_raw_parts_mut(buf as *mut u8, len);
+    for chunk in dest.chunks_mut(WORD_SIZE) {
+        let word: u32 = env::read();
+        chunk.copy_from_slice(&word.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+#[cfg(not(feature = "no-io"))]
+#[no_mangle]
+unsafe extern "C" fn risc0_write(buf: *const c_void, len: usize) {
+    env::write(&Words::new(buf, len));
+}
+
+#[no_mangle]
+unsafe extern "C" fn risc0_commit(buf: *const c_void, len: usize) {
+    env::commit(&Words::new(buf, len));
+}
+
+#[cfg(not(feature = "no-io"))]
+#[no_mangle]
+unsafe extern "C" fn risc0_sendrecv(
+    channel: u32,
+    buf: *const c_void,
+    len: usize,
+    out_len: *mut usize,
+) -> *const c_void {
+    let reply = env::send_recv(channel, bytes(buf, len));
+    *out_len = reply.len();
+    reply.as_ptr() as *const c_void
+}
+
+#[no_mangle]
+unsafe extern "C" fn risc0_sha256(buf: *const c_void, len: usize, digest: *mut u32) {
+    let result = sha::digest_u8_slice(bytes(buf, len));
+    slice::from_raw_parts_mut(digest, result.get().len()).copy_from_slice(result.get());
+}
+
+#[no_mangle]
+extern "C" fn risc0_assert(cond: i32, code: u32) {
+    env::assert(cond != 0, code)
+}
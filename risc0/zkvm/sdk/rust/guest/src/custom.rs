@@ -0,0 +1,58 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Custom instructions added to the circuit.
+//!
+//! A circuit generated with custom ops (see
+//! `risc0/zkvm/circuit/custom_op.h`) decodes R-type instructions with the
+//! RISC-V custom-0 opcode to them, with func3 selecting the op.  These
+//! functions issue those instructions.  They only run on a prover whose
+//! circuit registers the op, since the standard circuit decodes no
+//! instruction with this opcode.
+
+macro_rules! insn {
+    ($func3:literal, $rs1:expr, $rs2:expr) => {{
+        #[cfg(target_arch = "riscv32")]
+        {
+            let rd: u32;
+            unsafe {
+                core::arch::asm!(
+                    concat!(".insn r 0x0b, ", $func3, ", 0, {rd}, {rs1}, {rs2}"),
+                    rd = out(reg) rd,
+                    rs1 = in(reg) $rs1,
+                    rs2 = in(reg) $rs2,
+                    options(nomem, nostack, pure),
+                );
+            }
+            rd
+        }
+        #[cfg(not(target_arch = "riscv32"))]
+        {
+            let _ = ($rs1, $rs2);
+            unimplemented!("Custom instructions only run in the zkVM")
+        }
+    }};
+}
+
+/// Run custom op 0 on `rs1` and `rs2`, returning its result.
+#[inline(always)]
+pub fn op0(rs1: u32, rs2: u32) -> u32 {
+    insn!(0, rs1, rs2)
+}
+
+/// Run custom op 1 on `rs1` and `rs2`, returning its result.
+#[inline(always)]
+pub fn op1(rs1: u32, rs2: u32) -> u32 {
+    insn!(1, rs1, rs2)
+}
@@ -14,22 +14,32 @@
 
 use core::{cell::UnsafeCell, mem::MaybeUninit, slice};
 
-use risc0_zkp::core::sha::Digest;
+use risc0_zkp::core::{keccak_cpu::Keccak256, sha::Digest};
 use risc0_zkvm::{
+    disclose::{self, Disclosable},
     platform::{
-        io::{IoDescriptor, GPIO_COMMIT, SENDRECV_CHANNEL_INITIAL_INPUT, SENDRECV_CHANNEL_STDOUT},
+        io::{
+            IoDescriptor, GPIO, JOURNAL_ASSERT_FAILED, SECRET_NOT_FOUND, SECRET_OK,
+            SENDRECV_CHANNEL_INITIAL_INPUT, SENDRECV_CHANNEL_SECRET, SENDRECV_CHANNEL_STDOUT,
+        },
         memory, WORD_SIZE,
     },
     serde::{Deserializer, Serializer, Slice},
+    shared::IoType,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{align_up, io::host_sendrecv, memory_barrier, sha};
+#[cfg(not(feature = "no-io"))]
+use crate::io::host_sendrecv;
+use crate::{align_up, gpio_write, halt, memory_barrier, sha};
 
 struct Env {
+    #[cfg(not(feature = "no-io"))]
     output: Serializer<Slice<'static>>,
     commit: Serializer<Slice<'static>>,
     commit_len: usize,
+    // The address of the result block passed to __start.
+    result: usize,
     initial_input_reader: Option<Reader>,
 }
 
@@ -73,31 +83,66 @@ impl<T> Once<T> {
 
 static ENV: Once<Env> = Once::new();
 
-pub(crate) fn init() {
-    ENV.init(Env::new());
+pub(crate) fn init(result: *mut usize) {
+    ENV.init(Env::new(result));
 }
 
 pub(crate) fn finalize(result: *mut usize) {
     ENV.get().finalize(result);
 }
 
+#[cfg(not(feature = "no-io"))]
 /// Exchanges data with the host, returning the data from the host
 /// as a slice of bytes.
 pub fn send_recv(channel: u32, buf: &[u8]) -> &'static [u8] {
     ENV.get().send_recv(channel, buf)
 }
 
+#[cfg(not(feature = "no-io"))]
 /// Exchanges data with the host, returning the data from the host as
 /// a slice of words and the length in bytes.
 pub fn send_recv_as_u32(channel: u32, buf: &[u8]) -> (&'static [u32], usize) {
     ENV.get().send_recv_as_u32(channel, buf)
 }
 
+#[cfg(not(feature = "no-io"))]
+/// Returns a [Reader] for the input stream the host supplies on the given
+/// channel, independently of the initial input read by [read].
+pub fn reader(channel: u32) -> Reader {
+    let (words, _) = send_recv_as_u32(channel, &[]);
+    Reader(Deserializer::new(words))
+}
+
+#[cfg(not(feature = "no-io"))]
+/// Read the confidential input which the host holds sealed under `name`.
+///
+/// The host unseals the input only when it is requested.  Like any other
+/// private data, the plaintext is not revealed by the receipt unless the
+/// guest commits it.
+///
+/// # Panics
+///
+/// Panics if the host has no input named `name`, or fails to unseal it.
+pub fn read_secret(name: &str) -> &'static [u8] {
+    let response = send_recv(SENDRECV_CHANNEL_SECRET, name.as_bytes());
+    assert!(
+        response.len() >= WORD_SIZE,
+        "Malformed secret input response"
+    );
+    let (status, plaintext) = response.split_at(WORD_SIZE);
+    match u32::from_le_bytes(status.try_into().unwrap()) {
+        SECRET_OK => plaintext,
+        SECRET_NOT_FOUND => panic!("No secret input named {name}"),
+        _ => panic!("Failed to unseal secret input {name}"),
+    }
+}
+
 /// Read private data from the host.
 pub fn read<T: Deserialize<'static>>() -> T {
     ENV.get().read()
 }
 
+#[cfg(not(feature = "no-io"))]
 /// Write private data to the host.
 pub fn write<T: Serialize>(data: &T) {
     ENV.get().write(data);
@@ -108,25 +153,82 @@ pub fn commit<T: Serialize>(data: &T) {
     ENV.get().commit(data);
 }
 
+/// Commit public data to the journal, prefixed with the `version` of its
+/// format, so that the host can decode journals of older guests with
+/// [risc0_zkvm::versioned::Migrations].
+///
+/// This must be the only data committed to the journal.
+pub fn commit_versioned<T: Serialize>(version: u32, data: &T) {
+    commit(&(version, data));
+}
+
+/// Read private data written by the host with
+/// `risc0_zkvm::shared::to_vec`, panicking if the host's build of `T` has a
+/// different layout from the guest's; see [risc0_zkvm::shared].
+pub fn read_shared<T: IoType>() -> T {
+    let (digest, data): (Digest, T) = read();
+    if digest != T::SCHEMA_DIGEST {
+        panic!(
+            "Host wrote input with schema {digest}, expected {}",
+            T::SCHEMA_DIGEST
+        );
+    }
+    data
+}
+
+/// Commit public data to the journal, prefixed with its schema digest, so
+/// that the host can decode it with [risc0_zkvm::shared::from_slice].
+///
+/// This must be the only data committed to the journal.
+pub fn commit_shared<T: IoType>(data: &T) {
+    commit(&(T::SCHEMA_DIGEST, data));
+}
+
+/// Commit the root of a Merkle tree over the fields of `value`, whose leaves
+/// are salted from `seed`, so that the host can later disclose any subset of
+/// the fields; see [risc0_zkvm::disclose].
+///
+/// The seed must be private input which is unpredictable to verifiers, and
+/// this must be the only data committed to the journal.
+pub fn commit_disclosable<T: Disclosable>(value: &T, seed: &Digest) {
+    commit(&disclose::root(&sha::Impl {}, value, seed));
+}
+
+/// Halt immediately with the failure `code` if `cond` is false.
+///
+/// Anything committed so far is discarded, and the journal instead records
+/// the failure code, which the host reads with `Receipt::get_assert_failure`.
+/// Since the guest stops here, rejecting bad input this way costs only the
+/// cycles run before the check.
+pub fn assert(cond: bool, code: u32) {
+    if !cond {
+        ENV.get().fail(code)
+    }
+}
+
 impl Env {
-    fn new() -> Self {
+    fn new(result: *mut usize) -> Self {
         Env {
             commit: Serializer::new(Slice::new(unsafe {
                 slice::from_raw_parts_mut(memory::COMMIT.start() as _, memory::COMMIT.len_words())
             })),
+            #[cfg(not(feature = "no-io"))]
             output: Serializer::new(Slice::new(unsafe {
                 slice::from_raw_parts_mut(memory::OUTPUT.start() as _, memory::OUTPUT.len_words())
             })),
 
             commit_len: 0,
+            result: result as usize,
             initial_input_reader: None,
         }
     }
 
+    #[cfg(not(feature = "no-io"))]
     pub fn send_recv_as_u32(&mut self, channel: u32, buf: &[u8]) -> (&'static [u32], usize) {
         host_sendrecv(channel, buf)
     }
 
+    #[cfg(not(feature = "no-io"))]
     pub fn send_recv(&mut self, channel: u32, buf: &[u8]) -> &'static [u8] {
         let (data, bytes) = self.send_recv_as_u32(channel, buf);
         &bytemuck::cast_slice(data)[..bytes]
@@ -134,7 +236,10 @@ impl Env {
 
     fn initial_input(&mut self) -> &mut Reader {
         if !self.initial_input_reader.is_some() {
+            #[cfg(not(feature = "no-io"))]
             let (words, _) = self.send_recv_as_u32(SENDRECV_CHANNEL_INITIAL_INPUT, &[]);
+            #[cfg(feature = "no-io")]
+            let words = crate::io::initial_input();
             self.initial_input_reader = Some(Reader(Deserializer::new(words)))
         }
         self.initial_input_reader.as_mut().unwrap()
@@ -144,6 +249,7 @@ impl Env {
         self.initial_input().read()
     }
 
+    #[cfg(not(feature = "no-io"))]
     fn write<T: Serialize>(&mut self, data: &T) {
         data.serialize(&mut self.output).unwrap();
         let buf = self.output.release().unwrap();
@@ -155,9 +261,22 @@ impl Env {
         let buf = self.commit.release().unwrap();
         self.commit_len += buf.len();
         // Copy to stdout
+        #[cfg(not(feature = "no-io"))]
         self.send_recv(SENDRECV_CHANNEL_STDOUT, bytemuck::cast_slice(buf));
     }
 
+    fn fail(&mut self, code: u32) -> ! {
+        let slice: &mut [u32] = unsafe {
+            slice::from_raw_parts_mut(memory::COMMIT.start() as _, memory::COMMIT.len_words())
+        };
+        slice[..2].copy_from_slice(&[JOURNAL_ASSERT_FAILED, code]);
+        self.commit_len = 2;
+        let result = self.result as *mut usize;
+        self.finalize(result);
+        // SAFETY: The result block was just written by finalize.
+        unsafe { halt(result) }
+    }
+
     fn finalize(&mut self, result: *mut usize) {
         let len_words = self.commit_len;
         let len_bytes = len_words * WORD_SIZE;
@@ -166,18 +285,17 @@ impl Env {
         };
 
         // Write the full data out to the host
-        unsafe {
-            let desc = IoDescriptor {
-                size: len_bytes as u32,
-                addr: slice.as_ptr() as u32,
-            };
-            let ptr: *const IoDescriptor = &desc;
-            memory_barrier(ptr);
-            GPIO_COMMIT.as_ptr().write_volatile(&desc);
-        }
+        let desc = IoDescriptor {
+            size: len_bytes as u32,
+            addr: slice.as_ptr() as u32,
+        };
+        let ptr: *const IoDescriptor = &desc;
+        memory_barrier(ptr);
+        gpio_write(&GPIO.commit, ptr);
 
         // If the total proof message is small (<= 32 bytes), return it directly
-        // from the proof, otherwise SHA it and return the hash.
+        // from the proof, otherwise hash it and return the hash: with SHA-256,
+        // or with Keccak-256 for the Keccak-256 hash suite.
         if len_words <= 8 {
             for i in 0..len_words {
                 unsafe {
@@ -189,6 +307,14 @@ impl Env {
             for i in len_words..8 {
                 unsafe { result.add(i).write_volatile(0) };
             }
+        } else if cfg!(feature = "keccak-journal") {
+            // There's no Keccak accelerator, so this is hashed in software.
+            let mut hasher = Keccak256::new();
+            hasher.update(bytemuck::cast_slice(&slice[..len_words]));
+            let digest = Digest::from_bytes(hasher.finalize());
+            for (i, word) in digest.get().iter().enumerate() {
+                unsafe { result.add(i).write_volatile(*word as usize) };
+            }
         } else {
             let cap = sha::compute_capacity_needed(len_bytes);
             let mut slice = &mut slice[..cap];
@@ -0,0 +1,66 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::vec::Vec;
+
+pub use risc0_zkp::field::baby_bear::{Elem, ExtElem};
+use risc0_zkp::field::Elem as _;
+use risc0_zkvm::platform::io::{
+    FIELD_MALFORMED, FIELD_OK, FIELD_OP_INV, FIELD_UNKNOWN_OP, SENDRECV_CHANNEL_FIELD,
+};
+
+use crate::io::host_sendrecv;
+
+// Sends a field operation and its operands to the host, returning the
+// host's untrusted results.  Callers must check the results.
+pub(crate) fn field_op(op: u32, operands: &[Elem]) -> Vec<Elem> {
+    let mut request = Vec::<u32>::with_capacity(operands.len() + 1);
+    request.push(op);
+    request.extend(operands.iter().map(u32::from));
+    let (words, nbytes) = host_sendrecv(SENDRECV_CHANNEL_FIELD, bytemuck::cast_slice(&request));
+    match words.first() {
+        Some(&FIELD_OK) => {}
+        Some(&FIELD_MALFORMED) => panic!("Host rejected the operands of field operation {op}"),
+        Some(&FIELD_UNKNOWN_OP) => panic!("Host does not support field operation {op}"),
+        _ => panic!("Malformed field operation response"),
+    }
+    assert_eq!(nbytes, (operands.len() + 1) * core::mem::size_of::<Elem>());
+    // Reduce each word, since the host is not trusted to return canonical
+    // elements.
+    words[1..].iter().map(|x| Elem::from(*x)).collect()
+}
+
+/// Computes the multiplicative inverse of each element, mapping zero to
+/// zero.
+///
+/// The inverses are computed by the host and each one is checked with a
+/// single multiplication, which is much cheaper than exponentiating in the
+/// guest.
+pub fn batch_inv(elems: &[Elem]) -> Vec<Elem> {
+    let result = field_op(FIELD_OP_INV, elems);
+    for (x, inv) in elems.iter().zip(result.iter()) {
+        let ok = if *x == Elem::ZERO {
+            *inv == Elem::ZERO
+        } else {
+            *x * *inv == Elem::ONE
+        };
+        assert!(ok, "Host returned an invalid field inverse");
+    }
+    result
+}
+
+/// Computes the multiplicative inverse of `elem`, mapping zero to zero.
+pub fn inv(elem: Elem) -> Elem {
+    batch_inv(&[elem])[0]
+}
@@ -0,0 +1,72 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::{alloc::Layout, panic::PanicInfo};
+
+/// The number of bytes at the end of the heap held back for hooks, so that
+/// an alloc-error hook can still allocate.  Hooks run with the reserve
+/// released and may not allocate more than this once the heap is exhausted.
+pub const HOOK_HEAP_SIZE: usize = 64 * 1024;
+
+static mut PANIC_HOOK: Option<fn(&PanicInfo)> = None;
+static mut ALLOC_ERROR_HOOK: Option<fn(Layout)> = None;
+static mut IN_HOOK: bool = false;
+
+/// Register a hook to run when the guest panics, before the fault is
+/// reported to the host, e.g. to emit partial diagnostics or scrub secrets.
+///
+/// Hooks run at most once per execution: if the hook itself panics or runs
+/// out of memory, the guest faults immediately.
+pub fn set_panic_hook(hook: fn(&PanicInfo)) {
+    unsafe { PANIC_HOOK = Some(hook) };
+    #[cfg(feature = "std")]
+    std::panic::set_hook(_alloc::boxed::Box::new(|info| run_panic_hook(info)));
+}
+
+/// Register a hook to run when an allocation fails, before the fault is
+/// reported to the host.  The same restrictions as for [set_panic_hook]
+/// apply, and the hook may allocate up to [HOOK_HEAP_SIZE] bytes.
+pub fn set_alloc_error_hook(hook: fn(Layout)) {
+    unsafe { ALLOC_ERROR_HOOK = Some(hook) };
+}
+
+// Runs `f` unless a hook is already running, giving it the heap reserve.
+fn run_once(f: impl FnOnce()) {
+    // SAFETY: the guest is single threaded.
+    unsafe {
+        if IN_HOOK {
+            return;
+        }
+        IN_HOOK = true;
+    }
+    #[cfg(not(feature = "std"))]
+    crate::alloc::release_reserve();
+    f();
+}
+
+pub(crate) fn run_panic_hook(info: &PanicInfo) {
+    if let Some(hook) = unsafe { PANIC_HOOK } {
+        run_once(|| hook(info));
+    }
+}
+
+#[allow(dead_code)]
+pub(crate) fn run_alloc_error_hook(layout: Layout) {
+    if let Some(hook) = unsafe { ALLOC_ERROR_HOOK } {
+        run_once(|| hook(layout));
+    }
+}
@@ -1,13 +1,19 @@
-use core::cell::UnsafeCell;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{compiler_fence, Ordering},
+};
 
+use risc0_zkp::core::sha::DIGEST_WORDS;
 use risc0_zkvm::platform::{
     io::{
-        IoDescriptor, GPIO_COMMIT, GPIO_SENDRECV_ADDR, GPIO_SENDRECV_CHANNEL, GPIO_SENDRECV_SIZE,
-        SENDRECV_CHANNEL_INITIAL_INPUT, SENDRECV_CHANNEL_STDOUT,
+        IoDescriptor, GPIO, SENDRECV_CHANNEL_CONTINUE, SENDRECV_CHANNEL_INITIAL_INPUT,
+        SENDRECV_CHANNEL_STDOUT, SENDRECV_CHUNK_LAST, SENDRECV_SPLIT,
     },
     memory, WORD_SIZE,
 };
 
+use crate::{alloc_fresh, gpio_write, sha};
+
 // Current offset in number of words from the INPUT memory region that
 // we're reading,
 static mut READ_PTR: UnsafeCell<usize> = UnsafeCell::new(0);
@@ -19,21 +25,30 @@ static mut READ_PTR: UnsafeCell<usize> = UnsafeCell::new(0);
 /// and a size in bytes of the returned data.  The size in bytes might
 /// not match the length of the returned slice * WORD_SIZE in the case
 /// that the returned buffer does not fall on a word boundry.
+///
+/// Responses too large for the remaining input space are delivered in
+/// chunks and reassembled on the heap; the reassembled response is
+/// checked against a digest sent by the host, so callers always see one
+/// contiguous slice.
+#[cfg(not(feature = "no-io"))]
 pub fn host_sendrecv(channel: u32, buf: &[u8]) -> (&'static [u32], usize) {
     // SAFETY: Single threaded, so it's ok to borrow READ_PTR while in this routine.
     let read_ptr: &mut usize = unsafe { &mut *READ_PTR.get() };
 
     // Tell the host to execute the sendrecv.
-    unsafe {
-        GPIO_SENDRECV_CHANNEL.as_ptr().write_volatile(channel);
-        GPIO_SENDRECV_SIZE.as_ptr().write_volatile(buf.len());
-        GPIO_SENDRECV_ADDR.as_ptr().write_volatile(buf.as_ptr());
-    }
+    gpio_write(&GPIO.sendrecv_channel, channel);
+    gpio_write(&GPIO.sendrecv_size, buf.len());
+    gpio_write(&GPIO.sendrecv_addr, buf.as_ptr());
 
     // Receive
     let read_start: *const u32 = memory::INPUT.start() as _;
-    let response_nbytes = unsafe { read_start.add(*read_ptr).read_volatile() } as usize;
+    let header = unsafe { read_start.add(*read_ptr).read_volatile() };
     *read_ptr += 1;
+    if header & SENDRECV_SPLIT != 0 {
+        let response_nbytes = (header & !SENDRECV_SPLIT) as usize;
+        return (recv_split(response_nbytes), response_nbytes);
+    }
+    let response_nbytes = header as usize;
     let response_nwords = (response_nbytes + WORD_SIZE - 1) / WORD_SIZE;
 
     assert!(*read_ptr + response_nwords < memory::INPUT.len_words());
@@ -44,3 +59,70 @@ pub fn host_sendrecv(channel: u32, buf: &[u8]) -> (&'static [u32], usize) {
 
     (response_data, response_nbytes)
 }
+
+// Fetches the chunks of a split response directly into fresh heap memory,
+// which the host is allowed to write since the guest has never touched it.
+#[cfg(not(feature = "no-io"))]
+fn recv_split(nbytes: usize) -> &'static [u32] {
+    let nwords = (nbytes + WORD_SIZE - 1) / WORD_SIZE;
+    let data = alloc_fresh::<u32>(nwords);
+    let mut offset = 0;
+    let expected = loop {
+        let header = alloc_fresh::<u32>(1 + DIGEST_WORDS);
+        // SAFETY: `offset` stays within `data`, since the host never sends
+        // more than the total length.
+        let dest = unsafe { data.add(offset / WORD_SIZE) };
+        let request = [header as u32, dest as u32];
+        gpio_write(&GPIO.sendrecv_channel, SENDRECV_CHANNEL_CONTINUE);
+        gpio_write(&GPIO.sendrecv_size, core::mem::size_of_val(&request));
+        gpio_write(&GPIO.sendrecv_addr, request.as_ptr() as _);
+        compiler_fence(Ordering::SeqCst);
+
+        // SAFETY: The host has just written the header.
+        let len = unsafe { header.read_volatile() };
+        let chunk_nbytes = (len & !SENDRECV_CHUNK_LAST) as usize;
+        offset += chunk_nbytes;
+        assert!(offset <= nbytes, "Split response overran its length");
+        if len & SENDRECV_CHUNK_LAST != 0 {
+            // SAFETY: The final header is followed by the digest.
+            break unsafe { core::slice::from_raw_parts(header, 1 + DIGEST_WORDS) };
+        }
+        assert!(
+            chunk_nbytes > 0 && chunk_nbytes % WORD_SIZE == 0,
+            "Bad split response chunk"
+        );
+    };
+    assert_eq!(offset, nbytes, "Split response is truncated");
+    // SAFETY: Every word up to `nwords` has been written by the host.
+    let data = unsafe { core::slice::from_raw_parts(data, nwords) };
+
+    let bytes: &[u8] = bytemuck::cast_slice(data);
+    assert_eq!(
+        sha::digest_u8_slice(&bytes[..nbytes]).as_slice(),
+        &expected[1..],
+        "Split response digest mismatch"
+    );
+    data
+}
+
+/// Fetches the initial input, the only request a guest built with the
+/// `no-io` feature makes of the host.  The input must fit within the INPUT
+/// region, since the split response path is not compiled in.
+#[cfg(feature = "no-io")]
+pub(crate) fn initial_input() -> &'static [u32] {
+    let buf: &[u8] = &[];
+    gpio_write(&GPIO.sendrecv_channel, SENDRECV_CHANNEL_INITIAL_INPUT);
+    gpio_write(&GPIO.sendrecv_size, 0);
+    gpio_write(&GPIO.sendrecv_addr, buf.as_ptr());
+
+    let read_start: *const u32 = memory::INPUT.start() as _;
+    let header = unsafe { read_start.read_volatile() };
+    assert!(
+        header & SENDRECV_SPLIT == 0,
+        "Initial input too large for a no-io guest"
+    );
+    let nwords = (header as usize + WORD_SIZE - 1) / WORD_SIZE;
+    assert!(1 + nwords < memory::INPUT.len_words());
+    // SAFETY: This region is in the INPUT region and we just did a bounds check.
+    unsafe { core::slice::from_raw_parts(read_start.add(1), nwords) }
+}
@@ -0,0 +1,56 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::vec::Vec;
+
+use risc0_zkp::core::sha::Digest;
+use risc0_zkvm::{kv::KvProof, platform::io::SENDRECV_CHANNEL_KV, serde::from_slice};
+
+use crate::{env, sha};
+
+/// A key-value store served by the host, identified by its root [Digest].
+///
+/// Every value returned is checked against the root, so the guest only needs
+/// to trust the root itself; typically it commits the root to the journal so
+/// that verifiers know which snapshot of the store was read.
+pub struct Kv {
+    root: Digest,
+}
+
+impl Kv {
+    /// Open the store with the given root.
+    pub fn new(root: Digest) -> Self {
+        Kv { root }
+    }
+
+    /// The root of this store.
+    pub fn root(&self) -> &Digest {
+        &self.root
+    }
+
+    /// Look up `key`, panicking if the host's answer does not match the root.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let (words, _) = env::send_recv_as_u32(SENDRECV_CHANNEL_KV, key);
+        let proof: KvProof = from_slice(words).unwrap();
+        proof
+            .verify(&sha::Impl {}, &self.root, key)
+            .unwrap()
+            .map(|value| value.to_vec())
+    }
+}
+
+/// Look up `key` in the store with the given root.
+pub fn kv_get(root: &Digest, key: &[u8]) -> Option<Vec<u8>> {
+    Kv::new(*root).get(key)
+}
@@ -22,6 +22,9 @@
 
 extern crate alloc as _alloc;
 
+#[cfg(all(feature = "no-io", feature = "heap-leak-check"))]
+compile_error!("heap-leak-check reports over stderr, which the no-io feature removes");
+
 #[cfg(not(feature = "std"))]
 mod alloc;
 
@@ -31,11 +34,52 @@ pub mod env;
 /// Functions for computing SHA-256 hashes.
 pub mod sha;
 
+/// Functions for computing BLAKE2s-256 hashes in software.
+pub mod blake2s;
+
+/// X25519 key agreement, computed in software.
+pub mod x25519;
+
+/// BabyBear field arithmetic with host acceleration.
+#[cfg(not(feature = "no-io"))]
+pub mod field;
+
+/// Number theoretic transforms computed in the guest.
+pub mod ntt;
+
+/// Verification of receipts from within the guest.
+pub mod verify;
+
+/// Verified reads from a key-value store served by the host.
+#[cfg(not(feature = "no-io"))]
+pub mod kv;
+
+/// Verified queries over tables laid out in a key-value store.
+#[cfg(not(feature = "no-io"))]
+pub mod table;
+
 /// Functions for handling input and output
 pub mod io;
 
+/// Leveled logging to the host.
+pub mod log;
+
+/// Custom instructions added to the circuit.
+pub mod custom;
+
+/// The C API declared in `risc0_guest.h`.
+pub mod c;
+
+/// Hooks which run before a panic or allocation failure is reported.
+pub mod hook;
+
+pub use hook::{set_alloc_error_hook, set_panic_hook};
+pub use risc0_zkvm::{disclosable, disclose::Disclosable};
+
 use core::{arch::asm, mem, panic::PanicInfo, ptr};
 
+use risc0_zkvm::platform::io::Gpio;
+
 extern "C" {
     fn _fault() -> !;
 }
@@ -43,13 +87,14 @@ extern "C" {
 #[cfg(all(target_arch = "riscv32", not(feature = "std")))]
 #[panic_handler]
 unsafe fn panic_fault(panic_info: &PanicInfo<'static>) -> ! {
-    use risc0_zkvm::platform::io::GPIO_FAULT;
+    use risc0_zkvm::platform::io::GPIO;
 
+    hook::run_panic_hook(panic_info);
     let msg = _alloc::format!("{}\0", panic_info);
     let ptr = msg.as_ptr();
     memory_barrier(ptr);
     // A compliant host should fault when it receives this descriptor.
-    GPIO_FAULT.as_ptr().write_volatile(ptr);
+    GPIO.fault.write(ptr);
 
     // As a fallback for uncompliant hosts, force an unaligned write, which causes a
     // fault within the Risc0 VM.
@@ -58,6 +103,10 @@ unsafe fn panic_fault(panic_info: &PanicInfo<'static>) -> ! {
 
 /// Used for defining a main entrypoint.
 ///
+/// The entrypoint either takes no arguments, or takes a single input which is
+/// read from the host with [env::read] and returns an output which is
+/// committed to the journal with [env::commit].
+///
 /// # Example
 ///
 /// ```
@@ -65,18 +114,50 @@ unsafe fn panic_fault(panic_info: &PanicInfo<'static>) -> ! {
 ///
 /// pub fn main() { }
 /// ```
+///
+/// ```
+/// risc0_zkvm::guest::entry!(main);
+///
+/// pub fn main((a, b): (u64, u64)) -> u64 {
+///     a * b
+/// }
+/// ```
 #[macro_export]
 macro_rules! entry {
     ($path:path) => {
         #[no_mangle]
         fn __main() {
-            // type check the given path
-            let f: fn() = $path;
-            f()
+            $crate::EntryPoint::call($path)
         }
     };
 }
 
+/// A function which can be used as the guest's entrypoint with [entry].
+pub trait EntryPoint<Args> {
+    /// Runs the entrypoint, handling its input and output.
+    fn call(self);
+}
+
+impl<F> EntryPoint<()> for F
+where
+    F: FnOnce(),
+{
+    fn call(self) {
+        self()
+    }
+}
+
+impl<F, T, U> EntryPoint<(T,)> for F
+where
+    F: FnOnce(T) -> U,
+    T: serde::Deserialize<'static>,
+    U: serde::Serialize,
+{
+    fn call(self) {
+        env::commit(&self(env::read()))
+    }
+}
+
 #[cfg(target_arch = "riscv32")]
 #[no_mangle]
 unsafe extern "C" fn __start(result: *mut usize) {
@@ -86,16 +167,75 @@ unsafe extern "C" fn __start(result: *mut usize) {
     }
     ptr::write_bytes(&mut __bss_begin as *mut u8, 0, __bss_size);
 
-    env::init();
+    env::init(result);
 
     extern "Rust" {
         fn __main();
     }
     __main();
 
+    #[cfg(not(feature = "std"))]
+    alloc::report_leaks();
     env::finalize(result);
 }
 
+/// Halt the zkVM with the given result block, as returning from __start
+/// does.
+///
+/// # Safety
+///
+/// `result` must point to a result block written by env::finalize.
+#[cfg(target_arch = "riscv32")]
+pub(crate) unsafe fn halt(result: *mut usize) -> ! {
+    asm!(
+        "lw x1, 0(a0)",
+        "lw x2, 4(a0)",
+        "lw x3, 8(a0)",
+        "lw x4, 12(a0)",
+        "lw x5, 16(a0)",
+        "lw x6, 20(a0)",
+        "lw x7, 24(a0)",
+        "lw x8, 28(a0)",
+        "lw x9, 32(a0)",
+        "ecall",
+        in("a0") result,
+        options(noreturn)
+    )
+}
+
+#[cfg(not(target_arch = "riscv32"))]
+pub(crate) unsafe fn halt(_result: *mut usize) -> ! {
+    unimplemented!("halt is only available inside the zkVM")
+}
+
+// Hands `value` to the host through `reg`.
+#[cfg(target_arch = "riscv32")]
+pub(crate) fn gpio_write<T>(reg: &Gpio<T>, value: T) {
+    reg.write(value)
+}
+
+#[cfg(not(target_arch = "riscv32"))]
+pub(crate) fn gpio_write<T>(_reg: &Gpio<T>, _value: T) {
+    unimplemented!("GPIO registers are only available inside the zkVM")
+}
+
+// Allocates room for `len` values of `T` which the host may write, since
+// the guest has never touched it; see alloc::alloc_fresh.  The memory is
+// never freed.  The heap features only apply to no_std guests, so std
+// guests allocate from the standard library's heap as before.
+pub(crate) fn alloc_fresh<T>(len: usize) -> *mut T {
+    let layout = core::alloc::Layout::array::<T>(len).unwrap();
+    // SAFETY: The pointer is only handed to the host to write into.
+    #[cfg(not(feature = "std"))]
+    let ptr = unsafe { alloc::alloc_fresh(layout) };
+    #[cfg(feature = "std")]
+    let ptr = unsafe { _alloc::alloc::alloc(layout) };
+    if ptr.is_null() {
+        _alloc::alloc::handle_alloc_error(layout)
+    }
+    ptr as *mut T
+}
+
 /// Align the given address `addr` upwards to alignment `align`.
 ///
 /// Requires that `align` is a power of two.
@@ -0,0 +1,150 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logging to the host, with levels which can be compiled out of the guest.
+//!
+//! Each [guest_log!](crate::guest_log) record is sent on a dedicated
+//! channel along with its level and the module which sent it, and the host
+//! decides which records to keep; see
+//! `risc0_zkvm::host::ProverOpts::with_guest_log`.  Records above
+//! [MAX_LEVEL] are removed at compile time, arguments and all, so release
+//! images can be built with one of the `log-max-level-*` features to strip
+//! debug logging entirely:
+//!
+//! ```toml
+//! risc0-zkvm-guest = { version = "0.11", features = ["log-max-level-info"] }
+//! ```
+//!
+//! As with the `log` crate, the most restrictive feature enabled wins.
+//! Without any, every level is compiled in.  The `no-io` feature compiles
+//! out every level.
+
+use risc0_zkvm::platform::io::{
+    LOG_LEVEL_DEBUG, LOG_LEVEL_ERROR, LOG_LEVEL_INFO, LOG_LEVEL_TRACE, LOG_LEVEL_WARN,
+};
+
+/// The level of a guest log record, from most to least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    /// Errors.
+    Error = LOG_LEVEL_ERROR,
+    /// Warnings.
+    Warn = LOG_LEVEL_WARN,
+    /// Informational messages.
+    Info = LOG_LEVEL_INFO,
+    /// Debugging messages.
+    Debug = LOG_LEVEL_DEBUG,
+    /// Very verbose debugging messages.
+    Trace = LOG_LEVEL_TRACE,
+}
+
+/// The most verbose level compiled into this guest, or `None` if logging is
+/// compiled out entirely.
+pub const MAX_LEVEL: Option<Level> = if cfg!(any(feature = "no-io", feature = "log-max-level-off"))
+{
+    None
+} else if cfg!(feature = "log-max-level-error") {
+    Some(Level::Error)
+} else if cfg!(feature = "log-max-level-warn") {
+    Some(Level::Warn)
+} else if cfg!(feature = "log-max-level-info") {
+    Some(Level::Info)
+} else if cfg!(feature = "log-max-level-debug") {
+    Some(Level::Debug)
+} else {
+    Some(Level::Trace)
+};
+
+/// Whether records at `level` are compiled into this guest.
+pub const fn enabled(level: Level) -> bool {
+    match MAX_LEVEL {
+        Some(max) => level as u8 <= max as u8,
+        None => false,
+    }
+}
+
+/// Send a log record to the host.  Use [guest_log!](crate::guest_log)
+/// rather than calling this directly.
+#[doc(hidden)]
+#[cfg(not(feature = "no-io"))]
+pub fn __write(level: Level, module: &str, args: core::fmt::Arguments) {
+    use _alloc::{fmt::format, vec::Vec};
+    use risc0_zkvm::platform::io::SENDRECV_CHANNEL_LOG;
+
+    let message = format(args);
+    // Module paths are far shorter than this, but keep the framing valid.
+    let module = &module.as_bytes()[..module.len().min(u16::MAX as usize)];
+    let mut record = Vec::with_capacity(3 + module.len() + message.len());
+    record.push(level as u8);
+    record.extend_from_slice(&(module.len() as u16).to_le_bytes());
+    record.extend_from_slice(module);
+    record.extend_from_slice(message.as_bytes());
+    crate::env::send_recv(SENDRECV_CHANNEL_LOG, &record);
+}
+
+#[doc(hidden)]
+#[cfg(feature = "no-io")]
+pub fn __write(_level: Level, _module: &str, _args: core::fmt::Arguments) {}
+
+/// Log a formatted message to the host at the given [Level].  Nothing is
+/// evaluated, or compiled into the image, if the level is above
+/// [MAX_LEVEL].
+///
+/// # Example
+///
+/// ```ignore
+/// use risc0_zkvm_guest::{guest_log, log::Level};
+///
+/// guest_log!(Level::Info, "processed {} blocks", 12);
+/// ```
+#[macro_export]
+macro_rules! guest_log {
+    ($level:expr, $($arg:tt)+) => {{
+        const LEVEL: $crate::log::Level = $level;
+        if $crate::log::enabled(LEVEL) {
+            $crate::log::__write(LEVEL, module_path!(), format_args!($($arg)+));
+        }
+    }};
+}
+
+/// Log an error with [guest_log!](crate::guest_log).
+#[macro_export]
+macro_rules! guest_error {
+    ($($arg:tt)+) => { $crate::guest_log!($crate::log::Level::Error, $($arg)+) };
+}
+
+/// Log a warning with [guest_log!](crate::guest_log).
+#[macro_export]
+macro_rules! guest_warn {
+    ($($arg:tt)+) => { $crate::guest_log!($crate::log::Level::Warn, $($arg)+) };
+}
+
+/// Log an informational message with [guest_log!](crate::guest_log).
+#[macro_export]
+macro_rules! guest_info {
+    ($($arg:tt)+) => { $crate::guest_log!($crate::log::Level::Info, $($arg)+) };
+}
+
+/// Log a debugging message with [guest_log!](crate::guest_log).
+#[macro_export]
+macro_rules! guest_debug {
+    ($($arg:tt)+) => { $crate::guest_log!($crate::log::Level::Debug, $($arg)+) };
+}
+
+/// Log a very verbose debugging message with [guest_log!](crate::guest_log).
+#[macro_export]
+macro_rules! guest_trace {
+    ($($arg:tt)+) => { $crate::guest_log!($crate::log::Level::Trace, $($arg)+) };
+}
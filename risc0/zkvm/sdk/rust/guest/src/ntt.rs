@@ -0,0 +1,42 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::vec::Vec;
+
+use risc0_zkp::{
+    core::ntt::{bit_reverse, evaluate_ntt_fp, interpolate_ntt_fp},
+    field::baby_bear::Elem,
+};
+
+/// Evaluates the polynomial with the given coefficients over the roots of
+/// unity of the same size, returning the evaluations in natural order.
+///
+/// The number of coefficients must be a power of two.  The transform runs
+/// in the guest with the butterflies of [risc0_zkp::core::ntt], so it needs
+/// nothing from the host.
+pub fn fwd(coeffs: &[Elem]) -> Vec<Elem> {
+    let mut io = coeffs.to_vec();
+    bit_reverse(&mut io);
+    evaluate_ntt_fp(&mut io, 0);
+    io
+}
+
+/// Interpolates the polynomial with the given evaluations over the roots of
+/// unity, returning its coefficients.  This is the inverse of [fwd].
+pub fn inv(evals: &[Elem]) -> Vec<Elem> {
+    let mut io = evals.to_vec();
+    interpolate_ntt_fp(&mut io);
+    bit_reverse(&mut io);
+    io
+}
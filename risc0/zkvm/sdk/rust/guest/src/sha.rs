@@ -22,14 +22,14 @@ use risc0_zkp::core::{
 };
 use risc0_zkvm::{
     platform::{
-        io::{SHADescriptor, GPIO_SHA},
+        io::{SHADescriptor, GPIO},
         memory, WORD_SIZE,
     },
     serde::to_vec_with_capacity,
 };
 use serde::Serialize;
 
-use crate::align_up;
+use crate::{align_up, alloc_fresh, gpio_write};
 
 // Current sha descriptor index.
 struct CurDesc(UnsafeCell<usize>);
@@ -62,9 +62,8 @@ pub fn raw_digest(data: &[u32]) -> &'static Digest {
     assert_eq!(data.len() % CHUNK_SIZE, 0);
     // Allocate fresh memory that's guaranteed to be uninitialized so
     // the host can write to it.
+    let digest = alloc_fresh::<Digest>(1);
     unsafe {
-        let alloced = Box::<mem::MaybeUninit<Digest>>::new(mem::MaybeUninit::<Digest>::uninit());
-        let digest = (*Box::into_raw(alloced)).as_mut_ptr();
         raw_digest_to(data, digest);
         &*digest
     }
@@ -87,7 +86,7 @@ pub(crate) unsafe fn raw_digest_to(data: &[u32], digest: *mut Digest) {
         digest: digest as u32,
     });
 
-    GPIO_SHA.as_ptr().write_volatile(desc_ptr);
+    gpio_write(&GPIO.sha, desc_ptr);
 }
 
 // Calculates the number of words of capacity needed, including end
@@ -209,20 +208,18 @@ impl risc0_zkp::core::sha::Sha for Impl {
     }
 
     fn hash_fps(&self, fps: &[Fp]) -> Self::DigestPtr {
-        // Fps do not not include standard sha header.
-        if fps.len() % CHUNK_SIZE == 0 {
-            raw_digest(bytemuck::cast_slice(fps))
-        } else {
-            let size = align_up(fps.len(), CHUNK_SIZE);
-            let mut buf: Vec<u32> = Vec::with_capacity(size);
-            buf.extend(bytemuck::cast_slice(fps));
-            buf.resize(size, 0);
-            raw_digest(&buf)
-        }
+        // Fps do not not include standard sha header.  Elements are stored in
+        // Montgomery form, so hash their canonical values.
+        let size = align_up(fps.len(), CHUNK_SIZE);
+        let mut buf: Vec<u32> = Vec::with_capacity(size);
+        buf.extend(fps.iter().map(u32::from));
+        buf.resize(size, 0);
+        raw_digest(&buf)
     }
 
     fn hash_fp4s(&self, fp4s: &[Fp4]) -> Self::DigestPtr {
-        self.hash_fps(bytemuck::cast_slice(fp4s))
+        let fps: Vec<Fp> = fp4s.iter().flat_map(|fp4| fp4.elems()).copied().collect();
+        self.hash_fps(&fps)
     }
 
     // Generate a new digest by mixing two digests together via XOR,
@@ -0,0 +1,78 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use risc0_zkvm::table::{decode, index_key, row_key, LEN_KEY};
+use serde::de::DeserializeOwned;
+
+use crate::kv::Kv;
+
+/// A table of rows of type `R`, read from a key-value store laid out by
+/// risc0_zkvm::table::TableBuilder.
+pub struct Table<R> {
+    kv: Kv,
+    len: u32,
+    phantom: PhantomData<R>,
+}
+
+impl<R: DeserializeOwned> Table<R> {
+    /// Open the table held in the given store.
+    pub fn open(kv: Kv) -> Self {
+        let len = decode(&kv.get(LEN_KEY).expect("Missing table length")).unwrap();
+        Table {
+            kv,
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The store holding this table.
+    pub fn kv(&self) -> &Kv {
+        &self.kv
+    }
+
+    /// The number of rows in this table.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns true if this table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read row `n`, which must be less than [Table::len].
+    pub fn get(&self, n: u32) -> R {
+        assert!(n < self.len, "Row {n} out of range");
+        decode(&self.kv.get(&row_key(n)).expect("Missing table row")).unwrap()
+    }
+
+    /// Read every row in order.
+    pub fn scan(&self) -> impl Iterator<Item = R> + '_ {
+        (0..self.len).map(|n| self.get(n))
+    }
+
+    /// Read the rows for which `index` yields `value`, in order.
+    pub fn lookup(&self, index: &str, value: &[u8]) -> Vec<R> {
+        match self.kv.get(&index_key(index, value)) {
+            Some(list) => {
+                let list: Vec<u32> = decode(&list).unwrap();
+                list.into_iter().map(|n| self.get(n)).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use _alloc::vec::Vec;
+
+pub use risc0_zkp::verify::VerificationError;
+use risc0_zkp::{
+    core::{
+        log2_ceil,
+        sha::{Digest, Sha, DIGEST_BYTES, DIGEST_WORDS},
+    },
+    verify::SealClaim,
+    MIN_CYCLES,
+};
+use risc0_zkvm_circuit::CircuitImpl;
+
+use crate::sha;
+
+/// Verifies, from within the guest, that `seal` proves an execution of the
+/// method with the given `method_id` which committed `journal`.
+///
+/// This runs the same STARK verifier as the host, but hashes through the
+/// guest's accelerated [Sha](sha::Impl) implementation, so that a guest can
+/// check receipts produced by other provers, e.g. for recursion.  The
+/// method ID is the serialized `MethodId` risc0-build embeds for each
+/// method, and only seals proven with SHA-256 are accepted.
+pub fn verify(method_id: &[u8], journal: &[u8], seal: &[u32]) -> Result<(), VerificationError> {
+    let circuit = CircuitImpl::new();
+    risc0_zkp::verify::verify_seal(&sha::Impl {}, &circuit, seal, |claim| {
+        check_claim(claim, method_id, journal)
+    })
+}
+
+fn check_claim(
+    claim: &SealClaim,
+    method_id: &[u8],
+    journal: &[u8],
+) -> Result<(), VerificationError> {
+    // The method ID holds the code root for each po2, starting at
+    // MIN_CYCLES, as little-endian words.
+    let index = (claim.po2 as usize)
+        .checked_sub(log2_ceil(MIN_CYCLES))
+        .ok_or(VerificationError::InvalidClaim)?;
+    let code_root = method_id
+        .chunks_exact(DIGEST_BYTES)
+        .nth(index)
+        .ok_or(VerificationError::InvalidClaim)?;
+    let code_root = Digest::from_slice(&risc0_zkvm::serde::bytes_to_words(code_root));
+    if code_root == Digest::default() || code_root != *claim.code_root {
+        return Err(VerificationError::InvalidClaim);
+    }
+
+    // Each output register is split into its low and high halves; the
+    // first DIGEST_WORDS registers hold the journal commitment and the next
+    // the journal's length.
+    let regs: Vec<u32> = claim
+        .outputs
+        .chunks_exact(2)
+        .map(|half| u32::from(half[0]) | u32::from(half[1]) << 16)
+        .collect();
+    if regs.len() <= DIGEST_WORDS || regs[DIGEST_WORDS] as usize != journal.len() {
+        return Err(VerificationError::InvalidClaim);
+    }
+    let commitment = if journal.len() > DIGEST_BYTES {
+        *sha::Impl {}.hash_bytes(journal)
+    } else {
+        let mut words = risc0_zkvm::serde::bytes_to_words(journal);
+        words.resize(DIGEST_WORDS, 0);
+        Digest::from_slice(&words)
+    };
+    if regs[..DIGEST_WORDS] != *commitment.as_slice() {
+        return Err(VerificationError::InvalidClaim);
+    }
+    Ok(())
+}
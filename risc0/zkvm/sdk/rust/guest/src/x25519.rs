@@ -0,0 +1,304 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! X25519 Diffie-Hellman, as specified by RFC 7748, computed in software.
+//!
+//! The circuit has no Curve25519 accelerator, so the Montgomery ladder runs
+//! on the guest's RV32IM instructions and costs as many cycles as any other
+//! guest code; a host syscall couldn't be used instead, since the guest
+//! would have no way to check its answer.
+//!
+//! The API follows the `x25519-dalek` crate, so code written against it
+//! can switch to this module by changing its imports. Field elements use
+//! ten limbs of alternately 26 and 25 bits, which keeps every product
+//! within a 64-bit accumulator and suits the 32-bit multiplier of the
+//! zkVM. All operations on secrets run in constant time.
+
+/// The u-coordinate of the Curve25519 base point.
+pub const X25519_BASEPOINT_BYTES: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// The bit offset of each limb; limb i holds bits OFFSETS[i]..OFFSETS[i + 1].
+const OFFSETS: [u32; 11] = [0, 26, 51, 77, 102, 128, 153, 179, 204, 230, 255];
+
+// 2p, for subtracting without underflow.
+const TWO_P: Fe = Fe([
+    0x7ffffda, 0x3fffffe, 0x7fffffe, 0x3fffffe, 0x7fffffe, 0x3fffffe, 0x7fffffe, 0x3fffffe,
+    0x7fffffe, 0x3fffffe,
+]);
+
+const fn width(i: usize) -> u32 {
+    OFFSETS[i + 1] - OFFSETS[i]
+}
+
+// An element of GF(2^255 - 19).
+#[derive(Clone, Copy)]
+struct Fe([i64; 10]);
+
+impl Fe {
+    const ZERO: Fe = Fe([0; 10]);
+    const ONE: Fe = Fe([1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut words = [0u64; 4];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        // The most significant bit is ignored.
+        words[3] &= (1 << 63) - 1;
+        let mut limbs = [0i64; 10];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (word, shift) = ((OFFSETS[i] / 64) as usize, OFFSETS[i] % 64);
+            let mut bits = words[word] >> shift;
+            if shift + width(i) > 64 {
+                bits |= words[word + 1] << (64 - shift);
+            }
+            *limb = (bits & ((1 << width(i)) - 1)) as i64;
+        }
+        Fe(limbs)
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut h = self.carry().0;
+        // Compute q = floor((h + 19) / 2^255), which is 1 exactly when
+        // h >= p, and subtract q * p.
+        let mut q = (19 * h[9] + (1 << 24)) >> 25;
+        for (i, limb) in h.iter().enumerate() {
+            q = (limb + q) >> width(i);
+        }
+        h[0] += 19 * q;
+        for i in 0..9 {
+            let carry = h[i] >> width(i);
+            h[i] -= carry << width(i);
+            h[i + 1] += carry;
+        }
+        h[9] &= (1 << width(9)) - 1;
+
+        let mut words = [0u64; 4];
+        for (i, limb) in h.iter().enumerate() {
+            let (word, shift) = ((OFFSETS[i] / 64) as usize, OFFSETS[i] % 64);
+            words[word] |= (*limb as u64) << shift;
+            if shift + width(i) > 64 {
+                words[word + 1] |= (*limb as u64) >> (64 - shift);
+            }
+        }
+        let mut out = [0u8; 32];
+        for (chunk, word) in out.chunks_exact_mut(8).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    // Bring every limb back within its width, folding the carry out of the
+    // top limb back into the bottom one.
+    fn carry(self) -> Self {
+        let mut h = self.0;
+        for i in 0..10 {
+            let carry = h[i] >> width(i);
+            h[i] -= carry << width(i);
+            if i == 9 {
+                h[0] += 19 * carry;
+            } else {
+                h[i + 1] += carry;
+            }
+        }
+        // The folded carry can be large, so settle it once more.
+        let carry = h[0] >> width(0);
+        h[0] -= carry << width(0);
+        h[1] += carry;
+        Fe(h)
+    }
+
+    fn add(&self, rhs: &Fe) -> Fe {
+        let mut h = self.0;
+        for (h, g) in h.iter_mut().zip(rhs.0) {
+            *h += g;
+        }
+        Fe(h).carry()
+    }
+
+    fn sub(&self, rhs: &Fe) -> Fe {
+        let mut h = self.0;
+        for ((h, g), p) in h.iter_mut().zip(rhs.0).zip(TWO_P.0) {
+            *h += p - g;
+        }
+        Fe(h).carry()
+    }
+
+    fn mul(&self, rhs: &Fe) -> Fe {
+        let (f, g) = (&self.0, &rhs.0);
+        let mut h = [0i64; 10];
+        for i in 0..10 {
+            for j in 0..10 {
+                // Two odd limbs each sit half a bit above their weight, and
+                // 2^255 wraps around to 19.
+                let mut term = f[i] * g[j];
+                if i % 2 == 1 && j % 2 == 1 {
+                    term *= 2;
+                }
+                if i + j >= 10 {
+                    term *= 19;
+                }
+                h[(i + j) % 10] += term;
+            }
+        }
+        Fe(h).carry()
+    }
+
+    fn square(&self) -> Fe {
+        self.mul(self)
+    }
+
+    fn mul_small(&self, k: i64) -> Fe {
+        let mut h = self.0;
+        for h in h.iter_mut() {
+            *h *= k;
+        }
+        Fe(h).carry()
+    }
+
+    // Compute self^(p - 2) = self^(2^255 - 21), the inverse of self.
+    fn invert(&self) -> Fe {
+        let mut r = Fe::ONE;
+        for bit in (0..255).rev() {
+            r = r.square();
+            if bit != 2 && bit != 4 {
+                r = r.mul(self);
+            }
+        }
+        r
+    }
+
+    fn cswap(a: &mut Fe, b: &mut Fe, swap: u8) {
+        let mask = -(swap as i64);
+        for (a, b) in a.0.iter_mut().zip(b.0.iter_mut()) {
+            let t = mask & (*a ^ *b);
+            *a ^= t;
+            *b ^= t;
+        }
+    }
+}
+
+fn clamp(mut scalar: [u8; 32]) -> [u8; 32] {
+    scalar[0] &= 248;
+    scalar[31] &= 127;
+    scalar[31] |= 64;
+    scalar
+}
+
+/// Multiply the point with u-coordinate `u` by the clamped `scalar`,
+/// returning the u-coordinate of the result.
+pub fn x25519(scalar: [u8; 32], u: [u8; 32]) -> [u8; 32] {
+    let k = clamp(scalar);
+    let x1 = Fe::from_bytes(&u);
+    let (mut x2, mut z2, mut x3, mut z3) = (Fe::ONE, Fe::ZERO, x1, Fe::ONE);
+    let mut swap = 0;
+    for t in (0..255).rev() {
+        let bit = (k[t / 8] >> (t % 8)) & 1;
+        swap ^= bit;
+        Fe::cswap(&mut x2, &mut x3, swap);
+        Fe::cswap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&e.mul_small(121665)));
+    }
+    Fe::cswap(&mut x2, &mut x3, swap);
+    Fe::cswap(&mut z2, &mut z3, swap);
+    x2.mul(&z2.invert()).to_bytes()
+}
+
+/// A Diffie-Hellman secret key which may be used more than once.
+#[derive(Clone)]
+pub struct StaticSecret([u8; 32]);
+
+impl StaticSecret {
+    /// Perform a Diffie-Hellman key agreement with `their_public`.
+    pub fn diffie_hellman(&self, their_public: &PublicKey) -> SharedSecret {
+        SharedSecret(x25519(self.0, their_public.0))
+    }
+
+    /// The bytes of this secret key.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl From<[u8; 32]> for StaticSecret {
+    fn from(bytes: [u8; 32]) -> Self {
+        StaticSecret(bytes)
+    }
+}
+
+/// A Diffie-Hellman public key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    /// The bytes of this public key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The bytes of this public key.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl From<[u8; 32]> for PublicKey {
+    fn from(bytes: [u8; 32]) -> Self {
+        PublicKey(bytes)
+    }
+}
+
+impl<'a> From<&'a StaticSecret> for PublicKey {
+    fn from(secret: &'a StaticSecret) -> Self {
+        PublicKey(x25519(secret.0, X25519_BASEPOINT_BYTES))
+    }
+}
+
+/// The result of a Diffie-Hellman key agreement.
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// The bytes of this shared secret.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The bytes of this shared secret.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// Whether the key agreement was contributory, meaning the peer's
+    /// public key was not one of the low-order points that force the
+    /// shared secret to zero.
+    pub fn was_contributory(&self) -> bool {
+        self.0.iter().fold(0, |acc, b| acc | b) != 0
+    }
+}
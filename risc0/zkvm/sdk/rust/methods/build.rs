@@ -1,4 +1,4 @@
-use risc0_build::{embed_methods_with_options, GuestOptions};
+use risc0_build::{embed_methods_with_options, GuestOptions, MethodTypes};
 use std::{collections::HashMap, env};
 
 fn main() {
@@ -10,6 +10,15 @@ fn main() {
     let inner_pkg_options = GuestOptions {
         code_limit: 10,
         features: vec!["test_feature1".to_string(), "test_feature2".to_string()],
+        method_types: HashMap::from([(
+            "typed_main".to_string(),
+            MethodTypes {
+                input: "(u32, u32)".to_string(),
+                output: "u64".to_string(),
+            },
+        )]),
+        frame_pointers: true,
+        ..Default::default()
     };
 
     let map = HashMap::from([("risc0-zkvm-methods-inner", inner_pkg_options)]);
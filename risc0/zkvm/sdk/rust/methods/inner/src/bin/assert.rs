@@ -0,0 +1,31 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkvm_guest::{env, sha};
+
+risc0_zkvm_guest::entry!(main);
+
+pub fn main() {
+    let (limit, rounds): (u32, u32) = env::read();
+    env::commit(&limit);
+    env::assert(rounds <= limit, 7);
+    let mut digest = *sha::digest_u8_slice(&[]);
+    for _ in 0..rounds {
+        digest = *sha::digest(&digest);
+    }
+    env::commit(&digest);
+}
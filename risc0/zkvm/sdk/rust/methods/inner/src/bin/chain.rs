@@ -0,0 +1,39 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkp::core::sha::Digest;
+use risc0_zkvm_guest::{env, sha};
+
+risc0_zkvm_guest::entry!(main);
+
+// Appends to a chain whose state is a counter: the journal holds the claim
+// digest of the previous link followed by the new count.  The previous link
+// comes with the image ID of its method.
+pub fn main() {
+    let prev: Option<(Digest, Vec<u8>)> = env::read();
+    let (prev_claim, count): (Digest, u32) = match prev {
+        Some((image_id, journal)) => {
+            // The count follows the 32-byte claim digest.
+            let count = u32::from_le_bytes(journal[32..36].try_into().unwrap());
+            let mut claim = image_id.to_bytes().to_vec();
+            claim.extend_from_slice(&journal);
+            (*sha::digest_u8_slice(&claim), count)
+        }
+        None => (env::read(), 0),
+    };
+    env::commit(&(prev_claim, count + 1));
+}
@@ -0,0 +1,38 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use risc0_zkp::core::sha::Digest;
+use risc0_zkvm_guest::env;
+
+risc0_zkvm_guest::entry!(main);
+
+risc0_zkvm_guest::disclosable! {
+    pub struct Passport {
+        pub name: String,
+        pub birth_year: u32,
+        pub country: String,
+    }
+}
+
+pub fn main() {
+    let (name, birth_year, country, seed): (String, u32, String, Digest) = env::read();
+    let passport = Passport {
+        name,
+        birth_year,
+        country,
+    };
+    env::commit_disclosable(&passport, &seed);
+}
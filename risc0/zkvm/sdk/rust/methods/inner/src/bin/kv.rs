@@ -0,0 +1,28 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkp::core::sha::Digest;
+use risc0_zkvm_guest::{env, kv::Kv};
+
+risc0_zkvm_guest::entry!(main);
+
+pub fn main() {
+    let (root, keys): (Digest, Vec<Vec<u8>>) = env::read();
+    let store = Kv::new(root);
+    let values: Vec<Option<Vec<u8>>> = keys.iter().map(|key| store.get(key)).collect();
+    env::commit(&(root, values));
+}
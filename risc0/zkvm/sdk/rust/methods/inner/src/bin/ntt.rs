@@ -0,0 +1,32 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkp::field::baby_bear::Elem;
+use risc0_zkvm_guest::{env, ntt};
+
+risc0_zkvm_guest::entry!(main);
+
+// Commits the forward transform of the input coefficients, followed by the
+// inverse transform of those evaluations.
+pub fn main() {
+    let coeffs: Vec<u32> = env::read();
+    let coeffs: Vec<Elem> = coeffs.into_iter().map(Elem::from).collect();
+    let evals = ntt::fwd(&coeffs);
+    let roundtrip = ntt::inv(&evals);
+    let to_u32 = |elems: &[Elem]| elems.iter().map(|x| u32::from(*x)).collect::<Vec<u32>>();
+    env::commit(&(to_u32(&evals), to_u32(&roundtrip)));
+}
@@ -0,0 +1,37 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkp::core::sha::Digest;
+use risc0_zkvm_guest::{env, sha};
+
+risc0_zkvm_guest::entry!(main);
+
+// A pipeline stage which adds its input to the totals of the stages it
+// depends on.  The journal holds the claim digest of each dependency,
+// followed by the new total.
+pub fn main() {
+    let deps: Vec<(Digest, Vec<u8>)> = env::read();
+    let mut total: u32 = env::read();
+    for (image_id, journal) in &deps {
+        let mut claim = image_id.to_bytes().to_vec();
+        claim.extend_from_slice(journal);
+        env::commit(sha::digest_u8_slice(&claim));
+        // The total is the last word of each journal.
+        total += u32::from_le_bytes(journal[journal.len() - 4..].try_into().unwrap());
+    }
+    env::commit(&total);
+}
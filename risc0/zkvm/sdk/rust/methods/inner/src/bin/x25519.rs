@@ -0,0 +1,30 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use risc0_zkvm_guest::{
+    env,
+    x25519::{PublicKey, StaticSecret},
+};
+
+risc0_zkvm_guest::entry!(main);
+
+pub fn main() {
+    let (secret, their_public): ([u8; 32], [u8; 32]) = env::read();
+    let secret = StaticSecret::from(secret);
+    let shared = secret.diffie_hellman(&PublicKey::from(their_public));
+    env::commit(&(PublicKey::from(&secret).to_bytes(), shared.to_bytes()));
+}
@@ -0,0 +1,42 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use risc0_zkvm_guest::{env, sha};
+
+risc0_zkvm_guest::entry!(main);
+
+// Hashes the input, then rehashes each digest `rounds` times.  Every round
+// frees the buffer it hashed, so the TLSF heap hands that memory out again
+// while the host keeps writing digests into the guest.
+pub fn main() {
+    let data: &[u8] = env::read();
+    let rounds: u32 = env::read();
+    let mut digest = *sha::digest_u8_slice(data);
+    for _ in 0..rounds {
+        let bytes: Vec<u8> = digest
+            .as_slice()
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        digest = *sha::digest_u8_slice(&bytes);
+    }
+    env::commit(&digest);
+}
@@ -0,0 +1,60 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The contract between the zkVM and the code running in it, for frontends
+//! other than the Rust guest crate (see zkvm/platform/tinygo for TinyGo).
+//!
+//! The zkVM starts executing at the ELF entry point, conventionally
+//! [ENTRY], with every register zero.  The guest talks to the host through
+//! the memory mapped registers in [crate::io::GPIO] and finishes by loading
+//! its result block into registers x1 through x9 and executing `ecall`.
+
+use crate::{memory, WORD_SIZE};
+
+/// The entry symbol named by the linker scripts.
+pub const ENTRY: &str = "_start";
+
+/// Where the entry shim points the stack; the stack grows down from the
+/// top of [memory::STACK].
+pub const STACK_INIT: usize = memory::STACK.end() - WORD_SIZE;
+
+/// The encoding of `ecall`, which halts the zkVM.
+pub const ECALL: u32 = 0x0000_0073;
+
+/// The number of words in the result block.
+pub const RESULT_WORDS: usize = 9;
+
+/// The register holding the first word of the result block at `ecall`; the
+/// rest follow in order.
+pub const RESULT_FIRST_REG: usize = 1;
+
+/// Journals of at most this many words are returned in the result block
+/// itself.  Longer journals are returned as the SHA-256 digest of the
+/// journal in the [memory::COMMIT] region, padded as for a write-only
+/// memory region.
+pub const INLINE_JOURNAL_WORDS: usize = 8;
+
+/// The result block for a guest whose journal, already committed through
+/// [crate::io::GPIO]`.commit`, is `journal`: the journal words padded with
+/// zeros, followed by its length in bytes.  Returns None if the journal must
+/// be returned as a digest instead.
+pub fn inline_result(journal: &[u32]) -> Option<[u32; RESULT_WORDS]> {
+    if journal.len() > INLINE_JOURNAL_WORDS {
+        return None;
+    }
+    let mut result = [0; RESULT_WORDS];
+    result[..journal.len()].copy_from_slice(journal);
+    result[INLINE_JOURNAL_WORDS] = (journal.len() * WORD_SIZE) as u32;
+    Some(result)
+}
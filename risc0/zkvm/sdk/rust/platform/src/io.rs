@@ -17,6 +17,7 @@
 
 use core::marker::PhantomData;
 
+/// A memory-mapped register used by the guest to signal the host.
 pub struct Gpio<T> {
     addr: u32,
     _marker: PhantomData<T>,
@@ -37,28 +38,58 @@ impl<T> Gpio<T> {
     pub const fn addr(&self) -> u32 {
         self.addr
     }
+
+    /// Writes `value` to this register, handing it to the host.  Any memory
+    /// that `value` points to must be fully written beforehand.
+    ///
+    /// GPIO registers only exist inside the zkVM, so this is only compiled
+    /// for the zkVM's target.
+    #[cfg(target_arch = "riscv32")]
+    pub fn write(&self, value: T) {
+        // SAFETY: GPIO registers are always mapped inside the zkVM, and the
+        // host validates any guest addresses it is handed.
+        unsafe { self.as_ptr().write_volatile(value) }
+    }
+}
+
+/// The block of GPIO registers shared between the guest and the host.
+pub struct GpioRegs {
+    pub sha: Gpio<*const SHADescriptor>,
+    pub commit: Gpio<*const IoDescriptor>,
+    pub fault: Gpio<*const u8>,
+    pub log: Gpio<*const u8>,
+    pub getkey: Gpio<*const GetKeyDescriptor>,
+
+    pub sendrecv_channel: Gpio<u32>,
+    pub sendrecv_size: Gpio<usize>,
+    pub sendrecv_addr: Gpio<*const u8>,
 }
 
-pub const GPIO_SHA: Gpio<*const SHADescriptor> = Gpio::new(0x01F0_0000);
-pub const GPIO_COMMIT: Gpio<*const IoDescriptor> = Gpio::new(0x01F0_0004);
-pub const GPIO_FAULT: Gpio<*const u8> = Gpio::new(0x01F0_0008);
-pub const GPIO_LOG: Gpio<*const u8> = Gpio::new(0x01F0_000C);
-pub const GPIO_GETKEY: Gpio<*const GetKeyDescriptor> = Gpio::new(0x01F0_0010);
+pub const GPIO: GpioRegs = GpioRegs {
+    sha: Gpio::new(0x01F0_0000),
+    commit: Gpio::new(0x01F0_0004),
+    fault: Gpio::new(0x01F0_0008),
+    log: Gpio::new(0x01F0_000C),
+    getkey: Gpio::new(0x01F0_0010),
 
-pub const GPIO_SENDRECV_CHANNEL: Gpio<u32> = Gpio::new(0x01F0_0014);
-pub const GPIO_SENDRECV_SIZE: Gpio<usize> = Gpio::new(0x01F0_0018);
-pub const GPIO_SENDRECV_ADDR: Gpio<*const u8> = Gpio::new(0x01F0_001C);
+    sendrecv_channel: Gpio::new(0x01F0_0014),
+    sendrecv_size: Gpio::new(0x01F0_0018),
+    sendrecv_addr: Gpio::new(0x01F0_001C),
+};
 
+// Raw register addresses, for hosts that decode guest memory writes.
 pub mod addr {
-    pub const GPIO_SHA: u32 = super::GPIO_SHA.addr();
-    pub const GPIO_COMMIT: u32 = super::GPIO_COMMIT.addr();
-    pub const GPIO_FAULT: u32 = super::GPIO_FAULT.addr();
-    pub const GPIO_LOG: u32 = super::GPIO_LOG.addr();
-    pub const GPIO_GETKEY: u32 = super::GPIO_GETKEY.addr();
-
-    pub const GPIO_SENDRECV_CHANNEL: u32 = super::GPIO_SENDRECV_CHANNEL.addr();
-    pub const GPIO_SENDRECV_SIZE: u32 = super::GPIO_SENDRECV_SIZE.addr();
-    pub const GPIO_SENDRECV_ADDR: u32 = super::GPIO_SENDRECV_ADDR.addr();
+    use super::GPIO;
+
+    pub const GPIO_SHA: u32 = GPIO.sha.addr();
+    pub const GPIO_COMMIT: u32 = GPIO.commit.addr();
+    pub const GPIO_FAULT: u32 = GPIO.fault.addr();
+    pub const GPIO_LOG: u32 = GPIO.log.addr();
+    pub const GPIO_GETKEY: u32 = GPIO.getkey.addr();
+
+    pub const GPIO_SENDRECV_CHANNEL: u32 = GPIO.sendrecv_channel.addr();
+    pub const GPIO_SENDRECV_SIZE: u32 = GPIO.sendrecv_size.addr();
+    pub const GPIO_SENDRECV_ADDR: u32 = GPIO.sendrecv_addr.addr();
 }
 
 #[repr(C)]
@@ -86,3 +117,57 @@ pub struct GetKeyDescriptor {
 pub const SENDRECV_CHANNEL_INITIAL_INPUT: u32 = 0;
 pub const SENDRECV_CHANNEL_STDOUT: u32 = 1;
 pub const SENDRECV_CHANNEL_STDERR: u32 = 2;
+pub const SENDRECV_CHANNEL_FIELD: u32 = 3;
+pub const SENDRECV_CHANNEL_KV: u32 = 4;
+pub const SENDRECV_CHANNEL_SECRET: u32 = 5;
+pub const SENDRECV_CHANNEL_LOG: u32 = 6;
+
+// Levels of the records sent on SENDRECV_CHANNEL_LOG, in the order of the
+// log crate's; must match zkvm/platform/io.h.  Each record is the level
+// byte, the length of the module path as a little-endian u16, the module
+// path and then the message, all UTF-8.
+pub const LOG_LEVEL_ERROR: u8 = 1;
+pub const LOG_LEVEL_WARN: u8 = 2;
+pub const LOG_LEVEL_INFO: u8 = 3;
+pub const LOG_LEVEL_DEBUG: u8 = 4;
+pub const LOG_LEVEL_TRACE: u8 = 5;
+
+// Status words which start each response on SENDRECV_CHANNEL_SECRET; must
+// match zkvm/platform/io.h.
+pub const SECRET_OK: u32 = 0;
+pub const SECRET_NOT_FOUND: u32 = 1;
+pub const SECRET_UNSEAL_FAILED: u32 = 2;
+
+// The journal of a guest which failed an env::assert holds exactly two
+// words: JOURNAL_ASSERT_FAILED followed by the guest's failure code.  Must
+// match zkvm/platform/io.h.
+pub const JOURNAL_ASSERT_FAILED: u32 = 0xA55E_27F0;
+
+/// The failure code of a guest which failed an env::assert, given its
+/// journal, or None if it completed normally.
+pub fn assert_failure(journal: &[u32]) -> Option<u32> {
+    match journal {
+        [JOURNAL_ASSERT_FAILED, code] => Some(*code),
+        _ => None,
+    }
+}
+
+// Reserved channel used by the guest runtime to fetch the pieces of a
+// response that did not fit in the INPUT region; see zkvm/platform/io.h.
+pub const SENDRECV_CHANNEL_CONTINUE: u32 = 0xFFFF_FFFF;
+// Set in a response length word when the response was split; the
+// remaining bits hold its total length in bytes.
+pub const SENDRECV_SPLIT: u32 = 0x8000_0000;
+// Set in a chunk length word on the final chunk of a split response.
+pub const SENDRECV_CHUNK_LAST: u32 = 0x8000_0000;
+// Maximum number of bytes the host delivers per chunk.
+pub const SENDRECV_CHUNK_SIZE: usize = 64 * 1024;
+
+// Operations available on SENDRECV_CHANNEL_FIELD; must match
+// zkvm/platform/io.h.
+pub const FIELD_OP_INV: u32 = 0;
+
+// Status words which start each response on SENDRECV_CHANNEL_FIELD.
+pub const FIELD_OK: u32 = 0;
+pub const FIELD_MALFORMED: u32 = 1;
+pub const FIELD_UNKNOWN_OP: u32 = 2;
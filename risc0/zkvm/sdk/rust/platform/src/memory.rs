@@ -17,6 +17,10 @@ use super::WORD_SIZE;
 pub const MEM_BITS: usize = 24;
 pub const MEM_SIZE: usize = (1 << MEM_BITS) * 4;
 
+/// Granularity at which the host reports guest memory usage.  Must match
+/// kPageSize in zkvm/platform/memory.h.
+pub const PAGE_SIZE: usize = 1024;
+
 pub struct Region {
     start: usize,
     len_bytes: usize,
@@ -51,6 +55,10 @@ impl Region {
     pub const fn end(&self) -> usize {
         self.start + self.len_bytes
     }
+
+    pub const fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end()
+    }
 }
 
 // These should match zkvm/platform/memory.h and zkvm/platform/risc0.ld.
@@ -65,3 +73,64 @@ pub const SHA: Region = Region::new(0x02A0_0000, mb(1));
 pub const WOM: Region = Region::new(0x02B0_0000, mb(21));
 pub const OUTPUT: Region = Region::new(0x02B0_0000, mb(20));
 pub const COMMIT: Region = Region::new(0x03F0_0000, mb(1));
+
+/// An entry in the MEMORY block of the guest linker script.
+pub struct LayoutEntry {
+    pub name: &'static str,
+    pub attrs: &'static str,
+    pub region: Region,
+}
+
+/// The guest memory map, as seen by the linker and the host's ELF loader.
+///
+/// This is the single source of truth for the linker script: risc0-build
+/// generates the MEMORY block from this table and checks that risc0.ld
+/// agrees with it.  OUTPUT and COMMIT are subregions of WOM, so they don't
+/// appear here.
+pub const LAYOUT: &[LayoutEntry] = &[
+    LayoutEntry {
+        name: "stack",
+        attrs: "",
+        region: STACK,
+    },
+    LayoutEntry {
+        name: "data",
+        attrs: "RW",
+        region: DATA,
+    },
+    LayoutEntry {
+        name: "heap",
+        attrs: "",
+        region: HEAP,
+    },
+    LayoutEntry {
+        name: "input",
+        attrs: "",
+        region: INPUT,
+    },
+    LayoutEntry {
+        name: "gpio",
+        attrs: "",
+        region: GPIO,
+    },
+    LayoutEntry {
+        name: "prog",
+        attrs: "X",
+        region: PROG,
+    },
+    LayoutEntry {
+        name: "sha",
+        attrs: "",
+        region: SHA,
+    },
+    LayoutEntry {
+        name: "wom",
+        attrs: "",
+        region: WOM,
+    },
+];
+
+/// Returns true if `addr` falls within a region of the guest memory map.
+pub fn in_layout(addr: usize) -> bool {
+    LAYOUT.iter().any(|entry| entry.region.contains(addr))
+}
@@ -0,0 +1,344 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selective disclosure of committed fields.
+//!
+//! A guest which commits a [Disclosable] value through
+//! risc0_zkvm_guest::env::commit_disclosable writes only the [root] of a
+//! Merkle tree over the value's fields to the journal. Since the root fits
+//! in a [Digest], the seal commits to it verbatim. The prover can later hand
+//! a verifier a [Disclosure] of any subset of the fields, which the verifier
+//! checks against the journal of a verified receipt.
+//!
+//! Each leaf binds the field's position and a salt derived from a seed that
+//! the prover keeps private, so fields which are not disclosed can't be
+//! recovered from the sibling digests in an opening by guessing their
+//! values.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use risc0_zkp::core::sha::{Digest, Sha, DIGEST_WORDS};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The serialized fields of a [Disclosable] value, in declaration order.
+pub type FieldWords = Vec<Vec<u32>>;
+
+/// A value whose fields can be disclosed individually.
+///
+/// Implement this with the [disclosable](crate::disclosable) macro.
+pub trait Disclosable {
+    /// The names of the fields, in declaration order.
+    const FIELDS: &'static [&'static str];
+
+    /// Serialize each field with [crate::serde::to_vec].
+    fn field_words(&self) -> FieldWords;
+}
+
+/// Declare a struct whose fields can be disclosed individually.
+///
+/// ```
+/// risc0_zkvm::disclosable! {
+///     pub struct Passport {
+///         pub name: String,
+///         pub birth_year: u32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! disclosable {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        impl $crate::disclose::Disclosable for $name {
+            const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            fn field_words(&self) -> $crate::disclose::FieldWords {
+                [$($crate::serde::to_vec(&self.$field).unwrap()),*].into()
+            }
+        }
+    };
+}
+
+/// Returned when a [Disclosure] does not match the committed root, or does
+/// not describe the expected type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidDisclosure;
+
+impl fmt::Display for InvalidDisclosure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Invalid disclosure")
+    }
+}
+
+fn depth(len: u32) -> usize {
+    (len.max(1) as usize).next_power_of_two().trailing_zeros() as usize
+}
+
+fn salt<S: Sha>(sha: &S, seed: &Digest, index: u32) -> Digest {
+    let mut words = [0; DIGEST_WORDS];
+    words[0] = index;
+    *sha.hash_pair(seed, &Digest::new(words))
+}
+
+fn leaf_digest<S: Sha>(sha: &S, index: u32, salt: &Digest, words: &[u32]) -> Digest {
+    let mut buf = Vec::with_capacity(1 + DIGEST_WORDS + words.len());
+    buf.push(index);
+    buf.extend_from_slice(salt.as_slice());
+    buf.extend_from_slice(words);
+    *sha.hash_words(&buf)
+}
+
+// Binds the number of fields into the root.
+fn fields_root<S: Sha>(sha: &S, tree_root: &Digest, len: u32) -> Digest {
+    let mut words = [0; DIGEST_WORDS];
+    words[0] = len;
+    *sha.hash_pair(tree_root, &Digest::new(words))
+}
+
+// Every layer of the tree over `fields`, from the padded leaves to the root.
+fn layers<S: Sha>(sha: &S, fields: &FieldWords, seed: &Digest) -> Vec<Vec<Digest>> {
+    let len = fields.len() as u32;
+    let mut leaves: Vec<Digest> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, words)| {
+            let index = index as u32;
+            leaf_digest(sha, index, &salt(sha, seed, index), words)
+        })
+        .collect();
+    leaves.resize(1 << depth(len), Digest::default());
+    let mut layers = Vec::from([leaves]);
+    while layers.last().unwrap().len() > 1 {
+        let next = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| *sha.hash_pair(&pair[0], &pair[1]))
+            .collect();
+        layers.push(next);
+    }
+    layers
+}
+
+/// The root committed for `value` when its leaves are salted from `seed`.
+pub fn root<S: Sha, T: Disclosable>(sha: &S, value: &T, seed: &Digest) -> Digest {
+    let fields = value.field_words();
+    let layers = layers(sha, &fields, seed);
+    fields_root(sha, &layers.last().unwrap()[0], fields.len() as u32)
+}
+
+/// One disclosed field along with its Merkle path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisclosedField {
+    /// The position of the field in declaration order.
+    pub index: u32,
+
+    /// The salt of the field's leaf.
+    pub salt: Digest,
+
+    /// The serialized value of the field.
+    pub words: Vec<u32>,
+
+    /// The sibling digests from the leaf up to the root of the tree.
+    pub path: Vec<Digest>,
+}
+
+/// A subset of the fields of a committed [Disclosable] value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Disclosure {
+    /// The number of fields in the value.
+    pub len: u32,
+
+    /// The disclosed fields.
+    pub fields: Vec<DisclosedField>,
+}
+
+impl Disclosure {
+    /// Disclose the fields of `value` with the given names, whose leaves were
+    /// salted from `seed`.  Returns None if a name is not a field of `T`.
+    pub fn new<S: Sha, T: Disclosable>(
+        sha: &S,
+        value: &T,
+        seed: &Digest,
+        names: &[&str],
+    ) -> Option<Self> {
+        let fields = value.field_words();
+        let layers = layers(sha, &fields, seed);
+        let disclosed = names
+            .iter()
+            .map(|name| {
+                let index = T::FIELDS.iter().position(|field| field == name)?;
+                let path = layers[..layers.len() - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(level, layer)| layer[(index >> level) ^ 1])
+                    .collect();
+                Some(DisclosedField {
+                    index: index as u32,
+                    salt: salt(sha, seed, index as u32),
+                    words: fields[index].clone(),
+                    path,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Disclosure {
+            len: fields.len() as u32,
+            fields: disclosed,
+        })
+    }
+
+    /// Check every disclosed field against `root`.
+    pub fn verify<S: Sha>(&self, sha: &S, root: &Digest) -> Result<(), InvalidDisclosure> {
+        for field in self.fields.iter() {
+            if field.index >= self.len || field.path.len() != depth(self.len) {
+                return Err(InvalidDisclosure);
+            }
+            let mut node = leaf_digest(sha, field.index, &field.salt, &field.words);
+            let mut index = field.index;
+            for sibling in field.path.iter() {
+                node = if index & 1 == 0 {
+                    *sha.hash_pair(&node, sibling)
+                } else {
+                    *sha.hash_pair(sibling, &node)
+                };
+                index >>= 1;
+            }
+            if fields_root(sha, &node, self.len) != *root {
+                return Err(InvalidDisclosure);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every disclosed field against the root committed in `journal`,
+    /// which must come from a verified receipt.
+    pub fn verify_journal<S: Sha>(&self, sha: &S, journal: &[u8]) -> Result<(), InvalidDisclosure> {
+        let words: Vec<u32> = journal
+            .chunks(4)
+            .map(|chunk| {
+                let mut word = [0; 4];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u32::from_le_bytes(word)
+            })
+            .collect();
+        let root = Digest::try_from_slice(&words).map_err(|_| InvalidDisclosure)?;
+        self.verify(sha, &root)
+    }
+
+    /// Decode the disclosed field `name` of a value of type `T`.  Returns
+    /// None if the field was not disclosed.
+    ///
+    /// This does not check the disclosure; call [Disclosure::verify] first.
+    pub fn get<T: Disclosable, V: DeserializeOwned>(
+        &self,
+        name: &str,
+    ) -> Result<Option<V>, InvalidDisclosure> {
+        if self.len as usize != T::FIELDS.len() {
+            return Err(InvalidDisclosure);
+        }
+        let index = T::FIELDS
+            .iter()
+            .position(|field| *field == name)
+            .ok_or(InvalidDisclosure)?;
+        match self
+            .fields
+            .iter()
+            .find(|field| field.index as usize == index)
+        {
+            Some(field) => crate::serde::from_slice(&field.words)
+                .map(Some)
+                .map_err(|_| InvalidDisclosure),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use risc0_zkp::core::sha::{default_implementation, Digest};
+
+    use super::{root, Disclosure, InvalidDisclosure};
+
+    crate::disclosable! {
+        struct Passport {
+            name: String,
+            birth_year: u32,
+            country: String,
+        }
+    }
+
+    fn passport() -> Passport {
+        Passport {
+            name: "Ada".to_string(),
+            birth_year: 1815,
+            country: "GB".to_string(),
+        }
+    }
+
+    #[test]
+    fn disclose() {
+        let sha = default_implementation();
+        let seed = Digest::new([7; 8]);
+        let root = root(sha, &passport(), &seed);
+        let disclosure =
+            Disclosure::new(sha, &passport(), &seed, &["birth_year", "country"]).unwrap();
+        disclosure.verify(sha, &root).unwrap();
+        assert_eq!(
+            disclosure.get::<Passport, u32>("birth_year").unwrap(),
+            Some(1815)
+        );
+        assert_eq!(
+            disclosure.get::<Passport, String>("country").unwrap(),
+            Some("GB".to_string())
+        );
+        assert_eq!(disclosure.get::<Passport, String>("name").unwrap(), None);
+        assert!(disclosure.get::<Passport, String>("age").is_err());
+        assert!(Disclosure::new(sha, &passport(), &seed, &["age"]).is_none());
+    }
+
+    #[test]
+    fn tampered() {
+        let sha = default_implementation();
+        let seed = Digest::new([7; 8]);
+        let root = root(sha, &passport(), &seed);
+        let disclosure = Disclosure::new(sha, &passport(), &seed, &["birth_year"]).unwrap();
+
+        let mut forged = disclosure.clone();
+        forged.fields[0].words = crate::serde::to_vec(&1990u32).unwrap();
+        assert_eq!(forged.verify(sha, &root), Err(InvalidDisclosure));
+
+        let mut moved = disclosure.clone();
+        moved.fields[0].index = 0;
+        assert_eq!(moved.verify(sha, &root), Err(InvalidDisclosure));
+
+        let mut shrunk = disclosure;
+        shrunk.len = 2;
+        assert_eq!(shrunk.verify(sha, &root), Err(InvalidDisclosure));
+
+        // A different seed gives different salts, and so a different root.
+        let other = Disclosure::new(sha, &passport(), &Digest::new([8; 8]), &["birth_year"]);
+        assert!(other.unwrap().verify(sha, &root).is_err());
+    }
+}
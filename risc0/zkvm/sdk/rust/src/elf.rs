@@ -14,57 +14,128 @@
 
 use alloc::collections::BTreeMap;
 
-use anyhow::{anyhow, bail, Result};
+use thiserror::Error;
 use xmas_elf::{
     header::{Class, Data, Machine, Version},
     program::ProgramHeader,
     ElfFile,
 };
 
+use crate::platform::memory;
+
+/// Reasons an ELF image can be rejected by [Program::load_elf].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    #[error("Malformed ELF: {0}")]
+    Malformed(&'static str),
+    #[error("Not a 32-bit ELF")]
+    NotElf32,
+    #[error("Not little endian")]
+    NotLittleEndian,
+    #[error("Invalid ELF version")]
+    InvalidVersion,
+    #[error("Invalid machine type, must be RISC-V")]
+    InvalidMachine,
+    #[error("Invalid ELF type, must be executable")]
+    NotExecutable,
+    #[error("Invalid entry point")]
+    InvalidEntry,
+    #[error("Too many program headers")]
+    TooManyProgramHeaders,
+    #[error("Address 0x{0:08x} is outside the guest memory map")]
+    AddressOutOfRange(u64),
+    #[error("Segment at file offset 0x{0:08x} extends past the end of the file")]
+    TruncatedSegment(u32),
+}
+
+impl ElfError {
+    /// A stable numeric code for this error; see [crate::host::Error::code].
+    pub fn code(&self) -> u32 {
+        match self {
+            ElfError::Malformed(_) => 100,
+            ElfError::NotElf32 => 101,
+            ElfError::NotLittleEndian => 102,
+            ElfError::InvalidVersion => 103,
+            ElfError::InvalidMachine => 104,
+            ElfError::NotExecutable => 105,
+            ElfError::InvalidEntry => 106,
+            ElfError::TooManyProgramHeaders => 107,
+            ElfError::AddressOutOfRange(_) => 108,
+            ElfError::TruncatedSegment(_) => 109,
+        }
+    }
+}
+
 pub struct Program {
     pub entry: u32,
     pub image: BTreeMap<u32, u32>,
 }
 
 impl Program {
-    pub fn load_elf(input: &[u8], max_mem: u32) -> Result<Program> {
+    pub fn load_elf(input: &[u8], max_mem: u32) -> Result<Program, ElfError> {
         let mut image: BTreeMap<u32, u32> = BTreeMap::new();
-        let elf = ElfFile::new(input).map_err(|err| anyhow!(err))?;
-        xmas_elf::header::sanity_check(&elf).map_err(|err| anyhow!(err))?;
+        let elf = ElfFile::new(input).map_err(ElfError::Malformed)?;
+        xmas_elf::header::sanity_check(&elf).map_err(ElfError::Malformed)?;
         if elf.header.pt1.class() != Class::ThirtyTwo {
-            bail!("Not a 32-bit ELF");
+            return Err(ElfError::NotElf32);
         }
         if elf.header.pt1.data() != Data::LittleEndian {
-            bail!("Not little endian");
+            return Err(ElfError::NotLittleEndian);
         }
         if elf.header.pt1.version() != Version::Current || elf.header.pt2.version() != 1 {
-            bail!("Invalid ELF version");
+            return Err(ElfError::InvalidVersion);
         }
         if elf.header.pt2.machine().as_machine() != Machine::RISC_V {
-            bail!("Invalid machine type, must be RISC-V");
+            return Err(ElfError::InvalidMachine);
         }
         if elf.header.pt2.type_().as_type() != xmas_elf::header::Type::Executable {
-            bail!("Invalid ELF type, must be executable");
+            return Err(ElfError::NotExecutable);
         }
-        let entry: u32 = elf.header.pt2.entry_point().try_into()?;
+        let entry: u32 = elf
+            .header
+            .pt2
+            .entry_point()
+            .try_into()
+            .map_err(|_| ElfError::InvalidEntry)?;
         if entry >= max_mem || entry % 4 != 0 {
-            bail!("Invalid entry point");
+            return Err(ElfError::InvalidEntry);
         }
         if elf.header.pt2.ph_count() > 256 {
-            bail!("Too many program headers");
+            return Err(ElfError::TooManyProgramHeaders);
+        }
+        // xmas_elf slices program headers out of the input without checking
+        // that the table fits, so check it here.
+        let ph_end = elf.header.pt2.ph_offset()
+            + elf.header.pt2.ph_count() as u64 * elf.header.pt2.ph_entry_size() as u64;
+        if ph_end > input.len() as u64 {
+            return Err(ElfError::Malformed("Program header table is truncated"));
         }
         for phdr in elf.program_iter() {
-            let type_ = phdr.get_type().map_err(|err| anyhow!(err))?;
+            let type_ = phdr.get_type().map_err(ElfError::Malformed)?;
             if type_ != xmas_elf::program::Type::Load {
                 continue;
             }
 
             let phdr = match phdr {
                 ProgramHeader::Ph32(phdr) => phdr,
-                ProgramHeader::Ph64(_) => bail!("Not a 32-bit ELF"),
+                ProgramHeader::Ph64(_) => return Err(ElfError::NotElf32),
             };
+            // Reject segments whose file contents don't fit in the input, or
+            // whose memory image runs past the top of guest memory, before
+            // touching any bytes.
+            let file_end = phdr.offset as u64 + phdr.file_size as u64;
+            if file_end > input.len() as u64 {
+                return Err(ElfError::TruncatedSegment(phdr.offset));
+            }
+            let mem_end = phdr.virtual_addr as u64 + phdr.mem_size as u64;
+            if mem_end > max_mem as u64 {
+                return Err(ElfError::AddressOutOfRange(mem_end));
+            }
             for i in (0..phdr.mem_size).step_by(4) {
                 let addr = phdr.virtual_addr + i;
+                if !memory::in_layout(addr as usize) {
+                    return Err(ElfError::AddressOutOfRange(addr as u64));
+                }
                 if i >= phdr.file_size {
                     // Past the file size, all zeros.
                     image.insert(addr, 0);
@@ -84,3 +155,47 @@ impl Program {
         Ok(Program { entry, image })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_methods::IO_PATH;
+
+    use super::{ElfError, Program};
+    use crate::platform::memory::MEM_SIZE;
+
+    fn load(input: &[u8]) -> Result<Program, ElfError> {
+        Program::load_elf(input, MEM_SIZE as u32)
+    }
+
+    // Returns the offset of the first PT_LOAD program header.
+    fn first_load(elf: &[u8]) -> usize {
+        let word = |offset: usize| u32::from_le_bytes(elf[offset..offset + 4].try_into().unwrap());
+        let half = |offset: usize| u16::from_le_bytes(elf[offset..offset + 2].try_into().unwrap());
+        let phoff = word(28) as usize;
+        let entsize = half(42) as usize;
+        (0..half(44) as usize)
+            .map(|i| phoff + i * entsize)
+            .find(|&ph| word(ph) == 1)
+            .unwrap()
+    }
+
+    #[test]
+    fn malformed() {
+        let elf = std::fs::read(IO_PATH).unwrap();
+        load(&elf).unwrap();
+
+        assert!(matches!(load(b"garbage"), Err(ElfError::Malformed(_))));
+        for len in [0, 16, 52] {
+            assert!(load(&elf[..len]).is_err());
+        }
+
+        let ph = first_load(&elf);
+        let mut bad = elf.clone();
+        bad[ph + 16..ph + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(load(&bad), Err(ElfError::TruncatedSegment(_))));
+
+        let mut bad = elf;
+        bad[ph + 8..ph + 12].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        assert!(matches!(load(&bad), Err(ElfError::AddressOutOfRange(_))));
+    }
+}
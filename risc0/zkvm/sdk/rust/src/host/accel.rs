@@ -0,0 +1,102 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Host-side handlers for guest accelerators that are serviced over
+//! sendrecv channels.
+
+use risc0_zkp::field::{baby_bear::Elem, bytes_to_elems, elems_to_bytes, Elem as _};
+
+use crate::platform::io::{FIELD_MALFORMED, FIELD_OK, FIELD_OP_INV, FIELD_UNKNOWN_OP};
+
+/// Handle a request on SENDRECV_CHANNEL_FIELD.
+///
+/// The request is an operation word followed by the operands, one field
+/// element per word.  The reply is a status word, followed by one field
+/// element per word if the status is [FIELD_OK].  Operands which are not
+/// canonical are rejected rather than reduced.  The request comes from the
+/// guest, so a malformed one is answered with an error status rather than
+/// failing the host.
+pub(crate) fn on_field(_channel: u32, buf: &[u8]) -> Vec<u8> {
+    let status = |status: u32| status.to_le_bytes().to_vec();
+    if buf.len() < 4 {
+        log::warn!("Guest sent a field request without an operation");
+        return status(FIELD_MALFORMED);
+    }
+    let (op, operands) = buf.split_at(4);
+    let op = u32::from_le_bytes(op.try_into().unwrap());
+    let mut operands: Vec<Elem> = match bytes_to_elems(operands) {
+        Ok(operands) => operands,
+        Err(err) => {
+            log::warn!("Guest sent invalid field operands: {err}");
+            return status(FIELD_MALFORMED);
+        }
+    };
+    let result: Vec<Elem> = match op {
+        FIELD_OP_INV => {
+            Elem::batch_inv(&mut operands);
+            operands
+        }
+        _ => {
+            log::warn!("Guest requested unknown field operation {op}");
+            return status(FIELD_UNKNOWN_OP);
+        }
+    };
+    let mut response = status(FIELD_OK);
+    response.extend(elems_to_bytes(&result));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp::field::{baby_bear::Elem, Elem as _};
+
+    use super::on_field;
+    use crate::platform::io::{
+        FIELD_MALFORMED, FIELD_OK, FIELD_OP_INV, FIELD_UNKNOWN_OP, SENDRECV_CHANNEL_FIELD,
+    };
+
+    fn encode(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    // Send a request, returning the status word and the results.
+    fn call(request: &[u32]) -> (u32, Vec<u32>) {
+        let reply: Vec<u32> = on_field(SENDRECV_CHANNEL_FIELD, &encode(request))
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        (reply[0], reply[1..].to_vec())
+    }
+
+    #[test]
+    fn field_inv() {
+        let (status, reply) = call(&[FIELD_OP_INV, 0, 1, 2, 1234]);
+        assert_eq!(status, FIELD_OK);
+        let reply: Vec<Elem> = reply.into_iter().map(Elem::from).collect();
+        assert_eq!(reply.len(), 4);
+        assert_eq!(reply[0], Elem::ZERO);
+        assert_eq!(reply[1], Elem::ONE);
+        assert_eq!(reply[2] * Elem::from(2u32), Elem::ONE);
+        assert_eq!(reply[3] * Elem::from(1234u32), Elem::ONE);
+    }
+
+    #[test]
+    fn field_malformed() {
+        let p = u32::from(-Elem::ONE) + 1;
+        assert_eq!(call(&[FIELD_OP_INV, 1, p]), (FIELD_MALFORMED, vec![]));
+        assert_eq!(call(&[0xffff]), (FIELD_UNKNOWN_OP, vec![]));
+        let reply = on_field(SENDRECV_CHANNEL_FIELD, &[1, 2]);
+        assert_eq!(reply, FIELD_MALFORMED.to_le_bytes());
+    }
+}
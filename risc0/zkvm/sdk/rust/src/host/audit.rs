@@ -0,0 +1,272 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signed audit manifests for proofs.
+//!
+//! With [ProverOpts::with_audit](super::ProverOpts::with_audit), each
+//! successful call to `Prover::run` also produces an [AuditManifest],
+//! available from `Prover::take_audit_manifest`.  The manifest is generated
+//! by the prover rather than by the caller, so it records what was actually
+//! proven:
+//!
+//! * `elf_sha256` and `method_id_sha256`: the guest.
+//! * `input_sha256`: the initial input words.
+//! * `transcript_sha256` and `requests`: every sendrecv request answered by a
+//!   callback, in order.  Each contributes its channel and the SHA-256 of its
+//!   request and response, except that responses on the secret channel are left
+//!   out.
+//! * `journal_sha256` and `seal_sha256`: the receipt.
+//! * `version`, `hash_suite`, `circuit_version`, `po2`, `skip_seal` and
+//!   `threads`: the software and parameters used.
+//! * `zk` and `random_seed`: whether the trace was padded for zero knowledge,
+//!   and where that randomness came from.  This prover always draws it from the
+//!   operating system, so proofs are reproducible in every field but the seal;
+//!   without zero knowledge there is none, and the seal is reproducible too.
+//! * `started_unix_ms` and `elapsed_ms`: when the run started and how long it
+//!   took.
+//!
+//! The manifest is signed over its [body](AuditManifest::body) by a
+//! [ManifestSigner].  [HmacSha256Signer] is provided for deployments with a
+//! shared audit key; others can plug in their own signature scheme.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use risc0_zkp::core::sha::{default_implementation, Digest, Sha};
+
+use super::{compat::VERSION, policy::CIRCUIT_VERSION, ProverOpts};
+use crate::platform::io::SENDRECV_CHANNEL_SECRET;
+
+/// Signs audit manifests.
+pub trait ManifestSigner: Sync {
+    /// The name of the signature scheme, recorded in the manifest.
+    fn scheme(&self) -> &str;
+
+    /// Sign `message`.
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Signs manifests with HMAC-SHA-256 under a shared key.
+pub struct HmacSha256Signer {
+    key: Vec<u8>,
+}
+
+impl HmacSha256Signer {
+    /// Construct a signer with the given key.
+    pub fn new(key: &[u8]) -> Self {
+        HmacSha256Signer { key: key.to_vec() }
+    }
+}
+
+const HMAC_BLOCK: usize = 64;
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    default_implementation().hash_bytes(bytes).to_bytes()
+}
+
+impl ManifestSigner for HmacSha256Signer {
+    fn scheme(&self) -> &str {
+        "hmac-sha256"
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let mut key = [0; HMAC_BLOCK];
+        if self.key.len() > HMAC_BLOCK {
+            key[..32].copy_from_slice(&sha256(&self.key));
+        } else {
+            key[..self.key.len()].copy_from_slice(&self.key);
+        }
+        let pad = |byte: u8| key.iter().map(move |k| k ^ byte);
+        let inner: Vec<u8> = pad(0x36).chain(message.iter().copied()).collect();
+        let outer: Vec<u8> = pad(0x5c).chain(sha256(&inner)).collect();
+        sha256(&outer).to_vec()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// A signed record of one proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditManifest {
+    /// The fields of the manifest, in order; see [the module docs](self).
+    pub fields: Vec<(String, String)>,
+
+    /// The name of the scheme the manifest was signed with.
+    pub scheme: String,
+
+    /// The signature over [AuditManifest::body].
+    pub signature: Vec<u8>,
+}
+
+impl AuditManifest {
+    /// The value of the field named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| &value[..])
+    }
+
+    /// The signed part of the manifest: one `key = value` line per field.
+    pub fn body(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(key, value)| format!("{key} = {value}\n"))
+            .collect()
+    }
+
+    /// Whether `signer` produces this manifest's signature.  This checks
+    /// schemes such as HMAC, where signing and verifying use the same key.
+    pub fn verify_with(&self, signer: &dyn ManifestSigner) -> bool {
+        signer.scheme() == self.scheme && signer.sign(self.body().as_bytes()) == self.signature
+    }
+}
+
+impl fmt::Display for AuditManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.body())?;
+        writeln!(f, "signature_scheme = {}", self.scheme)?;
+        writeln!(f, "signature = {}", hex(&self.signature))
+    }
+}
+
+// The size of each request's record in a transcript: its channel, followed
+// by the SHA-256 of its request and of its response.
+const RECORD_SIZE: usize = 4 + 32 + 32;
+
+/// The sendrecv requests of one run, in the order they were answered.
+#[derive(Default)]
+pub(crate) struct Transcript {
+    records: Mutex<Vec<u8>>,
+}
+
+impl Transcript {
+    pub fn record(&self, channel: u32, request: &[u8], response: &[u8]) {
+        let mut records = self.records.lock().unwrap();
+        records.extend_from_slice(&channel.to_le_bytes());
+        records.extend_from_slice(&sha256(request));
+        if channel == SENDRECV_CHANNEL_SECRET {
+            records.extend_from_slice(&[0; 32]);
+        } else {
+            records.extend_from_slice(&sha256(response));
+        }
+    }
+}
+
+/// What a prover knows about a successful run, from which the manifest is
+/// built.
+pub(crate) struct AuditRecord<'a> {
+    pub elf_sha256: Digest,
+    pub method_id: &'a [u8],
+    pub input: &'a [u8],
+    pub transcript: &'a Transcript,
+    pub journal: &'a [u8],
+    pub seal: &'a [u32],
+    pub po2: usize,
+    pub started: SystemTime,
+    pub elapsed: Duration,
+}
+
+impl<'a> AuditRecord<'a> {
+    pub fn sign(&self, opts: &ProverOpts, signer: &dyn ManifestSigner) -> AuditManifest {
+        let digest = |bytes: &[u8]| hex(&sha256(bytes));
+        let transcript = self.transcript.records.lock().unwrap();
+        let started = self.started.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let fields = [
+            ("version", VERSION.to_string()),
+            ("hash_suite", opts.hash_suite.name().to_string()),
+            ("circuit_version", CIRCUIT_VERSION.to_string()),
+            ("elf_sha256", self.elf_sha256.to_hex()),
+            ("method_id_sha256", digest(self.method_id)),
+            ("input_sha256", digest(self.input)),
+            ("transcript_sha256", digest(&transcript)),
+            ("requests", (transcript.len() / RECORD_SIZE).to_string()),
+            ("journal_sha256", digest(self.journal)),
+            (
+                "seal_sha256",
+                digest(&crate::serde::words_to_bytes(self.seal)),
+            ),
+            ("po2", self.po2.to_string()),
+            ("skip_seal", opts.skip_seal.to_string()),
+            (
+                "threads",
+                opts.threads.map_or("all".to_string(), |n| n.to_string()),
+            ),
+            ("zk", opts.zk.to_string()),
+            (
+                "random_seed",
+                if opts.zk { "os" } else { "none" }.to_string(),
+            ),
+            ("started_unix_ms", started.as_millis().to_string()),
+            ("elapsed_ms", self.elapsed.as_millis().to_string()),
+        ];
+        let mut manifest = AuditManifest {
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            scheme: signer.scheme().to_string(),
+            signature: Vec::new(),
+        };
+        manifest.signature = signer.sign(manifest.body().as_bytes());
+        manifest
+    }
+}
+
+/// The SHA-256 of a guest ELF, as recorded in manifests.
+pub(crate) fn elf_sha256(elf: &[u8]) -> Digest {
+    *default_implementation().hash_bytes(elf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex, AuditManifest, HmacSha256Signer, ManifestSigner};
+
+    #[test]
+    fn hmac_sha256() {
+        // RFC 4231, test cases 1 and 6.
+        let signer = HmacSha256Signer::new(&[0x0b; 20]);
+        assert_eq!(
+            hex(&signer.sign(b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        let signer = HmacSha256Signer::new(&[0xaa; 131]);
+        assert_eq!(
+            hex(&signer.sign(b"Test Using Larger Than Block-Size Key - Hash Key First")),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn signature() {
+        let signer = HmacSha256Signer::new(b"audit key");
+        let mut manifest = AuditManifest {
+            fields: vec![("po2".to_string(), "14".to_string())],
+            scheme: signer.scheme().to_string(),
+            signature: Vec::new(),
+        };
+        manifest.signature = signer.sign(manifest.body().as_bytes());
+        assert!(manifest.verify_with(&signer));
+        assert!(!manifest.verify_with(&HmacSha256Signer::new(b"other key")));
+        assert_eq!(manifest.get("po2"), Some("14"));
+
+        manifest.fields[0].1 = "15".to_string();
+        assert!(!manifest.verify_with(&signer));
+    }
+}
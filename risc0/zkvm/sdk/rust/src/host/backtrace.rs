@@ -0,0 +1,142 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Frame-pointer backtraces for faulting guests.
+//!
+//! Guests built with `risc0_build::GuestOptions::frame_pointers` keep `s0`
+//! pointing at the top of the current frame, with the return address saved at
+//! `fp - 4` and the caller's frame pointer at `fp - 8`.  When an execution
+//! faults, the executor follows that chain through guest memory, so a backtrace
+//! of return addresses is available even for a stripped ELF.  Symbolize the
+//! addresses against the unstripped binary with `addr2line` or similar.
+
+use std::fmt::{self, Display};
+
+use crate::platform::{memory, WORD_SIZE};
+
+/// The deepest backtrace recorded; longer chains are truncated.
+pub const MAX_FRAMES: usize = 64;
+
+/// Return addresses of the guest's call stack at the point it faulted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Backtrace {
+    /// The faulting pc followed by each caller's return address, innermost
+    /// first.
+    pub frames: Vec<u32>,
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, pc) in self.frames.iter().enumerate() {
+            writeln!(f, "#{i:<2} 0x{pc:08x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Walk the frame-pointer chain starting at `fp`, reading guest words with
+/// `load`.  The walk stops at the first frame which doesn't look like one: an
+/// unaligned or out-of-stack frame pointer, a return address outside the
+/// program, or a caller frame that isn't above the current one.
+pub(crate) fn walk(pc: u32, fp: u32, load: impl Fn(u32) -> Option<u32>) -> Backtrace {
+    let word = WORD_SIZE as u32;
+    let mut frames = vec![pc];
+    let mut fp = fp;
+    while frames.len() < MAX_FRAMES {
+        if fp % word != 0 || fp < 2 * word || fp as usize > memory::STACK.end() {
+            break;
+        }
+        let (ra, next) = match (load(fp - word), load(fp - 2 * word)) {
+            (Some(ra), Some(next)) => (ra, next),
+            _ => break,
+        };
+        if !memory::PROG.contains(ra as usize) {
+            break;
+        }
+        frames.push(ra);
+        if next <= fp {
+            break;
+        }
+        fp = next;
+    }
+    Backtrace { frames }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{walk, Backtrace, MAX_FRAMES};
+    use crate::platform::memory::PROG;
+
+    const PC: u32 = PROG.start() as u32 + 0x100;
+
+    // Lay out frames at the given frame pointers, each returning to a
+    // distinct address in PROG and linking to the next.
+    fn stack(fps: &[u32]) -> BTreeMap<u32, u32> {
+        let mut mem = BTreeMap::new();
+        for (i, fp) in fps.iter().enumerate() {
+            let next = fps.get(i + 1).copied().unwrap_or(0);
+            mem.insert(fp - 4, PC + 4 * (i as u32 + 1));
+            mem.insert(fp - 8, next);
+        }
+        mem
+    }
+
+    #[test]
+    fn chain() {
+        let mem = stack(&[0x1000, 0x1040, 0x1100]);
+        let bt = walk(PC, 0x1000, |addr| mem.get(&addr).copied());
+        assert_eq!(bt.frames, vec![PC, PC + 4, PC + 8, PC + 12]);
+        assert_eq!(
+            bt.to_string(),
+            "#0  0x02000100\n#1  0x02000104\n#2  0x02000108\n#3  0x0200010c\n"
+        );
+    }
+
+    #[test]
+    fn garbage_frames() {
+        // No frame pointer at all.
+        assert_eq!(walk(PC, 0, |_| Some(0)).frames, vec![PC]);
+        // An unaligned frame pointer.
+        assert_eq!(walk(PC, 0x1002, |_| Some(PC)).frames, vec![PC]);
+        // Unmapped memory.
+        assert_eq!(walk(PC, 0x1000, |_| None).frames, vec![PC]);
+        // A return address outside the program.
+        let mut mem = stack(&[0x1000]);
+        mem.insert(0x1000 - 4, 0x1234);
+        assert_eq!(
+            walk(PC, 0x1000, |addr| mem.get(&addr).copied()),
+            Backtrace { frames: vec![PC] }
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        // A frame which links to itself would loop forever.
+        let mut mem = stack(&[0x1000]);
+        mem.insert(0x1000 - 8, 0x1000);
+        assert_eq!(
+            walk(PC, 0x1000, |addr| mem.get(&addr).copied())
+                .frames
+                .len(),
+            2
+        );
+
+        let fps: Vec<u32> = (1..=2 * MAX_FRAMES as u32).map(|i| i * 0x100).collect();
+        let mem = stack(&fps);
+        let bt = walk(PC, fps[0], |addr| mem.get(&addr).copied());
+        assert_eq!(bt.frames.len(), MAX_FRAMES);
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Memoization of responses on deterministic sendrecv channels.
+//!
+//! Re-executing a guest, e.g. once to preflight and once to prove, or across
+//! repeated test runs, would otherwise repeat every request to the services
+//! behind its channels. A [ResponseCache] answers repeated requests from
+//! memory, and can be persisted so that later runs see the same responses.
+
+use std::{collections::BTreeMap, fs, path::Path, sync::Mutex};
+
+use super::{Exception, Result};
+use crate::serde::{bytes_to_words, words_to_bytes};
+
+// Responses keyed by channel and request bytes.
+type Entries = BTreeMap<(u32, Vec<u8>), Vec<u8>>;
+
+/// A cache of sendrecv responses, keyed by channel and request bytes.
+#[derive(Default)]
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cache previously written by [ResponseCache::save], or return an
+    /// empty cache if `path` does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let bytes = fs::read(path).map_err(|err| Exception::new(&err.to_string()))?;
+        if bytes.len() % 4 != 0 {
+            return Err(Exception::new("Truncated response cache"));
+        }
+        let words = bytes_to_words(&bytes);
+        let entries: Vec<(u32, Vec<u8>, Vec<u8>)> =
+            crate::serde::from_slice(&words).map_err(|err| Exception::new(&err.to_string()))?;
+        let entries = entries
+            .into_iter()
+            .map(|(channel, request, response)| ((channel, request), response))
+            .collect();
+        Ok(ResponseCache {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Write the contents of this cache to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let entries: Vec<(u32, Vec<u8>, Vec<u8>)> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((channel, request), response)| (*channel, request.clone(), response.clone()))
+            .collect();
+        let words =
+            crate::serde::to_vec(&entries).map_err(|err| Exception::new(&err.to_string()))?;
+        fs::write(path, words_to_bytes(&words)).map_err(|err| Exception::new(&err.to_string()))
+    }
+
+    /// The number of cached responses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns true if no responses are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the cached response to `request` on `channel`, if there is one.
+    pub(crate) fn get(&self, channel: u32, request: &[u8]) -> Option<Vec<u8>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(channel, request.to_vec()))
+            .cloned()
+    }
+
+    /// Cache `response` to `request` on `channel`, unless a response is
+    /// already cached.
+    pub(crate) fn insert(&self, channel: u32, request: &[u8], response: Vec<u8>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .entry((channel, request.to_vec()))
+            .or_insert(response);
+    }
+
+    /// Return the cached response to `request` on `channel`, computing and
+    /// caching it with `respond` if there is none.
+    pub(crate) fn get_or_insert_with(
+        &self,
+        channel: u32,
+        request: &[u8],
+        respond: impl FnOnce() -> Vec<u8>,
+    ) -> Vec<u8> {
+        let key = (channel, request.to_vec());
+        if let Some(response) = self.entries.lock().unwrap().get(&key) {
+            return response.clone();
+        }
+        // Don't hold the lock while waiting on a potentially slow service.
+        let response = respond();
+        self.entries
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert(response)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::ResponseCache;
+    use crate::host::ProverOpts;
+
+    #[test]
+    fn memoizes_callback() {
+        let calls = AtomicUsize::new(0);
+        let cache = ResponseCache::new();
+        let opts = ProverOpts::default()
+            .with_sendrecv_callback(10, |_, buf| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                buf.iter().rev().copied().collect()
+            })
+            .with_response_cache(10, &cache);
+        let callback = &opts.sendrecv_callbacks[&10];
+
+        assert_eq!(callback(10, &[1, 2, 3]), [3, 2, 1]);
+        assert_eq!(callback(10, &[1, 2, 3]), [3, 2, 1]);
+        assert_eq!(callback(10, &[4, 5]), [5, 4]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("responses");
+        assert!(ResponseCache::load(&path).unwrap().is_empty());
+
+        let cache = ResponseCache::new();
+        cache.get_or_insert_with(1, b"question", || b"answer".to_vec());
+        cache.get_or_insert_with(2, b"", Vec::new);
+        cache.save(&path).unwrap();
+
+        let cache = ResponseCache::load(&path).unwrap();
+        assert_eq!(cache.len(), 2);
+        let response = cache.get_or_insert_with(1, b"question", || unreachable!());
+        assert_eq!(response, b"answer");
+    }
+}
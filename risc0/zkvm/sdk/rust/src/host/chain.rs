@@ -0,0 +1,145 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chains of receipts for stateful applications.
+//!
+//! Each link of a [ReceiptChain] is a receipt whose journal begins with the
+//! claim digest of the link before it, where the claim digest of a receipt is
+//! the SHA-256 of the [image ID](image_id) of its method followed by its
+//! journal (see [claim_digest]). The first link instead begins with a genesis
+//! digest chosen by the application.
+//!
+//! To bind its state to the chain, a guest should take the previous journal
+//! and the image ID of the method that produced it as input, read its state
+//! from the journal, and commit the claim digest of the two before
+//! committing its new state.  A guest can't know its own image ID, so one
+//! which follows itself takes it as input too; a verifier which supplies
+//! the method IDs it expects catches any other ID.
+
+use anyhow::{bail, Result};
+use risc0_zkp::core::sha::{default_implementation, Digest, Sha, DIGEST_WORDS};
+
+use super::Receipt;
+
+/// The image ID of a method: the SHA-256 of its serialized method ID.
+/// Claims commit to this rather than to the method ID itself, which holds a
+/// code root for each trace size.
+pub fn image_id(method_id: &[u8]) -> Digest {
+    *default_implementation().hash_bytes(method_id)
+}
+
+/// Compute the digest by which the next link of a chain refers to a receipt
+/// of the method `method_id` with the given journal: the SHA-256 of the
+/// bytes of the method's [image_id], followed by the journal.
+pub fn claim_digest(method_id: &[u8], journal: &[u8]) -> Digest {
+    let mut claim = image_id(method_id).to_bytes().to_vec();
+    claim.extend_from_slice(journal);
+    *default_implementation().hash_bytes(&claim)
+}
+
+/// Check that `receipt` is a valid link from `prev`, returning its claim
+/// digest.
+fn verify_link(prev: &Digest, method_id: &[u8], receipt: &Receipt) -> Result<Digest> {
+    receipt.verify(method_id)?;
+    check_link(prev, method_id, receipt)
+}
+
+/// Check that the journal of `receipt` begins with `prev`, without verifying
+/// its seal, returning its claim digest as a receipt of `method_id`.
+pub(super) fn check_link(prev: &Digest, method_id: &[u8], receipt: &Receipt) -> Result<Digest> {
+    let journal = receipt.get_journal_vec()?;
+    if journal.len() < DIGEST_WORDS {
+        bail!("Journal is too short to link to a previous claim");
+    }
+    if journal[..DIGEST_WORDS] != *prev.as_slice() {
+        bail!("Receipt does not link to the previous claim {prev}");
+    }
+    Ok(claim_digest(method_id, &receipt.get_journal()?))
+}
+
+/// A sequence of receipts, each of which commits to the claim of the one
+/// before it.
+pub struct ReceiptChain {
+    genesis: Digest,
+    head: Digest,
+    links: Vec<(Vec<u8>, Receipt)>,
+}
+
+impl ReceiptChain {
+    /// Start an empty chain from the given genesis digest.
+    pub fn new(genesis: Digest) -> Self {
+        ReceiptChain {
+            genesis,
+            head: genesis,
+            links: Vec::new(),
+        }
+    }
+
+    /// The genesis digest of this chain.
+    pub fn genesis(&self) -> &Digest {
+        &self.genesis
+    }
+
+    /// The claim digest of the last link, or the genesis digest if the chain
+    /// is empty.  The next link must commit to this digest.
+    pub fn head(&self) -> &Digest {
+        &self.head
+    }
+
+    /// The number of links in this chain.
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Returns true if this chain has no links.
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// The links of this chain, as pairs of method ID and receipt.
+    pub fn links(&self) -> impl Iterator<Item = (&[u8], &Receipt)> {
+        self.links
+            .iter()
+            .map(|(method_id, receipt)| (method_id.as_slice(), receipt))
+    }
+
+    /// Verify `receipt` against `method_id` and the head of this chain, and
+    /// append it if it is a valid link.
+    pub fn append(&mut self, method_id: &[u8], receipt: Receipt) -> Result<()> {
+        self.head = verify_link(&self.head, method_id, &receipt)?;
+        self.links.push((method_id.to_vec(), receipt));
+        Ok(())
+    }
+}
+
+/// Verify every link of `chain` starting from `genesis`, returning the claim
+/// digest at its head.  Each link must be a receipt of one of `method_ids`.
+///
+/// Neither the genesis digest nor the method IDs recorded in the chain are
+/// trusted; verifiers must supply the ones they expect.
+pub fn verify_chain(
+    genesis: &Digest,
+    method_ids: &[&[u8]],
+    chain: &ReceiptChain,
+) -> Result<Digest> {
+    let mut head = *genesis;
+    for (i, (recorded, receipt)) in chain.links().enumerate() {
+        let method_id = match method_ids.iter().find(|method_id| **method_id == recorded) {
+            Some(method_id) => *method_id,
+            None => bail!("Link {i} of the chain is not a receipt of an expected method"),
+        };
+        head = verify_link(&head, method_id, receipt)?;
+    }
+    Ok(head)
+}
@@ -0,0 +1,369 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encodings for storing and transmitting receipts.
+//!
+//! A [ReceiptCodec] turns a [Receipt] into bytes and back.  Every built-in
+//! codec encodes the same [ReceiptParts], the journal bytes and the seal, so
+//! receipts from either prover backend encode identically and a receipt can
+//! be moved between codecs without loss.
+//!
+//! [Protobuf] follows the `risc0.zkvm.Receipt` message in
+//! `proto/receipt.proto`, which is generated by [proto_schema].  Services
+//! with protobuf APIs can import that file and carry a receipt as a message
+//! field, filling it from [ReceiptParts] rather than nesting an opaque
+//! encoded blob inside their own messages.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::Receipt;
+
+/// Encodes receipts for storage or transport.
+pub trait ReceiptCodec {
+    /// The name this codec is looked up by with [by_name].
+    fn name(&self) -> &'static str;
+
+    /// The media type of the encoded bytes.
+    fn media_type(&self) -> &'static str;
+
+    /// Encodes `receipt`.
+    fn encode(&self, receipt: &Receipt) -> Result<Vec<u8>>;
+
+    /// Decodes a receipt encoded by [ReceiptCodec::encode].
+    fn decode(&self, bytes: &[u8]) -> Result<Receipt>;
+}
+
+/// The contents of a receipt, independent of the prover backend that made it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptParts {
+    /// The journal committed by the guest.
+    pub journal: Vec<u8>,
+
+    /// The seal proving the journal.
+    pub seal: Vec<u32>,
+}
+
+impl ReceiptParts {
+    /// Copies the parts out of `receipt`.
+    pub fn from_receipt(receipt: &Receipt) -> Result<Self> {
+        Ok(ReceiptParts {
+            journal: receipt.get_journal()?.to_vec(),
+            seal: receipt.get_seal()?.to_vec(),
+        })
+    }
+
+    /// Reassembles a receipt from its parts.
+    pub fn into_receipt(self) -> Result<Receipt> {
+        Ok(Receipt::new(&self.journal, &self.seal)?)
+    }
+}
+
+/// Encodes receipts with [bincode](https://docs.rs/bincode).
+pub struct Bincode;
+
+impl ReceiptCodec for Bincode {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/octet-stream"
+    }
+
+    fn encode(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&ReceiptParts::from_receipt(receipt)?)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Receipt> {
+        bincode::deserialize::<ReceiptParts>(bytes)?.into_receipt()
+    }
+}
+
+/// Encodes receipts as CBOR.
+pub struct Cbor;
+
+impl ReceiptCodec for Cbor {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn encode(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&ReceiptParts::from_receipt(receipt)?, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Receipt> {
+        ciborium::de::from_reader::<ReceiptParts, _>(bytes)?.into_receipt()
+    }
+}
+
+/// Encodes receipts as the protobuf message in `proto/receipt.proto`.
+pub struct Protobuf;
+
+impl ReceiptCodec for Protobuf {
+    fn name(&self) -> &'static str {
+        "protobuf"
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/x-protobuf"
+    }
+
+    fn encode(&self, receipt: &Receipt) -> Result<Vec<u8>> {
+        Ok(proto::encode(&ReceiptParts::from_receipt(receipt)?))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Receipt> {
+        proto::decode(bytes)?.into_receipt()
+    }
+}
+
+/// The built-in codecs.
+pub const CODECS: &[&dyn ReceiptCodec] = &[&Bincode, &Cbor, &Protobuf];
+
+/// Looks up a built-in codec by its [name](ReceiptCodec::name).
+pub fn by_name(name: &str) -> Option<&'static dyn ReceiptCodec> {
+    CODECS.iter().copied().find(|codec| codec.name() == name)
+}
+
+/// Generates the protobuf schema for [ReceiptParts], as checked in at
+/// `proto/receipt.proto`.
+pub fn proto_schema() -> String {
+    let mut schema = String::from("// Generated by risc0_zkvm::host::codec::proto_schema.\n\n");
+    schema += "syntax = \"proto3\";\n\n";
+    writeln!(schema, "package {};\n", proto::PACKAGE).unwrap();
+    schema += "message Receipt {\n";
+    for field in proto::FIELDS {
+        writeln!(schema, "  {} {} = {};", field.ty, field.name, field.tag).unwrap();
+    }
+    schema += "}\n";
+    schema
+}
+
+// A hand-rolled encoding of the two-field Receipt message, which is simpler
+// than generating code for it.
+mod proto {
+    use anyhow::{anyhow, bail, Result};
+
+    use super::ReceiptParts;
+
+    pub const PACKAGE: &str = "risc0.zkvm";
+
+    pub struct Field {
+        pub name: &'static str,
+        pub tag: u32,
+        pub ty: &'static str,
+    }
+
+    const JOURNAL: u32 = 1;
+    const SEAL: u32 = 2;
+
+    pub const FIELDS: &[Field] = &[
+        Field {
+            name: "journal",
+            tag: JOURNAL,
+            ty: "bytes",
+        },
+        Field {
+            name: "seal",
+            tag: SEAL,
+            ty: "repeated fixed32",
+        },
+    ];
+
+    const VARINT: u32 = 0;
+    const FIXED64: u32 = 1;
+    const LEN: u32 = 2;
+    const FIXED32: u32 = 5;
+
+    fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn put_bytes(out: &mut Vec<u8>, tag: u32, bytes: &[u8]) {
+        put_varint(out, (tag << 3 | LEN) as u64);
+        put_varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    // Empty fields are omitted, as proto3 does for default values.
+    pub fn encode(parts: &ReceiptParts) -> Vec<u8> {
+        let mut out = Vec::new();
+        if !parts.journal.is_empty() {
+            put_bytes(&mut out, JOURNAL, &parts.journal);
+        }
+        if !parts.seal.is_empty() {
+            let packed: Vec<u8> = parts.seal.iter().flat_map(|w| w.to_le_bytes()).collect();
+            put_bytes(&mut out, SEAL, &packed);
+        }
+        out
+    }
+
+    struct Reader<'a>(&'a [u8]);
+
+    impl<'a> Reader<'a> {
+        fn varint(&mut self) -> Result<u64> {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let (&byte, rest) = self.0.split_first().ok_or_else(|| anyhow!("Truncated"))?;
+                self.0 = rest;
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(value);
+                }
+            }
+            bail!("Varint is too long")
+        }
+
+        fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+            if len > self.0.len() {
+                bail!("Truncated");
+            }
+            let (head, rest) = self.0.split_at(len);
+            self.0 = rest;
+            Ok(head)
+        }
+    }
+
+    // Accepts fields in any order, unpacked seal words and unknown fields,
+    // as any protobuf parser would.
+    pub fn decode(bytes: &[u8]) -> Result<ReceiptParts> {
+        let mut reader = Reader(bytes);
+        let mut parts = ReceiptParts {
+            journal: Vec::new(),
+            seal: Vec::new(),
+        };
+        while !reader.0.is_empty() {
+            let key = reader.varint()?;
+            let (tag, wire) = ((key >> 3) as u32, (key & 7) as u32);
+            match (tag, wire) {
+                (JOURNAL, LEN) => {
+                    let len = reader.varint()? as usize;
+                    parts.journal = reader.take(len)?.to_vec();
+                }
+                (SEAL, LEN) => {
+                    let len = reader.varint()? as usize;
+                    let packed = reader.take(len)?;
+                    if packed.len() % 4 != 0 {
+                        bail!("Packed seal is not a whole number of words");
+                    }
+                    parts.seal.extend(
+                        packed
+                            .chunks(4)
+                            .map(|w| u32::from_le_bytes(w.try_into().unwrap())),
+                    );
+                }
+                (SEAL, FIXED32) => {
+                    parts
+                        .seal
+                        .push(u32::from_le_bytes(reader.take(4)?.try_into().unwrap()));
+                }
+                (JOURNAL | SEAL, _) => bail!("Wrong wire type {wire} for field {tag}"),
+                (_, VARINT) => {
+                    reader.varint()?;
+                }
+                (_, FIXED64) => {
+                    reader.take(8)?;
+                }
+                (_, LEN) => {
+                    let len = reader.varint()? as usize;
+                    reader.take(len)?;
+                }
+                (_, FIXED32) => {
+                    reader.take(4)?;
+                }
+                _ => bail!("Unsupported wire type {wire}"),
+            }
+        }
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{by_name, proto, proto_schema, ReceiptParts, CODECS};
+    use crate::host::Receipt;
+
+    fn parts() -> ReceiptParts {
+        ReceiptParts {
+            journal: b"journal!".to_vec(),
+            seal: vec![1, 0xdead_beef, 0xffff_ffff],
+        }
+    }
+
+    fn receipt() -> Receipt {
+        parts().into_receipt().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for codec in CODECS {
+            let bytes = codec.encode(&receipt()).unwrap();
+            let decoded = codec.decode(&bytes).unwrap();
+            assert_eq!(ReceiptParts::from_receipt(&decoded).unwrap(), parts());
+            assert!(by_name(codec.name()).is_some());
+        }
+        assert!(by_name("json").is_none());
+    }
+
+    #[test]
+    fn protobuf_wire_format() {
+        let bytes = proto::encode(&parts());
+        let mut expected = vec![0x0a, 8];
+        expected.extend(b"journal!");
+        expected.extend([
+            0x12, 12, 1, 0, 0, 0, 0xef, 0xbe, 0xad, 0xde, 0xff, 0xff, 0xff, 0xff,
+        ]);
+        assert_eq!(bytes, expected);
+        assert!(proto::encode(&ReceiptParts {
+            journal: vec![],
+            seal: vec![]
+        })
+        .is_empty());
+
+        // Unknown fields are skipped and unpacked seal words accepted.
+        let mut bytes = vec![0x18, 0x96, 0x01, 0x15, 1, 0, 0, 0];
+        bytes.extend([0x0a, 8]);
+        bytes.extend(b"journal!");
+        bytes.extend([0x15, 0xef, 0xbe, 0xad, 0xde, 0x15, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(proto::decode(&bytes).unwrap(), parts());
+
+        assert!(proto::decode(&[0x0a, 9, 0]).is_err());
+        assert!(proto::decode(&[0x12, 3, 0, 0, 0]).is_err());
+        assert!(proto::decode(&[0x08, 1]).is_err());
+    }
+
+    #[test]
+    fn schema_is_current() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/proto/receipt.proto");
+        let checked_in = std::fs::read_to_string(path).unwrap();
+        let schema = proto_schema();
+        assert!(
+            checked_in.ends_with(&schema),
+            "{path} is out of date; regenerate it from proto_schema():\n{schema}"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential testing between versions of this crate.
+//!
+//! Before upgrading, an integrator records a snapshot of a [Corpus] of
+//! their guests with the release they run today, then checks it with the
+//! candidate release:
+//!
+//! ```no_run
+//! # use risc0_zkvm::host::compat::{check_snapshot, Corpus};
+//! # fn main() -> anyhow::Result<()> {
+//! # let elf = vec![];
+//! // With the current release:
+//! Corpus::new().with_guest("transfer", elf, vec![1, 2, 3]).record("snapshot")?;
+//! // With the candidate release:
+//! let report = check_snapshot("snapshot")?;
+//! assert!(report.is_ok(), "{report}");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Checking verifies each recorded receipt with the running version, which
+//! is the upgrade guarantee for receipts already issued, and proves each
+//! guest again to confirm that its journal is unchanged.  Recording with
+//! the new release and checking with the old one tests the reverse, for
+//! releases which promise that old verifiers accept new receipts.
+//!
+//! A snapshot is a directory holding one [GoldenVector] per guest, in
+//! subdirectories named after the guests, and a `version` file naming the
+//! release that recorded it.
+
+use std::{fmt, fs, path::Path};
+
+use anyhow::{Context, Result};
+
+use super::{
+    policy::HashSuite,
+    testdata::{verify_golden, GoldenVector},
+};
+
+/// The release of this crate, as recorded in snapshots.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A set of guests, with inputs, to compare across versions.
+#[derive(Clone, Debug, Default)]
+pub struct Corpus {
+    guests: Vec<(String, Vec<u8>, Vec<u32>)>,
+}
+
+impl Corpus {
+    /// Construct an empty [Corpus].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the guest `elf`, run with `input`, under `name`.  The name is
+    /// used as a directory name in snapshots.
+    pub fn with_guest(mut self, name: &str, elf: Vec<u8>, input: Vec<u32>) -> Self {
+        self.guests.push((name.to_string(), elf, input));
+        self
+    }
+
+    /// Prove every guest with this version, committing with SHA-256, and
+    /// write the results as a snapshot in `dir`.
+    pub fn record(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("version"), VERSION)?;
+        for (name, elf, input) in &self.guests {
+            GoldenVector::generate(elf, input, HashSuite::Sha256)
+                .and_then(|vector| vector.save(dir.join(name)))
+                .with_context(|| format!("Recording {name}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of checking one guest of a snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestReport {
+    /// The name of the guest.
+    pub name: String,
+
+    /// Why the recorded receipt failed to verify, if it did.
+    pub verify_error: Option<String>,
+
+    /// Why the guest could not be proven again, or how its journal changed,
+    /// if either happened.
+    pub journal_error: Option<String>,
+}
+
+impl GuestReport {
+    /// Whether the guest passed every check.
+    pub fn is_ok(&self) -> bool {
+        self.verify_error.is_none() && self.journal_error.is_none()
+    }
+}
+
+/// The outcome of [check_snapshot].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompatReport {
+    /// The release which recorded the snapshot.
+    pub recorded: String,
+
+    /// The release which checked it.
+    pub checked: String,
+
+    /// The outcome for each guest, ordered by name.
+    pub guests: Vec<GuestReport>,
+}
+
+impl CompatReport {
+    /// Whether every guest passed every check.
+    pub fn is_ok(&self) -> bool {
+        self.guests.iter().all(GuestReport::is_ok)
+    }
+}
+
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Snapshot from {} checked with {}:",
+            self.recorded, self.checked
+        )?;
+        for guest in &self.guests {
+            let status = if guest.is_ok() { "ok" } else { "FAILED" };
+            writeln!(f, "  {}: {status}", guest.name)?;
+            for error in [&guest.verify_error, &guest.journal_error]
+                .into_iter()
+                .flatten()
+            {
+                writeln!(f, "    {error}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_guest(name: String, recorded: &GoldenVector) -> GuestReport {
+    let verify_error = verify_golden(recorded).err().map(|err| format!("{err:#}"));
+    let journal_error = match GoldenVector::generate(&recorded.elf, &recorded.input, recorded.suite)
+    {
+        Ok(current) if current.journal == recorded.journal => None,
+        Ok(current) => Some(format!(
+            "Journal changed from {} to {} bytes{}",
+            recorded.journal.len(),
+            current.journal.len(),
+            if current.journal.len() == recorded.journal.len() {
+                " with different contents"
+            } else {
+                ""
+            }
+        )),
+        Err(err) => Some(format!("Proving failed: {err:#}")),
+    };
+    GuestReport {
+        name,
+        verify_error,
+        journal_error,
+    }
+}
+
+/// Check the snapshot in `dir` against this version.  Errors reading the
+/// snapshot are returned directly; failed checks are reported in the
+/// [CompatReport].
+pub fn check_snapshot(dir: impl AsRef<Path>) -> Result<CompatReport> {
+    let dir = dir.as_ref();
+    let recorded = fs::read_to_string(dir.join("version"))
+        .with_context(|| format!("Reading {}", dir.join("version").display()))?;
+    let mut guests = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let vector = GoldenVector::load(&path)?;
+            guests.push(check_guest(name, &vector));
+        }
+    }
+    guests.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(CompatReport {
+        recorded: recorded.trim().to_string(),
+        checked: VERSION.to_string(),
+        guests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_methods::SHA_PATH;
+
+    use super::*;
+    use crate::serde::to_vec;
+
+    #[test]
+    fn snapshot() {
+        let elf = fs::read(SHA_PATH).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        Corpus::new()
+            .with_guest("sha_abc", elf.clone(), to_vec(&"abc").unwrap())
+            .with_guest("sha_empty", elf, to_vec(&"").unwrap())
+            .record(dir.path())
+            .unwrap();
+
+        let report = check_snapshot(dir.path()).unwrap();
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.recorded, VERSION);
+        let names: Vec<_> = report.guests.iter().map(|guest| &guest.name[..]).collect();
+        assert_eq!(names, ["sha_abc", "sha_empty"]);
+
+        // A guest whose output changed between versions.
+        let path = dir.path().join("sha_abc");
+        let mut vector = GoldenVector::load(&path).unwrap();
+        vector.journal[0] ^= 1;
+        vector.save(&path).unwrap();
+        let report = check_snapshot(dir.path()).unwrap();
+        assert!(!report.is_ok());
+        assert!(report.guests[0].verify_error.is_some());
+        assert!(report.guests[0].journal_error.is_some());
+        assert!(report.guests[1].is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Receipts for executions proven in several segments.
+//!
+//! A [CompositeReceipt] holds one receipt per segment of an execution.  The
+//! segments are linked as in a [ReceiptChain](super::ReceiptChain): the
+//! journal of each segment after the first begins with the
+//! [claim digest](super::claim_digest) of the segment before it, so the
+//! linking structure can be checked by hashing journals alone.
+//!
+//! This version of the prover proves each execution as a single segment, so
+//! a guest which splits its work takes the previous segment's journal as
+//! input, as described for chains.
+//!
+//! Verifying the seal of every segment costs time proportional to the number
+//! of segments.  [CompositeReceipt::verify_sample] instead checks the linking
+//! structure in full and verifies the seals of a random subset, for
+//! consumers willing to trade assurance for latency: if any `k` of `n`
+//! segments are invalid, a sample of `m` segments misses all of them with
+//! probability at most `(1 - k / n)^m`.
+
+use anyhow::{bail, Result};
+use rand::{rngs::StdRng, seq::index, SeedableRng};
+use risc0_zkp::core::sha::Digest;
+
+use super::{chain::check_link, claim_digest, Receipt};
+
+/// A receipt for an execution proven as a sequence of linked segments.
+pub struct CompositeReceipt {
+    segments: Vec<Receipt>,
+}
+
+impl CompositeReceipt {
+    /// Construct a [CompositeReceipt] from the receipts of each segment of
+    /// an execution of `method_id`, in order.  This checks the linking
+    /// structure but not the seals.
+    pub fn new(method_id: &[u8], segments: Vec<Receipt>) -> Result<Self> {
+        let composite = CompositeReceipt { segments };
+        composite.verify_links(method_id)?;
+        Ok(composite)
+    }
+
+    /// The receipts of each segment, in order.
+    pub fn segments(&self) -> &[Receipt] {
+        &self.segments
+    }
+
+    /// A copy of the journal of the last segment, which holds the output of
+    /// the execution.
+    pub fn get_journal(&self) -> Result<Vec<u8>> {
+        match self.segments.last() {
+            Some(last) => Ok(last.get_journal()?.to_vec()),
+            None => bail!("Composite receipt has no segments"),
+        }
+    }
+
+    /// Verify the linking structure and the seal of every segment against
+    /// `method_id`.
+    pub fn verify(&self, method_id: &[u8]) -> Result<()> {
+        self.verify_links(method_id)?;
+        for receipt in self.segments.iter() {
+            receipt.verify(method_id)?;
+        }
+        Ok(())
+    }
+
+    /// Verify the linking structure, and the seals of a random subset of
+    /// the segments against `method_id`.
+    ///
+    /// The subset holds a fraction `rate` of the segments, rounded up, and
+    /// is chosen from `seed`.  A verifier must choose the seed where the
+    /// prover cannot predict it, since a prover who knows which segments
+    /// will be sampled can forge the others.  A `rate` of 1 is equivalent
+    /// to [CompositeReceipt::verify], and a `rate` of 0 checks only the
+    /// linking structure.
+    pub fn verify_sample(&self, method_id: &[u8], rate: f64, seed: u64) -> Result<()> {
+        self.verify_links(method_id)?;
+        for i in self.sample(rate, seed)? {
+            self.segments[i].verify(method_id)?;
+        }
+        Ok(())
+    }
+
+    /// The indices of the segments whose seals
+    /// [CompositeReceipt::verify_sample] verifies for the given `rate` and
+    /// `seed`, in ascending order.
+    pub fn sample(&self, rate: f64, seed: u64) -> Result<Vec<usize>> {
+        if !(0.0..=1.0).contains(&rate) {
+            bail!("Sampling rate {rate} is not between 0 and 1");
+        }
+        let len = self.segments.len();
+        let amount = ((len as f64) * rate).ceil() as usize;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut indices = index::sample(&mut rng, len, amount.min(len)).into_vec();
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
+    fn verify_links(&self, method_id: &[u8]) -> Result<()> {
+        let mut segments = self.segments.iter();
+        let mut prev: Digest = match segments.next() {
+            Some(first) => claim_digest(method_id, &first.get_journal()?),
+            None => bail!("Composite receipt has no segments"),
+        };
+        for receipt in segments {
+            prev = check_link(&prev, method_id, receipt)?;
+        }
+        Ok(())
+    }
+}
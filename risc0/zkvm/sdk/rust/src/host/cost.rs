@@ -0,0 +1,129 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimate the cost of proving an execution before running the prover.
+//!
+//! A [CostEstimator] maps the resources consumed by an execution (cycles,
+//! segments and seal size) onto user-supplied pricing units, so that a
+//! proving service can quote a price without generating a seal.
+
+use risc0_zkp::MAX_CYCLES;
+
+/// The resources used by an execution, as measured by a dry run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionCost {
+    /// The number of cycles executed by the guest.
+    pub cycles: u64,
+
+    /// The number of segments needed to prove the execution.
+    pub segments: u64,
+
+    /// The size of the resulting seal, in bytes.
+    pub seal_bytes: u64,
+}
+
+impl ExecutionCost {
+    /// Construct an [ExecutionCost] from a cycle count, assuming each segment
+    /// holds at most [MAX_CYCLES] cycles.
+    pub fn from_cycles(cycles: u64, seal_bytes: u64) -> Self {
+        let max_cycles = MAX_CYCLES as u64;
+        ExecutionCost {
+            cycles,
+            segments: (cycles + max_cycles - 1) / max_cycles,
+            seal_bytes,
+        }
+    }
+}
+
+/// Converts an [ExecutionCost] into pricing units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimator {
+    per_cycle: u64,
+    per_segment: u64,
+    per_byte: u64,
+}
+
+impl CostEstimator {
+    /// Construct a [CostEstimator] where every resource is free.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price charged for each executed cycle.
+    pub fn with_per_cycle(self, per_cycle: u64) -> Self {
+        Self { per_cycle, ..self }
+    }
+
+    /// Set the price charged for each proven segment.
+    pub fn with_per_segment(self, per_segment: u64) -> Self {
+        Self {
+            per_segment,
+            ..self
+        }
+    }
+
+    /// Set the price charged for each byte of the seal.
+    pub fn with_per_byte(self, per_byte: u64) -> Self {
+        Self { per_byte, ..self }
+    }
+
+    /// Compute the total price of the given execution, or `None` if the
+    /// total does not fit in a `u64`.
+    pub fn estimate(&self, cost: &ExecutionCost) -> Option<u64> {
+        let cycles = cost.cycles.checked_mul(self.per_cycle)?;
+        let segments = cost.segments.checked_mul(self.per_segment)?;
+        let bytes = cost.seal_bytes.checked_mul(self.per_byte)?;
+        cycles.checked_add(segments)?.checked_add(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CostEstimator, ExecutionCost};
+    use risc0_zkp::MAX_CYCLES;
+
+    #[test]
+    fn segments_from_cycles() {
+        let max_cycles = MAX_CYCLES as u64;
+        assert_eq!(ExecutionCost::from_cycles(0, 0).segments, 0);
+        assert_eq!(ExecutionCost::from_cycles(1, 0).segments, 1);
+        assert_eq!(ExecutionCost::from_cycles(max_cycles, 0).segments, 1);
+        assert_eq!(ExecutionCost::from_cycles(max_cycles + 1, 0).segments, 2);
+    }
+
+    #[test]
+    fn estimate() {
+        let estimator = CostEstimator::new()
+            .with_per_cycle(2)
+            .with_per_segment(1000)
+            .with_per_byte(3);
+        let cost = ExecutionCost {
+            cycles: 10,
+            segments: 2,
+            seal_bytes: 100,
+        };
+        assert_eq!(estimator.estimate(&cost), Some(20 + 2000 + 300));
+        assert_eq!(CostEstimator::new().estimate(&cost), Some(0));
+    }
+
+    #[test]
+    fn estimate_overflow() {
+        let estimator = CostEstimator::new().with_per_cycle(u64::MAX);
+        let cost = ExecutionCost {
+            cycles: 2,
+            ..Default::default()
+        };
+        assert_eq!(estimator.estimate(&cost), None);
+    }
+}
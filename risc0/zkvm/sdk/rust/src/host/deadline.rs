@@ -0,0 +1,139 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time-bounded proving.
+//!
+//! With [ProverOpts::with_deadline](super::ProverOpts::with_deadline), a run
+//! which is still executing the guest when its deadline passes stops with a
+//! deadline error instead of running to completion.  The deadline is checked
+//! whenever the guest calls the host, and once more after execution
+//! finishes, before proving starts.  Proving itself is never interrupted.
+//!
+//! The work that can be saved is the host's: every response the
+//! [sendrecv callbacks](super::ProverOpts::with_sendrecv_callback) gave
+//! before the deadline is kept in a [Checkpoint], available from
+//! `Prover::take_checkpoint`.  Passing the checkpoint to
+//! [ProverOpts::with_checkpoint](super::ProverOpts::with_checkpoint) resumes
+//! the run by answering those requests from the checkpoint, so services
+//! behind the callbacks are not queried twice.  Responses on the secret
+//! channel are never recorded.
+//!
+//! This prover proves each execution as a single segment, so there are no
+//! finished segment receipts to return from an interrupted run.
+
+use std::{path::Path, time::Instant};
+
+use super::{audit::Transcript, ProverOpts, ResponseCache, Result, SessionEvent};
+use crate::platform::io::SENDRECV_CHANNEL_SECRET;
+
+/// The host responses recorded by a run which was stopped by its deadline.
+#[derive(Default)]
+pub struct Checkpoint {
+    responses: ResponseCache,
+}
+
+impl Checkpoint {
+    /// The recorded responses.
+    pub fn responses(&self) -> &ResponseCache {
+        &self.responses
+    }
+
+    /// Load a checkpoint written by [Checkpoint::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Checkpoint {
+            responses: ResponseCache::load(path)?,
+        })
+    }
+
+    /// Write this checkpoint to `path`, e.g. to resume in another process.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.responses.save(path)
+    }
+}
+
+/// When a run must stop, if ever.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// Whether this deadline has passed.
+    pub fn expired(&self) -> bool {
+        self.0.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+}
+
+/// The state of one run of a prover which its sendrecv callbacks update.
+#[derive(Default)]
+pub(crate) struct Run {
+    pub deadline: Deadline,
+    recorded: Checkpoint,
+    pub transcript: Transcript,
+}
+
+impl Run {
+    pub fn start(opts: &ProverOpts) -> Self {
+        Run {
+            deadline: Deadline(opts.deadline.map(|deadline| Instant::now() + deadline)),
+            recorded: Checkpoint::default(),
+            transcript: Transcript::default(),
+        }
+    }
+
+    /// Answer a request with the registered callback for `channel`, if
+    /// there is one, preferring a response from the checkpoint being
+    /// resumed.
+    pub fn respond(&self, opts: &ProverOpts, channel: u32, request: &[u8]) -> Option<Vec<u8>> {
+        let response = self.respond_with_callback(opts, channel, request)?;
+        opts.emit(SessionEvent::HostCall {
+            channel,
+            request_len: request.len(),
+            response_len: response.len(),
+        });
+        Some(response)
+    }
+
+    fn respond_with_callback(
+        &self,
+        opts: &ProverOpts,
+        channel: u32,
+        request: &[u8],
+    ) -> Option<Vec<u8>> {
+        let callback = opts.sendrecv_callbacks.get(&channel)?;
+        if channel == SENDRECV_CHANNEL_SECRET {
+            let response = callback(channel, request);
+            if opts.audit.is_some() {
+                self.transcript.record(channel, request, &response);
+            }
+            return Some(response);
+        }
+        let resumed = opts
+            .checkpoint
+            .and_then(|checkpoint| checkpoint.responses.get(channel, request));
+        let response = resumed.unwrap_or_else(|| callback(channel, request));
+        if self.deadline.0.is_some() {
+            self.recorded
+                .responses
+                .insert(channel, request, response.clone());
+        }
+        if opts.audit.is_some() {
+            self.transcript.record(channel, request, &response);
+        }
+        Some(response)
+    }
+
+    /// The responses recorded so far.
+    pub fn into_checkpoint(self) -> Checkpoint {
+        self.recorded
+    }
+}
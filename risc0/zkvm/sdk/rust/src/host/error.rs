@@ -0,0 +1,236 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "verify")]
+use risc0_zkp::verify::VerificationError;
+use thiserror::Error as ThisError;
+
+#[cfg(feature = "prove")]
+pub use crate::elf::ElfError;
+
+use super::exception::Exception;
+
+/// The top-level error for anything a host can be handed by an untrusted
+/// party: guest images, guest execution, and receipts.
+///
+/// Every variant carries a stable numeric [code](Error::code) so services can
+/// report failures without matching on message text. Host APIs which return
+/// [anyhow::Result] wrap these errors; use [error_code] to recover the code.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    /// The guest ELF image was rejected by the loader.
+    #[cfg(feature = "prove")]
+    #[error(transparent)]
+    Elf(#[from] ElfError),
+
+    /// The guest misbehaved while executing.
+    #[error(transparent)]
+    Execution(#[from] ExecutionError),
+
+    /// A receipt could not be decoded or did not verify.
+    #[error(transparent)]
+    Receipt(#[from] ReceiptError),
+
+    /// The underlying C++ prover raised an exception.
+    #[error(transparent)]
+    Prover(#[from] Exception),
+}
+
+impl Error {
+    /// A stable numeric code for this error.
+    ///
+    /// Codes are grouped by origin: 1xx for ELF loading, 2xx for guest
+    /// execution, 3xx for receipts and 400 for prover exceptions.
+    pub fn code(&self) -> u32 {
+        match self {
+            #[cfg(feature = "prove")]
+            Error::Elf(err) => err.code(),
+            Error::Execution(err) => err.code(),
+            Error::Receipt(err) => err.code(),
+            Error::Prover(_) => 400,
+        }
+    }
+}
+
+/// Errors caused by a guest during execution.
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum ExecutionError {
+    /// The guest asked the host to access an unaligned word.
+    #[error("Unaligned memory access at 0x{0:08X}")]
+    UnalignedAccess(u32),
+
+    /// The guest asked the host to read memory that was never loaded.
+    #[error("Address out of range: 0x{0:08X}")]
+    AddressOutOfRange(u32),
+
+    /// The host would have had to overwrite memory the guest already touched.
+    #[error("Host cannot mutate existing memory at 0x{0:08X}")]
+    HostMutatedMemory(u32),
+
+    /// A string passed to the host was not valid UTF-8.
+    #[error("Guest string is not valid UTF-8")]
+    InvalidUtf8,
+
+    /// The guest issued more requests than fit in the INPUT region.
+    #[error("Read buffer overrun")]
+    ReadBufferOverrun,
+
+    /// A host response is too large to be sent to the guest at all.
+    #[error("Response of {0} bytes is too large")]
+    ResponseTooLarge(usize),
+
+    /// The guest issued a new request before reading all of a split response.
+    #[error("Split response was not fully read")]
+    SplitPending,
+
+    /// The guest sent a malformed or unexpected continuation request.
+    #[error("Bad continuation request")]
+    BadContinuation,
+
+    /// A continuation request pointed outside the guest heap.
+    #[error("Bad continuation address: 0x{0:08X}")]
+    BadContinuationAddress(u32),
+
+    /// The guest sent a request on a channel with no handler.
+    #[error("Unknown channel: {0}")]
+    UnknownChannel(u32),
+
+    /// The guest reported a fault.
+    #[error("Guest fault: {0}")]
+    GuestFault(String),
+
+    /// The guest used a host feature that is not supported.
+    #[error("Unsupported guest operation: {0}")]
+    Unsupported(&'static str),
+
+    /// The deadline set with ProverOpts::with_deadline passed before the
+    /// guest finished.
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
+    /// The segment ran for more cycles than ProverOpts::with_max_segment_cycles
+    /// allows.
+    #[error("Segment exceeded its limit of {0} cycles")]
+    SegmentCycleLimit(u64),
+
+    /// The guest touched more memory than
+    /// ProverOpts::with_max_segment_memory allows.
+    #[error("Segment exceeded its limit of {0} bytes of memory")]
+    SegmentMemoryLimit(u64),
+}
+
+impl ExecutionError {
+    /// A stable numeric code for this error; see [Error::code].
+    pub fn code(&self) -> u32 {
+        match self {
+            ExecutionError::UnalignedAccess(_) => 200,
+            ExecutionError::AddressOutOfRange(_) => 201,
+            ExecutionError::HostMutatedMemory(_) => 202,
+            ExecutionError::InvalidUtf8 => 203,
+            ExecutionError::ReadBufferOverrun => 204,
+            ExecutionError::ResponseTooLarge(_) => 205,
+            ExecutionError::SplitPending => 206,
+            ExecutionError::BadContinuation => 207,
+            ExecutionError::BadContinuationAddress(_) => 208,
+            ExecutionError::UnknownChannel(_) => 209,
+            ExecutionError::GuestFault(_) => 210,
+            ExecutionError::Unsupported(_) => 211,
+            ExecutionError::DeadlineExceeded => 212,
+            ExecutionError::SegmentCycleLimit(_) => 213,
+            ExecutionError::SegmentMemoryLimit(_) => 214,
+        }
+    }
+}
+
+/// Errors raised while decoding or verifying a receipt.
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum ReceiptError {
+    /// The receipt is structurally invalid: truncated, oversized or garbled.
+    #[error("Malformed receipt")]
+    Malformed,
+
+    /// The seal is well formed but does not prove the claimed execution.
+    #[error("Verification failed")]
+    VerificationFailed,
+
+    /// The journal does not match the one committed to in the seal.
+    #[error("Receipt journal/seal root mismatch")]
+    JournalMismatch,
+
+    /// The journal is committed directly and cannot be claimed by digest.
+    #[error("Journal is too short to be claimed by digest")]
+    JournalTooShort,
+}
+
+impl ReceiptError {
+    /// A stable numeric code for this error; see [Error::code].
+    pub fn code(&self) -> u32 {
+        match self {
+            ReceiptError::Malformed => 300,
+            ReceiptError::VerificationFailed => 301,
+            ReceiptError::JournalMismatch => 302,
+            ReceiptError::JournalTooShort => 303,
+        }
+    }
+}
+
+#[cfg(feature = "verify")]
+impl From<VerificationError> for ReceiptError {
+    fn from(err: VerificationError) -> Self {
+        match err {
+            VerificationError::ReceiptFormatError => ReceiptError::Malformed,
+            VerificationError::MethodVerificationError
+            | VerificationError::InvalidClaim
+            | VerificationError::HashSuiteMismatch => ReceiptError::VerificationFailed,
+        }
+    }
+}
+
+/// Finds the code of the first structured error in an [anyhow::Error]'s
+/// chain, if there is one.
+pub fn error_code(err: &anyhow::Error) -> Option<u32> {
+    err.chain().find_map(|cause| {
+        if let Some(err) = cause.downcast_ref::<Error>() {
+            return Some(err.code());
+        }
+        #[cfg(feature = "prove")]
+        if let Some(err) = cause.downcast_ref::<ElfError>() {
+            return Some(err.code());
+        }
+        if let Some(err) = cause.downcast_ref::<ExecutionError>() {
+            return Some(err.code());
+        }
+        if let Some(err) = cause.downcast_ref::<ReceiptError>() {
+            return Some(err.code());
+        }
+        if cause.downcast_ref::<Exception>().is_some() {
+            return Some(400);
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error_code, Error, ExecutionError, ReceiptError};
+
+    #[test]
+    fn codes() {
+        let err = anyhow::Error::from(ExecutionError::UnknownChannel(9)).context("while proving");
+        assert_eq!(error_code(&err), Some(209));
+        let err = anyhow::Error::from(Error::from(ReceiptError::Malformed));
+        assert_eq!(error_code(&err), Some(300));
+        assert_eq!(error_code(&anyhow::anyhow!("unstructured")), None);
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Live events from a running prover.
+//!
+//! [session_events] returns a connected [EventSink] and [SessionEvents].
+//! Pass the sink to [ProverOpts::with_events](super::ProverOpts::with_events)
+//! and read the events as they happen, typically from another thread:
+//!
+//! ```no_run
+//! use risc0_zkvm::host::{events::session_events, Prover, ProverOpts};
+//!
+//! # let (elf, method_id): (&[u8], &[u8]) = (&[], &[]);
+//! let (sink, events) = session_events();
+//! let monitor = std::thread::spawn(move || {
+//!     for event in events {
+//!         println!("{event:?}");
+//!     }
+//! });
+//! let mut prover =
+//!     Prover::new_with_opts(elf, method_id, ProverOpts::default().with_events(sink)).unwrap();
+//! prover.run().unwrap();
+//! drop(prover);
+//! monitor.join().unwrap();
+//! ```
+//!
+//! The iterator ends once the prover holding the sink is dropped.  Event
+//! loops which must not block, such as async tasks, can poll with
+//! [SessionEvents::try_next] instead.
+//!
+//! This prover executes and proves each run as a single segment, which keeps
+//! all of guest memory resident, so the pages a run touched are reported
+//! together just before [SessionEvent::SegmentFinished], and there are no
+//! page-out events.
+
+use std::sync::{
+    mpsc::{self, Receiver, Sender, TryRecvError},
+    Mutex,
+};
+
+/// Something which happened during a call to `Prover::run`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// Execution of a segment started.
+    SegmentStarted {
+        /// The index of the segment within the run.
+        index: usize,
+    },
+    /// The guest called a
+    /// [sendrecv callback](super::ProverOpts::with_sendrecv_callback).
+    HostCall {
+        /// The channel the guest called.
+        channel: u32,
+        /// The size of the guest's request, in bytes.
+        request_len: usize,
+        /// The size of the host's response, in bytes.
+        response_len: usize,
+    },
+    /// The segment's execution read or wrote a page of guest memory.  Each
+    /// page is reported once.
+    PageIn {
+        /// The address of the page.
+        addr: u32,
+        /// The number of reads from the page.
+        reads: u32,
+        /// The number of writes to the page.
+        writes: u32,
+    },
+    /// A segment was executed and proven.
+    SegmentFinished {
+        /// The index of the segment within the run.
+        index: usize,
+        /// The trace of the segment had 2^`po2` cycles.
+        po2: usize,
+    },
+    /// The run failed, e.g. because the guest faulted.
+    Fault {
+        /// A description of the failure.
+        msg: String,
+    },
+}
+
+/// The sending half of a session event stream, passed to
+/// [ProverOpts::with_events](super::ProverOpts::with_events).
+pub struct EventSink(Mutex<Sender<SessionEvent>>);
+
+impl EventSink {
+    /// Report an event.  Events are dropped once the receiving
+    /// [SessionEvents] is gone.
+    pub(crate) fn emit(&self, event: SessionEvent) {
+        let _ = self.0.lock().unwrap().send(event);
+    }
+}
+
+/// The receiving half of a session event stream.
+pub struct SessionEvents(Receiver<SessionEvent>);
+
+impl SessionEvents {
+    /// The next event if one is ready, without blocking.  Returns
+    /// `Ok(None)` while the run is still going, and `Err(Disconnected)` once
+    /// the prover holding the sink is dropped and all events were read.
+    pub fn try_next(&self) -> Result<Option<SessionEvent>, mpsc::TryRecvError> {
+        match self.0.try_recv() {
+            Ok(event) => Ok(Some(event)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Iterator for SessionEvents {
+    type Item = SessionEvent;
+
+    /// Wait for the next event.
+    fn next(&mut self) -> Option<SessionEvent> {
+        self.0.recv().ok()
+    }
+}
+
+/// Create a connected [EventSink] and [SessionEvents].
+pub fn session_events() -> (EventSink, SessionEvents) {
+    let (sender, receiver) = mpsc::channel();
+    (EventSink(Mutex::new(sender)), SessionEvents(receiver))
+}
@@ -12,11 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use risc0_zkp::core::sha::Digest;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{ffi::CStr, mem, os::raw::c_char};
+use std::{
+    cell::RefCell,
+    ffi::CStr,
+    os::raw::c_char,
+    time::{Instant, SystemTime},
+};
 
 use super::exception::Exception;
-use super::ProverOpts;
+use super::policy::{HashSuite, VerifierPolicy};
+use super::{
+    audit::{elf_sha256, AuditRecord},
+    deadline::Run,
+    AuditManifest, Backtrace, Checkpoint, ExecutionError, PageInfo, ProverOpts, ProverStats,
+    SessionEvent,
+};
+use crate::platform::io::{assert_failure, SENDRECV_CHANNEL_SECRET};
+
+// Must match kMaxCyclesPo2 in risc0/zkp/core/constants.h.
+const DEFAULT_MAX_PO2: usize = 24;
+
+// Must match kOutputRegs in risc0/zkvm/circuit/constants.h.
+const OUTPUT_REGS: usize = 9;
+
+// Must match kNonZkFlag in risc0/zkvm/circuit/constants.h.
+const NON_ZK_FLAG: u32 = 1 << 31;
 
 pub(crate) enum RawString {}
 pub(crate) enum RawProver {}
@@ -24,6 +46,14 @@ pub(crate) enum RawReceipt {}
 pub(crate) enum RawMethodId {}
 pub(crate) enum RawU8Buffer {}
 
+#[repr(C)]
+pub(crate) struct RawPageInfo {
+    addr: u32,
+    reads: u32,
+    writes: u32,
+    digest: Digest,
+}
+
 #[repr(C)]
 pub(crate) struct RawError {
     msg: *const RawString,
@@ -47,7 +77,7 @@ where
     } else {
         let what = unsafe {
             let str = risc0_string_ptr(err.msg);
-            let msg = CStr::from_ptr(str).to_str().unwrap().to_string();
+            let msg = CStr::from_ptr(str).to_string_lossy().into_owned();
             risc0_string_free(err.msg);
             msg
         };
@@ -118,6 +148,49 @@ extern "C" {
         skip_seal: bool,
     );
 
+    pub(crate) fn risc0_prover_set_zk(err: *mut RawError, prover: *mut RawProver, zk: bool);
+
+    pub(crate) fn risc0_prover_set_max_threads(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        max_threads: u32,
+    );
+
+    pub(crate) fn risc0_prover_set_deadline(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        millis: u64,
+    );
+
+    pub(crate) fn risc0_prover_set_po2_range(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        min_po2: u32,
+        max_po2: u32,
+    );
+
+    pub(crate) fn risc0_prover_get_po2(err: *mut RawError, prover: *const RawProver) -> u32;
+
+    pub(crate) fn risc0_prover_get_touched_pages_buf(
+        err: *mut RawError,
+        prover: *const RawProver,
+    ) -> *const RawPageInfo;
+
+    pub(crate) fn risc0_prover_get_touched_pages_len(
+        err: *mut RawError,
+        prover: *const RawProver,
+    ) -> usize;
+
+    pub(crate) fn risc0_prover_get_backtrace_buf(
+        err: *mut RawError,
+        prover: *const RawProver,
+    ) -> *const u32;
+
+    pub(crate) fn risc0_prover_get_backtrace_len(
+        err: *mut RawError,
+        prover: *const RawProver,
+    ) -> usize;
+
     pub(crate) fn risc0_prover_set_sendrecv_handler(
         rr: *mut RawError,
         prover: *mut RawProver,
@@ -131,6 +204,13 @@ extern "C" {
         cbdata: *const u8,
     );
 
+    pub(crate) fn risc0_prover_set_stdout_handler(
+        err: *mut RawError,
+        prover: *mut RawProver,
+        callback: unsafe extern "C" fn(buf: *const u8, len: usize, cbdata: *const u8) -> bool,
+        cbdata: *const u8,
+    );
+
     pub(crate) fn risc0_receipt_new(
         err: *mut RawError,
         journal: *const u8,
@@ -146,6 +226,14 @@ extern "C" {
         method_id_len: usize,
     );
 
+    pub(crate) fn risc0_receipt_verify_claim(
+        err: *mut RawError,
+        receipt: *const RawReceipt,
+        method_id: *const u8,
+        method_id_len: usize,
+        journal_digest: *const u32,
+    );
+
     pub(crate) fn risc0_receipt_get_seal_buf(
         err: *mut RawError,
         receipt: *const RawReceipt,
@@ -181,7 +269,21 @@ pub struct Receipt {
 /// The prover generates a [Receipt] by executing a given method in a ZKVM.
 pub struct Prover<'a> {
     ptr: *mut RawProver,
+    dispatch: Box<Dispatch<'a>>,
+    checkpoint: RefCell<Option<Checkpoint>>,
+    // What an audit manifest records about the guest and its input; only
+    // kept when auditing.
+    elf_sha256: Digest,
+    method_id: Vec<u8>,
+    input: Vec<u8>,
+    audit: RefCell<Option<AuditManifest>>,
+}
+
+// The state that sendrecv requests are answered from.  This is boxed so
+// that the C++ prover can hold a pointer to it across moves of the Prover.
+struct Dispatch<'a> {
     opts: ProverOpts<'a>,
+    run: RefCell<Run>,
 }
 
 /// A MethodId represents a unique identifier associated with a particular ELF
@@ -193,7 +295,9 @@ pub struct MethodId {
 fn into_words(slice: &[u8]) -> super::Result<Vec<u32>> {
     let mut vec = Vec::new();
     let chunks = slice.chunks_exact(4);
-    assert!(chunks.remainder().len() == 0);
+    if !chunks.remainder().is_empty() {
+        return Err(Exception::new("Buffer length is not a multiple of 4"));
+    }
     for chunk in chunks {
         let word = chunk[0] as u32
             | (chunk[1] as u32) << 8
@@ -236,6 +340,12 @@ impl MethodId {
             std::slice::from_raw_parts(ptr, len as usize)
         })
     }
+
+    /// A copy of the serialized method ID, as [MethodId::as_slice] borrows
+    /// it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.as_slice().unwrap().to_vec()
+    }
 }
 
 impl Clone for MethodId {
@@ -282,6 +392,105 @@ impl Receipt {
         check(err, || ())
     }
 
+    /// Verify that the current [Receipt] is a valid result of executing the
+    /// method associated with the given method ID, and that it commits to a
+    /// journal with the given digest; see [HashSuite::journal_digest].
+    ///
+    /// This lets verifiers that store the journal elsewhere check a proof
+    /// without the journal bytes. Journals of 32 bytes or less are committed
+    /// directly instead of by digest, and must be checked with
+    /// [Receipt::verify] instead.
+    pub fn verify_claim(&self, method_id: &[u8], journal_sha: Digest) -> super::Result<()> {
+        let mut err = RawError::default();
+        unsafe {
+            risc0_receipt_verify_claim(
+                &mut err,
+                self.ptr,
+                method_id.as_ptr(),
+                method_id.len(),
+                journal_sha.as_slice().as_ptr(),
+            )
+        };
+        check(err, || ())
+    }
+
+    /// Verify this [Receipt] like [Receipt::verify], additionally requiring
+    /// that it meets the given [VerifierPolicy].
+    pub fn verify_with_policy(
+        &self,
+        method_id: &[u8],
+        policy: &VerifierPolicy,
+    ) -> super::Result<()> {
+        policy.check(self.get_journal()?.len())?;
+        policy.check_hash_suite(self.hash_suite()?)?;
+        policy.check_zk(self.is_zk()?)?;
+        self.verify(method_id)
+    }
+
+    /// Whether this [Receipt] was proven with zero knowledge, as recorded in
+    /// its seal; see [ProverOpts::with_zk].
+    pub fn is_zk(&self) -> super::Result<bool> {
+        match self.get_seal()?.get(Self::PO2_INDEX) {
+            Some(po2) => Ok(po2 & NON_ZK_FLAG == 0),
+            None => Err(Exception::new("Seal is too short")),
+        }
+    }
+
+    /// The hash suite the seal of this [Receipt] was committed with; see
+    /// [ProverOpts::with_hash_suite].
+    pub fn hash_suite(&self) -> super::Result<HashSuite> {
+        use risc0_zkp::core::hash::{SUITE_ID_MASK, SUITE_ID_SHIFT};
+
+        // The C++ prover always records SHA-256, but Rust-proven seals may
+        // record another suite.
+        match self.get_seal()?.get(Self::PO2_INDEX) {
+            Some(po2) => HashSuite::from_id((po2 >> SUITE_ID_SHIFT) & SUITE_ID_MASK)
+                .ok_or_else(|| Exception::new("Seal records an unknown hash suite")),
+            None => Err(Exception::new("Seal is too short")),
+        }
+    }
+
+    // The position of the po2 word in the seal, after the output registers.
+    pub(crate) const PO2_INDEX: usize = OUTPUT_REGS;
+
+    /// Verify this [Receipt] like [Receipt::verify], then check the seal
+    /// again with the independent reference verifier in
+    /// [risc0_zkp::verify::reference].
+    ///
+    /// The reference verifier is much slower; use this for receipts that
+    /// warrant the extra assurance.
+    #[cfg(feature = "verify")]
+    pub fn paranoid_verify(&self, method_id: &[u8]) -> super::Result<()> {
+        use risc0_zkp::{core::sha::default_implementation, verify::adapter::VerifyAdapter};
+        use risc0_zkvm_circuit::CircuitImpl;
+
+        self.verify(method_id)?;
+        let circuit = CircuitImpl::new();
+        let mut verifier = VerifyAdapter::new(&circuit);
+        let sha = default_implementation();
+        risc0_zkp::verify::reference::verify(sha, &mut verifier, &self.rust_seal()?)
+            .map_err(|err| Exception::new(&super::ReceiptError::from(err).to_string()))
+    }
+
+    // The seal in the layout read by the verifiers in risc0_zkp. The C++
+    // prover writes each output register as one word, where those verifiers
+    // expect its low and high halves as separate field elements; the rest
+    // of the seal is the same.
+    #[cfg(feature = "verify")]
+    pub(crate) fn rust_seal(&self) -> super::Result<Vec<u32>> {
+        let seal = self.get_seal()?;
+        let regs = OUTPUT_REGS;
+        if seal.len() < regs {
+            return Err(Exception::new("Seal is too short"));
+        }
+        let mut out: Vec<u32> = seal[..regs]
+            .iter()
+            .flat_map(|reg| [reg & 0xffff, reg >> 16])
+            .collect();
+        out.extend_from_slice(&seal[regs..]);
+        Ok(out)
+    }
+
     /// Provides access to the `seal` of a [Receipt].
     pub fn get_seal(&self) -> super::Result<&[u32]> {
         unsafe {
@@ -312,6 +521,13 @@ impl Receipt {
     pub fn get_journal_vec(&self) -> super::Result<Vec<u32>> {
         into_words(self.get_journal()?)
     }
+
+    /// The code passed to risc0_zkvm_guest::env::assert if the guest halted
+    /// on a failed assertion, or None if it ran to completion.  Only trust
+    /// this once the receipt is verified.
+    pub fn get_assert_failure(&self) -> super::Result<Option<u32>> {
+        Ok(assert_failure(&self.get_journal_vec()?))
+    }
 }
 
 // TODO(nils): Lift "Receipt" from the pure-rust verify implementation so we
@@ -325,9 +541,10 @@ struct ReceiptData {
 impl Serialize for Receipt {
     /// Generate a serialized version of the whole receipt.
     fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::Error;
         let data: ReceiptData = ReceiptData {
-            journal: self.get_journal().unwrap().into(),
-            seal: self.get_seal().unwrap().into(),
+            journal: self.get_journal().map_err(S::Error::custom)?.into(),
+            seal: self.get_seal().map_err(S::Error::custom)?.into(),
         };
         data.serialize(serializer)
     }
@@ -339,8 +556,9 @@ impl<'de> Deserialize<'de> for Receipt {
     where
         D: Deserializer<'de>,
     {
+        use serde::de::Error;
         let data = ReceiptData::deserialize(deserializer)?;
-        Ok(Receipt::new(&data.journal, &data.seal).unwrap())
+        Receipt::new(&data.journal, &data.seal).map_err(D::Error::custom)
     }
 }
 
@@ -360,6 +578,10 @@ impl<'a> Prover<'a> {
         method_id: &[u8],
         opts: ProverOpts<'a>,
     ) -> super::Result<Self> {
+        let (elf_sha256, method_id_copy) = match opts.audit {
+            Some(_) => (elf_sha256(elf_contents), method_id.to_vec()),
+            None => (Digest::default(), Vec::new()),
+        };
         let mut err = RawError::default();
         let ptr = unsafe {
             risc0_prover_new(
@@ -370,30 +592,36 @@ impl<'a> Prover<'a> {
                 method_id.len(),
             )
         };
-        check(err, || Prover { ptr, opts })
+        check(err, || Prover {
+            ptr,
+            dispatch: Box::new(Dispatch {
+                opts,
+                run: RefCell::new(Run::default()),
+            }),
+            checkpoint: RefCell::new(None),
+            elf_sha256,
+            method_id: method_id_copy,
+            input: Vec::new(),
+            audit: RefCell::new(None),
+        })
     }
 
     /// Provide private input data that is availble to guest-side method code
     /// to 'read'.
     pub fn add_input(&mut self, slice: &[u32]) -> super::Result<()> {
+        let bytes = crate::serde::words_to_bytes(slice);
+        if self.dispatch.opts.audit.is_some() {
+            self.input.extend_from_slice(&bytes);
+        }
         let mut err = RawError::default();
-        unsafe {
-            risc0_prover_add_input(
-                &mut err,
-                self.ptr,
-                slice.as_ptr().cast(),
-                slice.len() * mem::size_of::<u32>(),
-            )
-        };
+        unsafe { risc0_prover_add_input(&mut err, self.ptr, bytes.as_ptr(), bytes.len()) };
         check(err, || ())
     }
 
     /// Compatibility with pure-rust prover
     pub fn add_input_u8_slice(&mut self, slice: &[u8]) {
-        let mut v: Vec<u32> = Vec::new();
-        v.resize((slice.len() + 3) / 4, 0);
-        bytemuck::cast_slice_mut(v.as_mut_slice())[..slice.len()].clone_from_slice(slice);
-        self.add_input(v.as_slice()).unwrap()
+        self.add_input(&crate::serde::bytes_to_words(slice))
+            .unwrap()
     }
 
     /// Compatibility with pure-rust prover
@@ -422,39 +650,203 @@ impl<'a> Prover<'a> {
         into_words(self.get_output()?)
     }
 
+    /// Provide statistics about the last call to [Prover::run].
+    pub fn get_stats(&self) -> super::Result<ProverStats> {
+        let mut err = RawError::default();
+        let po2 = unsafe { risc0_prover_get_po2(&mut err, self.ptr) };
+        check(err, || ProverStats { po2: po2 as usize })
+    }
+
+    /// The pages of memory the guest accessed during the last call to
+    /// [Prover::run], ordered by address.
+    pub fn touched_pages(&self) -> super::Result<Vec<PageInfo>> {
+        let len = self.page_faults()?;
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let mut err = RawError::default();
+        let buf = unsafe { risc0_prover_get_touched_pages_buf(&mut err, self.ptr) };
+        let buf = check(err, || buf)?;
+        let raw = unsafe { std::slice::from_raw_parts(buf, len) };
+        Ok(raw
+            .iter()
+            .map(|page| PageInfo {
+                addr: page.addr,
+                reads: page.reads,
+                writes: page.writes,
+                digest: page.digest,
+            })
+            .collect())
+    }
+
+    /// The number of distinct pages the guest accessed during the last call
+    /// to [Prover::run].  A paged executor would fault once on each of them.
+    pub fn page_faults(&self) -> super::Result<usize> {
+        let mut err = RawError::default();
+        let len = unsafe { risc0_prover_get_touched_pages_len(&mut err, self.ptr) };
+        check(err, || len)
+    }
+
+    /// The guest's frame-pointer [Backtrace] if the last call to
+    /// [Prover::run] failed during execution.  Only guests built with frame
+    /// pointers produce more than the faulting pc.
+    pub fn backtrace(&self) -> super::Result<Option<Backtrace>> {
+        let mut err = RawError::default();
+        let len = unsafe { risc0_prover_get_backtrace_len(&mut err, self.ptr) };
+        let len = check(err, || len)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut err = RawError::default();
+        let buf = unsafe { risc0_prover_get_backtrace_buf(&mut err, self.ptr) };
+        let buf = check(err, || buf)?;
+        let frames = unsafe { std::slice::from_raw_parts(buf, len) };
+        Ok(Some(Backtrace {
+            frames: frames.to_vec(),
+        }))
+    }
+
+    /// The host responses recorded before the last call to [Prover::run]
+    /// was stopped by its deadline, if it was; see [deadline](super::deadline).
+    pub fn take_checkpoint(&mut self) -> Option<Checkpoint> {
+        self.checkpoint.get_mut().take()
+    }
+
+    /// The [AuditManifest] of the last successful call to [Prover::run], if
+    /// auditing was enabled with [ProverOpts::with_audit].
+    pub fn take_audit_manifest(&mut self) -> Option<AuditManifest> {
+        self.audit.get_mut().take()
+    }
+
     unsafe extern "C" fn handle_callback(
         channel_id: u32,
         buf: *const u8,
         len: usize,
         cbdata: *const u8,
     ) -> *mut RawU8Buffer {
-        let cb = cbdata as *const Box<dyn Fn(u32, &[u8]) -> Vec<u8>>;
+        let dispatch = &*(cbdata as *const Dispatch);
 
         let from_guest = std::slice::from_raw_parts(buf, len);
-        let to_guest = (*cb)(channel_id, from_guest);
+        // Unwinding into the C++ prover would abort the host, so a panicking
+        // callback instead fails the run, as does a channel with no callback.
+        let response = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            dispatch
+                .run
+                .borrow()
+                .respond(&dispatch.opts, channel_id, from_guest)
+        }));
+        let mut to_guest = match response {
+            Ok(Some(to_guest)) => to_guest,
+            Ok(None) => {
+                log::error!("Guest sent a request on channel {channel_id}, which has no callback");
+                return std::ptr::null_mut();
+            }
+            Err(_) => {
+                log::error!("The callback for channel {channel_id} panicked");
+                return std::ptr::null_mut();
+            }
+        };
+
+        let buf = risc0_u8buffer_new(to_guest.as_ptr(), to_guest.len());
+        if channel_id == SENDRECV_CHANNEL_SECRET {
+            super::secret::wipe(&mut to_guest);
+        }
+        buf
+    }
 
-        risc0_u8buffer_new(to_guest.as_ptr(), to_guest.len())
+    unsafe extern "C" fn handle_stdout(buf: *const u8, len: usize, cbdata: *const u8) -> bool {
+        let dispatch = &*(cbdata as *const Dispatch);
+        let buf = std::slice::from_raw_parts(buf, len);
+        match dispatch.opts.write_stdout(buf) {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!("Failed to write guest stdout: {err}");
+                false
+            }
+        }
     }
 
     /// Execute the ZKVM to produce a [Receipt].
     pub fn run(&self) -> super::Result<Receipt> {
+        if self.dispatch.opts.hash_suite != HashSuite::Sha256 {
+            return Err(Exception::new(&format!(
+                "The C++ prover can't prove with {:?}",
+                self.dispatch.opts.hash_suite
+            )));
+        }
+        if self.dispatch.opts.max_segment_cycles.is_some()
+            || self.dispatch.opts.max_segment_memory.is_some()
+        {
+            return Err(Exception::new(
+                "The C++ prover can't limit segments by cycles or memory",
+            ));
+        }
+
         let mut err = RawError::default();
 
         unsafe {
-            risc0_prover_set_skip_seal(&mut err, self.ptr, self.opts.skip_seal);
+            risc0_prover_set_skip_seal(&mut err, self.ptr, self.dispatch.opts.skip_seal);
         };
         check(err, || ())?;
 
-        for (channel_id, cb) in self.opts.sendrecv_callbacks.iter() {
+        let mut err = RawError::default();
+        unsafe { risc0_prover_set_zk(&mut err, self.ptr, self.dispatch.opts.zk) };
+        check(err, || ())?;
+
+        if let Some(threads) = self.dispatch.opts.threads {
+            let mut err = RawError::default();
+            unsafe { risc0_prover_set_max_threads(&mut err, self.ptr, threads as u32) };
+            check(err, || ())?;
+        }
+
+        if self.dispatch.opts.min_po2.is_some() || self.dispatch.opts.max_po2.is_some() {
+            let min_po2 = self.dispatch.opts.min_po2.unwrap_or(0);
+            let max_po2 = self.dispatch.opts.max_po2.unwrap_or(DEFAULT_MAX_PO2);
+            let mut err = RawError::default();
+            unsafe {
+                risc0_prover_set_po2_range(&mut err, self.ptr, min_po2 as u32, max_po2 as u32)
+            };
+            check(err, || ())?;
+        }
+
+        let started = (SystemTime::now(), Instant::now());
+        let run = Run::start(&self.dispatch.opts);
+        if let Some(deadline) = self.dispatch.opts.deadline {
+            let mut err = RawError::default();
+            unsafe { risc0_prover_set_deadline(&mut err, self.ptr, deadline.as_millis() as u64) };
+            check(err, || ())?;
+        }
+        *self.dispatch.run.borrow_mut() = run;
+        self.checkpoint.replace(None);
+        self.audit.replace(None);
+        self.dispatch
+            .opts
+            .emit(SessionEvent::SegmentStarted { index: 0 });
+
+        for channel_id in self.dispatch.opts.sendrecv_callbacks.keys() {
             let mut err = RawError::default();
             unsafe {
-                let cb: *const Box<_> = cb;
+                let dispatch: *const Dispatch = &*self.dispatch;
                 risc0_prover_set_sendrecv_handler(
                     &mut err,
                     self.ptr,
                     *channel_id,
                     Self::handle_callback,
-                    cb.cast(),
+                    dispatch.cast(),
+                );
+            };
+            check(err, || ())?;
+        }
+
+        if self.dispatch.opts.stdout.is_some() {
+            let mut err = RawError::default();
+            unsafe {
+                let dispatch: *const Dispatch = &*self.dispatch;
+                risc0_prover_set_stdout_handler(
+                    &mut err,
+                    self.ptr,
+                    Self::handle_stdout,
+                    dispatch.cast(),
                 );
             };
             check(err, || ())?;
@@ -462,7 +854,51 @@ impl<'a> Prover<'a> {
 
         let mut err = RawError::default();
         let ptr = unsafe { risc0_prover_run(&mut err, self.ptr) };
-        check(err, || Receipt { ptr })
+        let receipt = check(err, || Receipt { ptr }).map_err(|err| {
+            // The C++ prover reports the deadline with the same message.
+            if err.what() == ExecutionError::DeadlineExceeded.to_string() {
+                let run = self.dispatch.run.take();
+                self.checkpoint.replace(Some(run.into_checkpoint()));
+            }
+            self.dispatch.opts.emit(SessionEvent::Fault {
+                msg: err.what().to_string(),
+            });
+            err
+        })?;
+
+        if cfg!(debug_assertions) && !self.dispatch.opts.skip_seal {
+            super::journal::check_commitment(
+                receipt.get_journal()?,
+                receipt.get_seal()?,
+                self.dispatch.opts.hash_suite,
+            )?;
+        }
+
+        if let Some(signer) = self.dispatch.opts.audit {
+            let run = self.dispatch.run.borrow();
+            let record = AuditRecord {
+                elf_sha256: self.elf_sha256,
+                method_id: &self.method_id,
+                input: &self.input,
+                transcript: &run.transcript,
+                journal: receipt.get_journal()?,
+                seal: receipt.get_seal()?,
+                po2: self.get_stats()?.po2,
+                started: started.0,
+                elapsed: started.1.elapsed(),
+            };
+            let manifest = record.sign(&self.dispatch.opts, signer);
+            self.audit.replace(Some(manifest));
+        }
+
+        if self.dispatch.opts.events.is_some() {
+            self.dispatch.opts.emit_pages(&self.touched_pages()?);
+            self.dispatch.opts.emit(SessionEvent::SegmentFinished {
+                index: 0,
+                po2: self.get_stats()?.po2,
+            });
+        }
+        Ok(receipt)
     }
 }
 
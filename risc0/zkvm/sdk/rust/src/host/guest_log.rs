@@ -0,0 +1,155 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Log records sent by the guest's `guest_log!` macros.
+//!
+//! By default, each record is passed to the [log] crate at its own level,
+//! with the guest's module path prefixed by `guest::` as the target, so the
+//! usual filters such as `RUST_LOG=guest::my_method=debug` apply.  Use
+//! [ProverOpts::with_guest_log](super::ProverOpts::with_guest_log) to
+//! handle records some other way, or to drop verbose ones before they are
+//! formatted.  Guests can also remove levels at compile time; see
+//! `risc0_zkvm_guest::log`.
+
+use log::{Level, LevelFilter};
+
+use crate::platform::io::{
+    LOG_LEVEL_DEBUG, LOG_LEVEL_ERROR, LOG_LEVEL_INFO, LOG_LEVEL_TRACE, LOG_LEVEL_WARN,
+};
+
+/// A log record sent by the guest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestLogRecord {
+    /// The level the record was logged at.
+    pub level: Level,
+
+    /// The path of the guest module which logged the record.
+    pub module: String,
+
+    /// The formatted message.
+    pub message: String,
+}
+
+impl GuestLogRecord {
+    /// Decode a record as the guest sends it, or `None` if it is malformed.
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let (&level, rest) = buf.split_first()?;
+        let level = match level {
+            LOG_LEVEL_ERROR => Level::Error,
+            LOG_LEVEL_WARN => Level::Warn,
+            LOG_LEVEL_INFO => Level::Info,
+            LOG_LEVEL_DEBUG => Level::Debug,
+            LOG_LEVEL_TRACE => Level::Trace,
+            _ => return None,
+        };
+        if rest.len() < 2 {
+            return None;
+        }
+        let (len, rest) = rest.split_at(2);
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        if rest.len() < len {
+            return None;
+        }
+        let (module, message) = rest.split_at(len);
+        Some(GuestLogRecord {
+            level,
+            module: std::str::from_utf8(module).ok()?.to_string(),
+            message: String::from_utf8_lossy(message).into_owned(),
+        })
+    }
+
+    /// Pass the record to the [log] crate, as the host does by default.
+    pub fn forward(&self) {
+        log::log!(
+            target: &format!("guest::{}", self.module),
+            self.level,
+            "{}",
+            self.message
+        );
+    }
+}
+
+// Decode a record from the guest and pass it to `handler` if its level is
+// at most `max_level`.
+pub(crate) fn on_log(
+    buf: &[u8],
+    max_level: LevelFilter,
+    handler: &(impl Fn(&GuestLogRecord) + ?Sized),
+) -> Vec<u8> {
+    if buf
+        .first()
+        .map_or(true, |&level| level as usize > max_level as usize)
+    {
+        // Skip decoding records which will be dropped, and malformed ones
+        // with no level.
+        if buf.is_empty() {
+            log::warn!("Ignoring empty guest log record");
+        }
+        return Vec::new();
+    }
+    match GuestLogRecord::decode(buf) {
+        Some(record) => handler(&record),
+        None => log::warn!("Ignoring malformed guest log record"),
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use log::{Level, LevelFilter};
+
+    use super::{on_log, GuestLogRecord};
+
+    fn encode(level: u8, module: &str, message: &str) -> Vec<u8> {
+        let mut buf = vec![level];
+        buf.extend((module.len() as u16).to_le_bytes());
+        buf.extend(module.as_bytes());
+        buf.extend(message.as_bytes());
+        buf
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            GuestLogRecord::decode(&encode(3, "guest::main", "hello")),
+            Some(GuestLogRecord {
+                level: Level::Info,
+                module: "guest::main".to_string(),
+                message: "hello".to_string(),
+            })
+        );
+        assert_eq!(
+            GuestLogRecord::decode(&encode(5, "m", "")).unwrap().level,
+            Level::Trace
+        );
+        assert_eq!(GuestLogRecord::decode(&[]), None);
+        assert_eq!(GuestLogRecord::decode(&encode(0, "m", "x")), None);
+        assert_eq!(GuestLogRecord::decode(&encode(6, "m", "x")), None);
+        assert_eq!(GuestLogRecord::decode(&[1, 4, 0, b'm']), None);
+    }
+
+    #[test]
+    fn filter() {
+        let seen = RefCell::new(Vec::new());
+        let handler = |record: &GuestLogRecord| seen.borrow_mut().push(record.level);
+        for level in 1..=5 {
+            assert!(on_log(&encode(level, "m", "x"), LevelFilter::Info, &handler).is_empty());
+        }
+        on_log(&[], LevelFilter::Trace, &handler);
+        on_log(&encode(1, "m", "x"), LevelFilter::Off, &handler);
+        assert_eq!(*seen.borrow(), [Level::Error, Level::Warn, Level::Info]);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Debug-build cross-check of the journal commitment.
+//!
+//! The circuit commits to the journal through the first words of the seal:
+//! the journal itself if it fits in a [Digest], or its
+//! [digest](HashSuite::journal_digest) otherwise, followed by the journal
+//! length. Recomputing that value on the host and
+//! comparing it byte by byte pinpoints serialization bugs that would
+//! otherwise only surface as an opaque verification failure.
+
+use risc0_zkp::core::sha::{Digest, DIGEST_WORDS};
+
+use super::{policy::HashSuite, Exception, Result};
+use crate::serde::{bytes_to_words, words_to_bytes};
+
+const DIGEST_BYTES: usize = DIGEST_WORDS * 4;
+
+/// Compute the words the circuit is expected to commit for `journal`, when
+/// proven with `suite`.
+pub(crate) fn expected_commitment(journal: &[u8], suite: HashSuite) -> Digest {
+    if journal.len() > DIGEST_BYTES {
+        return suite.journal_digest(journal);
+    }
+    let mut words = bytes_to_words(journal);
+    words.resize(DIGEST_WORDS, 0);
+    Digest::from_slice(&words)
+}
+
+/// Check that the commitment found in the output registers `regs` of a seal,
+/// as whole words, matches `journal`, reporting the first range of differing
+/// bytes if it does not.
+pub(crate) fn check_commitment(journal: &[u8], regs: &[u32], suite: HashSuite) -> Result<()> {
+    if regs.len() <= DIGEST_WORDS {
+        return Err(Exception::new(
+            "Seal has too few registers to hold a journal commitment",
+        ));
+    }
+    if regs[DIGEST_WORDS] as usize != journal.len() {
+        return Err(Exception::new(&format!(
+            "Journal length mismatch: journal has {} bytes, seal commits to {}",
+            journal.len(),
+            regs[DIGEST_WORDS]
+        )));
+    }
+    let expected = expected_commitment(journal, suite);
+    let expected = words_to_bytes(expected.as_slice());
+    let found = words_to_bytes(&regs[..DIGEST_WORDS]);
+    let start = match (0..DIGEST_BYTES).find(|&i| expected[i] != found[i]) {
+        Some(start) => start,
+        None => return Ok(()),
+    };
+    let end = (start..DIGEST_BYTES)
+        .find(|&i| expected[i] == found[i])
+        .unwrap_or(DIGEST_BYTES);
+    let what = if journal.len() > DIGEST_BYTES {
+        "journal digest"
+    } else {
+        "journal"
+    };
+    Err(Exception::new(&format!(
+        "Journal commitment mismatch in {what} bytes {start}..{end}: expected {:02x?}, found {:02x?}",
+        &expected[start..end],
+        &found[start..end]
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_commitment, expected_commitment, HashSuite, DIGEST_WORDS};
+
+    const SHA: HashSuite = HashSuite::Sha256;
+
+    fn seal_for(journal: &[u8]) -> Vec<u32> {
+        let mut seal = expected_commitment(journal, SHA).as_slice().to_vec();
+        seal.push(journal.len() as u32);
+        seal
+    }
+
+    #[test]
+    fn matching() {
+        for len in [0, 4, 32, 36, 100] {
+            let journal: Vec<u8> = (0..len as u8).collect();
+            check_commitment(&journal, &seal_for(&journal), SHA).unwrap();
+        }
+    }
+
+    #[test]
+    fn keccak() {
+        // keccak256(abi.encodePacked(uint256(1), uint256(2))), as computed by
+        // Solidity.
+        let mut journal = [0u8; 64];
+        journal[31] = 1;
+        journal[63] = 2;
+        let suite = HashSuite::Keccak256;
+        assert_eq!(
+            expected_commitment(&journal, suite).to_hex(),
+            "e90b7bceb6e7df5418fb78d8ee546e97c83a08bbccc01a0644d599ccd2a7c2e0"
+        );
+        // Short journals are committed directly, whatever the suite.
+        assert_eq!(
+            expected_commitment(&journal[..32], suite),
+            expected_commitment(&journal[..32], SHA)
+        );
+        assert!(check_commitment(&journal, &seal_for(&journal), suite).is_err());
+    }
+
+    #[test]
+    fn short_mismatch() {
+        let journal = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let mut seal = seal_for(&journal);
+        seal[1] ^= 0x0000_ff00;
+        let err = check_commitment(&journal, &seal, SHA).unwrap_err();
+        assert!(err.what().contains("journal bytes 5..6"), "{}", err);
+    }
+
+    #[test]
+    fn digest_mismatch() {
+        let journal = [7u8; 64];
+        let mut seal = seal_for(&journal);
+        seal[DIGEST_WORDS - 1] = !seal[DIGEST_WORDS - 1];
+        let err = check_commitment(&journal, &seal, SHA).unwrap_err();
+        assert!(
+            err.what().contains("journal digest bytes 28..32"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let journal = [0u8; 8];
+        let mut seal = seal_for(&journal);
+        seal[DIGEST_WORDS] = 12;
+        assert!(check_commitment(&journal, &seal, SHA).is_err());
+    }
+}
@@ -0,0 +1,724 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Running statically linked rv32im Linux binaries in the zkVM.
+//!
+//! The zkVM halts on `ecall`, so a Linux binary can't make syscalls as built.
+//! [translate] rewrites such a binary into a zkVM guest: each `ecall` in an
+//! executable segment becomes a jump to a small shim, appended to the image,
+//! which implements a handful of syscalls on top of the host channels and
+//! returns to the instruction after the call.
+//!
+//! The shim implements:
+//!
+//! * `read` and `readv`: fd 0 reads the initial input, as added with
+//!   `add_input`.
+//! * `write` and `writev`: fd 1 and 2 go to the host's stdout and stderr.
+//! * `brk`: grows a heap placed just above the image.
+//! * `exit` and `exit_group`: halt, committing the exit status as the journal.
+//!
+//! Every other syscall fails with `ENOSYS`, and reads or writes on other file
+//! descriptors fail with `EBADF`.  The binary starts with an empty argument
+//! vector and environment, and a stack at the top of [memory::HEAP].  The
+//! journal is only the exit status, so what the binary writes to stdout is
+//! not covered by the receipt.
+//!
+//! Binaries must be static, non-PIE executables built without the compressed
+//! extension, such as those from a `riscv32-linux-musl` toolchain with
+//! `-march=rv32im -static`.  A word in an executable segment which happens to
+//! encode `ecall` is rewritten as well, so keep constant pools out of `.text`.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+use xmas_elf::{program::ProgramHeader, ElfFile};
+
+use crate::{
+    elf::{ElfError, Program},
+    platform::{
+        abi,
+        io::{
+            addr::{GPIO_COMMIT, GPIO_FAULT, GPIO_SENDRECV_CHANNEL},
+            SENDRECV_CHANNEL_INITIAL_INPUT, SENDRECV_CHANNEL_STDERR, SENDRECV_CHANNEL_STDOUT,
+        },
+        memory::{self, MEM_SIZE},
+        WORD_SIZE,
+    },
+};
+
+/// Reasons a Linux binary can be rejected by [translate].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LinuxError {
+    /// The binary could not be loaded as a zkVM image at all.
+    #[error(transparent)]
+    Elf(#[from] ElfError),
+
+    /// A segment overlaps the page the shim keeps its state in.
+    #[error("Segment at 0x{0:08x} overlaps the shim's scratch page")]
+    LowAddress(u32),
+
+    /// The image leaves no room for the heap and stack.
+    #[error("Image ends at 0x{0:08x}, leaving no room for the heap and stack")]
+    TooLarge(u32),
+
+    /// A syscall is too far from the shim to jump to.
+    #[error("Syscall at 0x{0:08x} is out of range of the shim")]
+    SiteOutOfRange(u32),
+}
+
+/// Segments must start above the shim's scratch page.
+pub const MIN_ADDR: u32 = 0x1000;
+
+/// The initial stack pointer, below which the stack grows.
+pub const STACK_TOP: u32 = memory::HEAP.end() as u32;
+
+/// The room left for the stack; `brk` fails past `STACK_TOP - STACK_SIZE`.
+pub const STACK_SIZE: u32 = 1024 * 1024;
+
+const PAGE: u32 = 4096;
+
+// The shim's state, addressed relative to x0 so that the shim needs no free
+// register to reach it.
+const SAVE_RA: i32 = 0x400;
+const SAVE_REGS: i32 = 0x404;
+const READ_PTR: i32 = 0x430;
+const BRK: i32 = 0x434;
+const STDIN_READY: i32 = 0x438;
+const STDIN_PTR: i32 = 0x43c;
+const STDIN_LEFT: i32 = 0x440;
+const IO_DESC: i32 = 0x448;
+const FAULT_MSG: u32 = 0x500;
+
+const FAULT_TEXT: &[u8] = b"Linux shim: initial input is too large\0";
+
+const SYS_READ: i32 = 63;
+const SYS_WRITE: i32 = 64;
+const SYS_READV: i32 = 65;
+const SYS_WRITEV: i32 = 66;
+const SYS_EXIT: i32 = 93;
+const SYS_EXIT_GROUP: i32 = 94;
+const SYS_BRK: i32 = 214;
+
+const EBADF: i32 = 9;
+const ENOSYS: i32 = 38;
+
+const AT_NULL: i32 = 0;
+const AT_PAGESZ: i32 = 6;
+
+const ZERO: u32 = 0;
+const RA: u32 = 1;
+const SP: u32 = 2;
+const T0: u32 = 5;
+const T1: u32 = 6;
+const T2: u32 = 7;
+const S0: u32 = 8;
+const S1: u32 = 9;
+const A0: u32 = 10;
+const A1: u32 = 11;
+const A2: u32 = 12;
+const A7: u32 = 17;
+const S3: u32 = 19;
+const T3: u32 = 28;
+const T4: u32 = 29;
+const T5: u32 = 30;
+const T6: u32 = 31;
+
+// The registers the shim uses, restored before it returns.  Only a0 changes
+// across a syscall, as on Linux.
+const SAVED: [u32; 10] = [T0, T1, T2, T3, T4, T5, T6, S0, S1, S3];
+
+/// Rewrites a statically linked rv32im Linux binary into a zkVM guest ELF,
+/// which can be passed to `MethodId::compute` and `Prover::new` like any
+/// other.  See the [module docs](self) for what the binary can rely on.
+pub fn translate(elf: &[u8]) -> Result<Vec<u8>, LinuxError> {
+    let Program { entry, mut image } = Program::load_elf(elf, MEM_SIZE as u32)?;
+    let low = *image
+        .keys()
+        .next()
+        .ok_or(ElfError::Malformed("No loadable segments"))?;
+    if low < MIN_ADDR {
+        return Err(LinuxError::LowAddress(low));
+    }
+    let end = image.keys().next_back().unwrap() + WORD_SIZE as u32;
+
+    let exec = executable_ranges(elf)?;
+    let sites: Vec<u32> = image
+        .iter()
+        .filter(|(addr, word)| **word == abi::ECALL && exec.iter().any(|r| r.contains(*addr)))
+        .map(|(addr, _)| *addr)
+        .collect();
+
+    // The shim's length doesn't depend on where the heap starts, so assemble
+    // it once to find out.
+    let base = align_up(end, PAGE);
+    let shim_len = assemble(base, entry, 0, &sites)?.0.len() as u32;
+    let brk_start = align_up(base + shim_len * WORD_SIZE as u32, PAGE);
+    if brk_start > STACK_TOP - STACK_SIZE {
+        return Err(LinuxError::TooLarge(brk_start));
+    }
+    let (shim, stubs) = assemble(base, entry, brk_start, &sites)?;
+
+    for (site, stub) in sites.iter().zip(stubs) {
+        let jump =
+            jal(ZERO, stub.wrapping_sub(*site) as i32).ok_or(LinuxError::SiteOutOfRange(*site))?;
+        image.insert(*site, jump);
+    }
+    for (i, word) in shim.into_iter().enumerate() {
+        image.insert(base + (i * WORD_SIZE) as u32, word);
+    }
+    for (i, chunk) in FAULT_TEXT.chunks(WORD_SIZE).enumerate() {
+        let mut word = [0; WORD_SIZE];
+        word[..chunk.len()].copy_from_slice(chunk);
+        image.insert(FAULT_MSG + (i * WORD_SIZE) as u32, u32::from_le_bytes(word));
+    }
+    Ok(write_elf(base, &image))
+}
+
+// The address ranges of the binary's executable segments.
+fn executable_ranges(input: &[u8]) -> Result<Vec<std::ops::Range<u32>>, ElfError> {
+    let elf = ElfFile::new(input).map_err(ElfError::Malformed)?;
+    let mut ranges = Vec::new();
+    for phdr in elf.program_iter() {
+        if let ProgramHeader::Ph32(phdr) = phdr {
+            if phdr.get_type().map_err(ElfError::Malformed)? == xmas_elf::program::Type::Load
+                && phdr.flags.is_execute()
+            {
+                ranges.push(phdr.virtual_addr..phdr.virtual_addr + phdr.file_size);
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+// Assembles the shim at `base`, returning its code and the address of the
+// stub for each of `sites`.
+fn assemble(
+    base: u32,
+    entry: u32,
+    brk_start: u32,
+    sites: &[u32],
+) -> Result<(Vec<u32>, Vec<u32>), LinuxError> {
+    let mut a = Asm::new(base);
+
+    // Entry: lay out argc, argv, envp and auxv the way the kernel would, set
+    // up the shim's state and jump to the binary's own entry point.
+    a.li(SP, STACK_TOP - 32);
+    for offset in [0, 4, 8] {
+        a.emit(sw(ZERO, SP, offset));
+    }
+    a.emit(addi(T0, ZERO, AT_PAGESZ));
+    a.emit(sw(T0, SP, 12));
+    a.li(T0, PAGE);
+    a.emit(sw(T0, SP, 16));
+    a.emit(addi(T0, ZERO, AT_NULL));
+    a.emit(sw(T0, SP, 20));
+    a.emit(sw(ZERO, SP, 24));
+    a.emit(sw(ZERO, ZERO, READ_PTR));
+    a.emit(sw(ZERO, ZERO, STDIN_READY));
+    a.li(T0, brk_start);
+    a.emit(sw(T0, ZERO, BRK));
+    a.li(T0, entry);
+    a.emit(jalr(ZERO, T0, 0));
+
+    let handler = a.label();
+    let ret = a.label();
+    let bad_fd = a.label();
+    let send = a.label();
+    let fetch = a.label();
+    let copy = a.label();
+    let fault = a.label();
+    let [do_read, do_readv, do_write, do_writev, do_brk, do_exit] = [(); 6].map(|_| a.label());
+
+    // Dispatch on the syscall number in a7.
+    a.bind(handler);
+    for (i, reg) in SAVED.iter().enumerate() {
+        a.emit(sw(*reg, ZERO, SAVE_REGS + 4 * i as i32));
+    }
+    for (nr, label) in [
+        (SYS_READ, do_read),
+        (SYS_READV, do_readv),
+        (SYS_WRITE, do_write),
+        (SYS_WRITEV, do_writev),
+        (SYS_BRK, do_brk),
+        (SYS_EXIT, do_exit),
+        (SYS_EXIT_GROUP, do_exit),
+    ] {
+        a.emit(addi(T0, ZERO, nr));
+        a.branch(BEQ, A7, T0, label);
+    }
+    a.emit(addi(A0, ZERO, -ENOSYS));
+    a.bind(ret);
+    for (i, reg) in SAVED.iter().enumerate() {
+        a.emit(lw(*reg, ZERO, SAVE_REGS + 4 * i as i32));
+    }
+    a.emit(jalr(ZERO, RA, 0));
+    a.bind(bad_fd);
+    a.emit(addi(A0, ZERO, -EBADF));
+    a.jump(ret);
+
+    // read(fd, buf, len)
+    a.bind(do_read);
+    a.branch(BNE, A0, ZERO, bad_fd);
+    a.jal(S3, fetch);
+    a.emit(addi(T2, A1, 0));
+    a.emit(addi(T3, A2, 0));
+    a.jal(T6, copy);
+    a.emit(addi(A0, T3, 0));
+    a.jump(ret);
+
+    // readv(fd, iov, iovcnt)
+    a.bind(do_readv);
+    a.branch(BNE, A0, ZERO, bad_fd);
+    a.jal(S3, fetch);
+    a.emit(addi(S0, A1, 0));
+    a.emit(addi(S1, A2, 0));
+    a.emit(addi(A0, ZERO, 0));
+    let readv_loop = a.here();
+    a.branch(BEQ, S1, ZERO, ret);
+    a.emit(lw(T2, S0, 0));
+    a.emit(lw(T3, S0, 4));
+    a.jal(T6, copy);
+    a.emit(add(A0, A0, T3));
+    a.emit(addi(S0, S0, 8));
+    a.emit(addi(S1, S1, -1));
+    a.jump(readv_loop);
+
+    // write(fd, buf, len), with the channel for fd left in t1.
+    let stdout = a.label();
+    a.bind(do_write);
+    a.jal(S3, stdout);
+    a.emit(addi(T2, A1, 0));
+    a.emit(addi(T3, A2, 0));
+    a.jal(T6, send);
+    a.emit(addi(A0, A2, 0));
+    a.jump(ret);
+
+    // writev(fd, iov, iovcnt)
+    a.bind(do_writev);
+    a.jal(S3, stdout);
+    a.emit(addi(S0, A1, 0));
+    a.emit(addi(S1, A2, 0));
+    a.emit(addi(A0, ZERO, 0));
+    let writev_loop = a.here();
+    a.branch(BEQ, S1, ZERO, ret);
+    a.emit(lw(T2, S0, 0));
+    a.emit(lw(T3, S0, 4));
+    a.jal(T6, send);
+    a.emit(add(A0, A0, T3));
+    a.emit(addi(S0, S0, 8));
+    a.emit(addi(S1, S1, -1));
+    a.jump(writev_loop);
+
+    // Maps fd 1 and 2 to their channels in t1, or fails with EBADF.
+    a.bind(stdout);
+    a.emit(addi(T1, ZERO, SENDRECV_CHANNEL_STDOUT as i32));
+    a.emit(addi(T0, ZERO, 1));
+    let mapped = a.label();
+    a.branch(BEQ, A0, T0, mapped);
+    a.emit(addi(T1, ZERO, SENDRECV_CHANNEL_STDERR as i32));
+    a.emit(addi(T0, ZERO, 2));
+    a.branch(BNE, A0, T0, bad_fd);
+    a.bind(mapped);
+    a.emit(jalr(ZERO, S3, 0));
+
+    // brk(addr): moves the break if addr lies within the heap, and returns
+    // the break either way.
+    a.bind(do_brk);
+    let current = a.label();
+    a.emit(lw(T0, ZERO, BRK));
+    a.li(T1, brk_start);
+    a.branch(BLTU, A0, T1, current);
+    a.li(T1, STACK_TOP - STACK_SIZE);
+    a.branch(BLTU, T1, A0, current);
+    a.emit(sw(A0, ZERO, BRK));
+    a.jump(ret);
+    a.bind(current);
+    a.emit(addi(A0, T0, 0));
+    a.jump(ret);
+
+    // exit(status): commits the status as the journal and halts.
+    a.bind(do_exit);
+    a.li(T0, memory::COMMIT.start() as u32);
+    a.emit(sw(A0, T0, 0));
+    a.emit(addi(T1, ZERO, WORD_SIZE as i32));
+    a.emit(sw(T1, ZERO, IO_DESC));
+    a.emit(sw(T0, ZERO, IO_DESC + 4));
+    a.emit(addi(T1, ZERO, IO_DESC));
+    a.li(T2, GPIO_COMMIT);
+    a.emit(sw(T1, T2, 0));
+    a.emit(addi(abi::RESULT_FIRST_REG as u32, A0, 0));
+    for i in 1..abi::INLINE_JOURNAL_WORDS {
+        a.emit(addi((abi::RESULT_FIRST_REG + i) as u32, ZERO, 0));
+    }
+    a.emit(addi(
+        (abi::RESULT_FIRST_REG + abi::INLINE_JOURNAL_WORDS) as u32,
+        ZERO,
+        WORD_SIZE as i32,
+    ));
+    a.emit(abi::ECALL);
+
+    // Sends t3 bytes at t2 on channel t1, leaving the response length in t4
+    // and its address in t5.
+    a.bind(send);
+    a.li(T0, GPIO_SENDRECV_CHANNEL);
+    a.emit(sw(T1, T0, 0));
+    a.emit(sw(T3, T0, 4));
+    a.emit(sw(T2, T0, 8));
+    a.emit(lw(T4, ZERO, READ_PTR));
+    a.emit(slli(T5, T4, 2));
+    a.li(T0, memory::INPUT.start() as u32);
+    a.emit(add(T5, T5, T0));
+    a.emit(lw(T0, T5, 0));
+    a.branch(BLT, T0, ZERO, fault);
+    a.emit(addi(T5, T5, WORD_SIZE as i32));
+    // Skip the header and the padded response.
+    a.emit(addi(T4, T4, 1));
+    a.emit(addi(T0, T0, 3));
+    a.emit(srli(T0, T0, 2));
+    a.emit(add(T4, T4, T0));
+    a.emit(sw(T4, ZERO, READ_PTR));
+    a.emit(lw(T4, T5, -(WORD_SIZE as i32)));
+    a.emit(jalr(ZERO, T6, 0));
+
+    // Fetches the initial input on first use.
+    a.bind(fetch);
+    a.emit(lw(T0, ZERO, STDIN_READY));
+    let fetched = a.label();
+    a.branch(BNE, T0, ZERO, fetched);
+    a.emit(addi(T1, ZERO, SENDRECV_CHANNEL_INITIAL_INPUT as i32));
+    a.emit(addi(T2, ZERO, 0));
+    a.emit(addi(T3, ZERO, 0));
+    a.jal(T6, send);
+    a.emit(sw(T5, ZERO, STDIN_PTR));
+    a.emit(sw(T4, ZERO, STDIN_LEFT));
+    a.emit(addi(T0, ZERO, 1));
+    a.emit(sw(T0, ZERO, STDIN_READY));
+    a.bind(fetched);
+    a.emit(jalr(ZERO, S3, 0));
+
+    // Copies up to t3 bytes of the remaining input to t2, leaving the number
+    // copied in t3.
+    a.bind(copy);
+    let clamped = a.label();
+    let done = a.label();
+    a.emit(lw(T4, ZERO, STDIN_LEFT));
+    a.branch(BGEU, T4, T3, clamped);
+    a.emit(addi(T3, T4, 0));
+    a.bind(clamped);
+    a.emit(sub(T4, T4, T3));
+    a.emit(sw(T4, ZERO, STDIN_LEFT));
+    a.emit(lw(T5, ZERO, STDIN_PTR));
+    a.emit(addi(T0, ZERO, 0));
+    let copy_loop = a.here();
+    a.branch(BEQ, T0, T3, done);
+    a.emit(add(T1, T5, T0));
+    a.emit(lbu(T1, T1, 0));
+    a.emit(add(T4, T2, T0));
+    a.emit(sb(T1, T4, 0));
+    a.emit(addi(T0, T0, 1));
+    a.jump(copy_loop);
+    a.bind(done);
+    a.emit(add(T5, T5, T3));
+    a.emit(sw(T5, ZERO, STDIN_PTR));
+    a.emit(jalr(ZERO, T6, 0));
+
+    // Split responses aren't supported, so the whole input must fit in the
+    // INPUT region.
+    a.bind(fault);
+    a.emit(addi(T0, ZERO, FAULT_MSG as i32));
+    a.li(T1, GPIO_FAULT);
+    a.emit(sw(T0, T1, 0));
+    let hang = a.here();
+    a.jump(hang);
+
+    // One stub per syscall site, which calls the handler with the caller's
+    // ra saved and jumps back past the ecall.
+    let mut stubs = Vec::with_capacity(sites.len());
+    for site in sites {
+        stubs.push(a.pc());
+        a.emit(sw(RA, ZERO, SAVE_RA));
+        a.jal(RA, handler);
+        a.emit(lw(RA, ZERO, SAVE_RA));
+        let back = (site + WORD_SIZE as u32).wrapping_sub(a.pc()) as i32;
+        a.emit(jal(ZERO, back).ok_or(LinuxError::SiteOutOfRange(*site))?);
+    }
+    Ok((a.finish(), stubs))
+}
+
+// Writes a zkVM ELF with one segment for each contiguous run of `image`.
+fn write_elf(entry: u32, image: &BTreeMap<u32, u32>) -> Vec<u8> {
+    let mut segments: Vec<(u32, Vec<u32>)> = Vec::new();
+    for (addr, word) in image {
+        match segments.last_mut() {
+            Some((start, words)) if *start + (words.len() * WORD_SIZE) as u32 == *addr => {
+                words.push(*word)
+            }
+            _ => segments.push((*addr, vec![*word])),
+        }
+    }
+
+    const EHDR_SIZE: u32 = 52;
+    const PHDR_SIZE: u32 = 32;
+    let mut elf = b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+    for half in [2u16, 0xf3] {
+        elf.extend(half.to_le_bytes());
+    }
+    for word in [1, entry, EHDR_SIZE, 0, 0] {
+        elf.extend(word.to_le_bytes());
+    }
+    for half in [
+        EHDR_SIZE as u16,
+        PHDR_SIZE as u16,
+        segments.len() as u16,
+        40,
+        0,
+        0,
+    ] {
+        elf.extend(half.to_le_bytes());
+    }
+    let mut offset = EHDR_SIZE + PHDR_SIZE * segments.len() as u32;
+    for (addr, words) in &segments {
+        let len = (words.len() * WORD_SIZE) as u32;
+        // PT_LOAD, readable, writable and executable.
+        for word in [1, offset, *addr, *addr, len, len, 7, WORD_SIZE as u32] {
+            elf.extend(word.to_le_bytes());
+        }
+        offset += len;
+    }
+    for (_, words) in &segments {
+        for word in words {
+            elf.extend(word.to_le_bytes());
+        }
+    }
+    elf
+}
+
+fn align_up(addr: u32, align: u32) -> u32 {
+    (addr + align - 1) & !(align - 1)
+}
+
+const BEQ: u32 = 0;
+const BNE: u32 = 1;
+const BLT: u32 = 4;
+const BLTU: u32 = 6;
+const BGEU: u32 = 7;
+
+fn i_type(opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i32) -> u32 {
+    assert!((-2048..2048).contains(&imm));
+    (imm as u32 & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn s_type(funct3: u32, rs2: u32, rs1: u32, imm: i32) -> u32 {
+    assert!((-2048..2048).contains(&imm));
+    let imm = imm as u32 & 0xfff;
+    (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | 0x23
+}
+
+fn r_type(funct7: u32, funct3: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | 0x33
+}
+
+fn addi(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(0x13, 0, rd, rs1, imm)
+}
+
+fn slli(rd: u32, rs1: u32, shamt: i32) -> u32 {
+    i_type(0x13, 1, rd, rs1, shamt)
+}
+
+fn srli(rd: u32, rs1: u32, shamt: i32) -> u32 {
+    i_type(0x13, 5, rd, rs1, shamt)
+}
+
+fn lw(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(0x03, 2, rd, rs1, imm)
+}
+
+fn lbu(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(0x03, 4, rd, rs1, imm)
+}
+
+fn sw(rs2: u32, rs1: u32, imm: i32) -> u32 {
+    s_type(2, rs2, rs1, imm)
+}
+
+fn sb(rs2: u32, rs1: u32, imm: i32) -> u32 {
+    s_type(0, rs2, rs1, imm)
+}
+
+fn add(rd: u32, rs1: u32, rs2: u32) -> u32 {
+    r_type(0, 0, rd, rs1, rs2)
+}
+
+fn sub(rd: u32, rs1: u32, rs2: u32) -> u32 {
+    r_type(0x20, 0, rd, rs1, rs2)
+}
+
+fn jalr(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(0x67, 0, rd, rs1, imm)
+}
+
+fn lui(rd: u32, value: u32) -> u32 {
+    (value & 0xffff_f000) | rd << 7 | 0x37
+}
+
+// Returns None if `offset` is out of range of a jal.
+fn jal(rd: u32, offset: i32) -> Option<u32> {
+    if !(-(1 << 20)..1 << 20).contains(&offset) || offset % 2 != 0 {
+        return None;
+    }
+    let imm = offset as u32;
+    Some(
+        (imm >> 20 & 1) << 31
+            | (imm >> 1 & 0x3ff) << 21
+            | (imm >> 11 & 1) << 20
+            | (imm >> 12 & 0xff) << 12
+            | rd << 7
+            | 0x6f,
+    )
+}
+
+fn branch(funct3: u32, rs1: u32, rs2: u32, offset: i32) -> u32 {
+    assert!((-4096..4096).contains(&offset) && offset % 2 == 0);
+    let imm = offset as u32;
+    (imm >> 12 & 1) << 31
+        | (imm >> 5 & 0x3f) << 25
+        | rs2 << 20
+        | rs1 << 15
+        | funct3 << 12
+        | (imm >> 1 & 0xf) << 8
+        | (imm >> 11 & 1) << 7
+        | 0x63
+}
+
+#[derive(Clone, Copy)]
+struct Label(usize);
+
+enum Fixup {
+    Branch(u32, u32, u32),
+    Jal(u32),
+}
+
+// Just enough of an assembler for the shim: straight-line code with jumps
+// to labels, which are patched in once every label is bound.
+struct Asm {
+    base: u32,
+    code: Vec<u32>,
+    labels: Vec<Option<usize>>,
+    fixups: Vec<(usize, Label, Fixup)>,
+}
+
+impl Asm {
+    fn new(base: u32) -> Self {
+        Asm {
+            base,
+            code: Vec::new(),
+            labels: Vec::new(),
+            fixups: Vec::new(),
+        }
+    }
+
+    fn pc(&self) -> u32 {
+        self.base + (self.code.len() * WORD_SIZE) as u32
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.code.len());
+    }
+
+    // A label bound to the current position.
+    fn here(&mut self) -> Label {
+        let label = self.label();
+        self.bind(label);
+        label
+    }
+
+    fn emit(&mut self, insn: u32) {
+        self.code.push(insn);
+    }
+
+    // Loads `value` into `rd`, always in two instructions so that the shim's
+    // layout doesn't depend on the values it loads.
+    fn li(&mut self, rd: u32, value: u32) {
+        self.emit(lui(rd, value.wrapping_add(0x800)));
+        self.emit(addi(rd, rd, (value << 20) as i32 >> 20));
+    }
+
+    fn branch(&mut self, funct3: u32, rs1: u32, rs2: u32, target: Label) {
+        self.fixups
+            .push((self.code.len(), target, Fixup::Branch(funct3, rs1, rs2)));
+        self.emit(0);
+    }
+
+    fn jal(&mut self, rd: u32, target: Label) {
+        self.fixups.push((self.code.len(), target, Fixup::Jal(rd)));
+        self.emit(0);
+    }
+
+    fn jump(&mut self, target: Label) {
+        self.jal(ZERO, target);
+    }
+
+    fn finish(mut self) -> Vec<u32> {
+        for (at, label, fixup) in self.fixups {
+            let target = self.labels[label.0].expect("Unbound label");
+            let offset = (target as i32 - at as i32) * WORD_SIZE as i32;
+            self.code[at] = match fixup {
+                Fixup::Branch(funct3, rs1, rs2) => branch(funct3, rs1, rs2, offset),
+                Fixup::Jal(rd) => jal(rd, offset).unwrap(),
+            };
+        }
+        self.code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        assert_eq!(addi(A0, A0, 1), 0x0015_0513);
+        assert_eq!(addi(A0, ZERO, -1), 0xfff0_0513);
+        assert_eq!(sw(RA, ZERO, SAVE_RA), 0x4010_2023);
+        assert_eq!(lw(RA, ZERO, SAVE_RA), 0x4000_2083);
+        assert_eq!(branch(BEQ, A7, T0, 8), 0x0058_8463);
+        assert_eq!(branch(BNE, A0, ZERO, -4), 0xfe05_1ee3);
+        assert_eq!(jal(ZERO, 0), Some(0x0000_006f));
+        assert_eq!(jal(RA, 2048), Some(0x0010_00ef));
+        assert_eq!(jal(ZERO, -8), Some(0xff9f_f06f));
+        assert_eq!(jal(ZERO, 1 << 20), None);
+        assert_eq!(add(A0, A0, T3), 0x01c5_0533);
+        assert_eq!(sub(T4, T4, T3), 0x41ce_8eb3);
+    }
+
+    #[test]
+    fn labels() {
+        let mut a = Asm::new(0x1000);
+        let end = a.label();
+        let start = a.here();
+        a.branch(BEQ, A0, ZERO, end);
+        a.jump(start);
+        a.bind(end);
+        assert_eq!(
+            a.finish(),
+            [branch(BEQ, A0, ZERO, 8), jal(ZERO, -4).unwrap()]
+        );
+    }
+}
@@ -15,7 +15,23 @@
 #![deny(missing_docs)]
 #![doc = include_str!("README.md")]
 
-use std::collections::HashMap;
+use std::{collections::HashMap, io::Write, path::Path, sync::Mutex, time::Duration};
+
+use anyhow::{bail, Context};
+
+use risc0_zkp::core::sha::Digest;
+#[cfg(feature = "prove")]
+use risc0_zkp::hal::backend::HalBackend;
+use serde::Serialize;
+
+use crate::{
+    kv::KvTree,
+    method::TypedMethod,
+    platform::io::{
+        SENDRECV_CHANNEL_CONTINUE, SENDRECV_CHANNEL_FIELD, SENDRECV_CHANNEL_KV,
+        SENDRECV_CHANNEL_LOG, SENDRECV_CHANNEL_SECRET,
+    },
+};
 
 #[cfg(not(feature = "pure-prove"))]
 mod ffi;
@@ -26,9 +42,49 @@ use ffi as prove;
 
 pub use prove::Prover;
 
+mod accel;
+pub mod audit;
+pub mod backtrace;
+mod cache;
+mod chain;
+pub mod codec;
+pub mod compat;
+mod composite;
+pub mod cost;
+pub mod deadline;
+mod error;
+pub mod events;
 mod exception;
+pub mod guest_log;
+pub(crate) mod journal;
+#[cfg(feature = "prove")]
+pub mod linux;
+pub mod pipeline;
+pub mod policy;
+pub mod profile;
+pub mod secret;
+#[cfg(all(feature = "prove", feature = "verify"))]
+pub mod testdata;
+#[cfg(feature = "prove")]
+pub mod tune;
+pub mod witness;
 
+pub use audit::{AuditManifest, HmacSha256Signer, ManifestSigner};
+pub use backtrace::Backtrace;
+pub use cache::ResponseCache;
+pub use chain::{claim_digest, image_id, verify_chain, ReceiptChain};
+pub use codec::{ReceiptCodec, ReceiptParts};
+pub use composite::CompositeReceipt;
+pub use deadline::Checkpoint;
+#[cfg(feature = "prove")]
+pub use error::ElfError;
+pub use error::{error_code, Error, ExecutionError, ReceiptError};
+pub use events::{session_events, EventSink, SessionEvent, SessionEvents};
 pub use exception::Exception;
+pub use guest_log::GuestLogRecord;
+pub use pipeline::{Pipeline, PipelineReceipt, StageId};
+pub use secret::{KeyProvider, SecretInputs};
+pub use witness::{SegmentWitness, WitnessQueue};
 
 #[cxx::bridge]
 mod bridge {}
@@ -37,6 +93,33 @@ mod bridge {}
 pub struct ProverOpts<'a> {
     pub(crate) skip_seal: bool,
 
+    pub(crate) zk: bool,
+
+    pub(crate) hash_suite: policy::HashSuite,
+
+    pub(crate) min_po2: Option<usize>,
+
+    pub(crate) max_po2: Option<usize>,
+
+    pub(crate) max_segment_cycles: Option<u64>,
+
+    pub(crate) max_segment_memory: Option<u64>,
+
+    pub(crate) threads: Option<usize>,
+
+    #[cfg(feature = "prove")]
+    pub(crate) backend: Option<HalBackend>,
+
+    pub(crate) deadline: Option<Duration>,
+
+    pub(crate) checkpoint: Option<&'a Checkpoint>,
+
+    pub(crate) audit: Option<&'a dyn ManifestSigner>,
+
+    pub(crate) events: Option<EventSink>,
+
+    pub(crate) stdout: Option<Mutex<Box<dyn Write + Send + 'a>>>,
+
     pub(crate) sendrecv_callbacks: HashMap<u32, Box<dyn Fn(u32, &[u8]) -> Vec<u8> + 'a + Sync>>,
 }
 
@@ -49,6 +132,279 @@ impl<'a> ProverOpts<'a> {
         Self { skip_seal, ..self }
     }
 
+    /// If false, pad the trace with zeros rather than random values.  The
+    /// receipt then proves the execution was correct but may reveal
+    /// information about it, such as private inputs; use this only for
+    /// workloads whose inputs are public.  Proving skips the random padding
+    /// and is reproducible, though the seal stays the same size since the
+    /// padding cycles are part of the method ID.  The seal records the
+    /// choice, and [policy::VerifierPolicy::with_require_zk] rejects such
+    /// receipts.
+    pub fn with_zk(self, zk: bool) -> Self {
+        Self { zk, ..self }
+    }
+
+    /// Commit to the trace with `hash_suite` rather than
+    /// [policy::HASH_SUITE].  The seal records the choice, and a verifier
+    /// checks it with the same suite.  With
+    /// [Poseidon](policy::HashSuite::Poseidon), receipts are much cheaper to
+    /// verify within another proof, but proving is slower; with
+    /// [Blake3](policy::HashSuite::Blake3), proving is faster, but receipts
+    /// can't be verified on-chain; with
+    /// [Keccak256](policy::HashSuite::Keccak256), receipts are cheaper to
+    /// verify on the EVM, and their journal digest is the one Solidity
+    /// computes, provided the guest is built to commit with it.  Poseidon
+    /// proves on Metal as well as the CPU, and the other two on the CPU
+    /// only; the C++ prover supports only SHA-256.
+    pub fn with_hash_suite(self, hash_suite: policy::HashSuite) -> Self {
+        Self { hash_suite, ..self }
+    }
+
+    /// Prove with the parameters of `profile`; see [profile].  Options set
+    /// after this one override the profile's.
+    pub fn with_profile(self, profile: profile::Profile) -> Self {
+        let params = profile.params();
+        self.with_zk(params.zk).with_hash_suite(params.hash_suite)
+    }
+
+    /// Use a trace of at least 2^`min_po2` cycles.  By default, the prover
+    /// picks the smallest trace which fits the execution, and reports the
+    /// chosen size in [ProverStats].
+    pub fn with_min_po2(self, min_po2: usize) -> Self {
+        Self {
+            min_po2: Some(min_po2),
+            ..self
+        }
+    }
+
+    /// Fail if the execution does not fit in a trace of 2^`max_po2` cycles.
+    pub fn with_max_po2(self, max_po2: usize) -> Self {
+        Self {
+            max_po2: Some(max_po2),
+            ..self
+        }
+    }
+
+    /// Fail with [ExecutionError::SegmentCycleLimit] if the segment runs for
+    /// more than `max_cycles` cycles, counting those which load the image.
+    /// Unlike [ProverOpts::with_max_po2], this bounds the cycles actually
+    /// executed rather than the padded trace.  This version of the prover
+    /// proves each execution as a single segment, so it stops the execution
+    /// rather than splitting it.  Only the Rust prover of the `pure-prove`
+    /// feature supports this.
+    pub fn with_max_segment_cycles(self, max_cycles: u64) -> Self {
+        Self {
+            max_segment_cycles: Some(max_cycles),
+            ..self
+        }
+    }
+
+    /// Fail with [ExecutionError::SegmentMemoryLimit] if the guest touches
+    /// more than `max_bytes` bytes of memory in the segment, counted in whole
+    /// pages as in [Prover::touched_pages].  As with
+    /// [ProverOpts::with_max_segment_cycles], the execution stops rather
+    /// than being split, and only the `pure-prove` prover supports this.
+    pub fn with_max_segment_memory(self, max_bytes: u64) -> Self {
+        Self {
+            max_segment_memory: Some(max_bytes),
+            ..self
+        }
+    }
+
+    /// Prove with at most `threads` threads.  By default, the prover uses
+    /// every hardware thread.  The Rust prover sizes its thread pool once
+    /// per process, so with the `pure-prove` feature only the first run
+    /// with this option set takes effect.
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: Some(threads.max(1)),
+            ..self
+        }
+    }
+
+    /// Prove on `backend` rather than the fastest one available.  The
+    /// `RISC0_HAL` environment variable still takes precedence; see
+    /// [HalBackend::from_env].  Proving fails if the backend isn't
+    /// available on this machine, or can't hash with the
+    /// [hash suite](ProverOpts::with_hash_suite), rather than proving on the
+    /// CPU.  Only the Rust prover of the `pure-prove` feature runs on a
+    /// [HalBackend].
+    #[cfg(feature = "prove")]
+    pub fn with_backend(self, backend: HalBackend) -> Self {
+        Self {
+            backend: Some(backend),
+            ..self
+        }
+    }
+
+    /// Measure this machine and apply the fastest settings found, reusing
+    /// an earlier measurement when one was saved for this machine: the
+    /// [backend](ProverOpts::with_backend), the number of
+    /// [threads](ProverOpts::with_threads), and the largest trace which fits
+    /// in the backend's memory, as a [max_po2](ProverOpts::with_max_po2).
+    /// Options set after this one override the tuned ones, such as a
+    /// backend which can hash with the hash suite when the tuned one can't.
+    /// See [tune] for where measurements are kept.
+    #[cfg(feature = "prove")]
+    pub fn auto_tune(self) -> Self {
+        let path = tune::TuneConfig::default_path();
+        let config = match tune::TuneConfig::load(&path) {
+            Ok(Some(config)) if config.is_current() => config,
+            saved => {
+                if let Err(err) = saved {
+                    log::warn!("Ignoring tune config {}: {err}", path.display());
+                }
+                let config = tune::TuneConfig::measure();
+                if let Err(err) = config.save(&path) {
+                    log::warn!("Failed to save tune config {}: {err}", path.display());
+                }
+                config
+            }
+        };
+        self.with_threads(config.threads)
+            .with_backend(config.backend)
+            .with_max_po2(config.po2)
+    }
+
+    /// Stop executing the guest once `deadline` has passed since the start
+    /// of [Prover::run], keeping the host's work in a [Checkpoint]; see
+    /// [deadline].
+    pub fn with_deadline(self, deadline: Duration) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Resume from a run which was stopped by its deadline, answering
+    /// requests from the given [Checkpoint] before invoking callbacks.
+    pub fn with_checkpoint(self, checkpoint: &'a Checkpoint) -> Self {
+        Self {
+            checkpoint: Some(checkpoint),
+            ..self
+        }
+    }
+
+    /// Produce an [AuditManifest] for each proof, signed by `signer`; see
+    /// [audit].
+    pub fn with_audit(self, signer: &'a dyn ManifestSigner) -> Self {
+        Self {
+            audit: Some(signer),
+            ..self
+        }
+    }
+
+    /// Report the progress of each run to `sink` as it happens; see
+    /// [events].
+    pub fn with_events(self, sink: EventSink) -> Self {
+        Self {
+            events: Some(sink),
+            ..self
+        }
+    }
+
+    /// Pass what the guest writes to stdout to `stdout` as the guest writes
+    /// it, flushing after each write, rather than only collecting it for
+    /// [Prover::get_output] once the run has finished.  A write error fails
+    /// the run.
+    pub fn with_stdout(self, stdout: impl Write + Send + 'a) -> Self {
+        Self {
+            stdout: Some(Mutex::new(Box::new(stdout))),
+            ..self
+        }
+    }
+
+    /// Pass each record the guest logs with `guest_log!` at or below
+    /// `max_level` to `handler`, in place of forwarding it to the [log]
+    /// crate; see [guest_log].  Records above `max_level` are dropped
+    /// without being decoded.
+    pub fn with_guest_log(
+        self,
+        max_level: log::LevelFilter,
+        handler: impl Fn(&GuestLogRecord) + 'a + Sync,
+    ) -> Self {
+        self.with_sendrecv_callback(SENDRECV_CHANNEL_LOG, move |_, buf| {
+            guest_log::on_log(buf, max_level, &handler)
+        })
+    }
+
+    /// Supply an input stream of raw bytes on the given channel.  The guest
+    /// receives the whole stream each time it calls
+    /// risc0_zkvm_guest::env::send_recv on this channel.
+    ///
+    /// Fails if the channel is one of the standard ones, from
+    /// SENDRECV_CHANNEL_INITIAL_INPUT through SENDRECV_CHANNEL_LOG, or
+    /// SENDRECV_CHANNEL_CONTINUE.
+    pub fn with_input_stream(
+        self,
+        channel_id: u32,
+        data: impl Into<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        if channel_id <= SENDRECV_CHANNEL_LOG || channel_id == SENDRECV_CHANNEL_CONTINUE {
+            bail!("Channel {channel_id} is reserved and can't carry an input stream");
+        }
+        let data = data.into();
+        Ok(self.with_sendrecv_callback(channel_id, move |_, _| data.clone()))
+    }
+
+    /// Supply a serialized value as an input stream on the given channel,
+    /// which the guest can read with risc0_zkvm_guest::env::reader.  Fails
+    /// as [ProverOpts::with_input_stream] does, or if the value can't be
+    /// serialized.
+    pub fn with_input_value<T: Serialize>(
+        self,
+        channel_id: u32,
+        value: &T,
+    ) -> anyhow::Result<Self> {
+        let words = crate::serde::to_vec(value)?;
+        self.with_input_stream(channel_id, crate::serde::words_to_bytes(&words))
+    }
+
+    /// Supply the contents of the file at `path` as an input stream on the
+    /// given channel.  The file is read once, here.  Fails as
+    /// [ProverOpts::with_input_stream] does, or if the file can't be read.
+    pub fn with_input_file(self, channel_id: u32, path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        self.with_input_stream(channel_id, data)
+    }
+
+    /// Serve lookups from the given key-value store, which the guest can
+    /// query with risc0_zkvm_guest::kv.
+    pub fn with_kv_store(self, store: KvTree) -> Self {
+        self.with_sendrecv_callback(SENDRECV_CHANNEL_KV, move |_, key| {
+            let words = crate::serde::to_vec(&store.prove(key)).unwrap();
+            crate::serde::words_to_bytes(&words)
+        })
+    }
+
+    /// Serve the given sealed inputs, which the guest can read with
+    /// risc0_zkvm_guest::env::read_secret.  Each input is unsealed when the
+    /// guest requests it; see [secret] for how the plaintext is handled.
+    pub fn with_secret_inputs(self, inputs: SecretInputs<'a>) -> Self {
+        self.with_sendrecv_callback(SENDRECV_CHANNEL_SECRET, move |_, name| inputs.respond(name))
+    }
+
+    /// Answer repeated requests on the given channel from `cache`, only
+    /// invoking the channel's callback for requests not seen before.  This
+    /// must only be used for channels whose response is determined by the
+    /// request, and never for the secret channel, since the cache keeps
+    /// responses in the clear.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no callback has been registered for the channel yet.
+    pub fn with_response_cache(mut self, channel_id: u32, cache: &'a ResponseCache) -> Self {
+        let callback = self
+            .sendrecv_callbacks
+            .remove(&channel_id)
+            .expect("No callback registered for channel");
+        self.with_sendrecv_callback(channel_id, move |channel, buf| {
+            cache.get_or_insert_with(channel, buf, || callback(channel, buf))
+        })
+    }
+
     /// Add a callback handler for sendrecv ports, indexed by channel
     /// numbers.  The guest can call these callbacks by invoking
     /// risc0_zkvm_guest::host_sendrecv.
@@ -63,15 +419,85 @@ impl<'a> ProverOpts<'a> {
     }
 }
 
+impl<'a> ProverOpts<'a> {
+    pub(crate) fn emit(&self, event: SessionEvent) {
+        if let Some(sink) = &self.events {
+            sink.emit(event);
+        }
+    }
+
+    // Pass guest stdout to the writer given to with_stdout, if any.
+    pub(crate) fn write_stdout(&self, buf: &[u8]) -> std::io::Result<()> {
+        if let Some(stdout) = &self.stdout {
+            let mut stdout = stdout.lock().unwrap();
+            stdout.write_all(buf)?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+
+    // Report the pages touched by a finished execution.
+    pub(crate) fn emit_pages(&self, pages: &[PageInfo]) {
+        for page in pages {
+            self.emit(SessionEvent::PageIn {
+                addr: page.addr,
+                reads: page.reads,
+                writes: page.writes,
+            });
+        }
+    }
+}
+
 impl<'a> Default for ProverOpts<'a> {
     fn default() -> ProverOpts<'a> {
         ProverOpts {
             skip_seal: false,
+            zk: true,
+            hash_suite: policy::HASH_SUITE,
+            min_po2: None,
+            max_po2: None,
+            max_segment_cycles: None,
+            max_segment_memory: None,
+            threads: None,
+            #[cfg(feature = "prove")]
+            backend: None,
+            deadline: None,
+            checkpoint: None,
+            audit: None,
+            events: None,
+            stdout: None,
             sendrecv_callbacks: HashMap::new(),
         }
+        .with_sendrecv_callback(SENDRECV_CHANNEL_FIELD, accel::on_field)
+        .with_guest_log(log::LevelFilter::Trace, GuestLogRecord::forward)
     }
 }
 
+/// Statistics about the last run of a [Prover].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProverStats {
+    /// The trace of the execution had 2^`po2` cycles.
+    pub po2: usize,
+}
+
+/// How the guest used one [PAGE_SIZE](crate::platform::memory::PAGE_SIZE)
+/// page of memory during a call to [Prover::run].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageInfo {
+    /// The address of the first byte of the page.
+    pub addr: u32,
+
+    /// The number of word reads the guest made within the page.
+    pub reads: u32,
+
+    /// The number of word writes the guest made within the page.
+    pub writes: u32,
+
+    /// SHA-256 of the page's contents once execution finished, as
+    /// little-endian words with unset words read as zero.
+    pub digest: Digest,
+}
+
 /// The default digest count when generating a MethodId.
 pub const DEFAULT_METHOD_ID_LIMIT: u32 = 12;
 
@@ -80,19 +506,47 @@ pub type Result<T> = std::result::Result<T, Exception>;
 
 pub use prove::{MethodId, Receipt};
 
+/// Run the method `M` on `input`, returning its verified [Receipt] along with
+/// the decoded journal.
+pub fn prove_method<M: TypedMethod>(input: &M::Input) -> anyhow::Result<(Receipt, M::Output)> {
+    prove_method_with_opts::<M>(input, ProverOpts::default())
+}
+
+/// Like [prove_method], but with the given [ProverOpts].  The receipt is only
+/// verified if a seal is generated.
+pub fn prove_method_with_opts<M: TypedMethod>(
+    input: &M::Input,
+    opts: ProverOpts,
+) -> anyhow::Result<(Receipt, M::Output)> {
+    let skip_seal = opts.skip_seal;
+    let mut prover = Prover::new_with_opts(M::ELF, M::ID, opts)?;
+    prover.add_input_u32_slice(&crate::serde::to_vec(input)?);
+    let receipt = prover.run()?;
+    if !skip_seal {
+        receipt.verify(M::ID)?;
+    }
+    let output = crate::serde::from_slice(&receipt.get_journal_vec()?)?;
+    Ok((receipt, output))
+}
+
 #[cfg(test)]
 mod test {
     use super::{MethodId, Prover, ProverOpts, Receipt};
     use crate::{
-        platform::memory::{COMMIT, HEAP},
+        platform::memory::{COMMIT, HEAP, INPUT},
         serde::{from_slice, to_vec},
     };
     use anyhow::Result;
     use risc0_zkp::core::sha::Digest;
     use risc0_zkvm_methods::{
-        FAIL_ID, FAIL_PATH, IO_ID, IO_PATH, SENDRECV_ID, SENDRECV_PATH, SHA_ID, SHA_PATH,
+        ASSERT_ID, ASSERT_PATH, BLAKE2S_ID, BLAKE2S_PATH, CHAIN_ID, CHAIN_PATH, DISCLOSE_ID,
+        DISCLOSE_PATH, FAIL_ID, FAIL_PATH, GUEST_LOG_ID, GUEST_LOG_PATH, IO_ID, IO_PATH, KV_ID,
+        KV_PATH, NTT_ID, NTT_PATH, PANIC_HOOK_ID, PANIC_HOOK_PATH, PIPELINE_ID, PIPELINE_PATH,
+        SECRET_ID, SECRET_PATH, SENDRECV_ID, SENDRECV_PATH, SHA_ID, SHA_PATH, SHA_TLSF_ID,
+        SHA_TLSF_PATH, STREAMS_ID, STREAMS_PATH, TYPED_MAIN_ID, TYPED_MAIN_PATH, X25519_ID,
+        X25519_PATH,
     };
-    use std::sync::Mutex;
+    use std::{sync::Mutex, time::Duration};
     use test_log::test;
 
     #[test]
@@ -136,6 +590,172 @@ mod test {
         from_slice::<Digest>(vec.as_slice()).unwrap()
     }
 
+    // The TLSF heap hands freed memory out again, so this checks that the
+    // digests the host writes into the guest never land in recycled blocks.
+    #[test]
+    fn sha_tlsf() {
+        use risc0_zkp::core::sha::{default_implementation, Sha};
+
+        let msg = "abc";
+        let rounds = 16u32;
+        let elf = std::fs::read(SHA_TLSF_PATH).unwrap();
+        let mut prover = Prover::new(&elf, SHA_TLSF_ID).unwrap();
+        prover.add_input_u32_slice(&to_vec(msg.as_bytes()).unwrap());
+        prover.add_input_u32_slice(&to_vec(&rounds).unwrap());
+        let receipt = prover.run().unwrap();
+        receipt.verify(SHA_TLSF_ID).unwrap();
+
+        let sha = default_implementation();
+        let mut expected = *sha.hash_bytes(msg.as_bytes());
+        for _ in 0..rounds {
+            let bytes: Vec<u8> = expected
+                .as_slice()
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect();
+            expected = *sha.hash_bytes(&bytes);
+        }
+        let journal = receipt.get_journal_vec().unwrap();
+        assert_eq!(from_slice::<Digest>(&journal).unwrap(), expected);
+    }
+
+    #[test]
+    fn blake2s() {
+        use risc0_zkp::core::blake2s::digest;
+
+        for msg in ["", "abc", &"blake2s".repeat(20)] {
+            let mut prover =
+                Prover::new(&std::fs::read(BLAKE2S_PATH).unwrap(), BLAKE2S_ID).unwrap();
+            prover.add_input_u32_slice(&to_vec(&msg).unwrap());
+            let receipt = prover.run().unwrap();
+            let journal = receipt.get_journal_vec().unwrap();
+            assert_eq!(
+                from_slice::<[u8; 32]>(&journal).unwrap(),
+                digest(msg.as_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn ntt() {
+        use risc0_zkp::core::{
+            fp::Fp,
+            ntt::{bit_reverse, evaluate_ntt_fp},
+        };
+
+        let coeffs: Vec<u32> = (0..64).map(|i| i * i + 7).collect();
+        let mut prover = Prover::new(&std::fs::read(NTT_PATH).unwrap(), NTT_ID).unwrap();
+        prover.add_input_u32_slice(&to_vec(&coeffs).unwrap());
+        let receipt = prover.run().unwrap();
+        let (evals, roundtrip): (Vec<u32>, Vec<u32>) =
+            from_slice(&receipt.get_journal_vec().unwrap()).unwrap();
+
+        let mut expected: Vec<Fp> = coeffs.iter().map(|x| Fp::from(*x)).collect();
+        bit_reverse(&mut expected);
+        evaluate_ntt_fp(&mut expected, 0);
+        let expected: Vec<u32> = expected.into_iter().map(u32::from).collect();
+        assert_eq!(evals, expected);
+        assert_eq!(roundtrip, coeffs);
+    }
+
+    #[test]
+    fn x25519() {
+        fn bytes(hex: &str) -> [u8; 32] {
+            let mut out = [0; 32];
+            for (i, byte) in out.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+            }
+            out
+        }
+
+        // The Diffie-Hellman example from RFC 7748, section 6.1.
+        let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob_public = bytes("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+        let mut prover = Prover::new(&std::fs::read(X25519_PATH).unwrap(), X25519_ID).unwrap();
+        prover.add_input_u32_slice(&to_vec(&(alice, bob_public)).unwrap());
+        let receipt = prover.run().unwrap();
+        let journal = receipt.get_journal_vec().unwrap();
+        let (alice_public, shared) = from_slice::<([u8; 32], [u8; 32])>(&journal).unwrap();
+        assert_eq!(
+            alice_public,
+            bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            shared,
+            bytes("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+        );
+    }
+
+    #[test]
+    fn secret_inputs() {
+        use super::{KeyProvider, SecretInputs};
+        use risc0_zkp::core::sha::{default_implementation, Sha};
+
+        struct XorProvider;
+
+        impl KeyProvider for XorProvider {
+            fn unseal(&self, _name: &str, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+                Ok(sealed.iter().map(|b| b ^ 0xa5).collect())
+            }
+        }
+
+        let plaintext = b"patient records";
+        let sealed: Vec<u8> = plaintext.iter().map(|b| b ^ 0xa5).collect();
+        let run = |name: &str| -> Result<Receipt> {
+            let inputs = SecretInputs::new(&XorProvider).with_sealed("records", sealed.clone());
+            let opts = ProverOpts::default()
+                .with_skip_seal(true)
+                .with_secret_inputs(inputs);
+            let elf = std::fs::read(SECRET_PATH).unwrap();
+            let mut prover = Prover::new_with_opts(&elf, SECRET_ID, opts)?;
+            prover.add_input_u32_slice(&to_vec(&name).unwrap());
+            Ok(prover.run()?)
+        };
+
+        let journal = run("records").unwrap().get_journal_vec().unwrap();
+        assert_eq!(
+            from_slice::<Digest>(&journal).unwrap(),
+            *default_implementation().hash_bytes(plaintext)
+        );
+        assert!(run("other").is_err());
+    }
+
+    #[test]
+    fn selective_disclosure() {
+        use crate::disclose::Disclosure;
+        use risc0_zkp::core::sha::default_implementation;
+
+        crate::disclosable! {
+            struct Passport {
+                name: String,
+                birth_year: u32,
+                country: String,
+            }
+        }
+
+        let passport = Passport {
+            name: "Ada".to_string(),
+            birth_year: 1815,
+            country: "GB".to_string(),
+        };
+        let seed = Digest::new([0x5eed; 8]);
+        let mut prover = Prover::new(&std::fs::read(DISCLOSE_PATH).unwrap(), DISCLOSE_ID).unwrap();
+        let input = (&passport.name, passport.birth_year, &passport.country, seed);
+        prover.add_input_u32_slice(&to_vec(&input).unwrap());
+        let receipt = prover.run().unwrap();
+        receipt.verify(DISCLOSE_ID).unwrap();
+
+        let sha = default_implementation();
+        let disclosure = Disclosure::new(sha, &passport, &seed, &["birth_year"]).unwrap();
+        let journal = receipt.get_journal().unwrap();
+        disclosure.verify_journal(sha, &journal).unwrap();
+        assert_eq!(
+            disclosure.get::<Passport, u32>("birth_year").unwrap(),
+            Some(1815)
+        );
+        assert_eq!(disclosure.get::<Passport, String>("name").unwrap(), None);
+    }
+
     #[test]
     fn memory_io() {
         // Double write to WOM are fine
@@ -172,16 +792,118 @@ mod test {
             vec.push(*second as u32);
         }
         let skip_seal = opts.skip_seal;
-        let mut prover =
-            Prover::new_with_opts(&std::fs::read(IO_PATH).unwrap(), IO_ID, opts).unwrap();
+        let suite = opts.hash_suite;
+        let elf = std::fs::read(IO_PATH).unwrap();
+        let mut prover = Prover::new_with_opts(&elf, IO_ID, opts).unwrap();
         prover.add_input_u32_slice(vec.as_slice());
         let receipt = prover.run()?;
         if !skip_seal {
-            receipt.verify(IO_ID).unwrap();
+            let method_id = crate::method_id::MethodId::compute_for_suite(&elf, suite).unwrap();
+            receipt.verify(method_id.to_bytes().as_slice()).unwrap();
         }
         Ok(receipt)
     }
 
+    #[test]
+    fn profiles() {
+        use super::profile::Profile;
+
+        // Every profile proves with the prover that's built in.
+        let elf = std::fs::read(IO_PATH).unwrap();
+        for profile in Profile::ALL {
+            let opts = ProverOpts::default().with_profile(profile);
+            let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap();
+            let suite = profile.params().hash_suite;
+            let method_id = crate::method_id::MethodId::compute_for_suite(&elf, suite).unwrap();
+            receipt
+                .verify_with_policy(method_id.to_bytes().as_slice(), &profile.policy())
+                .unwrap();
+        }
+    }
+
+    fn run_po2(opts: ProverOpts) -> Result<usize> {
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(IO_PATH).unwrap(), IO_ID, opts).unwrap();
+        prover.add_input_u32_slice(&[1, HEAP.start() as u32, 0]);
+        prover.run()?;
+        Ok(prover.get_stats()?.po2)
+    }
+
+    #[test]
+    fn po2_range() {
+        let opts = || ProverOpts::default().with_skip_seal(true);
+        let auto = run_po2(opts()).unwrap();
+        assert_eq!(run_po2(opts().with_min_po2(auto + 1)).unwrap(), auto + 1);
+        assert_eq!(run_po2(opts().with_max_po2(auto)).unwrap(), auto);
+        assert!(run_po2(opts().with_max_po2(auto - 1)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "pure-prove")]
+    fn segment_limits() {
+        use super::ExecutionError;
+        use crate::platform::memory::PAGE_SIZE;
+
+        let opts = || ProverOpts::default().with_skip_seal(true);
+        let run = |opts: ProverOpts| {
+            let mut prover =
+                Prover::new_with_opts(&std::fs::read(IO_PATH).unwrap(), IO_ID, opts).unwrap();
+            prover.add_input_u32_slice(&[1, HEAP.start() as u32, 0]);
+            prover.run().map(|_| prover.touched_pages().unwrap().len())
+        };
+        let auto = run_po2(opts()).unwrap();
+        let pages = run(opts()).unwrap();
+
+        // The trace has room for every executed cycle.
+        run(opts().with_max_segment_cycles(1 << auto)).unwrap();
+        let err = run(opts().with_max_segment_cycles(1 << (auto - 2))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ExecutionError::SegmentCycleLimit(1 << (auto - 2)).to_string()
+        );
+
+        let bytes = (pages * PAGE_SIZE) as u64;
+        run(opts().with_max_segment_memory(bytes)).unwrap();
+        let err = run(opts().with_max_segment_memory(bytes - 1)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ExecutionError::SegmentMemoryLimit(bytes - 1).to_string()
+        );
+    }
+
+    #[test]
+    fn touched_pages() {
+        use crate::platform::memory::PAGE_SIZE;
+        use risc0_zkp::core::sha::{default_implementation, Sha};
+
+        let page = HEAP.start() + 64 * PAGE_SIZE;
+        let opts = ProverOpts::default().with_skip_seal(true);
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(IO_PATH).unwrap(), IO_ID, opts).unwrap();
+        prover.add_input_u32_slice(&[1, page as u32 + 8, 0x1234]);
+        prover.run().unwrap();
+
+        let pages = prover.touched_pages().unwrap();
+        assert_eq!(prover.page_faults().unwrap(), pages.len());
+        assert!(pages.windows(2).all(|pair| pair[0].addr < pair[1].addr));
+        assert!(pages.iter().all(|info| info.addr as usize % PAGE_SIZE == 0));
+        assert!(!pages
+            .iter()
+            .any(|info| info.addr as usize == page + PAGE_SIZE));
+
+        let written = pages
+            .iter()
+            .find(|info| info.addr as usize == page)
+            .unwrap();
+        assert!(written.writes > 0);
+        let mut contents = vec![0u8; PAGE_SIZE];
+        contents[8..12].copy_from_slice(&0x1234u32.to_le_bytes());
+        assert_eq!(
+            written.digest,
+            *default_implementation().hash_bytes(&contents)
+        );
+    }
+
     #[test]
     fn receipt_serde() {
         let receipt: Receipt =
@@ -207,6 +929,252 @@ mod test {
         assert!(de.verify(IO_ID).is_err());
     }
 
+    #[test]
+    #[cfg(feature = "verify")]
+    fn paranoid_verify() {
+        use risc0_zkp::{
+            core::sha::default_implementation,
+            verify::{adapter::VerifyAdapter, reference, verify},
+        };
+        use risc0_zkvm_circuit::CircuitImpl;
+
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], ProverOpts::default()).unwrap();
+        receipt.paranoid_verify(IO_ID).unwrap();
+
+        // The fast and reference verifiers must agree on every seal.
+        let seal = &receipt.rust_seal().unwrap();
+        let circuit = CircuitImpl::new();
+        let sha = default_implementation();
+        let check = |seal: &[u32]| {
+            let fast = verify(sha, &mut VerifyAdapter::new(&circuit), seal);
+            let slow = reference::verify(sha, &mut VerifyAdapter::new(&circuit), seal);
+            assert_eq!(fast.is_ok(), slow.is_ok());
+            fast.is_ok()
+        };
+        assert!(check(seal));
+        for pos in [0, seal.len() / 3, seal.len() / 2, seal.len() - 1] {
+            let mut garbled = seal.to_vec();
+            garbled[pos] ^= 1;
+            assert!(!check(&garbled));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "verify")]
+    fn verify_seal() {
+        use risc0_zkp::{
+            core::sha::default_implementation,
+            verify::{verify_seal, VerificationError},
+        };
+        use risc0_zkvm_circuit::CircuitImpl;
+
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], ProverOpts::default()).unwrap();
+        let seal = &receipt.rust_seal().unwrap();
+        let circuit = CircuitImpl::new();
+        let sha = default_implementation();
+        let mut claimed = None;
+        verify_seal(sha, &circuit, seal, |claim| {
+            claimed = Some((claim.outputs.len(), claim.po2, claim.zk));
+            Ok(())
+        })
+        .unwrap();
+        let (outputs, po2, zk) = claimed.unwrap();
+        // The po2 word follows the outputs.
+        assert_eq!(seal[outputs], po2);
+        assert!(zk);
+
+        // The claim check decides the result, but only runs on valid seals.
+        assert!(matches!(
+            verify_seal(sha, &circuit, seal, |_| Err(
+                VerificationError::InvalidClaim
+            )),
+            Err(VerificationError::InvalidClaim)
+        ));
+        let mut garbled = seal.to_vec();
+        garbled[seal.len() / 2] ^= 1;
+        assert!(verify_seal(sha, &circuit, &garbled, |_| unreachable!()).is_err());
+    }
+
+    #[test]
+    fn non_zk() {
+        use super::policy::VerifierPolicy;
+
+        let run = |zk| {
+            run_memio_with_opts(&[(HEAP.start(), 0)], ProverOpts::default().with_zk(zk)).unwrap()
+        };
+        let receipt = run(false);
+        assert!(!receipt.is_zk().unwrap());
+        receipt.verify(IO_ID).unwrap();
+        receipt
+            .verify_with_policy(IO_ID, &VerifierPolicy::default())
+            .unwrap();
+        let strict = VerifierPolicy::default().with_require_zk(true);
+        assert!(receipt.verify_with_policy(IO_ID, &strict).is_err());
+        // Without random padding, proving is deterministic.
+        assert_eq!(run(false).get_seal().unwrap(), receipt.get_seal().unwrap());
+
+        // The flag is bound to the proof, so it can't simply be cleared.
+        let journal = receipt.get_journal().unwrap();
+        let mut unflagged = receipt.get_seal().unwrap().to_vec();
+        unflagged[Receipt::PO2_INDEX] &= !risc0_zkp::NON_ZK_FLAG;
+        let unflagged = Receipt::new(&journal, &unflagged).unwrap();
+        assert!(unflagged.is_zk().unwrap());
+        assert!(unflagged.verify(IO_ID).is_err());
+
+        let receipt = run(true);
+        assert!(receipt.is_zk().unwrap());
+        receipt.verify_with_policy(IO_ID, &strict).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "pure-prove")]
+    fn poseidon() {
+        use super::policy::{HashSuite, VerifierPolicy};
+
+        let opts = ProverOpts::default().with_hash_suite(HashSuite::Poseidon);
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap();
+        assert_eq!(receipt.hash_suite().unwrap(), HashSuite::Poseidon);
+        let method_id = io_method_id(HashSuite::Poseidon);
+        receipt.verify(method_id.as_slice()).unwrap();
+        receipt.paranoid_verify(method_id.as_slice()).unwrap();
+        // The code roots of the SHA-256 method ID don't match.
+        assert!(receipt.verify(IO_ID).is_err());
+        assert!(receipt
+            .verify_with_policy(method_id.as_slice(), &VerifierPolicy::default())
+            .is_err());
+        let policy = VerifierPolicy::default().with_hash_suites(&[HashSuite::Poseidon]);
+        receipt
+            .verify_with_policy(method_id.as_slice(), &policy)
+            .unwrap();
+
+        // Relabelling the seal as SHA-256 verifies it as such, which fails.
+        use risc0_zkp::core::hash::{SUITE_ID_MASK, SUITE_ID_SHIFT};
+        let journal = receipt.get_journal().unwrap();
+        let mut relabelled = receipt.get_seal().unwrap().to_vec();
+        relabelled[Receipt::PO2_INDEX] &= !(SUITE_ID_MASK << SUITE_ID_SHIFT);
+        let relabelled = Receipt::new(&journal, &relabelled).unwrap();
+        assert_eq!(relabelled.hash_suite().unwrap(), HashSuite::Sha256);
+        assert!(relabelled.verify(IO_ID).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "metal")]
+    fn poseidon_metal() {
+        use risc0_zkp::hal::backend::HalBackend;
+
+        use super::policy::HashSuite;
+
+        if !HalBackend::Metal.is_available() {
+            return;
+        }
+        let opts = ProverOpts::default()
+            .with_hash_suite(HashSuite::Poseidon)
+            .with_backend(HalBackend::Metal);
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap();
+        let method_id = io_method_id(HashSuite::Poseidon);
+        receipt.verify(method_id.as_slice()).unwrap();
+
+        // Metal can't hash with Keccak, and proving fails rather than
+        // quietly running on the CPU.
+        let opts = ProverOpts::default()
+            .with_hash_suite(HashSuite::Keccak256)
+            .with_backend(HalBackend::Metal);
+        let err = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The metal backend can't hash with Keccak256; choose the cpu backend"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "pure-prove")]
+    fn keccak() {
+        use super::policy::{HashSuite, VerifierPolicy};
+
+        let opts = ProverOpts::default().with_hash_suite(HashSuite::Keccak256);
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap();
+        assert_eq!(receipt.hash_suite().unwrap(), HashSuite::Keccak256);
+        let method_id = io_method_id(HashSuite::Keccak256);
+        receipt.verify(method_id.as_slice()).unwrap();
+        let policy = VerifierPolicy::default().with_hash_suites(&[HashSuite::Keccak256]);
+        receipt
+            .verify_with_policy(method_id.as_slice(), &policy)
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "pure-prove")]
+    fn blake3() {
+        use super::policy::{HashSuite, VerifierPolicy};
+
+        let opts = ProverOpts::default().with_hash_suite(HashSuite::Blake3);
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], opts).unwrap();
+        assert_eq!(receipt.hash_suite().unwrap(), HashSuite::Blake3);
+        let method_id = io_method_id(HashSuite::Blake3);
+        receipt.verify(method_id.as_slice()).unwrap();
+        receipt.paranoid_verify(method_id.as_slice()).unwrap();
+        let policy = VerifierPolicy::default().with_hash_suites(&[HashSuite::Blake3]);
+        receipt
+            .verify_with_policy(method_id.as_slice(), &policy)
+            .unwrap();
+    }
+
+    // The method ID of the IO guest when proven with `suite`.
+    #[cfg(feature = "pure-prove")]
+    fn io_method_id(suite: super::policy::HashSuite) -> Vec<u8> {
+        let elf = std::fs::read(IO_PATH).unwrap();
+        crate::method_id::MethodId::compute_for_suite(&elf, suite)
+            .unwrap()
+            .to_bytes()
+    }
+
+    #[test]
+    fn verify_binds_claim() {
+        let mut prover = Prover::new(&std::fs::read(SHA_PATH).unwrap(), SHA_ID).unwrap();
+        prover.add_input_u32_slice(&to_vec(&"abc").unwrap());
+        let receipt = prover.run().unwrap();
+        receipt.verify(SHA_ID).unwrap();
+        let journal = receipt.get_journal().unwrap().to_vec();
+        let seal = receipt.get_seal().unwrap().to_vec();
+
+        // A receipt of another guest's method ID.
+        assert!(receipt.verify(IO_ID).is_err());
+
+        // The seal of another journal.
+        let mut tampered = journal.clone();
+        tampered[0] ^= 1;
+        assert!(Receipt::new(&tampered, &seal)
+            .unwrap()
+            .verify(SHA_ID)
+            .is_err());
+        let mut longer = journal;
+        longer.extend_from_slice(&[0; 4]);
+        assert!(Receipt::new(&longer, &seal)
+            .unwrap()
+            .verify(SHA_ID)
+            .is_err());
+    }
+
+    #[test]
+    fn malformed_receipt() {
+        let receipt = run_memio_with_opts(&[(HEAP.start(), 0)], ProverOpts::default()).unwrap();
+        let journal = receipt.get_journal().unwrap();
+        let seal = receipt.get_seal().unwrap();
+        let mut garbled = seal.to_vec();
+        garbled[seal.len() / 2] ^= 1;
+        // None of these should panic the verifier.
+        for bad in [
+            Vec::new(),
+            seal[..seal.len() / 2].to_vec(),
+            [seal, &[0]].concat(),
+            garbled,
+            vec![u32::MAX; 64],
+        ] {
+            let receipt = Receipt::new(&journal, &bad).unwrap();
+            assert!(receipt.verify(IO_ID).is_err());
+        }
+    }
+
     #[test]
     fn fail() {
         // Check that a compliant host will fault.
@@ -214,6 +1182,217 @@ mod test {
         assert!(prover.run().is_err());
     }
 
+    #[test]
+    fn backtrace() {
+        use crate::platform::memory::PROG;
+
+        // The test guests are built with frame pointers, so the panic can be
+        // traced back through its callers.
+        let mut prover = Prover::new(&std::fs::read(FAIL_PATH).unwrap(), FAIL_ID).unwrap();
+        assert!(prover.backtrace().unwrap().is_none());
+        assert!(prover.run().is_err());
+        let backtrace = prover.backtrace().unwrap().unwrap();
+        assert!(backtrace.frames.len() > 1, "{backtrace}");
+        for pc in backtrace.frames.iter() {
+            assert!(PROG.contains(*pc as usize), "{backtrace}");
+        }
+
+        let mut prover = Prover::new(&std::fs::read(SHA_PATH).unwrap(), SHA_ID).unwrap();
+        prover.add_input_u32_slice(&to_vec(&"abc").unwrap());
+        prover.run().unwrap();
+        assert!(prover.backtrace().unwrap().is_none());
+    }
+
+    #[test]
+    fn with_threads() {
+        let opts = ProverOpts::default().with_threads(1);
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(SHA_PATH).unwrap(), SHA_ID, opts).unwrap();
+        prover.add_input_u32_slice(&to_vec(&"abc").unwrap());
+        let receipt = prover.run().unwrap();
+        receipt.verify(SHA_ID).unwrap();
+        let digest = from_slice::<Digest>(&receipt.get_journal_vec().unwrap()).unwrap();
+        assert_eq!(digest, run_sha("abc"));
+    }
+
+    #[test]
+    fn guest_assert() {
+        let run = |limit: u32, rounds: u32| {
+            let mut prover = Prover::new(&std::fs::read(ASSERT_PATH).unwrap(), ASSERT_ID).unwrap();
+            prover.add_input_u32_slice(&to_vec(&(limit, rounds)).unwrap());
+            let receipt = prover.run().unwrap();
+            receipt.verify(ASSERT_ID).unwrap();
+            (receipt, prover.get_stats().unwrap().po2)
+        };
+
+        let (receipt, passed_po2) = run(1000, 1000);
+        assert_eq!(receipt.get_assert_failure().unwrap(), None);
+        assert_eq!(receipt.get_journal_vec().unwrap()[0], 1000);
+
+        let (receipt, failed_po2) = run(1000, 1001);
+        assert_eq!(receipt.get_assert_failure().unwrap(), Some(7));
+        assert_eq!(
+            receipt.get_journal_vec().unwrap(),
+            [crate::platform::io::JOURNAL_ASSERT_FAILED, 7]
+        );
+        assert!(failed_po2 < passed_po2);
+    }
+
+    // A guest written directly against the platform ABI, as a frontend other
+    // than the Rust guest crate would produce, keeps working.
+    #[test]
+    fn foreign_abi() {
+        use crate::platform::{abi, io::addr::GPIO_COMMIT, memory};
+
+        let lui = |rd: u32, imm: u32| (imm & 0xffff_f000) | rd << 7 | 0x37;
+        let addi =
+            |rd: u32, rs1: u32, imm: i32| (imm as u32 & 0xfff) << 20 | rs1 << 15 | rd << 7 | 0x13;
+        let sw = |rs2: u32, rs1: u32, imm: i32| {
+            let imm = imm as u32 & 0xfff;
+            (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | 2 << 12 | (imm & 0x1f) << 7 | 0x23
+        };
+        let li = |rd: u32, value: u32| {
+            [
+                lui(rd, value.wrapping_add(0x800)),
+                addi(rd, rd, (value << 20) as i32 >> 20),
+            ]
+        };
+
+        // Commit the journal, then halt with its result block.
+        let journal = [0xdead_beef, 42];
+        let mut code = Vec::new();
+        code.extend(li(5, memory::COMMIT.start() as u32));
+        for (i, word) in journal.iter().enumerate() {
+            code.extend(li(6, *word));
+            code.push(sw(6, 5, 4 * i as i32));
+        }
+        code.extend(li(2, abi::STACK_INIT as u32));
+        code.extend(li(6, 4 * journal.len() as u32));
+        code.push(sw(6, 2, -8));
+        code.push(sw(5, 2, -4));
+        code.push(addi(7, 2, -8));
+        code.extend(li(28, GPIO_COMMIT));
+        code.push(sw(7, 28, 0));
+        let result = abi::inline_result(&journal).unwrap();
+        for (i, word) in result.iter().enumerate() {
+            code.extend(li((abi::RESULT_FIRST_REG + i) as u32, *word));
+        }
+        code.push(abi::ECALL);
+        let code: Vec<u8> = code.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        // A minimal ELF with a single loadable segment at the entry point.
+        let entry = memory::PROG.start() as u32;
+        let mut elf = b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        for half in [2u16, 0xf3] {
+            elf.extend(half.to_le_bytes());
+        }
+        for word in [1, entry, 52, 0, 0] {
+            elf.extend(u32::to_le_bytes(word));
+        }
+        for half in [52u16, 32, 1, 40, 0, 0] {
+            elf.extend(half.to_le_bytes());
+        }
+        let len = code.len() as u32;
+        for word in [1, 84, entry, entry, len, len, 5, 4] {
+            elf.extend(u32::to_le_bytes(word));
+        }
+        elf.extend(code);
+
+        let method_id = MethodId::compute(&elf).unwrap().to_bytes();
+        let mut prover = Prover::new(&elf, &method_id).unwrap();
+        let receipt = prover.run().unwrap();
+        receipt.verify(&method_id[..]).unwrap();
+        assert_eq!(receipt.get_journal_vec().unwrap(), journal);
+    }
+
+    // A binary written against the Linux syscall ABI runs once translated.
+    #[cfg(feature = "prove")]
+    #[test]
+    fn linux_binary() {
+        use crate::platform::abi;
+
+        let lui = |rd: u32, imm: u32| (imm & 0xffff_f000) | rd << 7 | 0x37;
+        let addi =
+            |rd: u32, rs1: u32, imm: i32| (imm as u32 & 0xfff) << 20 | rs1 << 15 | rd << 7 | 0x13;
+        let lw = |rd: u32, rs1: u32, imm: i32| {
+            (imm as u32 & 0xfff) << 20 | rs1 << 15 | 2 << 12 | rd << 7 | 0x03
+        };
+        let li = |rd: u32, value: u32| {
+            [
+                lui(rd, value.wrapping_add(0x800)),
+                addi(rd, rd, (value << 20) as i32 >> 20),
+            ]
+        };
+        let (s0, a0, a1, a2, a7) = (8, 10, 11, 12, 17);
+
+        // write(1, "hi\n", 3); buf = brk(0); brk(buf + 16); read(0, buf, 4);
+        // exit(*buf + 1)
+        let entry = 0x1_0000;
+        let msg = entry + 0x100;
+        let mut code = Vec::new();
+        code.extend(li(a7, 64));
+        code.push(addi(a0, 0, 1));
+        code.extend(li(a1, msg));
+        code.push(addi(a2, 0, 3));
+        code.push(abi::ECALL);
+        code.push(addi(a7, 0, 214));
+        code.push(addi(a0, 0, 0));
+        code.push(abi::ECALL);
+        code.push(addi(s0, a0, 0));
+        code.push(addi(a0, s0, 16));
+        code.push(abi::ECALL);
+        code.push(addi(a7, 0, 63));
+        code.push(addi(a0, 0, 0));
+        code.push(addi(a1, s0, 0));
+        code.push(addi(a2, 0, 4));
+        code.push(abi::ECALL);
+        code.push(lw(a0, s0, 0));
+        code.push(addi(a0, a0, 1));
+        code.push(addi(a7, 0, 93));
+        code.push(abi::ECALL);
+        code.resize(0x40, 0);
+        code.push(u32::from_le_bytes(*b"hi\n\0"));
+        let code: Vec<u8> = code.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        // A static executable with one read-only, executable segment.
+        let mut elf = b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        for half in [2u16, 0xf3] {
+            elf.extend(half.to_le_bytes());
+        }
+        for word in [1, entry, 52, 0, 0] {
+            elf.extend(u32::to_le_bytes(word));
+        }
+        for half in [52u16, 32, 1, 40, 0, 0] {
+            elf.extend(half.to_le_bytes());
+        }
+        let len = code.len() as u32;
+        for word in [1, 84, entry, entry, len, len, 5, 4] {
+            elf.extend(u32::to_le_bytes(word));
+        }
+        elf.extend(code);
+
+        let elf = super::linux::translate(&elf).unwrap();
+        let method_id = MethodId::compute(&elf).unwrap().to_bytes();
+        let mut prover = Prover::new(&elf, &method_id).unwrap();
+        prover.add_input_u32_slice(&[41]);
+        let receipt = prover.run().unwrap();
+        receipt.verify(&method_id[..]).unwrap();
+        assert_eq!(receipt.get_journal_vec().unwrap(), [42]);
+        assert_eq!(prover.get_output().unwrap(), b"hi\n");
+
+        // The same output can be streamed as it is written.
+        let mut stdout = Vec::new();
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_stdout(&mut stdout);
+        let mut prover = Prover::new_with_opts(&elf, &method_id, opts).unwrap();
+        prover.add_input_u32_slice(&[41]);
+        prover.run().unwrap();
+        assert_eq!(prover.get_output().unwrap(), b"hi\n");
+        drop(prover);
+        assert_eq!(stdout, b"hi\n");
+    }
+
     #[test]
     fn clone_method_id() {
         let method_id = MethodId::from_slice(FAIL_ID).unwrap();
@@ -249,6 +1428,243 @@ mod test {
         assert_eq!(*actual.lock().unwrap(), expected[..expected.len() - 1]);
     }
 
+    #[test]
+    fn guest_log() {
+        use log::{Level, LevelFilter};
+
+        let run = |max_level| {
+            let records = Mutex::new(Vec::new());
+            let opts = ProverOpts::default()
+                .with_skip_seal(true)
+                .with_guest_log(max_level, |record| {
+                    records.lock().unwrap().push(record.clone())
+                });
+            let mut prover =
+                Prover::new_with_opts(&std::fs::read(GUEST_LOG_PATH).unwrap(), GUEST_LOG_ID, opts)
+                    .unwrap();
+            prover.add_input_u32_slice(&[2]);
+            prover.run().unwrap();
+            drop(prover);
+            records.into_inner().unwrap()
+        };
+
+        let records = run(LevelFilter::Trace);
+        let messages: Vec<_> = records
+            .iter()
+            .map(|record| (record.level, record.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Level::Warn, "starting"),
+                (Level::Debug, "step 0"),
+                (Level::Debug, "step 1"),
+                (Level::Info, "done after 2 steps"),
+                (Level::Info, "bye"),
+            ]
+        );
+        assert!(records.iter().all(|record| record.module == "guest_log"));
+
+        let levels: Vec<_> = run(LevelFilter::Info)
+            .iter()
+            .map(|record| record.level)
+            .collect();
+        assert_eq!(levels, [Level::Warn, Level::Info, Level::Info]);
+    }
+
+    #[test]
+    fn deadline() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            thread::sleep,
+        };
+
+        let calls = AtomicUsize::new(0);
+        let callback = |_, buf: &[u8]| -> Vec<u8> {
+            calls.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(30));
+            [buf, b"x"].concat()
+        };
+        let run = |opts| {
+            // Typed here rather than on the parameter, where the options
+            // would have to be 'static and so couldn't borrow `calls`.
+            let opts: ProverOpts = opts;
+            let opts = opts
+                .with_skip_seal(true)
+                .with_sendrecv_callback(16, callback);
+            let mut prover =
+                Prover::new_with_opts(&std::fs::read(SENDRECV_PATH).unwrap(), SENDRECV_ID, opts)
+                    .unwrap();
+            prover.add_input_u32_slice(&[16, 5]);
+            let result = prover.run().map(|_| ());
+            (result, prover.take_checkpoint())
+        };
+
+        let (result, checkpoint) =
+            run(ProverOpts::default().with_deadline(Duration::from_millis(50)));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Deadline exceeded"));
+        let checkpoint = checkpoint.unwrap();
+        let recorded = checkpoint.responses().len();
+        assert!(recorded > 0 && recorded < 5);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), recorded);
+
+        let (result, checkpoint) = run(ProverOpts::default().with_checkpoint(&checkpoint));
+        result.unwrap();
+        assert!(checkpoint.is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 5 - recorded);
+    }
+
+    #[test]
+    fn session_events() {
+        use super::{session_events, SessionEvent};
+
+        let (sink, events) = session_events();
+        let opts = ProverOpts::default()
+            .with_events(sink)
+            .with_sendrecv_callback(16, |_, buf: &[u8]| [buf, b"x"].concat());
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(SENDRECV_PATH).unwrap(), SENDRECV_ID, opts)
+                .unwrap();
+        prover.add_input_u32_slice(&[16, 3]);
+        prover.run().unwrap();
+        let pages = prover.touched_pages().unwrap();
+        let po2 = prover.get_stats().unwrap().po2;
+        drop(prover);
+
+        let events: Vec<SessionEvent> = events.collect();
+        assert_eq!(events[0], SessionEvent::SegmentStarted { index: 0 });
+        let calls: Vec<(usize, usize)> = events
+            .iter()
+            .filter_map(|event| match event {
+                SessionEvent::HostCall {
+                    channel: 16,
+                    request_len,
+                    response_len,
+                } => Some((*request_len, *response_len)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, [(0, 1), (1, 2), (2, 3)]);
+        let paged: Vec<u32> = events
+            .iter()
+            .filter_map(|event| match event {
+                SessionEvent::PageIn { addr, .. } => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            paged,
+            pages.iter().map(|page| page.addr).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            events.last(),
+            Some(&SessionEvent::SegmentFinished { index: 0, po2 })
+        );
+
+        let (sink, events) = session_events();
+        let opts = ProverOpts::default().with_events(sink);
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(FAIL_PATH).unwrap(), FAIL_ID, opts).unwrap();
+        assert!(prover.run().is_err());
+        assert!(matches!(
+            events.try_next(),
+            Ok(Some(SessionEvent::SegmentStarted { index: 0 }))
+        ));
+        let rest: Vec<SessionEvent> = std::iter::from_fn(|| events.try_next().unwrap()).collect();
+        assert!(matches!(rest.last(), Some(SessionEvent::Fault { .. })));
+        drop(prover);
+        assert!(events.try_next().is_err());
+    }
+
+    #[test]
+    fn audit_manifest() {
+        use super::audit::{HmacSha256Signer, ManifestSigner};
+
+        let signer = HmacSha256Signer::new(b"audit key");
+        let run = || {
+            let opts = ProverOpts::default()
+                .with_audit(&signer)
+                .with_sendrecv_callback(16, |_, buf: &[u8]| [buf, b"x"].concat());
+            let mut prover =
+                Prover::new_with_opts(&std::fs::read(SENDRECV_PATH).unwrap(), SENDRECV_ID, opts)
+                    .unwrap();
+            prover.add_input_u32_slice(&[16, 5]);
+            prover.run().unwrap().verify(SENDRECV_ID).unwrap();
+            prover.take_audit_manifest().unwrap()
+        };
+
+        let first = run();
+        assert!(first.verify_with(&signer));
+        assert!(!first.verify_with(&HmacSha256Signer::new(b"other key")));
+        assert_eq!(signer.scheme(), "hmac-sha256");
+        assert_eq!(first.get("requests"), Some("5"));
+        assert_eq!(first.get("random_seed"), Some("os"));
+
+        // Everything that determines the journal is reproducible.
+        let second = run();
+        for key in [
+            "elf_sha256",
+            "method_id_sha256",
+            "input_sha256",
+            "transcript_sha256",
+            "journal_sha256",
+            "po2",
+        ] {
+            assert_eq!(first.get(key), second.get(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn host_sendrecv_split() {
+        // Too large for the INPUT region, so it has to arrive in chunks.
+        let big: Vec<u8> = (0..INPUT.len_bytes() + 4097)
+            .map(|i| (i * 7) as u8)
+            .collect();
+        let echoed: Mutex<Vec<Vec<u8>>> = Vec::new().into();
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_sendrecv_callback(5, |_, buf| {
+                let mut echoed = echoed.lock().unwrap();
+                echoed.push(buf.into());
+                if echoed.len() == 1 {
+                    big.clone()
+                } else {
+                    Vec::new()
+                }
+            });
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(SENDRECV_PATH).unwrap(), SENDRECV_ID, opts)
+                .unwrap();
+        prover.add_input_u32_slice(&[5, 2]);
+        prover.run().unwrap();
+
+        assert_eq!(*echoed.lock().unwrap(), [Vec::new(), big.clone()]);
+    }
+
+    #[test]
+    fn panic_hook() {
+        let hooked: Mutex<Vec<Vec<u8>>> = Vec::new().into();
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_sendrecv_callback(9, |_, buf| {
+                hooked.lock().unwrap().push(buf.into());
+                Vec::new()
+            });
+        let mut prover = Prover::new_with_opts(
+            &std::fs::read(PANIC_HOOK_PATH).unwrap(),
+            PANIC_HOOK_ID,
+            opts,
+        )
+        .unwrap();
+        prover.add_input_u32_slice(&[9]);
+        assert!(prover.run().is_err());
+        drop(prover);
+        assert_eq!(*hooked.lock().unwrap(), [b"hooked".to_vec()]);
+    }
+
     // Make sure panics in the callback get propagated correctly.
     #[test]
     #[should_panic(expected = "I am panicking from here!")]
@@ -264,4 +1680,280 @@ mod test {
         prover.add_input_u32_slice(&[5, 5]);
         prover.run().unwrap();
     }
+
+    #[test]
+    fn input_streams() {
+        let data: Vec<u8> = "Hello, streams".into();
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_input_value(7, &1234u32)
+            .unwrap()
+            .with_input_stream(8, data.clone())
+            .unwrap();
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(STREAMS_PATH).unwrap(), STREAMS_ID, opts).unwrap();
+        prover.add_input_u32_slice(&to_vec(&(7u32, 8u32)).unwrap());
+        let receipt = prover.run().unwrap();
+        let (config, actual): (u32, Vec<u8>) =
+            from_slice(&receipt.get_journal_vec().unwrap()).unwrap();
+        assert_eq!(config, 1234);
+        assert_eq!(actual, data);
+    }
+
+    #[test]
+    fn input_file() {
+        let data: Vec<u8> = "Hello, file".into();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_input_value(7, &1234u32)
+            .unwrap()
+            .with_input_file(8, file.path())
+            .unwrap();
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(STREAMS_PATH).unwrap(), STREAMS_ID, opts).unwrap();
+        prover.add_input_u32_slice(&to_vec(&(7u32, 8u32)).unwrap());
+        let receipt = prover.run().unwrap();
+        let (_, actual): (u32, Vec<u8>) = from_slice(&receipt.get_journal_vec().unwrap()).unwrap();
+        assert_eq!(actual, data);
+
+        assert!(ProverOpts::default()
+            .with_input_file(8, file.path().with_extension("missing"))
+            .is_err());
+    }
+
+    #[test]
+    fn reserved_input_channels() {
+        use crate::platform::io::{
+            SENDRECV_CHANNEL_CONTINUE, SENDRECV_CHANNEL_FIELD, SENDRECV_CHANNEL_INITIAL_INPUT,
+            SENDRECV_CHANNEL_KV, SENDRECV_CHANNEL_LOG, SENDRECV_CHANNEL_SECRET,
+            SENDRECV_CHANNEL_STDERR, SENDRECV_CHANNEL_STDOUT,
+        };
+
+        for channel in [
+            SENDRECV_CHANNEL_INITIAL_INPUT,
+            SENDRECV_CHANNEL_STDOUT,
+            SENDRECV_CHANNEL_STDERR,
+            SENDRECV_CHANNEL_FIELD,
+            SENDRECV_CHANNEL_KV,
+            SENDRECV_CHANNEL_SECRET,
+            SENDRECV_CHANNEL_LOG,
+            SENDRECV_CHANNEL_CONTINUE,
+        ] {
+            assert!(ProverOpts::default()
+                .with_input_stream(channel, vec![1])
+                .is_err());
+            assert!(ProverOpts::default()
+                .with_input_value(channel, &1u32)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn verify_claim() {
+        use risc0_zkp::core::sha::{default_implementation, Sha};
+
+        let data: Vec<u8> = "A journal too long to be committed directly".into();
+        let opts = ProverOpts::default()
+            .with_input_value(7, &1234u32)
+            .unwrap()
+            .with_input_stream(8, data)
+            .unwrap();
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(STREAMS_PATH).unwrap(), STREAMS_ID, opts).unwrap();
+        prover.add_input_u32_slice(&to_vec(&(7u32, 8u32)).unwrap());
+        let receipt = prover.run().unwrap();
+        let journal_sha = *default_implementation().hash_bytes(&receipt.get_journal().unwrap());
+        receipt.verify_claim(STREAMS_ID, journal_sha).unwrap();
+        assert!(receipt.verify_claim(STREAMS_ID, Digest::default()).is_err());
+    }
+
+    #[test]
+    fn typed_main() {
+        let opts = ProverOpts::default().with_skip_seal(true);
+        let mut prover = Prover::new_with_opts(
+            &std::fs::read(TYPED_MAIN_PATH).unwrap(),
+            TYPED_MAIN_ID,
+            opts,
+        )
+        .unwrap();
+        prover.add_input_u32_slice(&to_vec(&(0x1_0000u32, 0x2_0000u32)).unwrap());
+        let receipt = prover.run().unwrap();
+        let product: u64 = from_slice(&receipt.get_journal_vec().unwrap()).unwrap();
+        assert_eq!(product, 0x2_0000_0000);
+    }
+
+    #[test]
+    fn kv_store() {
+        use crate::kv::KvTree;
+        use risc0_zkp::core::sha::default_implementation;
+        use std::collections::BTreeMap;
+
+        let store = BTreeMap::from([
+            (b"alice".to_vec(), b"10".to_vec()),
+            (b"bob".to_vec(), b"20".to_vec()),
+            (b"carol".to_vec(), b"30".to_vec()),
+        ]);
+        let tree = KvTree::new(default_implementation(), store);
+        let root = tree.root();
+        let keys: Vec<Vec<u8>> = vec![b"bob".to_vec(), b"dave".to_vec(), b"alice".to_vec()];
+        let opts = ProverOpts::default()
+            .with_skip_seal(true)
+            .with_kv_store(tree);
+        let mut prover =
+            Prover::new_with_opts(&std::fs::read(KV_PATH).unwrap(), KV_ID, opts).unwrap();
+        prover.add_input_u32_slice(&to_vec(&(root, keys)).unwrap());
+        let receipt = prover.run().unwrap();
+        let (committed, values): (Digest, Vec<Option<Vec<u8>>>) =
+            from_slice(&receipt.get_journal_vec().unwrap()).unwrap();
+        assert_eq!(committed, root);
+        assert_eq!(
+            values,
+            vec![Some(b"20".to_vec()), None, Some(b"10".to_vec())]
+        );
+    }
+
+    #[test]
+    fn receipt_chain() {
+        use super::{image_id, verify_chain, ReceiptChain};
+
+        let elf = std::fs::read(CHAIN_PATH).unwrap();
+        let prove = |prev: Option<&Receipt>, genesis: &Digest| {
+            let mut prover = Prover::new(&elf, CHAIN_ID).unwrap();
+            let prev = prev.map(|receipt| {
+                let journal = receipt.get_journal().unwrap().to_vec();
+                (image_id(CHAIN_ID), journal)
+            });
+            let first = prev.is_none();
+            prover.add_input_u32_slice(&to_vec(&prev).unwrap());
+            if first {
+                prover.add_input_u32_slice(&to_vec(genesis).unwrap());
+            }
+            prover.run().unwrap()
+        };
+
+        let genesis = Digest::new([1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut chain = ReceiptChain::new(genesis);
+        chain.append(CHAIN_ID, prove(None, &genesis)).unwrap();
+        let second = prove(chain.links().last().map(|(_, receipt)| receipt), &genesis);
+        chain.append(CHAIN_ID, second).unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(
+            verify_chain(&genesis, &[CHAIN_ID], &chain).unwrap(),
+            *chain.head()
+        );
+
+        // A chain doesn't verify from the wrong genesis or for other methods,
+        // and a new chain doesn't accept a receipt linking to another genesis.
+        let other = Digest::default();
+        assert!(verify_chain(&other, &[CHAIN_ID], &chain).is_err());
+        assert!(verify_chain(&genesis, &[PIPELINE_ID], &chain).is_err());
+        assert!(ReceiptChain::new(other)
+            .append(CHAIN_ID, prove(None, &genesis))
+            .is_err());
+    }
+
+    #[test]
+    fn pipeline() {
+        use super::Pipeline;
+
+        let elf = std::fs::read(PIPELINE_PATH).unwrap();
+        let mut pipeline = Pipeline::new();
+        let a = pipeline.add_stage("a", &elf, PIPELINE_ID, &[]).unwrap();
+        let b = pipeline.add_stage("b", &elf, PIPELINE_ID, &[a]).unwrap();
+        let c = pipeline.add_stage("c", &elf, PIPELINE_ID, &[a]).unwrap();
+        let d = pipeline.add_stage("d", &elf, PIPELINE_ID, &[b, c]).unwrap();
+        for (stage, input) in [(a, 1u32), (b, 2), (c, 10), (d, 0)] {
+            pipeline.add_input_u32_slice(stage, &to_vec(&input).unwrap());
+        }
+        assert_eq!(pipeline.stage("c"), Some(c));
+        assert!(pipeline.add_stage("a", &elf, PIPELINE_ID, &[]).is_err());
+        // Stages can only depend on stages already in the same pipeline.
+        assert!(Pipeline::new()
+            .add_stage("e", &elf, PIPELINE_ID, &[d])
+            .is_err());
+
+        let receipt = pipeline.run().unwrap();
+        receipt.verify().unwrap();
+        let total = |stage| {
+            let journal = receipt.get(stage).receipt().get_journal_vec().unwrap();
+            *journal.last().unwrap()
+        };
+        assert_eq!(total(b), 3);
+        assert_eq!(total(c), 11);
+        assert_eq!(total(d), 14);
+        assert_eq!(receipt.get(d).deps(), [b, c]);
+    }
+
+    #[test]
+    fn composite_receipt() {
+        use super::CompositeReceipt;
+
+        let elf = std::fs::read(CHAIN_PATH).unwrap();
+        let prove = |prev: Option<&Receipt>| {
+            let mut prover = Prover::new(&elf, CHAIN_ID).unwrap();
+            let prev = prev.map(|receipt| {
+                let journal = receipt.get_journal().unwrap().to_vec();
+                (super::image_id(CHAIN_ID), journal)
+            });
+            let first = prev.is_none();
+            prover.add_input_u32_slice(&to_vec(&prev).unwrap());
+            if first {
+                prover.add_input_u32_slice(&to_vec(&Digest::default()).unwrap());
+            }
+            prover.run().unwrap()
+        };
+        let mut segments = vec![prove(None)];
+        for _ in 0..3 {
+            segments.push(prove(segments.last()));
+        }
+        let journal = segments.last().unwrap().get_journal().unwrap().to_vec();
+        let composite = CompositeReceipt::new(CHAIN_ID, segments).unwrap();
+        assert_eq!(composite.get_journal().unwrap(), journal);
+        composite.verify(CHAIN_ID).unwrap();
+        composite.verify_sample(CHAIN_ID, 0.5, 7).unwrap();
+
+        // Sampling is determined by the seed.
+        assert_eq!(composite.sample(0.5, 7).unwrap().len(), 2);
+        assert_eq!(
+            composite.sample(0.5, 7).unwrap(),
+            composite.sample(0.5, 7).unwrap()
+        );
+        assert_eq!(composite.sample(1.0, 7).unwrap(), [0, 1, 2, 3]);
+        assert!(composite.sample(0.0, 7).unwrap().is_empty());
+        assert!(composite.sample(1.5, 7).is_err());
+
+        // A garbled seal is caught only if its segment is sampled.
+        let copy = || -> Vec<Receipt> {
+            composite
+                .segments()
+                .iter()
+                .map(|receipt| {
+                    Receipt::new(&receipt.get_journal().unwrap(), receipt.get_seal().unwrap())
+                        .unwrap()
+                })
+                .collect()
+        };
+        let garble = |i: usize| {
+            let mut segments = copy();
+            let mut seal = segments[i].get_seal().unwrap().to_vec();
+            let pos = seal.len() / 2;
+            seal[pos] ^= 1;
+            segments[i] = Receipt::new(&segments[i].get_journal().unwrap(), &seal).unwrap();
+            CompositeReceipt::new(CHAIN_ID, segments).unwrap()
+        };
+        let sampled = composite.sample(0.25, 3).unwrap();
+        let unsampled = (0..4).find(|i| !sampled.contains(i)).unwrap();
+        let forged = garble(unsampled);
+        forged.verify_sample(CHAIN_ID, 0.25, 3).unwrap();
+        assert!(forged.verify(CHAIN_ID).is_err());
+        assert!(garble(sampled[0]).verify_sample(CHAIN_ID, 0.25, 3).is_err());
+
+        // Segments out of order don't link.
+        let mut segments = copy();
+        segments.swap(1, 2);
+        assert!(CompositeReceipt::new(CHAIN_ID, segments).is_err());
+        assert!(CompositeReceipt::new(CHAIN_ID, Vec::new()).is_err());
+    }
 }
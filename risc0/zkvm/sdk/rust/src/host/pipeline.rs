@@ -0,0 +1,231 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Running several guests whose inputs depend on each other's outputs.
+//!
+//! A [Pipeline] is a DAG of stages, each a guest method.  A stage may depend
+//! on earlier stages, and receives their journals, in the order its
+//! dependencies were listed, as its first input: a `Vec<(Digest, Vec<u8>)>`
+//! of the [image ID](super::image_id) of each dependency's method and its
+//! journal, to `env::read`.  Any inputs added with
+//! [Pipeline::add_input_u32_slice] follow.  Since a stage can only depend on
+//! stages added before it, the order stages are added in is already a valid
+//! schedule, and [Pipeline::run] proves them one at a time in that order, so
+//! the same pipeline always produces the same receipts.
+//!
+//! The receipts are composed by claim linking, as in a
+//! [ReceiptChain](super::ReceiptChain): each stage must commit the
+//! [claim digest](super::claim_digest) of each dependency, in order,
+//! before anything else.  [PipelineReceipt::verify] checks every
+//! receipt and that each journal begins with those digests, so a verifier
+//! knows each stage consumed the outputs its dependencies proved.
+
+use anyhow::{bail, Result};
+use risc0_zkp::core::sha::{Digest, DIGEST_WORDS};
+
+use super::{claim_digest, image_id, Prover, Receipt};
+use crate::serde::to_vec;
+
+/// Identifies a stage of a [Pipeline].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StageId(usize);
+
+struct Stage {
+    name: String,
+    elf: Vec<u8>,
+    method_id: Vec<u8>,
+    deps: Vec<StageId>,
+    input: Vec<u32>,
+}
+
+/// A DAG of guest methods, each fed the journals of the stages it depends
+/// on.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// Construct an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a stage named `name`, running the method `elf` with ID
+    /// `method_id`, which depends on `deps`.  Names must be unique, and each
+    /// dependency must be a stage already in this pipeline.
+    pub fn add_stage(
+        &mut self,
+        name: &str,
+        elf: &[u8],
+        method_id: &[u8],
+        deps: &[StageId],
+    ) -> Result<StageId> {
+        if self.stages.iter().any(|stage| stage.name == name) {
+            bail!("Pipeline already has a stage named {name}");
+        }
+        if let Some(dep) = deps.iter().find(|dep| dep.0 >= self.stages.len()) {
+            bail!("Stage {name} depends on unknown stage {}", dep.0);
+        }
+        self.stages.push(Stage {
+            name: name.to_string(),
+            elf: elf.to_vec(),
+            method_id: method_id.to_vec(),
+            deps: deps.to_vec(),
+            input: Vec::new(),
+        });
+        Ok(StageId(self.stages.len() - 1))
+    }
+
+    /// Add input for `stage`, following the journals of its dependencies.
+    pub fn add_input_u32_slice(&mut self, stage: StageId, slice: &[u32]) {
+        self.stages[stage.0].input.extend_from_slice(slice);
+    }
+
+    /// Look up a stage by name.
+    pub fn stage(&self, name: &str) -> Option<StageId> {
+        self.stages
+            .iter()
+            .position(|stage| stage.name == name)
+            .map(StageId)
+    }
+
+    /// Prove every stage, in the order they were added.
+    pub fn run(&self) -> Result<PipelineReceipt> {
+        let mut receipts: Vec<Receipt> = Vec::with_capacity(self.stages.len());
+        for stage in &self.stages {
+            let deps = stage
+                .deps
+                .iter()
+                .map(|dep| {
+                    let method_id = self.stages[dep.0].method_id.as_slice();
+                    Ok((method_id, receipts[dep.0].get_journal()?.to_vec()))
+                })
+                .collect::<Result<Vec<(&[u8], Vec<u8>)>>>()?;
+            let input: Vec<(Digest, &Vec<u8>)> = deps
+                .iter()
+                .map(|(method_id, journal)| (image_id(method_id), journal))
+                .collect();
+            let mut prover = Prover::new(&stage.elf, &stage.method_id)?;
+            prover.add_input_u32_slice(&to_vec(&input)?);
+            prover.add_input_u32_slice(&stage.input);
+            let receipt = prover.run()?;
+            check_links(&stage.name, &deps, &receipt)?;
+            receipts.push(receipt);
+        }
+        Ok(PipelineReceipt {
+            stages: self
+                .stages
+                .iter()
+                .zip(receipts)
+                .map(|(stage, receipt)| StageReceipt {
+                    name: stage.name.clone(),
+                    method_id: stage.method_id.clone(),
+                    deps: stage.deps.clone(),
+                    receipt,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Check that the journal of `receipt` begins with the claim digests of
+/// `deps`, pairs of method ID and journal, in order.
+fn check_links(name: &str, deps: &[(&[u8], Vec<u8>)], receipt: &Receipt) -> Result<()> {
+    let journal = receipt.get_journal_vec()?;
+    if journal.len() < deps.len() * DIGEST_WORDS {
+        bail!("Journal of stage {name} is too short to link to its dependencies");
+    }
+    for (i, (method_id, dep)) in deps.iter().enumerate() {
+        let claim: Digest = claim_digest(method_id, dep);
+        if journal[i * DIGEST_WORDS..(i + 1) * DIGEST_WORDS] != *claim.as_slice() {
+            bail!("Stage {name} does not link to the claim {claim} of dependency {i}");
+        }
+    }
+    Ok(())
+}
+
+/// The receipt of one stage of a [PipelineReceipt].
+pub struct StageReceipt {
+    name: String,
+    method_id: Vec<u8>,
+    deps: Vec<StageId>,
+    receipt: Receipt,
+}
+
+impl StageReceipt {
+    /// The name of the stage.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The method ID the stage ran.
+    pub fn method_id(&self) -> &[u8] {
+        &self.method_id
+    }
+
+    /// The stages this one depends on.
+    pub fn deps(&self) -> &[StageId] {
+        &self.deps
+    }
+
+    /// The stage's receipt.
+    pub fn receipt(&self) -> &Receipt {
+        &self.receipt
+    }
+}
+
+/// The receipts of every stage of a [Pipeline], in the order they ran.
+pub struct PipelineReceipt {
+    stages: Vec<StageReceipt>,
+}
+
+impl PipelineReceipt {
+    /// The receipts of every stage.
+    pub fn stages(&self) -> &[StageReceipt] {
+        &self.stages
+    }
+
+    /// The receipt of `stage`.
+    pub fn get(&self, stage: StageId) -> &StageReceipt {
+        &self.stages[stage.0]
+    }
+
+    /// Verify every stage against its recorded method ID, and that each
+    /// stage links to the journals of its dependencies.
+    ///
+    /// The method IDs are not otherwise trusted; verifiers should check
+    /// [StageReceipt::method_id] against the methods they expect.
+    pub fn verify(&self) -> Result<()> {
+        for (i, stage) in self.stages.iter().enumerate() {
+            stage.receipt.verify(&stage.method_id[..])?;
+            let deps = stage
+                .deps
+                .iter()
+                .map(|dep| {
+                    if dep.0 >= i {
+                        bail!("Stage {} depends on a later stage", stage.name);
+                    }
+                    let dep = &self.stages[dep.0];
+                    Ok((
+                        dep.method_id.as_slice(),
+                        dep.receipt.get_journal()?.to_vec(),
+                    ))
+                })
+                .collect::<Result<Vec<(&[u8], Vec<u8>)>>>()?;
+            check_links(&stage.name, &deps, &stage.receipt)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Organizational requirements on the receipts a verifier accepts.
+//!
+//! A [VerifierPolicy] is checked in addition to the cryptographic validity
+//! of a receipt by [Receipt::verify_with_policy](super::Receipt). Its
+//! defaults accept exactly the receipts produced by this version of the
+//! prover.
+
+use risc0_zkp::{
+    core::{
+        blake3_cpu, keccak_cpu, log2_ceil, poseidon,
+        sha::{default_implementation, Digest, Sha, DIGEST_WORDS},
+        sha_cpu,
+    },
+    INV_RATE, QUERIES,
+};
+
+use super::{Exception, Result};
+use crate::platform::memory::COMMIT;
+
+/// The version of the RISC-V circuit that receipts are proven against.
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// A hash function used to commit to the execution trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashSuite {
+    /// SHA-256, used by the prover in this crate.
+    Sha256,
+
+    /// Keccak-256, for receipts checked by a verifier on the EVM, where it
+    /// is natively available as the `KECCAK256` opcode. Such receipts also
+    /// commit to their journal with it; see [HashSuite::journal_digest]. See
+    /// [risc0_zkp::core::keccak_cpu].
+    Keccak256,
+
+    /// Poseidon over the base field, for receipts verified within another
+    /// proof, where SHA-256 would dominate the cost. See
+    /// [risc0_zkp::core::poseidon].
+    Poseidon,
+
+    /// BLAKE3, for receipts that needn't be verified on-chain, where it
+    /// commits to the trace in about half the time of SHA-256. See
+    /// [risc0_zkp::core::blake3_cpu].
+    Blake3,
+}
+
+impl HashSuite {
+    /// The name of this suite, as used in file formats.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashSuite::Sha256 => "sha-256",
+            HashSuite::Keccak256 => "keccak-256",
+            HashSuite::Poseidon => "poseidon",
+            HashSuite::Blake3 => "blake3",
+        }
+    }
+
+    /// Look up a suite by its [name](HashSuite::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|suite| suite.name() == name)
+    }
+
+    /// The id this suite is recorded with in a seal; see
+    /// [risc0_zkp::core::hash::HashSuite::id].
+    pub fn id(&self) -> u32 {
+        match self {
+            HashSuite::Sha256 => <sha_cpu::Impl as Sha>::SUITE_ID,
+            HashSuite::Keccak256 => <keccak_cpu::Impl as Sha>::SUITE_ID,
+            HashSuite::Poseidon => <poseidon::Impl as Sha>::SUITE_ID,
+            HashSuite::Blake3 => <blake3_cpu::Impl as Sha>::SUITE_ID,
+        }
+    }
+
+    /// The digest that a receipt proven with this suite commits to its
+    /// journal with, if the journal is longer than a [Digest]; this is what
+    /// `Receipt::verify_claim` checks.  It's SHA-256, except with
+    /// [Keccak256](HashSuite::Keccak256), where it's Keccak-256 so that a
+    /// Solidity contract computes it with `keccak256(journal)`.  The guest
+    /// computes the digest, and must be built with the `keccak-journal`
+    /// feature of `risc0-zkvm-guest` to commit with Keccak-256.
+    pub fn journal_digest(&self, journal: &[u8]) -> Digest {
+        match self {
+            HashSuite::Keccak256 => *keccak_cpu::Impl {}.hash_bytes(journal),
+            _ => *default_implementation().hash_bytes(journal),
+        }
+    }
+
+    /// The bits in a digest of this suite.  Poseidon digests hold a base
+    /// field element in each word, which has just under 31 bits.
+    pub fn digest_bits(&self) -> u32 {
+        match self {
+            HashSuite::Poseidon => DIGEST_WORDS as u32 * 30,
+            _ => DIGEST_WORDS as u32 * 32,
+        }
+    }
+
+    /// Look up a suite by its [id](HashSuite::id).
+    pub fn from_id(id: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|suite| suite.id() == id)
+    }
+
+    pub(crate) const ALL: [HashSuite; 4] = [
+        HashSuite::Sha256,
+        HashSuite::Keccak256,
+        HashSuite::Poseidon,
+        HashSuite::Blake3,
+    ];
+}
+
+/// The hash suite that receipts are produced with, unless another is chosen
+/// with [ProverOpts::with_hash_suite](super::ProverOpts::with_hash_suite).
+pub const HASH_SUITE: HashSuite = HashSuite::Sha256;
+
+/// The estimated security of a receipt committed with `suite`, in bits.
+///
+/// This is the conjectured soundness of the FRI queries, capped by the
+/// collision resistance of the suite's digests, which is half their
+/// [bits](HashSuite::digest_bits).
+pub fn security_bits(suite: HashSuite) -> u32 {
+    let fri_bits = (QUERIES * log2_ceil(INV_RATE)) as u32;
+    fri_bits.min(suite.digest_bits() / 2)
+}
+
+/// Requirements a receipt must meet, beyond being valid, to be accepted.
+#[derive(Clone, Debug)]
+pub struct VerifierPolicy {
+    /// The minimum estimated security, in bits; see [security_bits].
+    pub min_security_bits: u32,
+
+    /// The hash suites which are accepted.
+    pub hash_suites: Vec<HashSuite>,
+
+    /// The maximum length of the journal, in bytes.
+    pub max_journal_len: usize,
+
+    /// Whether to reject receipts proven without zero knowledge; see
+    /// [ProverOpts::with_zk](super::ProverOpts::with_zk).
+    pub require_zk: bool,
+}
+
+impl Default for VerifierPolicy {
+    fn default() -> Self {
+        VerifierPolicy {
+            min_security_bits: 100,
+            hash_suites: vec![HASH_SUITE],
+            max_journal_len: COMMIT.len_bytes(),
+            require_zk: false,
+        }
+    }
+}
+
+impl VerifierPolicy {
+    /// Require at least `bits` of estimated security.
+    pub fn with_min_security_bits(self, bits: u32) -> Self {
+        Self {
+            min_security_bits: bits,
+            ..self
+        }
+    }
+
+    /// Accept only the given hash suites.
+    pub fn with_hash_suites(self, hash_suites: &[HashSuite]) -> Self {
+        Self {
+            hash_suites: hash_suites.to_vec(),
+            ..self
+        }
+    }
+
+    /// Reject journals longer than `len` bytes.
+    pub fn with_max_journal_len(self, len: usize) -> Self {
+        Self {
+            max_journal_len: len,
+            ..self
+        }
+    }
+
+    /// Reject receipts proven without zero knowledge if `require_zk` is set.
+    pub fn with_require_zk(self, require_zk: bool) -> Self {
+        Self { require_zk, ..self }
+    }
+
+    /// Check that a receipt whose seal was committed with `suite` meets this
+    /// policy, including its [security_bits].
+    pub fn check_hash_suite(&self, suite: HashSuite) -> Result<()> {
+        if !self.hash_suites.contains(&suite) {
+            return Err(Exception::new(&format!(
+                "Hash suite {suite:?} is not accepted"
+            )));
+        }
+        if security_bits(suite) < self.min_security_bits {
+            return Err(Exception::new(&format!(
+                "Receipt security of {} bits is below the required {}",
+                security_bits(suite),
+                self.min_security_bits
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that a receipt whose seal was proven with (`zk`) or without
+    /// zero knowledge meets this policy.
+    pub fn check_zk(&self, zk: bool) -> Result<()> {
+        if self.require_zk && !zk {
+            return Err(Exception::new("Receipt was proven without zero knowledge"));
+        }
+        Ok(())
+    }
+
+    /// Check that a receipt with a journal of `journal_len` bytes meets this
+    /// policy.  The hash suite, and with it the security, and zero knowledge
+    /// are recorded in the seal, and checked separately.
+    pub fn check(&self, journal_len: usize) -> Result<()> {
+        if journal_len > self.max_journal_len {
+            return Err(Exception::new(&format!(
+                "Journal of {journal_len} bytes exceeds the maximum of {}",
+                self.max_journal_len
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{security_bits, HashSuite, VerifierPolicy, HASH_SUITE};
+
+    #[test]
+    fn default_accepts() {
+        let policy = VerifierPolicy::default();
+        assert!(security_bits(HASH_SUITE) >= policy.min_security_bits);
+        policy.check(0).unwrap();
+        policy.check(policy.max_journal_len).unwrap();
+        policy.check_hash_suite(HASH_SUITE).unwrap();
+        assert!(policy.check_hash_suite(HashSuite::Poseidon).is_err());
+    }
+
+    #[test]
+    fn rejects() {
+        let policy = VerifierPolicy::default();
+        assert!(policy.check(policy.max_journal_len + 1).is_err());
+        let policy = VerifierPolicy::default().with_max_journal_len(32);
+        assert!(policy.check(33).is_err());
+        let policy =
+            VerifierPolicy::default().with_min_security_bits(security_bits(HASH_SUITE) + 1);
+        assert!(policy.check_hash_suite(HASH_SUITE).is_err());
+        policy.check(0).unwrap();
+        let policy = VerifierPolicy::default().with_hash_suites(&[]);
+        assert!(policy.check_hash_suite(HASH_SUITE).is_err());
+        let policy = VerifierPolicy::default().with_hash_suites(&[HashSuite::Keccak256]);
+        assert!(policy.check_hash_suite(HASH_SUITE).is_err());
+        let policy = VerifierPolicy::default().with_hash_suites(&[HashSuite::Poseidon]);
+        policy.check_hash_suite(HashSuite::Poseidon).unwrap();
+        VerifierPolicy::default().check_zk(false).unwrap();
+        let policy = VerifierPolicy::default().with_require_zk(true);
+        policy.check_zk(true).unwrap();
+        assert!(policy.check_zk(false).is_err());
+    }
+
+    #[test]
+    fn security_by_suite() {
+        use risc0_zkp::{core::log2_ceil, INV_RATE, QUERIES};
+
+        let fri_bits = (QUERIES * log2_ceil(INV_RATE)) as u32;
+        for suite in HashSuite::ALL {
+            assert_eq!(security_bits(suite), fri_bits.min(suite.digest_bits() / 2));
+        }
+        assert!(HashSuite::Poseidon.digest_bits() < HashSuite::Sha256.digest_bits());
+    }
+
+    #[test]
+    fn suite_names() {
+        for suite in HashSuite::ALL {
+            assert_eq!(HashSuite::from_name(suite.name()), Some(suite));
+            assert_eq!(HashSuite::from_id(suite.id()), Some(suite));
+        }
+        assert_eq!(HashSuite::from_name("sha3-256"), None);
+    }
+}
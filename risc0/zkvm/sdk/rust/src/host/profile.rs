@@ -0,0 +1,168 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named bundles of proving parameters.
+//!
+//! A [Profile] fixes the hash suite and zero-knowledge blinding a receipt is
+//! proven with, so that services refer to a profile by
+//! [name](Profile::name) rather than each tuning the raw parameters.  A
+//! prover selects one with
+//! [ProverOpts::with_profile](super::ProverOpts::with_profile), and a
+//! verifier enforces one with the [VerifierPolicy] from [Profile::policy].
+//!
+//! Profiles can't tune the FRI parameters: this version of the prover
+//! always makes [QUERIES](risc0_zkp::QUERIES) queries without
+//! proof-of-work, so a profile's [security_bits] depend only on its hash
+//! suite.
+
+use super::policy::{security_bits, HashSuite, VerifierPolicy, HASH_SUITE};
+
+// The C++ prover proves with SHA-256 only, so Dev commits with BLAKE3 only
+// when the Rust prover of the `pure-prove` feature is built in.
+#[cfg(feature = "pure-prove")]
+const DEV_HASH_SUITE: HashSuite = HashSuite::Blake3;
+#[cfg(not(feature = "pure-prove"))]
+const DEV_HASH_SUITE: HashSuite = HASH_SUITE;
+
+/// The parameters a [Profile] proves with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileParams {
+    /// The hash suite the trace is committed with; see
+    /// [ProverOpts::with_hash_suite](super::ProverOpts::with_hash_suite).
+    pub hash_suite: HashSuite,
+
+    /// Whether the trace is padded with random values, giving zero
+    /// knowledge; see [ProverOpts::with_zk](super::ProverOpts::with_zk).
+    pub zk: bool,
+}
+
+/// A named bundle of proving parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// For development and tests on public inputs: commits without zero
+    /// knowledge, so proving is reproducible, and has a policy which accepts
+    /// the receipts of any profile.  With the `pure-prove` feature, it
+    /// commits with [BLAKE3](HashSuite::Blake3), which proves faster on the
+    /// CPU; otherwise with [HASH_SUITE], the only suite the C++ prover
+    /// supports.
+    Dev,
+
+    /// The default for production: commits with [HASH_SUITE] and zero
+    /// knowledge, and has the default [VerifierPolicy].
+    Standard,
+
+    /// For receipts over private inputs: as [Profile::Standard], but the
+    /// policy also rejects receipts proven without zero knowledge.
+    HighSecurity,
+}
+
+impl Profile {
+    /// Every profile, from the weakest to the strongest.
+    pub const ALL: [Profile; 3] = [Profile::Dev, Profile::Standard, Profile::HighSecurity];
+
+    /// The name of this profile, as used in configuration files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Dev => "dev",
+            Profile::Standard => "standard",
+            Profile::HighSecurity => "high-security",
+        }
+    }
+
+    /// Look up a profile by its [name](Profile::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+
+    /// The parameters receipts are proven with under this profile.
+    pub fn params(&self) -> ProfileParams {
+        match self {
+            Profile::Dev => ProfileParams {
+                hash_suite: DEV_HASH_SUITE,
+                zk: false,
+            },
+            Profile::Standard | Profile::HighSecurity => ProfileParams {
+                hash_suite: HASH_SUITE,
+                zk: true,
+            },
+        }
+    }
+
+    /// A policy which accepts the receipts proven under this profile, or
+    /// under a stronger one.
+    pub fn policy(&self) -> VerifierPolicy {
+        let policy = VerifierPolicy::default().with_require_zk(*self == Profile::HighSecurity);
+        match self {
+            Profile::Dev => policy
+                .with_hash_suites(&HashSuite::ALL)
+                .with_min_security_bits(0),
+            _ => policy
+                .with_hash_suites(&[self.params().hash_suite])
+                .with_min_security_bits(security_bits(self.params().hash_suite)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use crate::host::ProverOpts;
+
+    #[test]
+    fn names() {
+        for profile in Profile::ALL {
+            assert_eq!(Profile::from_name(profile.name()), Some(profile));
+        }
+        assert_eq!(Profile::from_name("fast"), None);
+    }
+
+    #[test]
+    fn provable() {
+        // Each profile accepts its own receipts, and proves with its own
+        // parameters.
+        for profile in Profile::ALL {
+            let params = profile.params();
+            let policy = profile.policy();
+            policy.check(0).unwrap();
+            policy.check_hash_suite(params.hash_suite).unwrap();
+            policy.check_zk(params.zk).unwrap();
+            let opts = ProverOpts::default().with_profile(profile);
+            assert_eq!(opts.zk, params.zk);
+            assert_eq!(opts.hash_suite, params.hash_suite);
+        }
+    }
+
+    #[test]
+    fn stronger_policies() {
+        // A policy accepts the receipts of stronger profiles, but not of
+        // weaker ones.
+        for (i, weak) in Profile::ALL.into_iter().enumerate() {
+            for strong in &Profile::ALL[i..] {
+                let policy = weak.policy();
+                policy.check_zk(strong.params().zk).unwrap();
+                policy.check_hash_suite(strong.params().hash_suite).unwrap();
+            }
+        }
+        #[cfg(feature = "pure-prove")]
+        assert!(Profile::Standard
+            .policy()
+            .check_hash_suite(Profile::Dev.params().hash_suite)
+            .is_err());
+        assert!(Profile::HighSecurity
+            .policy()
+            .check_zk(Profile::Dev.params().zk)
+            .is_err());
+        assert!(Profile::Standard.policy().min_security_bits > 0);
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Confidential inputs that are only decrypted while the guest executes.
+//!
+//! An operator proving over a third party's data holds that data sealed,
+//! i.e. encrypted to a key which the operator's [KeyProvider] controls, such
+//! as a key in an HSM or a cloud KMS. The sealed inputs are registered with
+//! [SecretInputs] and served to the guest on
+//! [SENDRECV_CHANNEL_SECRET](crate::platform::io::SENDRECV_CHANNEL_SECRET).
+//! Each input is unsealed only when the guest asks for it, and the host's
+//! plaintext copy is overwritten as soon as it has been handed to the
+//! executor.
+//!
+//! The plaintext becomes part of the guest's memory, and so of the witness
+//! from which the seal is generated. The prover process must therefore be
+//! treated as confidential too. Neither the receipt nor the journal
+//! contains the plaintext unless the guest commits it.
+
+use std::{collections::HashMap, sync::atomic};
+
+use crate::platform::io::{SECRET_NOT_FOUND, SECRET_OK, SECRET_UNSEAL_FAILED};
+
+/// Unseals inputs which were encrypted for this operator.
+pub trait KeyProvider: Sync {
+    /// Decrypt the sealed input registered as `name`.
+    fn unseal(&self, name: &str, sealed: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+// Plaintext which is overwritten when dropped.
+struct Plaintext(Vec<u8>);
+
+impl Drop for Plaintext {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+// Overwrite `buf` with zeros in a way the compiler may not elide.
+pub(crate) fn wipe(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned reference.
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    atomic::compiler_fence(atomic::Ordering::SeqCst);
+}
+
+/// Sealed inputs available to a guest, along with the [KeyProvider] that
+/// unseals them.
+pub struct SecretInputs<'a> {
+    provider: &'a dyn KeyProvider,
+    sealed: HashMap<String, Vec<u8>>,
+}
+
+impl<'a> SecretInputs<'a> {
+    /// Create a set of inputs unsealed by `provider`.
+    pub fn new(provider: &'a dyn KeyProvider) -> Self {
+        SecretInputs {
+            provider,
+            sealed: HashMap::new(),
+        }
+    }
+
+    /// Register the sealed input `sealed` under `name`, which the guest
+    /// passes to risc0_zkvm_guest::env::read_secret.
+    pub fn with_sealed(mut self, name: &str, sealed: impl Into<Vec<u8>>) -> Self {
+        self.sealed.insert(name.to_string(), sealed.into());
+        self
+    }
+
+    // Answer a request for the input named by `request`.  The response is a
+    // status word, followed by the plaintext if the status is SECRET_OK.
+    pub(crate) fn respond(&self, request: &[u8]) -> Vec<u8> {
+        let status = |status: u32| status.to_le_bytes().to_vec();
+        let name = String::from_utf8_lossy(request);
+        let sealed = match self.sealed.get(name.as_ref()) {
+            Some(sealed) => sealed,
+            None => {
+                log::warn!("Guest requested unknown secret input {name:?}");
+                return status(SECRET_NOT_FOUND);
+            }
+        };
+        let plaintext = match self.provider.unseal(&name, sealed) {
+            Ok(plaintext) => Plaintext(plaintext),
+            Err(err) => {
+                log::warn!("Failed to unseal secret input {name:?}: {err}");
+                return status(SECRET_UNSEAL_FAILED);
+            }
+        };
+        let mut response = status(SECRET_OK);
+        response.extend_from_slice(&plaintext.0);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wipe, KeyProvider, SecretInputs};
+    use crate::platform::io::{SECRET_NOT_FOUND, SECRET_OK, SECRET_UNSEAL_FAILED};
+
+    // Seals by XORing with a fixed byte; only useful for testing.
+    struct XorProvider(u8);
+
+    impl KeyProvider for XorProvider {
+        fn unseal(&self, name: &str, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+            if name == "corrupt" {
+                anyhow::bail!("Bad tag");
+            }
+            Ok(sealed.iter().map(|b| b ^ self.0).collect())
+        }
+    }
+
+    #[test]
+    fn respond() {
+        let provider = XorProvider(0x5a);
+        let sealed: Vec<u8> = b"hunter2".iter().map(|b| b ^ 0x5a).collect();
+        let inputs = SecretInputs::new(&provider)
+            .with_sealed("password", sealed)
+            .with_sealed("corrupt", [1, 2, 3]);
+
+        let response = inputs.respond(b"password");
+        assert_eq!(response[..4], SECRET_OK.to_le_bytes());
+        assert_eq!(&response[4..], b"hunter2");
+        assert_eq!(inputs.respond(b"missing"), SECRET_NOT_FOUND.to_le_bytes());
+        assert_eq!(
+            inputs.respond(b"corrupt"),
+            SECRET_UNSEAL_FAILED.to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn wipes() {
+        let mut buf = b"hunter2".to_vec();
+        wipe(&mut buf);
+        assert_eq!(buf, [0; 7]);
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Golden test vectors, for checking other verifier implementations.
+//!
+//! A vector is a directory of plain files, so that bindings in other
+//! languages and on-chain verifiers can read it without this crate. Words
+//! are stored as little-endian bytes.
+//!
+//! * `suite`: the [name](HashSuite::name) of the hash suite.
+//! * `method.elf`: the guest ELF.
+//! * `method_id`: the method ID of the ELF, with code roots committed by the
+//!   hash suite.
+//! * `input`: the input words passed to the guest.
+//! * `journal`: the bytes committed by the guest.
+//! * `seal`: the seal of the receipt, in the layout read by
+//!   [risc0_zkp::verify], with each output register split into 16-bit halves.
+//!
+//! [GoldenVector::generate] proves a guest to produce a vector, and
+//! [verify_golden] checks one against this crate. A vector made by one
+//! version of the prover should keep verifying with later versions that
+//! support the same hash suite and circuit. The vectors checked in under
+//! `testdata/` cover every [HashSuite].
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use risc0_zkp::{
+    core::sha::DIGEST_WORDS,
+    verify::{verify_seal, VerificationError},
+};
+use risc0_zkvm_circuit::CircuitImpl;
+
+use super::{journal::expected_commitment, policy::HashSuite, Prover, ProverOpts, ReceiptError};
+use crate::{
+    method_id::MethodId,
+    receipt::{check_code_root, join_halves, suite_impl},
+    serde::{bytes_to_words, words_to_bytes},
+};
+
+/// A guest, its input, and a receipt for running it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenVector {
+    /// The hash suite the seal was produced with.
+    pub suite: HashSuite,
+
+    /// The guest ELF.
+    pub elf: Vec<u8>,
+
+    /// The method ID of the guest.
+    pub method_id: Vec<u8>,
+
+    /// The input words passed to the guest.
+    pub input: Vec<u32>,
+
+    /// The bytes committed by the guest.
+    pub journal: Vec<u8>,
+
+    /// The seal of the receipt.
+    pub seal: Vec<u32>,
+}
+
+impl GoldenVector {
+    /// Prove `elf` with `input` to produce a new vector, committed with
+    /// `suite`.  Only SHA-256 can be proven without the `pure-prove`
+    /// feature.
+    pub fn generate(elf: &[u8], input: &[u32], suite: HashSuite) -> Result<Self> {
+        let method_id = MethodId::compute_for_suite(elf, suite)?.to_bytes();
+        let opts = ProverOpts::default().with_hash_suite(suite);
+        let mut prover = Prover::new_with_opts(elf, &method_id, opts)?;
+        prover.add_input_u32_slice(input);
+        let receipt = prover.run()?;
+        Ok(GoldenVector {
+            suite,
+            elf: elf.to_vec(),
+            method_id,
+            input: input.to_vec(),
+            journal: receipt.get_journal()?.to_vec(),
+            seal: receipt.rust_seal()?,
+        })
+    }
+
+    /// Read a vector from the directory `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let read = |name: &str| {
+            fs::read(dir.join(name))
+                .with_context(|| format!("Reading {}", dir.join(name).display()))
+        };
+        let read_words = |name: &str| -> Result<Vec<u32>> {
+            let bytes = read(name)?;
+            if bytes.len() % 4 != 0 {
+                bail!(
+                    "{} is not a whole number of words",
+                    dir.join(name).display()
+                );
+            }
+            Ok(bytes_to_words(&bytes))
+        };
+        let suite = String::from_utf8(read("suite")?)?;
+        let suite = match HashSuite::from_name(suite.trim()) {
+            Some(suite) => suite,
+            None => bail!("Unknown hash suite {suite:?}"),
+        };
+        Ok(GoldenVector {
+            suite,
+            elf: read("method.elf")?,
+            method_id: read("method_id")?,
+            input: read_words("input")?,
+            journal: read("journal")?,
+            seal: read_words("seal")?,
+        })
+    }
+
+    /// Write this vector to the directory `dir`, creating it if needed.
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("suite"), self.suite.name())?;
+        fs::write(dir.join("method.elf"), &self.elf)?;
+        fs::write(dir.join("method_id"), &self.method_id)?;
+        fs::write(dir.join("input"), words_to_bytes(&self.input))?;
+        fs::write(dir.join("journal"), &self.journal)?;
+        fs::write(dir.join("seal"), words_to_bytes(&self.seal))?;
+        Ok(())
+    }
+}
+
+/// Check that `vector` is accepted by this crate: its method ID must match
+/// its ELF, and its seal must verify with its hash suite, prove a run of the
+/// code in its method ID, and commit to its journal.
+pub fn verify_golden(vector: &GoldenVector) -> Result<()> {
+    let method_id = MethodId::compute_for_suite(&vector.elf, vector.suite)?;
+    if method_id.to_bytes() != vector.method_id {
+        bail!("Method ID does not match the ELF");
+    }
+    let circuit = CircuitImpl::new();
+    verify_seal(suite_impl(vector.suite), &circuit, &vector.seal, |claim| {
+        check_code_root(claim, &method_id)?;
+        let expected = expected_commitment(&vector.journal, vector.suite);
+        let regs = join_halves(claim.outputs.iter().map(|&half| u32::from(half)));
+        if regs.len() <= DIGEST_WORDS
+            || regs[DIGEST_WORDS] as usize != vector.journal.len()
+            || regs[..DIGEST_WORDS] != *expected.as_slice()
+        {
+            return Err(VerificationError::InvalidClaim);
+        }
+        Ok(())
+    })
+    .map_err(ReceiptError::from)?;
+    Ok(())
+}
+
+/// Check every vector in the subdirectories of `root` with [verify_golden],
+/// returning how many were checked.
+pub fn verify_golden_dir(root: impl AsRef<Path>) -> Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let vector = GoldenVector::load(&path)?;
+            verify_golden(&vector).with_context(|| format!("Verifying {}", path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp::core::sha::DIGEST_WORDS;
+    use risc0_zkvm_methods::SHA_PATH;
+
+    use super::*;
+    use crate::{host::policy::HASH_SUITE, serde::to_vec};
+
+    // The guest inputs of the checked-in vectors, proven with every suite.
+    const GOLDEN: [(&str, &str); 2] = [("empty", ""), ("abc", "abc")];
+
+    fn testdata() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata")
+    }
+
+    #[test]
+    fn roundtrip() {
+        let elf = fs::read(SHA_PATH).unwrap();
+        let vector = GoldenVector::generate(&elf, &to_vec(&"abc").unwrap(), HASH_SUITE).unwrap();
+        verify_golden(&vector).unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        vector.save(root.path().join("sha")).unwrap();
+        assert_eq!(GoldenVector::load(root.path().join("sha")).unwrap(), vector);
+        assert_eq!(verify_golden_dir(root.path()).unwrap(), 1);
+
+        let mut tampered = vector.clone();
+        tampered.seal[DIGEST_WORDS + 1] ^= 1;
+        assert!(verify_golden(&tampered).is_err());
+
+        let mut tampered = vector.clone();
+        tampered.method_id[0] ^= 1;
+        assert!(verify_golden(&tampered).is_err());
+
+        let mut tampered = vector;
+        tampered.journal = b"abd".to_vec();
+        assert!(verify_golden(&tampered).is_err());
+    }
+
+    // The checked-in vectors must keep verifying, and must cover every suite
+    // so that no verifier goes untested.
+    #[test]
+    fn golden() {
+        let root = testdata();
+        assert_eq!(
+            verify_golden_dir(&root).unwrap(),
+            GOLDEN.len() * HashSuite::ALL.len()
+        );
+        for suite in HashSuite::ALL {
+            for (name, _) in GOLDEN {
+                let dir = root.join(format!("{}_{name}", suite.name()));
+                assert_eq!(GoldenVector::load(&dir).unwrap().suite, suite);
+            }
+        }
+    }
+
+    // Regenerates the vectors under `testdata/`; run after a deliberate
+    // change to the proof format, with the `pure-prove` feature so that
+    // every suite can be proven.
+    #[test]
+    #[ignore]
+    #[cfg(feature = "pure-prove")]
+    fn regenerate_golden() {
+        let root = testdata();
+        let elf = fs::read(SHA_PATH).unwrap();
+        for suite in HashSuite::ALL {
+            for (name, msg) in GOLDEN {
+                let input = to_vec(&msg).unwrap();
+                let vector = GoldenVector::generate(&elf, &input, suite).unwrap();
+                vector
+                    .save(root.join(format!("{}_{name}", suite.name())))
+                    .unwrap();
+            }
+        }
+        assert_eq!(
+            verify_golden_dir(&root).unwrap(),
+            GOLDEN.len() * HashSuite::ALL.len()
+        );
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-specific tuning of the prover.
+//!
+//! [ProverOpts::auto_tune](super::ProverOpts::auto_tune) times the
+//! prover's hot loops on the current machine and remembers the fastest
+//! settings in a [TuneConfig] file, so the measurement only happens once.
+//! The file is written to `$RISC0_TUNE_CONFIG` if set, and to
+//! `~/.risc0/tune.cfg` otherwise.
+//!
+//! The tuner times an NTT on every [HalBackend] this machine can run, with
+//! each candidate thread count, and keeps the fastest combination.  It then
+//! bounds the trace at the largest po2 whose buffers fit in that backend's
+//! memory, so that a longer execution fails up front rather than swapping
+//! or running out of memory partway through the proof.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use risc0_zkp::{
+    adapter::TapsProvider,
+    core::{
+        fp::Fp,
+        sha::{default_implementation, Sha},
+    },
+    hal::{backend::HalBackend, Hal},
+    taps::RegisterGroup,
+    INV_RATE, MAX_CYCLES_PO2, MIN_PO2,
+};
+use risc0_zkvm_circuit::CircuitImpl;
+
+use super::{Exception, Result};
+
+// The version of the file format written by TuneConfig::save.
+const VERSION: u32 = 2;
+
+// The shape of the NTT timed for each candidate thread count: COLUMNS
+// columns of 2^NTT_PO2 elements, expanded by 2^EXPAND_BITS as in the prover.
+const NTT_PO2: usize = 14;
+const COLUMNS: usize = 16;
+const EXPAND_BITS: usize = 2;
+
+// The number of runs each measurement takes the best of.
+const RUNS: usize = 3;
+
+/// The settings chosen for a machine, along with the measurements behind
+/// them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TuneConfig {
+    /// The number of hardware threads when the machine was measured.  A
+    /// config is discarded once this changes.
+    pub cpus: usize,
+
+    /// Whether a GPU backend was available.
+    pub gpu: bool,
+
+    /// The backend to run the prover on.
+    pub backend: HalBackend,
+
+    /// The number of threads to run the prover with.
+    pub threads: usize,
+
+    /// The largest trace, as a power of two, which fits in the memory of
+    /// [TuneConfig::backend].
+    pub po2: usize,
+
+    /// The time taken by the benchmark NTT on [TuneConfig::backend] with
+    /// [TuneConfig::threads] threads, in microseconds.
+    pub ntt_micros: u64,
+
+    /// SHA-256 throughput over field elements on one thread, in MiB per
+    /// second.
+    pub sha_mib_per_sec: u64,
+}
+
+fn cpus() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn best_of<F: FnMut()>(mut f: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// Time the benchmark NTT on `backend`, with a pool of `threads` threads.
+fn time_ntt(backend: HalBackend, threads: usize) -> Duration {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let hal = backend.hal();
+        let size = COLUMNS << NTT_PO2;
+        let input: Vec<Fp> = (0..size as u32).map(Fp::new).collect();
+        let input = hal.copy_from(&input);
+        let output = hal.alloc(size << EXPAND_BITS);
+        best_of(|| {
+            hal.batch_expand(&output, &input, COLUMNS);
+            hal.batch_evaluate_ntt(&output, COLUMNS, EXPAND_BITS);
+        })
+    })
+}
+
+fn sha_mib_per_sec() -> u64 {
+    let sha = default_implementation();
+    let fps: Vec<Fp> = (0..1 << 18).map(Fp::new).collect();
+    let elapsed = best_of(|| {
+        sha.hash_fps(&fps);
+    });
+    let mib = (fps.len() * core::mem::size_of::<Fp>()) as f64 / (1 << 20) as f64;
+    (mib / elapsed.as_secs_f64().max(f64::EPSILON)) as u64
+}
+
+// Total physical memory, where the OS reports it.
+fn host_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+// The memory the prover's buffers live in on `backend`, if it is known.
+fn memory(backend: HalBackend) -> Option<u64> {
+    match backend {
+        HalBackend::Cpu => host_memory(),
+        #[cfg(feature = "cuda")]
+        HalBackend::Cuda => risc0_zkp::hal::cuda::device_memory(),
+        #[cfg(feature = "metal")]
+        HalBackend::Metal => risc0_zkp::hal::metal::device_memory(),
+    }
+}
+
+// An estimate of the memory the prover uses per cycle of the trace, in bytes:
+// each column of the trace along with its low-degree extension.
+fn bytes_per_cycle() -> u64 {
+    let circuit = CircuitImpl::new();
+    let taps = circuit.get_taps();
+    let columns: usize = [
+        RegisterGroup::Accum,
+        RegisterGroup::Code,
+        RegisterGroup::Data,
+    ]
+    .into_iter()
+    .map(|group| taps.group_size(group))
+    .sum();
+    (columns * (1 + INV_RATE) * core::mem::size_of::<u32>()) as u64
+}
+
+// The largest po2 whose trace fits in `memory` bytes, as estimated by
+// bytes_per_cycle.
+fn max_po2(memory: Option<u64>) -> usize {
+    let memory = match memory {
+        Some(memory) => memory,
+        None => return MAX_CYCLES_PO2,
+    };
+    (MIN_PO2..=MAX_CYCLES_PO2)
+        .rev()
+        .find(|po2| bytes_per_cycle() << po2 <= memory)
+        .unwrap_or(MIN_PO2)
+}
+
+impl TuneConfig {
+    /// Measure the current machine.  This takes a few seconds.
+    pub fn measure() -> Self {
+        let cpus = cpus();
+        // Powers of two, and the full count when that isn't one.
+        let mut candidates: Vec<usize> = (0..usize::BITS)
+            .map(|bit| 1 << bit)
+            .take_while(|&threads| threads < cpus)
+            .collect();
+        candidates.push(cpus);
+        let backends: Vec<HalBackend> = HalBackend::ALL
+            .iter()
+            .copied()
+            .filter(|backend| backend.is_available())
+            .collect();
+        let (backend, threads, ntt) = backends
+            .iter()
+            .flat_map(|&backend| candidates.iter().map(move |&threads| (backend, threads)))
+            .map(|(backend, threads)| (backend, threads, time_ntt(backend, threads)))
+            .min_by_key(|(_, _, elapsed)| *elapsed)
+            .unwrap();
+        TuneConfig {
+            cpus,
+            gpu: backends.iter().any(|&backend| backend != HalBackend::Cpu),
+            backend,
+            threads,
+            po2: max_po2(memory(backend)),
+            ntt_micros: ntt.as_micros() as u64,
+            sha_mib_per_sec: sha_mib_per_sec(),
+        }
+    }
+
+    /// Whether this config was measured on a machine like the current one.
+    pub fn is_current(&self) -> bool {
+        self.cpus == cpus() && self.backend.is_available()
+    }
+
+    /// The file that [ProverOpts::auto_tune](super::ProverOpts::auto_tune)
+    /// reads and writes.
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = std::env::var_os("RISC0_TUNE_CONFIG") {
+            return PathBuf::from(path);
+        }
+        let home = std::env::var_os("HOME").map_or_else(std::env::temp_dir, PathBuf::from);
+        home.join(".risc0").join("tune.cfg")
+    }
+
+    /// Parse a config in the format written by [TuneConfig::save].
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut fields = std::collections::HashMap::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| Exception::new(&format!("Malformed tune config line: {line}")))?;
+            fields.insert(key.trim(), value.trim());
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| Exception::new(&format!("Tune config is missing {key}")))
+        };
+        let number = |key: &str| -> Result<u64> {
+            field(key)?
+                .parse()
+                .map_err(|_| Exception::new(&format!("Tune config has a malformed {key}")))
+        };
+        if number("version")? != VERSION as u64 {
+            return Err(Exception::new("Unsupported tune config version"));
+        }
+        let backend = field("backend")?;
+        let po2 = number("po2")? as usize;
+        if !(MIN_PO2..=MAX_CYCLES_PO2).contains(&po2) {
+            return Err(Exception::new(&format!(
+                "Tune config has po2 {po2} out of range"
+            )));
+        }
+        Ok(TuneConfig {
+            cpus: number("cpus")? as usize,
+            gpu: number("gpu")? != 0,
+            backend: HalBackend::from_name(backend)
+                .ok_or_else(|| Exception::new(&format!("Unknown backend {backend}")))?,
+            threads: number("threads")?.max(1) as usize,
+            po2,
+            ntt_micros: number("ntt_micros")?,
+            sha_mib_per_sec: number("sha_mib_per_sec")?,
+        })
+    }
+
+    /// Load the config at `path`, or return None if there is none.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path).map_err(|err| Exception::new(&err.to_string()))?;
+        Self::parse(&text).map(Some)
+    }
+
+    /// Write this config to `path`, creating its directory if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| Exception::new(&err.to_string()))?;
+        }
+        fs::write(path, self.to_string()).map_err(|err| Exception::new(&err.to_string()))
+    }
+}
+
+impl std::fmt::Display for TuneConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "# Written by risc0_zkvm::host::ProverOpts::auto_tune")?;
+        writeln!(f, "version = {VERSION}")?;
+        writeln!(f, "cpus = {}", self.cpus)?;
+        writeln!(f, "gpu = {}", self.gpu as u32)?;
+        writeln!(f, "backend = {}", self.backend.name())?;
+        writeln!(f, "threads = {}", self.threads)?;
+        writeln!(f, "po2 = {}", self.po2)?;
+        writeln!(f, "ntt_micros = {}", self.ntt_micros)?;
+        writeln!(f, "sha_mib_per_sec = {}", self.sha_mib_per_sec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp::{hal::backend::HalBackend, MAX_CYCLES_PO2, MIN_PO2};
+
+    use super::{bytes_per_cycle, max_po2, TuneConfig};
+
+    #[test]
+    fn round_trip() {
+        let config = TuneConfig {
+            cpus: 8,
+            gpu: false,
+            backend: HalBackend::Cpu,
+            threads: 4,
+            po2: 20,
+            ntt_micros: 1234,
+            sha_mib_per_sec: 300,
+        };
+        assert_eq!(TuneConfig::parse(&config.to_string()).unwrap(), config);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("tune.cfg");
+        assert_eq!(TuneConfig::load(&path).unwrap(), None);
+        config.save(&path).unwrap();
+        assert_eq!(TuneConfig::load(&path).unwrap(), Some(config));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(TuneConfig::parse("").is_err());
+        assert!(TuneConfig::parse("version = 1\ncpus").is_err());
+        let text = "version = 2\ncpus = 1\ngpu = 0\nbackend = tpu\nthreads = 1\npo2 = 20\n\
+                    ntt_micros = 1\nsha_mib_per_sec = 1";
+        assert!(TuneConfig::parse(text).is_err());
+        let text = text.replace("tpu", "cpu");
+        assert!(TuneConfig::parse(&text).is_ok());
+        assert!(TuneConfig::parse(&text.replace("version = 2", "version = 1")).is_err());
+        assert!(TuneConfig::parse(&text.replace("po2 = 20", "po2 = 40")).is_err());
+    }
+
+    #[test]
+    fn po2_fits_memory() {
+        assert_eq!(max_po2(None), MAX_CYCLES_PO2);
+        assert_eq!(max_po2(Some(0)), MIN_PO2);
+        assert_eq!(max_po2(Some(bytes_per_cycle() << 18)), 18);
+        assert_eq!(max_po2(Some((bytes_per_cycle() << 18) - 1)), 17);
+    }
+
+    #[test]
+    fn measure() {
+        let config = TuneConfig::measure();
+        assert!(config.is_current());
+        assert!(HalBackend::ALL.contains(&config.backend));
+        assert!((MIN_PO2..=MAX_CYCLES_PO2).contains(&config.po2));
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact storage for the witnesses of segments waiting to be proven.
+//!
+//! A [SegmentWitness] is the machine state a segment starts from: its pc,
+//! registers and memory image.  When segments are produced faster than they
+//! can be proven, a pipeline has to hold their witnesses until a prover is
+//! free, and full memory images quickly add up.  Consecutive segments of one
+//! execution share most of their memory, so a [WitnessQueue] stores each
+//! witness as a [WitnessDelta] against the one before it: the registers that
+//! changed, and for each page that changed, the words that differ.  Deltas
+//! are kept encoded with variable-length integers, so a segment which
+//! touched a handful of pages costs a few hundred bytes.
+//!
+//! This version of the prover proves an execution as a single segment (see
+//! [ProverOpts::with_max_segment_cycles](super::ProverOpts::with_max_segment_cycles)),
+//! so these are for pipelines which split executions into segments
+//! themselves.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, VecDeque},
+};
+
+use anyhow::{bail, Result};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "prove")]
+use crate::elf::Program;
+use crate::platform::{memory::PAGE_SIZE, WORD_SIZE};
+
+/// The number of RISC-V registers.
+pub const REGISTER_COUNT: usize = 32;
+
+/// The state a segment starts executing from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentWitness {
+    /// The address of the next instruction.
+    pub pc: u32,
+
+    /// The values of the general purpose registers, x0 through x31.
+    pub registers: [u32; REGISTER_COUNT],
+
+    /// The initialized words of memory, keyed by byte address.  Words which
+    /// are not present have never been written or loaded.
+    pub memory: BTreeMap<u32, u32>,
+}
+
+#[cfg(feature = "prove")]
+impl From<&Program> for SegmentWitness {
+    /// The state a program starts from, before its first segment.
+    fn from(program: &Program) -> Self {
+        SegmentWitness {
+            pc: program.entry,
+            registers: [0; REGISTER_COUNT],
+            memory: program.image.clone(),
+        }
+    }
+}
+
+/// The words of one page which differ between two witnesses.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PageDelta {
+    /// The page index, i.e. its address divided by [PAGE_SIZE].
+    page: u32,
+
+    /// The changed words, by word index within the page, with `None` where
+    /// the word is no longer initialized.
+    words: Vec<(u16, Option<u32>)>,
+}
+
+/// The difference between a [SegmentWitness] and the one before it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessDelta {
+    pc: u32,
+    registers: Vec<(u8, u32)>,
+    pages: Vec<PageDelta>,
+}
+
+// Variable-length integers keep small register values, page indices and word
+// offsets to a byte or two.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().with_varint_encoding()
+}
+
+fn split_addr(addr: u32) -> (u32, u16) {
+    let page = addr / PAGE_SIZE as u32;
+    let word = (addr % PAGE_SIZE as u32) / WORD_SIZE as u32;
+    (page, word as u16)
+}
+
+impl SegmentWitness {
+    /// Computes the delta which turns `base` into `self`.
+    pub fn delta_from(&self, base: &SegmentWitness) -> WitnessDelta {
+        let registers = (0..REGISTER_COUNT)
+            .filter(|&i| self.registers[i] != base.registers[i])
+            .map(|i| (i as u8, self.registers[i]))
+            .collect();
+
+        // Walk both images in address order, collecting changed words.
+        let mut changes: BTreeMap<u32, Vec<(u16, Option<u32>)>> = BTreeMap::new();
+        let mut record = |addr: u32, value: Option<u32>| {
+            let (page, word) = split_addr(addr);
+            changes.entry(page).or_default().push((word, value));
+        };
+        let mut old = base.memory.iter().peekable();
+        let mut new = self.memory.iter().peekable();
+        loop {
+            match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(&(&addr, _)), None) => {
+                    record(addr, None);
+                    old.next();
+                }
+                (None, Some(&(&addr, &value))) => {
+                    record(addr, Some(value));
+                    new.next();
+                }
+                (Some(&(&old_addr, &old_value)), Some(&(&new_addr, &new_value))) => {
+                    match old_addr.cmp(&new_addr) {
+                        Ordering::Less => {
+                            record(old_addr, None);
+                            old.next();
+                        }
+                        Ordering::Greater => {
+                            record(new_addr, Some(new_value));
+                            new.next();
+                        }
+                        Ordering::Equal => {
+                            if old_value != new_value {
+                                record(new_addr, Some(new_value));
+                            }
+                            old.next();
+                            new.next();
+                        }
+                    }
+                }
+            }
+        }
+
+        WitnessDelta {
+            pc: self.pc,
+            registers,
+            pages: changes
+                .into_iter()
+                .map(|(page, words)| PageDelta { page, words })
+                .collect(),
+        }
+    }
+}
+
+impl WitnessDelta {
+    /// Applies this delta to `base`, the witness it was computed against.
+    pub fn apply(&self, base: &SegmentWitness) -> Result<SegmentWitness> {
+        let mut witness = base.clone();
+        witness.pc = self.pc;
+        for &(reg, value) in &self.registers {
+            if reg as usize >= REGISTER_COUNT {
+                bail!("Witness delta sets register x{reg}");
+            }
+            witness.registers[reg as usize] = value;
+        }
+        for page in &self.pages {
+            for &(word, value) in &page.words {
+                if word as usize >= PAGE_SIZE / WORD_SIZE {
+                    bail!("Witness delta word {word} is outside its page");
+                }
+                let addr = page.page * PAGE_SIZE as u32 + word as u32 * WORD_SIZE as u32;
+                match value {
+                    Some(value) => witness.memory.insert(addr, value),
+                    None => witness.memory.remove(&addr),
+                };
+            }
+        }
+        Ok(witness)
+    }
+
+    /// The number of pages this delta changes.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Encodes this delta compactly.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(options().serialize(self)?)
+    }
+
+    /// Decodes a delta encoded by [WitnessDelta::encode].
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(options().deserialize(bytes)?)
+    }
+}
+
+/// A first-in, first-out queue of witnesses, each stored encoded against
+/// the witness pushed before it.
+///
+/// Only two witnesses are held in full: the one the front of the queue is
+/// relative to, and the most recently pushed one, which the next push is
+/// encoded against.
+pub struct WitnessQueue {
+    front_base: SegmentWitness,
+    back: SegmentWitness,
+    pending: VecDeque<Vec<u8>>,
+}
+
+impl WitnessQueue {
+    /// Construct an empty queue whose first witness will be encoded against
+    /// `base`, e.g. the program's initial state.
+    pub fn new(base: SegmentWitness) -> Self {
+        WitnessQueue {
+            front_base: base.clone(),
+            back: base,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Adds `witness` to the back of the queue.
+    pub fn push(&mut self, witness: SegmentWitness) -> Result<()> {
+        self.pending
+            .push_back(witness.delta_from(&self.back).encode()?);
+        self.back = witness;
+        Ok(())
+    }
+
+    /// Removes and returns the witness at the front of the queue.
+    pub fn pop(&mut self) -> Result<Option<SegmentWitness>> {
+        let bytes = match self.pending.pop_front() {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let witness = WitnessDelta::decode(&bytes)?.apply(&self.front_base)?;
+        self.front_base = witness.clone();
+        Ok(Some(witness))
+    }
+
+    /// The number of witnesses in the queue.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns true if the queue holds no witnesses.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// The total size of the encoded deltas in the queue, in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.pending.iter().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{SegmentWitness, WitnessDelta, WitnessQueue, REGISTER_COUNT};
+    use crate::platform::memory::PAGE_SIZE;
+
+    fn witness(words: usize) -> SegmentWitness {
+        SegmentWitness {
+            pc: 0x1000,
+            registers: [0; REGISTER_COUNT],
+            memory: (0..words as u32).map(|i| (i * 4, i)).collect(),
+        }
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let base = witness(4 * PAGE_SIZE);
+        let mut next = base.clone();
+        next.pc = 0x1004;
+        next.registers[10] = 7;
+        next.memory.insert(8, 99);
+        next.memory.insert(PAGE_SIZE as u32 * 3 + 4, 5);
+        next.memory.remove(&(PAGE_SIZE as u32 * 2));
+        next.memory.insert(PAGE_SIZE as u32 * 100, 1);
+
+        let delta = next.delta_from(&base);
+        assert_eq!(delta.page_count(), 4);
+        let bytes = delta.encode().unwrap();
+        assert!(bytes.len() < 64, "{} byte delta", bytes.len());
+        let decoded = WitnessDelta::decode(&bytes).unwrap();
+        assert_eq!(decoded, delta);
+        assert_eq!(decoded.apply(&base).unwrap(), next);
+        assert_eq!(base.delta_from(&base).page_count(), 0);
+    }
+
+    #[test]
+    fn queue() {
+        let base = witness(16 * PAGE_SIZE);
+        let mut queue = WitnessQueue::new(base.clone());
+        let mut expected = Vec::new();
+        let mut cur = base;
+        for i in 0..100u32 {
+            cur.pc += 4;
+            cur.registers[(i % 31 + 1) as usize] = i;
+            cur.memory.insert(i * 4, i + 1000);
+            queue.push(cur.clone()).unwrap();
+            expected.push(cur.clone());
+        }
+        assert_eq!(queue.len(), 100);
+        assert!(queue.encoded_len() < 100 * 32);
+
+        for witness in expected {
+            assert_eq!(queue.pop().unwrap(), Some(witness));
+        }
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop().unwrap(), None);
+    }
+
+    #[test]
+    fn reject_bad_delta() {
+        let base = SegmentWitness {
+            pc: 0,
+            registers: [0; REGISTER_COUNT],
+            memory: BTreeMap::new(),
+        };
+        let mut next = base.clone();
+        next.registers[1] = 1;
+        let mut delta = next.delta_from(&base);
+        delta.registers[0].0 = REGISTER_COUNT as u8;
+        assert!(delta.apply(&base).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A Merkleized key-value store that the host serves to a guest.
+//!
+//! The host holds the whole store as a [KvTree] and answers each lookup with
+//! a [KvProof]. The guest checks every answer against the root [Digest] of
+//! the store, which it must trust independently, e.g. by committing it to the
+//! journal. Entries are sorted by key, so a missing key is proven by the
+//! entries on either side of it.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::fmt;
+
+use risc0_zkp::core::sha::{Digest, Sha, DIGEST_WORDS};
+use serde::{Deserialize, Serialize};
+
+/// An entry of a [KvTree] along with its Merkle path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KvLeaf {
+    /// The key of this entry.
+    pub key: Vec<u8>,
+
+    /// The value of this entry.
+    pub value: Vec<u8>,
+
+    /// The position of this entry in key order.
+    pub index: u32,
+
+    /// The sibling digests from the leaf up to the root of the tree.
+    pub path: Vec<Digest>,
+}
+
+/// The host's answer to a lookup in a [KvTree].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KvProof {
+    /// The number of entries in the store.
+    pub len: u32,
+
+    /// The entry for the key if present, otherwise the entry with the largest
+    /// smaller key, if any.
+    pub lower: Option<KvLeaf>,
+
+    /// The entry with the smallest larger key, if the key is absent.
+    pub upper: Option<KvLeaf>,
+}
+
+/// Returned by [KvProof::verify] when a proof does not match the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidKvProof;
+
+impl fmt::Display for InvalidKvProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Invalid key-value proof")
+    }
+}
+
+fn depth(len: u32) -> usize {
+    (len.max(1) as usize).next_power_of_two().trailing_zeros() as usize
+}
+
+fn leaf_digest<S: Sha>(sha: &S, key: &[u8], value: &[u8]) -> Digest {
+    let mut buf = Vec::with_capacity(4 + key.len() + value.len());
+    buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    buf.extend_from_slice(key);
+    buf.extend_from_slice(value);
+    *sha.hash_bytes(&buf)
+}
+
+// Binds the number of entries into the root, so that proofs at the edges of
+// the store can't omit entries.
+fn store_root<S: Sha>(sha: &S, tree_root: &Digest, len: u32) -> Digest {
+    let mut words = [0; DIGEST_WORDS];
+    words[0] = len;
+    *sha.hash_pair(tree_root, &Digest::new(words))
+}
+
+impl KvLeaf {
+    fn root<S: Sha>(&self, sha: &S, len: u32) -> Result<Digest, InvalidKvProof> {
+        if self.index >= len || self.path.len() != depth(len) {
+            return Err(InvalidKvProof);
+        }
+        let mut node = leaf_digest(sha, &self.key, &self.value);
+        let mut index = self.index;
+        for sibling in self.path.iter() {
+            node = if index & 1 == 0 {
+                *sha.hash_pair(&node, sibling)
+            } else {
+                *sha.hash_pair(sibling, &node)
+            };
+            index >>= 1;
+        }
+        Ok(store_root(sha, &node, len))
+    }
+}
+
+impl KvProof {
+    /// Check this proof of a lookup of `key` against the `root` of the store,
+    /// returning the value stored under `key`, if any.
+    pub fn verify<S: Sha>(
+        &self,
+        sha: &S,
+        root: &Digest,
+        key: &[u8],
+    ) -> Result<Option<&[u8]>, InvalidKvProof> {
+        for leaf in self.lower.iter().chain(self.upper.iter()) {
+            if leaf.root(sha, self.len)? != *root {
+                return Err(InvalidKvProof);
+            }
+        }
+        match (&self.lower, &self.upper) {
+            (Some(lower), None) if lower.key == key => return Ok(Some(&lower.value)),
+            (Some(lower), Some(upper)) => {
+                if lower.key.as_slice() >= key
+                    || upper.key.as_slice() <= key
+                    || upper.index != lower.index + 1
+                {
+                    return Err(InvalidKvProof);
+                }
+            }
+            (Some(lower), None) => {
+                if lower.key.as_slice() >= key || lower.index + 1 != self.len {
+                    return Err(InvalidKvProof);
+                }
+            }
+            (None, Some(upper)) => {
+                if upper.key.as_slice() <= key || upper.index != 0 {
+                    return Err(InvalidKvProof);
+                }
+            }
+            (None, None) => {
+                if self.len != 0 || store_root(sha, &Digest::default(), 0) != *root {
+                    return Err(InvalidKvProof);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A key-value store along with the Merkle tree over its entries.
+pub struct KvTree {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    // layers[0] holds the (padded) leaves, the last layer holds the root.
+    layers: Vec<Vec<Digest>>,
+    root: Digest,
+}
+
+impl KvTree {
+    /// Build the Merkle tree over the entries of `store`.
+    pub fn new<S: Sha>(sha: &S, store: BTreeMap<Vec<u8>, Vec<u8>>) -> Self {
+        let entries: Vec<_> = store.into_iter().collect();
+        let len = entries.len() as u32;
+        let mut leaves: Vec<Digest> = entries
+            .iter()
+            .map(|(key, value)| leaf_digest(sha, key, value))
+            .collect();
+        leaves.resize(1 << depth(len), Digest::default());
+        let mut layers = Vec::from([leaves]);
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| *sha.hash_pair(&pair[0], &pair[1]))
+                .collect();
+            layers.push(next);
+        }
+        let root = store_root(sha, &layers.last().unwrap()[0], len);
+        KvTree {
+            entries,
+            layers,
+            root,
+        }
+    }
+
+    /// The root digest which guests check lookups against.
+    pub fn root(&self) -> Digest {
+        self.root
+    }
+
+    fn leaf(&self, index: usize) -> KvLeaf {
+        let (key, value) = &self.entries[index];
+        let path = self.layers[..self.layers.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, layer)| layer[(index >> level) ^ 1])
+            .collect();
+        KvLeaf {
+            key: key.clone(),
+            value: value.clone(),
+            index: index as u32,
+            path,
+        }
+    }
+
+    /// Produce a proof of the lookup of `key`.
+    pub fn prove(&self, key: &[u8]) -> KvProof {
+        let len = self.entries.len() as u32;
+        match self
+            .entries
+            .binary_search_by(|(k, _)| k.as_slice().cmp(key))
+        {
+            Ok(index) => KvProof {
+                len,
+                lower: Some(self.leaf(index)),
+                upper: None,
+            },
+            Err(index) => KvProof {
+                len,
+                lower: index.checked_sub(1).map(|index| self.leaf(index)),
+                upper: (index < self.entries.len()).then(|| self.leaf(index)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{collections::BTreeMap, vec::Vec};
+
+    use risc0_zkp::core::sha::default_implementation;
+
+    use super::{InvalidKvProof, KvTree};
+
+    fn tree_of(keys: &[&[u8]]) -> KvTree {
+        let store: BTreeMap<Vec<u8>, Vec<u8>> = keys
+            .iter()
+            .map(|key| (key.to_vec(), [key, &b"-value"[..]].concat()))
+            .collect();
+        KvTree::new(default_implementation(), store)
+    }
+
+    #[test]
+    fn lookups() {
+        let sha = default_implementation();
+        let key_sets: [&[&[u8]]; 4] = [
+            &[],
+            &[b"b"],
+            &[b"b", b"d", b"f"],
+            &[b"b", b"d", b"f", b"h", b"j"],
+        ];
+        for keys in key_sets {
+            let tree = tree_of(keys);
+            let root = tree.root();
+            for key in [&b"a"[..], b"b", b"c", b"d", b"f", b"g", b"j", b"k"] {
+                let expected = keys.contains(&key).then(|| [key, &b"-value"[..]].concat());
+                let proof = tree.prove(key);
+                let actual = proof.verify(sha, &root, key).unwrap();
+                assert_eq!(actual.map(|value| value.to_vec()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tampering() {
+        let sha = default_implementation();
+        let tree = tree_of(&[b"b", b"d", b"f"]);
+        let root = tree.root();
+
+        let mut proof = tree.prove(b"d");
+        proof.lower.as_mut().unwrap().value = b"forged".to_vec();
+        assert_eq!(proof.verify(sha, &root, b"d"), Err(InvalidKvProof));
+
+        // Claim "d" is absent by presenting non-adjacent neighbors.
+        let mut proof = tree.prove(b"c");
+        proof.upper = tree.prove(b"f").lower;
+        assert_eq!(proof.verify(sha, &root, b"d"), Err(InvalidKvProof));
+
+        // Claim "f" is absent by dropping the upper edge.
+        let mut proof = tree.prove(b"e");
+        proof.upper = None;
+        assert_eq!(proof.verify(sha, &root, b"f"), Err(InvalidKvProof));
+
+        // A proof for a different store doesn't verify.
+        let other = tree_of(&[b"b", b"d"]);
+        assert_eq!(
+            other.prove(b"d").verify(sha, &root, b"d"),
+            Err(InvalidKvProof)
+        );
+    }
+}
@@ -36,10 +36,13 @@
 extern crate alloc;
 
 pub mod core;
+pub mod disclose;
 #[cfg(feature = "prove")]
 pub mod elf;
 #[cfg(feature = "host")]
 pub mod host;
+pub mod kv;
+pub mod method;
 #[cfg(feature = "host")]
 pub mod method_id;
 #[cfg(feature = "prove")]
@@ -47,8 +50,12 @@ pub mod prove;
 #[cfg(feature = "host")]
 pub mod receipt;
 pub mod serde;
+pub mod shared;
+pub mod smt;
+pub mod table;
 #[cfg(feature = "verify")]
 pub mod verify;
+pub mod versioned;
 
 pub use risc0_zkvm_platform as platform;
 
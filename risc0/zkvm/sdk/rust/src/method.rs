@@ -0,0 +1,49 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compile-time descriptions of guest methods.
+//!
+//! risc0-build generates a unit struct implementing [Method] for every guest
+//! method it embeds, and additionally implements [TypedMethod] when the
+//! method's input and output types are declared in its
+//! `GuestOptions::method_types`.
+
+use serde::{Deserialize, Serialize};
+
+/// A guest method embedded by risc0-build.
+pub trait Method {
+    /// The name of the method, i.e. the name of its guest binary.
+    const NAME: &'static str;
+
+    /// The path to the method's ELF binary.
+    const PATH: &'static str;
+
+    /// The contents of the method's ELF binary.
+    const ELF: &'static [u8];
+
+    /// The serialized MethodId of the method.
+    const ID: &'static [u8];
+}
+
+/// A [Method] whose input and output types are known.
+///
+/// `Input` is what the guest reads with `env::read`, and `Output` is what it
+/// commits to the journal.
+pub trait TypedMethod: Method {
+    /// The type the guest reads as its input.
+    type Input: Serialize;
+
+    /// The type the guest commits to its journal.
+    type Output: for<'de> Deserialize<'de>;
+}
@@ -15,7 +15,7 @@
 extern crate alloc;
 
 use alloc::vec::Vec;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use risc0_zkp::{
     core::{
         fp::Fp,
@@ -25,6 +25,9 @@ use risc0_zkp::{
     MAX_CYCLES, MIN_CYCLES, ZK_CYCLES,
 };
 
+#[cfg(feature = "prove")]
+use crate::host::policy::HashSuite;
+
 /// The default digest count when generating a MethodId.
 pub const DEFAULT_METHOD_ID_LIMIT: u32 = 12;
 
@@ -36,10 +39,23 @@ pub struct MethodId {
 }
 
 impl MethodId {
+    // Method IDs are exchanged as little-endian words, which can only be
+    // borrowed in place on little-endian hosts.
+    #[deprecated(note = "Use MethodId::to_bytes, which works on any host")]
     pub fn as_slice(&self) -> Result<&[u8]> {
+        if cfg!(target_endian = "big") {
+            bail!("MethodId::as_slice is not supported on big-endian hosts");
+        }
         Ok(bytemuck::cast_slice(self.table.as_slice()))
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.table
+            .iter()
+            .flat_map(|digest| crate::serde::words_to_bytes(digest.as_slice()))
+            .collect()
+    }
+
     pub fn from_slice(bytes: &[u8]) -> Result<Self> {
         let mut table = Vec::new();
         for digest in bytes.chunks_exact(DIGEST_WORDS * DIGEST_WORD_SIZE) {
@@ -65,13 +81,41 @@ impl MethodId {
 
     #[cfg(feature = "prove")]
     pub fn compute_with_limit(elf_contents: &[u8], limit: u32) -> Result<Self> {
+        MethodId::compute_with_sha(elf_contents, limit, risc0_zkp::core::sha_cpu::Impl {})
+    }
+
+    /// Compute the method ID that receipts of a guest proven with `suite` are
+    /// verified against, whose code roots are committed with that suite.
+    #[cfg(feature = "prove")]
+    pub fn compute_for_suite(elf_contents: &[u8], suite: HashSuite) -> Result<Self> {
+        use risc0_zkp::core::{blake3_cpu, keccak_cpu, poseidon};
+
+        let limit = DEFAULT_METHOD_ID_LIMIT;
+        match suite {
+            HashSuite::Sha256 => MethodId::compute(elf_contents),
+            HashSuite::Keccak256 => {
+                Self::compute_with_sha(elf_contents, limit, keccak_cpu::Impl {})
+            }
+            HashSuite::Poseidon => Self::compute_with_sha(elf_contents, limit, poseidon::Impl {}),
+            HashSuite::Blake3 => Self::compute_with_sha(elf_contents, limit, blake3_cpu::Impl {}),
+        }
+    }
+
+    /// Compute the method ID of a guest proven with a hash suite other than
+    /// SHA-256, whose code roots are committed with that suite's [Sha].
+    #[cfg(feature = "prove")]
+    pub fn compute_with_sha<S: risc0_zkp::core::sha::Sha + Sync>(
+        elf_contents: &[u8],
+        limit: u32,
+        sha: S,
+    ) -> Result<Self> {
         use crate::{elf::Program, platform::memory::MEM_SIZE, CODE_SIZE};
         use risc0_zkp::{
             hal::{cpu::CpuHal, Hal},
             prove::poly_group::PolyGroup,
         };
 
-        let hal = CpuHal {};
+        let hal = CpuHal::with_sha(sha);
         let program = Program::load_elf(elf_contents, MEM_SIZE as u32)?;
 
         // Start with an empty table
@@ -122,3 +166,21 @@ fn load_code(code: &mut [Fp], elf: &crate::elf::Program, cycles: usize) -> Resul
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkp::core::sha::Digest;
+
+    use super::MethodId;
+
+    #[test]
+    fn byte_order() {
+        let id = MethodId {
+            table: vec![Digest::new([0x0403_0201, 2, 3, 4, 5, 6, 7, 0x2019_1817])],
+        };
+        let bytes = id.to_bytes();
+        assert_eq!(bytes[..5], [1, 2, 3, 4, 2]);
+        assert_eq!(bytes[28..], [0x17, 0x18, 0x19, 0x20]);
+        assert!(MethodId::from_slice(&bytes).unwrap() == id);
+    }
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use core::{
-    cmp::Ordering,
+    cmp::{max, Ordering},
     ops::{Index, IndexMut},
 };
 use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
@@ -24,7 +24,11 @@ use log::{debug, trace};
 use risc0_zkp::core::sha::Sha;
 use risc0_zkp::{
     adapter::{CircuitDef, CustomStep},
-    core::{fp::Fp, log2_ceil, sha::DIGEST_WORDS},
+    core::{
+        fp::Fp,
+        log2_ceil,
+        sha::{Digest, DIGEST_WORDS},
+    },
     field::Elem,
     prove::executor::Executor,
     MAX_CYCLES_PO2, ZK_CYCLES,
@@ -36,18 +40,28 @@ use risc0_zkvm_platform::{
             GPIO_COMMIT, GPIO_FAULT, GPIO_GETKEY, GPIO_SENDRECV_ADDR, GPIO_SENDRECV_CHANNEL,
             GPIO_SENDRECV_SIZE, GPIO_SHA,
         },
-        IoDescriptor, SHADescriptor,
+        IoDescriptor, SHADescriptor, SENDRECV_CHANNEL_CONTINUE, SENDRECV_CHANNEL_SECRET,
+        SENDRECV_CHUNK_LAST, SENDRECV_CHUNK_SIZE, SENDRECV_SPLIT,
     },
-    memory::INPUT,
+    memory::{HEAP, INPUT},
     WORD_SIZE,
 };
 
-use crate::{elf::Program, platform::memory::MEM_BITS, CODE_SIZE};
+use crate::{
+    elf::Program,
+    host::{
+        backtrace::{self, Backtrace},
+        secret::wipe,
+        ExecutionError, PageInfo,
+    },
+    platform::memory::{MEM_BITS, PAGE_SIZE},
+    CODE_SIZE,
+};
 
 pub trait IoHandler {
     fn on_commit(&mut self, buf: &[u32]);
-    fn on_fault(&mut self, msg: &str);
-    fn on_txrx(&mut self, channel: u32, buf: &[u8]) -> Vec<u8>;
+    fn on_fault(&mut self, msg: &str) -> Result<()>;
+    fn on_txrx(&mut self, channel: u32, buf: &[u8]) -> Result<Vec<u8>>;
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -72,6 +86,10 @@ impl std::fmt::Debug for MemoryEvent {
 struct MemoryState {
     pub memory: BTreeMap<u32, u32>,
     pub history: BTreeSet<MemoryEvent>,
+    // Read and write counts by page index for accesses made by the guest
+    // itself, i.e. at or after `track_from`, the first cycle past the image.
+    pub pages: BTreeMap<u32, (u32, u32)>,
+    pub track_from: u32,
 }
 
 /// Align the given address `addr` upwards to alignment `align`.
@@ -81,52 +99,53 @@ const fn align_up(addr: usize, align: usize) -> usize {
     (addr + align - 1) & !(align - 1)
 }
 
+// Memory accesses on behalf of the guest can be steered to any address, so
+// they report an error rather than panicking.
+type MemResult<T> = core::result::Result<T, ExecutionError>;
+
+// Returns the end of the region [start, start + size), failing if it wraps.
+fn region_end(start: u32, size: u32) -> MemResult<u32> {
+    start
+        .checked_add(size)
+        .ok_or(ExecutionError::AddressOutOfRange(start))
+}
+
 impl MemoryState {
-    #[track_caller]
-    fn load_u8(&self, addr: u32) -> u8 {
+    fn load_u8(&self, addr: u32) -> MemResult<u8> {
         // debug!("load_u8: 0x{addr:08X}");
         // align to the nearest word
         let aligned = addr & !(WORD_SIZE as u32 - 1);
         let offset = addr % WORD_SIZE as u32;
-        let word = self.load_u32(aligned);
-        ((word >> (offset * 8)) & 0xff) as u8
+        let word = self.load_u32(aligned)?;
+        Ok(((word >> (offset * 8)) & 0xff) as u8)
     }
 
-    #[track_caller]
-    fn load_u32(&self, addr: u32) -> u32 {
+    fn load_u32(&self, addr: u32) -> MemResult<u32> {
         // debug!("load_u32: 0x{addr:08X}");
-        assert_eq!(addr % WORD_SIZE as u32, 0, "unaligned load");
+        if addr % WORD_SIZE as u32 != 0 {
+            return Err(ExecutionError::UnalignedAccess(addr));
+        }
         let key = addr / 4;
         match self.memory.get(&key) {
-            Some(word) => *word,
-            None => panic!("addr out of range: 0x{addr:08X}"),
+            Some(word) => Ok(*word),
+            None => Err(ExecutionError::AddressOutOfRange(addr)),
         }
     }
 
-    #[track_caller]
-    fn load_be_u32(&self, addr: u32) -> u32 {
-        self.load_u32(addr).to_be()
-    }
-
-    #[track_caller]
-    fn load_region_u32(&self, start: u32, size: u32) -> Vec<u32> {
-        (start..start + size)
+    fn load_region_u32(&self, start: u32, size: u32) -> MemResult<Vec<u32>> {
+        (start..region_end(start, size)?)
             .step_by(WORD_SIZE)
             .map(|addr| self.load_u32(addr))
             .collect()
     }
 
-    #[track_caller]
-    fn load_region(&self, addr: u32, size: u32) -> Vec<u8> {
-        let mut region = Vec::new();
-        for addr in addr..addr + size {
-            region.push(self.load_u8(addr));
-        }
-        region
+    fn load_region(&self, addr: u32, size: u32) -> MemResult<Vec<u8>> {
+        (addr..region_end(addr, size)?)
+            .map(|addr| self.load_u8(addr))
+            .collect()
     }
 
-    #[track_caller]
-    fn store_u8(&mut self, addr: u32, value: u8) {
+    fn store_u8(&mut self, addr: u32, value: u8) -> MemResult<()> {
         // debug!("store_u8: 0x{addr:08X} <= 0x{value:08X}");
         // align to the nearest word
         let aligned = addr & !(WORD_SIZE as u32 - 1);
@@ -134,13 +153,14 @@ impl MemoryState {
         let key = aligned / 4;
         let mut word = self.memory.get(&key).unwrap_or(&0) & !(0xff << (offset * 8));
         word |= (value as u32) << (offset * 8);
-        self.store_u32(aligned, word);
+        self.store_u32(aligned, word)
     }
 
-    #[track_caller]
-    fn store_u32(&mut self, addr: u32, value: u32) {
+    fn store_u32(&mut self, addr: u32, value: u32) -> MemResult<()> {
         // debug!("store_u32: 0x{addr:08X} <= 0x{value:08X}");
-        assert_eq!(addr % WORD_SIZE as u32, 0, "unaligned store");
+        if addr % WORD_SIZE as u32 != 0 {
+            return Err(ExecutionError::UnalignedAccess(addr));
+        }
         let key = addr / 4;
         match self.memory.entry(key) {
             Entry::Occupied(mut entry) => {
@@ -162,7 +182,7 @@ impl MemoryState {
                         debug!("addr: 0x{key:08X}, value: 0x{value:08X}, txn: {txn:?}");
                         // The guest has actually touched this memory, and we are not writing the
                         // same value
-                        panic!("Host cannot mutate existing memory");
+                        return Err(ExecutionError::HostMutatedMemory(addr));
                     }
                 }
                 entry.insert(value);
@@ -171,32 +191,40 @@ impl MemoryState {
                 entry.insert(value);
             }
         }
+        Ok(())
     }
 
-    #[track_caller]
-    fn store_region(&mut self, addr: u32, slice: &[u8]) {
+    fn store_region(&mut self, addr: u32, slice: &[u8]) -> MemResult<()> {
         // debug!("store_region: 0x{addr:08X} <= {} bytes", slice.len());
+        region_end(addr, slice.len() as u32)?;
         for i in 0..slice.len() {
-            self.store_u8(addr + i as u32, slice[i]);
+            self.store_u8(addr + i as u32, slice[i])?;
         }
+        Ok(())
     }
 
-    #[track_caller]
-    fn store_region_u32(&mut self, addr: u32, slice: &[u32]) {
-        assert!(addr % WORD_SIZE as u32 == 0);
+    fn store_region_u32(&mut self, addr: u32, slice: &[u32]) -> MemResult<()> {
+        region_end(addr, (WORD_SIZE * slice.len()) as u32)?;
         for (offset, word) in slice.iter().enumerate() {
-            self.store_u32(addr + WORD_SIZE as u32 * offset as u32, *word);
+            self.store_u32(addr + WORD_SIZE as u32 * offset as u32, *word)?;
         }
+        Ok(())
     }
 
-    fn strlen(&self, addr: u32) -> usize {
-        let mut addr = addr;
-        let mut len = 0;
-        while self.load_u8(addr) != 0 {
-            addr += 1;
-            len += 1;
+    // Loads the NUL-terminated string at `addr`.
+    fn load_str(&self, addr: u32) -> MemResult<String> {
+        let mut buf = Vec::new();
+        let mut cur = addr;
+        loop {
+            match self.load_u8(cur)? {
+                0 => break,
+                byte => buf.push(byte),
+            }
+            cur = cur
+                .checked_add(1)
+                .ok_or(ExecutionError::AddressOutOfRange(addr))?;
         }
-        len
+        String::from_utf8(buf).map_err(|_| ExecutionError::InvalidUtf8)
     }
 
     fn pop_history(&mut self) -> MemoryEvent {
@@ -215,6 +243,23 @@ pub struct MachineContext<'a, H: IoHandler> {
     memory: MemoryState,
     io: &'a mut H,
     cur_host_to_guest_offset: usize,
+    split: Option<SplitResponse>,
+}
+
+// A response too large for the INPUT region, held back until the guest
+// fetches it chunk by chunk over SENDRECV_CHANNEL_CONTINUE.
+struct SplitResponse {
+    data: Vec<u8>,
+    offset: usize,
+    digest: Digest,
+}
+
+// A split response may hold a secret input, so it is wiped once the guest
+// has all of it.
+impl Drop for SplitResponse {
+    fn drop(&mut self) {
+        wipe(&mut self.data);
+    }
 }
 
 impl PartialOrd for MemoryEvent {
@@ -268,8 +313,43 @@ impl MemoryState {
         Self {
             memory: BTreeMap::new(),
             history: BTreeSet::new(),
+            pages: BTreeMap::new(),
+            track_from: 0,
+        }
+    }
+
+    fn touch(&mut self, cycle: u32, addr: u32, is_write: bool) {
+        if cycle < self.track_from {
+            return;
+        }
+        let counts = self.pages.entry(addr * 4 / PAGE_SIZE as u32).or_default();
+        if is_write {
+            counts.1 += 1;
+        } else {
+            counts.0 += 1;
         }
     }
+
+    fn touched_pages(&self) -> Vec<PageInfo> {
+        const PAGE_WORDS: u32 = (PAGE_SIZE / WORD_SIZE) as u32;
+        let sha = risc0_zkp::core::sha::default_implementation();
+        self.pages
+            .iter()
+            .map(|(&page, &(reads, writes))| {
+                let base = page * PAGE_WORDS;
+                let mut words = vec![0u32; PAGE_WORDS as usize];
+                for (&addr, &word) in self.memory.range(base..base + PAGE_WORDS) {
+                    words[(addr - base) as usize] = word;
+                }
+                PageInfo {
+                    addr: page * PAGE_SIZE as u32,
+                    reads,
+                    writes,
+                    digest: *sha.hash_bytes(&crate::serde::words_to_bytes(&words)),
+                }
+            })
+            .collect()
+    }
 }
 
 impl MemoryState {}
@@ -290,6 +370,7 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
             memory: MemoryState::new(),
             io,
             cur_host_to_guest_offset: INPUT.start(),
+            split: None,
         }
     }
 
@@ -354,6 +435,7 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
         let addr: u32 = addr.into();
         // debug!("[{}] R: 0x{:08X}", cycle, addr);
         let data = *self.memory.memory.entry(addr).or_insert(0);
+        self.memory.touch(cycle, addr, false);
         // debug!("data: 0x{data:08X}");
         self.memory.history.insert(MemoryEvent {
             cycle,
@@ -376,6 +458,7 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
             data,
             is_write,
         });
+        self.memory.touch(cycle, addr, true);
         match self.memory.memory.entry(addr) {
             Entry::Occupied(mut entry) => {
                 if *entry.get() != data && !is_write {
@@ -392,11 +475,10 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
                 entry.insert(data);
             }
         };
-        self.on_write(cycle, addr * 4, data);
-        Ok(())
+        self.on_write(cycle, addr * 4, data)
     }
 
-    fn on_write(&mut self, cycle: u32, addr: u32, value: u32) {
+    fn on_write(&mut self, cycle: u32, addr: u32, value: u32) -> Result<()> {
         use risc0_zkvm_platform::io::addr::GPIO_LOG;
 
         // debug!("on_write: 0x{:08X}: 0x{:08X}", addr, value);
@@ -406,7 +488,7 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
                 const SZ: usize = core::mem::size_of::<IoDescriptor>();
                 let descbuf: [u32; SZ / WORD_SIZE] = self
                     .memory
-                    .load_region_u32(value, SZ as u32)
+                    .load_region_u32(value, SZ as u32)?
                     .as_slice()
                     .try_into()
                     .unwrap();
@@ -418,64 +500,124 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
                     desc.addr, desc.size
                 );
 
-                let buf = self.memory.load_region_u32(desc.addr, desc.size);
+                let buf = self.memory.load_region_u32(desc.addr, desc.size)?;
                 debug!("Data: {:08X?}", &buf);
                 self.io.on_commit(buf.as_slice());
             }
             GPIO_FAULT => {
                 debug!("on_write> GPIO_FAULT");
-                let len = self.memory.strlen(value);
-                let buf = self.memory.load_region(value, len as u32);
-                let str = String::from_utf8(buf).unwrap();
-                self.io.on_fault(&str);
+                let str = self.memory.load_str(value)?;
+                self.io.on_fault(&str)?;
             }
             GPIO_GETKEY => {
                 debug!("on_write> GPIO_GETKEY");
-                todo!()
+                return Err(ExecutionError::Unsupported("GPIO_GETKEY").into());
             }
             GPIO_LOG => {
                 debug!("on_write> GPIO_LOG");
-                let len = self.memory.strlen(value);
-                let buf = self.memory.load_region(value, len as u32);
-                let str = String::from_utf8(buf).unwrap();
+                let str = self.memory.load_str(value)?;
                 debug!("R0VM[C{cycle}> {}", str);
             }
             GPIO_SENDRECV_ADDR => {
                 debug!("on_write> GPIO_SENDRECV_ADDR");
-                let channel = self.memory.load_u32(GPIO_SENDRECV_CHANNEL);
-                let size = self.memory.load_u32(GPIO_SENDRECV_SIZE);
-                let region = self.memory.load_region(value, size);
-                let result = self.io.on_txrx(channel, &region);
+                let channel = self.memory.load_u32(GPIO_SENDRECV_CHANNEL)?;
+                let size = self.memory.load_u32(GPIO_SENDRECV_SIZE)?;
+                let region = self.memory.load_region(value, size)?;
+                if channel == SENDRECV_CHANNEL_CONTINUE {
+                    self.send_chunk(&region)?;
+                    return Ok(());
+                }
+                let mut result = self.io.on_txrx(channel, &region)?;
                 let aligned_len = align_up(result.len(), WORD_SIZE);
-                assert!(
-                    self.cur_host_to_guest_offset + WORD_SIZE + aligned_len < INPUT.end(),
-                    "Read buffer overrun"
-                );
+                if self.cur_host_to_guest_offset + WORD_SIZE >= INPUT.end() {
+                    return Err(ExecutionError::ReadBufferOverrun.into());
+                }
+                if self.cur_host_to_guest_offset + WORD_SIZE + aligned_len >= INPUT.end() {
+                    if self.split.is_some() {
+                        return Err(ExecutionError::SplitPending.into());
+                    }
+                    if result.len() >= SENDRECV_SPLIT as usize {
+                        return Err(ExecutionError::ResponseTooLarge(result.len()).into());
+                    }
+                    debug!("on_write> splitting {} byte response", result.len());
+                    self.memory.store_u32(
+                        self.cur_host_to_guest_offset as u32,
+                        SENDRECV_SPLIT | result.len() as u32,
+                    )?;
+                    self.cur_host_to_guest_offset += WORD_SIZE;
+                    let sha = risc0_zkp::core::sha::default_implementation();
+                    self.split = Some(SplitResponse {
+                        digest: *sha.hash_bytes(&result),
+                        data: result,
+                        offset: 0,
+                    });
+                    return Ok(());
+                }
                 self.memory
-                    .store_u32(self.cur_host_to_guest_offset as u32, result.len() as u32);
+                    .store_u32(self.cur_host_to_guest_offset as u32, result.len() as u32)?;
                 self.cur_host_to_guest_offset += WORD_SIZE;
                 self.memory
-                    .store_region(self.cur_host_to_guest_offset as u32, &result);
+                    .store_region(self.cur_host_to_guest_offset as u32, &result)?;
                 self.cur_host_to_guest_offset += aligned_len;
+                if channel == SENDRECV_CHANNEL_SECRET {
+                    wipe(&mut result);
+                }
             }
             GPIO_SHA => {
                 debug!("on_write> GPIO_SHA, descriptor ptr = {value:08X}");
                 const SZ: usize = core::mem::size_of::<SHADescriptor>();
                 let descbuf: [u32; SZ / WORD_SIZE] = self
                     .memory
-                    .load_region_u32(value, SZ as u32)
+                    .load_region_u32(value, SZ as u32)?
                     .try_into()
                     .unwrap();
                 // SAFETY: SHADescriptor is a plain-old-data type with
                 // repr(C) and no pointers so it's safe to fill it from bytes.
                 let desc: SHADescriptor = unsafe { std::mem::transmute(descbuf) };
-                self.process_sha(&desc);
+                self.process_sha(&desc)?;
             }
             _ => {}
         };
+        Ok(())
     }
 
-    fn process_sha(&mut self, desc: &SHADescriptor) {
+    // Writes the next chunk of a split response to the header and data
+    // addresses the guest sent on SENDRECV_CHANNEL_CONTINUE.
+    fn send_chunk(&mut self, request: &[u8]) -> MemResult<()> {
+        let split = self.split.as_mut().ok_or(ExecutionError::BadContinuation)?;
+        let request: [u8; 2 * WORD_SIZE] = request
+            .try_into()
+            .map_err(|_| ExecutionError::BadContinuation)?;
+        let header = u32::from_le_bytes(request[..WORD_SIZE].try_into().unwrap());
+        let dest = u32::from_le_bytes(request[WORD_SIZE..].try_into().unwrap());
+        let len = SENDRECV_CHUNK_SIZE.min(split.data.len() - split.offset);
+        let last = split.offset + len == split.data.len();
+        let header_len = WORD_SIZE * if last { 1 + DIGEST_WORDS } else { 1 };
+        for (addr, len) in [(header, header_len), (dest, len)] {
+            if addr as usize % WORD_SIZE != 0
+                || !HEAP.contains(addr as usize)
+                || addr as usize + len > HEAP.end()
+            {
+                return Err(ExecutionError::BadContinuationAddress(addr));
+            }
+        }
+        let chunk = &split.data[split.offset..split.offset + len];
+        self.memory.store_region(dest, chunk)?;
+        split.offset += len;
+        if last {
+            let digest = split.digest;
+            self.memory
+                .store_u32(header, SENDRECV_CHUNK_LAST | len as u32)?;
+            self.memory
+                .store_region_u32(header + WORD_SIZE as u32, digest.as_slice())?;
+            self.split = None;
+        } else {
+            self.memory.store_u32(header, len as u32)?;
+        }
+        Ok(())
+    }
+
+    fn process_sha(&mut self, desc: &SHADescriptor) -> MemResult<()> {
         let sha_type: u16 = ((desc.type_count & 0xFFFF) >> 4) as u16;
         let count: u16 = (desc.type_count & 0xFFFF) as u16;
         debug!(
@@ -486,13 +628,13 @@ impl<'a, H: IoHandler> MachineContext<'a, H> {
         let sha = risc0_zkp::core::sha::default_implementation();
         let words = self
             .memory
-            .load_region_u32(desc.source as u32, (count * 64) as u32);
+            .load_region_u32(desc.source as u32, count as u32 * 64)?;
         let digest = sha.hash_raw_words(bytemuck::cast_slice(words.as_slice()));
 
         debug!("Digest result is {:X?}", digest.as_slice());
 
         self.memory
-            .store_region_u32(desc.digest as u32, digest.as_slice());
+            .store_region_u32(desc.digest as u32, digest.as_slice())
     }
 }
 
@@ -699,34 +841,90 @@ where
     Ok(())
 }
 
+/// Limits on the resources one segment may use; see
+/// [ProverOpts::with_max_segment_cycles](crate::host::ProverOpts::with_max_segment_cycles).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SegmentLimits {
+    pub max_cycles: Option<u64>,
+    pub max_memory: Option<u64>,
+}
+
+impl SegmentLimits {
+    fn check(&self, cycles: usize, memory: &MemoryState) -> MemResult<()> {
+        if let Some(max_cycles) = self.max_cycles {
+            if cycles as u64 > max_cycles {
+                return Err(ExecutionError::SegmentCycleLimit(max_cycles));
+            }
+        }
+        if let Some(max_memory) = self.max_memory {
+            if (memory.pages.len() * PAGE_SIZE) as u64 > max_memory {
+                return Err(ExecutionError::SegmentMemoryLimit(max_memory));
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct RV32Executor<'a, H: IoHandler> {
     elf: &'a Program,
     pub executor: Executor<CircuitImpl, MachineContext<'a, H>>,
+    pub limits: SegmentLimits,
 }
 
 impl<'a, H: IoHandler> RV32Executor<'a, H> {
     pub fn new(elf: &'a Program, io: &'a mut H) -> Self {
+        Self::new_with_po2(elf, io, 0, MAX_CYCLES_PO2)
+    }
+
+    // The trace starts at the smallest po2 which fits the image (but at least
+    // min_po2), and expands up to max_po2 as needed.
+    pub fn new_with_po2(elf: &'a Program, io: &'a mut H, min_po2: usize, max_po2: usize) -> Self {
         debug!("image.size(): {}", elf.image.len());
         let circuit = CircuitImpl::new();
-        let machine = MachineContext::new(io);
-        let min_po2 = log2_ceil(elf.image.len() + 3 + ZK_CYCLES);
-        let executor = Executor::new(circuit, machine, min_po2, MAX_CYCLES_PO2);
-        Self { elf, executor }
+        let mut machine = MachineContext::new(io);
+        // Cycles before this one initialize memory from the image.
+        machine.memory.track_from = elf.image.len() as u32 + 1;
+        let min_po2 = max(log2_ceil(elf.image.len() + 3 + ZK_CYCLES), min_po2);
+        let executor = Executor::new(circuit, machine, min_po2, max_po2);
+        Self {
+            elf,
+            executor,
+            limits: SegmentLimits::default(),
+        }
     }
 
     pub fn run(&mut self) -> Result<()> {
+        let limits = self.limits;
         load_code(self.elf.entry, &self.elf.image, |chunk, fini| {
-            self.executor.step(chunk, fini)
+            let keep_going = self.executor.step(chunk, fini)?;
+            limits.check(self.executor.cycle, &self.executor.custom.memory)?;
+            Ok(keep_going)
         })?;
         self.executor.finalize();
         Ok(())
     }
 
+    pub fn touched_pages(&self) -> Vec<PageInfo> {
+        self.executor.custom.memory.touched_pages()
+    }
+
+    /// Walk the guest's frame pointers from the last executed cycle.  The pc
+    /// is two-bit decomposed into the first 16 data columns, and the
+    /// registers follow as (low, high) pairs from column 30.
+    pub fn backtrace(&self) -> Backtrace {
+        let cycle = self.executor.cycle.saturating_sub(1);
+        let data = |offset| self.executor.get_data(cycle, offset);
+        let pc = (0..16).fold(0, |pc, i| {
+            let digit: u32 = data(i).into();
+            pc | (digit & 3) << (2 * i)
+        });
+        let fp = merge_word((data(30 + 2 * 8), data(30 + 2 * 8 + 1)));
+        backtrace::walk(pc, fp, |addr| {
+            self.executor.custom.memory.load_u32(addr).ok()
+        })
+    }
+
     pub fn run_without_seal(&mut self) -> Result<()> {
-        load_code(self.elf.entry, &self.elf.image, |chunk, fini| {
-            self.executor.step(chunk, fini)
-        })?;
-        self.executor.finalize();
-        Ok(())
+        self.run()
     }
 }
@@ -14,16 +14,37 @@
 
 pub mod exec;
 
-use std::io::Write;
+use std::{
+    io::Write,
+    time::{Instant, SystemTime},
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+#[cfg(feature = "metal")]
+use risc0_zkp::hal::metal::MetalHal;
 use risc0_zkp::{
-    core::sha::default_implementation, hal::cpu::CpuHal, prove::adapter::ProveAdapter,
+    core::{
+        blake3_cpu, hash, keccak_cpu, poseidon,
+        sha::{default_implementation, Digest, Sha, DIGEST_WORDS},
+    },
+    hal::{
+        backend::{HalBackend, HalBackendError, HAL_BACKEND_ENV},
+        cpu::CpuHal,
+        Hal,
+    },
+    prove::{adapter::ProveAdapter, Circuit},
+    MAX_CYCLES_PO2,
 };
 
 use crate::{
     elf::Program,
-    host::ProverOpts,
+    host::{
+        audit::{elf_sha256, AuditRecord},
+        deadline::Run,
+        policy::HashSuite,
+        AuditManifest, Backtrace, Checkpoint, ExecutionError, PageInfo, ProverOpts, ProverStats,
+        SessionEvent,
+    },
     method_id::MethodId,
     platform::{
         io::{SENDRECV_CHANNEL_INITIAL_INPUT, SENDRECV_CHANNEL_STDERR, SENDRECV_CHANNEL_STDOUT},
@@ -32,12 +53,18 @@ use crate::{
     receipt::Receipt,
 };
 
-use self::exec::{IoHandler, RV32Executor};
+use self::exec::{IoHandler, RV32Executor, SegmentLimits};
 
 pub struct Prover<'a> {
     elf: Program,
     inner: ProverImpl<'a>,
     method_id: MethodId,
+    stats: ProverStats,
+    pages: Vec<PageInfo>,
+    checkpoint: Option<Checkpoint>,
+    elf_sha256: Digest,
+    audit: Option<AuditManifest>,
+    backtrace: Option<Backtrace>,
 }
 
 impl<'a> Prover<'a> {
@@ -46,10 +73,26 @@ impl<'a> Prover<'a> {
     }
 
     pub fn new_with_opts(elf: &[u8], method_id: &[u8], opts: ProverOpts<'a>) -> Result<Self> {
+        let elf_sha256 = match opts.audit {
+            Some(_) => elf_sha256(elf),
+            None => Digest::default(),
+        };
+        // Receipts are verified against the code roots committed with their
+        // own hash suite, which `method_id` only holds for SHA-256.
+        let method_id = match opts.hash_suite {
+            HashSuite::Sha256 => MethodId::from_slice(method_id)?,
+            suite => MethodId::compute_for_suite(elf, suite)?,
+        };
         Ok(Prover {
             elf: Program::load_elf(&elf, MEM_SIZE as u32)?,
             inner: ProverImpl::new(opts),
-            method_id: MethodId::from_slice(method_id).unwrap(),
+            method_id,
+            stats: ProverStats::default(),
+            pages: Vec::new(),
+            checkpoint: None,
+            elf_sha256,
+            audit: None,
+            backtrace: None,
         })
     }
 
@@ -60,29 +103,127 @@ impl<'a> Prover<'a> {
     pub fn add_input_u32_slice(&mut self, slice: &[u32]) {
         self.inner
             .input
-            .extend_from_slice(bytemuck::cast_slice(slice));
+            .extend_from_slice(&crate::serde::words_to_bytes(slice));
     }
 
     pub fn get_output(&self) -> Result<&[u8]> {
         Ok(&self.inner.output)
     }
 
+    pub fn get_stats(&self) -> Result<ProverStats> {
+        Ok(self.stats)
+    }
+
+    pub fn touched_pages(&self) -> Result<Vec<PageInfo>> {
+        Ok(self.pages.clone())
+    }
+
+    pub fn page_faults(&self) -> Result<usize> {
+        Ok(self.pages.len())
+    }
+
+    pub fn take_checkpoint(&mut self) -> Option<Checkpoint> {
+        self.checkpoint.take()
+    }
+
+    pub fn take_audit_manifest(&mut self) -> Option<AuditManifest> {
+        self.audit.take()
+    }
+
+    pub fn backtrace(&self) -> Result<Option<Backtrace>> {
+        Ok(self.backtrace.clone())
+    }
+
     pub fn run(&mut self) -> Result<Receipt> {
         let skip_seal = self.inner.opts.skip_seal;
+        let zk = self.inner.opts.zk;
+        let min_po2 = self.inner.opts.min_po2.unwrap_or(0);
+        let max_po2 = self.inner.opts.max_po2.unwrap_or(MAX_CYCLES_PO2);
+        if min_po2 > max_po2 || max_po2 > MAX_CYCLES_PO2 {
+            bail!("Invalid po2 range: {min_po2}..={max_po2}");
+        }
+        let hash_suite = self.inner.opts.hash_suite;
+        // Reject a misconfigured RISC0_HAL before executing.
+        let backend = hal_backend(self.inner.opts.backend)?;
+        let chosen =
+            self.inner.opts.backend.is_some() || std::env::var_os(HAL_BACKEND_ENV).is_some();
+        if chosen && !hashes_with(backend, hash_suite) {
+            bail!(
+                "The {} backend can't hash with {hash_suite:?}; choose the cpu backend",
+                backend.name()
+            );
+        }
 
-        let mut executor = RV32Executor::new(&self.elf, &mut self.inner);
-        executor.run()?;
+        if let Some(threads) = self.inner.opts.threads {
+            if !risc0_zkp::parallel::init_thread_pool(threads) {
+                log::debug!("Thread pool already built; ignoring thread limit");
+            }
+        }
+
+        let started = (SystemTime::now(), Instant::now());
+        self.checkpoint = None;
+        self.audit = None;
+        self.backtrace = None;
+        self.inner.run = Run::start(&self.inner.opts);
+        self.inner
+            .opts
+            .emit(SessionEvent::SegmentStarted { index: 0 });
+        let deadline = self.inner.run.deadline;
+        let limits = SegmentLimits {
+            max_cycles: self.inner.opts.max_segment_cycles,
+            max_memory: self.inner.opts.max_segment_memory,
+        };
+        let mut executor = RV32Executor::new_with_po2(&self.elf, &mut self.inner, min_po2, max_po2);
+        executor.executor.zk = zk;
+        executor.limits = limits;
+        // Stop before proving if the deadline passed during execution.
+        let result = executor.run().and_then(|()| {
+            if deadline.expired() {
+                bail!(ExecutionError::DeadlineExceeded)
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            self.backtrace = Some(executor.backtrace());
+            drop(executor);
+            self.inner.opts.emit(SessionEvent::Fault {
+                msg: err.to_string(),
+            });
+            if let Some(ExecutionError::DeadlineExceeded) = err.downcast_ref() {
+                let run = std::mem::take(&mut self.inner.run);
+                self.checkpoint = Some(run.into_checkpoint());
+            }
+            return Err(err);
+        }
+        self.stats = ProverStats {
+            po2: executor.executor.po2,
+        };
+        self.pages = executor.touched_pages();
 
         let mut prover = ProveAdapter::new(&mut executor.executor);
-        let hal = CpuHal {};
-        let sha = default_implementation();
-
-        let seal = if skip_seal {
-            risc0_zkp::prove::prove_without_seal(&hal, sha, &mut prover);
-            Vec::new()
-        } else {
-            risc0_zkp::prove::prove(&hal, sha, &mut prover)
+        // Suites which the backend can't hash with prove on the CPU, unless
+        // the backend was chosen, which failed above.
+        let seal = match hash_suite {
+            HashSuite::Sha256 => {
+                // The CPU unless a GPU backend is built in and present, or
+                // another was chosen; see HalBackend::from_env.
+                let hal = backend.hal();
+                prove_seal(&hal, default_implementation(), &mut prover, skip_seal)
+            }
+            #[cfg(feature = "metal")]
+            HashSuite::Poseidon if backend == HalBackend::Metal => {
+                let hal = MetalHal::with_poseidon();
+                prove_seal(&hal, &poseidon::Impl {}, &mut prover, skip_seal)
+            }
+            HashSuite::Poseidon => prove_seal_cpu(poseidon::Impl {}, &mut prover, skip_seal),
+            HashSuite::Keccak256 => prove_seal_cpu(keccak_cpu::Impl {}, &mut prover, skip_seal),
+            HashSuite::Blake3 => prove_seal_cpu(blake3_cpu::Impl {}, &mut prover, skip_seal),
         };
+        // With the zeroize feature these zeroize the trace when dropped, and
+        // so hold their borrow of the prover until then.
+        drop(prover);
+        drop(executor);
+        self.inner.opts.emit_pages(&self.pages);
 
         // Attach the full version of the output journal & construct receipt object
         let receipt = Receipt {
@@ -91,19 +232,93 @@ impl<'a> Prover<'a> {
         };
 
         if !skip_seal {
+            if cfg!(debug_assertions) {
+                // The journal is padded to whole words; the seal has its
+                // length in bytes.
+                let regs = receipt.output_regs()?;
+                let journal = receipt.get_journal()?;
+                let len = (regs[DIGEST_WORDS] as usize).min(journal.len());
+                crate::host::journal::check_commitment(&journal[..len], &regs, hash_suite)?;
+            }
             // Verify receipt to make sure it works
             receipt.verify(&self.method_id)?;
         }
 
+        if let Some(signer) = self.inner.opts.audit {
+            let record = AuditRecord {
+                elf_sha256: self.elf_sha256,
+                method_id: &self.method_id.to_bytes(),
+                input: &self.inner.input,
+                transcript: &self.inner.run.transcript,
+                journal: &receipt.get_journal()?,
+                seal: &receipt.seal,
+                po2: self.stats.po2,
+                started: started.0,
+                elapsed: started.1.elapsed(),
+            };
+            self.audit = Some(record.sign(&self.inner.opts, signer));
+        }
+
+        self.inner.opts.emit(SessionEvent::SegmentFinished {
+            index: 0,
+            po2: self.stats.po2,
+        });
         Ok(receipt)
     }
 }
 
+// The backend named by RISC0_HAL, which takes precedence over the one chosen
+// in the options, which in turn takes precedence over the fastest available.
+// Fails if the backend can't run here.
+fn hal_backend(chosen: Option<HalBackend>) -> Result<HalBackend, HalBackendError> {
+    match chosen {
+        Some(backend) if std::env::var_os(HAL_BACKEND_ENV).is_none() => {
+            if !backend.is_available() {
+                return Err(HalBackendError::Unavailable(backend));
+            }
+            Ok(backend)
+        }
+        _ => HalBackend::from_env(),
+    }
+}
+
+// Whether `backend` can hash with `suite`: the CPU hashes with every suite,
+// Metal with SHA-256 and Poseidon, and CUDA with SHA-256 only.
+fn hashes_with(backend: HalBackend, suite: HashSuite) -> bool {
+    #[cfg(feature = "metal")]
+    {
+        if backend == HalBackend::Metal && suite == HashSuite::Poseidon {
+            return true;
+        }
+    }
+    backend == HalBackend::Cpu || suite == HashSuite::Sha256
+}
+
+fn prove_seal<H: Hal, C: Circuit>(
+    hal: &H,
+    suite: &dyn hash::HashSuite,
+    circuit: &mut C,
+    skip_seal: bool,
+) -> Vec<u32> {
+    if skip_seal {
+        risc0_zkp::prove::prove_without_seal(hal, suite, circuit);
+        Vec::new()
+    } else {
+        risc0_zkp::prove::prove(hal, suite, circuit)
+    }
+}
+
+fn prove_seal_cpu<S: Sha + Sync, C: Circuit>(sha: S, circuit: &mut C, skip_seal: bool) -> Vec<u32> {
+    let hal = CpuHal::with_sha(sha.clone());
+    prove_seal(&hal, &sha, circuit, skip_seal)
+}
+
 struct ProverImpl<'a> {
     pub input: Vec<u8>,
     pub output: Vec<u8>,
     pub commit: Vec<u32>,
     pub opts: ProverOpts<'a>,
+    pub run: Run,
 }
 
 impl<'a> ProverImpl<'a> {
@@ -113,31 +328,36 @@ impl<'a> ProverImpl<'a> {
             output: Vec::new(),
             commit: Vec::new(),
             opts,
+            run: Run::default(),
         }
     }
 }
 
 impl<'a> IoHandler for ProverImpl<'a> {
-    fn on_txrx(&mut self, channel: u32, buf: &[u8]) -> Vec<u8> {
-        if let Some(cb) = self.opts.sendrecv_callbacks.get(&channel) {
-            return cb(channel, buf);
+    fn on_txrx(&mut self, channel: u32, buf: &[u8]) -> Result<Vec<u8>> {
+        if self.run.deadline.expired() {
+            return Err(ExecutionError::DeadlineExceeded.into());
+        }
+        if let Some(response) = self.run.respond(&self.opts, channel, buf) {
+            return Ok(response);
         }
         match channel {
             SENDRECV_CHANNEL_INITIAL_INPUT => {
                 log::debug!("SENDRECV_CHANNEL_INITIAL_INPUT: {}", buf.len());
-                self.input.clone()
+                Ok(self.input.clone())
             }
             SENDRECV_CHANNEL_STDOUT => {
                 log::debug!("SENDRECV_CHANNEL_STDOUT: {}", buf.len());
                 self.output.extend(buf);
-                Vec::new()
+                self.opts.write_stdout(buf)?;
+                Ok(Vec::new())
             }
             SENDRECV_CHANNEL_STDERR => {
                 log::debug!("SENDRECV_CHANNEL_STDERR: {}", buf.len());
-                std::io::stderr().lock().write_all(buf).unwrap();
-                Vec::new()
+                std::io::stderr().lock().write_all(buf)?;
+                Ok(Vec::new())
             }
-            _ => panic!("Unknown channel: {channel}"),
+            _ => Err(ExecutionError::UnknownChannel(channel).into()),
         }
     }
 
@@ -145,7 +365,7 @@ impl<'a> IoHandler for ProverImpl<'a> {
         self.commit.extend_from_slice(buf);
     }
 
-    fn on_fault(&mut self, msg: &str) {
-        panic!("{}", msg);
+    fn on_fault(&mut self, msg: &str) -> Result<()> {
+        Err(ExecutionError::GuestFault(msg.to_string()).into())
     }
 }
@@ -12,14 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use risc0_zkp::core::sha::default_implementation;
-use risc0_zkp::verify::adapter::VerifyAdapter;
+use risc0_zkp::core::{
+    blake3_cpu,
+    hash::{self, SUITE_ID_MASK, SUITE_ID_SHIFT},
+    keccak_cpu, log2_ceil, poseidon,
+    sha::{default_implementation, Digest, DIGEST_WORDS},
+};
+use risc0_zkp::{
+    verify::{adapter::VerifyAdapter, SealClaim, VerificationError},
+    MIN_CYCLES, NON_ZK_FLAG,
+};
 use risc0_zkvm_circuit::CircuitImpl;
 
-use crate::method_id::MethodId;
+use crate::{
+    host::{
+        journal::expected_commitment,
+        policy::{HashSuite, VerifierPolicy},
+        ReceiptError,
+    },
+    method_id::MethodId,
+    platform::io::assert_failure,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct Receipt {
@@ -29,21 +45,155 @@ pub struct Receipt {
 
 // FIXME: Remove this temporary trait to convert method IDs once our API is the
 // same between FFI and rust-based provers.
-pub trait IntoMethodId {}
-impl IntoMethodId for &MethodId {}
-impl IntoMethodId for &[u8] {}
+pub trait IntoMethodId {
+    fn into_method_id(self) -> Result<MethodId>;
+}
+
+impl IntoMethodId for &MethodId {
+    fn into_method_id(self) -> Result<MethodId> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoMethodId for &[u8] {
+    fn into_method_id(self) -> Result<MethodId> {
+        MethodId::from_slice(self)
+    }
+}
 
 impl Receipt {
+    // Compatible API with FFI-based prover.
+    pub fn new(journal: &[u8], seal: &[u32]) -> Result<Self> {
+        Ok(Receipt {
+            journal: crate::serde::bytes_to_words(journal),
+            seal: seal.to_vec(),
+        })
+    }
+
+    // Checks that the seal proves a run of the code in `method_id`, which
+    // must have its code roots committed with the receipt's hash suite, and
+    // that it commits to the journal.
+    #[cfg(feature = "verify")]
+    pub fn verify<M>(&self, method_id: M) -> Result<()>
+    where
+        M: IntoMethodId,
+    {
+        let suite = self.hash_suite()?;
+        let regs = self.verify_regs(method_id)?;
+        let journal = crate::serde::words_to_bytes(&self.journal);
+        let len = regs[DIGEST_WORDS] as usize;
+        // The journal is padded with zeroes to a whole number of words.
+        if len > journal.len()
+            || journal.len() - len >= 4
+            || journal[len..].iter().any(|&byte| byte != 0)
+        {
+            return Err(ReceiptError::JournalMismatch.into());
+        }
+        if regs[..DIGEST_WORDS] != *expected_commitment(&journal[..len], suite).as_slice() {
+            return Err(ReceiptError::JournalMismatch.into());
+        }
+        Ok(())
+    }
+
+    // Verifies the seal against `method_id`, returning the output registers
+    // it proves.
     #[cfg(feature = "verify")]
-    pub fn verify<M>(&self, _method_id: M) -> Result<()>
+    fn verify_regs<M>(&self, method_id: M) -> Result<Vec<u32>>
     where
         M: IntoMethodId,
     {
+        let method_id = method_id.into_method_id()?;
+        let circuit = CircuitImpl::new();
+        let suite = suite_impl(self.hash_suite()?);
+        let mut regs = Vec::new();
+        risc0_zkp::verify::verify_seal(suite, &circuit, &self.seal, |claim| {
+            check_code_root(claim, &method_id)?;
+            regs = join_halves(claim.outputs.iter().map(|&half| u32::from(half)));
+            if regs.len() <= DIGEST_WORDS {
+                return Err(VerificationError::InvalidClaim);
+            }
+            Ok(())
+        })
+        .map_err(ReceiptError::from)?;
+        Ok(regs)
+    }
+
+    // Compatible API with FFI-based prover.
+    #[cfg(feature = "verify")]
+    pub fn paranoid_verify<M>(&self, method_id: M) -> Result<()>
+    where
+        M: IntoMethodId,
+    {
+        self.verify(method_id)?;
         let circuit = CircuitImpl::new();
         let mut verifier = VerifyAdapter::new(&circuit);
-        let sha = default_implementation();
-        risc0_zkp::verify::verify(sha, &mut verifier, &self.seal)
-            .map_err(|err| anyhow!("Verification failed: {:?}", err))
+        let suite = suite_impl(self.hash_suite()?);
+        risc0_zkp::verify::paranoid_verify(suite, &mut verifier, &self.seal)
+            .map_err(ReceiptError::from)?;
+        Ok(())
+    }
+
+    // Compatible API with FFI-based prover.
+    #[cfg(all(feature = "pure-prove", feature = "verify"))]
+    pub(crate) fn rust_seal(&self) -> Result<Vec<u32>> {
+        Ok(self.seal.clone())
+    }
+
+    // Compatible API with FFI-based prover.
+    #[cfg(feature = "verify")]
+    pub fn verify_with_policy<M>(&self, method_id: M, policy: &VerifierPolicy) -> Result<()>
+    where
+        M: IntoMethodId,
+    {
+        policy.check(self.journal.len() * 4)?;
+        policy.check_hash_suite(self.hash_suite()?)?;
+        policy.check_zk(self.is_zk()?)?;
+        self.verify(method_id)
+    }
+
+    // Compatible API with FFI-based prover.
+    pub fn is_zk(&self) -> Result<bool> {
+        match self.seal.get(Self::PO2_INDEX) {
+            Some(po2) => Ok(po2 & NON_ZK_FLAG == 0),
+            None => Err(ReceiptError::Malformed.into()),
+        }
+    }
+
+    // Compatible API with FFI-based prover.
+    pub fn hash_suite(&self) -> Result<HashSuite> {
+        self.seal
+            .get(Self::PO2_INDEX)
+            .and_then(|po2| HashSuite::from_id((po2 >> SUITE_ID_SHIFT) & SUITE_ID_MASK))
+            .ok_or_else(|| ReceiptError::Malformed.into())
+    }
+
+    // The position of the po2 word in the seal, after the circuit's outputs.
+    // Must match CircuitImpl::output_size.
+    pub(crate) const PO2_INDEX: usize = 18;
+
+    // Compatible API with FFI-based prover.
+    #[cfg(feature = "verify")]
+    pub fn verify_claim<M>(&self, method_id: M, journal_sha: Digest) -> Result<()>
+    where
+        M: IntoMethodId,
+    {
+        let regs = self.verify_regs(method_id)?;
+        // Journals of up to 32 bytes are committed directly rather than hashed.
+        if regs[DIGEST_WORDS] as usize <= DIGEST_WORDS * 4 {
+            return Err(ReceiptError::JournalTooShort.into());
+        }
+        if regs[..DIGEST_WORDS] != *journal_sha.as_slice() {
+            return Err(ReceiptError::JournalMismatch.into());
+        }
+        Ok(())
+    }
+
+    // The output registers committed in the seal, before it's verified.
+    pub(crate) fn output_regs(&self) -> Result<Vec<u32>> {
+        match self.seal.get(..Self::PO2_INDEX) {
+            Some(halves) => Ok(join_halves(halves.iter().copied())),
+            None => Err(ReceiptError::Malformed.into()),
+        }
     }
 
     // Compatible API with FFI-based prover.
@@ -52,8 +202,15 @@ impl Receipt {
     }
 
     // Compatible API with FFI-based prover.
-    pub fn get_journal(&self) -> Result<&[u8]> {
-        Ok(bytemuck::cast_slice(self.journal.as_slice()))
+    pub fn get_assert_failure(&self) -> Result<Option<u32>> {
+        Ok(assert_failure(&self.journal))
+    }
+
+    // Compatible API with FFI-based prover, except that the journal is
+    // copied out as the little-endian bytes the guest committed, so this
+    // works on any host.  Callers which borrow the result work with either.
+    pub fn get_journal(&self) -> Result<Vec<u8>> {
+        Ok(crate::serde::words_to_bytes(&self.journal))
     }
 
     // Compatible API with FFI-based prover.
@@ -62,3 +219,58 @@ impl Receipt {
         Ok(self.seal.as_slice())
     }
 }
+
+// Joins the 16-bit halves which Rust seals split each output register into.
+pub(crate) fn join_halves(halves: impl Iterator<Item = u32>) -> Vec<u32> {
+    let halves: Vec<u32> = halves.collect();
+    halves
+        .chunks_exact(2)
+        .map(|half| half[0] | half[1] << 16)
+        .collect()
+}
+
+// Checks that `claim` proves a run of the code whose root `method_id` holds
+// for the claimed po2.
+#[cfg(feature = "verify")]
+pub(crate) fn check_code_root(
+    claim: &SealClaim,
+    method_id: &MethodId,
+) -> Result<(), VerificationError> {
+    // The table starts at MIN_CYCLES, and has zeroes for the sizes the code
+    // doesn't fit in.
+    let code_root = (claim.po2 as usize)
+        .checked_sub(log2_ceil(MIN_CYCLES))
+        .and_then(|index| method_id.table.get(index));
+    match code_root {
+        Some(root) if *root != Digest::default() && root == claim.code_root => Ok(()),
+        _ => Err(VerificationError::MethodVerificationError),
+    }
+}
+
+// The implementation of a hash suite that seals are verified with.
+#[cfg(feature = "verify")]
+pub(crate) fn suite_impl(suite: HashSuite) -> &'static dyn hash::HashSuite {
+    match suite {
+        HashSuite::Sha256 => default_implementation(),
+        HashSuite::Keccak256 => &keccak_cpu::Impl {},
+        HashSuite::Poseidon => &poseidon::Impl {},
+        HashSuite::Blake3 => &blake3_cpu::Impl {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Receipt;
+
+    #[test]
+    fn journal_byte_order() {
+        // The same bytes on any host.
+        let receipt = Receipt {
+            journal: vec![0x0403_0201, 0x0605],
+            seal: Vec::new(),
+        };
+        assert_eq!(receipt.get_journal().unwrap(), [1, 2, 3, 4, 5, 6, 0, 0]);
+        let copy = Receipt::new(&[1, 2, 3, 4, 5, 6], &[]).unwrap();
+        assert_eq!(copy.journal, receipt.journal);
+    }
+}
@@ -157,11 +157,23 @@ impl<'de> Deserializer<'de> {
         }
     }
 
+    // Bytes are packed little-endian, so on little-endian targets they can be
+    // borrowed in place.
+    #[cfg(target_endian = "little")]
     fn try_take_n_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
         let len_words = align_up(len, 4) / 4;
         let words: &'de [u32] = self.try_take_n(len_words)?;
         Ok(&bytemuck::cast_slice(words)[..len])
     }
+
+    #[cfg(target_endian = "big")]
+    fn try_take_n_bytes(&mut self, len: usize) -> Result<alloc::vec::Vec<u8>> {
+        let len_words = align_up(len, 4) / 4;
+        let words: &'de [u32] = self.try_take_n(len_words)?;
+        let mut bytes = super::words_to_bytes(words);
+        bytes.truncate(len);
+        Ok(bytes)
+    }
 }
 
 impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
@@ -268,7 +280,7 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         if len_bytes > 4 {
             return Err(Error::DeserializeBadChar);
         }
-        let bytes: &'de [u8] = self.try_take_n_bytes(len_bytes)?;
+        let bytes = self.try_take_n_bytes(len_bytes)?;
         // we pass the character through string conversion because
         // this handles transforming the array of code units to a
         // codepoint. we can't use char::from_u32() because it expects
@@ -287,8 +299,17 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         let len_bytes = self.try_take_word()? as usize;
         let bytes = self.try_take_n_bytes(len_bytes)?;
-        let str = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
-        visitor.visit_borrowed_str(str)
+        #[cfg(target_endian = "little")]
+        {
+            let str = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
+            visitor.visit_borrowed_str(str)
+        }
+        #[cfg(target_endian = "big")]
+        {
+            let str =
+                alloc::string::String::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
+            visitor.visit_string(str)
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -304,7 +325,14 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         let len_bytes = self.try_take_word()? as usize;
         let bytes = self.try_take_n_bytes(len_bytes)?;
-        visitor.visit_borrowed_bytes(bytes)
+        #[cfg(target_endian = "little")]
+        {
+            visitor.visit_borrowed_bytes(bytes)
+        }
+        #[cfg(target_endian = "big")]
+        {
+            visitor.visit_byte_buf(bytes)
+        }
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
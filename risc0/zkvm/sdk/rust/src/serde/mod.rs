@@ -17,6 +17,28 @@ mod err;
 mod serializer;
 
 pub use deserializer::{from_slice, Deserializer};
+pub use err::{Error, Result};
+
+use alloc::vec::Vec;
+
+/// Convert serialized words into the little-endian byte stream seen by the
+/// guest, independently of the host's byte order.
+pub fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Convert a little-endian byte stream into words, zero-padding the last
+/// word if needed.
+pub fn bytes_to_words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .collect()
+}
 pub use serializer::{to_slice, to_vec, to_vec_with_capacity, AllocVec, Serializer, Slice};
 
 /// Align the given address `addr` upwards to alignment `align`.
@@ -28,9 +50,29 @@ fn align_up(addr: usize, align: usize) -> usize {
 
 #[cfg(test)]
 mod test {
-    use crate::serde::{from_slice, to_vec};
+    use crate::serde::{bytes_to_words, from_slice, to_vec, words_to_bytes};
     use std::collections::HashMap;
 
+    // The encoding must be identical on every host, whatever its byte order
+    // or word size.
+    #[test]
+    fn test_vectors() {
+        let input: (u8, u64, &str, char, Vec<u16>) = (7, 0x1_0000_0002, "abcde", 'é', vec![9]);
+        let words = to_vec(&input).unwrap();
+        assert_eq!(
+            words,
+            [7, 2, 1, 5, 0x6463_6261, 0x0000_0065, 2, 0x0000_a9c3, 1, 9]
+        );
+        assert_eq!(
+            words_to_bytes(&words[3..6]),
+            [5, 0, 0, 0, b'a', b'b', b'c', b'd', b'e', 0, 0, 0]
+        );
+        assert_eq!(bytes_to_words(&words_to_bytes(&words)), words);
+        assert_eq!(bytes_to_words(&[1, 2, 3, 4, 5]), [0x0403_0201, 5]);
+        let output: (u8, u64, String, char, Vec<u16>) = from_slice(&words).unwrap();
+        assert_eq!(output, (7, 0x1_0000_0002, "abcde".into(), 'é', vec![9]));
+    }
+
     #[test]
     fn test_vec_round_trip() {
         let input: Vec<u64> = vec![1, 2, 3];
@@ -421,9 +421,14 @@ impl<'a> StreamWriter for Slice<'a> {
             return Err(Error::SerializeBufferFull);
         }
 
+        // Pack bytes little-endian so that the encoding doesn't depend on the
+        // byte order of the host.
         let slice = &mut self.slice[self.idx..self.idx + len_words];
-        let bytes: &mut [u8] = bytemuck::cast_slice_mut(slice);
-        bytes[..len_bytes].copy_from_slice(data);
+        for (word, chunk) in slice.iter_mut().zip(data.chunks(WORD_SIZE)) {
+            let mut bytes = [0; WORD_SIZE];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            *word = u32::from_le_bytes(bytes);
+        }
 
         self.idx += len_words;
 
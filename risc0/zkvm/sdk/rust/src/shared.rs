@@ -0,0 +1,467 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Types shared between host and guest.
+//!
+//! A struct which the host and guest both compile and exchange, as input or
+//! through the journal, must serialize identically on both sides, but the
+//! two are built for different targets and often with different features.
+//! Declaring it with [risc0_io](crate::risc0_io) checks, when the struct
+//! is compiled, that every field has a type the word-oriented format of
+//! [crate::serde] handles the same way on every target, and derives an
+//! [IoType::SCHEMA_DIGEST] from the field names and types:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! risc0_zkvm::risc0_io! {
+//!     #[derive(Serialize, Deserialize)]
+//!     pub struct Order {
+//!         /// The buyer's account.
+//!         pub account: u64,
+//!         pub items: Vec<(u32, u32)>,
+//!         pub note: Option<String>,
+//!     }
+//! }
+//! ```
+//!
+//! Floats and `usize` are rejected, since the format doesn't support floats
+//! and `usize` has a different width on the host.  Fields take only doc
+//! comments: `#[cfg]` on a field is exactly how host and guest builds drift
+//! apart, and serde attributes such as `skip` or `flatten` either change the
+//! layout or aren't supported by the format.
+//!
+//! The digest is a compile-time constant, so host and guest can compare
+//! theirs at run time.  [to_vec] prefixes a value with its digest, and
+//! [from_slice], like risc0_zkvm_guest::env::read_shared, checks the prefix
+//! before decoding, so a mismatch is reported as such rather than as
+//! garbled data.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt;
+
+pub use risc0_zkp::core::sha::Digest;
+use risc0_zkp::core::sha::DIGEST_WORDS;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A type which serializes identically on host and guest.
+///
+/// Implement this for structs with the [risc0_io](crate::risc0_io) macro.
+pub trait IoType: Serialize + DeserializeOwned {
+    /// A digest of how values of the type are laid out, which differs
+    /// between types with different field names, field types or nesting.
+    const SCHEMA_DIGEST: Digest;
+}
+
+/// A struct declared with [risc0_io](crate::risc0_io).
+pub trait IoStruct: IoType {
+    /// The declaration of the struct's fields, for diagnosing a
+    /// [SchemaError::Mismatch].
+    const SCHEMA: &'static str;
+}
+
+macro_rules! impl_io_type {
+    ($($ty:ty),*) => {
+        $(impl IoType for $ty {
+            const SCHEMA_DIGEST: Digest = SchemaHasher::new(stringify!($ty)).finish();
+        })*
+    };
+}
+
+impl_io_type!(bool, char, u8, u16, u32, u64, i8, i16, i32, i64, String, ());
+
+impl IoType for Digest {
+    const SCHEMA_DIGEST: Digest = SchemaHasher::new("Digest").finish();
+}
+
+impl<T: IoType> IoType for Option<T> {
+    const SCHEMA_DIGEST: Digest = SchemaHasher::new("Option")
+        .digest(&T::SCHEMA_DIGEST)
+        .finish();
+}
+
+impl<T: IoType> IoType for Vec<T> {
+    const SCHEMA_DIGEST: Digest = SchemaHasher::new("Vec").digest(&T::SCHEMA_DIGEST).finish();
+}
+
+// A box serializes as its contents.
+impl<T: IoType> IoType for Box<T> {
+    const SCHEMA_DIGEST: Digest = T::SCHEMA_DIGEST;
+}
+
+impl<T: IoType, const N: usize> IoType for [T; N]
+where
+    [T; N]: Serialize + DeserializeOwned,
+{
+    const SCHEMA_DIGEST: Digest = SchemaHasher::new("array")
+        .u32(N as u32)
+        .digest(&T::SCHEMA_DIGEST)
+        .finish();
+}
+
+macro_rules! impl_io_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: IoType),+> IoType for ($($name,)+) {
+            const SCHEMA_DIGEST: Digest = SchemaHasher::new("tuple")
+                $(.digest(&$name::SCHEMA_DIGEST))+
+                .finish();
+        }
+    };
+}
+
+impl_io_tuple!(A);
+impl_io_tuple!(A, B);
+impl_io_tuple!(A, B, C);
+impl_io_tuple!(A, B, C, D);
+
+/// Declare a struct which host and guest can share; see [shared](self).
+///
+/// ```
+/// use risc0_zkvm::shared::{IoStruct, IoType};
+/// use serde::{Deserialize, Serialize};
+///
+/// risc0_zkvm::risc0_io! {
+///     #[derive(Serialize, Deserialize)]
+///     pub struct Point {
+///         pub x: i32,
+///         pub y: i32,
+///     }
+/// }
+///
+/// assert_eq!(Point::SCHEMA, "struct Point { x: i32, y: i32, }");
+/// ```
+#[macro_export]
+macro_rules! risc0_io {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[doc = $doc:expr])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[doc = $doc])* $field_vis $field: $ty),*
+        }
+
+        impl $crate::shared::IoType for $name {
+            const SCHEMA_DIGEST: $crate::shared::Digest =
+                $crate::shared::SchemaHasher::new("struct")
+                    .str(stringify!($name))
+                    $(
+                        .str(stringify!($field))
+                        .digest(&<$ty as $crate::shared::IoType>::SCHEMA_DIGEST)
+                    )*
+                    .finish();
+        }
+
+        impl $crate::shared::IoStruct for $name {
+            const SCHEMA: &'static str = concat!(
+                "struct ",
+                stringify!($name),
+                " {",
+                $(" ", stringify!($field), ": ", stringify!($ty), ",",)*
+                " }"
+            );
+        }
+    };
+}
+
+/// Returned when a value prefixed with its schema digest can't be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The data is too short to hold a digest.
+    Missing,
+    /// The data was written for a type with a different layout.
+    Mismatch {
+        /// The digest of the type being decoded.
+        expected: Digest,
+        /// The digest the data was written with.
+        found: Digest,
+    },
+    /// The data doesn't deserialize as the type.
+    Decode(crate::serde::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Missing => f.write_str("Data has no schema digest"),
+            SchemaError::Mismatch { expected, found } => {
+                write!(f, "Schema mismatch: expected {expected}, found {found}")
+            }
+            SchemaError::Decode(err) => write!(f, "Malformed data: {err}"),
+        }
+    }
+}
+
+/// Serialize `value` prefixed with its [IoType::SCHEMA_DIGEST].
+pub fn to_vec<T: IoType>(value: &T) -> crate::serde::Result<Vec<u32>> {
+    crate::serde::to_vec(&(T::SCHEMA_DIGEST, value))
+}
+
+/// Decode a value written by [to_vec], checking that it was written for a
+/// type with the same layout as `T`.
+pub fn from_slice<T: IoType>(words: &[u32]) -> Result<T, SchemaError> {
+    if words.len() < DIGEST_WORDS {
+        return Err(SchemaError::Missing);
+    }
+    let (digest, rest) = words.split_at(DIGEST_WORDS);
+    let found = Digest::new(digest.try_into().unwrap());
+    if found != T::SCHEMA_DIGEST {
+        return Err(SchemaError::Mismatch {
+            expected: T::SCHEMA_DIGEST,
+            found,
+        });
+    }
+    crate::serde::from_slice(rest).map_err(SchemaError::Decode)
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Computes [IoType::SCHEMA_DIGEST] at compile time: SHA-256 over a kind
+/// tag followed by the parts of the type, with strings prefixed by their
+/// length so that the encoding is unambiguous.
+pub struct SchemaHasher {
+    state: [u32; 8],
+    block: [u8; 64],
+    len: usize,
+}
+
+impl SchemaHasher {
+    /// Start a digest for a type of the given kind, such as `"struct"`.
+    pub const fn new(kind: &str) -> Self {
+        SchemaHasher {
+            state: IV,
+            block: [0; 64],
+            len: 0,
+        }
+        .str(kind)
+    }
+
+    /// Add a name.
+    pub const fn str(self, s: &str) -> Self {
+        self.u32(s.len() as u32).bytes(s.as_bytes())
+    }
+
+    /// Add a number, such as the length of an array.
+    pub const fn u32(self, x: u32) -> Self {
+        self.bytes(&x.to_be_bytes())
+    }
+
+    /// Add the digest of a component type.
+    pub const fn digest(self, digest: &Digest) -> Self {
+        let words = digest.get();
+        let mut out = self;
+        let mut i = 0;
+        while i < DIGEST_WORDS {
+            out = out.u32(words[i]);
+            i += 1;
+        }
+        out
+    }
+
+    /// Returns the digest.
+    pub const fn finish(self) -> Digest {
+        let bits = (self.len as u64) * 8;
+        let mut out = self.bytes(&[0x80]);
+        while out.len % 64 != 56 {
+            out = out.bytes(&[0]);
+        }
+        out = out.bytes(&bits.to_be_bytes());
+        Digest::new(out.state)
+    }
+
+    const fn bytes(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self.block[self.len % 64] = bytes[i];
+            self.len += 1;
+            if self.len % 64 == 0 {
+                self.state = compress(self.state, &self.block);
+            }
+            i += 1;
+        }
+        self
+    }
+}
+
+// The SHA-256 compression function.
+const fn compress(state: [u32; 8], block: &[u8; 64]) -> [u32; 8] {
+    let mut w = [0u32; 64];
+    let mut i = 0;
+    while i < 16 {
+        w[i] = u32::from_be_bytes([
+            block[4 * i],
+            block[4 * i + 1],
+            block[4 * i + 2],
+            block[4 * i + 3],
+        ]);
+        i += 1;
+    }
+    while i < 64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+        i += 1;
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    let mut i = 0;
+    while i < 64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        i += 1;
+    }
+    [
+        state[0].wrapping_add(a),
+        state[1].wrapping_add(b),
+        state[2].wrapping_add(c),
+        state[3].wrapping_add(d),
+        state[4].wrapping_add(e),
+        state[5].wrapping_add(f),
+        state[6].wrapping_add(g),
+        state[7].wrapping_add(h),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{from_slice, to_vec, IoStruct, IoType, SchemaError};
+
+    crate::risc0_io! {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Order {
+            /// The buyer's account.
+            account: u64,
+            items: Vec<(u32, u32)>,
+            note: Option<String>,
+        }
+    }
+
+    mod renamed {
+        use super::*;
+
+        crate::risc0_io! {
+            #[derive(Serialize, Deserialize)]
+            pub struct Order {
+                pub account: u64,
+                pub lines: Vec<(u32, u32)>,
+                pub note: Option<String>,
+            }
+        }
+    }
+
+    mod retyped {
+        use super::*;
+
+        crate::risc0_io! {
+            #[derive(Serialize, Deserialize)]
+            pub struct Order {
+                pub account: u32,
+                pub items: Vec<(u32, u32)>,
+                pub note: Option<String>,
+            }
+        }
+    }
+
+    #[test]
+    fn primitive_digest() {
+        // SHA-256 of the length-prefixed name.
+        assert_eq!(
+            u32::SCHEMA_DIGEST.to_string(),
+            "dea2dae5a49553c7254a61b3e3ca2b058caa1c80a4ef4c96dcd0bfa58b97cb59"
+        );
+    }
+
+    #[test]
+    fn digests_differ() {
+        assert_ne!(Order::SCHEMA_DIGEST, renamed::Order::SCHEMA_DIGEST);
+        assert_ne!(Order::SCHEMA_DIGEST, retyped::Order::SCHEMA_DIGEST);
+        assert_ne!(u32::SCHEMA_DIGEST, i32::SCHEMA_DIGEST);
+        assert_ne!(<[u8; 4]>::SCHEMA_DIGEST, <[u8; 5]>::SCHEMA_DIGEST);
+        assert_ne!(<(u8, u16)>::SCHEMA_DIGEST, <(u16, u8)>::SCHEMA_DIGEST);
+        assert_eq!(
+            Order::SCHEMA,
+            "struct Order { account: u64, items: Vec<(u32, u32)>, note: Option<String>, }"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let order = Order {
+            account: 1 << 40,
+            items: vec![(1, 2), (3, 4)],
+            note: Some("express".into()),
+        };
+        let words = to_vec(&order).unwrap();
+        assert_eq!(from_slice::<Order>(&words).unwrap(), order);
+    }
+
+    #[test]
+    fn reject() {
+        assert_eq!(from_slice::<Order>(&[1, 2]), Err(SchemaError::Missing));
+        let order = retyped::Order {
+            account: 7,
+            items: Vec::new(),
+            note: None,
+        };
+        let words = to_vec(&order).unwrap();
+        assert_eq!(
+            from_slice::<Order>(&words),
+            Err(SchemaError::Mismatch {
+                expected: Order::SCHEMA_DIGEST,
+                found: retyped::Order::SCHEMA_DIGEST,
+            })
+        );
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A sparse Merkle tree over a key-value state.
+//!
+//! Each key is placed at the leaf its SHA-256 selects among 2^256, bit by
+//! bit from the most significant bit of the digest's first word. Empty
+//! subtrees hash to the zero [Digest], and a subtree holding a single entry
+//! is replaced by that entry's leaf, so a tree of `n` entries is about
+//! `log2(n)` levels deep.
+//!
+//! The host holds the state as a [SparseMerkleTree] and answers each lookup
+//! with an [SmtProof]. The guest checks the proof against the root, which it
+//! must trust independently, e.g. by committing it to the journal, and can
+//! use the same proof to compute the root after updating the key. All
+//! hashing goes through [Sha], so guests pass risc0_zkvm_guest::sha::Impl to
+//! use the SHA accelerator.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, mem};
+
+use risc0_zkp::core::sha::{Digest, Sha, DIGEST_WORDS};
+use serde::{Deserialize, Serialize};
+
+/// The greatest depth of a leaf, one level per bit of a key's digest.
+pub const MAX_DEPTH: usize = DIGEST_WORDS * 32;
+
+// The second block of a leaf's hash, which tells leaves apart from branches.
+const LEAF_TAG: [u32; 16] = [0x6c656166, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// The entry at the bottom of an [SmtProof].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmtLeaf {
+    /// The digest of the entry's key, which is its path from the root.
+    pub path: Digest,
+
+    /// The digest of the entry's value.
+    pub value: Digest,
+}
+
+/// The host's answer to a lookup in a [SparseMerkleTree].
+///
+/// The proof descends along the path of the key until it reaches an empty
+/// subtree or a single leaf. If that leaf is the key's, the key is present;
+/// otherwise it is absent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmtProof {
+    /// The leaf at the bottom of the path, if the subtree there isn't empty.
+    pub leaf: Option<SmtLeaf>,
+
+    /// The sibling digests from the bottom of the path up to the root.
+    pub siblings: Vec<Digest>,
+}
+
+/// Returned when an [SmtProof] doesn't match the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidSmtProof;
+
+impl fmt::Display for InvalidSmtProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Invalid sparse Merkle proof")
+    }
+}
+
+/// The path of `key` from the root.
+pub fn key_path<S: Sha>(sha: &S, key: &[u8]) -> Digest {
+    *sha.hash_bytes(key)
+}
+
+/// The digest of `value`, as stored in a leaf.
+pub fn value_digest<S: Sha>(sha: &S, value: &[u8]) -> Digest {
+    *sha.hash_bytes(value)
+}
+
+fn bit(path: &Digest, depth: usize) -> usize {
+    (path.get()[depth / 32] >> (31 - depth % 32)) as usize & 1
+}
+
+fn leaf_digest<S: Sha>(sha: &S, path: &Digest, value: &Digest) -> Digest {
+    let mut words = [0; 32];
+    words[..8].copy_from_slice(path.get());
+    words[8..16].copy_from_slice(value.get());
+    words[16..].copy_from_slice(&LEAF_TAG);
+    *sha.hash_raw_words(&words)
+}
+
+// Hash `node`, at `depth` along `path`, with its sibling.
+fn branch<S: Sha>(sha: &S, path: &Digest, depth: usize, node: &Digest, sibling: &Digest) -> Digest {
+    if bit(path, depth - 1) == 0 {
+        *sha.hash_pair(node, sibling)
+    } else {
+        *sha.hash_pair(sibling, node)
+    }
+}
+
+impl SmtProof {
+    // The root with `node` at the bottom of this proof's path.
+    fn root<S: Sha>(&self, sha: &S, path: &Digest, mut node: Digest) -> Digest {
+        let depth = self.siblings.len();
+        for (i, sibling) in self.siblings.iter().enumerate() {
+            node = branch(sha, path, depth - i, &node, sibling);
+        }
+        node
+    }
+
+    fn check<S: Sha>(&self, sha: &S, root: &Digest, path: &Digest) -> Result<(), InvalidSmtProof> {
+        let depth = self.siblings.len();
+        if depth > MAX_DEPTH {
+            return Err(InvalidSmtProof);
+        }
+        let node = match &self.leaf {
+            Some(leaf) => {
+                if (0..depth).any(|d| bit(&leaf.path, d) != bit(path, d)) {
+                    return Err(InvalidSmtProof);
+                }
+                leaf_digest(sha, &leaf.path, &leaf.value)
+            }
+            None => Digest::default(),
+        };
+        if self.root(sha, path, node) != *root {
+            return Err(InvalidSmtProof);
+        }
+        Ok(())
+    }
+
+    /// Check this proof of a lookup of `key` against `root`, returning the
+    /// [value_digest] of the value stored under `key`, if any.
+    pub fn verify<S: Sha>(
+        &self,
+        sha: &S,
+        root: &Digest,
+        key: &[u8],
+    ) -> Result<Option<Digest>, InvalidSmtProof> {
+        let path = key_path(sha, key);
+        self.check(sha, root, &path)?;
+        Ok(self
+            .leaf
+            .as_ref()
+            .filter(|leaf| leaf.path == path)
+            .map(|leaf| leaf.value))
+    }
+
+    /// Check this proof of a lookup of `key` against `root`, returning the
+    /// root once `key` is set to `value`.
+    ///
+    /// A key can't be removed this way, since removing it may merge its
+    /// sibling into their parent, and the proof doesn't reveal whether the
+    /// sibling is a leaf.
+    pub fn update<S: Sha>(
+        &self,
+        sha: &S,
+        root: &Digest,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Digest, InvalidSmtProof> {
+        let path = key_path(sha, key);
+        self.check(sha, root, &path)?;
+        let depth = self.siblings.len();
+        let mut node = leaf_digest(sha, &path, &value_digest(sha, value));
+        if let Some(leaf) = self.leaf.as_ref().filter(|leaf| leaf.path != path) {
+            // The two leaves share a subtree down to the first bit their
+            // paths differ in.
+            let split = (depth..MAX_DEPTH)
+                .find(|&d| bit(&leaf.path, d) != bit(&path, d))
+                .unwrap();
+            let other = leaf_digest(sha, &leaf.path, &leaf.value);
+            node = branch(sha, &path, split + 1, &node, &other);
+            for d in (depth + 1..=split).rev() {
+                node = branch(sha, &path, d, &node, &Digest::default());
+            }
+        }
+        Ok(self.root(sha, &path, node))
+    }
+}
+
+enum Node {
+    Empty,
+    Leaf {
+        path: Digest,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        digest: Digest,
+    },
+    Branch {
+        children: Box<[Node; 2]>,
+        digest: Digest,
+    },
+}
+
+impl Node {
+    fn digest(&self) -> Digest {
+        match self {
+            Node::Empty => Digest::default(),
+            Node::Leaf { digest, .. } | Node::Branch { digest, .. } => *digest,
+        }
+    }
+
+    fn branch<S: Sha>(sha: &S, children: [Node; 2]) -> Node {
+        let digest = *sha.hash_pair(&children[0].digest(), &children[1].digest());
+        Node::Branch {
+            children: Box::new(children),
+            digest,
+        }
+    }
+
+    // Join two leaves into the subtree at `depth` holding both.
+    fn split<S: Sha>(sha: &S, depth: usize, a: Node, b: Node) -> Node {
+        let (a_bit, b_bit) = match (&a, &b) {
+            (Node::Leaf { path: a, .. }, Node::Leaf { path: b, .. }) => {
+                (bit(a, depth), bit(b, depth))
+            }
+            _ => unreachable!(),
+        };
+        let children = if a_bit == b_bit {
+            let below = Node::split(sha, depth + 1, a, b);
+            if a_bit == 0 {
+                [below, Node::Empty]
+            } else {
+                [Node::Empty, below]
+            }
+        } else if a_bit == 0 {
+            [a, b]
+        } else {
+            [b, a]
+        };
+        Node::branch(sha, children)
+    }
+
+    // Insert `leaf` into this subtree at `depth`, returning the value it
+    // replaced, if any.
+    fn insert<S: Sha>(&mut self, sha: &S, depth: usize, leaf: Node) -> Option<Vec<u8>> {
+        let path = match &leaf {
+            Node::Leaf { path, .. } => *path,
+            _ => unreachable!(),
+        };
+        match mem::replace(self, Node::Empty) {
+            Node::Empty => {
+                *self = leaf;
+                None
+            }
+            Node::Leaf {
+                path: old_path,
+                value,
+                ..
+            } if old_path == path => {
+                *self = leaf;
+                Some(value)
+            }
+            old @ Node::Leaf { .. } => {
+                *self = Node::split(sha, depth, old, leaf);
+                None
+            }
+            Node::Branch { mut children, .. } => {
+                let old = children[bit(&path, depth)].insert(sha, depth + 1, leaf);
+                *self = Node::branch(sha, *children);
+                old
+            }
+        }
+    }
+
+    // Remove the leaf at `path` from this subtree at `depth`, returning its
+    // value, if any.
+    fn remove<S: Sha>(&mut self, sha: &S, depth: usize, path: &Digest) -> Option<Vec<u8>> {
+        match self {
+            Node::Empty => None,
+            Node::Leaf {
+                path: leaf_path, ..
+            } if leaf_path != path => None,
+            Node::Leaf { .. } => match mem::replace(self, Node::Empty) {
+                Node::Leaf { value, .. } => Some(value),
+                _ => unreachable!(),
+            },
+            Node::Branch { children, .. } => {
+                let old = children[bit(path, depth)].remove(sha, depth + 1, path)?;
+                let [left, right] = match mem::replace(self, Node::Empty) {
+                    Node::Branch { children, .. } => *children,
+                    _ => unreachable!(),
+                };
+                // A branch left with a single leaf collapses into it.
+                *self = match (left, right) {
+                    (Node::Empty, leaf @ Node::Leaf { .. })
+                    | (leaf @ Node::Leaf { .. }, Node::Empty) => leaf,
+                    (left, right) => Node::branch(sha, [left, right]),
+                };
+                Some(old)
+            }
+        }
+    }
+}
+
+/// A key-value state along with the sparse Merkle tree over it.
+pub struct SparseMerkleTree<S: Sha> {
+    sha: S,
+    root: Node,
+    len: usize,
+}
+
+impl<S: Sha> SparseMerkleTree<S> {
+    /// An empty state, hashed with `sha`.
+    pub fn new(sha: S) -> Self {
+        SparseMerkleTree {
+            sha,
+            root: Node::Empty,
+            len: 0,
+        }
+    }
+
+    /// The root digest which guests check lookups against.
+    pub fn root(&self) -> Digest {
+        self.root.digest()
+    }
+
+    /// The number of entries in the state.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the state has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Set `key` to `value`, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let path = key_path(&self.sha, key);
+        let digest = leaf_digest(&self.sha, &path, &value_digest(&self.sha, value));
+        let leaf = Node::Leaf {
+            path,
+            key: key.to_vec(),
+            value: value.to_vec(),
+            digest,
+        };
+        let old = self.root.insert(&self.sha, 0, leaf);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Remove `key`, returning its value, if any.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let path = key_path(&self.sha, key);
+        let old = self.root.remove(&self.sha, 0, &path);
+        if old.is_some() {
+            self.len -= 1;
+        }
+        old
+    }
+
+    /// Look up `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = key_path(&self.sha, key);
+        let mut node = &self.root;
+        for depth in 0.. {
+            match node {
+                Node::Branch { children, .. } => node = &children[bit(&path, depth)],
+                Node::Leaf {
+                    path: leaf_path,
+                    value,
+                    ..
+                } if *leaf_path == path => return Some(value),
+                _ => break,
+            }
+        }
+        None
+    }
+
+    /// Iterate over the entries, in the order of their paths.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        let mut stack = Vec::from([&self.root]);
+        core::iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                match node {
+                    Node::Empty => {}
+                    Node::Leaf { key, value, .. } => {
+                        return Some((key.as_slice(), value.as_slice()))
+                    }
+                    Node::Branch { children, .. } => {
+                        stack.push(&children[1]);
+                        stack.push(&children[0]);
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    /// Produce a proof of the lookup of `key`.
+    pub fn prove(&self, key: &[u8]) -> SmtProof {
+        let path = key_path(&self.sha, key);
+        let mut node = &self.root;
+        let mut siblings = Vec::new();
+        let mut depth = 0;
+        while let Node::Branch { children, .. } = node {
+            let bit = bit(&path, depth);
+            siblings.push(children[bit ^ 1].digest());
+            node = &children[bit];
+            depth += 1;
+        }
+        siblings.reverse();
+        let leaf = match node {
+            Node::Leaf { path, value, .. } => Some(SmtLeaf {
+                path: *path,
+                value: value_digest(&self.sha, value),
+            }),
+            _ => None,
+        };
+        SmtProof { leaf, siblings }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use risc0_zkp::core::sha::{default_implementation, DefaultImplementation, Digest};
+
+    use super::{value_digest, InvalidSmtProof, SmtLeaf, SparseMerkleTree};
+
+    fn tree(len: usize) -> SparseMerkleTree<DefaultImplementation> {
+        let mut tree = SparseMerkleTree::new(default_implementation().clone());
+        for i in 0..len {
+            tree.insert(format!("key{i}").as_bytes(), format!("value{i}").as_bytes());
+        }
+        tree
+    }
+
+    // The tree built from scratch from the given entries.
+    fn rebuilt<'a>(entries: impl Iterator<Item = (&'a [u8], &'a [u8])>) -> Digest {
+        let mut tree = tree(0);
+        for (key, value) in entries {
+            tree.insert(key, value);
+        }
+        tree.root()
+    }
+
+    #[test]
+    fn lookups() {
+        let sha = default_implementation();
+        for len in [0, 1, 2, 5, 100] {
+            let tree = tree(len);
+            assert_eq!(tree.len(), len);
+            assert_eq!(tree.iter().count(), len);
+            let root = tree.root();
+            for i in 0..len + 5 {
+                let key = format!("key{i}");
+                let value = format!("value{i}");
+                let expected = (i < len).then(|| value.as_bytes());
+                assert_eq!(tree.get(key.as_bytes()), expected);
+                let proof = tree.prove(key.as_bytes());
+                assert_eq!(
+                    proof.verify(sha, &root, key.as_bytes()),
+                    Ok(expected.map(|value| value_digest(sha, value)))
+                );
+            }
+        }
+        assert_eq!(tree(0).root(), Digest::default());
+    }
+
+    #[test]
+    fn updates() {
+        let sha = default_implementation();
+        let mut tree = tree(50);
+        for i in (0..60).step_by(3) {
+            let key = format!("key{i}");
+            let value = format!("new{i}");
+            let proof = tree.prove(key.as_bytes());
+            let root = proof
+                .update(sha, &tree.root(), key.as_bytes(), value.as_bytes())
+                .unwrap();
+            tree.insert(key.as_bytes(), value.as_bytes());
+            assert_eq!(root, tree.root());
+        }
+        assert_eq!(tree.len(), 53);
+
+        // The root only depends on the entries, not the order of updates.
+        while tree.len() > 10 {
+            let key = tree.iter().next().unwrap().0.to_vec();
+            assert!(tree.remove(&key).is_some());
+            assert!(tree.remove(&key).is_none());
+            assert_eq!(tree.root(), rebuilt(tree.iter()));
+        }
+    }
+
+    #[test]
+    fn tampering() {
+        let sha = default_implementation();
+        let tree = tree(20);
+        let root = tree.root();
+
+        let mut proof = tree.prove(b"key3");
+        proof.leaf.as_mut().unwrap().value = value_digest(sha, b"forged");
+        assert_eq!(proof.verify(sha, &root, b"key3"), Err(InvalidSmtProof));
+
+        // Claim "key3" is absent by moving another leaf onto its path.
+        let mut proof = tree.prove(b"key3");
+        proof.leaf = Some(SmtLeaf {
+            path: Digest::default(),
+            value: value_digest(sha, b"value3"),
+        });
+        assert_eq!(proof.verify(sha, &root, b"key3"), Err(InvalidSmtProof));
+
+        let mut proof = tree.prove(b"key3");
+        proof.siblings.pop();
+        assert_eq!(proof.verify(sha, &root, b"key3"), Err(InvalidSmtProof));
+        assert_eq!(
+            proof.update(sha, &root, b"key3", b"new"),
+            Err(InvalidSmtProof)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verified tables laid out over a [kv](crate::kv) store.
+//!
+//! A table is a sequence of rows of a single serializable type, with optional
+//! secondary indexes on values derived from each row. A [TableBuilder] lays
+//! the table out as key-value entries:
+//!
+//! * [LEN_KEY] holds the number of rows,
+//! * [row_key]`(n)` holds row `n`, and
+//! * [index_key]`(index, value)` holds the numbers of the rows for which
+//!   `index` yields `value`, in ascending order.
+//!
+//! Guests query a table with risc0_zkvm_guest::table, which proves each read
+//! against the root of the store. The root thus pins down a snapshot of the
+//! dataset, which a verifier can rebuild with the same [TableBuilder].
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+
+use risc0_zkp::core::sha::Sha;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::kv::KvTree;
+
+/// The key under which the number of rows is stored.
+pub const LEN_KEY: &[u8] = b"len";
+
+/// The key under which row `n` is stored.
+pub fn row_key(n: u32) -> Vec<u8> {
+    [&b"row/"[..], &n.to_be_bytes()].concat()
+}
+
+/// The key under which the rows for which `index` yields `value` are listed.
+pub fn index_key(index: &str, value: &[u8]) -> Vec<u8> {
+    let len = (index.len() as u32).to_le_bytes();
+    [&b"idx/"[..], &len, index.as_bytes(), value].concat()
+}
+
+/// Serialize a value stored in a table.
+pub fn encode<T: Serialize>(value: &T) -> crate::serde::Result<Vec<u8>> {
+    Ok(crate::serde::words_to_bytes(&crate::serde::to_vec(value)?))
+}
+
+/// Deserialize a value stored in a table.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> crate::serde::Result<T> {
+    crate::serde::from_slice(&crate::serde::bytes_to_words(bytes))
+}
+
+// Computes a row's key in a named index.
+type IndexKey<'a, R> = Box<dyn Fn(&R) -> Vec<u8> + 'a>;
+
+/// Lays out a table and its indexes as a key-value store.
+pub struct TableBuilder<'a, R> {
+    rows: Vec<R>,
+    indexes: Vec<(String, IndexKey<'a, R>)>,
+}
+
+impl<'a, R: Serialize> TableBuilder<'a, R> {
+    /// Start a table with the given rows.
+    pub fn new(rows: Vec<R>) -> Self {
+        TableBuilder {
+            rows,
+            indexes: Vec::new(),
+        }
+    }
+
+    /// Add an index named `name`, keyed by the value `key` yields for each
+    /// row.
+    pub fn with_index(mut self, name: &str, key: impl Fn(&R) -> Vec<u8> + 'a) -> Self {
+        self.indexes.push((name.into(), Box::new(key)));
+        self
+    }
+
+    /// The key-value entries of the table.
+    pub fn entries(&self) -> crate::serde::Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let mut entries = BTreeMap::new();
+        entries.insert(LEN_KEY.to_vec(), encode(&(self.rows.len() as u32))?);
+        for (n, row) in self.rows.iter().enumerate() {
+            entries.insert(row_key(n as u32), encode(row)?);
+        }
+        for (name, key) in self.indexes.iter() {
+            let mut lists: BTreeMap<Vec<u8>, Vec<u32>> = BTreeMap::new();
+            for (n, row) in self.rows.iter().enumerate() {
+                lists.entry(key(row)).or_default().push(n as u32);
+            }
+            for (value, list) in lists {
+                entries.insert(index_key(name, &value), encode(&list)?);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Build a [KvTree] which serves the table.
+    pub fn build<S: Sha>(&self, sha: &S) -> crate::serde::Result<KvTree> {
+        Ok(KvTree::new(sha, self.entries()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use risc0_zkp::core::sha::default_implementation;
+
+    use super::{decode, index_key, row_key, TableBuilder, LEN_KEY};
+
+    #[test]
+    fn layout() {
+        let sha = default_implementation();
+        let rows: Vec<(u32, u64)> = Vec::from([(1, 100), (2, 250), (1, 75)]);
+        let tree = TableBuilder::new(rows)
+            .with_index("account", |row| row.0.to_le_bytes().to_vec())
+            .build(sha)
+            .unwrap();
+        let root = tree.root();
+        let get = |key: &[u8]| {
+            let proof = tree.prove(key);
+            proof
+                .verify(sha, &root, key)
+                .unwrap()
+                .map(|value| value.to_vec())
+        };
+
+        assert_eq!(decode::<u32>(&get(LEN_KEY).unwrap()).unwrap(), 3);
+        assert_eq!(
+            decode::<(u32, u64)>(&get(&row_key(1)).unwrap()).unwrap(),
+            (2, 250)
+        );
+        assert!(get(&row_key(3)).is_none());
+        let list = get(&index_key("account", &1u32.to_le_bytes())).unwrap();
+        assert_eq!(decode::<Vec<u32>>(&list).unwrap(), [0, 2]);
+        assert!(get(&index_key("account", &3u32.to_le_bytes())).is_none());
+    }
+}
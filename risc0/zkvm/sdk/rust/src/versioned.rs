@@ -0,0 +1,204 @@
+// Copyright 2022 Risc0, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Journals whose format evolves across guest versions.
+//!
+//! A guest which commits through risc0_zkvm_guest::env::commit_versioned
+//! writes a `u32` version before the value itself. A consumer which must
+//! keep accepting receipts from older guests, such as an on-chain verifier,
+//! decodes the journal with a [Migrations] registry: the version prefix
+//! selects the type the journal was written as, and a registered migration
+//! converts it to the current type.
+
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Returned when a versioned journal can't be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionError {
+    /// The journal is too short to hold a version.
+    Missing,
+    /// No type is registered for the journal's version.
+    Unknown(u32),
+    /// The journal doesn't deserialize as the type registered for its
+    /// version.
+    Decode(crate::serde::Error),
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionError::Missing => f.write_str("Journal has no version prefix"),
+            VersionError::Unknown(version) => write!(f, "Unknown journal version {version}"),
+            VersionError::Decode(err) => write!(f, "Malformed journal: {err}"),
+        }
+    }
+}
+
+/// Serialize `value` as a journal of the given `version`.
+pub fn to_vec<T: Serialize>(version: u32, value: &T) -> crate::serde::Result<Vec<u32>> {
+    crate::serde::to_vec(&(version, value))
+}
+
+/// The version prefix of a journal written in words.
+pub fn version(journal: &[u32]) -> Result<u32, VersionError> {
+    journal.first().copied().ok_or(VersionError::Missing)
+}
+
+type Decoder<'a, T> = Box<dyn Fn(&[u32]) -> Result<T, VersionError> + 'a>;
+
+/// Decodes versioned journals into the current type `T`, migrating older
+/// versions on the way.
+pub struct Migrations<'a, T> {
+    current: u32,
+    decoders: BTreeMap<u32, Decoder<'a, T>>,
+}
+
+impl<'a, T: DeserializeOwned + 'a> Migrations<'a, T> {
+    /// Accept journals of version `current`, which are decoded as `T`.
+    pub fn new(current: u32) -> Self {
+        let mut decoders: BTreeMap<u32, Decoder<'a, T>> = BTreeMap::new();
+        decoders.insert(current, Box::new(|words: &[u32]| decode(words)));
+        Migrations { current, decoders }
+    }
+
+    /// Also accept journals of `version`, which are decoded as `Old` and
+    /// then converted with `migrate`.  To migrate across several versions,
+    /// compose the conversions in `migrate`.
+    ///
+    /// Panics if `version` is the current version.
+    pub fn with_migration<Old: DeserializeOwned + 'a>(
+        mut self,
+        version: u32,
+        migrate: impl Fn(Old) -> T + 'a,
+    ) -> Self {
+        assert!(
+            version != self.current,
+            "Version {version} is decoded as the current type"
+        );
+        self.decoders.insert(
+            version,
+            Box::new(move |words: &[u32]| decode(words).map(&migrate)),
+        );
+        self
+    }
+
+    /// The version journals are decoded as without migration.
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// The accepted versions, in ascending order.
+    pub fn versions(&self) -> impl Iterator<Item = u32> + '_ {
+        self.decoders.keys().copied()
+    }
+
+    /// Decode a journal written in words, as returned by
+    /// `Receipt::get_journal_vec`.
+    pub fn decode(&self, journal: &[u32]) -> Result<T, VersionError> {
+        let version = version(journal)?;
+        match self.decoders.get(&version) {
+            Some(decoder) => decoder(&journal[1..]),
+            None => Err(VersionError::Unknown(version)),
+        }
+    }
+
+    /// Decode a journal given as bytes, as returned by
+    /// `Receipt::get_journal`.
+    pub fn decode_bytes(&self, journal: &[u8]) -> Result<T, VersionError> {
+        self.decode(&crate::serde::bytes_to_words(journal))
+    }
+}
+
+fn decode<T: DeserializeOwned>(words: &[u32]) -> Result<T, VersionError> {
+    crate::serde::from_slice(words).map_err(VersionError::Decode)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{to_vec, version, Migrations, VersionError};
+
+    #[derive(Serialize, Deserialize)]
+    struct V1 {
+        balance: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V2 {
+        owner: String,
+        balance: u64,
+    }
+
+    fn migrations<'a>() -> Migrations<'a, V2> {
+        Migrations::new(2).with_migration(1, |old: V1| V2 {
+            owner: String::new(),
+            balance: old.balance as u64,
+        })
+    }
+
+    #[test]
+    fn migrate() {
+        let migrations = migrations();
+        assert_eq!(migrations.versions().collect::<Vec<_>>(), [1, 2]);
+
+        let current = V2 {
+            owner: "alice".to_string(),
+            balance: 1 << 40,
+        };
+        let journal = to_vec(2, &current).unwrap();
+        assert_eq!(version(&journal), Ok(2));
+        assert_eq!(migrations.decode(&journal).unwrap(), current);
+
+        let journal = to_vec(1, &V1 { balance: 7 }).unwrap();
+        assert_eq!(
+            migrations.decode(&journal).unwrap(),
+            V2 {
+                owner: String::new(),
+                balance: 7
+            }
+        );
+        let bytes = crate::serde::words_to_bytes(&journal);
+        assert_eq!(migrations.decode_bytes(&bytes).unwrap().balance, 7);
+    }
+
+    #[test]
+    fn reject() {
+        let migrations = migrations();
+        assert_eq!(migrations.decode(&[]), Err(VersionError::Missing));
+        let journal = to_vec(3, &V1 { balance: 7 }).unwrap();
+        assert_eq!(migrations.decode(&journal), Err(VersionError::Unknown(3)));
+        // A version 2 journal cut short.
+        assert_eq!(
+            migrations.decode(&[2]),
+            Err(VersionError::Decode(
+                crate::serde::Error::DeserializeUnexpectedEnd
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn current_migration() {
+        migrations().with_migration(2, |old: V2| old);
+    }
+}
@@ -14,7 +14,7 @@
 
 use anyhow::Result;
 use risc0_zkvm::{method_id::MethodId, prove::Prover, receipt::Receipt};
-use risc0_zkvm_methods::{IO_ID, IO_PATH};
+use risc0_zkvm_methods::{TypedMain, IO_ID, IO_PATH};
 
 fn run_memio(pairs: &[(usize, usize)]) -> Result<Receipt> {
     let mut vec = Vec::new();
@@ -26,7 +26,7 @@ fn run_memio(pairs: &[(usize, usize)]) -> Result<Receipt> {
     let elf_contents = std::fs::read(IO_PATH).unwrap();
     let method_id = MethodId::from_slice(IO_ID).unwrap();
     log::debug!("io_path: {IO_PATH}");
-    let mut prover = Prover::new(&elf_contents, method_id.as_slice()?)?;
+    let mut prover = Prover::new(&elf_contents, &method_id.to_bytes())?;
     prover.add_input_u32_slice(vec.as_slice());
     prover.run()
 }
@@ -61,4 +61,13 @@ mod integration {
         // Unaligned read is bad
         assert!(run_memio(&[(HEAP.start() + 1, 0)]).is_err());
     }
+
+    #[test]
+    fn prove_method() {
+        use risc0_zkvm::host::{prove_method_with_opts, ProverOpts};
+
+        let opts = ProverOpts::default().with_skip_seal(true);
+        let (_, product) = prove_method_with_opts::<crate::TypedMain>(&(6, 7), opts).unwrap();
+        assert_eq!(product, 42);
+    }
 }
@@ -148,8 +148,16 @@ void RiscVVerifyCircuit::execute(ReadIOP& iop) {
     globals_[2 * i] = reg & 0xffff;
     globals_[2 * i + 1] = reg >> 16;
   }
-  // Read the po2 size
+  // Read the po2 size, and commit to the flag of a non-ZK trace
   iop.read(&po2_, 1);
+  if (po2_ & kNonZkFlag) {
+    po2_ &= ~kNonZkFlag;
+    ShaDigest flag;
+    for (size_t i = 0; i < 8; i++) {
+      flag.words[i] = kNonZkFlag;
+    }
+    iop.commit(flag);
+  }
 }
 
 void RiscVVerifyCircuit::accumulate(ReadIOP& iop) {